@@ -0,0 +1,14 @@
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use path_miner::{ Tag, region::decompress_chunk };
+
+// The first byte picks the compression type, like in a region file
+fuzz_target!(|data: &[u8]| {
+    let Some((&compression, data)) = data.split_first() else {
+        return;
+    };
+    if let Ok(decompressed) = decompress_chunk(compression, data) {
+        let _ = Tag::parse(&mut decompressed.iter());
+    }
+});
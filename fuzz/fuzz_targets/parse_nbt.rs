@@ -0,0 +1,21 @@
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use path_miner::{ NbtReader, Tag, TagRef };
+
+// Every parser has to turn arbitrary bytes into a tag or an error, without
+// panicking, overflowing the stack or running out of memory
+fuzz_target!(|data: &[u8]| {
+    if let Ok(tag) = Tag::parse(&mut data.iter()) {
+        // Whatever was read has to write back out and read the same again
+        let mut written = Vec::new();
+        tag.write(&mut written).expect("writing a parsed tag");
+        let reread = Tag::parse(&mut written.iter()).expect("parsing a written tag");
+        assert!(tag.diff(&reread).is_empty());
+    }
+
+    let _ = TagRef::parse(data);
+
+    let mut reader = NbtReader::new(data);
+    while let Ok(Some(_)) = reader.next_event() {}
+});
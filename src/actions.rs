@@ -0,0 +1,100 @@
+use std::{ collections::HashSet, fmt };
+
+use crate::{ hazards::HazardKind, pathfinding::{ Route, clearance }, pos::BlockPos, world::WorldView };
+
+// One thing to do while following a route, in the order they're done
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Action {
+    Mine(BlockPos),
+    // Put a block at the position, under a gap or against lava and water
+    Place(BlockPos),
+    Torch(BlockPos),
+    // Step into the position, feet first
+    Move(BlockPos),
+}
+
+impl fmt::Display for Action {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Action::Mine(pos) => write!(f, "mine {pos}"),
+            Action::Place(pos) => write!(f, "place {pos}"),
+            Action::Torch(pos) => write!(f, "torch {pos}"),
+            Action::Move(pos) => write!(f, "move {pos}"),
+        }
+    }
+}
+
+// Built like ParseOptions, so new options don't break anyone
+#[derive(Clone, Copy, Debug, Default)]
+#[non_exhaustive]
+pub struct ActionOptions {
+    // Put a torch on the floor every this many steps
+    pub torch_every: Option<u32>,
+}
+
+impl ActionOptions {
+    pub fn new() -> ActionOptions {
+        ActionOptions::default()
+    }
+
+    pub fn torch_every(mut self, steps: u32) -> ActionOptions {
+        self.torch_every = Some(steps);
+        self
+    }
+}
+
+// Turns a route into what to do at each step: mine the feet and head blocks
+// of the next position and the head room a step up or down takes, seal off
+// lava and water they opened up, put in the floor where the route places a
+// support and move in. Torches go on the floor behind, once the
+// position has been left, wherever there's a floor to put them on.
+pub fn route_actions(view: &WorldView, route: &Route, options: ActionOptions) -> Vec<Action> {
+    let tunnel: HashSet<BlockPos> = route.steps.iter().flat_map(|feet| [*feet, feet.offset(0, 1, 0)]).collect();
+    let supports: HashSet<BlockPos> = route.supports.iter().copied().collect();
+    let mined: HashSet<BlockPos> = route.mined.iter().copied().collect();
+    let mut dug = HashSet::new();
+    let mut placed = HashSet::new();
+    let mut torches = HashSet::new();
+    let mut actions = Vec::new();
+    let mut since_torch = 0;
+
+    for pair in route.steps.windows(2) {
+        let (from, to) = (pair[0], pair[1]);
+
+        for block in clearance(from, to).into_iter().chain([to, to.offset(0, 1, 0)]) {
+            if !mined.contains(&block) || !dug.insert(block) {
+                continue;
+            }
+            actions.push(Action::Mine(block));
+            for hazard in &route.hazards {
+                if hazard.mined == Some(block) && matches!(hazard.kind, HazardKind::Lava | HazardKind::Water) && placed.insert(hazard.pos) {
+                    actions.push(Action::Place(hazard.pos));
+                }
+            }
+        }
+
+        let floor = to.offset(0, -1, 0);
+        if supports.contains(&floor) && placed.insert(floor) {
+            actions.push(Action::Place(floor));
+        }
+        actions.push(Action::Move(to));
+
+        if let Some(every) = options.torch_every {
+            since_torch += 1;
+            if torches.contains(&from) {
+                since_torch = 0;
+            } else if since_torch >= every && has_floor(view, from, &tunnel, &placed) {
+                actions.push(Action::Torch(from));
+                torches.insert(from);
+                since_torch = 0;
+            }
+        }
+    }
+
+    actions
+}
+
+fn has_floor(view: &WorldView, feet: BlockPos, tunnel: &HashSet<BlockPos>, placed: &HashSet<BlockPos>) -> bool {
+    let floor = feet.offset(0, -1, 0);
+    placed.contains(&floor) || (!tunnel.contains(&floor) && view.block_at(floor).is_some_and(|block| !block.is_air()))
+}
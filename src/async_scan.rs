@@ -0,0 +1,207 @@
+use anyhow::{ Result, anyhow };
+use std::{ path::Path, sync::Arc };
+use tokio::sync::{ Semaphore, mpsc };
+
+use crate::chunk::Chunk;
+use crate::nbt::ParseOptions;
+use crate::region::read_region_bytes;
+use crate::world::{ Dimension, RegionInfo, World };
+
+// Room in the channel per region being read. Regions are parsed whole before
+// their chunks are sent, so memory is bounded by the regions in flight, and
+// this only lets a slow receiver fall behind without stalling the parsing.
+const CHUNKS_PER_REGION: usize = 64;
+
+impl World {
+    // Like chunks, but for tokio applications. See AsyncScan::start.
+    pub fn scan_async(&self) -> AsyncScan {
+        AsyncScan::new(self.regions().to_vec())
+    }
+
+    pub fn scan_async_in(&self, dimension: Dimension) -> AsyncScan {
+        AsyncScan::new(self.regions_in(dimension).cloned().collect())
+    }
+}
+
+// A scan over a world's regions that reads the files with tokio and parses
+// them on its blocking threads, so slow disks like network mounts don't hold
+// up the runtime
+pub struct AsyncScan {
+    regions: Vec<RegionInfo>,
+    options: ParseOptions,
+    modified_since: Option<u32>,
+    concurrency: usize,
+}
+
+impl AsyncScan {
+    fn new(regions: Vec<RegionInfo>) -> AsyncScan {
+        AsyncScan { regions, options: ParseOptions::default(), modified_since: None, concurrency: 4 }
+    }
+
+    // Parses the chunks with these options, e.g. to skip light data
+    pub fn with_options(mut self, options: ParseOptions) -> Self {
+        self.options = options;
+        self
+    }
+
+    // Skips chunks last saved before the given Unix time
+    pub fn modified_since(mut self, since: u32) -> Self {
+        self.modified_since = Some(since);
+        self
+    }
+
+    // How many regions are read and parsed at once, 4 by default
+    pub fn concurrency(mut self, regions: usize) -> Self {
+        self.concurrency = regions.max(1);
+        self
+    }
+
+    // Starts reading in the background and hands out the chunks as their
+    // regions are parsed, so chunks of different regions can come interleaved.
+    // Errors say which region and chunk they're about. Dropping the receiver
+    // stops the scan. Has to be called from within a tokio runtime.
+    pub fn start(self) -> mpsc::Receiver<Result<(Dimension, Chunk)>> {
+        let (sender, receiver) = mpsc::channel(CHUNKS_PER_REGION * self.concurrency);
+        let limit = Arc::new(Semaphore::new(self.concurrency));
+        let options = Arc::new(self.options);
+        let since = self.modified_since;
+
+        tokio::spawn(async move {
+            for region in self.regions {
+                let Ok(permit) = limit.clone().acquire_owned().await else {
+                    return;
+                };
+                if sender.is_closed() {
+                    return;
+                }
+                let sender = sender.clone();
+                let options = options.clone();
+                tokio::spawn(async move {
+                    scan_region(region, since, options, sender).await;
+                    drop(permit);
+                });
+            }
+        });
+
+        receiver
+    }
+}
+
+async fn scan_region(region: RegionInfo, since: Option<u32>, options: Arc<ParseOptions>, sender: mpsc::Sender<Result<(Dimension, Chunk)>>) {
+    // The game leaves 0 byte region files around, they have no chunks
+    if region.is_empty() {
+        return;
+    }
+    let chunks = match tokio::fs::read(&region.path).await {
+        Ok(bytes) => {
+            let path = region.path.clone();
+            tokio::task::spawn_blocking(move || parse_region(&path, &bytes, since, &options)).await
+                .unwrap_or_else(|e| Err(anyhow!("Parsing panicked: {e}")))
+        },
+        Err(e) => Err(e.into()),
+    };
+
+    let chunks = match chunks {
+        Ok(chunks) => chunks,
+        Err(e) => vec![Err(e.context(format!("Could not read region {}", region.path.display())))],
+    };
+    for chunk in chunks {
+        if sender.send(chunk.map(|chunk| (region.dimension, chunk))).await.is_err() {
+            return;
+        }
+    }
+}
+
+// Corrupt chunks come out as errors after the ones that could be read
+fn parse_region(path: &Path, bytes: &[u8], since: Option<u32>, options: &ParseOptions) -> Result<Vec<Result<Chunk>>> {
+    let report = read_region_bytes(path, bytes, since, options)?;
+    let failed = report.failed.into_iter().map(|(i, e)| Err(e.context(format!("Could not read chunk {i} of region {}", path.display()))));
+    Ok(report.ok.into_iter().map(Ok).chain(failed).collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::nbt::{ Tag, TagPayload };
+    use crate::region::{ RegionWriter, chunk_index_in_region };
+    use std::fs;
+
+    fn tag(name: &str, payload: TagPayload) -> Tag {
+        Tag { name: name.to_string(), payload }
+    }
+
+    // A 1.20.4 chunk with one all stone section at the bottom
+    fn chunk_tag(x: i32, z: i32) -> Tag {
+        let palette = vec![TagPayload::Compound(vec![tag("Name", TagPayload::String("minecraft:stone".to_string()))].into())];
+        let block_states = vec![tag("palette", TagPayload::List(palette))];
+        let section = TagPayload::Compound(vec![tag("Y", TagPayload::Byte(0)), tag("block_states", TagPayload::Compound(block_states.into()))].into());
+        let root = vec![
+            tag("DataVersion", TagPayload::Int(3700)),
+            tag("xPos", TagPayload::Int(x)),
+            tag("zPos", TagPayload::Int(z)),
+            tag("yPos", TagPayload::Int(0)),
+            tag("sections", TagPayload::List(vec![section])),
+        ];
+        tag("", TagPayload::Compound(root.into()))
+    }
+
+    fn scan(scan: AsyncScan) -> (Vec<(i32, i32)>, Vec<anyhow::Error>) {
+        let runtime = tokio::runtime::Builder::new_current_thread().build().unwrap();
+        runtime.block_on(async move {
+            let mut receiver = scan.start();
+            let (mut chunks, mut errors) = (Vec::new(), Vec::new());
+            while let Some(chunk) = receiver.recv().await {
+                match chunk {
+                    Ok((_, chunk)) => chunks.push((chunk.x(), chunk.z())),
+                    Err(e) => errors.push(e),
+                }
+            }
+            chunks.sort();
+            (chunks, errors)
+        })
+    }
+
+    #[test]
+    fn scans_every_chunk_and_skips_empty_region_files() {
+        let dir = std::env::temp_dir().join(format!("path-miner-async-scan-{}", std::process::id()));
+        let region_dir = dir.join("region");
+        fs::create_dir_all(&region_dir).unwrap();
+        let mut writer = RegionWriter::new();
+        for (x, z, timestamp) in [(0, 0, 100), (1, 0, 100), (0, 1, 200)] {
+            writer.set_chunk(chunk_index_in_region(x, z), &chunk_tag(x, z), timestamp).unwrap();
+        }
+        let mut bytes = Vec::new();
+        writer.write(&mut bytes).unwrap();
+        fs::write(region_dir.join("r.0.0.mca"), bytes).unwrap();
+        // The game leaves these behind
+        fs::write(region_dir.join("r.1.0.mca"), []).unwrap();
+        fs::write(region_dir.join("r.0.1.mca"), []).unwrap();
+
+        let world = World::open(&dir).unwrap();
+        let (chunks, errors) = scan(world.scan_async().concurrency(2));
+        assert!(errors.is_empty(), "{errors:?}");
+        assert_eq!(chunks, vec![(0, 0), (0, 1), (1, 0)]);
+
+        let (chunks, errors) = scan(world.scan_async().modified_since(150));
+        assert!(errors.is_empty(), "{errors:?}");
+        assert_eq!(chunks, vec![(0, 1)]);
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn reports_regions_that_cannot_be_read() {
+        let dir = std::env::temp_dir().join(format!("path-miner-async-scan-broken-{}", std::process::id()));
+        let region_dir = dir.join("region");
+        fs::create_dir_all(&region_dir).unwrap();
+        fs::write(region_dir.join("r.0.0.mca"), [0; 100]).unwrap();
+
+        let world = World::open(&dir).unwrap();
+        let (chunks, errors) = scan(world.scan_async());
+        assert!(chunks.is_empty());
+        assert_eq!(errors.len(), 1);
+        assert!(format!("{:#}", errors[0]).contains("r.0.0.mca"));
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
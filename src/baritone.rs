@@ -0,0 +1,21 @@
+use crate::{ pathfinding::Route, pos::BlockPos };
+
+// Baritone chat commands that follow a route: a #goto for every corner, so
+// its own pathing sticks to the planned tunnel, and for every target on the
+// way. Pasted into chat one after the other once the previous goal is reached.
+pub fn goto_commands(route: &Route, targets: &[BlockPos]) -> Vec<String> {
+    let mut goals = Vec::new();
+    for (i, step) in route.steps.iter().enumerate().skip(1) {
+        let corner = route.steps.get(i + 1).is_none_or(|next| direction(&route.steps[i - 1], step) != direction(step, next));
+        if corner || targets.contains(step) {
+            goals.push(*step);
+        }
+    }
+    goals.dedup();
+
+    goals.iter().map(|BlockPos { x, y, z }| format!("#goto {x} {y} {z}")).collect()
+}
+
+fn direction(from: &BlockPos, to: &BlockPos) -> (i32, i32, i32) {
+    ((to.x - from.x).signum(), (to.y - from.y).signum(), (to.z - from.z).signum())
+}
@@ -0,0 +1,378 @@
+use anyhow::{ Result, Context, bail, ensure };
+use std::path::Path;
+
+use crate::{ chunk::{ Chunk, pack_padded_indices }, leveldb::LevelDb, nbt::{ Compound, NbtFlavor, ParseOptions, Tag, TagPayload }, world::Dimension };
+
+// What comes after a chunk's position in its keys
+const VERSION: u8 = 44;
+const SUB_CHUNK: u8 = 47;
+const BLOCK_ENTITY: u8 = 49;
+const LEGACY_VERSION: u8 = 118;
+
+// Chunks are handed out laid out like Java 1.20 chunks, though their blocks
+// keep Bedrock's names and states
+const DATA_VERSION: i32 = 3465;
+
+// The chunks of a Bedrock world, read from the LevelDB database in its db folder
+pub struct BedrockDb {
+    db: LevelDb,
+    // Every chunk with a version key, sorted
+    chunks: Vec<(Dimension, i32, i32)>,
+}
+
+impl BedrockDb {
+    pub fn open(path: impl AsRef<Path>) -> Result<BedrockDb> {
+        let db = LevelDb::open(path)?;
+        let mut chunks: Vec<(Dimension, i32, i32)> = db.keys()
+            .filter_map(parse_chunk_key)
+            .filter(|(_, _, _, tag)| matches!(*tag, VERSION | LEGACY_VERSION))
+            .map(|(dimension, x, z, _)| (dimension, x, z))
+            .collect();
+        chunks.sort();
+        chunks.dedup();
+        Ok(BedrockDb { db, chunks })
+    }
+
+    pub fn chunk_positions(&self, dimension: Dimension) -> impl Iterator<Item = (i32, i32)> + '_ {
+        self.chunks.iter().filter(move |(d, _, _)| *d == dimension).map(|(_, x, z)| (*x, *z))
+    }
+
+    // Every chunk of the dimension, or of the whole world
+    pub fn chunks(&self, dimension: Option<Dimension>) -> impl Iterator<Item = Result<(Dimension, Chunk)>> + '_ {
+        self.chunks.iter()
+            .filter(move |(d, _, _)| dimension.is_none_or(|dimension| *d == dimension))
+            .filter_map(|&(dimension, x, z)| self.chunk(dimension, x, z).transpose().map(|chunk| chunk.map(|chunk| (dimension, chunk))))
+    }
+
+    // Builds the chunk from its sub-chunks and block entities. Sub-chunks
+    // from before 1.3, which still use numeric block ids, can't be read.
+    pub fn chunk(&self, dimension: Dimension, x: i32, z: i32) -> Result<Option<Chunk>> {
+        let prefix = chunk_prefix(dimension, x, z);
+        let key = |tag: u8| [prefix.as_slice(), &[tag]].concat();
+        if self.db.get(&key(VERSION))?.is_none() && self.db.get(&key(LEGACY_VERSION))?.is_none() {
+            return Ok(None);
+        }
+        let context = || format!("Invalid chunk ({x}, {z}) in {dimension:?}");
+
+        let mut sections = Vec::new();
+        let sub_chunks: Vec<Vec<u8>> = self.db.keys_with_prefix(&prefix)
+            .filter(|key| key.len() == prefix.len() + 2 && key[prefix.len()] == SUB_CHUNK)
+            .map(<[u8]>::to_vec)
+            .collect();
+        for key in sub_chunks {
+            let y = key[key.len() - 1] as i8;
+            let Some(data) = self.db.get(&key)? else {
+                continue;
+            };
+            let section = read_sub_chunk(&data, y).with_context(|| format!("Invalid sub-chunk {y}")).with_context(context)?;
+            sections.push(section);
+        }
+
+        let mut block_entities = Vec::new();
+        if let Some(data) = self.db.get(&key(BLOCK_ENTITY))? {
+            let mut cursor = data.as_slice();
+            while !cursor.is_empty() {
+                let mut block_entity = read_le_tag(&mut cursor).context("Invalid block entity").with_context(context)?.payload;
+                if let Some(TagPayload::String(id)) = block_entity.get_mut("id") {
+                    *id = java_id(id);
+                }
+                block_entities.push(block_entity);
+            }
+        }
+
+        let min_section = sections.iter()
+            .filter_map(|section| match section.get("Y") {
+                Some(TagPayload::Byte(y)) => Some(*y as i32),
+                _ => None,
+            })
+            .min()
+            .unwrap_or(0);
+
+        let tag = |name: &str, payload: TagPayload| Tag { name: name.to_string(), payload };
+        let root = vec![
+            tag("DataVersion", TagPayload::Int(DATA_VERSION)),
+            tag("xPos", TagPayload::Int(x)),
+            tag("zPos", TagPayload::Int(z)),
+            tag("yPos", TagPayload::Int(min_section)),
+            tag("sections", TagPayload::List(sections)),
+            tag("block_entities", TagPayload::List(block_entities)),
+        ];
+        Chunk::from_nbt(Tag { name: String::new(), payload: TagPayload::Compound(root.into()) }).with_context(context).map(Some)
+    }
+}
+
+// Dimension, chunk position and what the key holds, for the keys that belong to a chunk
+fn parse_chunk_key(key: &[u8]) -> Option<(Dimension, i32, i32, u8)> {
+    let int = |at: usize| i32::from_le_bytes(key[at..at + 4].try_into().unwrap());
+    let (dimension, tag_at) = match key.len() {
+        9 | 10 => (Dimension::Overworld, 8),
+        13 | 14 => match int(8) {
+            1 => (Dimension::Nether, 12),
+            2 => (Dimension::End, 12),
+            _ => return None,
+        },
+        _ => return None,
+    };
+    let tag = key[tag_at];
+    // Only sub-chunk keys have the extra byte for their height
+    if key.len() == tag_at + 2 && tag != SUB_CHUNK || !matches!(tag, 43..=65 | LEGACY_VERSION) {
+        return None;
+    }
+    Some((dimension, int(0), int(4), tag))
+}
+
+fn chunk_prefix(dimension: Dimension, x: i32, z: i32) -> Vec<u8> {
+    let mut prefix = [x.to_le_bytes(), z.to_le_bytes()].concat();
+    match dimension {
+        Dimension::Overworld => {},
+        Dimension::Nether => prefix.extend(1i32.to_le_bytes()),
+        Dimension::End => prefix.extend(2i32.to_le_bytes()),
+    }
+    prefix
+}
+
+// A sub-chunk as a section of a Java chunk. Of its block layers only the
+// first is read, the second holds the water of waterlogged blocks.
+fn read_sub_chunk(data: &[u8], y: i8) -> Result<TagPayload> {
+    let mut cursor = data;
+    let version = take(&mut cursor, 1)?[0];
+    match version {
+        1 => {},
+        8 => { take(&mut cursor, 1)?; },
+        9 => { take(&mut cursor, 2)?; },
+        _ => bail!("Sub-chunk version {version} isn't supported"),
+    }
+
+    let header = take(&mut cursor, 1)?[0];
+    ensure!(header & 1 == 0, "Sub-chunk uses runtime ids, which only show up in network packets");
+    let bits = (header >> 1) as usize;
+
+    // Blocks are packed into 32-bit words without spanning two, in x, z, y
+    // order, Java goes by y, z, x
+    let mut indices = vec![0; 4096];
+    let palette_len = if bits == 0 {
+        1
+    } else {
+        ensure!(matches!(bits, 1..=6 | 8 | 16), "Sub-chunk has {bits} bits per block");
+        let per_word = 32 / bits;
+        let words = take(&mut cursor, 4096usize.div_ceil(per_word) * 4)?;
+        let mask = (1u32 << bits) - 1;
+        for i in 0..4096 {
+            let at = i / per_word * 4;
+            let word = u32::from_le_bytes(words[at..at + 4].try_into()?);
+            let (x, z, y) = (i >> 8, (i >> 4) & 15, i & 15);
+            indices[y << 8 | z << 4 | x] = ((word >> ((i % per_word) * bits)) & mask) as usize;
+        }
+        i32::from_le_bytes(take(&mut cursor, 4)?.try_into()?) as usize
+    };
+
+    let mut palette = Vec::with_capacity(palette_len.min(4096));
+    for _ in 0..palette_len {
+        palette.push(java_block(read_le_tag(&mut cursor)?.payload)?);
+    }
+    if let Some(index) = indices.iter().find(|index| **index >= palette.len()) {
+        bail!("Block refers to palette entry {index} of {}", palette.len());
+    }
+
+    let tag = |name: &str, payload: TagPayload| Tag { name: name.to_string(), payload };
+    let len = palette.len();
+    let mut block_states = vec![tag("palette", TagPayload::List(palette))];
+    if len > 1 {
+        let bits = (usize::BITS - (len - 1).leading_zeros()).max(4) as usize;
+        block_states.push(tag("data", TagPayload::LongArray(pack_padded_indices(&indices, bits))));
+    }
+    Ok(TagPayload::Compound(vec![tag("Y", TagPayload::Byte(y)), tag("block_states", TagPayload::Compound(block_states.into()))].into()))
+}
+
+// A palette entry as Java writes it. Bedrock's states are bytes for true
+// and false, ints and strings, Java keeps them all as strings.
+fn java_block(entry: TagPayload) -> Result<TagPayload> {
+    let Some(TagPayload::String(name)) = entry.get("name") else {
+        bail!("Palette entry has no name");
+    };
+    let mut properties = Compound::new();
+    if let Some(TagPayload::Compound(states)) = entry.get("states") {
+        for state in states.iter() {
+            let value = match &state.payload {
+                TagPayload::Byte(0) => "false".to_string(),
+                TagPayload::Byte(1) => "true".to_string(),
+                TagPayload::Byte(b) => b.to_string(),
+                TagPayload::Int(i) => i.to_string(),
+                TagPayload::String(s) => s.clone(),
+                _ => continue,
+            };
+            properties.push(Tag { name: state.name.clone(), payload: TagPayload::String(value) });
+        }
+    }
+
+    let mut block = vec![Tag { name: "Name".to_string(), payload: TagPayload::String(name.clone()) }];
+    if !properties.is_empty() {
+        block.push(Tag { name: "Properties".to_string(), payload: TagPayload::Compound(properties) });
+    }
+    Ok(TagPayload::Compound(block.into()))
+}
+
+// Bedrock's block entity ids like MobSpawner turn into Java's
+// minecraft:mob_spawner, which is right for most of them
+fn java_id(id: &str) -> String {
+    let mut java = String::from("minecraft:");
+    for (i, c) in id.chars().enumerate() {
+        if c.is_ascii_uppercase() && i > 0 {
+            java.push('_');
+        }
+        java.push(c.to_ascii_lowercase());
+    }
+    java
+}
+
+fn take<'a>(cursor: &mut &'a [u8], n: usize) -> Result<&'a [u8]> {
+    ensure!(cursor.len() >= n, "Sub-chunk is cut short");
+    let (taken, rest) = cursor.split_at(n);
+    *cursor = rest;
+    Ok(taken)
+}
+
+// Palette entries and block entities are little-endian NBT, one root tag after another
+fn read_le_tag(cursor: &mut &[u8]) -> Result<Tag> {
+    let mut iterator = cursor.iter();
+    let tag = Tag::parse_with(&mut iterator, &ParseOptions::new().flavor(NbtFlavor::Bedrock))?;
+    *cursor = iterator.as_slice();
+    Ok(tag)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::chunk::unpack_padded_indices;
+
+    fn tag(name: &str, payload: TagPayload) -> Tag {
+        Tag { name: name.to_string(), payload }
+    }
+
+    fn palette_entry(name: &str, states: Vec<Tag>) -> Vec<u8> {
+        let entry = vec![tag("name", TagPayload::String(name.to_string())), tag("states", TagPayload::Compound(states.into()))];
+        let mut bytes = Vec::new();
+        tag("", TagPayload::Compound(entry.into())).write_as(&mut bytes, NbtFlavor::Bedrock).unwrap();
+        bytes
+    }
+
+    // A version 8 sub-chunk with one layer, blocks given in Bedrock's x, z, y order
+    fn sub_chunk(bits: usize, indices: &[usize], palette: &[Vec<u8>]) -> Vec<u8> {
+        let mut data = vec![8, 1, (bits << 1) as u8];
+        if let Some(per_word) = 32usize.checked_div(bits) {
+            for word in indices.chunks(per_word) {
+                let word = word.iter().enumerate().fold(0u32, |word, (i, index)| word | (*index as u32) << (i * bits));
+                data.extend(word.to_le_bytes());
+            }
+            data.extend((palette.len() as i32).to_le_bytes());
+        }
+        for entry in palette {
+            data.extend_from_slice(entry);
+        }
+        data
+    }
+
+    fn names(section: &TagPayload) -> Vec<String> {
+        let Some(TagPayload::List(palette)) = section.get_path("block_states.palette").ok() else { panic!("no palette") };
+        palette.iter().map(|entry| match entry.get("Name") {
+            Some(TagPayload::String(name)) => name.clone(),
+            _ => panic!("no name"),
+        }).collect()
+    }
+
+    // The section's blocks as palette indices in Java's y, z, x order
+    fn java_indices(section: &TagPayload, palette_len: usize) -> Vec<usize> {
+        match section.get_path("block_states.data") {
+            Ok(TagPayload::LongArray(data)) => {
+                let bits = (usize::BITS - (palette_len - 1).leading_zeros()).max(4) as usize;
+                unpack_padded_indices(data, bits).unwrap()
+            },
+            _ => vec![0; 4096],
+        }
+    }
+
+    #[test]
+    fn parses_the_keys_of_chunks() {
+        let key = |x: i32, z: i32, dimension: Option<i32>, rest: &[u8]| {
+            let mut key = [x.to_le_bytes(), z.to_le_bytes()].concat();
+            if let Some(dimension) = dimension {
+                key.extend(dimension.to_le_bytes());
+            }
+            key.extend_from_slice(rest);
+            key
+        };
+        assert_eq!(parse_chunk_key(&key(-1, 2, None, &[VERSION])), Some((Dimension::Overworld, -1, 2, VERSION)));
+        assert_eq!(parse_chunk_key(&key(5, -7, Some(1), &[SUB_CHUNK, 0xfc])), Some((Dimension::Nether, 5, -7, SUB_CHUNK)));
+        assert_eq!(parse_chunk_key(&key(0, 0, Some(2), &[LEGACY_VERSION])), Some((Dimension::End, 0, 0, LEGACY_VERSION)));
+        assert_eq!(chunk_prefix(Dimension::Nether, 5, -7), key(5, -7, Some(1), &[]));
+
+        // Unknown dimensions and tags, a height byte on a key that isn't a sub-chunk
+        assert_eq!(parse_chunk_key(&key(0, 0, Some(3), &[VERSION])), None);
+        assert_eq!(parse_chunk_key(&key(0, 0, None, &[100])), None);
+        assert_eq!(parse_chunk_key(&key(0, 0, None, &[BLOCK_ENTITY, 0])), None);
+        // Keys of other things that happen to have a chunk key's length
+        assert_eq!(parse_chunk_key(b"~local_player"), None);
+        assert_eq!(parse_chunk_key(b"Overworld"), None);
+        assert_eq!(parse_chunk_key(b"portals"), None);
+    }
+
+    #[test]
+    fn reads_sub_chunks_at_0_3_and_16_bits_per_block() {
+        let stone = palette_entry("minecraft:stone", Vec::new());
+        let section = read_sub_chunk(&sub_chunk(0, &[], &[stone]), -4).unwrap();
+        assert!(matches!(section.get("Y"), Some(TagPayload::Byte(-4))));
+        assert_eq!(names(&section), ["minecraft:stone"]);
+        assert!(section.get_path("block_states.data").is_err());
+
+        // Bedrock's x, z, y order turned into Java's y, z, x
+        let bedrock_at = |i: usize| (i >> 8, (i >> 4) & 15, i & 15);
+        let java = |(x, z, y): (usize, usize, usize)| y << 8 | z << 4 | x;
+        let pattern = |(x, z, y): (usize, usize, usize), len: usize| (x + 2 * z + 3 * y) % len;
+
+        let palette: Vec<Vec<u8>> = (0..5).map(|i| palette_entry(&format!("minecraft:block_{i}"), Vec::new())).collect();
+        let indices: Vec<usize> = (0..4096).map(|i| pattern(bedrock_at(i), 5)).collect();
+        let section = read_sub_chunk(&sub_chunk(3, &indices, &palette), 0).unwrap();
+        assert_eq!(names(&section).len(), 5);
+        let read = java_indices(&section, 5);
+        assert!((0..4096).all(|i| read[java(bedrock_at(i))] == indices[i]));
+
+        let palette: Vec<Vec<u8>> = (0..40).map(|i| palette_entry(&format!("minecraft:block_{i}"), Vec::new())).collect();
+        let indices: Vec<usize> = (0..4096).map(|i| pattern(bedrock_at(i), 40)).collect();
+        let section = read_sub_chunk(&sub_chunk(16, &indices, &palette), 3).unwrap();
+        assert_eq!(names(&section)[39], "minecraft:block_39");
+        let read = java_indices(&section, 40);
+        assert!((0..4096).all(|i| read[java(bedrock_at(i))] == indices[i]));
+    }
+
+    #[test]
+    fn turns_block_states_into_java_properties() {
+        let states = vec![
+            tag("open_bit", TagPayload::Byte(1)),
+            tag("upside_down_bit", TagPayload::Byte(0)),
+            tag("direction", TagPayload::Int(3)),
+            tag("color", TagPayload::String("red".to_string())),
+        ];
+        let section = read_sub_chunk(&sub_chunk(0, &[], &[palette_entry("minecraft:trapdoor", states)]), 0).unwrap();
+        let Ok(TagPayload::Compound(properties)) = section.get_path("block_states.palette[0].Properties") else { panic!("no properties") };
+        let properties: Vec<(&str, &str)> = properties.iter().map(|state| match &state.payload {
+            TagPayload::String(value) => (state.name.as_str(), value.as_str()),
+            _ => panic!("{} isn't a string", state.name),
+        }).collect();
+        assert_eq!(properties, [("open_bit", "true"), ("upside_down_bit", "false"), ("direction", "3"), ("color", "red")]);
+        assert_eq!(java_id("MobSpawner"), "minecraft:mob_spawner");
+    }
+
+    #[test]
+    fn rejects_broken_sub_chunks() {
+        let stone = palette_entry("minecraft:stone", Vec::new());
+        // A block pointing past the palette
+        let indices: Vec<usize> = (0..4096).map(|i| i % 3).collect();
+        assert!(read_sub_chunk(&sub_chunk(3, &indices, &[stone.clone(), stone.clone()]), 0).is_err());
+        // Runtime ids, an unsupported width and a cut off palette
+        assert!(read_sub_chunk(&[8, 1, 1], 0).is_err());
+        assert!(read_sub_chunk(&sub_chunk(7, &[0; 4096], std::slice::from_ref(&stone)), 0).is_err());
+        let data = sub_chunk(3, &[0; 4096], &[stone]);
+        assert!(read_sub_chunk(&data[..data.len() - 2], 0).is_err());
+        assert!(read_sub_chunk(&[2], 0).is_err());
+    }
+}
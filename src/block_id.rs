@@ -0,0 +1,106 @@
+use std::{ cmp::Ordering, collections::HashMap, fmt, ops::Deref, sync::{ LazyLock, RwLock } };
+
+// A block name like minecraft:stone, stored once for the whole process and
+// passed around as a number. Every palette of every chunk repeats the same few
+// hundred names, so palettes hold these instead of a String per entry, and
+// counting or comparing blocks doesn't have to look at the text at all.
+#[derive(Clone, Copy, PartialEq, Eq, Hash)]
+pub struct BlockId(u32);
+
+struct Interner {
+    names: Vec<&'static str>,
+    ids: HashMap<&'static str, u32>,
+}
+
+static INTERNER: LazyLock<RwLock<Interner>> = LazyLock::new(|| RwLock::new(Interner { names: Vec::new(), ids: HashMap::new() }));
+
+impl BlockId {
+    pub fn intern(name: &str) -> BlockId {
+        if let Some(id) = BlockId::get(name) {
+            return id;
+        }
+
+        let mut interner = INTERNER.write().unwrap_or_else(|e| e.into_inner());
+        if let Some(&id) = interner.ids.get(name) {
+            return BlockId(id);
+        }
+        // Never freed, there are only so many block names
+        let name: &'static str = Box::leak(name.into());
+        let id = interner.names.len() as u32;
+        interner.names.push(name);
+        interner.ids.insert(name, id);
+        BlockId(id)
+    }
+
+    // Without interning, None means no block read so far has the name
+    pub fn get(name: &str) -> Option<BlockId> {
+        INTERNER.read().unwrap_or_else(|e| e.into_inner()).ids.get(name).map(|&id| BlockId(id))
+    }
+
+    pub fn as_str(self) -> &'static str {
+        INTERNER.read().unwrap_or_else(|e| e.into_inner()).names[self.0 as usize]
+    }
+
+    // Ids are handed out in the order names are first seen, so they're only
+    // good for as long as the process runs
+    pub fn index(self) -> usize {
+        self.0 as usize
+    }
+}
+
+impl From<&str> for BlockId {
+    fn from(name: &str) -> BlockId {
+        BlockId::intern(name)
+    }
+}
+
+impl Deref for BlockId {
+    type Target = str;
+
+    fn deref(&self) -> &str {
+        self.as_str()
+    }
+}
+
+// By name, so sorted blocks come out in alphabetical order
+impl Ord for BlockId {
+    fn cmp(&self, other: &BlockId) -> Ordering {
+        if self == other { Ordering::Equal } else { self.as_str().cmp(other.as_str()) }
+    }
+}
+
+impl PartialOrd for BlockId {
+    fn partial_cmp(&self, other: &BlockId) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl PartialEq<str> for BlockId {
+    fn eq(&self, other: &str) -> bool {
+        self.as_str() == other
+    }
+}
+
+impl PartialEq<&str> for BlockId {
+    fn eq(&self, other: &&str) -> bool {
+        self.as_str() == *other
+    }
+}
+
+impl PartialEq<String> for BlockId {
+    fn eq(&self, other: &String) -> bool {
+        self.as_str() == other
+    }
+}
+
+impl fmt::Display for BlockId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl fmt::Debug for BlockId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Debug::fmt(self.as_str(), f)
+    }
+}
@@ -0,0 +1,148 @@
+use anyhow::{ Result, bail };
+use std::{ collections::HashSet, str::FromStr };
+
+use crate::{ pathfinding::dig_cost, pos::BlockPos, world::WorldView };
+
+// Which way the main tunnel runs
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Heading {
+    North,
+    South,
+    East,
+    West,
+}
+
+impl Heading {
+    // One block forward as x and z
+    pub fn step(self) -> (i32, i32) {
+        match self {
+            Heading::North => (0, -1),
+            Heading::South => (0, 1),
+            Heading::East => (1, 0),
+            Heading::West => (-1, 0),
+        }
+    }
+
+    pub fn left(self) -> Heading {
+        match self {
+            Heading::North => Heading::West,
+            Heading::West => Heading::South,
+            Heading::South => Heading::East,
+            Heading::East => Heading::North,
+        }
+    }
+
+    pub fn right(self) -> Heading {
+        self.left().left().left()
+    }
+}
+
+impl FromStr for Heading {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Heading> {
+        match s.to_ascii_lowercase().as_str() {
+            "north" | "n" => Ok(Heading::North),
+            "south" | "s" => Ok(Heading::South),
+            "east" | "e" => Ok(Heading::East),
+            "west" | "w" => Ok(Heading::West),
+            _ => bail!("Unknown heading \"{s}\", expected north, south, east or west"),
+        }
+    }
+}
+
+pub struct BranchMineOptions {
+    pub heading: Heading,
+    // Length of the main tunnel
+    pub depth: u32,
+    // Solid blocks left between two branches. With 2 every block between
+    // them is seen from one branch or the other.
+    pub spacing: u32,
+    pub branch_length: u32,
+}
+
+// One straight 1x2 tunnel, clipped to where it can be dug
+pub struct Tunnel {
+    pub name: String,
+    // Feet positions of the first and last block the tunnel reaches
+    pub from: BlockPos,
+    pub to: BlockPos,
+    // Feet and head blocks that have to be mined, caves along the way left out
+    pub mined: Vec<BlockPos>,
+}
+
+pub struct BranchMine {
+    pub tunnels: Vec<Tunnel>,
+    // Solid blocks next to the tunnels, the ones ores can be spotted in
+    pub exposed: usize,
+}
+
+impl BranchMine {
+    pub fn mined(&self) -> usize {
+        self.tunnels.iter().map(|tunnel| tunnel.mined.len()).sum()
+    }
+}
+
+// A main tunnel from start with branches off both sides of it every
+// spacing + 1 blocks. Tunnels stop at blocks that can't be dug through, like
+// lava or bedrock, and where the view runs out. Air they pass through, like a
+// cave, isn't mined, and branches that would only run through caves are left out.
+pub fn plan_branch_mine(view: &WorldView, start: BlockPos, options: &BranchMineOptions) -> BranchMine {
+    let mut tunnels = Vec::new();
+    let Some(main) = dig_tunnel(view, "Main tunnel".to_string(), start, options.heading, options.depth) else {
+        return BranchMine { tunnels, exposed: 0 };
+    };
+
+    let reached = main.from.manhattan_distance(&main.to) + 1;
+    let every = options.spacing + 1;
+    let (dx, dz) = options.heading.step();
+    let mut branches = Vec::new();
+    for (number, along) in (every..reached).step_by(every as usize).enumerate() {
+        let at = main.from.offset(dx * along as i32, 0, dz * along as i32);
+        for (side, heading) in [("left", options.heading.left()), ("right", options.heading.right())] {
+            let (sx, sz) = heading.step();
+            let name = format!("Branch {} {side}", number + 1);
+            branches.extend(dig_tunnel(view, name, at.offset(sx, 0, sz), heading, options.branch_length).filter(|branch| !branch.mined.is_empty()));
+        }
+    }
+    tunnels.push(main);
+    tunnels.extend(branches);
+
+    let open: HashSet<BlockPos> = tunnels.iter()
+        .flat_map(|tunnel| walk(tunnel.from, tunnel.to))
+        .flat_map(|feet| [feet, feet.offset(0, 1, 0)])
+        .collect();
+    let exposed: HashSet<BlockPos> = open.iter()
+        .flat_map(|pos| pos.neighbors())
+        .filter(|pos| !open.contains(pos) && view.block_at(*pos).is_some_and(|block| !block.is_air()))
+        .collect();
+
+    BranchMine { tunnels, exposed: exposed.len() }
+}
+
+fn dig_tunnel(view: &WorldView, name: String, start: BlockPos, heading: Heading, length: u32) -> Option<Tunnel> {
+    let (dx, dz) = heading.step();
+    let mut mined = Vec::new();
+    let mut to = None;
+    for i in 0..length as i32 {
+        let feet = start.offset(dx * i, 0, dz * i);
+        let head = feet.offset(0, 1, 0);
+        let (Some(feet_block), Some(head_block)) = (view.block_at(feet), view.block_at(head)) else {
+            break;
+        };
+        if dig_cost(feet_block).is_none() || dig_cost(head_block).is_none() {
+            break;
+        }
+
+        mined.extend([(feet, feet_block), (head, head_block)].into_iter().filter(|(_, block)| !block.is_air()).map(|(pos, _)| pos));
+        to = Some(feet);
+    }
+    Some(Tunnel { name, from: start, to: to?, mined })
+}
+
+// Feet positions from one end of a straight tunnel to the other
+fn walk(from: BlockPos, to: BlockPos) -> impl Iterator<Item = BlockPos> {
+    let length = from.manhattan_distance(&to) as i32;
+    let step = ((to.x - from.x).signum(), (to.z - from.z).signum());
+    (0..=length).map(move |i| from.offset(step.0 * i, 0, step.1 * i))
+}
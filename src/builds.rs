@@ -0,0 +1,116 @@
+use std::{ fmt, str::FromStr };
+use anyhow::{ Result, bail };
+
+use crate::{ chunk::BlockType, pos::BlockPos, query::glob, world::WorldView };
+
+// Blocks that hardly ever generate on their own, so are taken as placed by a
+// player. Villages, mineshafts and the like have some of them too and get
+// protected the same.
+const ARTIFICIAL: &[&str] = &[
+    "*_planks", "*_slab", "*_stairs", "*_fence", "*_fence_gate", "*_door", "*_trapdoor", "*_bed",
+    "*_wool", "*_carpet", "*_concrete", "*_glazed_terracotta", "glass", "*_glass", "glass_pane", "*_glass_pane",
+    "bricks", "*_bricks", "polished_*", "smooth_stone", "smooth_*_sandstone", "smooth_sandstone", "smooth_quartz", "cut_*", "chiseled_*",
+    "*_button", "*_pressure_plate", "*_sign", "*_wall_sign", "*_banner", "*_wall_banner", "*_shulker_box",
+    "torch", "wall_torch", "lantern", "chest", "trapped_chest", "barrel", "furnace", "blast_furnace", "smoker",
+    "crafting_table", "bookshelf", "hopper", "beacon", "anvil", "enchanting_table", "brewing_stand",
+    "iron_block", "gold_block", "diamond_block", "emerald_block", "netherite_block", "lapis_block", "redstone_block",
+    "hay_block", "sea_lantern", "redstone_lamp", "rail", "*_rail", "scaffolding",
+];
+
+pub fn is_artificial(block: &BlockType) -> bool {
+    let name = block.name.strip_prefix("minecraft:").unwrap_or(&block.name);
+    ARTIFICIAL.iter().any(|pattern| glob(pattern, name))
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Severity {
+    // Never dig under a build
+    Forbid,
+    // Dig under one only for BUILD_COST more per block
+    Penalize,
+}
+
+impl FromStr for Severity {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s {
+            "forbid" => Ok(Severity::Forbid),
+            "penalize" => Ok(Severity::Penalize),
+            _ => bail!("Unknown severity {s:?}, expected forbid or penalize"),
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug)]
+pub struct ProtectBuilds {
+    pub severity: Severity,
+    // How far above a mined block to look for builds
+    pub distance: u32,
+}
+
+// The lowest artificial block from pos up to distance blocks above it
+pub fn build_above(view: &WorldView, pos: BlockPos, distance: u32) -> Option<BlockPos> {
+    (0..=distance as i32)
+        .map(|dy| pos.offset(0, dy, 0))
+        .find(|above| view.block_at(*above).is_some_and(is_artificial))
+}
+
+// Worst first
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub enum DamageKind {
+    // Part of the tunnel itself
+    Mined,
+    // Nothing left under it
+    Floating,
+    // Open to the tunnel from the side or below
+    Exposed,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct BuildDamage {
+    pub kind: DamageKind,
+    // The artificial block
+    pub pos: BlockPos,
+    // The mined block that does it
+    pub mined: BlockPos,
+}
+
+impl fmt::Display for BuildDamage {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.kind {
+            DamageKind::Floating => write!(f, "Build block at {} is left floating when {} is mined", self.pos, self.mined),
+            DamageKind::Exposed => write!(f, "Build block at {} is opened up when {} is mined", self.pos, self.mined),
+            DamageKind::Mined => write!(f, "Build block at {} is mined", self.pos),
+        }
+    }
+}
+
+// What mining the blocks does to the builds around them. A build block is
+// floating once the block under it is mined, and exposed once any other
+// neighbour is. Each build block is reported once, as the worst of these,
+// and the worst come first.
+pub fn build_damage(view: &WorldView, mined: &[BlockPos]) -> Vec<BuildDamage> {
+    let mut damage: Vec<BuildDamage> = Vec::new();
+    for &block in mined {
+        for pos in std::iter::once(block).chain(block.neighbors()) {
+            if pos != block && mined.contains(&pos) || !view.block_at(pos).is_some_and(is_artificial) {
+                continue;
+            }
+            let kind = if pos == block {
+                DamageKind::Mined
+            } else if pos == block.offset(0, 1, 0) {
+                DamageKind::Floating
+            } else {
+                DamageKind::Exposed
+            };
+            match damage.iter_mut().find(|known| known.pos == pos) {
+                Some(known) if kind < known.kind => *known = BuildDamage { kind, pos, mined: block },
+                Some(_) => {},
+                None => damage.push(BuildDamage { kind, pos, mined: block }),
+            }
+        }
+    }
+    damage.sort_by_key(|damage| damage.kind);
+    damage
+}
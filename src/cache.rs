@@ -0,0 +1,603 @@
+use anyhow::{ Result, Context, bail, ensure };
+use std::{ collections::{ BTreeMap, HashMap, VecDeque }, fmt, fs, io::{ BufReader, BufWriter, Read, Write }, path::Path, str::FromStr, time::Instant };
+
+use crate::{ chunk::Chunk, world::Dimension };
+
+const LOG_MAGIC: &[u8; 4] = b"PMAL";
+const LOG_VERSION: u8 = 1;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct ChunkKey {
+    pub dimension: Dimension,
+    pub x: i32,
+    pub z: i32,
+}
+
+impl ChunkKey {
+    pub fn new(dimension: Dimension, x: i32, z: i32) -> ChunkKey {
+        ChunkKey { dimension, x, z }
+    }
+}
+
+// What looking a chunk up did to a cache
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Lookup {
+    pub hit: bool,
+    // Chunks pushed out to make room, on a miss. A cache with no room pushes
+    // out the chunk itself.
+    pub evicted: Vec<ChunkKey>,
+}
+
+// Decides which chunks a cache keeps, without holding any of them. The cache
+// in World and the simulator both go through this, so a policy can be tried
+// on recorded accesses without reading anything.
+pub trait CachePolicy: Send {
+    // Looks the chunk up, taking it in on a miss
+    fn access(&mut self, key: ChunkKey) -> Lookup;
+    // Whether the chunk is kept, without counting as a use
+    fn contains(&self, key: ChunkKey) -> bool;
+}
+
+// Pushes out the chunk used longest ago
+pub struct Lru {
+    capacity: usize,
+    clock: u64,
+    used: HashMap<ChunkKey, u64>,
+    order: BTreeMap<u64, ChunkKey>,
+}
+
+impl Lru {
+    pub fn new(capacity: usize) -> Lru {
+        Lru { capacity, clock: 0, used: HashMap::new(), order: BTreeMap::new() }
+    }
+
+    fn len(&self) -> usize {
+        self.used.len()
+    }
+
+    fn pop_oldest(&mut self) -> Option<ChunkKey> {
+        let (_, key) = self.order.pop_first()?;
+        self.used.remove(&key);
+        Some(key)
+    }
+}
+
+impl CachePolicy for Lru {
+    fn access(&mut self, key: ChunkKey) -> Lookup {
+        self.clock += 1;
+        let hit = match self.used.insert(key, self.clock) {
+            Some(used) => self.order.remove(&used).is_some(),
+            None => false,
+        };
+        self.order.insert(self.clock, key);
+
+        let mut evicted = Vec::new();
+        while self.len() > self.capacity {
+            evicted.extend(self.pop_oldest());
+        }
+        Lookup { hit, evicted }
+    }
+
+    fn contains(&self, key: ChunkKey) -> bool {
+        self.used.contains_key(&key)
+    }
+}
+
+// The full 2Q of Johnson and Shasha: chunks seen once wait in a FIFO, and
+// only the ones asked for again after falling out of it, which a FIFO of
+// ghosts remembers, get into the LRU part. A scan over many chunks used once
+// then can't push out the ones used all the time.
+pub struct TwoQueue {
+    capacity: usize,
+    // Most that wait in a1in, and ghosts remembered in a1out
+    kin: usize,
+    kout: usize,
+    // Newest at the front
+    a1in: VecDeque<ChunkKey>,
+    a1out: VecDeque<ChunkKey>,
+    am: Lru,
+}
+
+impl TwoQueue {
+    pub fn new(capacity: usize) -> TwoQueue {
+        TwoQueue {
+            capacity,
+            kin: (capacity / 4).max(1),
+            kout: (capacity / 2).max(1),
+            a1in: VecDeque::new(),
+            a1out: VecDeque::new(),
+            am: Lru::new(usize::MAX),
+        }
+    }
+
+    // Makes room for one more chunk, giving back the one pushed out
+    fn reclaim(&mut self) -> Option<ChunkKey> {
+        if self.a1in.len() + self.am.len() < self.capacity {
+            return None;
+        }
+        if self.a1in.len() > self.kin || self.am.len() == 0 {
+            let key = self.a1in.pop_back()?;
+            self.a1out.push_front(key);
+            self.a1out.truncate(self.kout);
+            Some(key)
+        } else {
+            self.am.pop_oldest()
+        }
+    }
+}
+
+impl CachePolicy for TwoQueue {
+    fn access(&mut self, key: ChunkKey) -> Lookup {
+        if self.capacity == 0 {
+            return Lookup { hit: false, evicted: vec![key] };
+        }
+        if self.am.contains(key) {
+            self.am.access(key);
+            return Lookup { hit: true, evicted: Vec::new() };
+        }
+        if self.a1in.contains(&key) {
+            return Lookup { hit: true, evicted: Vec::new() };
+        }
+
+        // The ghost comes out before reclaiming, which could push it out of a1out
+        let ghost = self.a1out.iter().position(|ghost| *ghost == key).and_then(|ghost| self.a1out.remove(ghost));
+        let evicted = self.reclaim().into_iter().collect();
+        if ghost.is_some() {
+            self.am.access(key);
+        } else {
+            self.a1in.push_front(key);
+        }
+        Lookup { hit: false, evicted }
+    }
+
+    fn contains(&self, key: ChunkKey) -> bool {
+        self.am.contains(key) || self.a1in.contains(&key)
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum EvictionPolicy {
+    Lru,
+    TwoQueue,
+}
+
+impl EvictionPolicy {
+    fn id(self) -> u8 {
+        match self {
+            EvictionPolicy::Lru => 0,
+            EvictionPolicy::TwoQueue => 1,
+        }
+    }
+}
+
+impl FromStr for EvictionPolicy {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<EvictionPolicy> {
+        match s {
+            "lru" => Ok(EvictionPolicy::Lru),
+            "2q" => Ok(EvictionPolicy::TwoQueue),
+            _ => bail!("Unknown eviction policy \"{s}\", expected lru or 2q"),
+        }
+    }
+}
+
+impl fmt::Display for EvictionPolicy {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.pad(match self {
+            EvictionPolicy::Lru => "lru",
+            EvictionPolicy::TwoQueue => "2q",
+        })
+    }
+}
+
+// How a chunk cache is set up. Nothing is kept by default.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct CacheConfig {
+    // Most chunks kept, missing ones included
+    pub capacity: usize,
+    // On a miss, the chunks this many chunks around it are read too
+    pub prefetch_radius: u32,
+    pub policy: EvictionPolicy,
+}
+
+impl Default for CacheConfig {
+    fn default() -> CacheConfig {
+        CacheConfig { capacity: 0, prefetch_radius: 0, policy: EvictionPolicy::Lru }
+    }
+}
+
+impl CacheConfig {
+    pub fn new(capacity: usize, policy: EvictionPolicy) -> CacheConfig {
+        CacheConfig { capacity, policy, ..CacheConfig::default() }
+    }
+
+    pub fn prefetch_radius(mut self, radius: u32) -> CacheConfig {
+        self.prefetch_radius = radius;
+        self
+    }
+
+    pub fn build(&self) -> Box<dyn CachePolicy> {
+        match self.policy {
+            EvictionPolicy::Lru => Box::new(Lru::new(self.capacity)),
+            EvictionPolicy::TwoQueue => Box::new(TwoQueue::new(self.capacity)),
+        }
+    }
+}
+
+impl fmt::Display for CacheConfig {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{} chunks, {}, prefetching {} around", self.capacity, self.policy, self.prefetch_radius)
+    }
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct CacheStats {
+    pub hits: usize,
+    pub misses: usize,
+    // Chunks read ahead on misses
+    pub prefetched: usize,
+}
+
+impl CacheStats {
+    pub fn accesses(&self) -> usize {
+        self.hits + self.misses
+    }
+
+    pub fn hit_rate(&self) -> f64 {
+        if self.accesses() == 0 {
+            return 0.0;
+        }
+        self.hits as f64 / self.accesses() as f64
+    }
+
+    // Every chunk read and decoded, asked for or prefetched
+    pub fn decodes(&self) -> usize {
+        self.misses + self.prefetched
+    }
+}
+
+impl fmt::Display for CacheStats {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{} accesses, {} hits, {} misses, {:.1}% hit rate, {} chunks decoded", self.accesses(), self.hits, self.misses, self.hit_rate() * 100.0, self.decodes())
+    }
+}
+
+// One chunk looked up, microseconds after recording started
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Access {
+    pub key: ChunkKey,
+    pub micros: u64,
+    pub hit: bool,
+}
+
+// The accesses of a command, with the cache they went through
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct AccessLog {
+    pub config: CacheConfig,
+    pub accesses: Vec<Access>,
+}
+
+impl AccessLog {
+    pub fn stats(&self) -> CacheStats {
+        let hits = self.accesses.iter().filter(|access| access.hit).count();
+        CacheStats { hits, misses: self.accesses.len() - hits, prefetched: 0 }
+    }
+
+    // A header with the cache, then a byte of dimension and hit for each
+    // access and varints of how far it moved from the last one, in chunks
+    // and microseconds. Accesses mostly go to chunks next to each other, so
+    // most take four bytes.
+    pub fn write(&self, w: &mut impl Write) -> Result<()> {
+        w.write_all(LOG_MAGIC)?;
+        w.write_all(&[LOG_VERSION, self.config.policy.id()])?;
+        write_varint(w, self.config.capacity as u64)?;
+        write_varint(w, self.config.prefetch_radius as u64)?;
+        write_varint(w, self.accesses.len() as u64)?;
+
+        let (mut x, mut z, mut micros) = (0, 0, 0);
+        for access in &self.accesses {
+            let dimension = Dimension::ALL.iter().position(|dimension| *dimension == access.key.dimension).unwrap() as u8;
+            w.write_all(&[dimension | if access.hit { 0x80 } else { 0 }])?;
+            write_varint(w, zigzag(access.key.x as i64 - x as i64))?;
+            write_varint(w, zigzag(access.key.z as i64 - z as i64))?;
+            write_varint(w, access.micros - micros)?;
+            (x, z, micros) = (access.key.x, access.key.z, access.micros);
+        }
+        Ok(())
+    }
+
+    pub fn read(r: &mut impl Read) -> Result<AccessLog> {
+        let mut header = [0; 6];
+        r.read_exact(&mut header).context("Too short for an access log")?;
+        ensure!(&header[..4] == LOG_MAGIC, "Not an access log");
+        ensure!(header[4] == LOG_VERSION, "Access log version {} isn't known", header[4]);
+        let policy = match header[5] {
+            0 => EvictionPolicy::Lru,
+            1 => EvictionPolicy::TwoQueue,
+            other => bail!("Unknown eviction policy {other} in the access log"),
+        };
+        let capacity = read_varint(r)? as usize;
+        let prefetch_radius = u32::try_from(read_varint(r)?).context("Prefetch radius out of range")?;
+        let count = read_varint(r)?;
+
+        let mut accesses = Vec::new();
+        let (mut x, mut z, mut micros) = (0i32, 0i32, 0u64);
+        for _ in 0..count {
+            let mut flags = [0];
+            r.read_exact(&mut flags).context("Access log ends early")?;
+            let Some(dimension) = Dimension::ALL.get((flags[0] & 0x7f) as usize) else {
+                bail!("Unknown dimension {} in the access log", flags[0] & 0x7f);
+            };
+            x = x.wrapping_add(unzigzag(read_varint(r)?) as i32);
+            z = z.wrapping_add(unzigzag(read_varint(r)?) as i32);
+            micros += read_varint(r)?;
+            accesses.push(Access { key: ChunkKey::new(*dimension, x, z), micros, hit: flags[0] & 0x80 != 0 });
+        }
+
+        Ok(AccessLog { config: CacheConfig { capacity, prefetch_radius, policy }, accesses })
+    }
+
+    pub fn save(&self, path: impl AsRef<Path>) -> Result<()> {
+        let mut w = BufWriter::new(fs::File::create(path)?);
+        self.write(&mut w)?;
+        w.flush()?;
+        Ok(())
+    }
+
+    pub fn load(path: impl AsRef<Path>) -> Result<AccessLog> {
+        let path = path.as_ref();
+        let file = fs::File::open(path).with_context(|| format!("Could not read {}", path.display()))?;
+        AccessLog::read(&mut BufReader::new(file)).with_context(|| format!("Could not read {}", path.display()))
+    }
+}
+
+fn zigzag(n: i64) -> u64 {
+    ((n << 1) ^ (n >> 63)) as u64
+}
+
+fn unzigzag(n: u64) -> i64 {
+    (n >> 1) as i64 ^ -((n & 1) as i64)
+}
+
+fn write_varint(w: &mut impl Write, mut n: u64) -> Result<()> {
+    loop {
+        let byte = (n & 0x7f) as u8;
+        n >>= 7;
+        if n == 0 {
+            w.write_all(&[byte])?;
+            return Ok(());
+        }
+        w.write_all(&[byte | 0x80])?;
+    }
+}
+
+fn read_varint(r: &mut impl Read) -> Result<u64> {
+    let mut n = 0;
+    for shift in (0..64).step_by(7) {
+        let mut byte = [0];
+        r.read_exact(&mut byte).context("Access log ends early")?;
+        n |= ((byte[0] & 0x7f) as u64) << shift;
+        if byte[0] & 0x80 == 0 {
+            return Ok(n);
+        }
+    }
+    bail!("Varint too long in the access log")
+}
+
+// What a lookup comes to: whether it hit, the chunks to read and the ones to
+// drop. Shared by the cache and the simulator, so both count the same.
+struct Step {
+    hit: bool,
+    // The chunk asked for first on a miss, then the ones read ahead
+    read: Vec<ChunkKey>,
+    evicted: Vec<ChunkKey>,
+}
+
+fn step(policy: &mut dyn CachePolicy, config: &CacheConfig, key: ChunkKey) -> Step {
+    let Lookup { hit, mut evicted } = policy.access(key);
+    if hit {
+        return Step { hit, read: Vec::new(), evicted };
+    }
+
+    let mut read = vec![key];
+    let radius = config.prefetch_radius as i32;
+    for dz in -radius..=radius {
+        for dx in -radius..=radius {
+            let next = ChunkKey::new(key.dimension, key.x + dx, key.z + dz);
+            if next != key && !policy.contains(next) {
+                evicted.extend(policy.access(next).evicted);
+                read.push(next);
+            }
+        }
+    }
+    Step { hit, read, evicted }
+}
+
+// Runs recorded accesses through a cache set up as config, without reading
+// any chunks
+pub fn simulate(accesses: &[Access], config: &CacheConfig) -> CacheStats {
+    let mut policy = config.build();
+    let mut stats = CacheStats::default();
+    for access in accesses {
+        let step = step(&mut *policy, config, access.key);
+        if step.hit {
+            stats.hits += 1;
+        } else {
+            stats.misses += 1;
+            stats.prefetched += step.read.len() - 1;
+        }
+    }
+    stats
+}
+
+// Parsed chunks kept between reads, chunks that aren't there included, with
+// the accesses recorded if asked for
+pub struct ChunkCache {
+    config: CacheConfig,
+    policy: Box<dyn CachePolicy>,
+    chunks: HashMap<ChunkKey, Option<Chunk>>,
+    stats: CacheStats,
+    recording: Option<(Instant, Vec<Access>)>,
+}
+
+impl ChunkCache {
+    pub fn new(config: CacheConfig) -> ChunkCache {
+        ChunkCache { config, policy: config.build(), chunks: HashMap::new(), stats: CacheStats::default(), recording: None }
+    }
+
+    pub fn config(&self) -> CacheConfig {
+        self.config
+    }
+
+    pub fn stats(&self) -> CacheStats {
+        self.stats
+    }
+
+    pub fn record(&mut self) {
+        self.recording = Some((Instant::now(), Vec::new()));
+    }
+
+    // The accesses so far, if they're being recorded
+    pub fn log(&self) -> Option<AccessLog> {
+        let (_, accesses) = self.recording.as_ref()?;
+        Some(AccessLog { config: self.config, accesses: accesses.clone() })
+    }
+
+    // The chunk from the cache, or read with load on a miss, along with the
+    // ones around it within the prefetch radius
+    pub fn get(&mut self, key: ChunkKey, mut load: impl FnMut(ChunkKey) -> Option<Chunk>) -> Option<Chunk> {
+        let step = step(&mut *self.policy, &self.config, key);
+        if let Some((started, accesses)) = &mut self.recording {
+            accesses.push(Access { key, micros: started.elapsed().as_micros() as u64, hit: step.hit });
+        }
+
+        let chunk = if step.hit {
+            self.stats.hits += 1;
+            self.chunks.get(&key).cloned().flatten()
+        } else {
+            self.stats.misses += 1;
+            self.stats.prefetched += step.read.len() - 1;
+            let mut chunk = None;
+            for read in step.read {
+                let loaded = load(read);
+                if read == key {
+                    chunk = loaded.clone();
+                }
+                if self.policy.contains(read) {
+                    self.chunks.insert(read, loaded);
+                }
+            }
+            chunk
+        };
+        for evicted in step.evicted {
+            if !self.policy.contains(evicted) {
+                self.chunks.remove(&evicted);
+            }
+        }
+        chunk
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{ BlockPos, World, nbt::{ Tag, TagPayload }, pathfinding::Pathfinder, region::put_chunks };
+
+    fn key(x: i32, z: i32) -> ChunkKey {
+        ChunkKey::new(Dimension::Overworld, x, z)
+    }
+
+    fn tag(name: &str, payload: TagPayload) -> Tag {
+        Tag { name: name.to_string(), payload }
+    }
+
+    // Stone from y 0 to 15 and air from 16 to 31
+    fn ground_chunk(x: i32, z: i32) -> Tag {
+        let section = |y: i8, block: &str| {
+            let palette = TagPayload::List(vec![TagPayload::Compound(vec![tag("Name", TagPayload::String(block.to_string()))].into())]);
+            TagPayload::Compound(vec![tag("Y", TagPayload::Byte(y)), tag("block_states", TagPayload::Compound(vec![tag("palette", palette)].into()))].into())
+        };
+        let root = vec![
+            tag("DataVersion", TagPayload::Int(3700)),
+            tag("xPos", TagPayload::Int(x)),
+            tag("zPos", TagPayload::Int(z)),
+            tag("yPos", TagPayload::Int(0)),
+            tag("sections", TagPayload::List(vec![section(0, "minecraft:stone"), section(1, "minecraft:air")])),
+        ];
+        tag("", TagPayload::Compound(root.into()))
+    }
+
+    #[test]
+    fn two_queue_keeps_hot_chunks_through_a_scan() {
+        // Two chunks asked for again after every scan of ten chunks used once
+        let mut accesses = Vec::new();
+        for round in 0..3 {
+            accesses.extend([key(0, 0), key(1, 0)]);
+            accesses.extend((0..10).map(|x| key(100 + round * 10 + x, 0)));
+        }
+        let accesses: Vec<Access> = accesses.into_iter().map(|key| Access { key, micros: 0, hit: false }).collect();
+
+        let lru = simulate(&accesses, &CacheConfig::new(8, EvictionPolicy::Lru));
+        let two_queue = simulate(&accesses, &CacheConfig::new(8, EvictionPolicy::TwoQueue));
+        assert_eq!(lru.accesses(), 36);
+        assert_eq!(lru.hits, 0);
+        assert_eq!(two_queue.hits, 2);
+        assert_eq!(simulate(&accesses, &CacheConfig::new(0, EvictionPolicy::TwoQueue)).hits, 0);
+    }
+
+    #[test]
+    fn prefetches_around_misses() {
+        let accesses: Vec<Access> = (0..4).map(|x| Access { key: key(x, 0), micros: 0, hit: false }).collect();
+        let stats = simulate(&accesses, &CacheConfig::new(100, EvictionPolicy::Lru).prefetch_radius(1));
+        assert_eq!((stats.hits, stats.misses, stats.prefetched), (2, 2, 13));
+    }
+
+    #[test]
+    fn writes_and_reads_logs() {
+        let log = AccessLog {
+            config: CacheConfig::new(64, EvictionPolicy::TwoQueue).prefetch_radius(2),
+            accesses: vec![
+                Access { key: key(-3, 7), micros: 12, hit: false },
+                Access { key: ChunkKey::new(Dimension::Nether, -2, 7), micros: 40, hit: true },
+                Access { key: ChunkKey::new(Dimension::End, i32::MAX, i32::MIN), micros: 1 << 40, hit: false },
+            ],
+        };
+        let mut bytes = Vec::new();
+        log.write(&mut bytes).unwrap();
+        assert_eq!(AccessLog::read(&mut bytes.as_slice()).unwrap(), log);
+        assert!(AccessLog::read(&mut &bytes[..bytes.len() - 1]).is_err());
+    }
+
+    #[test]
+    fn replays_a_pathfinding_session() {
+        let dir = std::env::temp_dir().join(format!("path-miner-cache-replay-{}", std::process::id()));
+        fs::create_dir_all(dir.join("region")).unwrap();
+        let chunks: Vec<(usize, Tag)> = (0..4).flat_map(|z| (0..4).map(move |x| (z * 32 + x, ground_chunk(x as i32, z as i32)))).collect();
+        put_chunks(dir.join("region").join("r.0.0.mca"), &chunks.iter().map(|(index, chunk)| (*index, chunk)).collect::<Vec<_>>()).unwrap();
+
+        let config = CacheConfig::new(6, EvictionPolicy::TwoQueue).prefetch_radius(1);
+        let world = World::open(&dir).unwrap().with_cache(config).record_accesses();
+        // A tour of three legs, each with the chunks around it loaded, like tour does
+        let stops = [BlockPos::new(2, 16, 2), BlockPos::new(30, 16, 20), BlockPos::new(60, 16, 40), BlockPos::new(20, 16, 50)];
+        for leg in stops.windows(2) {
+            let (from, to) = (leg[0], leg[1]);
+            let min_chunk = (from.chunk_x().min(to.chunk_x()), from.chunk_z().min(to.chunk_z()));
+            let max_chunk = (from.chunk_x().max(to.chunk_x()), from.chunk_z().max(to.chunk_z()));
+            let view = world.view(Dimension::Overworld, min_chunk, max_chunk).unwrap();
+            assert!(Pathfinder::new(&view).find_route(from, to).is_some());
+        }
+
+        let stats = world.cache_stats();
+        assert!(stats.hits > 0 && stats.misses > 0, "{stats}");
+        let path = dir.join("accesses.log");
+        world.access_log().unwrap().save(&path).unwrap();
+
+        let log = AccessLog::load(&path).unwrap();
+        assert_eq!(log.config, config);
+        assert_eq!(log.stats(), CacheStats { prefetched: 0, ..stats });
+        assert_eq!(simulate(&log.accesses, &log.config), stats);
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,150 @@
+use std::collections::{ HashMap, HashSet };
+
+use crate::{ chunk::{ BlockType, is_below_surface }, costs::CostModel, pos::BlockPos, world::WorldView };
+
+// What moving outside a cave costs on top of the usual when preferring caves
+pub const OUTSIDE_CAVE_COST: u32 = 2;
+
+// Air below the surface that's all connected, sides only, no diagonals
+pub struct Cave {
+    pub blocks: Vec<BlockPos>,
+    pub min: BlockPos,
+    pub max: BlockPos,
+    // The highest block of every opening to the surface
+    pub entrances: Vec<BlockPos>,
+}
+
+impl Cave {
+    pub fn volume(&self) -> usize {
+        self.blocks.len()
+    }
+}
+
+// Surface heights of the chunks looked at so far
+struct Surface<'a> {
+    view: &'a WorldView,
+    heights: HashMap<(i32, i32), [[i32; 16]; 16]>,
+}
+
+impl Surface<'_> {
+    // Same as Chunk::is_underground, cave air always counts
+    fn is_underground(&mut self, pos: BlockPos, block: &BlockType) -> bool {
+        let view = self.view;
+        let heights = self.heights.entry((pos.chunk_x(), pos.chunk_z()))
+            .or_insert_with(|| view.chunk(pos.chunk_x(), pos.chunk_z()).map_or([[i32::MIN; 16]; 16], |chunk| chunk.surface_heights()));
+        block.name == "minecraft:cave_air" || is_below_surface(heights, pos)
+    }
+}
+
+// Every cave in the view of at least min_volume blocks, largest first. Caves
+// running out of the view are cut off at its edge.
+pub fn find_caves(view: &WorldView, min_volume: usize) -> Vec<Cave> {
+    let mut surface = Surface { view, heights: HashMap::new() };
+    let mut seen: HashSet<BlockPos> = HashSet::new();
+    let mut caves = Vec::new();
+
+    for chunk in view.chunks() {
+        for section in chunk.sections() {
+            if !section.palette().iter().any(BlockType::is_air) {
+                continue;
+            }
+            for y in 0..16 {
+                for z in 0..16 {
+                    for x in 0..16 {
+                        let block = section.block_at(x, y, z);
+                        let pos = BlockPos::new(chunk.x() * 16 + x as i32, section.y() * 16 + y as i32, chunk.z() * 16 + z as i32);
+                        if !block.is_air() || seen.contains(&pos) || !surface.is_underground(pos, block) {
+                            continue;
+                        }
+                        let cave = flood_fill(view, &mut surface, &mut seen, pos);
+                        if cave.volume() >= min_volume {
+                            caves.push(cave);
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    caves.sort_by_key(|cave| std::cmp::Reverse(cave.volume()));
+    caves
+}
+
+fn flood_fill(view: &WorldView, surface: &mut Surface, seen: &mut HashSet<BlockPos>, start: BlockPos) -> Cave {
+    seen.insert(start);
+    let mut blocks = vec![start];
+    let mut openings = HashSet::new();
+    let (mut min, mut max) = (start, start);
+
+    let mut i = 0;
+    while i < blocks.len() {
+        let pos = blocks[i];
+        i += 1;
+        min = BlockPos::new(min.x.min(pos.x), min.y.min(pos.y), min.z.min(pos.z));
+        max = BlockPos::new(max.x.max(pos.x), max.y.max(pos.y), max.z.max(pos.z));
+
+        for next in pos.neighbors() {
+            let Some(block) = view.block_at(next) else {
+                continue;
+            };
+            if !block.is_air() || block.is_outside_world() {
+                continue;
+            }
+            if !surface.is_underground(next, block) {
+                openings.insert(pos);
+            } else if seen.insert(next) {
+                blocks.push(next);
+            }
+        }
+    }
+
+    Cave { entrances: highest_of_each_group(openings), blocks, min, max }
+}
+
+// Blocks touching, corners and edges too, make up one opening, which is
+// reported by its highest block
+fn highest_of_each_group(mut blocks: HashSet<BlockPos>) -> Vec<BlockPos> {
+    let mut highest = Vec::new();
+    while let Some(&first) = blocks.iter().next() {
+        blocks.remove(&first);
+        let mut group = vec![first];
+        let mut i = 0;
+        while i < group.len() {
+            let pos = group[i];
+            for (dx, dy, dz) in (-1..=1).flat_map(|dx| (-1..=1).flat_map(move |dy| (-1..=1).map(move |dz| (dx, dy, dz)))) {
+                let next = pos.offset(dx, dy, dz);
+                if blocks.remove(&next) {
+                    group.push(next);
+                }
+            }
+            i += 1;
+        }
+        highest.extend(group.into_iter().max_by_key(|pos| (pos.y, pos.x, pos.z)));
+    }
+    highest.sort();
+    highest
+}
+
+// Makes every move that doesn't end in one of the caves cost OUTSIDE_CAVE_COST
+// more, so routes follow caves where they can
+pub struct PreferCaves<'a> {
+    inner: &'a dyn CostModel,
+    blocks: HashSet<BlockPos>,
+}
+
+impl<'a> PreferCaves<'a> {
+    pub fn new(inner: &'a dyn CostModel, caves: &[Cave]) -> PreferCaves<'a> {
+        PreferCaves { inner, blocks: caves.iter().flat_map(|cave| cave.blocks.iter().copied()).collect() }
+    }
+}
+
+impl CostModel for PreferCaves<'_> {
+    fn dig_cost(&self, block: &BlockType) -> Option<u32> {
+        self.inner.dig_cost(block)
+    }
+
+    fn move_cost(&self, from: BlockPos, to: BlockPos) -> u32 {
+        let outside = if self.blocks.contains(&to) { 0 } else { OUTSIDE_CAVE_COST };
+        self.inner.move_cost(from, to) + outside
+    }
+}
@@ -0,0 +1,1751 @@
+use anyhow::{ Result, Context, bail };
+
+use std::{ collections::{ BTreeMap, HashMap }, fmt, str::FromStr, sync::LazyLock };
+
+use crate::{ block_id::BlockId, height::WorldHeight, item::{ ItemStack, items_in }, legacy::legacy_block_name, nbt::{ Compound, Tag, TagPayload }, pos::BlockPos, query::Query, spawning::is_spawn_space, structure::ChunkStructures, world::Dimension };
+
+// 1.13, the first version with block state palettes instead of numeric ids
+pub const FLATTENING_DATA_VERSION: i32 = 1451;
+// Chunks older than this pack block indices back to back, so an entry can span two longs
+pub const NON_SPANNING_PACKING_DATA_VERSION: i32 = 2566;
+// 1.18 moved the chunk out of the Level compound and renamed the section fields
+pub const TOP_LEVEL_SECTIONS_DATA_VERSION: i32 = 2844;
+
+// What block_at gives for heights outside the world, like the game does
+pub static OUTSIDE_WORLD: LazyLock<BlockType> = LazyLock::new(|| BlockType { name: BlockId::intern("minecraft:void_air"), properties: Vec::new() });
+
+// Chunk generation stages in the order they happen, since 1.14 and then
+// before it. Some only appear in some versions.
+pub const CHUNK_STATUSES: &[&str] = &[
+    "empty", "structure_starts", "structure_references", "biomes", "noise", "surface", "carvers", "liquid_carvers",
+    "features", "initialize_light", "light", "spawn", "heightmaps", "full",
+];
+pub const LEGACY_CHUNK_STATUSES: &[&str] = &[
+    "empty", "base", "carved", "liquid_carved", "decorated", "lighted", "mobs_spawned", "finalized", "fullchunk", "postprocessed",
+];
+// The last stage, which was fullchunk or postprocessed before 1.14
+const FULL_STATUSES: &[&str] = &["full", "fullchunk", "postprocessed"];
+
+// Name endings of blocks without collision that mobs can't spawn in for
+// other reasons, so is_spawn_space doesn't count them as open
+const NOT_MOTION_BLOCKING: &[&str] = &[
+    "torch", "_sapling", "_tulip", "poppy", "dandelion", "orchid", "allium", "azure_bluet", "oxeye_daisy", "cornflower",
+    "lily_of_the_valley", "wither_rose", "torchflower", "_petals", "sunflower", "lilac", "rose_bush", "peony", "tall_grass",
+    "large_fern", "_mushroom", "cobweb", "_roots", "_fungus", "nether_sprouts", "sweet_berry_bush", "wheat", "carrots",
+    "potatoes", "beetroots", "melon_stem", "pumpkin_stem", "nether_wart", "_vines", "_vines_plant", "fire", "minecraft:light",
+    "structure_void",
+];
+
+// Whether a block counts for the MOTION_BLOCKING heightmap: it has collision
+// or holds a fluid. Goes by name like is_spawn_space, which it builds on.
+pub fn blocks_motion(block: &BlockType) -> bool {
+    !is_spawn_space(block) && !NOT_MOTION_BLOCKING.iter().any(|part| block.name.as_str().ends_with(part))
+}
+
+pub fn unpack_straddled_indices(data: &[i64], bits_per_block: usize) -> Option<Vec<usize>> {
+    unpack_straddled(data, bits_per_block, 4096)
+}
+
+fn unpack_straddled(data: &[i64], bits: usize, count: usize) -> Option<Vec<usize>> {
+    let mask = (1u64 << bits) - 1;
+    let mut indices = Vec::with_capacity(count);
+
+    for i in 0..count {
+        let bit = i * bits;
+        let word = bit / 64;
+        let offset = bit % 64;
+
+        let mut value = (*data.get(word)? as u64) >> offset;
+        if offset + bits > 64 {
+            value |= (*data.get(word + 1)? as u64) << (64 - offset);
+        }
+
+        indices.push((value & mask) as usize);
+    }
+
+    Some(indices)
+}
+
+// Litematica still packs this way no matter the version
+pub fn pack_straddled_indices(indices: &[usize], bits_per_block: usize) -> Vec<i64> {
+    let mut data = vec![0u64; (indices.len() * bits_per_block).div_ceil(64)];
+
+    for (i, index) in indices.iter().enumerate() {
+        let bit = i * bits_per_block;
+        let word = bit / 64;
+        let offset = bit % 64;
+
+        data[word] |= (*index as u64) << offset;
+        if offset + bits_per_block > 64 {
+            data[word + 1] |= (*index as u64) >> (64 - offset);
+        }
+    }
+
+    data.into_iter().map(|long| long as i64).collect()
+}
+
+pub fn pack_padded_indices(indices: &[usize], bits_per_block: usize) -> Vec<i64> {
+    let per_long = 64 / bits_per_block;
+    let mut data = vec![0u64; indices.len().div_ceil(per_long)];
+
+    for (i, index) in indices.iter().enumerate() {
+        data[i / per_long] |= (*index as u64) << ((i % per_long) * bits_per_block);
+    }
+
+    data.into_iter().map(|long| long as i64).collect()
+}
+
+pub fn unpack_padded_indices(data: &[i64], bits_per_block: usize) -> Option<Vec<usize>> {
+    unpack_padded(data, bits_per_block, 4096)
+}
+
+fn unpack_padded(data: &[i64], bits: usize, count: usize) -> Option<Vec<usize>> {
+    let per_long = 64 / bits;
+    let mask = (1u64 << bits) - 1;
+    let mut indices = Vec::with_capacity(count);
+
+    for i in 0..count {
+        let value = (*data.get(i / per_long)? as u64) >> ((i % per_long) * bits);
+        indices.push((value & mask) as usize);
+    }
+
+    Some(indices)
+}
+
+// Block states never use fewer than 4 bits per entry
+fn block_bits_for_palette(palette_len: usize) -> usize {
+    let bits = usize::BITS - (palette_len.max(1) - 1).leading_zeros();
+    (bits as usize).max(4)
+}
+
+fn block_index(x: usize, y: usize, z: usize) -> usize {
+    assert!(x < 16 && y < 16 && z < 16, "Block ({x}, {y}, {z}) is outside the section");
+    (y << 8) | (z << 4) | x
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct BlockType {
+    pub name: BlockId,
+    // Properties like facing or waterlogged in the order they're stored. Always
+    // empty for chunks from before 1.13.
+    pub properties: Vec<(String, String)>,
+}
+
+impl BlockType {
+    // The name followed by the properties, e.g. minecraft:oak_log[axis=y], as
+    // commands and schematics write block states
+    pub fn state_string(&self) -> String {
+        if self.properties.is_empty() {
+            return self.name.to_string();
+        }
+        let properties: Vec<String> = self.properties.iter().map(|(key, value)| format!("{key}={value}")).collect();
+        format!("{}[{}]", self.name, properties.join(","))
+    }
+
+    pub fn is_air(&self) -> bool {
+        matches!(self.name.as_str(), "minecraft:air" | "minecraft:cave_air" | "minecraft:void_air")
+    }
+
+    // Saved chunks never hold void air, it only stands for OUTSIDE_WORLD
+    pub fn is_outside_world(&self) -> bool {
+        self.name.as_str() == "minecraft:void_air"
+    }
+
+    // Whether this block has the state's name and every property the state
+    // lists, so minecraft:oak_log[axis=y] matches only upright logs and
+    // minecraft:oak_log all of them
+    pub fn matches(&self, pattern: &BlockState) -> bool {
+        self.name == pattern.name && pattern.properties.iter().all(|(key, value)| self.property(key) == Some(value.as_str()))
+    }
+
+    pub fn property(&self, key: &str) -> Option<&str> {
+        self.properties.iter().find(|(name, _)| name == key).map(|(_, value)| value.as_str())
+    }
+
+    // The properties in the order they're stored don't matter to the game, the state doesn't keep it
+    pub fn state(&self) -> BlockState {
+        BlockState { name: self.name.to_string(), properties: self.properties.iter().cloned().collect() }
+    }
+
+    fn to_nbt(&self) -> TagPayload {
+        let mut entry = vec![Tag { name: "Name".to_string(), payload: TagPayload::String(self.name.to_string()) }];
+        if !self.properties.is_empty() {
+            let properties = self.properties.iter()
+                .map(|(key, value)| Tag { name: key.clone(), payload: TagPayload::String(value.clone()) })
+                .collect();
+            entry.push(Tag { name: "Properties".to_string(), payload: TagPayload::Compound(properties) });
+        }
+        TagPayload::Compound(entry.into())
+    }
+}
+
+impl From<BlockState> for BlockType {
+    fn from(state: BlockState) -> BlockType {
+        BlockType { name: BlockId::intern(&state.name), properties: state.properties.into_iter().collect() }
+    }
+}
+
+// A block with its properties kept sorted by key, so two states are equal and
+// hash the same however their properties were ordered
+#[derive(Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct BlockState {
+    pub name: String,
+    pub properties: BTreeMap<String, String>,
+}
+
+impl BlockState {
+    pub fn new(name: impl Into<String>) -> BlockState {
+        BlockState { name: name.into(), properties: BTreeMap::new() }
+    }
+
+    pub fn with(mut self, key: impl Into<String>, value: impl Into<String>) -> BlockState {
+        self.properties.insert(key.into(), value.into());
+        self
+    }
+}
+
+// Writes states like commands take them, e.g. minecraft:oak_log[axis=y]
+impl fmt::Display for BlockState {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.name)?;
+        if !self.properties.is_empty() {
+            let properties: Vec<String> = self.properties.iter().map(|(key, value)| format!("{key}={value}")).collect();
+            write!(f, "[{}]", properties.join(","))?;
+        }
+        Ok(())
+    }
+}
+
+impl FromStr for BlockState {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<BlockState> {
+        let Some((name, properties)) = s.split_once('[') else {
+            return Ok(BlockState::new(s));
+        };
+        let Some(properties) = properties.strip_suffix(']') else {
+            bail!("Block state {s} is missing its closing ]");
+        };
+
+        let mut state = BlockState::new(name);
+        for property in properties.split(',').filter(|property| !property.is_empty()) {
+            match property.split_once('=') {
+                Some((key, value)) => state.properties.insert(key.trim().to_string(), value.trim().to_string()),
+                None => bail!("Block property {property} has no value"),
+            };
+        }
+        Ok(state)
+    }
+}
+
+#[derive(Clone)]
+pub struct Palette {
+    entries: Vec<BlockType>,
+}
+
+impl Palette {
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    pub fn get(&self, index: usize) -> Option<&BlockType> {
+        self.entries.get(index)
+    }
+
+    pub fn iter(&self) -> std::slice::Iter<'_, BlockType> {
+        self.entries.iter()
+    }
+}
+
+impl std::ops::Index<usize> for Palette {
+    type Output = BlockType;
+
+    fn index(&self, index: usize) -> &BlockType {
+        &self.entries[index]
+    }
+}
+
+impl<'a> IntoIterator for &'a Palette {
+    type Item = &'a BlockType;
+    type IntoIter = std::slice::Iter<'a, BlockType>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Packing {
+    // Entries never cross a long, leftover bits are padding (1.16+)
+    Padded,
+    // Entries are packed back to back and may span two longs (1.13-1.15)
+    Straddled,
+}
+
+impl Packing {
+    pub fn for_data_version(data_version: i32) -> Packing {
+        if data_version >= NON_SPANNING_PACKING_DATA_VERSION {
+            Packing::Padded
+        } else {
+            Packing::Straddled
+        }
+    }
+
+    pub fn unpack(self, data: &[i64], bits_per_block: usize) -> Option<Vec<usize>> {
+        match self {
+            Packing::Padded => unpack_padded_indices(data, bits_per_block),
+            Packing::Straddled => unpack_straddled_indices(data, bits_per_block),
+        }
+    }
+
+    // Longs needed to hold count entries of the given width
+    fn packed_len(self, count: usize, bits: usize) -> usize {
+        match self {
+            Packing::Padded => count.div_ceil(64 / bits),
+            Packing::Straddled => (count * bits).div_ceil(64),
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum HeightmapKind {
+    // Highest block that blocks movement or holds a fluid
+    MotionBlocking,
+    // Like MotionBlocking, but leaves don't count
+    MotionBlockingNoLeaves,
+    // Highest block that blocks movement
+    OceanFloor,
+    // Highest non-air block
+    WorldSurface,
+}
+
+impl HeightmapKind {
+    pub fn nbt_name(self) -> &'static str {
+        match self {
+            HeightmapKind::MotionBlocking => "MOTION_BLOCKING",
+            HeightmapKind::MotionBlockingNoLeaves => "MOTION_BLOCKING_NO_LEAVES",
+            HeightmapKind::OceanFloor => "OCEAN_FLOOR",
+            HeightmapKind::WorldSurface => "WORLD_SURFACE",
+        }
+    }
+}
+
+#[derive(Clone)]
+pub struct BlockStates {
+    palette: Palette,
+    // Empty when the palette holds a single block
+    data: Vec<i64>,
+    packing: Packing,
+}
+
+impl BlockStates {
+    pub fn palette(&self) -> &Palette {
+        &self.palette
+    }
+
+    pub fn data(&self) -> &[i64] {
+        &self.data
+    }
+
+    pub fn packing(&self) -> Packing {
+        self.packing
+    }
+
+    pub fn bits_per_block(&self) -> usize {
+        block_bits_for_palette(self.palette.len())
+    }
+
+    pub fn index_at(&self, x: usize, y: usize, z: usize) -> usize {
+        let i = block_index(x, y, z);
+
+        if self.data.is_empty() {
+            return 0;
+        }
+
+        let bits = self.bits_per_block();
+        let value = match self.packing {
+            Packing::Padded => {
+                let per_long = 64 / bits;
+                (self.data[i / per_long] as u64) >> ((i % per_long) * bits)
+            },
+            Packing::Straddled => {
+                let bit = i * bits;
+                let offset = bit % 64;
+                let mut value = (self.data[bit / 64] as u64) >> offset;
+                if offset + bits > 64 {
+                    value |= (self.data[bit / 64 + 1] as u64) << (64 - offset);
+                }
+                value
+            },
+        };
+        (value & ((1u64 << bits) - 1)) as usize
+    }
+
+    pub fn indices(&self) -> Vec<usize> {
+        if self.data.is_empty() {
+            vec![0; 4096]
+        } else {
+            // Length and palette range are checked when the section is loaded
+            self.packing.unpack(&self.data, self.bits_per_block()).unwrap_or_default()
+        }
+    }
+
+    pub fn block_at(&self, x: usize, y: usize, z: usize) -> &BlockType {
+        &self.palette.entries[self.index_at(x, y, z)]
+    }
+
+    // Puts a block at a position in the section, adding it to the palette if
+    // it's new. The data is packed again, wider, when the palette outgrows the
+    // bits per block it had. Returns false if the block was already there.
+    fn set_block(&mut self, x: usize, y: usize, z: usize, block: BlockState) -> bool {
+        let i = block_index(x, y, z);
+        let old_bits = self.bits_per_block();
+        let index = match self.palette.entries.iter().position(|entry| entry.state() == block) {
+            Some(index) if index == self.index_at(x, y, z) => return false,
+            Some(index) => index,
+            None => {
+                self.palette.entries.push(block.into());
+                self.palette.entries.len() - 1
+            },
+        };
+
+        let bits = self.bits_per_block();
+        if self.data.is_empty() || bits != old_bits {
+            let mut indices = if self.data.is_empty() { vec![0; 4096] } else { self.packing.unpack(&self.data, old_bits).unwrap_or_default() };
+            indices[i] = index;
+            self.data = match self.packing {
+                Packing::Padded => pack_padded_indices(&indices, bits),
+                Packing::Straddled => pack_straddled_indices(&indices, bits),
+            };
+            return true;
+        }
+
+        let mask = (1u64 << bits) - 1;
+        let mut put = |word: usize, shift: usize, value: u64, mask: u64| {
+            let long = self.data[word] as u64 & !(mask << shift) | (value & mask) << shift;
+            self.data[word] = long as i64;
+        };
+        match self.packing {
+            Packing::Padded => {
+                let per_long = 64 / bits;
+                put(i / per_long, (i % per_long) * bits, index as u64, mask);
+            },
+            Packing::Straddled => {
+                let bit = i * bits;
+                let offset = bit % 64;
+                put(bit / 64, offset, index as u64, mask);
+                if offset + bits > 64 {
+                    // The high bits of the entry start the next long
+                    put(bit / 64 + 1, 0, index as u64 >> (64 - offset), mask >> (64 - offset));
+                }
+            },
+        }
+        true
+    }
+
+    // Takes a new palette and an index into it for every block. Entries no
+    // block refers to are dropped and the rest packed at the width the palette
+    // needs. A palette of one block has no data unless keep_data asks for it.
+    fn set_blocks(&mut self, entries: Vec<BlockType>, indices: &[usize], keep_data: bool) {
+        let mut used = vec![false; entries.len()];
+        for &index in indices {
+            used[index] = true;
+        }
+
+        let mut remap = vec![0; entries.len()];
+        let mut kept = Vec::new();
+        for (i, entry) in entries.into_iter().enumerate() {
+            if used[i] {
+                remap[i] = kept.len();
+                kept.push(entry);
+            }
+        }
+        let indices: Vec<usize> = indices.iter().map(|&index| remap[index]).collect();
+
+        self.data = if kept.len() > 1 || keep_data {
+            let bits = block_bits_for_palette(kept.len());
+            match self.packing {
+                Packing::Padded => pack_padded_indices(&indices, bits),
+                Packing::Straddled => pack_straddled_indices(&indices, bits),
+            }
+        } else {
+            Vec::new()
+        };
+        self.palette = Palette { entries: kept };
+    }
+}
+
+// A section's biomes, one per 4x4x4 cell (1.18+)
+#[derive(Clone)]
+pub struct Biomes {
+    palette: Vec<String>,
+    // Empty when the palette holds a single biome
+    data: Vec<i64>,
+}
+
+impl Biomes {
+    pub fn palette(&self) -> &[String] {
+        &self.palette
+    }
+
+    fn bits(&self) -> usize {
+        (usize::BITS - (self.palette.len() - 1).leading_zeros()) as usize
+    }
+
+    // Palette index of each of the 64 cells, ordered like biome_at indexes them
+    pub fn indices(&self) -> Vec<usize> {
+        if self.data.is_empty() {
+            vec![0; 64]
+        } else {
+            // Length and palette range are checked when the section is loaded
+            unpack_padded(&self.data, self.bits(), 64).unwrap_or_default()
+        }
+    }
+
+    // x, y and z are cell coordinates from 0 to 3
+    pub fn biome_at(&self, x: usize, y: usize, z: usize) -> &str {
+        assert!(x < 4 && y < 4 && z < 4, "Cell ({x}, {y}, {z}) is outside the section");
+        if self.data.is_empty() {
+            return &self.palette[0];
+        }
+
+        let i = (y << 4) | (z << 2) | x;
+        let bits = self.bits();
+        let per_long = 64 / bits;
+        let value = (self.data[i / per_long] as u64) >> ((i % per_long) * bits);
+        &self.palette[(value & ((1u64 << bits) - 1)) as usize]
+    }
+}
+
+#[derive(Clone)]
+pub struct Section {
+    y: i32,
+    block_states: BlockStates,
+    // None before 1.18, when biomes were stored for the whole chunk
+    biomes: Option<Biomes>,
+    // None when the section has no light data, e.g. when the light hasn't
+    // been worked out yet or the chunk was read without it
+    block_light: Option<LightArray>,
+    sky_light: Option<LightArray>,
+}
+
+// Light levels from 0 to 15, a nibble per block in the same order as the
+// block data, the lower half of each byte first
+#[derive(Clone, Debug)]
+pub struct LightArray(Vec<u8>);
+
+impl LightArray {
+    pub fn get(&self, x: usize, y: usize, z: usize) -> u8 {
+        let i = y << 8 | z << 4 | x;
+        (self.0[i >> 1] >> ((i & 1) * 4)) & 15
+    }
+}
+
+impl Section {
+    pub fn y(&self) -> i32 {
+        self.y
+    }
+
+    pub fn block_states(&self) -> &BlockStates {
+        &self.block_states
+    }
+
+    pub fn palette(&self) -> &Palette {
+        &self.block_states.palette
+    }
+
+    pub fn block_at(&self, x: usize, y: usize, z: usize) -> &BlockType {
+        self.block_states.block_at(x, y, z)
+    }
+
+    pub fn biomes(&self) -> Option<&Biomes> {
+        self.biomes.as_ref()
+    }
+
+    // Light from torches, lava and other glowing blocks
+    pub fn block_light(&self, x: usize, y: usize, z: usize) -> Option<u8> {
+        Some(self.block_light.as_ref()?.get(x, y, z))
+    }
+
+    // Light from the sky, as it would be at noon
+    pub fn sky_light(&self, x: usize, y: usize, z: usize) -> Option<u8> {
+        Some(self.sky_light.as_ref()?.get(x, y, z))
+    }
+
+    // Biome of the cell holding block (x, y, z) of the section
+    pub fn biome_at(&self, x: usize, y: usize, z: usize) -> Option<&str> {
+        Some(self.biomes.as_ref()?.biome_at(x / 4, y / 4, z / 4))
+    }
+
+    // Indices into the section (y << 8 | z << 4 | x) of blocks with one of the given names
+    pub fn find_blocks(&self, names: &[&str]) -> Vec<usize> {
+        let ids: Vec<BlockId> = names.iter().filter_map(|name| BlockId::get(name)).collect();
+        self.find_where(|block| ids.contains(&block.name))
+    }
+
+    // Like find_blocks, for blocks matching one of the states
+    pub fn find_states(&self, states: &[BlockState]) -> Vec<usize> {
+        self.find_where(|block| states.iter().any(|state| block.matches(state)))
+    }
+
+    fn find_where(&self, wanted: impl Fn(&BlockType) -> bool) -> Vec<usize> {
+        let palette = self.palette();
+        let matches: Vec<bool> = palette.iter().map(wanted).collect();
+
+        if !matches.contains(&true) {
+            return Vec::new();
+        }
+
+        self.block_states.indices().into_iter()
+            .enumerate()
+            .filter(|(_, palette_index)| matches[*palette_index])
+            .map(|(i, _)| i)
+            .collect()
+    }
+}
+
+#[derive(Clone)]
+pub struct BlockEntity {
+    id: String,
+    pos: BlockPos,
+    nbt: TagPayload,
+}
+
+impl BlockEntity {
+    pub fn from_nbt(nbt: TagPayload) -> Result<BlockEntity> {
+        let id = match nbt.get("id") {
+            Some(TagPayload::String(id)) => id.clone(),
+            _ => bail!("Block entity has no id"),
+        };
+
+        let coord = |name: &str| match nbt.get(name) {
+            Some(TagPayload::Int(i)) => Ok(*i),
+            _ => bail!("{id} has no {name} coordinate"),
+        };
+        let pos = BlockPos::new(coord("x")?, coord("y")?, coord("z")?);
+
+        Ok(BlockEntity { id, pos, nbt })
+    }
+
+    pub fn id(&self) -> &str {
+        &self.id
+    }
+
+    pub fn pos(&self) -> BlockPos {
+        self.pos
+    }
+
+    // Empty for block entities that aren't containers
+    pub fn items(&self) -> Vec<ItemStack> {
+        self.nbt.get("Items").map(items_in).unwrap_or_default()
+    }
+
+    pub fn nbt(&self) -> &TagPayload {
+        &self.nbt
+    }
+}
+
+#[derive(Clone)]
+pub struct Chunk {
+    x: i32,
+    z: i32,
+    data_version: i32,
+    sections: Vec<Section>,
+    block_entities: Vec<BlockEntity>,
+    nbt: Tag,
+    height: WorldHeight,
+    // Sections and block entities changed since the NBT was last brought up to date
+    dirty_sections: Vec<i32>,
+    block_entities_dirty: bool,
+    // Whether flush works the heightmaps out again or leaves them to the game
+    recompute_heightmaps: bool,
+}
+
+impl Chunk {
+    // Picks the layout from the chunk's DataVersion. Chunks from before 1.13
+    // have their numeric block ids converted to a palette.
+    pub fn from_nbt(nbt: Tag) -> Result<Chunk> {
+        Chunk::from_nbt_where(nbt, |_| true)
+    }
+
+    // Leaves out the sections without a palette entry keep wants, before
+    // their block data is unpacked. Searches use this to skip the bulk of a
+    // world, where the blocks they look for don't appear at all.
+    pub fn from_nbt_where(nbt: Tag, keep: impl Fn(&BlockType) -> bool) -> Result<Chunk> {
+        let root = as_compound(&nbt.payload, "chunk root")?;
+
+        // Chunks from before 1.9 have no DataVersion at all
+        let data_version = match root.get("DataVersion") {
+            Some(payload) => as_int(payload, "DataVersion")?,
+            None => 0,
+        };
+        let (level, sections_name, block_entities_name) = if data_version >= TOP_LEVEL_SECTIONS_DATA_VERSION {
+            (root, "sections", "block_entities")
+        } else {
+            (as_compound(field(root, "Level")?, "Level")?, "Sections", "TileEntities")
+        };
+
+        let x = as_int(field(level, "xPos")?, "xPos")?;
+        let z = as_int(field(level, "zPos")?, "zPos")?;
+
+        let mut sections = Vec::new();
+        // Sections can be missing from old chunks that were never populated
+        if let Some(list) = level.get(sections_name) {
+            for (i, section) in as_list(list, sections_name)?.iter().enumerate() {
+                let section = parse_section(section, data_version, &keep).with_context(|| format!("Invalid section {i} in chunk ({x}, {z})"))?;
+                sections.extend(section);
+            }
+        }
+
+        let mut block_entities = Vec::new();
+        if let Some(list) = level.get(block_entities_name) {
+            for (i, block_entity) in as_list(list, block_entities_name)?.iter().enumerate() {
+                let block_entity = BlockEntity::from_nbt(block_entity.clone()).with_context(|| format!("Invalid block entity {i} in chunk ({x}, {z})"))?;
+                block_entities.push(block_entity);
+            }
+        }
+
+        // Chunks without sections to tell by get the overworld's height
+        let height = WorldHeight::of_chunk_nbt(&nbt.payload).unwrap_or(WorldHeight::for_data_version(Dimension::Overworld, data_version));
+
+        Ok(Chunk { x, z, data_version, sections, block_entities, nbt, height, dirty_sections: Vec::new(), block_entities_dirty: false, recompute_heightmaps: true })
+    }
+
+    pub fn x(&self) -> i32 {
+        self.x
+    }
+
+    pub fn z(&self) -> i32 {
+        self.z
+    }
+
+    pub fn data_version(&self) -> i32 {
+        self.data_version
+    }
+
+    pub fn sections(&self) -> &[Section] {
+        &self.sections
+    }
+
+    pub fn section(&self, y: i32) -> Option<&Section> {
+        self.sections.iter().find(|section| section.y == y)
+    }
+
+    // Chests, spawners, signs and the like
+    pub fn block_entities(&self) -> &[BlockEntity] {
+        &self.block_entities
+    }
+
+    // Structures starting in the chunk and references to the ones reaching
+    // into it, read from the NBT on every call
+    pub fn structures(&self) -> Result<ChunkStructures> {
+        let path = if self.data_version >= TOP_LEVEL_SECTIONS_DATA_VERSION { "structures" } else { "Level.Structures" };
+        ChunkStructures::from_nbt(self.nbt.payload.get_path(path).ok(), (self.x, self.z))
+            .with_context(|| format!("Invalid structures in chunk ({}, {})", self.x, self.z))
+    }
+
+    // How far world generation has got with the chunk, like "features" or
+    // "full", without the minecraft: prefix newer versions put on it
+    pub fn status(&self) -> Option<&str> {
+        let path = if self.data_version >= TOP_LEVEL_SECTIONS_DATA_VERSION { "Status" } else { "Level.Status" };
+        match self.nbt.payload.get_path(path) {
+            Ok(TagPayload::String(status)) => Some(status.strip_prefix("minecraft:").unwrap_or(status)),
+            _ => None,
+        }
+    }
+
+    // Whether the chunk has gone through every generation stage, as opposed
+    // to a proto-chunk the game still has to finish. Chunks from before 1.13
+    // have no status and go by TerrainPopulated instead.
+    pub fn is_fully_generated(&self) -> bool {
+        match self.status() {
+            Some(status) => FULL_STATUSES.contains(&status),
+            None => matches!(self.nbt.payload.get_path("Level.TerrainPopulated"), Ok(TagPayload::Byte(1))),
+        }
+    }
+
+    // Ticks players have spent near the chunk, added up over all of them. 0
+    // for chunks without the tag.
+    pub fn inhabited_time(&self) -> i64 {
+        let path = if self.data_version >= TOP_LEVEL_SECTIONS_DATA_VERSION { "InhabitedTime" } else { "Level.InhabitedTime" };
+        match self.nbt.payload.get_path(path) {
+            Ok(TagPayload::Long(ticks)) => *ticks,
+            _ => 0,
+        }
+    }
+
+    // The NBT as loaded, edits only show up in it once they're flushed
+    pub fn nbt(&self) -> &Tag {
+        &self.nbt
+    }
+
+    // x and z are local to the chunk, y is the world height. OUTSIDE_WORLD
+    // above and below the world's height, None for heights in it without a
+    // section, like those of chunks read without all their sections.
+    pub fn block_at(&self, x: usize, y: i32, z: usize) -> Option<&BlockType> {
+        if !self.height.contains(y) {
+            return Some(&OUTSIDE_WORLD);
+        }
+        let section = self.section(y.div_euclid(16))?;
+        Some(section.block_at(x, y.rem_euclid(16) as usize, z))
+    }
+
+    // x and z are local to the chunk, y is the world height. None before 1.18.
+    pub fn biome_at(&self, x: usize, y: i32, z: usize) -> Option<&str> {
+        let section = self.section(y.div_euclid(16))?;
+        section.biome_at(x, y.rem_euclid(16) as usize, z)
+    }
+
+    // x and z are local to the chunk, y is the world height. None where
+    // there's no section or it has no light data.
+    pub fn block_light(&self, x: usize, y: i32, z: usize) -> Option<u8> {
+        self.section(y.div_euclid(16))?.block_light(x, y.rem_euclid(16) as usize, z)
+    }
+
+    pub fn sky_light(&self, x: usize, y: i32, z: usize) -> Option<u8> {
+        self.section(y.div_euclid(16))?.sky_light(x, y.rem_euclid(16) as usize, z)
+    }
+
+    // Highest non-air block in a column, x and z local to the chunk
+    pub fn top_block(&self, x: usize, z: usize) -> Option<(i32, &BlockType)> {
+        let mut sections: Vec<&Section> = self.sections.iter().collect();
+        sections.sort_by_key(|section| std::cmp::Reverse(section.y));
+
+        for section in sections {
+            if section.palette().iter().all(BlockType::is_air) {
+                continue;
+            }
+
+            for y in (0..16).rev() {
+                let block = section.block_at(x, y, z);
+                if !block.is_air() {
+                    return Some((section.y * 16 + y as i32, block));
+                }
+            }
+        }
+
+        None
+    }
+
+    // Heights by [z][x], each the y just above the column's highest matching
+    // block. None for chunks from before 1.13 and chunks that haven't been
+    // generated far enough to have the heightmap.
+    pub fn heightmap(&self, kind: HeightmapKind) -> Option<[[i32; 16]; 16]> {
+        let path = if self.data_version >= TOP_LEVEL_SECTIONS_DATA_VERSION {
+            format!("Heightmaps.{}", kind.nbt_name())
+        } else if self.data_version >= FLATTENING_DATA_VERSION {
+            format!("Level.Heightmaps.{}", kind.nbt_name())
+        } else {
+            return None;
+        };
+
+        let TagPayload::LongArray(data) = self.nbt.payload.get_path(&path).ok()? else {
+            return None;
+        };
+
+        // Wide enough for every height from 0 to the world height itself.
+        // Chunks without sections to tell the height by can be from a
+        // dimension of another height, for them the narrowest width that fits
+        // the array is the best guess, though 11 and 12 bits take as many longs.
+        let packing = Packing::for_data_version(self.data_version);
+        let bits = Some(self.height.heightmap_bits())
+            .filter(|&bits| packing.packed_len(256, bits) == data.len())
+            .or_else(|| (1..=32).find(|&bits| packing.packed_len(256, bits) == data.len()))?;
+        let values = match packing {
+            Packing::Padded => unpack_padded(data, bits, 256)?,
+            Packing::Straddled => unpack_straddled(data, bits, 256)?,
+        };
+
+        let mut heights = [[0; 16]; 16];
+        for (i, value) in values.into_iter().enumerate() {
+            heights[i >> 4][i & 15] = value as i32 + self.height.min_y;
+        }
+        Some(heights)
+    }
+
+    // See WorldHeight::of_chunk_nbt, the overworld's height for the chunk's
+    // version when it has no sections to tell by
+    pub fn world_height(&self) -> WorldHeight {
+        self.height
+    }
+
+    // The WORLD_SURFACE heightmap, or the same worked out from the blocks for
+    // chunks without it, which is only right if all sections were kept.
+    // i32::MIN for columns without any blocks.
+    pub fn surface_heights(&self) -> [[i32; 16]; 16] {
+        if let Some(heights) = self.heightmap(HeightmapKind::WorldSurface) {
+            return heights;
+        }
+        let mut heights = [[i32::MIN; 16]; 16];
+        for (z, row) in heights.iter_mut().enumerate() {
+            for (x, height) in row.iter_mut().enumerate() {
+                *height = self.top_block(x, z).map_or(i32::MIN, |(y, _)| y + 1);
+            }
+        }
+        heights
+    }
+
+    // Whether pos is below the highest block of its column, so not open to
+    // the sky. See surface_heights.
+    pub fn is_underground(&self, pos: BlockPos) -> bool {
+        is_below_surface(&self.surface_heights(), pos)
+    }
+
+    pub fn block_pos(&self, section: &Section, index: usize) -> BlockPos {
+        BlockPos {
+            x: self.x * 16 + (index & 15) as i32,
+            y: section.y * 16 + (index >> 8) as i32,
+            z: self.z * 16 + ((index >> 4) & 15) as i32,
+        }
+    }
+
+    pub fn find_blocks(&self, names: &[&str]) -> Vec<(BlockPos, &BlockType)> {
+        self.found(|section| section.find_blocks(names))
+    }
+
+    pub fn find_states(&self, states: &[BlockState]) -> Vec<(BlockPos, &BlockType)> {
+        self.found(|section| section.find_states(states))
+    }
+
+    // Palette entries are tested against the section's bounds first, so
+    // positions are only looked at where the query depends on them
+    pub fn find_query(&self, query: &Query) -> Vec<(BlockPos, &BlockType)> {
+        self.found(|section| {
+            let min = BlockPos::new(self.x * 16, section.y * 16, self.z * 16);
+            let max = min.offset(15, 15, 15);
+            let results: Vec<Option<bool>> = section.palette().iter().map(|block| query.test(block, min, max)).collect();
+            if results.iter().all(|result| *result == Some(false)) {
+                return Vec::new();
+            }
+
+            section.block_states.indices().into_iter()
+                .enumerate()
+                .filter(|&(i, palette_index)| results[palette_index].unwrap_or_else(|| {
+                    let pos = self.block_pos(section, i);
+                    query.matches(&section.palette()[palette_index], pos)
+                }))
+                .map(|(i, _)| i)
+                .collect()
+        })
+    }
+
+    fn found(&self, find: impl Fn(&Section) -> Vec<usize>) -> Vec<(BlockPos, &BlockType)> {
+        let mut found = Vec::new();
+
+        for section in &self.sections {
+            for i in find(section) {
+                found.push((self.block_pos(section, i), section.block_states.block_at(i & 15, i >> 8, (i >> 4) & 15)));
+            }
+        }
+
+        found
+    }
+
+    // Turns every block matching from inside the inclusive box between min and
+    // max into to, marking the chunk dirty like set_block does. Block entities
+    // of blocks that change name go. Returns how many blocks changed, fails for
+    // boxes reaching above or below the world.
+    pub fn replace_blocks(&mut self, from: &BlockState, to: &BlockState, min: BlockPos, max: BlockPos) -> Result<usize> {
+        if self.data_version < FLATTENING_DATA_VERSION {
+            bail!("Chunk ({}, {}) is from before 1.13, which can't be edited", self.x, self.z);
+        }
+        if !self.height.contains(min.y) || !self.height.contains(max.y) {
+            bail!("The box from y {} to {} reaches outside of the world, which goes from {}", min.y, max.y, self.height);
+        }
+
+        let (chunk_x, chunk_z) = (self.x * 16, self.z * 16);
+        let keep_data = self.data_version < TOP_LEVEL_SECTIONS_DATA_VERSION;
+        let mut replaced = 0;
+        let mut removed_block_entities = Vec::new();
+
+        for section in &mut self.sections {
+            let palette = &section.block_states.palette.entries;
+            let matching: Vec<bool> = palette.iter().map(|block| block.matches(from) && block.state() != *to).collect();
+            if !matching.contains(&true) || section.y * 16 > max.y || section.y * 16 + 15 < min.y {
+                continue;
+            }
+
+            let mut entries = palette.clone();
+            let to_index = match entries.iter().position(|block| block.state() == *to) {
+                Some(index) => index,
+                None => {
+                    entries.push(to.clone().into());
+                    entries.len() - 1
+                },
+            };
+
+            let mut indices = section.block_states.indices();
+            let mut changed = 0;
+            for (i, index) in indices.iter_mut().enumerate() {
+                let pos = BlockPos::new(chunk_x + (i & 15) as i32, section.y * 16 + (i >> 8) as i32, chunk_z + ((i >> 4) & 15) as i32);
+                let inside = pos.x >= min.x && pos.x <= max.x && pos.y >= min.y && pos.y <= max.y && pos.z >= min.z && pos.z <= max.z;
+                if inside && matching[*index] {
+                    if entries[*index].name != to.name {
+                        removed_block_entities.push(pos);
+                    }
+                    *index = to_index;
+                    changed += 1;
+                }
+            }
+
+            if changed > 0 {
+                section.block_states.set_blocks(entries, &indices, keep_data);
+                if !self.dirty_sections.contains(&section.y) {
+                    self.dirty_sections.push(section.y);
+                }
+                replaced += changed;
+            }
+        }
+
+        self.remove_block_entities_at(&removed_block_entities);
+        Ok(replaced)
+    }
+
+    // Puts a block state at a position, x and z local to the chunk and y the
+    // world height, failing for x or z past 15. Old chunks get a new section
+    // if there's none at that height yet. The block entity of a block that's
+    // replaced by a different one goes. The chunk is marked dirty, flush or
+    // into_nbt write the change into its NBT.
+    pub fn set_block(&mut self, x: usize, y: i32, z: usize, block: BlockState) -> Result<()> {
+        if self.data_version < FLATTENING_DATA_VERSION {
+            bail!("Chunk ({}, {}) is from before 1.13, which can't be edited", self.x, self.z);
+        }
+        if x >= 16 || z >= 16 {
+            bail!("Block ({x}, {z}) is outside of chunk ({}, {}), x and z go from 0 to 15", self.x, self.z);
+        }
+        if !self.height.contains(y) {
+            bail!("y {y} is outside of the world, which goes from {}", self.height);
+        }
+
+        let section_y = y.div_euclid(16);
+        let section = match self.sections.iter().position(|section| section.y == section_y) {
+            Some(i) => &mut self.sections[i],
+            // Since 1.18 every section of the world's height is stored, even empty ones
+            None if self.data_version >= TOP_LEVEL_SECTIONS_DATA_VERSION => bail!("y {y} is outside of chunk ({}, {})", self.x, self.z),
+            None => {
+                let air = BlockType { name: BlockId::intern("minecraft:air"), properties: Vec::new() };
+                let packing = Packing::for_data_version(self.data_version);
+                let data = vec![0; packing.packed_len(4096, 4)];
+                self.sections.push(Section { y: section_y, block_states: BlockStates { palette: Palette { entries: vec![air] }, data, packing }, biomes: None, block_light: None, sky_light: None });
+                self.sections.last_mut().unwrap()
+            },
+        };
+
+        let local_y = y.rem_euclid(16) as usize;
+        let changes_name = section.block_at(x, local_y, z).name != block.name;
+        if !section.block_states.set_block(x, local_y, z, block) {
+            return Ok(());
+        }
+        if !self.dirty_sections.contains(&section_y) {
+            self.dirty_sections.push(section_y);
+        }
+        if changes_name {
+            let pos = BlockPos::new(self.x * 16 + x as i32, y, self.z * 16 + z as i32);
+            self.remove_block_entities_at(&[pos]);
+        }
+        Ok(())
+    }
+
+    fn remove_block_entities_at(&mut self, positions: &[BlockPos]) {
+        let before = self.block_entities.len();
+        self.block_entities.retain(|block_entity| !positions.contains(&block_entity.pos));
+        self.block_entities_dirty |= self.block_entities.len() != before;
+    }
+
+    // Whether there are edits that haven't been flushed into the NBT yet
+    pub fn is_dirty(&self) -> bool {
+        !self.dirty_sections.is_empty() || self.block_entities_dirty
+    }
+
+    // Writes the changed sections and block entities into the chunk's NBT.
+    // The heightmaps are recomputed, see recompute_heightmaps, and the light
+    // marked as not worked out so the game computes it when it loads the
+    // chunk.
+    pub fn flush(&mut self) -> Result<()> {
+        if !self.is_dirty() {
+            return Ok(());
+        }
+
+        let blocks_changed = !self.dirty_sections.is_empty();
+        for y in std::mem::take(&mut self.dirty_sections) {
+            self.write_section_blocks(y)?;
+        }
+        if self.block_entities_dirty {
+            self.write_block_entities()?;
+            self.block_entities_dirty = false;
+        }
+        if blocks_changed {
+            self.invalidate_derived_data()?;
+        }
+        Ok(())
+    }
+
+    // Whether flush recomputes the heightmaps, which it does unless turned
+    // off. Without it they're left out and the game computes them itself,
+    // going by its own idea of which blocks block motion.
+    pub fn set_recompute_heightmaps(&mut self, recompute: bool) {
+        self.recompute_heightmaps = recompute;
+    }
+
+    // Works MOTION_BLOCKING and WORLD_SURFACE out from the blocks and puts
+    // them into the NBT, packed the way the game packs them. The other
+    // heightmaps are dropped, the game computes missing ones when it loads
+    // the chunk. Fails for chunks read without all their sections.
+    pub fn recompute_heightmaps(&mut self) -> Result<()> {
+        if self.data_version < FLATTENING_DATA_VERSION {
+            bail!("Chunk ({}, {}) is from before 1.13, which has no heightmaps", self.x, self.z);
+        }
+        let sections_name = if self.data_version >= TOP_LEVEL_SECTIONS_DATA_VERSION { "sections" } else { "Level.Sections" };
+        let block_data_name = if self.data_version >= TOP_LEVEL_SECTIONS_DATA_VERSION { "block_states" } else { "Palette" };
+        let stored = match self.nbt.payload.get_path(sections_name) {
+            Ok(TagPayload::List(sections)) => sections.iter().filter(|section| section.get(block_data_name).is_some()).count(),
+            _ => 0,
+        };
+        // Sections set_block added are only in the NBT once they're flushed
+        let added = self.dirty_sections.iter().filter(|y| !self.nbt_has_section(**y)).count();
+        if self.sections.len() != stored + added {
+            bail!("Chunk ({}, {}) was read without some of its sections", self.x, self.z);
+        }
+
+        let min_y = self.height.min_y;
+        let bits = self.height.heightmap_bits();
+
+        let mut sections: Vec<&Section> = self.sections.iter().collect();
+        sections.sort_by_key(|section| std::cmp::Reverse(section.y));
+        let mut motion_blocking = vec![0; 256];
+        let mut world_surface = vec![0; 256];
+        for section in sections {
+            let palette = section.palette();
+            if palette.iter().all(BlockType::is_air) {
+                continue;
+            }
+            let blocking: Vec<bool> = palette.iter().map(blocks_motion).collect();
+            let indices = section.block_states.indices();
+            for column in 0..256 {
+                for y in (0..16).rev() {
+                    if world_surface[column] != 0 && motion_blocking[column] != 0 {
+                        break;
+                    }
+                    let index = indices[y << 8 | column];
+                    let height = (section.y * 16 + y as i32 + 1 - min_y) as usize;
+                    if world_surface[column] == 0 && !palette[index].is_air() {
+                        world_surface[column] = height;
+                    }
+                    if motion_blocking[column] == 0 && blocking[index] {
+                        motion_blocking[column] = height;
+                    }
+                }
+            }
+        }
+
+        let pack = |values: &[usize]| TagPayload::LongArray(match Packing::for_data_version(self.data_version) {
+            Packing::Padded => pack_padded_indices(values, bits),
+            Packing::Straddled => pack_straddled_indices(values, bits),
+        });
+        let heightmaps = vec![
+            Tag { name: HeightmapKind::MotionBlocking.nbt_name().to_string(), payload: pack(&motion_blocking) },
+            Tag { name: HeightmapKind::WorldSurface.nbt_name().to_string(), payload: pack(&world_surface) },
+        ];
+        let TagPayload::Compound(level) = self.level_mut()? else {
+            bail!("Chunk level is not a compound");
+        };
+        set_tag(level, "Heightmaps", TagPayload::Compound(heightmaps.into()));
+        Ok(())
+    }
+
+    fn nbt_has_section(&self, y: i32) -> bool {
+        let path = if self.data_version >= TOP_LEVEL_SECTIONS_DATA_VERSION { "sections" } else { "Level.Sections" };
+        let Ok(TagPayload::List(sections)) = self.nbt.payload.get_path(path) else {
+            return false;
+        };
+        sections.iter().any(|section| matches!(section.get("Y"), Some(TagPayload::Byte(section_y)) if *section_y as i32 == y)
+            || matches!(section.get("Y"), Some(TagPayload::Int(section_y)) if *section_y == y))
+    }
+
+    // The compound holding the sections, the root since 1.18 and Level before
+    fn level_mut(&mut self) -> Result<&mut TagPayload> {
+        if self.data_version >= TOP_LEVEL_SECTIONS_DATA_VERSION {
+            Ok(&mut self.nbt.payload)
+        } else {
+            self.nbt.payload.get_mut("Level").context("Missing Level tag")
+        }
+    }
+
+    // Puts a section's palette and block data back into the chunk's NBT
+    fn write_section_blocks(&mut self, y: i32) -> Result<()> {
+        let modern = self.data_version >= TOP_LEVEL_SECTIONS_DATA_VERSION;
+        let section = self.sections.iter().find(|section| section.y == y).context("No such section")?;
+        let palette = TagPayload::List(section.block_states.palette.iter().map(BlockType::to_nbt).collect());
+        let data = section.block_states.data.clone();
+
+        let sections_name = if modern { "sections" } else { "Sections" };
+        let Some(TagPayload::List(sections)) = self.level_mut()?.get_mut(sections_name) else {
+            bail!("Missing {sections_name} tag");
+        };
+        let position = sections.iter().position(|section| matches!(section.get("Y"), Some(TagPayload::Byte(section_y)) if *section_y as i32 == y)
+            || matches!(section.get("Y"), Some(TagPayload::Int(section_y)) if *section_y == y));
+        let section_nbt = match position {
+            Some(i) => &mut sections[i],
+            // A section set_block added, which has no light data
+            None if !modern => {
+                sections.push(TagPayload::Compound(vec![Tag { name: "Y".to_string(), payload: TagPayload::Byte(y as i8) }].into()));
+                sections.last_mut().unwrap()
+            },
+            None => bail!("Section {y} is missing from the NBT"),
+        };
+        let TagPayload::Compound(section_tags) = section_nbt else {
+            bail!("Section {y} is not a compound");
+        };
+
+        let (block_tags, palette_name, data_name) = if modern {
+            let Some(TagPayload::Compound(block_states)) = section_tags.get_mut("block_states") else {
+                bail!("Section {y} has no block_states compound");
+            };
+            (block_states, "palette", "data")
+        } else {
+            (section_tags, "Palette", "BlockStates")
+        };
+
+        set_tag(block_tags, palette_name, palette);
+        if data.is_empty() {
+            block_tags.retain(|tag| tag.name != data_name);
+        } else {
+            set_tag(block_tags, data_name, TagPayload::LongArray(data));
+        }
+        Ok(())
+    }
+
+    fn write_block_entities(&mut self) -> Result<()> {
+        let name = if self.data_version >= TOP_LEVEL_SECTIONS_DATA_VERSION { "block_entities" } else { "TileEntities" };
+        let list = TagPayload::List(self.block_entities.iter().map(|block_entity| block_entity.nbt.clone()).collect());
+        let TagPayload::Compound(level) = self.level_mut()? else {
+            bail!("Chunk level is not a compound");
+        };
+        set_tag(level, name, list);
+        Ok(())
+    }
+
+    // Recomputes the heightmaps, or leaves them out if that's turned off, and
+    // marks the light as not worked out, which makes the game compute it from
+    // the blocks when it loads the chunk
+    fn invalidate_derived_data(&mut self) -> Result<()> {
+        if self.recompute_heightmaps {
+            self.recompute_heightmaps()?;
+        }
+        let recomputed = self.recompute_heightmaps;
+        let TagPayload::Compound(level) = self.level_mut()? else {
+            bail!("Chunk level is not a compound");
+        };
+        if !recomputed {
+            level.retain(|tag| tag.name != "Heightmaps");
+        }
+        if let Some(payload) = level.get_mut("isLightOn") {
+            *payload = TagPayload::Byte(0);
+        }
+        Ok(())
+    }
+
+    // The NBT with every edit flushed into it
+    pub fn into_nbt(mut self) -> Result<Tag> {
+        self.flush()?;
+        Ok(self.nbt)
+    }
+}
+
+// Replaces the tag of that name in a compound, or adds it
+fn set_tag(compound: &mut Compound, name: &str, payload: TagPayload) {
+    match compound.get_mut(name) {
+        Some(existing) => *existing = payload,
+        None => compound.push(Tag { name: name.to_string(), payload }),
+    }
+}
+
+// None for sections that only hold light data, like the ones just below and
+// above the world that 1.18 and later save, and for sections with nothing
+// keep wants in their palette
+fn parse_section(section: &TagPayload, data_version: i32, keep: &impl Fn(&BlockType) -> bool) -> Result<Option<Section>> {
+    let section = as_compound(section, "section")?;
+
+    let y = match field(section, "Y")? {
+        TagPayload::Byte(y) => *y as i32,
+        TagPayload::Int(y) => *y,
+        _ => bail!("Section Y has the wrong type"),
+    };
+
+    if data_version < FLATTENING_DATA_VERSION {
+        let section = parse_legacy_section(section, y)?;
+        return Ok(section.filter(|section| section.palette().iter().any(keep)));
+    }
+
+    let (palette, data) = if data_version >= TOP_LEVEL_SECTIONS_DATA_VERSION {
+        let Some(block_states) = section.get("block_states") else {
+            return Ok(None);
+        };
+        let block_states = as_compound(block_states, "block_states")?;
+        (field(block_states, "palette")?, block_states.get("data"))
+    } else {
+        match section.get("Palette") {
+            Some(palette) => (palette, section.get("BlockStates")),
+            None => return Ok(None),
+        }
+    };
+
+    let mut entries = Vec::new();
+    for entry in as_list(palette, "palette")? {
+        let entry = as_compound(entry, "palette entry")?;
+        let name = match field(entry, "Name")? {
+            TagPayload::String(name) => BlockId::intern(name),
+            _ => bail!("Palette entry Name is not a string"),
+        };
+        let mut properties = Vec::new();
+        if let Some(payload) = entry.get("Properties") {
+            for property in as_compound(payload, "Properties")? {
+                match &property.payload {
+                    TagPayload::String(value) => properties.push((property.name.clone(), value.clone())),
+                    _ => bail!("Block property {} is not a string", property.name),
+                }
+            }
+        }
+        entries.push(BlockType { name, properties });
+    }
+
+    if entries.is_empty() {
+        bail!("Section {y} has an empty palette");
+    }
+    if !entries.iter().any(keep) {
+        return Ok(None);
+    }
+
+    let data = match data {
+        Some(TagPayload::LongArray(data)) => data.clone(),
+        Some(_) => bail!("Block state data is not a long array"),
+        None => Vec::new(),
+    };
+
+    let packing = Packing::for_data_version(data_version);
+    // Before 1.18 single-entry palettes still come with a data array, which is all zeroes
+    if entries.len() > 1 || !data.is_empty() {
+        let bits = block_bits_for_palette(entries.len());
+        let indices = match packing.unpack(&data, bits) {
+            Some(indices) => indices,
+            None => bail!("Section {y} block data is too short for {bits} bits per block"),
+        };
+        if indices.iter().any(|i| *i >= entries.len()) {
+            bail!("Section {y} block data refers past the end of its palette");
+        }
+    }
+
+    let biomes = match section.get("biomes") {
+        Some(biomes) => Some(parse_biomes(as_compound(biomes, "biomes")?).with_context(|| format!("Invalid biomes in section {y}"))?),
+        None => None,
+    };
+
+    Ok(Some(Section {
+        y,
+        block_states: BlockStates { palette: Palette { entries }, data, packing },
+        biomes,
+        block_light: parse_light(section, "BlockLight", y)?,
+        sky_light: parse_light(section, "SkyLight", y)?,
+    }))
+}
+
+// Like Chunk::is_underground with the chunk's surface_heights worked out
+// already. Columns without any blocks have nothing underground.
+pub fn is_below_surface(heights: &[[i32; 16]; 16], pos: BlockPos) -> bool {
+    heights[pos.z.rem_euclid(16) as usize][pos.x.rem_euclid(16) as usize].checked_sub(1).is_some_and(|top| pos.y < top)
+}
+
+fn parse_biomes(biomes: &Compound) -> Result<Biomes> {
+    let mut palette = Vec::new();
+    for entry in as_list(field(biomes, "palette")?, "biome palette")? {
+        match entry {
+            TagPayload::String(name) => palette.push(name.clone()),
+            _ => bail!("Biome palette entry is not a string"),
+        }
+    }
+
+    if palette.is_empty() {
+        bail!("Empty biome palette");
+    }
+
+    // A single biome takes 0 bits, any data that comes with it is ignored like
+    // the game does
+    let data = match biomes.get("data") {
+        _ if palette.len() == 1 => Vec::new(),
+        Some(TagPayload::LongArray(data)) => data.clone(),
+        Some(_) => bail!("Biome data is not a long array"),
+        None => Vec::new(),
+    };
+
+    let biomes = Biomes { palette, data };
+    if biomes.palette.len() > 1 {
+        let bits = biomes.bits();
+        match unpack_padded(&biomes.data, bits, 64) {
+            Some(indices) if indices.iter().all(|i| *i < biomes.palette.len()) => {},
+            Some(_) => bail!("Biome data refers past the end of its palette"),
+            None => bail!("Biome data is too short for {bits} bits per cell"),
+        }
+    }
+
+    Ok(biomes)
+}
+
+// Blocks holds the low 8 bits of each id, Add the optional high 4 bits and Data
+// the 4 bit data value. The nibble arrays put even indices in the low half.
+fn parse_legacy_section(section: &Compound, y: i32) -> Result<Option<Section>> {
+    let byte_array = |name: &str, len: usize| -> Result<Option<&Vec<i8>>> {
+        match section.get(name) {
+            Some(TagPayload::ByteArray(array)) if array.len() == len => Ok(Some(array)),
+            Some(_) => bail!("Section {y} {name} is not a byte array of length {len}"),
+            None => Ok(None),
+        }
+    };
+    let nibble = |array: &[i8], i: usize| (array[i >> 1] as u8 >> ((i & 1) * 4)) & 15;
+
+    let Some(blocks) = byte_array("Blocks", 4096)? else {
+        return Ok(None);
+    };
+    let data = byte_array("Data", 2048)?;
+    let add = byte_array("Add", 2048)?;
+
+    let mut entries: Vec<BlockType> = Vec::new();
+    let mut by_name: HashMap<BlockId, usize> = HashMap::new();
+    let mut by_id: HashMap<(u16, u8), usize> = HashMap::new();
+    let mut indices = Vec::with_capacity(4096);
+
+    for (i, block) in blocks.iter().enumerate() {
+        let mut id = *block as u8 as u16;
+        if let Some(add) = add {
+            id |= (nibble(add, i) as u16) << 8;
+        }
+        let meta = data.map_or(0, |data| nibble(data, i));
+
+        // Several data values can map to the same name, so the palette is keyed on the name
+        let index = *by_id.entry((id, meta)).or_insert_with(|| {
+            let name = BlockId::intern(&legacy_block_name(id, meta));
+            *by_name.entry(name).or_insert_with(|| {
+                entries.push(BlockType { name, properties: Vec::new() });
+                entries.len() - 1
+            })
+        });
+        indices.push(index);
+    }
+
+    let data = if entries.len() > 1 {
+        pack_padded_indices(&indices, block_bits_for_palette(entries.len()))
+    } else {
+        Vec::new()
+    };
+
+    Ok(Some(Section {
+        y,
+        block_states: BlockStates { palette: Palette { entries }, data, packing: Packing::Padded },
+        biomes: None,
+        block_light: parse_light(section, "BlockLight", y)?,
+        sky_light: parse_light(section, "SkyLight", y)?,
+    }))
+}
+
+fn parse_light(section: &Compound, name: &str, y: i32) -> Result<Option<LightArray>> {
+    match section.get(name) {
+        Some(TagPayload::ByteArray(array)) if array.len() == 2048 => Ok(Some(LightArray(array.iter().map(|&b| b as u8).collect()))),
+        Some(_) => bail!("Section {y} {name} is not a byte array of length 2048"),
+        None => Ok(None),
+    }
+}
+
+fn field<'a>(compound: &'a Compound, name: &str) -> Result<&'a TagPayload> {
+    match compound.get(name) {
+        Some(payload) => Ok(payload),
+        None => bail!("Missing {name} tag"),
+    }
+}
+
+fn as_int(payload: &TagPayload, what: &str) -> Result<i32> {
+    match payload {
+        TagPayload::Int(i) => Ok(*i),
+        _ => bail!("{what} is not an int"),
+    }
+}
+
+fn as_list<'a>(payload: &'a TagPayload, what: &str) -> Result<&'a [TagPayload]> {
+    match payload {
+        TagPayload::List(list) => Ok(list),
+        _ => bail!("{what} is not a list"),
+    }
+}
+
+fn as_compound<'a>(payload: &'a TagPayload, what: &str) -> Result<&'a Compound> {
+    match payload {
+        TagPayload::Compound(compound) => Ok(compound),
+        _ => bail!("{what} is not a compound"),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Sets each bit on its own, so the packing doesn't lean on the code under test
+    fn hand_pack_straddled(values: &[usize], bits: usize) -> Vec<i64> {
+        let mut data = vec![0u64; (values.len() * bits).div_ceil(64)];
+        for (i, value) in values.iter().enumerate() {
+            for b in 0..bits {
+                if value >> b & 1 == 1 {
+                    let bit = i * bits + b;
+                    data[bit / 64] |= 1 << (bit % 64);
+                }
+            }
+        }
+        data.into_iter().map(|long| long as i64).collect()
+    }
+
+    fn straddled_values() -> Vec<usize> {
+        (0..4096).map(|i| (i * 7 + i / 64) % 32).collect()
+    }
+
+    fn tag(name: &str, payload: TagPayload) -> Tag {
+        Tag { name: name.to_string(), payload }
+    }
+
+    #[test]
+    fn unpacks_5_bit_entries_across_long_boundaries() {
+        let values = straddled_values();
+        let data = hand_pack_straddled(&values, 5);
+        assert_eq!(data.len(), 320);
+
+        let indices = unpack_straddled_indices(&data, 5).unwrap();
+        for (i, (index, value)) in indices.iter().zip(&values).enumerate() {
+            assert_eq!(index, value, "index {i}");
+        }
+        assert_eq!(pack_straddled_indices(&values, 5), data);
+        assert!(unpack_straddled_indices(&data[..319], 5).is_none());
+    }
+
+    #[test]
+    fn reads_straddled_sections_of_1_15_chunks() {
+        let values = straddled_values();
+        let palette = (0..32)
+            .map(|i| TagPayload::Compound(vec![tag("Name", TagPayload::String(format!("minecraft:block_{i}")))].into()))
+            .collect();
+        let section = vec![
+            tag("Y", TagPayload::Byte(2)),
+            tag("Palette", TagPayload::List(palette)),
+            tag("BlockStates", TagPayload::LongArray(hand_pack_straddled(&values, 5))),
+        ];
+        let level = vec![
+            tag("xPos", TagPayload::Int(-3)),
+            tag("zPos", TagPayload::Int(4)),
+            tag("Sections", TagPayload::List(vec![TagPayload::Compound(section.into())])),
+        ];
+        // 1.15.2
+        let root = vec![tag("DataVersion", TagPayload::Int(2230)), tag("Level", TagPayload::Compound(level.into()))];
+        let chunk = Chunk::from_nbt(tag("", TagPayload::Compound(root.into()))).unwrap();
+
+        let states = chunk.section(2).unwrap().block_states();
+        assert_eq!(states.packing(), Packing::Straddled);
+        assert_eq!(states.bits_per_block(), 5);
+        assert_eq!(states.indices(), values);
+        for (i, value) in values.iter().enumerate() {
+            let block = chunk.block_at(i & 15, 32 + (i >> 8) as i32, (i >> 4) & 15).unwrap();
+            assert_eq!(block.name.as_str(), format!("minecraft:block_{value}"));
+        }
+    }
+
+    // A 1.20.4 chunk of the given sections, each all air
+    fn modern_chunk(min_section: i32, sections: i32) -> Chunk {
+        let air = TagPayload::Compound(vec![tag("Name", TagPayload::String("minecraft:air".to_string()))].into());
+        let sections = (min_section..min_section + sections)
+            .map(|y| {
+                let block_states = vec![tag("palette", TagPayload::List(vec![air.clone()]))];
+                TagPayload::Compound(vec![tag("Y", TagPayload::Byte(y as i8)), tag("block_states", TagPayload::Compound(block_states.into()))].into())
+            })
+            .collect();
+        let root = vec![
+            tag("DataVersion", TagPayload::Int(3700)),
+            tag("xPos", TagPayload::Int(2)),
+            tag("zPos", TagPayload::Int(-1)),
+            tag("yPos", TagPayload::Int(min_section)),
+            tag("sections", TagPayload::List(sections)),
+        ];
+        Chunk::from_nbt(tag("", TagPayload::Compound(root.into()))).unwrap()
+    }
+
+    #[test]
+    fn skips_the_light_only_sections_around_the_world() {
+        let light = |y: i8| TagPayload::Compound(vec![tag("Y", TagPayload::Byte(y)), tag("SkyLight", TagPayload::ByteArray(vec![-1; 2048]))].into());
+        let mut nbt = modern_chunk(-4, 24).into_nbt().unwrap();
+        let Some(TagPayload::List(sections)) = nbt.payload.get_mut("sections") else { panic!("no sections") };
+        sections.insert(0, light(-5));
+        sections.push(light(20));
+
+        let chunk = Chunk::from_nbt(nbt).unwrap();
+        assert_eq!(chunk.sections().len(), 24);
+        assert!(chunk.section(-5).is_none());
+        assert!(chunk.section(20).is_none());
+        assert_eq!(chunk.world_height(), WorldHeight::OVERWORLD);
+        assert!(chunk.block_at(0, -64, 0).unwrap().is_air());
+    }
+
+    #[test]
+    fn set_block_grows_the_palette_and_repacks() {
+        let mut chunk = modern_chunk(-4, 24);
+        for i in 0..17 {
+            chunk.set_block(i % 16, -60 + i as i32 / 16, 3, BlockState::new(format!("minecraft:block_{i}"))).unwrap();
+        }
+        assert!(chunk.is_dirty());
+
+        let states = chunk.section(-4).unwrap().block_states();
+        assert_eq!(states.palette().len(), 18);
+        assert_eq!(states.bits_per_block(), 5);
+        assert_eq!(states.data().len(), 4096usize.div_ceil(64 / 5));
+        for i in 0..17 {
+            assert_eq!(chunk.block_at(i % 16, -60 + i as i32 / 16, 3).unwrap().name.as_str(), format!("minecraft:block_{i}"));
+        }
+        assert!(chunk.block_at(0, -60, 4).unwrap().is_air());
+
+        let nbt = chunk.into_nbt().unwrap();
+        let chunk = Chunk::from_nbt(nbt).unwrap();
+        assert_eq!(chunk.block_at(0, -59, 3).unwrap().name.as_str(), "minecraft:block_16");
+    }
+
+    #[test]
+    fn set_block_refuses_positions_outside_the_chunk() {
+        let mut chunk = modern_chunk(-4, 24);
+        assert!(chunk.set_block(16, 0, 0, BlockState::new("minecraft:stone")).is_err());
+        assert!(chunk.set_block(0, 0, 16, BlockState::new("minecraft:stone")).is_err());
+        assert!(chunk.set_block(0, 320, 0, BlockState::new("minecraft:stone")).is_err());
+        assert!(!chunk.is_dirty());
+    }
+
+    fn with_heightmap(mut chunk: Chunk, kind: HeightmapKind, data: Vec<i64>) -> Chunk {
+        let heightmaps = vec![tag(kind.nbt_name(), TagPayload::LongArray(data))];
+        let TagPayload::Compound(root) = &mut chunk.nbt.payload else { unreachable!() };
+        set_tag(root, "Heightmaps", TagPayload::Compound(heightmaps.into()));
+        chunk
+    }
+
+    #[test]
+    fn heightmap_width_follows_the_world_height() {
+        // 384 blocks high, heights up to 384 take 9 bits
+        let values: Vec<usize> = (0..256).map(|i| i + 100).collect();
+        let chunk = with_heightmap(modern_chunk(-4, 24), HeightmapKind::WorldSurface, pack_padded_indices(&values, 9));
+        let heights = chunk.heightmap(HeightmapKind::WorldSurface).unwrap();
+        assert_eq!(heights[0][0], 100 - 64);
+        assert_eq!(heights[15][15], 355 - 64);
+
+        // 2048 blocks high takes 12 bits, which needs as many longs as 11 would
+        let values: Vec<usize> = (0..256).map(|i| i * 8).collect();
+        let data = pack_padded_indices(&values, 12);
+        assert_eq!(data.len(), pack_padded_indices(&values, 11).len());
+        let chunk = with_heightmap(modern_chunk(-64, 128), HeightmapKind::WorldSurface, data);
+        let heights = chunk.heightmap(HeightmapKind::WorldSurface).unwrap();
+        for (i, value) in values.iter().enumerate() {
+            assert_eq!(heights[i >> 4][i & 15], *value as i32 - 1024);
+        }
+        assert!(chunk.heightmap(HeightmapKind::MotionBlocking).is_none());
+    }
+
+    #[test]
+    fn keeps_1_17_and_1_18_chunks_to_their_heights() {
+        // A 1.17.1 chunk with stone from y 0 to 15, 256 blocks high
+        let stone = TagPayload::Compound(vec![tag("Name", TagPayload::String("minecraft:stone".to_string()))].into());
+        let section = vec![tag("Y", TagPayload::Byte(0)), tag("Palette", TagPayload::List(vec![stone])), tag("BlockStates", TagPayload::LongArray(vec![0; 256]))];
+        let heightmaps = vec![tag("WORLD_SURFACE", TagPayload::LongArray(pack_padded_indices(&[16; 256], 9)))];
+        let level = vec![
+            tag("xPos", TagPayload::Int(0)),
+            tag("zPos", TagPayload::Int(0)),
+            tag("Sections", TagPayload::List(vec![TagPayload::Compound(section.into())])),
+            tag("Heightmaps", TagPayload::Compound(heightmaps.into())),
+        ];
+        let root = vec![tag("DataVersion", TagPayload::Int(2730)), tag("Level", TagPayload::Compound(level.into()))];
+        let mut legacy = Chunk::from_nbt(tag("", TagPayload::Compound(root.into()))).unwrap();
+        let mut modern = with_heightmap(modern_chunk(-4, 24), HeightmapKind::WorldSurface, pack_padded_indices(&[80; 256], 9));
+
+        assert_eq!(legacy.world_height(), WorldHeight::LEGACY);
+        assert_eq!(modern.world_height(), WorldHeight::OVERWORLD);
+        assert_eq!(legacy.heightmap(HeightmapKind::WorldSurface).unwrap()[0][0], 16);
+        assert_eq!(modern.heightmap(HeightmapKind::WorldSurface).unwrap()[0][0], 16);
+
+        assert_eq!(legacy.block_at(0, 0, 0).unwrap().name.as_str(), "minecraft:stone");
+        assert!(legacy.block_at(0, 100, 0).is_none());
+        for y in [-1, 256] {
+            assert!(legacy.block_at(0, y, 0).unwrap().is_outside_world());
+        }
+        for y in [-65, 320] {
+            assert!(modern.block_at(0, y, 0).unwrap().is_outside_world());
+        }
+        assert!(!modern.block_at(0, -64, 0).unwrap().is_outside_world());
+
+        assert!(legacy.set_block(0, -1, 0, BlockState::new("minecraft:stone")).is_err());
+        assert!(legacy.set_block(0, 256, 0, BlockState::new("minecraft:stone")).is_err());
+        legacy.set_block(0, 255, 0, BlockState::new("minecraft:stone")).unwrap();
+        modern.set_block(0, -1, 0, BlockState::new("minecraft:stone")).unwrap();
+
+        // The modern chunk is chunk (2, -1)
+        let (stone, dirt) = (BlockState::new("minecraft:stone"), BlockState::new("minecraft:dirt"));
+        assert!(legacy.replace_blocks(&stone, &dirt, BlockPos::new(0, -10, 0), BlockPos::new(15, 10, 15)).is_err());
+        assert_eq!(modern.replace_blocks(&stone, &dirt, BlockPos::new(32, -10, -16), BlockPos::new(47, 10, -1)).unwrap(), 1);
+    }
+
+    fn packed_heightmap(chunk: &Chunk, kind: HeightmapKind) -> Vec<i64> {
+        match chunk.nbt().payload.get_path(&format!("Heightmaps.{}", kind.nbt_name())) {
+            Ok(TagPayload::LongArray(data)) => data.clone(),
+            _ => panic!("No {} heightmap", kind.nbt_name()),
+        }
+    }
+
+    #[test]
+    fn recomputes_heightmaps_after_digging() {
+        let mut chunk = modern_chunk(-4, 24);
+        for y in 60..=70 {
+            chunk.set_block(3, y, 5, BlockState::new("minecraft:stone")).unwrap();
+        }
+        chunk.set_block(3, 71, 5, BlockState::new("minecraft:torch")).unwrap();
+        chunk.flush().unwrap();
+
+        let column = 5 * 16 + 3;
+        let motion_blocking = unpack_padded(&packed_heightmap(&chunk, HeightmapKind::MotionBlocking), 9, 256).unwrap();
+        let world_surface = unpack_padded(&packed_heightmap(&chunk, HeightmapKind::WorldSurface), 9, 256).unwrap();
+        // Heights count from the bottom of the world at y -64
+        assert_eq!(motion_blocking[column], 71 + 64);
+        assert_eq!(world_surface[column], 72 + 64);
+        assert_eq!(motion_blocking[column + 1], 0);
+
+        chunk.set_block(3, 71, 5, BlockState::new("minecraft:air")).unwrap();
+        chunk.set_block(3, 70, 5, BlockState::new("minecraft:air")).unwrap();
+        chunk.recompute_heightmaps().unwrap();
+
+        let dug = unpack_padded(&packed_heightmap(&chunk, HeightmapKind::MotionBlocking), 9, 256).unwrap();
+        assert_eq!(motion_blocking[column] - dug[column], 1);
+        let dug = unpack_padded(&packed_heightmap(&chunk, HeightmapKind::WorldSurface), 9, 256).unwrap();
+        assert_eq!(world_surface[column] - dug[column], 2);
+
+        let chunk = Chunk::from_nbt(chunk.into_nbt().unwrap()).unwrap();
+        assert_eq!(chunk.heightmap(HeightmapKind::MotionBlocking).unwrap()[5][3], 70);
+        assert_eq!(chunk.heightmap(HeightmapKind::WorldSurface).unwrap()[5][3], 70);
+        assert_eq!(chunk.heightmap(HeightmapKind::WorldSurface).unwrap()[0][0], -64);
+    }
+
+    #[test]
+    fn leaves_heightmaps_to_the_game_when_asked() {
+        let mut chunk = modern_chunk(-4, 24);
+        chunk.set_recompute_heightmaps(false);
+        chunk.set_block(0, 0, 0, BlockState::new("minecraft:stone")).unwrap();
+        let chunk = Chunk::from_nbt(chunk.into_nbt().unwrap()).unwrap();
+        assert!(chunk.heightmap(HeightmapKind::WorldSurface).is_none());
+    }
+
+    #[test]
+    fn refuses_to_recompute_without_every_section() {
+        let mut chunk = modern_chunk(-4, 24);
+        chunk.set_block(0, 0, 0, BlockState::new("minecraft:stone")).unwrap();
+        let mut filtered = Chunk::from_nbt_where(chunk.into_nbt().unwrap(), |block| !block.is_air()).unwrap();
+        assert_eq!(filtered.sections().len(), 1);
+        assert!(filtered.recompute_heightmaps().is_err());
+    }
+}
@@ -0,0 +1,155 @@
+use anyhow::{ Result, Context, anyhow, bail };
+use std::{ fs, path::Path, str::FromStr };
+
+use crate::world::Dimension;
+
+// A chunk rectangle, in chunk coordinates
+#[derive(Clone, Debug, PartialEq, Eq)]
+struct Claim {
+    min: (i32, i32),
+    max: (i32, i32),
+    // None for every dimension
+    dimension: Option<Dimension>,
+}
+
+// Chunks picked out by a claims file, one rule per line:
+//
+//     # Blank lines and lines starting with # are skipped
+//     chunk <x> <z> [dimension]
+//     box <x1> <z1> <x2> <z2> [dimension]
+//
+// chunk takes chunk coordinates, box block coordinates and covers every chunk
+// with a block in it. Rules without a dimension hold in all of them.
+#[derive(Clone, Debug, Default)]
+pub struct Claims {
+    claims: Vec<Claim>,
+}
+
+impl Claims {
+    pub fn load(path: impl AsRef<Path>) -> Result<Claims> {
+        let path = path.as_ref();
+        let text = fs::read_to_string(path).with_context(|| format!("Could not read {}", path.display()))?;
+        text.parse().with_context(|| format!("Invalid claims file {}", path.display()))
+    }
+
+    // Region files read on their own have no dimension, every rule holds for them
+    pub fn contains(&self, dimension: Option<Dimension>, x: i32, z: i32) -> bool {
+        self.claims.iter().any(|claim| {
+            (claim.min.0..=claim.max.0).contains(&x) && (claim.min.1..=claim.max.1).contains(&z)
+                && (claim.dimension.is_none() || dimension.is_none() || claim.dimension == dimension)
+        })
+    }
+}
+
+impl FromStr for Claims {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Claims> {
+        let mut claims = Vec::new();
+        for (i, line) in s.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            claims.push(parse_claim(line).with_context(|| format!("Line {}", i + 1))?);
+        }
+        Ok(Claims { claims })
+    }
+}
+
+fn parse_claim(line: &str) -> Result<Claim> {
+    let words: Vec<&str> = line.split_whitespace().collect();
+    let coords = |words: &[&str]| -> Result<Vec<i32>> {
+        words.iter().map(|word| word.parse().map_err(|_| anyhow!("Invalid coordinate \"{word}\""))).collect()
+    };
+    let dimension = |word: Option<&&str>| word.map(|word| word.parse()).transpose();
+
+    match words.as_slice() {
+        ["chunk", rest @ ..] if rest.len() == 2 || rest.len() == 3 => {
+            let c = coords(&rest[..2])?;
+            Ok(Claim { min: (c[0], c[1]), max: (c[0], c[1]), dimension: dimension(rest.get(2))? })
+        },
+        ["box", rest @ ..] if rest.len() == 4 || rest.len() == 5 => {
+            let c = coords(&rest[..4])?;
+            Ok(Claim {
+                min: (c[0].min(c[2]).div_euclid(16), c[1].min(c[3]).div_euclid(16)),
+                max: (c[0].max(c[2]).div_euclid(16), c[1].max(c[3]).div_euclid(16)),
+                dimension: dimension(rest.get(4))?,
+            })
+        },
+        _ => bail!("Expected \"chunk x z [dimension]\" or \"box x1 z1 x2 z2 [dimension]\", got \"{line}\""),
+    }
+}
+
+// Which chunks scans and edits may touch. With include claims only the chunks
+// in them may be, and chunks in the exclude claims never are, exclude wins
+// where both have a chunk.
+#[derive(Clone, Debug, Default)]
+pub struct ChunkPolicy {
+    include: Option<Claims>,
+    exclude: Option<Claims>,
+}
+
+impl ChunkPolicy {
+    pub fn new() -> ChunkPolicy {
+        ChunkPolicy::default()
+    }
+
+    pub fn include(mut self, claims: Claims) -> Self {
+        self.include = Some(claims);
+        self
+    }
+
+    pub fn exclude(mut self, claims: Claims) -> Self {
+        self.exclude = Some(claims);
+        self
+    }
+
+    // Whether it lets every chunk through
+    pub fn is_empty(&self) -> bool {
+        self.include.is_none() && self.exclude.is_none()
+    }
+
+    pub fn allows(&self, dimension: Option<Dimension>, x: i32, z: i32) -> bool {
+        self.include.as_ref().is_none_or(|include| include.contains(dimension, x, z))
+            && !self.exclude.as_ref().is_some_and(|exclude| exclude.contains(dimension, x, z))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_chunks_and_boxes() {
+        let claims: Claims = "# spawn\nchunk 3 -4\n\n  box -20 0 15 31 nether\n".parse().unwrap();
+        assert!(claims.contains(Some(Dimension::Overworld), 3, -4));
+        assert!(!claims.contains(Some(Dimension::Overworld), 4, -4));
+        // Blocks -20 to 15 are chunks -2 to 0
+        assert!(claims.contains(Some(Dimension::Nether), -2, 1));
+        assert!(claims.contains(Some(Dimension::Nether), 0, 0));
+        assert!(!claims.contains(Some(Dimension::Nether), 1, 0));
+        assert!(!claims.contains(Some(Dimension::Overworld), -2, 1));
+        assert!(claims.contains(None, -2, 1));
+
+        assert!("chunk 1".parse::<Claims>().is_err());
+        assert!("box 1 2 3 x".parse::<Claims>().is_err());
+        assert!("chunk 1 2 moon".parse::<Claims>().is_err());
+        assert!("circle 0 0 5".parse::<Claims>().is_err());
+    }
+
+    #[test]
+    fn exclude_wins_over_include() {
+        let policy = ChunkPolicy::new()
+            .include("box 0 0 63 63".parse().unwrap())
+            .exclude("chunk 1 1\nchunk 9 9".parse().unwrap());
+        assert!(policy.allows(None, 0, 0));
+        assert!(policy.allows(None, 3, 3));
+        assert!(!policy.allows(None, 1, 1));
+        assert!(!policy.allows(None, 4, 0));
+        assert!(!policy.allows(None, 9, 9));
+
+        assert!(ChunkPolicy::new().is_empty());
+        assert!(ChunkPolicy::new().allows(Some(Dimension::End), 100, -100));
+    }
+}
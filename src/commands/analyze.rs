@@ -0,0 +1,110 @@
+use anyhow::Result;
+use clap::{ Args, ValueEnum };
+use std::{ collections::BTreeMap, path::PathBuf };
+use path_miner::{ Dimension, ParseOptions };
+
+#[derive(Args)]
+pub struct AnalyzeArgs {
+    /// World folder or region file (.mca)
+    path: PathBuf,
+    /// Only count blocks in this dimension of a world
+    #[arg(long)]
+    dimension: Option<Dimension>,
+    /// Count these blocks instead of every ore and ancient debris
+    #[arg(long)]
+    block: Vec<String>,
+    /// Count deepslate ores apart from their stone counterparts
+    #[arg(long)]
+    split_deepslate: bool,
+    #[arg(long, value_enum, default_value = "table")]
+    format: Format,
+    #[command(flatten)]
+    scan: super::ScanArgs,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, ValueEnum)]
+enum Format {
+    /// Columns aligned for reading
+    Table,
+    /// Comma separated, with a header row
+    Csv,
+}
+
+pub fn run(args: AnalyzeArgs) -> Result<()> {
+    let is_counted = |name: &str| {
+        if args.block.is_empty() {
+            name.ends_with("_ore") || name == "minecraft:ancient_debris"
+        } else {
+            args.block.iter().any(|block| block == name)
+        }
+    };
+
+    // Counts by block, then by y
+    let mut counts: BTreeMap<String, BTreeMap<i32, u64>> = BTreeMap::new();
+
+    super::for_each_chunk_with(&args.path, &args.scan, ParseOptions::for_block_search(), |dimension, chunk| {
+        if args.dimension.is_some() && dimension != args.dimension {
+            return;
+        }
+
+        for section in chunk.sections() {
+            let columns: Vec<Option<String>> = section.palette().iter()
+                .map(|block| is_counted(&block.name).then(|| column_name(&block.name, args.split_deepslate)))
+                .collect();
+            if columns.iter().all(Option::is_none) {
+                continue;
+            }
+
+            for (i, palette_index) in section.block_states().indices().into_iter().enumerate() {
+                if let Some(column) = &columns[palette_index] {
+                    let y = section.y() * 16 + (i >> 8) as i32;
+                    *counts.entry(column.clone()).or_default().entry(y).or_default() += 1;
+                }
+            }
+        }
+    })?;
+
+    if counts.is_empty() {
+        eprintln!("Found none of the blocks");
+        return Ok(());
+    }
+
+    let names: Vec<&String> = counts.keys().collect();
+    let min_y = counts.values().filter_map(|by_y| by_y.keys().next()).min().copied().unwrap_or(0);
+    let max_y = counts.values().filter_map(|by_y| by_y.keys().next_back()).max().copied().unwrap_or(0);
+    let count_at = |name: &String, y: i32| counts[name].get(&y).copied().unwrap_or(0);
+
+    match args.format {
+        Format::Csv => {
+            println!("y,{}", names.iter().map(|name| name.as_str()).collect::<Vec<_>>().join(","));
+            for y in (min_y..=max_y).rev() {
+                let row: Vec<String> = names.iter().map(|name| count_at(name, y).to_string()).collect();
+                println!("{y},{}", row.join(","));
+            }
+        },
+        Format::Table => {
+            let widths: Vec<usize> = names.iter()
+                .map(|name| name.len().max(counts[*name].values().max().map_or(1, |max| max.to_string().len())))
+                .collect();
+
+            let header: Vec<String> = names.iter().zip(&widths).map(|(name, width)| format!("{name:>width$}")).collect();
+            println!("{:>5}  {}", "y", header.join("  "));
+            for y in (min_y..=max_y).rev() {
+                let row: Vec<String> = names.iter().zip(&widths).map(|(name, width)| format!("{:>width$}", count_at(name, y))).collect();
+                println!("{y:>5}  {}", row.join("  "));
+            }
+        },
+    }
+
+    Ok(())
+}
+
+// Drops the namespace and, unless asked not to, the deepslate_ prefix of ores
+// so both variants of an ore share a column
+fn column_name(block: &str, split_deepslate: bool) -> String {
+    let name = block.strip_prefix("minecraft:").unwrap_or(block);
+    match name.strip_prefix("deepslate_") {
+        Some(ore) if !split_deepslate && ore.ends_with("_ore") => ore.to_string(),
+        _ => name.to_string(),
+    }
+}
@@ -0,0 +1,333 @@
+use anyhow::{ Result, Context };
+use clap::Args;
+use std::{ collections::HashMap, path::PathBuf };
+use ratatui::{
+    crossterm::event::{ self, Event, KeyCode, KeyEventKind },
+    layout::{ Constraint, Layout },
+    style::{ Color, Modifier, Style },
+    text::{ Line, Span },
+    widgets::{ Block, Paragraph, Wrap },
+    Frame,
+};
+use path_miner::{ Tag, TagPayload, ParseOptions, chunk::Chunk, region::{ Region, parse_region_file_name }, tree::TagTree };
+
+#[derive(Args)]
+pub struct BrowseArgs {
+    /// Region file (.mca)
+    region: PathBuf,
+}
+
+enum ChunkStatus {
+    Missing,
+    // In the file, not read yet
+    Unread,
+    Parsed(Box<Chunk>),
+    // The NBT too if it could be read, only the chunk made no sense
+    Corrupt(String, Option<Box<Tag>>),
+}
+
+// What the keys do, moving around the region or around a chunk's NBT
+enum Mode {
+    Grid,
+    Tree,
+    // Typing a name to look for in the tree
+    Search(String),
+}
+
+struct Browser {
+    title: String,
+    // Chunk coordinates of the region's first chunk, if the file name gives them away
+    origin: Option<(i32, i32)>,
+    region: Region,
+    chunks: Vec<ChunkStatus>,
+    selected: usize,
+    scroll: u16,
+    mode: Mode,
+    // Stays as it is going from chunk to chunk, so the same tags can be compared
+    tree: TagTree,
+    last_search: String,
+    // Printed on the way out, to paste into --query
+    copied: Option<String>,
+    message: String,
+}
+
+pub fn run(args: BrowseArgs) -> Result<()> {
+    let region = Region::open(&args.region).with_context(|| format!("Could not read region {}", args.region.display()))?;
+
+    // Chunks are only read once they're looked at
+    let chunks = (0..1024)
+        .map(|index| if region.has_chunk(index) { ChunkStatus::Unread } else { ChunkStatus::Missing })
+        .collect();
+
+    let name = args.region.file_name().map(|name| name.to_string_lossy().into_owned()).unwrap_or_default();
+    let origin = parse_region_file_name(&name).map(|(x, z)| (x * 32, z * 32));
+    let mut browser = Browser {
+        title: name, origin, region, chunks, selected: 0, scroll: 0, mode: Mode::Grid,
+        tree: TagTree::new(), last_search: String::new(), copied: None, message: String::new(),
+    };
+    browser.load();
+
+    let mut terminal = ratatui::init();
+    let result = loop {
+        if let Err(e) = terminal.draw(|frame| browser.draw(frame)) {
+            break Err(e.into());
+        }
+
+        match event::read() {
+            Ok(Event::Key(key)) if key.kind == KeyEventKind::Press => {
+                if !browser.key(key.code) {
+                    break Ok(());
+                }
+            },
+            Ok(_) => {},
+            Err(e) => break Err(e.into()),
+        }
+    };
+    ratatui::restore();
+
+    if let Some(path) = &browser.copied {
+        println!("{path}");
+    }
+    result
+}
+
+impl Browser {
+    // Whether to keep going
+    fn key(&mut self, code: KeyCode) -> bool {
+        self.message.clear();
+        if let Mode::Search(needle) = &mut self.mode {
+            match code {
+                KeyCode::Char(c) => needle.push(c),
+                KeyCode::Backspace => {
+                    needle.pop();
+                },
+                KeyCode::Enter => {
+                    self.last_search = std::mem::take(needle);
+                    self.mode = Mode::Tree;
+                    self.find();
+                },
+                KeyCode::Esc => self.mode = Mode::Tree,
+                _ => {},
+            }
+            return true;
+        }
+
+        match (&self.mode, code) {
+            (_, KeyCode::Char('q')) | (Mode::Grid, KeyCode::Esc) => return false,
+            (_, KeyCode::Char('[')) => self.step(-1),
+            (_, KeyCode::Char(']')) => self.step(1),
+
+            (Mode::Grid, KeyCode::Left | KeyCode::Char('h')) => self.select(-1, 0),
+            (Mode::Grid, KeyCode::Right | KeyCode::Char('l')) => self.select(1, 0),
+            (Mode::Grid, KeyCode::Up | KeyCode::Char('k')) => self.select(0, -1),
+            (Mode::Grid, KeyCode::Down | KeyCode::Char('j')) => self.select(0, 1),
+            (Mode::Grid, KeyCode::PageDown) => self.scroll = self.scroll.saturating_add(10),
+            (Mode::Grid, KeyCode::PageUp) => self.scroll = self.scroll.saturating_sub(10),
+            (Mode::Grid, KeyCode::Enter) if self.nbt().is_some() => self.mode = Mode::Tree,
+
+            (Mode::Tree, KeyCode::Esc) => self.mode = Mode::Grid,
+            (Mode::Tree, KeyCode::Char('/')) => self.mode = Mode::Search(String::new()),
+            (Mode::Tree, KeyCode::Char('n')) => self.find(),
+            (Mode::Tree, code) => {
+                let Some(root) = nbt(&self.chunks[self.selected]) else {
+                    return true;
+                };
+                let root = &root.payload;
+                let tree = &mut self.tree;
+                match code {
+                    KeyCode::Up | KeyCode::Char('k') => tree.up(),
+                    KeyCode::Down | KeyCode::Char('j') => tree.down(root),
+                    KeyCode::Left | KeyCode::Char('h') => tree.left(root),
+                    KeyCode::Right | KeyCode::Char('l') => tree.right(root),
+                    KeyCode::Enter => tree.toggle(root),
+                    KeyCode::PageDown => tree.page(root, 1),
+                    KeyCode::PageUp => tree.page(root, -1),
+                    KeyCode::Char('y') => {
+                        let path = tree.path(root);
+                        self.message = format!("Copied {path}, printed on quitting");
+                        self.copied = Some(path);
+                    },
+                    _ => {},
+                }
+            },
+            (Mode::Grid | Mode::Search(_), _) => {},
+        }
+        true
+    }
+
+    fn select(&mut self, dx: i32, dz: i32) {
+        let x = (self.selected % 32) as i32 + dx;
+        let z = (self.selected / 32) as i32 + dz;
+        if (0..32).contains(&x) && (0..32).contains(&z) {
+            self.selected = (z * 32 + x) as usize;
+            self.scroll = 0;
+            self.load();
+        }
+    }
+
+    // Goes to the previous or next chunk the region has
+    fn step(&mut self, direction: isize) {
+        let mut index = self.selected;
+        loop {
+            index = match index.checked_add_signed(direction) {
+                Some(index) if index < 1024 => index,
+                _ => return,
+            };
+            if !matches!(self.chunks[index], ChunkStatus::Missing) {
+                break;
+            }
+        }
+        self.selected = index;
+        self.scroll = 0;
+        self.load();
+
+        // The tree may have fewer rows in this chunk
+        if let Some(root) = self.nbt() {
+            let rows = self.tree.rows(&root.payload).len();
+            self.tree.cursor = self.tree.cursor.min(rows.saturating_sub(1));
+        } else if matches!(self.mode, Mode::Tree) {
+            self.mode = Mode::Grid;
+        }
+    }
+
+    fn load(&mut self) {
+        if !matches!(self.chunks[self.selected], ChunkStatus::Unread) {
+            return;
+        }
+        self.chunks[self.selected] = match self.region.read_chunk(self.selected, &ParseOptions::default()) {
+            Ok(None) => ChunkStatus::Missing,
+            Ok(Some(tag)) => match Chunk::from_nbt(tag.clone()) {
+                Ok(chunk) => ChunkStatus::Parsed(Box::new(chunk)),
+                Err(e) => ChunkStatus::Corrupt(format!("{e:#}"), Some(Box::new(tag))),
+            },
+            Err(e) => ChunkStatus::Corrupt(format!("{e:#}"), None),
+        };
+    }
+
+    fn nbt(&self) -> Option<&Tag> {
+        nbt(&self.chunks[self.selected])
+    }
+
+    fn find(&mut self) {
+        let Some(root) = nbt(&self.chunks[self.selected]) else {
+            return;
+        };
+        let root: &TagPayload = &root.payload;
+        if !self.last_search.is_empty() && !self.tree.find(root, &self.last_search) {
+            self.message = format!("No tag named like {}", self.last_search);
+        }
+    }
+
+    fn draw(&self, frame: &mut Frame) {
+        let [grid_area, detail_area] = Layout::horizontal([Constraint::Length(66), Constraint::Min(30)]).areas(frame.area());
+
+        let rows: Vec<Line> = (0..32)
+            .map(|z| {
+                Line::from((0..32).map(|x| {
+                    let index = z * 32 + x;
+                    let color = match self.chunks[index] {
+                        ChunkStatus::Missing => Color::DarkGray,
+                        ChunkStatus::Unread => Color::Gray,
+                        ChunkStatus::Parsed(_) => Color::Green,
+                        ChunkStatus::Corrupt(..) => Color::Red,
+                    };
+                    let mut style = Style::new().fg(color);
+                    if index == self.selected {
+                        style = style.bg(Color::White).add_modifier(Modifier::BOLD);
+                    }
+                    Span::styled(if matches!(self.chunks[index], ChunkStatus::Missing) { "··" } else { "██" }, style)
+                }).collect::<Vec<_>>())
+            })
+            .collect();
+        let help = match self.mode {
+            Mode::Grid => " arrows move, Enter opens the NBT, [ ] skip to chunks, q quits ",
+            Mode::Tree => " arrows walk the tree, PgUp/PgDn page arrays, / n find, y copies the path, Esc back ",
+            Mode::Search(_) => " type a name, Enter finds it, Esc cancels ",
+        };
+        let grid = Paragraph::new(rows).block(Block::bordered().title(format!(" {} ", self.title)).title_bottom(help));
+        frame.render_widget(grid, grid_area);
+
+        let status = match &self.mode {
+            Mode::Search(needle) => format!(" /{needle} "),
+            _ if !self.message.is_empty() => format!(" {} ", self.message),
+            _ => String::new(),
+        };
+        let block = Block::bordered().title_bottom(status);
+        let details = match (&self.mode, self.nbt()) {
+            (Mode::Tree | Mode::Search(_), Some(root)) => {
+                // Keep the cursor in the middle once it's far enough down
+                let visible = detail_area.height.saturating_sub(2) as usize;
+                let scroll = self.tree.cursor.saturating_sub(visible / 2) as u16;
+                Paragraph::new(self.tree_lines(&root.payload)).block(block.title(" NBT ")).scroll((scroll, 0))
+            },
+            _ => Paragraph::new(self.details()).block(block.title(" Chunk ")).wrap(Wrap { trim: false }).scroll((self.scroll, 0)),
+        };
+        frame.render_widget(details, detail_area);
+    }
+
+    fn tree_lines(&self, root: &TagPayload) -> Vec<Line<'static>> {
+        self.tree.rows(root).into_iter().enumerate()
+            .map(|(i, row)| {
+                let marker = match (row.expandable, row.expanded) {
+                    (true, true) => "▾ ",
+                    (true, false) => "▸ ",
+                    _ => "  ",
+                };
+                let text = format!("{}{marker}{}", "  ".repeat(row.depth), row.text);
+                if i == self.tree.cursor {
+                    Line::styled(text, Style::new().add_modifier(Modifier::REVERSED))
+                } else {
+                    Line::from(text)
+                }
+            })
+            .collect()
+    }
+
+    fn details(&self) -> Vec<Line<'static>> {
+        let (x, z) = (self.selected % 32, self.selected / 32);
+        let mut lines = vec![match self.origin {
+            Some((origin_x, origin_z)) => Line::from(format!("Index {} at chunk ({}, {})", self.selected, origin_x + x as i32, origin_z + z as i32)),
+            None => Line::from(format!("Index {} at ({x}, {z}) in the region", self.selected)),
+        }];
+
+        match &self.chunks[self.selected] {
+            ChunkStatus::Missing => lines.push(Line::from("Not generated")),
+            ChunkStatus::Unread => lines.push(Line::from("Not read yet")),
+            ChunkStatus::Corrupt(error, _) => {
+                lines.push(Line::styled("Corrupt", Style::new().fg(Color::Red)));
+                lines.push(Line::from(error.clone()));
+            },
+            ChunkStatus::Parsed(chunk) => {
+                lines.push(Line::from(format!("DataVersion {}, {} sections", chunk.data_version(), chunk.sections().len())));
+
+                let mut counts: HashMap<&str, usize> = HashMap::new();
+                for section in chunk.sections() {
+                    let palette = section.palette();
+                    for index in section.block_states().indices() {
+                        *counts.entry(palette[index].name.as_str()).or_default() += 1;
+                    }
+                }
+                let mut counts: Vec<(&str, usize)> = counts.into_iter().collect();
+                counts.sort_by(|a, b| b.1.cmp(&a.1).then(a.0.cmp(b.0)));
+
+                lines.push(Line::default());
+                lines.push(Line::styled("Palette", Style::new().add_modifier(Modifier::BOLD)));
+                lines.extend(counts.into_iter().map(|(name, count)| Line::from(format!("{count:>8} {name}"))));
+
+                lines.push(Line::default());
+                lines.push(Line::styled(format!("Block entities ({})", chunk.block_entities().len()), Style::new().add_modifier(Modifier::BOLD)));
+                lines.extend(chunk.block_entities().iter().map(|block_entity| Line::from(format!("{} at {}", block_entity.id(), block_entity.pos()))));
+            },
+        }
+
+        lines
+    }
+}
+
+fn nbt(status: &ChunkStatus) -> Option<&Tag> {
+    match status {
+        ChunkStatus::Parsed(chunk) => Some(chunk.nbt()),
+        ChunkStatus::Corrupt(_, nbt) => nbt.as_deref(),
+        ChunkStatus::Missing | ChunkStatus::Unread => None,
+    }
+}
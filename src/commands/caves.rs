@@ -0,0 +1,68 @@
+use anyhow::{ Result, anyhow };
+use clap::Args;
+use std::path::PathBuf;
+use path_miner::{ World, Dimension, caves::find_caves, render::{ BlockColors, MapLayer, Rgba, TopDownRenderer } };
+
+const CAVE_TINT: Rgba = [220, 40, 40, 140];
+
+#[derive(Args)]
+pub struct CavesArgs {
+    /// World folder
+    world: PathBuf,
+    /// Block position to look around, given as x,z
+    #[arg(long, value_parser = parse_center, allow_hyphen_values = true, default_value = "0,0")]
+    center: (i32, i32),
+    /// How many chunks to look in every direction
+    #[arg(long, default_value_t = 4)]
+    radius: i32,
+    #[arg(long, default_value = "overworld")]
+    dimension: Dimension,
+    /// Leave out caves smaller than this many blocks
+    #[arg(long, default_value_t = 20)]
+    min_volume: usize,
+    /// Also list the entrances of every cave
+    #[arg(long)]
+    entrances: bool,
+    /// Render a top-down map of the area with the caves tinted red to this PNG
+    #[arg(long, value_name = "FILE")]
+    map: Option<PathBuf>,
+}
+
+fn parse_center(s: &str) -> Result<(i32, i32)> {
+    let (x, z) = s.split_once(',').ok_or_else(|| anyhow!("Expected x,z"))?;
+    Ok((x.trim().parse()?, z.trim().parse()?))
+}
+
+pub fn run(args: CavesArgs) -> Result<()> {
+    let world = World::open(&args.world)?;
+    let center = (args.center.0.div_euclid(16), args.center.1.div_euclid(16));
+    let min_chunk = (center.0 - args.radius, center.1 - args.radius);
+    let max_chunk = (center.0 + args.radius, center.1 + args.radius);
+    let view = world.view(args.dimension, min_chunk, max_chunk)?;
+
+    let caves = find_caves(&view, args.min_volume);
+    for (i, cave) in caves.iter().enumerate() {
+        println!("Cave {}: {} blocks, {} to {}, {} entrances", i + 1, cave.volume(), cave.min, cave.max, cave.entrances.len());
+        if args.entrances {
+            for entrance in &cave.entrances {
+                println!("  {entrance}");
+            }
+        }
+    }
+    let volume: usize = caves.iter().map(|cave| cave.volume()).sum();
+    eprintln!("Found {} caves with {volume} blocks of air", caves.len());
+
+    if let Some(path) = &args.map {
+        let colors = BlockColors::default();
+        let mut renderer = TopDownRenderer::new(&colors, MapLayer::Blocks, min_chunk, max_chunk);
+        for chunk in view.chunks() {
+            renderer.add_chunk(chunk);
+        }
+        renderer.tint_columns(caves.iter().flat_map(|cave| cave.blocks.iter().map(|pos| (pos.x, pos.z))), CAVE_TINT);
+        let image = renderer.finish();
+        image.save_png(path)?;
+        eprintln!("Wrote {}x{} map with its corner at block ({}, {}) to {}", image.width, image.height, min_chunk.0 * 16, min_chunk.1 * 16, path.display());
+    }
+
+    Ok(())
+}
@@ -0,0 +1,27 @@
+use anyhow::{ Result, Context, bail };
+use clap::Args;
+use std::path::PathBuf;
+use path_miner::region::Region;
+
+#[derive(Args)]
+pub struct CheckArgs {
+    /// Region file (.mca)
+    region: PathBuf,
+}
+
+pub fn run(args: CheckArgs) -> Result<()> {
+    let mut region = Region::open(&args.region).with_context(|| format!("Could not read region header of {}", args.region.display()))?;
+    let problems = region.check()?;
+    let chunks = region.chunk_offsets().len();
+
+    for problem in &problems {
+        println!("{problem}");
+    }
+
+    if !problems.is_empty() {
+        bail!("Found {} problems in {} chunks", problems.len(), chunks);
+    }
+    eprintln!("All {chunks} chunks are fine");
+
+    Ok(())
+}
@@ -0,0 +1,51 @@
+use anyhow::{ Result, Context };
+use clap::Args;
+use std::{ fs, path::PathBuf };
+use path_miner::{ World, region::{ COMPRESSION_EXTERNAL, COMPRESSION_ZLIB, compress_zlib, decompress_chunk, rewrite_region } };
+
+#[derive(Args)]
+pub struct CompactArgs {
+    /// World folder or region file (.mca)
+    path: PathBuf,
+    /// Also recompress every chunk with zlib at this level, from 0 to 9
+    #[arg(long, value_parser = clap::value_parser!(u32).range(0..=9))]
+    level: Option<u32>,
+}
+
+pub fn run(args: CompactArgs) -> Result<()> {
+    let regions = if args.path.is_dir() {
+        let world = World::open(&args.path)?;
+        world.regions().iter().chain(world.entity_regions()).chain(world.poi_regions())
+            .filter(|region| !region.is_empty())
+            .map(|region| region.path.clone())
+            .collect()
+    } else {
+        vec![args.path.clone()]
+    };
+
+    let (mut before, mut after) = (0, 0);
+    for path in regions {
+        let size = fs::metadata(&path)?.len();
+
+        rewrite_region(&path, |_, compression, data| {
+            match args.level {
+                // Chunks in .mcc files stay where they are
+                Some(level) if compression & COMPRESSION_EXTERNAL == 0 => {
+                    let raw = decompress_chunk(compression, &data)?;
+                    Ok(Some((COMPRESSION_ZLIB, compress_zlib(&raw, level)?)))
+                },
+                _ => Ok(Some((compression, data))),
+            }
+        }).with_context(|| format!("Could not compact {}", path.display()))?;
+
+        // Regions without any chunks are deleted
+        let new_size = fs::metadata(&path).map_or(0, |metadata| metadata.len());
+        println!("{}: {} -> {} bytes", path.display(), size, new_size);
+        before += size;
+        after += new_size;
+    }
+
+    eprintln!("Saved {} of {} bytes", before.saturating_sub(after), before);
+
+    Ok(())
+}
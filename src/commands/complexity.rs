@@ -0,0 +1,115 @@
+use anyhow::{ Result, bail };
+use clap::{ Args, ValueEnum };
+use std::path::PathBuf;
+use path_miner::{ Dimension, ParseOptions, complexity::{ ChunkComplexity, ComplexityWeights, chunk_complexity }, render::{ Image, heat_color } };
+
+#[derive(Args)]
+pub struct ComplexityArgs {
+    /// World folder or region file (.mca)
+    path: PathBuf,
+    /// Only rank chunks in this dimension of a world
+    #[arg(long)]
+    dimension: Option<Dimension>,
+    /// TOML file with the weights of distinct_blocks, palette_entropy, block_entities, surface_variance and activity, 1 each if left out
+    #[arg(long, value_name = "FILE")]
+    weights: Option<PathBuf>,
+    /// How many of the highest scoring chunks to list
+    #[arg(long, default_value_t = 20)]
+    top: usize,
+    #[arg(long, value_enum, default_value = "text")]
+    format: Format,
+    /// Output PNG, one square per chunk from blue for the plainest to red for the busiest
+    #[arg(long, value_name = "FILE")]
+    overlay: Option<PathBuf>,
+    /// Pixels per chunk on the overlay
+    #[arg(long, default_value_t = 4)]
+    scale: usize,
+    #[command(flatten)]
+    scan: super::ScanArgs,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, ValueEnum)]
+enum Format {
+    /// One line per chunk with its score and what went into it
+    Text,
+    /// Comma separated, with a header row, one row per chunk
+    Csv,
+}
+
+pub fn run(args: ComplexityArgs) -> Result<()> {
+    let weights = match &args.weights {
+        Some(path) => ComplexityWeights::load(path)?,
+        None => ComplexityWeights::default(),
+    };
+
+    // Everything comes from the palettes, so no section is kept and no block
+    // data is unpacked
+    let mut chunks: Vec<(Option<Dimension>, ChunkComplexity)> = Vec::new();
+    super::for_each_chunk_where(&args.path, &args.scan, ParseOptions::for_surface_search(), |_| false, |dimension, chunk| {
+        if args.dimension.is_some() && dimension != args.dimension {
+            return;
+        }
+        chunks.push((dimension, chunk_complexity(chunk)));
+    })?;
+    if chunks.is_empty() {
+        bail!("No chunks to rank");
+    }
+
+    // The overlay is of one dimension, the overworld unless another is asked for
+    let shown = args.dimension.unwrap_or(Dimension::Overworld);
+    let overlay: Vec<&ChunkComplexity> = chunks.iter()
+        .filter(|(dimension, _)| dimension.is_none_or(|dimension| dimension == shown))
+        .map(|(_, chunk)| chunk)
+        .collect();
+
+    // Highest score first, ties by position
+    let mut ranked: Vec<(f64, Option<Dimension>, &ChunkComplexity)> = chunks.iter().map(|(dimension, chunk)| (chunk.score(&weights), *dimension, chunk)).collect();
+    ranked.sort_by(|a, b| b.0.total_cmp(&a.0).then((a.1, a.2.x, a.2.z).cmp(&(b.1, b.2.x, b.2.z))));
+
+    if args.format == Format::Csv {
+        println!("dimension,x,z,score,distinct_blocks,palette_entropy,block_entities,surface_spread,inhabited_time");
+        for (score, dimension, chunk) in ranked.iter().take(args.top) {
+            println!(
+                "{},{},{},{score:.4},{},{:.4},{},{:.4},{}",
+                dimension.map_or("", Dimension::id), chunk.x, chunk.z, chunk.distinct, chunk.entropy, chunk.block_entities, chunk.surface_spread, chunk.inhabited,
+            );
+        }
+    } else {
+        println!("{:>6} {:>14} {:>8} {:>7} {:>8} {:>9} {:>8}", "score", "chunk", "distinct", "entropy", "entities", "surface", "ticks");
+        for (score, dimension, chunk) in ranked.iter().take(args.top) {
+            let position = format!("({}, {})", chunk.x, chunk.z);
+            let dimension = dimension.map(|dimension| format!(" {dimension:?}")).unwrap_or_default();
+            println!(
+                "{score:>6.3} {position:>14} {:>8} {:>7.2} {:>8} {:>9.2} {:>8}{dimension}",
+                chunk.distinct, chunk.entropy, chunk.block_entities, chunk.surface_spread, chunk.inhabited,
+            );
+        }
+    }
+    eprintln!("Ranked {} chunks", ranked.len());
+
+    if let Some(path) = &args.overlay {
+        if overlay.is_empty() {
+            bail!("No chunks in {shown:?} for the overlay");
+        }
+        let scores: Vec<f64> = overlay.iter().map(|chunk| chunk.score(&weights)).collect();
+        let most = scores.iter().copied().fold(0.0, f64::max);
+
+        let scale = args.scale.max(1);
+        let min = (overlay.iter().map(|c| c.x).min().unwrap(), overlay.iter().map(|c| c.z).min().unwrap());
+        let max = (overlay.iter().map(|c| c.x).max().unwrap(), overlay.iter().map(|c| c.z).max().unwrap());
+        let mut image = Image::new((max.0 - min.0 + 1) as usize * scale, (max.1 - min.1 + 1) as usize * scale);
+        for (chunk, score) in overlay.iter().zip(scores) {
+            let color = heat_color(if most > 0.0 { (score / most) as f32 } else { 0.0 });
+            let (px, pz) = ((chunk.x - min.0) as usize * scale, (chunk.z - min.1) as usize * scale);
+            for dz in 0..scale {
+                for dx in 0..scale {
+                    image.set(px + dx, pz + dz, color);
+                }
+            }
+        }
+        image.save_png(path)?;
+        eprintln!("Wrote {}x{} overlay with chunk ({}, {}) in its corner to {}", image.width, image.height, min.0, min.1, path.display());
+    }
+
+    Ok(())
+}
@@ -0,0 +1,124 @@
+use anyhow::{ Result, Context, anyhow, bail };
+use clap::Args;
+use std::{ collections::{ BTreeMap, HashMap }, fs, path::{ Path, PathBuf } };
+use path_miner::{ Tag, TagPayload, Dimension, ParseOptions, region::{ Region, chunk_index_in_region, chunk_to_region_coord, put_chunks }, relocate::{ relocate_chunk, relocate_entity_chunk, relocate_poi_chunk } };
+
+type Relocate = fn(&mut TagPayload, i32, i32);
+// Chunks to write by destination region, with their index in it
+type Writes = BTreeMap<PathBuf, Vec<(usize, Tag)>>;
+
+// The folders of a dimension keyed by chunk, and how to move their chunks
+const FOLDERS: [(&str, Relocate); 3] = [
+    ("region", relocate_chunk),
+    ("entities", relocate_entity_chunk),
+    ("poi", relocate_poi_chunk),
+];
+
+#[derive(Args)]
+pub struct CopyChunksArgs {
+    /// World folder to copy from
+    from: PathBuf,
+    /// World folder to copy into, the same world if left out
+    #[arg(long)]
+    to: Option<PathBuf>,
+    /// Chunks to copy, given in chunk coordinates as x1,z1,x2,z2
+    #[arg(long, value_parser = parse_chunk_area, allow_hyphen_values = true)]
+    chunks: [i32; 4],
+    /// How many chunks to move the copies by, as x,z
+    #[arg(long, value_parser = parse_offset, allow_hyphen_values = true, default_value = "0,0")]
+    offset: (i32, i32),
+    #[arg(long, default_value = "overworld")]
+    dimension: Dimension,
+    /// Replace chunks already at the destination instead of stopping
+    #[arg(long)]
+    overwrite: bool,
+}
+
+fn parse_chunk_area(s: &str) -> Result<[i32; 4]> {
+    let values: Vec<i32> = s.split(',').map(|v| v.trim().parse()).collect::<Result<_, _>>().map_err(|_| anyhow!("Expected x1,z1,x2,z2"))?;
+    match values.as_slice() {
+        [x1, z1, x2, z2] => Ok([*x1.min(x2), *z1.min(z2), *x1.max(x2), *z1.max(z2)]),
+        _ => bail!("Expected x1,z1,x2,z2"),
+    }
+}
+
+fn parse_offset(s: &str) -> Result<(i32, i32)> {
+    let (x, z) = s.split_once(',').ok_or_else(|| anyhow!("Expected x,z"))?;
+    Ok((x.trim().parse()?, z.trim().parse()?))
+}
+
+pub fn run(args: CopyChunksArgs) -> Result<()> {
+    let to = args.to.as_ref().unwrap_or(&args.from);
+    if args.offset == (0, 0) && same_folder(&args.from, to) {
+        bail!("Copying chunks onto themselves, pass --to or --offset");
+    }
+    let source = args.from.join(args.dimension.directory());
+    let destination = to.join(args.dimension.directory());
+    let (dx, dz) = args.offset;
+    let [x1, z1, x2, z2] = args.chunks;
+
+    // Everything is read before anything is written, so areas that overlap copy right
+    let mut copies: Vec<(&str, Writes)> = Vec::new();
+    for (folder, relocate) in FOLDERS {
+        let mut regions: HashMap<PathBuf, Option<Region>> = HashMap::new();
+        let mut writes = Writes::new();
+        for z in z1..=z2 {
+            for x in x1..=x2 {
+                let path = region_path(&source.join(folder), x, z);
+                let region = match regions.entry(path.clone()).or_insert_with(|| Region::open(&path).ok()) {
+                    Some(region) => region,
+                    None => continue,
+                };
+                let Some(mut chunk) = region.read_chunk(chunk_index_in_region(x, z), &ParseOptions::default())
+                    .with_context(|| format!("Could not read chunk ({x}, {z}) from {}", path.display()))? else {
+                    continue;
+                };
+                relocate(&mut chunk.payload, dx, dz);
+                let (to_x, to_z) = (x + dx, z + dz);
+                writes.entry(region_path(&destination.join(folder), to_x, to_z)).or_default().push((chunk_index_in_region(to_x, to_z), chunk));
+            }
+        }
+        copies.push((folder, writes));
+    }
+
+    let terrain: usize = copies[0].1.values().map(Vec::len).sum();
+    if terrain == 0 {
+        bail!("There are no chunks between ({x1}, {z1}) and ({x2}, {z2}) in {}", source.display());
+    }
+    if !args.overwrite {
+        let mut taken = 0;
+        for (path, chunks) in &copies[0].1 {
+            if let Ok(region) = Region::open(path) {
+                taken += chunks.iter().filter(|(index, _)| region.has_chunk(*index)).count();
+            }
+        }
+        if taken > 0 {
+            bail!("{taken} of the {terrain} chunks would replace chunks already there, pass --overwrite to replace them");
+        }
+    }
+
+    for (folder, writes) in &copies {
+        for (path, chunks) in writes {
+            if let Some(parent) = path.parent() {
+                fs::create_dir_all(parent).with_context(|| format!("Could not create {}", parent.display()))?;
+            }
+            let chunks: Vec<(usize, &Tag)> = chunks.iter().map(|(index, chunk)| (*index, chunk)).collect();
+            put_chunks(path, &chunks).with_context(|| format!("Could not write {}", path.display()))?;
+            println!("{}: {} {folder} chunks", path.display(), chunks.len());
+        }
+    }
+    eprintln!("Copied {terrain} chunks to ({}, {}) through ({}, {})", x1 + dx, z1 + dz, x2 + dx, z2 + dz);
+
+    Ok(())
+}
+
+fn region_path(folder: &Path, chunk_x: i32, chunk_z: i32) -> PathBuf {
+    folder.join(format!("r.{}.{}.mca", chunk_to_region_coord(chunk_x), chunk_to_region_coord(chunk_z)))
+}
+
+fn same_folder(a: &Path, b: &Path) -> bool {
+    match (fs::canonicalize(a), fs::canonicalize(b)) {
+        (Ok(a), Ok(b)) => a == b,
+        _ => a == b,
+    }
+}
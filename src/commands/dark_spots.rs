@@ -0,0 +1,93 @@
+use anyhow::{ Result, bail };
+use clap::Args;
+use std::path::PathBuf;
+use path_miner::{ World, Dimension, BlockPos, actions::Action, lighting::{ TORCH_LIGHT, find_dark_zones }, mcfunction::{ torch_commands, write_mcfunction }, render::{ BlockColors, MapLayer, Rgba, TopDownRenderer } };
+
+const DARK_TINT: Rgba = [255, 0, 200, 150];
+const TORCH_TINT: Rgba = [255, 200, 0, 255];
+
+#[derive(Args)]
+pub struct DarkSpotsArgs {
+    /// World folder
+    world: PathBuf,
+    /// Box to look for dark spots in, given as x1,y1,z1,x2,y2,z2
+    #[arg(long = "box", value_name = "BOX", value_parser = super::parse_box, allow_hyphen_values = true)]
+    area: (BlockPos, BlockPos),
+    #[arg(long, default_value = "overworld")]
+    dimension: Dimension,
+    /// Brightest block light mobs spawn in, 0 since 1.18 and 7 before it
+    #[arg(long, default_value_t = 0)]
+    max_block_light: u8,
+    /// Also print every dark position of each zone
+    #[arg(long)]
+    positions: bool,
+    /// Render a top-down map of the box with the dark spots and suggested torches tinted to this PNG
+    #[arg(long, value_name = "FILE")]
+    map: Option<PathBuf>,
+    /// Only tint the dark spots with feet between these heights on the map, as y1,y2
+    #[arg(long, value_name = "Y1,Y2", value_parser = parse_band, allow_hyphen_values = true, requires = "map")]
+    band: Option<(i32, i32)>,
+    /// Also write the suggested torches as a .mcfunction file that puts them in
+    #[arg(long, value_name = "FILE")]
+    mcfunction: Option<PathBuf>,
+}
+
+fn parse_band(s: &str) -> Result<(i32, i32)> {
+    let Some((a, b)) = s.split_once(',') else {
+        bail!("Expected y1,y2");
+    };
+    let (a, b): (i32, i32) = (a.trim().parse()?, b.trim().parse()?);
+    Ok((a.min(b), a.max(b)))
+}
+
+pub fn run(args: DarkSpotsArgs) -> Result<()> {
+    if args.max_block_light >= TORCH_LIGHT {
+        bail!("--max-block-light has to be below {TORCH_LIGHT}, the light of a torch");
+    }
+    let world = World::open(&args.world)?;
+    let (min, max) = args.area;
+    // Light from blocks just outside the box reaches into it
+    let min_chunk = ((min.x - 15).div_euclid(16), (min.z - 15).div_euclid(16));
+    let max_chunk = ((max.x + 15).div_euclid(16), (max.z + 15).div_euclid(16));
+    let view = world.view(args.dimension, min_chunk, max_chunk)?;
+
+    let zones = find_dark_zones(&view, args.area, args.max_block_light);
+    for zone in &zones {
+        println!("{zone}");
+        if args.positions {
+            for pos in &zone.positions {
+                println!("  {pos}");
+            }
+        }
+        for torch in &zone.torches {
+            println!("  {}", Action::Torch(*torch));
+        }
+    }
+    let dark: usize = zones.iter().map(|zone| zone.positions.len()).sum();
+    let torches: Vec<BlockPos> = zones.iter().flat_map(|zone| zone.torches.iter().copied()).collect();
+    eprintln!("{} dark zones with {} blocks mobs can spawn on, {} torches light them", zones.len(), dark, torches.len());
+
+    if let Some(path) = &args.map {
+        let (low, high) = args.band.unwrap_or((min.y, max.y));
+        let in_band = |pos: &&BlockPos| (low..=high).contains(&pos.y);
+        let colors = BlockColors::default();
+        let box_min_chunk = (min.x.div_euclid(16), min.z.div_euclid(16));
+        let box_max_chunk = (max.x.div_euclid(16), max.z.div_euclid(16));
+        let mut renderer = TopDownRenderer::new(&colors, MapLayer::Blocks, box_min_chunk, box_max_chunk);
+        for chunk in view.chunks() {
+            renderer.add_chunk(chunk);
+        }
+        renderer.tint_columns(zones.iter().flat_map(|zone| &zone.positions).filter(in_band).map(|pos| (pos.x, pos.z)), DARK_TINT);
+        renderer.tint_columns(torches.iter().filter(in_band).map(|pos| (pos.x, pos.z)), TORCH_TINT);
+        let image = renderer.finish();
+        image.save_png(path)?;
+        eprintln!("Wrote {}x{} map with its corner at block ({}, {}) to {}", image.width, image.height, box_min_chunk.0 * 16, box_min_chunk.1 * 16, path.display());
+    }
+
+    if let Some(path) = &args.mcfunction {
+        write_mcfunction(path, &torch_commands(&torches))?;
+        eprintln!("Wrote {}", path.display());
+    }
+
+    Ok(())
+}
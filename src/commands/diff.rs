@@ -0,0 +1,79 @@
+use anyhow::{ Result, Context };
+use clap::Args;
+use std::path::{ Path, PathBuf };
+use path_miner::{ Tag, ParseOptions, diff::TagDiff, region::Region };
+
+#[derive(Args)]
+pub struct DiffArgs {
+    /// Older region file (.mca) or NBT file
+    a: PathBuf,
+    /// Newer region file or NBT file
+    b: PathBuf,
+    /// Only compare the chunk at this position in the regions
+    #[arg(long)]
+    chunk: Option<usize>,
+    /// Leave out tags with this name wherever they are, e.g. LastUpdate
+    #[arg(long)]
+    ignore: Vec<String>,
+}
+
+pub fn run(args: DiffArgs) -> Result<()> {
+    let is_region = |path: &Path| path.extension().is_some_and(|extension| extension == "mca");
+    let mut differences = 0;
+
+    if is_region(&args.a) && is_region(&args.b) {
+        let mut a = Region::open(&args.a).with_context(|| format!("Could not read region {}", args.a.display()))?;
+        let mut b = Region::open(&args.b).with_context(|| format!("Could not read region {}", args.b.display()))?;
+        let indices = match args.chunk {
+            Some(index) => index..index + 1,
+            None => 0..1024,
+        };
+
+        for index in indices {
+            let old = a.read_chunk(index, &ParseOptions::default()).with_context(|| format!("Could not read chunk {index} of {}", args.a.display()))?;
+            let new = b.read_chunk(index, &ParseOptions::default()).with_context(|| format!("Could not read chunk {index} of {}", args.b.display()))?;
+
+            match (old, new) {
+                (Some(old), Some(new)) => {
+                    let diffs = filtered(&old, &new, &args.ignore);
+                    if !diffs.is_empty() {
+                        println!("Chunk {index}:");
+                        for diff in &diffs {
+                            println!("  {diff}");
+                        }
+                        differences += diffs.len();
+                    }
+                },
+                (Some(_), None) => {
+                    println!("Chunk {index} removed");
+                    differences += 1;
+                },
+                (None, Some(_)) => {
+                    println!("Chunk {index} added");
+                    differences += 1;
+                },
+                (None, None) => {},
+            }
+        }
+    } else {
+        let old = super::load_nbt_file(&args.a)?;
+        let new = super::load_nbt_file(&args.b)?;
+        let diffs = filtered(&old, &new, &args.ignore);
+        for diff in &diffs {
+            println!("{diff}");
+        }
+        differences = diffs.len();
+    }
+
+    eprintln!("{differences} differences");
+
+    Ok(())
+}
+
+fn filtered(old: &Tag, new: &Tag, ignore: &[String]) -> Vec<TagDiff> {
+    let ignored = |diff: &TagDiff| {
+        // Every step of the path, with list indices cut off
+        diff.path().split('.').any(|step| ignore.iter().any(|name| step.split('[').next() == Some(name.as_str())))
+    };
+    old.diff(new).into_iter().filter(|diff| !ignored(diff)).collect()
+}
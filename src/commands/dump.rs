@@ -0,0 +1,55 @@
+use anyhow::Result;
+use clap::{ Args, ValueEnum };
+use std::path::PathBuf;
+use path_miner::json::tag_to_json;
+use path_miner::snbt::SnbtFormatter;
+
+#[derive(Args)]
+pub struct DumpArgs {
+    /// Region file (.mca)
+    region: PathBuf,
+    /// Only dump the chunk at this position in the region
+    #[arg(long)]
+    chunk: Option<usize>,
+    /// Output format
+    #[arg(long, value_enum, default_value = "plain")]
+    format: Format,
+    /// Shorthand for --format snbt
+    #[arg(long, conflicts_with = "format")]
+    snbt: bool,
+    /// Spread SNBT or JSON output over indented lines
+    #[arg(long)]
+    pretty: bool,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, ValueEnum)]
+enum Format {
+    /// Readable listing with tag types and names
+    Plain,
+    /// Stringified NBT, as used by commands
+    Snbt,
+    /// One JSON document per chunk; Longs are written as strings
+    Json,
+}
+
+pub fn run(args: DumpArgs) -> Result<()> {
+    let format = if args.snbt { Format::Snbt } else { args.format };
+    let formatter = if args.pretty { SnbtFormatter::pretty() } else { SnbtFormatter::new() };
+
+    for chunk in super::load_chunks(&args.region, args.chunk)? {
+        match format {
+            Format::Plain => println!("{}", chunk),
+            Format::Snbt => println!("{}", formatter.format(&chunk)),
+            Format::Json => {
+                let value = tag_to_json(&chunk);
+                if args.pretty {
+                    println!("{}", serde_json::to_string_pretty(&value)?);
+                } else {
+                    println!("{}", serde_json::to_string(&value)?);
+                }
+            },
+        }
+    }
+
+    Ok(())
+}
@@ -0,0 +1,60 @@
+use anyhow::Result;
+use clap::Args;
+use std::path::PathBuf;
+use path_miner::{ World, Dimension, ParseOptions, gateway::{ Gateway, outer_island_regions, return_gateway } };
+
+#[derive(Args)]
+pub struct EndGatewaysArgs {
+    /// World folder
+    world: PathBuf,
+    /// Stop at the first chunk that can't be read instead of skipping it
+    #[arg(long)]
+    strict: bool,
+}
+
+pub fn run(args: EndGatewaysArgs) -> Result<()> {
+    let world = World::open(&args.world)?;
+    // Gateways are block entities, the blocks themselves aren't needed
+    let options = ParseOptions::new()
+        .skip("sections")
+        .skip("Sections")
+        .skip("Entities")
+        .skip("Heightmaps");
+
+    let mut gateways = Vec::new();
+    for chunk in world.chunks_in(Dimension::End).with_options(options) {
+        match chunk {
+            Ok((_, chunk)) => gateways.extend(chunk.block_entities().iter().filter_map(Gateway::from_block_entity)),
+            Err(e) if args.strict => return Err(e),
+            Err(e) => log::warn!("Skipping chunk: {e:#}"),
+        }
+    }
+    gateways.sort_by_key(|gateway| (!gateway.is_on_main_island(), gateway.pos));
+
+    for gateway in &gateways {
+        let side = if gateway.is_on_main_island() { "main island" } else { "outer islands" };
+        match gateway.exit {
+            Some(exit) => {
+                print!("{} ({side}) -> {exit}", gateway.pos);
+                if gateway.exact {
+                    print!(" exactly");
+                }
+                if let Some(back) = return_gateway(gateway, &gateways) {
+                    print!(", back through {}", back.pos);
+                }
+                println!();
+            },
+            None => println!("{} ({side}) -> not used yet", gateway.pos),
+        }
+    }
+    let linked = gateways.iter().filter(|gateway| gateway.exit.is_some()).count();
+    eprintln!("Found {} gateways, {linked} of them linked", gateways.len());
+
+    let regions = outer_island_regions(&world)?;
+    for region in &regions {
+        println!("r.{}.{}.mca: {} outer island chunks", region.x, region.z, region.chunks);
+    }
+    eprintln!("{} regions have outer island chunks", regions.len());
+
+    Ok(())
+}
@@ -0,0 +1,147 @@
+use anyhow::Result;
+use clap::Args;
+use std::{ io::{ self, BufRead, Write }, path::PathBuf };
+use path_miner::{ TagPayload, snbt::SnbtFormatter, tree::{ join, summary, type_name } };
+
+#[derive(Args)]
+pub struct ExploreArgs {
+    /// Region file (.mca) or NBT file such as level.dat, gzipped or not
+    file: PathBuf,
+}
+
+const HELP: &str = "\
+ls [PATH]      list the tags in a compound or list
+cd PATH        move into a tag, e.g. sections[3].block_states, .. or /
+cat [PATH]     print a tag as SNBT
+find TEXT      search names and string values below here
+pwd            print the current path
+quit           leave";
+
+pub fn run(args: ExploreArgs) -> Result<()> {
+    // A region becomes a list of its chunks, so [3] is the fourth chunk in the file
+    let root = if args.file.extension().is_some_and(|extension| extension == "mca") {
+        TagPayload::List(super::load_chunks(&args.file, None)?.into_iter().map(|chunk| chunk.payload).collect())
+    } else {
+        super::load_nbt_file(&args.file)?.payload
+    };
+
+    let formatter = SnbtFormatter::pretty();
+    let mut cwd = String::new();
+    let mut stdin = io::stdin().lock();
+    eprintln!("Type help for a list of commands");
+
+    loop {
+        print!("/{cwd}> ");
+        io::stdout().flush()?;
+
+        let mut line = String::new();
+        if stdin.read_line(&mut line)? == 0 {
+            println!();
+            return Ok(());
+        }
+        let (command, argument) = line.trim().split_once(' ').unwrap_or((line.trim(), ""));
+        let argument = argument.trim();
+
+        match command {
+            "" => {},
+            "help" => println!("{HELP}"),
+            "quit" | "exit" => return Ok(()),
+            "pwd" => println!("/{cwd}"),
+            "ls" | "cat" | "cd" => {
+                let path = resolve(&cwd, argument);
+                let tag = match root.get_path(&path) {
+                    Ok(tag) => tag,
+                    Err(e) => {
+                        println!("{e}");
+                        continue;
+                    },
+                };
+
+                match command {
+                    "ls" => list(tag),
+                    "cat" => println!("{}", formatter.format_payload(tag)),
+                    _ if matches!(tag, TagPayload::Compound(_) | TagPayload::List(_)) => cwd = path,
+                    _ => println!("{} is not a compound or list", if path.is_empty() { "/" } else { &path }),
+                }
+            },
+            "find" if !argument.is_empty() => {
+                // cwd always resolved when it was entered
+                let Ok(tag) = root.get_path(&cwd) else { continue };
+                let mut found = 0;
+                search(tag, "", &argument.to_lowercase(), &mut found);
+                println!("{found} matches");
+            },
+            "find" => println!("find needs some text to look for"),
+            _ => println!("Unknown command {command}, try help"),
+        }
+    }
+}
+
+// Applies a cd style path to the current one. Steps are separated by slashes,
+// a leading slash starts from the root and .. goes up a tag.
+fn resolve(cwd: &str, path: &str) -> String {
+    let mut resolved = if path.starts_with('/') { String::new() } else { cwd.to_string() };
+
+    for part in path.split('/').filter(|part| !part.is_empty() && *part != ".") {
+        if part == ".." {
+            let cut = if resolved.ends_with(']') { resolved.rfind('[') } else { resolved.rfind('.') };
+            resolved.truncate(cut.unwrap_or(0));
+        } else {
+            resolved = join(&resolved, part);
+        }
+    }
+
+    resolved
+}
+
+fn list(tag: &TagPayload) {
+    match tag {
+        TagPayload::Compound(tags) => {
+            let width = tags.iter().map(|tag| tag.name.len()).max().unwrap_or(0);
+            for tag in tags {
+                println!("{:width$}  {:10}  {}", tag.name, type_name(&tag.payload), summary(&tag.payload));
+            }
+        },
+        TagPayload::List(items) => {
+            for (i, item) in items.iter().enumerate() {
+                println!("[{i}]  {:10}  {}", type_name(item), summary(item));
+            }
+        },
+        other => println!("{:10}  {}", type_name(other), summary(other)),
+    }
+}
+
+// Prints the path, relative to where the search started, of every tag whose
+// name or string value contains the text
+fn search(tag: &TagPayload, path: &str, needle: &str, found: &mut usize) {
+    match tag {
+        TagPayload::Compound(tags) => {
+            for child in tags {
+                let child_path = join(path, &child.name);
+                if child.name.to_lowercase().contains(needle) {
+                    println!("{child_path}  {}", summary(&child.payload));
+                    *found += 1;
+                } else if let TagPayload::String(text) = &child.payload {
+                    if text.to_lowercase().contains(needle) {
+                        println!("{child_path}  {}", summary(&child.payload));
+                        *found += 1;
+                    }
+                }
+                search(&child.payload, &child_path, needle, found);
+            }
+        },
+        TagPayload::List(items) => {
+            for (i, item) in items.iter().enumerate() {
+                let item_path = join(path, &format!("[{i}]"));
+                if let TagPayload::String(text) = item {
+                    if text.to_lowercase().contains(needle) {
+                        println!("{item_path}  {}", summary(item));
+                        *found += 1;
+                    }
+                }
+                search(item, &item_path, needle, found);
+            }
+        },
+        _ => {},
+    }
+}
@@ -0,0 +1,46 @@
+use anyhow::Result;
+use clap::Args;
+use std::path::PathBuf;
+use path_miner::{ World, Dimension, BlockPos, schematic::Schematic };
+
+#[derive(Args)]
+pub struct ExportSchemArgs {
+    /// World folder
+    world: PathBuf,
+    /// One corner of the box as x,y,z
+    #[arg(long, allow_hyphen_values = true)]
+    from: BlockPos,
+    /// The opposite corner as x,y,z
+    #[arg(long, allow_hyphen_values = true)]
+    to: BlockPos,
+    /// Output file, a Litematica schematic if it ends in .litematic and a Sponge one otherwise
+    #[arg(short, long)]
+    output: PathBuf,
+    #[arg(long, default_value = "overworld")]
+    dimension: Dimension,
+}
+
+pub fn run(args: ExportSchemArgs) -> Result<()> {
+    let world = World::open(&args.world)?;
+
+    let min_chunk = (args.from.chunk_x().min(args.to.chunk_x()), args.from.chunk_z().min(args.to.chunk_z()));
+    let max_chunk = (args.from.chunk_x().max(args.to.chunk_x()), args.from.chunk_z().max(args.to.chunk_z()));
+    let view = world.view(args.dimension, min_chunk, max_chunk)?;
+
+    let schematic = Schematic::from_view(&view, args.from, args.to)?;
+    if args.output.extension().is_some_and(|extension| extension == "litematic") {
+        let name = args.output.file_stem().map_or("path-miner".into(), |stem| stem.to_string_lossy());
+        schematic.write_litematic(&args.output, &name)?;
+    } else {
+        schematic.write_sponge(&args.output)?;
+    }
+
+    let (width, height, length) = schematic.size();
+    eprintln!(
+        "Wrote {width}x{height}x{length} schematic with {} block states to {}",
+        schematic.palette().len(),
+        args.output.display(),
+    );
+
+    Ok(())
+}
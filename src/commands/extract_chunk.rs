@@ -0,0 +1,32 @@
+use anyhow::{ Result, Context, anyhow };
+use clap::Args;
+use std::{ fs, path::PathBuf };
+use path_miner::region::Region;
+
+#[derive(Args)]
+pub struct ExtractChunkArgs {
+    /// Region file (.mca)
+    region: PathBuf,
+    /// Chunk x coordinate
+    #[arg(allow_hyphen_values = true)]
+    x: i32,
+    /// Chunk z coordinate
+    #[arg(allow_hyphen_values = true)]
+    z: i32,
+    /// File to write the chunk's uncompressed NBT to
+    #[arg(short, long)]
+    output: PathBuf,
+}
+
+pub fn run(args: ExtractChunkArgs) -> Result<()> {
+    let index = super::chunk_index(&args.region, args.x, args.z)?;
+    let mut region = Region::open(&args.region).with_context(|| format!("Could not read region header of {}", args.region.display()))?;
+    let data = region.read_chunk_data(index)
+        .with_context(|| format!("Could not read chunk ({}, {})", args.x, args.z))?
+        .ok_or_else(|| anyhow!("Region has no chunk ({}, {})", args.x, args.z))?;
+
+    fs::write(&args.output, &data).with_context(|| format!("Could not write {}", args.output.display()))?;
+    eprintln!("Wrote chunk ({}, {}), {} bytes, to {}", args.x, args.z, data.len(), args.output.display());
+
+    Ok(())
+}
@@ -0,0 +1,258 @@
+use anyhow::{ Result, Context, bail };
+use clap::Args;
+use indicatif::{ ProgressBar, ProgressStyle };
+use std::{ io::{ self, BufWriter, Write }, path::PathBuf };
+use path_miner::{ World, Dimension, ParseOptions, chunk::{ BlockState, BlockType, Chunk, is_below_surface }, index::WorldIndex, query::Query, region::Region, render::{ BlockColors, hashed_color }, search::{ Hit, HitFormat, HitOrder, Search, SearchRegion }, waypoints::{ Waypoint, WaypointFormat, write_waypoints } };
+
+#[derive(Args)]
+pub struct FindArgs {
+    /// World folder or region file (.mca)
+    path: PathBuf,
+    /// Block to look for, e.g. minecraft:diamond_ore, or minecraft:oak_log[axis=y] for only some of its states
+    #[arg(long, required_unless_present = "query")]
+    block: Vec<BlockState>,
+    /// Look for blocks matching a query instead, e.g. "minecraft:*_ore[!deepslate] & y<0"
+    #[arg(long, conflicts_with = "block")]
+    query: Option<Query>,
+    /// Only report blocks in this biome, e.g. minecraft:windswept_hills (1.18+ worlds)
+    #[arg(long)]
+    biome: Vec<String>,
+    /// Leave out blocks open to the sky, the highest block of their column
+    #[arg(long, conflicts_with = "surface_only")]
+    underground_only: bool,
+    /// Only report blocks open to the sky
+    #[arg(long)]
+    surface_only: bool,
+    /// Also write a waypoint for every find, for xaeros, journeymap or baritone
+    #[arg(long, value_name = "FORMAT")]
+    export_waypoints: Option<WaypointFormat>,
+    /// Folder the waypoints are written to
+    #[arg(long, value_name = "DIR", default_value = "waypoints")]
+    waypoints_dir: PathBuf,
+    /// Keep an index of which blocks each chunk has in the world folder and
+    /// only read the chunks it points to. The index is built on first use and
+    /// brought up to date with the chunks saved since on every later one.
+    #[arg(long, conflicts_with = "modified_since")]
+    index: bool,
+    /// How to print the finds: text, ndjson or csv
+    #[arg(long, default_value = "text")]
+    format: HitFormat,
+    /// unordered prints finds as soon as they're found, chunk prints them region by region and chunk by chunk, the same on every run
+    #[arg(long, default_value = "unordered")]
+    order: HitOrder,
+    /// Stop after this many finds
+    #[arg(long, value_name = "N")]
+    limit: Option<usize>,
+    /// Regions read at the same time, all cores if left out
+    #[arg(long)]
+    threads: Option<usize>,
+    #[command(flatten)]
+    scan: super::ScanArgs,
+}
+
+pub fn run(args: FindArgs) -> Result<()> {
+    let colors = BlockColors::default();
+    let mut waypoints = Vec::new();
+
+    // Chunks without a heightmap need all their sections to tell where the surface is
+    let all_sections = args.underground_only || args.surface_only;
+    let keep = |block: &BlockType| all_sections || match &args.query {
+        Some(query) => query.could_match(block),
+        None => args.block.iter().any(|state| block.matches(state)),
+    };
+
+    let find = |dimension: Option<Dimension>, chunk: &Chunk| {
+        let blocks = match &args.query {
+            Some(query) => chunk.find_query(query),
+            None => chunk.find_states(&args.block),
+        };
+        let surface = (args.underground_only || args.surface_only).then(|| chunk.surface_heights());
+        let mut hits = Vec::new();
+        for (pos, block) in blocks {
+            if let Some(heights) = &surface {
+                if is_below_surface(heights, pos) != args.underground_only {
+                    continue;
+                }
+            }
+            if !args.biome.is_empty() {
+                let biome = chunk.biome_at(pos.x.rem_euclid(16) as usize, pos.y, pos.z.rem_euclid(16) as usize);
+                if !biome.is_some_and(|biome| args.biome.iter().any(|wanted| wanted == biome)) {
+                    continue;
+                }
+            }
+            hits.push(Hit { dimension, block: block.name.to_string(), pos });
+        }
+        hits
+    };
+
+    let mut out = BufWriter::new(io::stdout().lock());
+    if let Some(header) = args.format.header() {
+        writeln!(out, "{header}")?;
+    }
+    let (found, truncated) = {
+        let mut write = |hit: &Hit| -> Result<()> {
+            writeln!(out, "{}", args.format.line(hit))?;
+            if args.export_waypoints.is_some() {
+                waypoints.push(Waypoint {
+                    name: hit.block.trim_start_matches("minecraft:").to_string(),
+                    pos: hit.pos,
+                    // A lone region file could be from any dimension, it's most likely the overworld
+                    dimension: hit.dimension.unwrap_or(Dimension::Overworld),
+                    color: colors.lookup(&hit.block).unwrap_or_else(|| hashed_color(&hit.block)),
+                });
+            }
+            Ok(())
+        };
+        if args.index {
+            find_with_index(&args, keep, find, &mut write)?
+        } else {
+            search(&args, keep, find, &mut write)?
+        }
+    };
+    out.flush()?;
+
+    if truncated {
+        eprintln!("Found {found} blocks, stopped at --limit");
+    } else {
+        eprintln!("Found {found} blocks");
+    }
+
+    if let Some(format) = args.export_waypoints {
+        let files = write_waypoints(&args.waypoints_dir, format, &waypoints)?;
+        eprintln!("Wrote {} waypoints to {files} files in {}", waypoints.len(), args.waypoints_dir.display());
+    }
+
+    Ok(())
+}
+
+type Find<'a> = dyn Fn(Option<Dimension>, &Chunk) -> Vec<Hit> + Sync + 'a;
+
+// Reads the regions on worker threads. Bedrock worlds have no regions and
+// are read one chunk after the other.
+fn search(args: &FindArgs, keep: impl Fn(&BlockType) -> bool + Sync, find: impl Fn(Option<Dimension>, &Chunk) -> Vec<Hit> + Sync, write: &mut dyn FnMut(&Hit) -> Result<()>) -> Result<(usize, bool)> {
+    let policy = args.scan.claims.policy()?;
+    let regions: Vec<SearchRegion> = if args.path.is_dir() {
+        let world = World::open(&args.path)?;
+        if world.regions().is_empty() {
+            return search_one_by_one(args, keep, &find, write);
+        }
+        world.regions().iter()
+            .filter(|info| !info.is_empty())
+            .map(|info| SearchRegion { dimension: Some(info.dimension), x: info.x, z: info.z, path: info.path.clone() })
+            .collect()
+    } else {
+        let (x, z) = super::region_position(&args.path, &policy)?;
+        vec![SearchRegion { dimension: None, x, z, path: args.path.clone() }]
+    };
+
+    let bar = if args.scan.quiet { ProgressBar::hidden() } else { super::PROGRESS.add(ProgressBar::new(regions.len() as u64)) };
+    bar.set_style(ProgressStyle::with_template("{bar:40} {pos}/{len} regions, {msg}, ETA {eta}")?);
+
+    let mut search = Search::new().order(args.order).with_options(parse_options(args)).only_allowed(&policy).on_progress(|progress| {
+        let seconds = bar.elapsed().as_secs_f64().max(0.001);
+        bar.set_position(progress.regions_done as u64);
+        bar.set_message(format!("{:.0} chunks/s", progress.chunks as f64 / seconds));
+    });
+    if let Some(threads) = args.threads {
+        search = search.threads(threads);
+    }
+    if let Some(limit) = args.limit {
+        search = search.limit(limit);
+    }
+    if let Some(since) = args.scan.modified_since {
+        search = search.modified_since(since);
+    }
+    if args.scan.strict {
+        search = search.strict();
+    }
+    let summary = search.run(&regions, keep, find, write)?;
+    bar.finish_and_clear();
+
+    if summary.skipped > 0 {
+        eprintln!("Skipped {} chunks that couldn't be read, use --strict to stop at the first one", summary.skipped);
+    }
+    super::report_excluded(summary.progress.excluded);
+    Ok((summary.hits, summary.truncated))
+}
+
+// --limit only cuts the output short here, the scan still reads every chunk
+fn search_one_by_one(args: &FindArgs, keep: impl Fn(&BlockType) -> bool, find: &Find, write: &mut dyn FnMut(&Hit) -> Result<()>) -> Result<(usize, bool)> {
+    let (mut found, mut truncated) = (0, false);
+    let mut result = Ok(());
+    super::for_each_chunk_where(&args.path, &args.scan, parse_options(args), keep, |dimension, chunk| {
+        for hit in find(dimension, chunk) {
+            if result.is_err() || truncated {
+                return;
+            }
+            if args.limit.is_some_and(|limit| found >= limit) {
+                truncated = true;
+                return;
+            }
+            result = write(&hit);
+            found += 1;
+        }
+    })?;
+    result?;
+    Ok((found, truncated))
+}
+
+// Heightmaps are only needed to tell the surface from what's underground
+fn parse_options(args: &FindArgs) -> ParseOptions {
+    if args.underground_only || args.surface_only {
+        ParseOptions::for_surface_search()
+    } else {
+        ParseOptions::for_block_search()
+    }
+}
+
+fn find_with_index(args: &FindArgs, keep: impl Fn(&BlockType) -> bool, find: impl Fn(Option<Dimension>, &Chunk) -> Vec<Hit>, write: &mut dyn FnMut(&Hit) -> Result<()>) -> Result<(usize, bool)> {
+    if !args.path.is_dir() {
+        bail!("--index needs a world folder");
+    }
+    let world = World::open(&args.path)?;
+
+    let mut index = WorldIndex::load(&world);
+    let read = index.update(&world).context("Could not update the index")?;
+    if read > 0 {
+        index.save()?;
+        eprintln!("Indexed {read} new or changed chunks, {} in total", index.chunk_count());
+    }
+
+    // The index only knows block names, the palettes of the chunks it points to sort out the rest
+    let hits = index.find(|name| match &args.query {
+        Some(query) => query.could_match_name(name),
+        None => args.block.iter().any(|state| state.name == name),
+    });
+
+    // Hits come sorted by region, so each region is opened once
+    let mut current: Option<((Dimension, i32, i32), Region)> = None;
+    let mut found = 0;
+    for hit in hits {
+        let key = (hit.dimension, hit.region_x, hit.region_z);
+        let region = match &mut current {
+            Some((open, region)) if *open == key => region,
+            _ => {
+                let info = world.region(hit.dimension, hit.region_x, hit.region_z).context("Indexed region is missing")?;
+                let region = Region::open(&info.path).with_context(|| format!("Could not read region {}", info.path.display()))?;
+                &mut current.insert((key, region)).1
+            },
+        };
+
+        let chunk = region.read_chunk(hit.index, &parse_options(args))
+            .and_then(|tag| tag.map(|tag| Chunk::from_nbt_where(tag, &keep)).transpose());
+        match chunk {
+            Ok(Some(chunk)) => for hit in find(Some(hit.dimension), &chunk) {
+                if args.limit.is_some_and(|limit| found >= limit) {
+                    return Ok((found, true));
+                }
+                write(&hit)?;
+                found += 1;
+            },
+            Ok(None) => {},
+            Err(e) if args.scan.strict => return Err(e),
+            Err(e) => log::warn!("Skipping chunk: {e:#}"),
+        }
+    }
+
+    Ok((found, false))
+}
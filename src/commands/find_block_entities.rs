@@ -0,0 +1,36 @@
+use anyhow::Result;
+use clap::Args;
+use std::path::PathBuf;
+
+#[derive(Args)]
+pub struct FindBlockEntitiesArgs {
+    /// World folder or region file (.mca)
+    path: PathBuf,
+    /// Block entity id to look for, e.g. minecraft:chest. Lists every block entity if left out
+    #[arg(long)]
+    id: Vec<String>,
+    #[command(flatten)]
+    scan: super::ScanArgs,
+}
+
+pub fn run(args: FindBlockEntitiesArgs) -> Result<()> {
+    let mut found = 0;
+
+    super::for_each_chunk(&args.path, &args.scan, |dimension, chunk| {
+        for block_entity in chunk.block_entities() {
+            if !args.id.is_empty() && !args.id.iter().any(|id| id == block_entity.id()) {
+                continue;
+            }
+
+            match dimension {
+                Some(dimension) => println!("{:?} {} {}", dimension, block_entity.id(), block_entity.pos()),
+                None => println!("{} {}", block_entity.id(), block_entity.pos()),
+            }
+            found += 1;
+        }
+    })?;
+
+    eprintln!("Found {found} block entities");
+
+    Ok(())
+}
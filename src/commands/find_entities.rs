@@ -0,0 +1,40 @@
+use anyhow::Result;
+use clap::Args;
+use std::path::PathBuf;
+use path_miner::{ World, Dimension, entity::{ Entity, EntityRegion } };
+
+#[derive(Args)]
+pub struct FindEntitiesArgs {
+    /// World folder or entity region file (.mca)
+    path: PathBuf,
+    /// Entity id to look for, e.g. minecraft:villager. Lists every entity if left out
+    #[arg(long)]
+    id: Vec<String>,
+}
+
+pub fn run(args: FindEntitiesArgs) -> Result<()> {
+    let matches = |entity: &Entity| args.id.is_empty() || args.id.iter().any(|id| id == entity.id());
+    let mut found = 0;
+
+    if args.path.is_dir() {
+        let world = World::open(&args.path)?;
+
+        for dimension in Dimension::ALL {
+            for region in world.entity_regions_in(dimension) {
+                for entity in EntityRegion::load(&region.path)?.entities().filter(|entity| matches(entity)) {
+                    println!("{:?} {} {}", dimension, entity.id(), entity.block_pos());
+                    found += 1;
+                }
+            }
+        }
+    } else {
+        for entity in EntityRegion::load(&args.path)?.entities().filter(|entity| matches(entity)) {
+            println!("{} {}", entity.id(), entity.block_pos());
+            found += 1;
+        }
+    }
+
+    eprintln!("Found {found} entities");
+
+    Ok(())
+}
@@ -0,0 +1,52 @@
+use anyhow::Result;
+use clap::Args;
+use std::path::PathBuf;
+
+#[derive(Args)]
+pub struct FindItemsArgs {
+    /// World folder or region file (.mca)
+    path: PathBuf,
+    /// Item id to look for, e.g. minecraft:diamond
+    #[arg(long, required = true)]
+    item: Vec<String>,
+    #[command(flatten)]
+    scan: super::ScanArgs,
+}
+
+pub fn run(args: FindItemsArgs) -> Result<()> {
+    let mut containers = 0;
+    let mut total = 0;
+
+    super::for_each_chunk(&args.path, &args.scan, |dimension, chunk| {
+        for block_entity in chunk.block_entities() {
+            let items = block_entity.items();
+            if items.is_empty() {
+                continue;
+            }
+
+            let mut found_here = false;
+            for id in &args.item {
+                // Shulker boxes and bundles inside the container are counted too
+                let count: i64 = items.iter().map(|item| item.count_of(id)).sum();
+                if count == 0 {
+                    continue;
+                }
+
+                match dimension {
+                    Some(dimension) => println!("{:?} {} {} {} {}", dimension, block_entity.id(), block_entity.pos(), id, count),
+                    None => println!("{} {} {} {}", block_entity.id(), block_entity.pos(), id, count),
+                }
+                found_here = true;
+                total += count;
+            }
+
+            if found_here {
+                containers += 1;
+            }
+        }
+    })?;
+
+    eprintln!("Found {total} items in {containers} containers");
+
+    Ok(())
+}
@@ -0,0 +1,65 @@
+use anyhow::Result;
+use clap::Args;
+use std::{ collections::BTreeMap, path::PathBuf };
+use path_miner::{ World, Dimension, poi::{ Poi, PoiRegion } };
+
+#[derive(Args)]
+pub struct FindPoiArgs {
+    /// World folder or point of interest region file (.mca) from poi/
+    path: PathBuf,
+    /// Type to look for, e.g. nether_portal or armorer. Lists every point of interest if left out
+    #[arg(long = "type", value_name = "TYPE")]
+    kinds: Vec<String>,
+    /// Only look in this dimension of a world
+    #[arg(long)]
+    dimension: Option<Dimension>,
+    /// Only list the workstations, beds and bells no villager has claimed yet
+    #[arg(long)]
+    unclaimed: bool,
+    /// Print how many there are of each type instead of every one
+    #[arg(long)]
+    count: bool,
+}
+
+pub fn run(args: FindPoiArgs) -> Result<()> {
+    let kinds: Vec<String> = args.kinds.iter()
+        .map(|kind| if kind.contains(':') { kind.clone() } else { format!("minecraft:{kind}") })
+        .collect();
+    let matches = |poi: &Poi| (kinds.is_empty() || kinds.iter().any(|kind| kind == poi.kind())) && (!args.unclaimed || poi.free_tickets() > 0);
+    let mut counts: BTreeMap<String, u64> = BTreeMap::new();
+    let mut found = 0;
+
+    let mut report = |dimension: Option<Dimension>, poi: &Poi| {
+        if args.count {
+            *counts.entry(poi.kind().to_string()).or_default() += 1;
+        } else {
+            match dimension {
+                Some(dimension) => println!("{:?} {} {}", dimension, poi.kind(), poi.pos()),
+                None => println!("{} {}", poi.kind(), poi.pos()),
+            }
+        }
+        found += 1;
+    };
+
+    if args.path.is_dir() {
+        let world = World::open(&args.path)?;
+        for dimension in Dimension::ALL.into_iter().filter(|dimension| args.dimension.is_none_or(|wanted| wanted == *dimension)) {
+            for region in world.poi_regions_in(dimension) {
+                for poi in PoiRegion::load(&region.path)?.pois().filter(|poi| matches(poi)) {
+                    report(Some(dimension), poi);
+                }
+            }
+        }
+    } else {
+        for poi in PoiRegion::load(&args.path)?.pois().filter(|poi| matches(poi)) {
+            report(None, poi);
+        }
+    }
+
+    for (kind, count) in &counts {
+        println!("{count:>8} {kind}");
+    }
+    eprintln!("Found {found} points of interest");
+
+    Ok(())
+}
@@ -0,0 +1,69 @@
+use anyhow::Result;
+use clap::Args;
+use std::path::PathBuf;
+use path_miner::{ Dimension, ParseOptions };
+
+#[derive(Args)]
+pub struct FindStructuresArgs {
+    /// World folder or region file (.mca)
+    path: PathBuf,
+    /// Structure to look for, e.g. fortress or minecraft:end_city. Lists every structure if left out
+    #[arg(long = "type", value_name = "TYPE")]
+    kinds: Vec<String>,
+    /// Only look in this dimension of a world
+    #[arg(long)]
+    dimension: Option<Dimension>,
+    /// Also list the pieces each structure is made of
+    #[arg(long)]
+    pieces: bool,
+    #[command(flatten)]
+    scan: super::ScanArgs,
+}
+
+pub fn run(args: FindStructuresArgs) -> Result<()> {
+    // Structures are all in the chunk root, the blocks aren't needed
+    let options = ParseOptions::new()
+        .skip("sections")
+        .skip("Sections")
+        .skip("block_entities")
+        .skip("TileEntities")
+        .skip("Entities")
+        .skip("Heightmaps");
+    let mut found = 0;
+
+    super::for_each_chunk_with(&args.path, &args.scan, options, |dimension, chunk| {
+        if args.dimension.is_some() && dimension != args.dimension {
+            return;
+        }
+        let structures = match chunk.structures() {
+            Ok(structures) => structures,
+            Err(e) => {
+                log::warn!("Skipping structures: {e:#}");
+                return;
+            },
+        };
+
+        for start in structures.starts() {
+            if !args.kinds.is_empty() && !args.kinds.iter().any(|kind| start.is_kind(kind)) {
+                continue;
+            }
+
+            let (min, max) = start.bounds();
+            match dimension {
+                Some(dimension) => println!("{:?} {} {} to {}", dimension, start.kind(), min, max),
+                None => println!("{} {} to {}", start.kind(), min, max),
+            }
+            if args.pieces {
+                for piece in start.pieces() {
+                    let (min, max) = piece.bounds();
+                    println!("  {} {} to {}", piece.id(), min, max);
+                }
+            }
+            found += 1;
+        }
+    })?;
+
+    eprintln!("Found {found} structures");
+
+    Ok(())
+}
@@ -0,0 +1,39 @@
+use anyhow::{ Result, Context, bail };
+use clap::Args;
+use std::path::PathBuf;
+use path_miner::region::{ chunk_position, put_chunk };
+
+#[derive(Args)]
+pub struct InjectChunkArgs {
+    /// Region file (.mca) to change in place, created if it doesn't exist
+    region: PathBuf,
+    /// Chunk x coordinate
+    #[arg(allow_hyphen_values = true)]
+    x: i32,
+    /// Chunk z coordinate
+    #[arg(allow_hyphen_values = true)]
+    z: i32,
+    /// NBT file with the chunk, gzipped or not, e.g. from extract-chunk
+    chunk: PathBuf,
+    /// Store the chunk even if its own coordinates say it belongs somewhere else
+    #[arg(long)]
+    force: bool,
+}
+
+pub fn run(args: InjectChunkArgs) -> Result<()> {
+    let index = super::chunk_index(&args.region, args.x, args.z)?;
+    let chunk = super::load_nbt_file(&args.chunk)?;
+
+    // The game reads chunks by where they're stored but trusts their own coordinates
+    match chunk_position(&chunk.payload) {
+        Some(position) if position == (args.x, args.z) => {},
+        Some((x, z)) if !args.force => bail!("{} holds chunk ({x}, {z}), pass --force to store it as ({}, {}) anyway", args.chunk.display(), args.x, args.z),
+        None if !args.force => bail!("{} has no chunk position, pass --force to store it anyway", args.chunk.display()),
+        _ => {},
+    }
+
+    put_chunk(&args.region, index, &chunk).with_context(|| format!("Could not write chunk to {}", args.region.display()))?;
+    eprintln!("Stored chunk ({}, {}) in {}", args.x, args.z, args.region.display());
+
+    Ok(())
+}
@@ -0,0 +1,207 @@
+use anyhow::{ Result, Context, anyhow, bail };
+use clap::Args;
+use indicatif::{ ProgressBar, ProgressStyle };
+use std::{ path::{ Path, PathBuf }, time::{ Duration, Instant } };
+use path_miner::{ World, Dimension, chunk::Chunk, claims::ChunkPolicy, render::{ BlockColors, Image, MapLayer, TopDownRenderer, Rgba, SLIME_TINT, heat_color, inhabited_fraction, parse_hex_color }, slime::slime_chunks_near, tiles::TileMap };
+
+#[derive(Args)]
+pub struct MapArgs {
+    /// World folder or region file (.mca)
+    path: PathBuf,
+    /// Output PNG
+    #[arg(short, long, required_unless_present = "tiles_only")]
+    output: Option<PathBuf>,
+    #[arg(long, default_value = "overworld")]
+    dimension: Dimension,
+    /// TOML or JSON file mapping block or biome names to colors
+    #[arg(long, value_name = "FILE")]
+    palette: Option<PathBuf>,
+    /// Override a block color, e.g. minecraft:stone=#7d7d7d, or a biome color with --biomes
+    #[arg(long = "color", value_parser = parse_color_override)]
+    colors: Vec<(String, Rgba)>,
+    /// Color the map by biome instead of by block (1.18+ worlds)
+    #[arg(long)]
+    biomes: bool,
+    /// Tint the slime chunks green
+    #[arg(long)]
+    slime_chunks: bool,
+    /// Tint every chunk by how long players have spent near it, from blue to red at 50 hours
+    #[arg(long)]
+    inhabited: bool,
+    /// World seed for --slime-chunks, read from level.dat if left out
+    #[arg(long, allow_hyphen_values = true)]
+    seed: Option<i64>,
+    /// Render one region at a time into this folder first, skipping the regions an earlier run already rendered and that haven't changed since
+    #[arg(long, value_name = "DIR", conflicts_with = "inhabited")]
+    tiles: Option<PathBuf>,
+    /// Leave the tiles with zoomed out levels of them for map viewers instead of writing one PNG
+    #[arg(long, requires = "tiles")]
+    tiles_only: bool,
+    /// Stop starting new tiles after this many seconds, a later run carries on from there
+    #[arg(long, value_name = "SECONDS", requires = "tiles")]
+    budget: Option<u64>,
+    #[command(flatten)]
+    claims: super::ClaimArgs,
+}
+
+fn parse_color_override(s: &str) -> Result<(String, Rgba)> {
+    let (name, color) = s.split_once('=').ok_or_else(|| anyhow!("Expected name=#rrggbb"))?;
+    let color = parse_hex_color(color).ok_or_else(|| anyhow!("Invalid color \"{color}\""))?;
+    Ok((name.to_string(), color))
+}
+
+pub fn run(args: MapArgs) -> Result<()> {
+    let (mut colors, layer) = if args.biomes {
+        (BlockColors::biomes(), MapLayer::Biomes)
+    } else {
+        (BlockColors::default(), MapLayer::Blocks)
+    };
+    if let Some(palette) = &args.palette {
+        colors.load(palette)?;
+    }
+    for (name, color) in &args.colors {
+        colors.set(name, *color);
+    }
+
+    let policy = args.claims.policy()?;
+    if let Some(dir) = &args.tiles {
+        return render_tiles(&args, dir, &colors, layer, &policy);
+    }
+    let image = if args.path.is_dir() {
+        let world = World::open(&args.path)?;
+        let Some((min_chunk, max_chunk)) = world.chunk_bounds(args.dimension) else {
+            bail!("World has no regions in {:?}", args.dimension);
+        };
+
+        let mut renderer = TopDownRenderer::new(&colors, layer, min_chunk, max_chunk);
+        if args.slime_chunks {
+            tint_slime_chunks(&mut renderer, world_seed(&args, &world)?, min_chunk, max_chunk);
+        }
+        let mut inhabited = Vec::new();
+        let mut chunks = world.chunks_in(args.dimension).only_allowed(&policy);
+        for chunk in &mut chunks {
+            match chunk {
+                Ok((_, chunk)) => {
+                    renderer.add_chunk(&chunk);
+                    inhabited.push(((chunk.x(), chunk.z()), chunk.inhabited_time()));
+                },
+                Err(e) => log::warn!("Skipping chunk: {e:#}"),
+            }
+        }
+        super::report_excluded(chunks.progress().excluded);
+        if args.inhabited {
+            tint_inhabited(&mut renderer, inhabited);
+        }
+        eprintln!("Map origin is block ({}, {})", min_chunk.0 * 16, min_chunk.1 * 16);
+        renderer.finish()
+    } else {
+        let mut chunks = super::load_region_chunks(&args.path, None)?;
+        let read = chunks.len();
+        chunks.retain(|chunk| policy.allows(None, chunk.x(), chunk.z()));
+        super::report_excluded(read - chunks.len());
+        if chunks.is_empty() {
+            bail!("Region has no chunks");
+        }
+
+        let min_chunk = (chunks.iter().map(Chunk::x).min().unwrap(), chunks.iter().map(Chunk::z).min().unwrap());
+        let max_chunk = (chunks.iter().map(Chunk::x).max().unwrap(), chunks.iter().map(Chunk::z).max().unwrap());
+
+        let mut renderer = TopDownRenderer::new(&colors, layer, min_chunk, max_chunk);
+        if args.slime_chunks {
+            let seed = args.seed.ok_or_else(|| anyhow!("Pass --seed to mark slime chunks on the map of a region file"))?;
+            tint_slime_chunks(&mut renderer, seed, min_chunk, max_chunk);
+        }
+        for chunk in &chunks {
+            renderer.add_chunk(chunk);
+        }
+        if args.inhabited {
+            tint_inhabited(&mut renderer, chunks.iter().map(|chunk| ((chunk.x(), chunk.z()), chunk.inhabited_time())).collect());
+        }
+        eprintln!("Map origin is block ({}, {})", min_chunk.0 * 16, min_chunk.1 * 16);
+        renderer.finish()
+    };
+
+    save(&image, args.output.as_deref().context("--output is needed unless --tiles-only")?)
+}
+
+fn save(image: &Image, output: &Path) -> Result<()> {
+    image.save_png(output)?;
+    eprintln!("Wrote {}x{} map to {}", image.width, image.height, output.display());
+    Ok(())
+}
+
+fn world_seed(args: &MapArgs, world: &World) -> Result<i64> {
+    if args.dimension != Dimension::Overworld {
+        bail!("Only the overworld has slime chunks");
+    }
+    match args.seed {
+        Some(seed) => Ok(seed),
+        None => world.level_dat()?.seed().ok_or_else(|| anyhow!("level.dat has no seed, pass --seed")),
+    }
+}
+
+fn render_tiles(args: &MapArgs, dir: &Path, colors: &BlockColors, layer: MapLayer, policy: &ChunkPolicy) -> Result<()> {
+    if !args.path.is_dir() {
+        bail!("--tiles needs a world folder");
+    }
+    let world = World::open(&args.path)?;
+    let mut tiles = TileMap::new(&world, args.dimension, dir, colors)?.layer(layer).only_allowed(policy);
+    if args.slime_chunks {
+        tiles = tiles.slime_chunks(world_seed(args, &world)?);
+    }
+
+    let started = Instant::now();
+    let bar = super::PROGRESS.add(ProgressBar::new(0));
+    bar.set_style(ProgressStyle::with_template("{bar:40} {pos}/{len} tiles, ETA {eta}")?);
+    let progress = tiles.render(|progress| {
+        bar.set_length(progress.total as u64);
+        bar.set_position((progress.rendered + progress.current) as u64);
+        args.budget.is_none_or(|budget| started.elapsed() < Duration::from_secs(budget))
+    })?;
+    bar.finish_and_clear();
+
+    eprintln!("Rendered {} tiles, {} were up to date", progress.rendered, progress.current);
+    if !progress.is_done() {
+        eprintln!("Out of time with {} tiles left, run again to carry on", progress.total - progress.rendered - progress.current);
+        return Ok(());
+    }
+
+    if args.tiles_only {
+        let levels = tiles.build_levels()?;
+        eprintln!("Wrote {levels} zoom levels to {}, tile x.z of level n starts at block (x * 512 * 2^n, z * 512 * 2^n)", dir.display());
+        return Ok(());
+    }
+    let (x, z) = tiles.min_chunk();
+    eprintln!("Map origin is block ({}, {})", x * 16, z * 16);
+    save(&tiles.stitch()?, args.output.as_deref().context("--output is needed unless --tiles-only")?)
+}
+
+fn tint_slime_chunks(renderer: &mut TopDownRenderer, seed: i64, min_chunk: (i32, i32), max_chunk: (i32, i32)) {
+    let center = ((min_chunk.0 + max_chunk.0) / 2, (min_chunk.1 + max_chunk.1) / 2);
+    let radius = (max_chunk.0 - min_chunk.0).max(max_chunk.1 - min_chunk.1) / 2 + 1;
+    renderer.tint_chunks(slime_chunks_near(seed, center, radius), SLIME_TINT);
+}
+
+// Chunks nobody has spent any time near are left as they are
+fn tint_inhabited(renderer: &mut TopDownRenderer, mut chunks: Vec<((i32, i32), i64)>) {
+    chunks.retain(|(_, ticks)| *ticks > 0);
+    for (chunk, ticks) in &chunks {
+        renderer.tint_chunks([*chunk], heat_color(inhabited_fraction(*ticks)));
+    }
+
+    chunks.sort_by_key(|(_, ticks)| std::cmp::Reverse(*ticks));
+    eprintln!("{} chunks have been inhabited, most of all:", chunks.len());
+    for ((x, z), ticks) in chunks.iter().take(10) {
+        eprintln!("  chunk ({x}, {z}) at block ({}, {}): {}", x * 16, z * 16, format_ticks(*ticks));
+    }
+}
+
+// 20 ticks a second
+fn format_ticks(ticks: i64) -> String {
+    let minutes = ticks as f64 / 1200.0;
+    if minutes < 60.0 {
+        format!("{minutes:.1} minutes")
+    } else {
+        format!("{:.1} hours", minutes / 60.0)
+    }
+}
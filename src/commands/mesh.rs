@@ -0,0 +1,52 @@
+use anyhow::{ Result, bail };
+use clap::Args;
+use std::path::PathBuf;
+use path_miner::{ World, Dimension, BlockPos, mesh::greedy_mesh, model::{ write_obj, write_glb }, render::BlockColors };
+
+#[derive(Args)]
+pub struct MeshArgs {
+    /// World folder
+    world: PathBuf,
+    /// One corner of the box as x,y,z
+    #[arg(long, allow_hyphen_values = true)]
+    from: BlockPos,
+    /// The opposite corner as x,y,z
+    #[arg(long, allow_hyphen_values = true)]
+    to: BlockPos,
+    /// Output .obj or .glb file. OBJ files get their materials in a .mtl next to them.
+    #[arg(short, long)]
+    output: PathBuf,
+    #[arg(long, default_value = "overworld")]
+    dimension: Dimension,
+    /// TOML or JSON file mapping block names to colors
+    #[arg(long, value_name = "FILE")]
+    palette: Option<PathBuf>,
+}
+
+pub fn run(args: MeshArgs) -> Result<()> {
+    let extension = args.output.extension().and_then(|extension| extension.to_str()).unwrap_or_default().to_ascii_lowercase();
+    if extension != "obj" && extension != "glb" {
+        bail!("Output needs to end in .obj or .glb");
+    }
+
+    let mut colors = BlockColors::default();
+    if let Some(palette) = &args.palette {
+        colors.load(palette)?;
+    }
+
+    let world = World::open(&args.world)?;
+    let min = BlockPos::new(args.from.x.min(args.to.x), args.from.y.min(args.to.y), args.from.z.min(args.to.z));
+    let max = BlockPos::new(args.from.x.max(args.to.x), args.from.y.max(args.to.y), args.from.z.max(args.to.z));
+    let view = world.view(args.dimension, (min.chunk_x(), min.chunk_z()), (max.chunk_x(), max.chunk_z()))?;
+
+    let quads = greedy_mesh(&view, min, max);
+    if extension == "obj" {
+        write_obj(&args.output, &quads, &colors, min)?;
+    } else {
+        write_glb(&args.output, &quads, &colors, min)?;
+    }
+
+    eprintln!("Wrote {} quads to {}, with block {} at the origin", quads.len(), args.output.display(), min);
+
+    Ok(())
+}
@@ -0,0 +1,311 @@
+pub mod dump;
+pub mod explore;
+pub mod diff;
+#[cfg(feature = "tui")]
+pub mod browse;
+pub mod palette;
+pub mod find;
+pub mod find_entities;
+pub mod find_block_entities;
+pub mod find_items;
+pub mod find_poi;
+pub mod find_structures;
+pub mod end_gateways;
+pub mod signs;
+pub mod players;
+pub mod check;
+pub mod prune;
+pub mod compact;
+pub mod patch;
+pub mod extract_chunk;
+pub mod inject_chunk;
+pub mod copy_chunks;
+pub mod repair;
+pub mod replace;
+pub mod analyze;
+pub mod stats;
+pub mod path;
+pub mod tour;
+pub mod plan_branch_mine;
+pub mod slime_chunks;
+pub mod caves;
+pub mod complexity;
+pub mod spawnable;
+pub mod dark_spots;
+pub mod map;
+pub mod report;
+pub mod status_map;
+pub mod slice;
+pub mod export_schem;
+pub mod mesh;
+pub mod replay;
+#[cfg(feature = "viewer")]
+pub mod view;
+
+use anyhow::{ Result, Context, anyhow, bail };
+use clap::Args;
+use flate2::read::GzDecoder;
+use indicatif::{ MultiProgress, ProgressBar, ProgressStyle };
+use std::{ fs, io::Read, path::{ Path, PathBuf }, sync::LazyLock };
+use path_miner::{ Tag, World, Dimension, BlockPos, ParseOptions, actions::ActionOptions, cache::{ CacheConfig, EvictionPolicy }, chunk::{ BlockType, Chunk }, claims::{ Claims, ChunkPolicy }, region::{ chunk_at_index, chunk_index_in_region, chunk_to_region_coord, parse_region_file_name, read_region, Region } };
+
+// Progress bars are drawn through this, so log messages can be printed above them
+pub static PROGRESS: LazyLock<MultiProgress> = LazyLock::new(MultiProgress::new);
+
+// Gives the box back as its lowest and highest corner
+pub fn parse_box(s: &str) -> Result<(BlockPos, BlockPos)> {
+    let values: Vec<i32> = s.split(',').map(|v| v.trim().parse()).collect::<Result<_, _>>().map_err(|_| anyhow!("Expected x1,y1,z1,x2,y2,z2"))?;
+    match values.as_slice() {
+        [x1, y1, z1, x2, y2, z2] => Ok((
+            BlockPos::new(*x1.min(x2), *y1.min(y2), *z1.min(z2)),
+            BlockPos::new(*x1.max(x2), *y1.max(y2), *z1.max(z2)),
+        )),
+        _ => bail!("Expected x1,y1,z1,x2,y2,z2"),
+    }
+}
+
+pub fn load_chunks(region: &Path, index: Option<usize>) -> Result<Vec<Tag>> {
+    let mut chunks = read_region(region).with_context(|| format!("Could not read region {}", region.display()))?;
+
+    if let Some(index) = index {
+        anyhow::ensure!(index < chunks.len(), "Region only has {} chunks", chunks.len());
+        chunks = vec![chunks.swap_remove(index)];
+    }
+
+    Ok(chunks)
+}
+
+// Like load_chunks, with the chunks parsed. Chunks that can't be read are
+// logged and left out.
+pub fn load_region_chunks(region: &Path, index: Option<usize>) -> Result<Vec<Chunk>> {
+    let mut chunks = Region::open(region).with_context(|| format!("Could not read region {}", region.display()))?
+        .read_chunks(&ParseOptions::default())
+        .log_failures();
+
+    if let Some(index) = index {
+        anyhow::ensure!(index < chunks.len(), "Region only has {} chunks", chunks.len());
+        chunks = vec![chunks.swap_remove(index)];
+    }
+
+    Ok(chunks)
+}
+
+// Index of the chunk at chunk coordinates x and z in a region file. Regions
+// named r.<x>.<z>.mca have to be the ones the chunk is in.
+pub fn chunk_index(region: &Path, x: i32, z: i32) -> Result<usize> {
+    let position = region.file_name().and_then(|name| name.to_str()).and_then(parse_region_file_name);
+    if let Some((region_x, region_z)) = position {
+        let (chunk_region_x, chunk_region_z) = (chunk_to_region_coord(x), chunk_to_region_coord(z));
+        anyhow::ensure!((chunk_region_x, chunk_region_z) == (region_x, region_z),
+            "Chunk ({x}, {z}) is in r.{chunk_region_x}.{chunk_region_z}.mca, not {}", region.display());
+    }
+    Ok(chunk_index_in_region(x, z))
+}
+
+// Reads a single NBT document like level.dat or a schematic, gzipped or not
+pub fn load_nbt_file(path: &Path) -> Result<Tag> {
+    let bytes = fs::read(path).with_context(|| format!("Could not read {}", path.display()))?;
+    let bytes = if bytes.starts_with(&[0x1f, 0x8b]) {
+        let mut decompressed = Vec::new();
+        GzDecoder::new(bytes.as_slice()).read_to_end(&mut decompressed)
+            .with_context(|| format!("Could not decompress {}", path.display()))?;
+        decompressed
+    } else {
+        bytes
+    };
+    Tag::parse(&mut bytes.iter()).with_context(|| format!("Could not parse {}", path.display()))
+}
+
+// Which chunks for_each_chunk visits and what happens to corrupt ones, shared
+// by the commands that scan every chunk
+#[derive(Args, Default)]
+pub struct ScanArgs {
+    /// Only read chunks saved at or after this Unix timestamp
+    #[arg(long, value_name = "EPOCH")]
+    modified_since: Option<u32>,
+    /// Stop at the first chunk that can't be read instead of skipping it
+    #[arg(long)]
+    strict: bool,
+    /// Don't show a progress bar while scanning a world
+    #[arg(short, long)]
+    quiet: bool,
+    #[command(flatten)]
+    claims: ClaimArgs,
+}
+
+// Claims files limiting the chunks a command reads or changes
+#[derive(Args, Default)]
+pub struct ClaimArgs {
+    /// Only touch the chunks in this claims file, lines of "chunk x z" or "box x1 z1 x2 z2" with an optional dimension after
+    #[arg(long, value_name = "FILE")]
+    include: Option<PathBuf>,
+    /// Never touch the chunks in this claims file, even where --include has them
+    #[arg(long, value_name = "FILE")]
+    exclude: Option<PathBuf>,
+}
+
+impl ClaimArgs {
+    pub fn policy(&self) -> Result<ChunkPolicy> {
+        let mut policy = ChunkPolicy::new();
+        if let Some(path) = &self.include {
+            policy = policy.include(Claims::load(path)?);
+        }
+        if let Some(path) = &self.exclude {
+            policy = policy.exclude(Claims::load(path)?);
+        }
+        Ok(policy)
+    }
+}
+
+// Where the chunks of a region file on its own are, which the policy needs to
+// know unless it allows every chunk
+pub fn region_position(path: &Path, policy: &ChunkPolicy) -> Result<(i32, i32)> {
+    match path.file_name().and_then(|name| name.to_str()).and_then(parse_region_file_name) {
+        Some(position) => Ok(position),
+        None if policy.is_empty() => Ok((0, 0)),
+        None => bail!("--include and --exclude need the region file to be named r.<x>.<z>.mca"),
+    }
+}
+
+pub fn report_excluded(excluded: usize) {
+    if excluded > 0 {
+        eprintln!("Left out {excluded} chunks excluded by --include/--exclude");
+    }
+}
+
+#[derive(Args)]
+pub struct ActionArgs {
+    /// Print what to do along the route, mine, place, torch and move, instead of the steps
+    #[arg(long)]
+    actions: bool,
+    /// Put a torch down every this many steps, with --actions
+    #[arg(long, value_name = "N", requires = "actions")]
+    torch_every: Option<u32>,
+}
+
+impl ActionArgs {
+    pub fn options(&self) -> Option<ActionOptions> {
+        if !self.actions {
+            return None;
+        }
+        let mut options = ActionOptions::new();
+        if let Some(steps) = self.torch_every {
+            options = options.torch_every(steps);
+        }
+        Some(options)
+    }
+}
+
+// A chunk cache for the areas a command reads, and recording what it looks up
+#[derive(Args)]
+pub struct CacheArgs {
+    /// Keep up to this many chunks between the areas the command reads
+    #[arg(long, value_name = "CHUNKS", default_value_t = 0)]
+    cache_size: usize,
+    /// Which chunks the cache pushes out first, lru or 2q
+    #[arg(long, value_name = "POLICY", default_value = "lru")]
+    cache_policy: EvictionPolicy,
+    /// Also read the chunks this many chunks around every chunk the cache doesn't have
+    #[arg(long, value_name = "CHUNKS", default_value_t = 0)]
+    prefetch_radius: u32,
+    /// Write every chunk looked up, and whether the cache had it, to a file for the replay command
+    #[arg(long, value_name = "FILE")]
+    record_accesses: Option<PathBuf>,
+}
+
+impl CacheArgs {
+    pub fn open(&self, path: &Path) -> Result<World> {
+        let config = CacheConfig::new(self.cache_size, self.cache_policy).prefetch_radius(self.prefetch_radius);
+        let mut world = World::open(path)?.with_cache(config);
+        if self.record_accesses.is_some() {
+            world = world.record_accesses();
+        }
+        Ok(world)
+    }
+
+    // Reports on the cache and writes the accesses out, once the command is done
+    pub fn finish(&self, world: &World) -> Result<()> {
+        if self.cache_size > 0 {
+            eprintln!("Chunk cache: {}", world.cache_stats());
+        }
+        if let (Some(path), Some(log)) = (&self.record_accesses, world.access_log()) {
+            log.save(path)?;
+            eprintln!("Wrote {} chunk accesses to {}", log.accesses.len(), path.display());
+        }
+        Ok(())
+    }
+}
+
+// Calls f with every chunk of a world folder, or of a single region file, in
+// which case there's no dimension to pass along
+pub fn for_each_chunk(path: &Path, scan: &ScanArgs, f: impl FnMut(Option<Dimension>, &Chunk)) -> Result<()> {
+    for_each_chunk_with(path, scan, ParseOptions::default(), f)
+}
+
+pub fn for_each_chunk_with(path: &Path, scan: &ScanArgs, options: ParseOptions, f: impl FnMut(Option<Dimension>, &Chunk)) -> Result<()> {
+    for_each_chunk_where(path, scan, options, |_| true, f)
+}
+
+// Chunks only come with the sections that have a palette entry keep wants,
+// for searches that can tell from the palette whether a section is of use
+pub fn for_each_chunk_where(path: &Path, scan: &ScanArgs, options: ParseOptions, keep: impl Fn(&BlockType) -> bool, mut f: impl FnMut(Option<Dimension>, &Chunk)) -> Result<()> {
+    let mut skipped = 0;
+    let policy = scan.claims.policy()?;
+    let excluded;
+
+    if path.is_dir() {
+        let world = World::open(path)?;
+
+        let bar = if scan.quiet { ProgressBar::hidden() } else { PROGRESS.add(ProgressBar::new(world.regions().len() as u64)) };
+        bar.set_style(ProgressStyle::with_template("{bar:40} {pos}/{len} regions, {msg}, ETA {eta}")?);
+
+        let mut chunks = world.chunks().with_options(options).only_sections_with(&keep).only_allowed(&policy).on_progress(|progress| {
+            let seconds = bar.elapsed().as_secs_f64().max(0.001);
+            bar.set_position(progress.regions_done as u64);
+            bar.set_message(format!("{:.0} chunks/s", progress.chunks as f64 / seconds));
+        });
+        if let Some(since) = scan.modified_since {
+            chunks = chunks.modified_since(since);
+        }
+
+        for chunk in &mut chunks {
+            match chunk {
+                Ok((dimension, chunk)) => f(Some(dimension), &chunk),
+                Err(e) if scan.strict => return Err(e),
+                Err(e) => {
+                    log::warn!("Skipping chunk: {e:#}");
+                    skipped += 1;
+                },
+            }
+        }
+        excluded = chunks.progress().excluded;
+        bar.finish_and_clear();
+    } else {
+        let (region_x, region_z) = region_position(path, &policy)?;
+        let allowed = |index| {
+            let (x, z) = chunk_at_index(region_x, region_z, index);
+            policy.allows(None, x, z)
+        };
+        let mut region = Region::open(path).with_context(|| format!("Could not read region {}", path.display()))?;
+        excluded = (0..1024).filter(|index| region.has_chunk(*index) && !allowed(*index)).count();
+        let report = region.read_chunks_at(&options, scan.modified_since, allowed, &keep);
+
+        let chunks = if scan.strict {
+            report.into_result().with_context(|| format!("Could not read region {}", path.display()))?
+        } else {
+            skipped += report.failed.len();
+            report.log_failures()
+        };
+
+        for chunk in chunks {
+            f(None, &chunk);
+        }
+    }
+
+    if skipped > 0 {
+        eprintln!("Skipped {skipped} chunks that couldn't be read, use --strict to stop at the first one");
+    }
+    report_excluded(excluded);
+
+    Ok(())
+}
@@ -0,0 +1,25 @@
+use anyhow::Result;
+use clap::Args;
+use std::path::PathBuf;
+
+#[derive(Args)]
+pub struct PaletteArgs {
+    /// Region file (.mca)
+    region: PathBuf,
+    /// Only list the chunk at this position in the region
+    #[arg(long)]
+    chunk: Option<usize>,
+}
+
+pub fn run(args: PaletteArgs) -> Result<()> {
+    for chunk in super::load_region_chunks(&args.region, args.chunk)? {
+        println!("Chunk ({}, {}):", chunk.x(), chunk.z());
+
+        for section in chunk.sections() {
+            let names: Vec<&str> = section.palette().iter().map(|block| block.name.as_str()).collect();
+            println!("  Section {}: {}", section.y(), names.join(", "));
+        }
+    }
+
+    Ok(())
+}
@@ -0,0 +1,97 @@
+use anyhow::{ Result, Context, anyhow, bail };
+use clap::Args;
+use std::{ fs, path::{ Path, PathBuf } };
+use path_miner::{ TagPayload, json::json_to_payload, region::edit_chunks, snbt::parse_snbt };
+
+#[derive(Args)]
+pub struct PatchArgs {
+    /// Region file (.mca) to change in place
+    region: PathBuf,
+    /// Patch the chunk at this position in the region, can be repeated
+    #[arg(long, required_unless_present = "all")]
+    chunk: Vec<usize>,
+    /// Patch every chunk of the region
+    #[arg(long, conflicts_with = "chunk")]
+    all: bool,
+    /// SNBT compound to merge into each chunk, e.g. '{Status:"minecraft:full"}'
+    #[arg(long, value_name = "SNBT")]
+    merge: Vec<String>,
+    /// Merge the compound in this file, JSON if it ends in .json and SNBT otherwise
+    #[arg(long, value_name = "FILE")]
+    merge_file: Vec<PathBuf>,
+    /// Remove the tag at this path before merging, e.g. 'block_entities[0]'
+    #[arg(long, value_name = "PATH")]
+    remove: Vec<String>,
+    /// Only print what would change
+    #[arg(long)]
+    dry_run: bool,
+}
+
+pub fn run(args: PatchArgs) -> Result<()> {
+    let mut patches = Vec::new();
+    for snbt in &args.merge {
+        patches.push(parse_snbt(snbt).with_context(|| format!("Could not parse {snbt}"))?);
+    }
+    for path in &args.merge_file {
+        patches.push(load_patch(path).with_context(|| format!("Could not read patch {}", path.display()))?);
+    }
+    if patches.iter().any(|patch| !matches!(patch, TagPayload::Compound(_))) {
+        bail!("Patches have to be compounds, they're merged into the chunk's root");
+    }
+    if patches.is_empty() && args.remove.is_empty() {
+        bail!("Nothing to do, pass --merge, --merge-file or --remove");
+    }
+
+    let indices: Vec<usize> = if args.all { (0..1024).collect() } else { args.chunk.clone() };
+    if let Some(index) = indices.iter().find(|&&index| index >= 1024) {
+        bail!("Chunk index {index} is outside the region");
+    }
+
+    let (mut changed, mut not_removed) = (0, 0);
+    let written = edit_chunks(&args.region, &indices, |index, chunk| {
+        let before = chunk.clone();
+
+        // Removals go first so they can clear out what a patch replaces
+        for path in &args.remove {
+            if let Err(e) = chunk.payload.remove_path(path) {
+                log::info!("Chunk {index}: not removing {path}, {e}");
+                not_removed += 1;
+            }
+        }
+        for patch in &patches {
+            chunk.payload.merge(patch.clone());
+        }
+
+        let diffs = before.diff(chunk);
+        if diffs.is_empty() {
+            return Ok(false);
+        }
+        println!("Chunk {index}:");
+        for diff in &diffs {
+            println!("  {diff}");
+        }
+        changed += 1;
+        Ok(!args.dry_run)
+    }).with_context(|| format!("Could not patch {}", args.region.display()))?;
+
+    if not_removed > 0 {
+        eprintln!("{not_removed} paths to remove weren't in their chunk, use -v to see which");
+    }
+    if args.dry_run {
+        eprintln!("Would change {changed} chunks");
+    } else {
+        eprintln!("Changed {written} chunks");
+    }
+
+    Ok(())
+}
+
+fn load_patch(path: &Path) -> Result<TagPayload> {
+    let text = fs::read_to_string(path)?;
+    if path.extension().is_some_and(|extension| extension == "json") {
+        let value: serde_json::Value = serde_json::from_str(&text)?;
+        json_to_payload(&value).ok_or_else(|| anyhow!("JSON with nulls or mixed arrays has no NBT form"))
+    } else {
+        Ok(parse_snbt(&text)?)
+    }
+}
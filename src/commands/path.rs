@@ -0,0 +1,205 @@
+use anyhow::{ Result, bail };
+use clap::Args;
+use std::{ fs, path::PathBuf };
+use path_miner::{ World, Dimension, BlockPos, actions::route_actions, baritone::goto_commands, builds::{ ProtectBuilds, Severity, build_damage }, caves::{ PreferCaves, find_caves }, flooding::{ FloodResponse, find_dry_route, seal_floods, simulate_floods }, pathfinding::Pathfinder, costs::{ CostConfig, CostModel, DefaultCosts }, mcfunction::{ RouteMarker, flood_commands, route_commands, write_mcfunction }, portals::{ LegOptions, find_portals, find_route_through_portals } };
+
+#[derive(Args)]
+pub struct PathArgs {
+    /// World folder
+    world: PathBuf,
+    /// Start position as x,y,z
+    #[arg(long)]
+    from: BlockPos,
+    /// Goal position as x,y,z
+    #[arg(long)]
+    to: BlockPos,
+    #[arg(long, default_value = "overworld")]
+    dimension: Dimension,
+    /// Chunks loaded around the start and goal
+    #[arg(long, default_value_t = 2)]
+    margin: i32,
+    /// Also write the route as a .mcfunction file to show it in game
+    #[arg(long, value_name = "FILE")]
+    mcfunction: Option<PathBuf>,
+    /// How the function shows the route, particles or blocks
+    #[arg(long, default_value = "particles")]
+    marker: RouteMarker,
+    /// Also write the route as Baritone #goto commands, one per line
+    #[arg(long, value_name = "FILE")]
+    baritone: Option<PathBuf>,
+    /// TOML file with dig costs per block, tool speeds and a penalty for going up or down
+    #[arg(long, value_name = "FILE")]
+    costs: Option<PathBuf>,
+    /// Go around lava, water, falling blocks and drops where it doesn't cost too much, instead of only warning about them
+    #[arg(long)]
+    avoid_hazards: bool,
+    /// Keep from digging under blocks that look player placed, forbid or penalize it
+    #[arg(long, value_name = "SEVERITY")]
+    protect_builds: Option<Severity>,
+    /// How far above the tunnel to look for builds to protect
+    #[arg(long, default_value_t = 3, requires = "protect_builds")]
+    build_distance: u32,
+    /// What to do about lava and water that would pour into the tunnel: plan around it (reject), make it cost more (penalize) or block it off (seal)
+    #[arg(long, value_name = "RESPONSE")]
+    floods: Option<FloodResponse>,
+    /// Keep to the caves around the route where it can
+    #[arg(long)]
+    prefer_caves: bool,
+    #[command(flatten)]
+    actions: super::ActionArgs,
+    #[command(flatten)]
+    cache: super::CacheArgs,
+    /// Also try going through the other dimension by the nether portals the world has, taking that if it's shorter
+    #[arg(long, conflicts_with_all = ["actions", "prefer_caves", "floods"])]
+    portals: bool,
+}
+
+pub fn run(args: PathArgs) -> Result<()> {
+    let world = args.cache.open(&args.world)?;
+    let costs = load_costs(args.costs.as_ref())?;
+    if args.portals {
+        run_through_portals(&world, &args, &*costs)?;
+        return args.cache.finish(&world);
+    }
+
+    let min_chunk = (args.from.chunk_x().min(args.to.chunk_x()) - args.margin, args.from.chunk_z().min(args.to.chunk_z()) - args.margin);
+    let max_chunk = (args.from.chunk_x().max(args.to.chunk_x()) + args.margin, args.from.chunk_z().max(args.to.chunk_z()) + args.margin);
+    let view = world.view(args.dimension, min_chunk, max_chunk)?;
+
+    let prefer_caves;
+    let costs: &dyn CostModel = if args.prefer_caves {
+        let caves = find_caves(&view, 1);
+        eprintln!("Found {} caves", caves.len());
+        prefer_caves = PreferCaves::new(&*costs, &caves);
+        &prefer_caves
+    } else {
+        &*costs
+    };
+
+    let mut pathfinder = Pathfinder::new(&view).with_costs(costs);
+    if args.avoid_hazards {
+        pathfinder = pathfinder.avoid_hazards();
+    }
+    if let Some(protect) = args.protect() {
+        pathfinder = pathfinder.protect_builds(protect);
+    }
+    let found = match args.floods {
+        Some(response) => find_dry_route(&mut pathfinder, &view, args.from, args.to, response),
+        None => pathfinder.find_route(args.from, args.to).map(|route| {
+            let floods = simulate_floods(&view, &route);
+            (route, floods)
+        }),
+    };
+    let Some((route, floods)) = found else {
+        if args.floods == Some(FloodResponse::Reject) {
+            bail!("No route found from {} to {} that keeps lava and water out", args.from, args.to);
+        }
+        bail!("No route found from {} to {}", args.from, args.to);
+    };
+
+    match args.actions.options() {
+        Some(options) => {
+            let mut actions = route_actions(&view, &route, options);
+            if args.floods == Some(FloodResponse::Seal) {
+                seal_floods(&mut actions, &floods);
+            }
+            for action in actions {
+                println!("{action}");
+            }
+        },
+        None => {
+            for step in &route.steps {
+                println!("{}", step);
+            }
+        },
+    }
+
+    eprintln!("Route: {} steps, {} blocks to mine, {} to place, cost {}", route.steps.len(), route.mined.len(), route.supports.len(), route.cost);
+    for hazard in &route.hazards {
+        eprintln!("{hazard}");
+    }
+    for damage in build_damage(&view, &route.mined) {
+        eprintln!("{damage}");
+    }
+    for flood in &floods {
+        eprintln!("{flood}");
+    }
+
+    if let Some(path) = &args.mcfunction {
+        let mut commands = route_commands(&route, &[args.to], args.marker);
+        commands.extend(flood_commands(&floods, args.marker));
+        write_mcfunction(path, &commands)?;
+        eprintln!("Wrote {}", path.display());
+    }
+
+    if let Some(path) = &args.baritone {
+        fs::write(path, goto_commands(&route, &[args.to]).join("\n") + "\n")?;
+        eprintln!("Wrote {}", path.display());
+    }
+
+    args.cache.finish(&world)
+}
+
+fn run_through_portals(world: &World, args: &PathArgs, costs: &dyn CostModel) -> Result<()> {
+    let portals = find_portals(world)?;
+    eprintln!("Found {} nether portals", portals.len());
+
+    let options = LegOptions { margin: args.margin, costs, avoid_hazards: args.avoid_hazards, protect_builds: args.protect() };
+    let Some(route) = find_route_through_portals(world, args.dimension, args.from, args.to, &portals, &options)? else {
+        bail!("No route found from {} to {}", args.from, args.to);
+    };
+
+    for leg in &route.legs {
+        for step in &leg.route.steps {
+            println!("{:?} {}", leg.dimension, step);
+        }
+    }
+
+    let steps: usize = route.legs.iter().map(|leg| leg.route.steps.len()).sum();
+    let mined: usize = route.legs.iter().map(|leg| leg.route.mined.len()).sum();
+    eprintln!("Route: {} steps in {} legs, {} blocks to mine, cost {}", steps, route.legs.len(), mined, route.cost);
+    for leg in &route.legs {
+        for hazard in &leg.route.hazards {
+            eprintln!("{:?} {hazard}", leg.dimension);
+        }
+    }
+
+    // Each leg's commands run in its own dimension
+    if let Some(path) = &args.mcfunction {
+        let mut commands = Vec::new();
+        for (i, leg) in route.legs.iter().enumerate() {
+            let targets = if i + 1 == route.legs.len() { vec![args.to] } else { Vec::new() };
+            for command in route_commands(&leg.route, &targets, args.marker) {
+                if command.starts_with('#') {
+                    commands.push(command);
+                } else {
+                    commands.push(format!("execute in {} run {command}", leg.dimension.id()));
+                }
+            }
+        }
+        write_mcfunction(path, &commands)?;
+        eprintln!("Wrote {}", path.display());
+    }
+
+    // Walking into a portal block is all it takes to change dimension
+    if let Some(path) = &args.baritone {
+        let commands: Vec<String> = route.legs.iter().flat_map(|leg| goto_commands(&leg.route, &[])).collect();
+        fs::write(path, commands.join("\n") + "\n")?;
+        eprintln!("Wrote {}", path.display());
+    }
+
+    Ok(())
+}
+
+impl PathArgs {
+    fn protect(&self) -> Option<ProtectBuilds> {
+        self.protect_builds.map(|severity| ProtectBuilds { severity, distance: self.build_distance })
+    }
+}
+
+pub(super) fn load_costs(path: Option<&PathBuf>) -> Result<Box<dyn CostModel>> {
+    Ok(match path {
+        Some(path) => Box::new(CostConfig::load(path)?),
+        None => Box::new(DefaultCosts),
+    })
+}
@@ -0,0 +1,94 @@
+use anyhow::{ Result, bail };
+use clap::Args;
+use std::path::PathBuf;
+use path_miner::{ World, Dimension, BlockPos, branch_mine::{ BranchMineOptions, Heading, plan_branch_mine }, schematic::Schematic, waypoints::{ Waypoint, WaypointFormat, write_waypoints } };
+
+// Blocks the schematic marks the tunnels with, like the blocks route marker
+const TUNNEL_BLOCK: &str = "minecraft:glass";
+const WAYPOINT_COLOR: [u8; 4] = [255, 170, 0, 255];
+
+#[derive(Args)]
+pub struct PlanBranchMineArgs {
+    /// World folder
+    world: PathBuf,
+    /// Feet position the main tunnel starts at, as x,y,z
+    #[arg(long, allow_hyphen_values = true)]
+    start: BlockPos,
+    /// Which way the main tunnel runs, north, south, east or west
+    #[arg(long, default_value = "north")]
+    heading: Heading,
+    /// How long the main tunnel is
+    #[arg(long, default_value_t = 64)]
+    depth: u32,
+    /// Solid blocks between branches, 2 leaves no block unseen
+    #[arg(long, default_value_t = 2)]
+    spacing: u32,
+    /// How long each branch is
+    #[arg(long, default_value_t = 16)]
+    branch_length: u32,
+    #[arg(long, default_value = "overworld")]
+    dimension: Dimension,
+    /// Write a waypoint at the end of every tunnel, for xaeros, journeymap or baritone
+    #[arg(long, value_name = "FORMAT")]
+    export_waypoints: Option<WaypointFormat>,
+    /// Folder the waypoints are written to
+    #[arg(long, value_name = "DIR", default_value = "waypoints")]
+    waypoints_dir: PathBuf,
+    /// Write the blocks to mine as glass into a schematic, Litematica if it ends in .litematic and Sponge otherwise
+    #[arg(long, value_name = "FILE")]
+    schematic: Option<PathBuf>,
+}
+
+pub fn run(args: PlanBranchMineArgs) -> Result<()> {
+    let world = World::open(&args.world)?;
+    let options = BranchMineOptions { heading: args.heading, depth: args.depth, spacing: args.spacing, branch_length: args.branch_length };
+
+    // The main tunnel's far end, and as far as branches reach to either side
+    let (dx, dz) = args.heading.step();
+    let (sx, sz) = args.heading.left().step();
+    let end = args.start.offset(dx * args.depth as i32, 0, dz * args.depth as i32);
+    let reach = args.branch_length as i32 + 1;
+    let corners = [args.start.offset(sx * reach, 0, sz * reach), end.offset(-sx * reach, 0, -sz * reach)];
+    let min_chunk = (corners[0].chunk_x().min(corners[1].chunk_x()), corners[0].chunk_z().min(corners[1].chunk_z()));
+    let max_chunk = (corners[0].chunk_x().max(corners[1].chunk_x()), corners[0].chunk_z().max(corners[1].chunk_z()));
+    let view = world.view(args.dimension, min_chunk, max_chunk)?;
+
+    let mine = plan_branch_mine(&view, args.start, &options);
+    if mine.tunnels.is_empty() {
+        bail!("Can't dig a tunnel at {}", args.start);
+    }
+
+    for tunnel in &mine.tunnels {
+        println!("{}: {} to {}, {} blocks to mine", tunnel.name, tunnel.from, tunnel.to, tunnel.mined.len());
+    }
+    let mined = mine.mined();
+    eprintln!("{} tunnels, {} blocks to mine, {} blocks exposed ({:.2} per block mined)",
+        mine.tunnels.len(), mined, mine.exposed, mine.exposed as f64 / mined.max(1) as f64);
+
+    if let Some(format) = args.export_waypoints {
+        let waypoints: Vec<Waypoint> = mine.tunnels.iter()
+            .map(|tunnel| Waypoint { name: tunnel.name.clone(), pos: tunnel.to, dimension: args.dimension, color: WAYPOINT_COLOR })
+            .collect();
+        let files = write_waypoints(&args.waypoints_dir, format, &waypoints)?;
+        eprintln!("Wrote {} waypoints to {files} files in {}", waypoints.len(), args.waypoints_dir.display());
+    }
+
+    if let Some(path) = &args.schematic {
+        let blocks: Vec<(BlockPos, String)> = mine.tunnels.iter()
+            .flat_map(|tunnel| &tunnel.mined)
+            .map(|pos| (*pos, TUNNEL_BLOCK.to_string()))
+            .collect();
+        let data_version = view.chunks().map(|chunk| chunk.data_version()).max().unwrap_or(0);
+        let schematic = Schematic::from_blocks(&blocks, data_version)?;
+        if path.extension().is_some_and(|extension| extension == "litematic") {
+            let name = path.file_stem().map_or("branch-mine".into(), |stem| stem.to_string_lossy());
+            schematic.write_litematic(path, &name)?;
+        } else {
+            schematic.write_sponge(path)?;
+        }
+        let origin = schematic.origin();
+        eprintln!("Wrote {} with its corner at {}", path.display(), origin);
+    }
+
+    Ok(())
+}
@@ -0,0 +1,86 @@
+use anyhow::{ Result, Context };
+use clap::Args;
+use serde::Deserialize;
+use std::{ collections::HashMap, fs, path::{ Path, PathBuf } };
+use path_miner::{ World, item::ItemStack, player::PlayerData };
+
+#[derive(Args)]
+pub struct PlayersArgs {
+    /// World folder
+    path: PathBuf,
+    /// Also list what each player carries and has in their ender chest
+    #[arg(long)]
+    items: bool,
+}
+
+#[derive(Deserialize)]
+struct CachedUser {
+    name: String,
+    uuid: String,
+}
+
+pub fn run(args: PlayersArgs) -> Result<()> {
+    let world = World::open(&args.path)?;
+    let names = user_names(&args.path);
+
+    let mut players = Vec::new();
+    for path in world.player_files()? {
+        match PlayerData::load(&path) {
+            Ok(player) => players.push(player),
+            Err(e) => log::warn!("Skipping player: {e:#}"),
+        }
+    }
+    match world.host_player() {
+        Ok(Some(host)) if !players.iter().any(|player| player.uuid() == host.uuid()) => players.push(host),
+        Ok(_) => {},
+        Err(e) => log::warn!("Skipping the level.dat player: {e:#}"),
+    }
+
+    for player in &players {
+        let name = names.get(player.uuid()).map_or(player.uuid(), String::as_str);
+        let [x, y, z] = player.pos();
+        println!("{name} {} {x:.1} {y:.1} {z:.1}", player.dimension_id());
+
+        if args.items {
+            print_items("Inventory", player.inventory());
+            print_items("Ender chest", player.ender_chest());
+        }
+    }
+
+    eprintln!("Found {} players", players.len());
+
+    Ok(())
+}
+
+fn print_items(title: &str, items: &[ItemStack]) {
+    if items.is_empty() {
+        return;
+    }
+    println!("  {title}:");
+    for item in items {
+        println!("    {} x{}", item.id(), item.count());
+        for inner in item.contents() {
+            println!("      {} x{}", inner.id(), inner.count());
+        }
+    }
+}
+
+// Servers keep the names of players who joined in usercache.json next to the
+// world folder. Without one players go by their uuid.
+fn user_names(world: &Path) -> HashMap<String, String> {
+    let Some(path) = world.canonicalize().ok().and_then(|world| world.parent().map(|server| server.join("usercache.json"))) else {
+        return HashMap::new();
+    };
+    let read = fs::read_to_string(&path)
+        .map_err(anyhow::Error::from)
+        .and_then(|text| serde_json::from_str::<Vec<CachedUser>>(&text).context("Malformed usercache.json"));
+    match read {
+        Ok(users) => users.into_iter().map(|user| (user.uuid, user.name)).collect(),
+        Err(e) => {
+            if path.exists() {
+                log::warn!("Not using {}: {e:#}", path.display());
+            }
+            HashMap::new()
+        },
+    }
+}
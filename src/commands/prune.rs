@@ -0,0 +1,149 @@
+use anyhow::{ Result, Context, anyhow, bail };
+use clap::Args;
+use std::path::{ Path, PathBuf };
+use path_miner::{ World, Dimension, ParseOptions, TagPayload, claims::ChunkPolicy, region::{ Region, chunk_at_index, parse_region_file_name, remove_chunks } };
+
+#[derive(Args)]
+pub struct PruneArgs {
+    /// World folder or region file (.mca)
+    path: PathBuf,
+    /// Keep only chunks touching this block area, given as x1,z1,x2,z2
+    #[arg(long, value_parser = parse_area, allow_hyphen_values = true)]
+    within: Option<[i32; 4]>,
+    /// Keep only chunks players have spent at least this many ticks in
+    #[arg(long, value_name = "TICKS")]
+    min_inhabited: Option<i64>,
+    /// Only report what would be removed
+    #[arg(long)]
+    dry_run: bool,
+    #[command(flatten)]
+    claims: super::ClaimArgs,
+}
+
+fn parse_area(s: &str) -> Result<[i32; 4]> {
+    let values: Vec<i32> = s.split(',').map(|v| v.trim().parse()).collect::<Result<_, _>>().map_err(|_| anyhow!("Expected x1,z1,x2,z2"))?;
+    match values.as_slice() {
+        [x1, z1, x2, z2] => Ok([*x1.min(x2), *z1.min(z2), *x1.max(x2), *z1.max(z2)]),
+        _ => bail!("Expected x1,z1,x2,z2"),
+    }
+}
+
+pub fn run(args: PruneArgs) -> Result<()> {
+    if args.within.is_none() && args.min_inhabited.is_none() {
+        bail!("Nothing to prune by, pass --within and/or --min-inhabited");
+    }
+
+    let policy = args.claims.policy()?;
+    let mut regions = Vec::new();
+    if args.path.is_dir() {
+        let world = World::open(&args.path)?;
+        regions.extend(world.regions().iter().map(|region| (region.path.clone(), Some(region.dimension), Some((region.x, region.z)))));
+    } else {
+        let position = args.path.file_name().and_then(|name| name.to_str()).and_then(parse_region_file_name);
+        regions.push((args.path.clone(), None, position));
+    }
+
+    let (mut total, mut removed, mut excluded) = (0, 0, 0);
+    for (path, dimension, position) in regions {
+        let (chunks, mut doomed) = chunks_to_remove(&args, &path, position).with_context(|| format!("Could not read region {}", path.display()))?;
+        if !policy.is_empty() {
+            let before = doomed.len();
+            doomed.retain(|index| allowed(&policy, dimension, position, *index));
+            excluded += before - doomed.len();
+        }
+        total += chunks;
+        removed += doomed.len();
+        if doomed.is_empty() {
+            continue;
+        }
+
+        println!("{}: removing {} of {} chunks", path.display(), doomed.len(), chunks);
+        if args.dry_run {
+            continue;
+        }
+
+        remove_chunks(&path, &doomed).with_context(|| format!("Could not prune {}", path.display()))?;
+
+        // The entities and points of interest of a world's chunks are stored in regions of the same name
+        let dimension = path.parent().filter(|dir| dir.ends_with("region")).and_then(Path::parent);
+        if let (Some(dimension), Some(name)) = (dimension, path.file_name()) {
+            for folder in ["entities", "poi"] {
+                let sibling = dimension.join(folder).join(name);
+                if sibling.is_file() {
+                    remove_chunks(&sibling, &doomed).with_context(|| format!("Could not prune {}", sibling.display()))?;
+                }
+            }
+        }
+    }
+
+    let verb = if args.dry_run { "Would remove" } else { "Removed" };
+    eprintln!("{verb} {removed} of {total} chunks");
+    if excluded > 0 {
+        eprintln!("Kept {excluded} chunks that would have been removed, they're excluded by --include/--exclude");
+    }
+
+    Ok(())
+}
+
+// Whether the policy lets the chunk at index be removed. Chunks of regions
+// named so that where they are isn't known are kept.
+fn allowed(policy: &ChunkPolicy, dimension: Option<Dimension>, position: Option<(i32, i32)>, index: usize) -> bool {
+    position.is_some_and(|(region_x, region_z)| {
+        let (x, z) = chunk_at_index(region_x, region_z, index);
+        policy.allows(dimension, x, z)
+    })
+}
+
+// How many chunks the region has and the indices of the ones to remove
+fn chunks_to_remove(args: &PruneArgs, path: &Path, position: Option<(i32, i32)>) -> Result<(usize, Vec<usize>)> {
+    let mut region = Region::open(path)?;
+    let options = ParseOptions::new()
+        .skip("sections")
+        .skip("Sections")
+        .skip("block_entities")
+        .skip("TileEntities")
+        .skip("Entities")
+        .skip("Heightmaps");
+
+    let present: Vec<usize> = (0..1024).filter(|&index| region.has_chunk(index)).collect();
+    let mut doomed = Vec::new();
+
+    for &index in &present {
+        if let Some([x1, z1, x2, z2]) = args.within {
+            let Some((region_x, region_z)) = position else {
+                bail!("--within needs the region file to be named r.<x>.<z>.mca");
+            };
+            let chunk_x = region_x * 32 + (index % 32) as i32;
+            let chunk_z = region_z * 32 + (index / 32) as i32;
+            let inside = chunk_x >= x1.div_euclid(16) && chunk_x <= x2.div_euclid(16)
+                && chunk_z >= z1.div_euclid(16) && chunk_z <= z2.div_euclid(16);
+            if !inside {
+                doomed.push(index);
+                continue;
+            }
+        }
+
+        if let Some(min_inhabited) = args.min_inhabited {
+            // Chunks that can't be read are left alone, check will point them out
+            let chunk = match region.read_chunk(index, &options) {
+                Ok(Some(chunk)) => chunk,
+                Ok(None) => continue,
+                Err(e) => {
+                    eprintln!("Keeping unreadable chunk {index} of {}: {e:#}", path.display());
+                    continue;
+                },
+            };
+
+            let root = &chunk.payload;
+            let inhabited = match root.get("Level").unwrap_or(root).get("InhabitedTime") {
+                Some(TagPayload::Long(ticks)) => *ticks,
+                _ => 0,
+            };
+            if inhabited < min_inhabited {
+                doomed.push(index);
+            }
+        }
+    }
+
+    Ok((present.len(), doomed))
+}
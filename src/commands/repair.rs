@@ -0,0 +1,94 @@
+use anyhow::{ Result, Context };
+use clap::Args;
+use std::{ fs, path::PathBuf };
+use path_miner::{ World, ParseOptions, region::{ Region, RegionProblem, restore_chunks } };
+
+#[derive(Args)]
+pub struct RepairArgs {
+    /// World folder to repair in place
+    world: PathBuf,
+    /// Copy of the world from before the chunks broke, e.g. an unpacked backup
+    #[arg(long, value_name = "WORLD")]
+    backup: PathBuf,
+    /// Only report what is broken and whether the backup has it
+    #[arg(long)]
+    dry_run: bool,
+}
+
+pub fn run(args: RepairArgs) -> Result<()> {
+    let world = World::open(&args.world)?;
+    let regions = world.regions().iter().chain(world.entity_regions()).chain(world.poi_regions());
+    let (mut broken, mut restored, mut lost) = (0, 0, 0);
+    let mut headers = 0;
+
+    for info in regions {
+        // The game leaves 0 byte region files around, there's nothing in them to break
+        if info.is_empty() {
+            continue;
+        }
+        let relative = info.path.strip_prefix(world.path()).unwrap_or(&info.path);
+        let backup_path = args.backup.join(relative);
+        // Points of interest are kept per section and have no chunk position
+        let poi = relative.parent().is_some_and(|parent| parent.ends_with("poi"));
+
+        let problems = match Region::open(&info.path).and_then(|mut region| region.check()) {
+            Ok(problems) => problems,
+            Err(e) => {
+                // Without a header there's no telling which chunks are fine
+                println!("{}: can't read the header: {e:#}", relative.display());
+                headers += 1;
+                if !backup_path.is_file() {
+                    println!("  no backup of the region, leaving it as it is");
+                } else if !args.dry_run {
+                    fs::copy(&backup_path, &info.path).with_context(|| format!("Could not copy {}", backup_path.display()))?;
+                    println!("  replaced the whole region with the backup");
+                }
+                continue;
+            },
+        };
+        let mut indices: Vec<usize> = problems.iter()
+            .filter(|problem| !(poi && matches!(problem, RegionProblem::NoPosition { .. })))
+            .map(RegionProblem::index)
+            .collect();
+        indices.dedup();
+        if indices.is_empty() {
+            continue;
+        }
+
+        println!("{}:", relative.display());
+        for problem in &problems {
+            println!("  {problem}");
+        }
+        broken += indices.len();
+
+        let mut backup = match Region::open(&backup_path) {
+            Ok(backup) => backup,
+            Err(_) => {
+                println!("  no backup of the region, leaving it as it is");
+                lost += indices.len();
+                continue;
+            },
+        };
+        if args.dry_run {
+            let readable = indices.iter().filter(|&&index| matches!(backup.read_chunk(index, &ParseOptions::default()), Ok(Some(_)))).count();
+            println!("  the backup has {readable} of the {} chunks", indices.len());
+            restored += readable;
+            lost += indices.len() - readable;
+            continue;
+        }
+
+        let report = restore_chunks(&info.path, &indices, &mut backup).with_context(|| format!("Could not repair {}", info.path.display()))?;
+        println!("  restored {} chunks, kept {} without a backup, dropped {} that can't be read at all",
+            report.restored.len(), report.kept.len(), report.dropped.len());
+        restored += report.restored.len();
+        lost += report.kept.len() + report.dropped.len();
+    }
+
+    let verb = if args.dry_run { "would restore" } else { "restored" };
+    eprintln!("{broken} broken chunks, {verb} {restored} from the backup, {lost} have no good copy in it");
+    if headers > 0 {
+        eprintln!("{headers} regions had a broken header");
+    }
+
+    Ok(())
+}
@@ -0,0 +1,33 @@
+use anyhow::Result;
+use clap::Args;
+use std::path::PathBuf;
+use path_miner::{ Dimension, BlockPos, chunk::BlockState, replace::replace_blocks };
+
+#[derive(Args)]
+pub struct ReplaceArgs {
+    /// World folder or region file (.mca), changed in place
+    path: PathBuf,
+    /// Block to replace, with properties to only replace some of its states, e.g. minecraft:oak_log[axis=y]
+    #[arg(long)]
+    from: BlockState,
+    /// Block state to put in its place
+    #[arg(long)]
+    to: BlockState,
+    /// Only replace blocks in this box, given as x1,y1,z1,x2,y2,z2
+    #[arg(long = "box", value_name = "BOX", value_parser = super::parse_box, allow_hyphen_values = true)]
+    area: (BlockPos, BlockPos),
+    #[arg(long, default_value = "overworld")]
+    dimension: Dimension,
+    /// Only count the blocks that would be replaced
+    #[arg(long)]
+    dry_run: bool,
+    #[command(flatten)]
+    claims: super::ClaimArgs,
+}
+
+pub fn run(args: ReplaceArgs) -> Result<()> {
+    let policy = args.claims.policy()?;
+    let summary = replace_blocks(&args.path, args.dimension, &args.from, &args.to, args.area, &policy, args.dry_run)?;
+    eprintln!("{summary}");
+    Ok(())
+}
@@ -0,0 +1,45 @@
+use anyhow::Result;
+use clap::Args;
+use std::path::PathBuf;
+use path_miner::cache::{ AccessLog, CacheConfig, EvictionPolicy, simulate };
+
+#[derive(Args)]
+pub struct ReplayArgs {
+    /// Access log written with --record-accesses
+    log: PathBuf,
+    /// Cache sizes to try, in chunks and comma separated, the recorded one if left out
+    #[arg(long, value_name = "CHUNKS", value_delimiter = ',')]
+    sizes: Vec<usize>,
+    /// Eviction policies to try, lru and 2q comma separated, the recorded one if left out
+    #[arg(long, value_name = "POLICIES", value_delimiter = ',')]
+    policies: Vec<EvictionPolicy>,
+    /// Prefetch radiuses to try, comma separated, the recorded one if left out
+    #[arg(long, value_name = "CHUNKS", value_delimiter = ',')]
+    prefetch_radius: Vec<u32>,
+    /// What reading and decoding one chunk takes, in microseconds
+    #[arg(long, value_name = "MICROS", default_value_t = 2000)]
+    decode_cost: u64,
+}
+
+pub fn run(args: ReplayArgs) -> Result<()> {
+    let log = AccessLog::load(&args.log)?;
+    let recorded = log.config;
+
+    println!("{:>8} {:>6} {:>8} {:>9} {:>8} {:>8} {:>10}", "size", "policy", "prefetch", "accesses", "hits", "hit rate", "decode ms");
+    for size in or_recorded(&args.sizes, recorded.capacity) {
+        for policy in or_recorded(&args.policies, recorded.policy) {
+            for radius in or_recorded(&args.prefetch_radius, recorded.prefetch_radius) {
+                let stats = simulate(&log.accesses, &CacheConfig::new(size, policy).prefetch_radius(radius));
+                let decode_ms = stats.decodes() as f64 * args.decode_cost as f64 / 1000.0;
+                println!("{size:>8} {policy:>6} {radius:>8} {:>9} {:>8} {:>7.1}% {decode_ms:>10.1}", stats.accesses(), stats.hits, stats.hit_rate() * 100.0);
+            }
+        }
+    }
+
+    eprintln!("Recorded with {recorded}: {}", log.stats());
+    Ok(())
+}
+
+fn or_recorded<T: Copy>(values: &[T], recorded: T) -> Vec<T> {
+    if values.is_empty() { vec![recorded] } else { values.to_vec() }
+}
@@ -0,0 +1,124 @@
+use anyhow::{ Result, Context, anyhow, bail };
+use clap::Args;
+use std::{ collections::{ BTreeMap, HashMap }, fs, path::{ Path, PathBuf } };
+use path_miner::{ World, Dimension, ParseOptions, region::{ Region, chunk_at_index, parse_region_file_name }, render::{ BlockColors, MapLayer, TopDownRenderer, is_ore }, report::{ Report, is_valuable, spawner_mob, write_html } };
+
+#[derive(Args)]
+pub struct ReportArgs {
+    /// World folder or region file (.mca)
+    path: PathBuf,
+    /// HTML file to write
+    #[arg(short, long)]
+    out: PathBuf,
+    #[arg(long, default_value = "overworld")]
+    dimension: Dimension,
+    /// Only cover the chunks with blocks from x1,z1 to x2,z2
+    #[arg(long, value_name = "X1,Z1,X2,Z2", value_parser = parse_bounds, allow_hyphen_values = true)]
+    bounds: Option<((i32, i32), (i32, i32))>,
+    #[command(flatten)]
+    scan: super::ScanArgs,
+}
+
+// Chunk bounds, inclusive
+fn parse_bounds(s: &str) -> Result<((i32, i32), (i32, i32))> {
+    let coords: Vec<i32> = s.split(',').map(|part| part.trim().parse()).collect::<Result<_, _>>()
+        .map_err(|_| anyhow!("Expected x1,z1,x2,z2"))?;
+    let [x1, z1, x2, z2] = coords[..] else {
+        bail!("Expected x1,z1,x2,z2");
+    };
+    Ok(((x1.min(x2).div_euclid(16), z1.min(z2).div_euclid(16)), (x1.max(x2).div_euclid(16), z1.max(z2).div_euclid(16))))
+}
+
+pub fn run(args: ReportArgs) -> Result<()> {
+    let in_bounds = |x: i32, z: i32| args.bounds.is_none_or(|(min, max)| (min.0..=max.0).contains(&x) && (min.1..=max.1).contains(&z));
+
+    // The scan leaves out the chunks the claims exclude by itself
+    let policy = args.scan.claims.policy()?;
+    let dimension = args.path.is_dir().then_some(args.dimension);
+    let activity: Vec<((i32, i32), u32)> = saved_chunks(&args.path, args.dimension)?
+        .into_iter()
+        .filter(|((x, z), _)| in_bounds(*x, *z) && policy.allows(dimension, *x, *z))
+        .collect();
+    let Some((min_chunk, max_chunk)) = args.bounds.or_else(|| {
+        let (xs, zs): (Vec<i32>, Vec<i32>) = activity.iter().map(|(chunk, _)| *chunk).unzip();
+        Some(((*xs.iter().min()?, *zs.iter().min()?), (*xs.iter().max()?, *zs.iter().max()?)))
+    }) else {
+        bail!("No chunks to report on");
+    };
+
+    let colors = BlockColors::default();
+    let mut renderer = TopDownRenderer::new(&colors, MapLayer::Blocks, min_chunk, max_chunk);
+    let mut blocks: HashMap<String, u64> = HashMap::new();
+    let mut ores: BTreeMap<String, BTreeMap<i32, u64>> = BTreeMap::new();
+    let mut valuables = Vec::new();
+    let mut spawners = Vec::new();
+
+    super::for_each_chunk_with(&args.path, &args.scan, ParseOptions::default(), |dimension, chunk| {
+        if dimension.is_some_and(|dimension| dimension != args.dimension) || !in_bounds(chunk.x(), chunk.z()) {
+            return;
+        }
+
+        renderer.add_chunk(chunk);
+        for section in chunk.sections() {
+            let palette = section.palette();
+            let mut counts = vec![0; palette.len()];
+            for (i, palette_index) in section.block_states().indices().into_iter().enumerate() {
+                counts[palette_index] += 1;
+                let name = &palette[palette_index].name;
+                if is_ore(name) {
+                    let y = section.y() * 16 + (i >> 8) as i32;
+                    *ores.entry(name.to_string()).or_default().entry(y).or_default() += 1;
+                }
+                if is_valuable(name) {
+                    valuables.push((name.to_string(), chunk.block_pos(section, i)));
+                }
+            }
+            for (block, count) in palette.iter().zip(counts) {
+                *blocks.entry(block.name.to_string()).or_default() += count;
+            }
+        }
+        for block_entity in chunk.block_entities().iter().filter(|block_entity| block_entity.id() == "minecraft:mob_spawner") {
+            spawners.push((spawner_mob(block_entity).map(str::to_string), block_entity.pos()));
+        }
+    })?;
+
+    let name = args.path.file_name().map_or_else(|| args.path.display().to_string(), |name| name.to_string_lossy().into_owned());
+    let report = Report {
+        title: format!("{name}, chunks ({}, {}) to ({}, {})", min_chunk.0, min_chunk.1, max_chunk.0, max_chunk.1),
+        map: Some(renderer.finish()),
+        map_origin: (min_chunk.0 * 16, min_chunk.1 * 16),
+        blocks: blocks.into_iter().collect(),
+        ores,
+        valuables,
+        spawners,
+        activity,
+    };
+    fs::write(&args.out, write_html(&report)?).with_context(|| format!("Could not write {}", args.out.display()))?;
+    eprintln!("Wrote {}", args.out.display());
+
+    Ok(())
+}
+
+// Every chunk stored in the world's dimension or the region file, with when
+// it was last saved, from the region headers
+fn saved_chunks(path: &Path, dimension: Dimension) -> Result<Vec<((i32, i32), u32)>> {
+    let regions: Vec<((i32, i32), PathBuf)> = if path.is_dir() {
+        let world = World::open(path)?;
+        world.regions_in(dimension).filter(|info| !info.is_empty()).map(|info| ((info.x, info.z), info.path.clone())).collect()
+    } else {
+        let name = path.file_name().and_then(|name| name.to_str()).unwrap_or_default();
+        let Some(position) = parse_region_file_name(name) else {
+            bail!("{} isn't named r.<x>.<z>.mca, so where its chunks are isn't known", path.display());
+        };
+        vec![(position, path.to_path_buf())]
+    };
+
+    let mut chunks = Vec::new();
+    for ((region_x, region_z), path) in regions {
+        let region = Region::open(&path).with_context(|| format!("Could not read region {}", path.display()))?;
+        for index in (0..1024).filter(|index| region.has_chunk(*index)) {
+            chunks.push((chunk_at_index(region_x, region_z, index), region.timestamps()[index]));
+        }
+    }
+    Ok(chunks)
+}
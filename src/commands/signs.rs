@@ -0,0 +1,56 @@
+use anyhow::Result;
+use clap::Args;
+use std::path::PathBuf;
+use path_miner::sign::SignText;
+
+#[derive(Args)]
+pub struct SignsArgs {
+    /// World folder or region file (.mca)
+    path: PathBuf,
+    /// Only print signs containing this text (case insensitive)
+    #[arg(long)]
+    contains: Option<String>,
+    /// Also print signs without any text
+    #[arg(long)]
+    include_empty: bool,
+    #[command(flatten)]
+    scan: super::ScanArgs,
+}
+
+pub fn run(args: SignsArgs) -> Result<()> {
+    let needle = args.contains.as_ref().map(|text| text.to_lowercase());
+    let mut found = 0;
+
+    super::for_each_chunk(&args.path, &args.scan, |dimension, chunk| {
+        for block_entity in chunk.block_entities() {
+            let Some(sign) = SignText::from_nbt(block_entity.nbt()) else {
+                continue;
+            };
+            if sign.is_empty() && !args.include_empty {
+                continue;
+            }
+
+            let front = sign.front.join(" | ");
+            let back = sign.back.join(" | ");
+            if let Some(needle) = &needle {
+                if !front.to_lowercase().contains(needle) && !back.to_lowercase().contains(needle) {
+                    continue;
+                }
+            }
+
+            if let Some(dimension) = dimension {
+                print!("{:?} ", dimension);
+            }
+            print!("{}: {}", block_entity.pos(), front);
+            if sign.back.iter().any(|line| !line.trim().is_empty()) {
+                print!(" / back: {}", back);
+            }
+            println!();
+            found += 1;
+        }
+    })?;
+
+    eprintln!("Found {found} signs");
+
+    Ok(())
+}
@@ -0,0 +1,107 @@
+use anyhow::{ Result, bail };
+use clap::{ Args, ArgGroup };
+use std::path::PathBuf;
+use path_miner::{ World, Dimension, height::WorldHeight, region::{ Region, parse_region_file_name }, render::{ BlockColors, SlicePlane, SliceRenderer } };
+
+#[derive(Args)]
+#[command(group(ArgGroup::new("plane").required(true).args(["y", "x", "z"])))]
+pub struct SliceArgs {
+    /// World folder or region file (.mca)
+    path: PathBuf,
+    /// Output PNG
+    #[arg(short, long)]
+    output: PathBuf,
+    /// Render the horizontal slice at this y
+    #[arg(short, allow_negative_numbers = true)]
+    y: Option<i32>,
+    /// Render the vertical slice at this x
+    #[arg(short, allow_negative_numbers = true)]
+    x: Option<i32>,
+    /// Render the vertical slice at this z
+    #[arg(short, allow_negative_numbers = true)]
+    z: Option<i32>,
+    #[arg(long, default_value = "overworld")]
+    dimension: Dimension,
+    /// Blocks to highlight instead of every ore and ancient debris
+    #[arg(long)]
+    highlight: Vec<String>,
+    /// TOML or JSON file mapping block names to colors
+    #[arg(long, value_name = "FILE")]
+    palette: Option<PathBuf>,
+    /// Lowest y shown on vertical slices, the bottom of the world if left out
+    #[arg(long, allow_negative_numbers = true)]
+    min_y: Option<i32>,
+    /// Draw air by its light level, so dark spaces stand out (needs the world's light data)
+    #[arg(long)]
+    light: bool,
+    /// Highest y shown on vertical slices, the top of the world if left out
+    #[arg(long, allow_negative_numbers = true)]
+    max_y: Option<i32>,
+}
+
+pub fn run(args: SliceArgs) -> Result<()> {
+    let plane = match (args.y, args.x, args.z) {
+        (Some(y), _, _) => SlicePlane::Y(y),
+        (_, Some(x), _) => SlicePlane::X(x),
+        (_, _, Some(z)) => SlicePlane::Z(z),
+        _ => unreachable!("clap requires one of -y, -x and -z"),
+    };
+
+    let mut colors = BlockColors::default();
+    if let Some(palette) = &args.palette {
+        colors.load(palette)?;
+    }
+
+    // Regions to read, by position, narrowed down to the ones the plane crosses
+    let (regions, height): (Vec<(PathBuf, i32, i32)>, WorldHeight) = if args.path.is_dir() {
+        let world = World::open(&args.path)?;
+        let regions = world.regions_in(args.dimension).map(|region| (region.path.clone(), region.x, region.z)).collect();
+        (regions, world.height(args.dimension)?)
+    } else {
+        let Some((x, z)) = args.path.file_name().and_then(|name| name.to_str()).and_then(parse_region_file_name) else {
+            bail!("Region file needs to be named r.<x>.<z>.mca");
+        };
+        let height = Region::open(&args.path)?.world_height().unwrap_or(WorldHeight::OVERWORLD);
+        (vec![(args.path.clone(), x, z)], height)
+    };
+    let (min_y, max_y) = (args.min_y.unwrap_or(height.min_y), args.max_y.unwrap_or(height.max_y()));
+    if min_y > max_y {
+        bail!("--min-y is above --max-y");
+    }
+    let regions: Vec<_> = regions.into_iter()
+        .filter(|(_, x, z)| match plane {
+            SlicePlane::Y(_) => true,
+            SlicePlane::X(block_x) => block_x.div_euclid(512) == *x,
+            SlicePlane::Z(block_z) => block_z.div_euclid(512) == *z,
+        })
+        .collect();
+    if regions.is_empty() {
+        bail!("No region crosses the slice");
+    }
+
+    let min_chunk = (regions.iter().map(|r| r.1).min().unwrap() * 32, regions.iter().map(|r| r.2).min().unwrap() * 32);
+    let max_chunk = (regions.iter().map(|r| r.1).max().unwrap() * 32 + 31, regions.iter().map(|r| r.2).max().unwrap() * 32 + 31);
+
+    let mut renderer = SliceRenderer::new(&colors, plane, min_chunk, max_chunk, min_y, max_y).highlight(args.highlight);
+    if args.light {
+        renderer = renderer.show_light();
+    }
+    for (path, _, _) in &regions {
+        for chunk in super::load_region_chunks(path, None)? {
+            renderer.add_chunk(&chunk);
+        }
+    }
+
+    let (origin_u, origin_v) = renderer.origin();
+    let image = renderer.finish();
+    image.save_png(&args.output)?;
+
+    match plane {
+        SlicePlane::Y(_) => eprintln!("Top left pixel is x {origin_u}, z {origin_v}"),
+        SlicePlane::X(_) => eprintln!("Top left pixel is z {origin_u}, y {origin_v}"),
+        SlicePlane::Z(_) => eprintln!("Top left pixel is x {origin_u}, y {origin_v}"),
+    }
+    eprintln!("Wrote {}x{} slice to {}", image.width, image.height, args.output.display());
+
+    Ok(())
+}
@@ -0,0 +1,44 @@
+use anyhow::{ Result, anyhow, bail };
+use clap::Args;
+use std::path::PathBuf;
+use path_miner::{ World, slime::slime_chunks_near };
+
+#[derive(Args)]
+pub struct SlimeChunksArgs {
+    /// World folder to read the seed from, not needed with --seed
+    path: Option<PathBuf>,
+    /// World seed
+    #[arg(long, allow_hyphen_values = true)]
+    seed: Option<i64>,
+    /// Block position to look around, given as x,z
+    #[arg(long, value_parser = parse_center, allow_hyphen_values = true, default_value = "0,0")]
+    center: (i32, i32),
+    /// How many chunks to look in every direction
+    #[arg(long, default_value_t = 8)]
+    radius: i32,
+}
+
+fn parse_center(s: &str) -> Result<(i32, i32)> {
+    let (x, z) = s.split_once(',').ok_or_else(|| anyhow!("Expected x,z"))?;
+    Ok((x.trim().parse()?, z.trim().parse()?))
+}
+
+pub fn run(args: SlimeChunksArgs) -> Result<()> {
+    let seed = match (args.seed, &args.path) {
+        (Some(seed), _) => seed,
+        (None, Some(path)) => World::open(path)?.level_dat()?.seed().ok_or_else(|| anyhow!("level.dat has no seed, pass --seed"))?,
+        (None, None) => bail!("Pass a world folder or --seed"),
+    };
+
+    let center = (args.center.0.div_euclid(16), args.center.1.div_euclid(16));
+    let mut found = 0;
+    for (x, z) in slime_chunks_near(seed, center, args.radius) {
+        println!("({x}, {z}) blocks {} {} to {} {}", x * 16, z * 16, x * 16 + 15, z * 16 + 15);
+        found += 1;
+    }
+
+    let side = args.radius * 2 + 1;
+    eprintln!("Found {found} slime chunks out of {}", side * side);
+
+    Ok(())
+}
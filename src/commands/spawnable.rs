@@ -0,0 +1,75 @@
+use anyhow::{ Result, bail };
+use clap::Args;
+use std::{ collections::BTreeMap, path::PathBuf };
+use path_miner::{ World, Dimension, BlockPos, spawning::{ SpawnOptions, find_spawnable }, render::{ BlockColors, MapLayer, Rgba, TopDownRenderer } };
+
+const SPAWNABLE_TINT: Rgba = [255, 0, 200, 150];
+
+#[derive(Args)]
+pub struct SpawnableArgs {
+    /// World folder
+    world: PathBuf,
+    /// Position to look around, like the AFK spot of a mob farm, as x,y,z
+    #[arg(long, allow_hyphen_values = true)]
+    center: BlockPos,
+    /// How far from the center to look, 128 is where mobs despawn right away
+    #[arg(long, default_value_t = 128.0)]
+    radius: f64,
+    /// Leave out blocks closer than this, mobs don't spawn within 24 blocks of a player
+    #[arg(long, default_value_t = 24.0)]
+    min_distance: f64,
+    /// Brightest block light mobs spawn in, 0 since 1.18 and 7 before it
+    #[arg(long, default_value_t = 0)]
+    max_block_light: u8,
+    #[arg(long, default_value = "overworld")]
+    dimension: Dimension,
+    /// Only print how many spawnable blocks there are at each height
+    #[arg(long)]
+    summary: bool,
+    /// Render a top-down map of the area with the columns mobs can spawn in tinted to this PNG
+    #[arg(long, value_name = "FILE")]
+    map: Option<PathBuf>,
+}
+
+pub fn run(args: SpawnableArgs) -> Result<()> {
+    if args.min_distance > args.radius {
+        bail!("--min-distance is larger than --radius");
+    }
+    let world = World::open(&args.world)?;
+    let reach = args.radius.ceil() as i32;
+    let min_chunk = ((args.center.x - reach).div_euclid(16), (args.center.z - reach).div_euclid(16));
+    let max_chunk = ((args.center.x + reach).div_euclid(16), (args.center.z + reach).div_euclid(16));
+    let view = world.view(args.dimension, min_chunk, max_chunk)?;
+
+    let options = SpawnOptions { min_distance: args.min_distance, radius: args.radius, max_block_light: args.max_block_light };
+    let spawnable = find_spawnable(&view, args.center, &options);
+
+    if args.summary {
+        let mut by_height: BTreeMap<i32, usize> = BTreeMap::new();
+        for pos in &spawnable {
+            *by_height.entry(pos.y).or_default() += 1;
+        }
+        for (y, count) in by_height.iter().rev() {
+            println!("y={y}: {count}");
+        }
+    } else {
+        for pos in &spawnable {
+            println!("{pos}");
+        }
+    }
+    eprintln!("{} blocks mobs can spawn on within {} blocks of {}", spawnable.len(), args.radius, args.center);
+
+    if let Some(path) = &args.map {
+        let colors = BlockColors::default();
+        let mut renderer = TopDownRenderer::new(&colors, MapLayer::Blocks, min_chunk, max_chunk);
+        for chunk in view.chunks() {
+            renderer.add_chunk(chunk);
+        }
+        renderer.tint_columns(spawnable.iter().map(|pos| (pos.x, pos.z)), SPAWNABLE_TINT);
+        let image = renderer.finish();
+        image.save_png(path)?;
+        eprintln!("Wrote {}x{} map with its corner at block ({}, {}) to {}", image.width, image.height, min_chunk.0 * 16, min_chunk.1 * 16, path.display());
+    }
+
+    Ok(())
+}
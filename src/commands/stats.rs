@@ -0,0 +1,136 @@
+use anyhow::Result;
+use clap::{ Args, ValueEnum };
+use std::{ collections::HashMap, fmt::Display, path::PathBuf };
+use path_miner::{ BlockId, Dimension, ParseOptions };
+
+#[derive(Args)]
+pub struct StatsArgs {
+    /// World folder or region file (.mca)
+    path: PathBuf,
+    /// Only count blocks in this dimension of a world
+    #[arg(long)]
+    dimension: Option<Dimension>,
+    /// Count every block instead of estimating from the palettes
+    #[arg(long)]
+    exact: bool,
+    /// Also list how many non-air blocks each chunk has
+    #[arg(long)]
+    per_chunk: bool,
+    #[arg(long, value_enum, default_value = "text")]
+    format: Format,
+    #[command(flatten)]
+    scan: super::ScanArgs,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, ValueEnum)]
+enum Format {
+    /// Indented lists with percentages
+    Text,
+    /// Comma separated, with a header row, one row per block, biome and with --per-chunk chunk
+    Csv,
+}
+
+pub fn run(args: StatsArgs) -> Result<()> {
+    let mut blocks: HashMap<BlockId, u64> = HashMap::new();
+    // Counted in blocks rather than 4x4x4 cells, so the numbers compare to the ones above
+    let mut biomes: HashMap<String, u64> = HashMap::new();
+    let mut chunks = Vec::new();
+
+    super::for_each_chunk_with(&args.path, &args.scan, ParseOptions::for_block_search(), |dimension, chunk| {
+        if args.dimension.is_some() && dimension != args.dimension {
+            return;
+        }
+
+        let mut non_air = 0;
+        for section in chunk.sections() {
+            let palette = section.palette();
+            let counts = if args.exact {
+                count_indices(section.block_states().indices(), palette.len())
+            } else {
+                estimate(4096, palette.len())
+            };
+            for (block, count) in palette.iter().zip(counts) {
+                *blocks.entry(block.name).or_default() += count;
+                if !block.is_air() {
+                    non_air += count;
+                }
+            }
+
+            if let Some(section_biomes) = section.biomes() {
+                let palette = section_biomes.palette();
+                let counts = if args.exact {
+                    count_indices(section_biomes.indices(), palette.len())
+                } else {
+                    estimate(64, palette.len())
+                };
+                for (biome, count) in palette.iter().zip(counts) {
+                    *biomes.entry(biome.clone()).or_default() += count * 64;
+                }
+            }
+        }
+        chunks.push((dimension, chunk.x(), chunk.z(), non_air));
+    })?;
+
+    if args.format == Format::Csv {
+        println!("kind,dimension,name,x,z,count");
+        for (name, count) in sorted(&blocks) {
+            println!("block,,{name},,,{count}");
+        }
+        for (name, count) in sorted(&biomes) {
+            println!("biome,,{name},,,{count}");
+        }
+        if args.per_chunk {
+            for (dimension, x, z, non_air) in chunks {
+                println!("chunk,{},,{x},{z},{non_air}", dimension.map_or("", Dimension::id));
+            }
+        }
+        return Ok(());
+    }
+
+    let kind = if args.exact { "" } else { ", estimated from palettes" };
+    print_counts(&format!("Blocks{kind}"), &blocks);
+    print_counts(&format!("Biomes, in blocks{kind}"), &biomes);
+
+    if args.per_chunk {
+        println!("Non-air blocks per chunk{kind}:");
+        for (dimension, x, z, non_air) in chunks {
+            match dimension {
+                Some(dimension) => println!("  {:?} ({x}, {z}) {non_air}", dimension),
+                None => println!("  ({x}, {z}) {non_air}"),
+            }
+        }
+    }
+
+    Ok(())
+}
+
+fn count_indices(indices: Vec<usize>, palette_len: usize) -> Vec<u64> {
+    let mut counts = vec![0; palette_len];
+    for i in indices {
+        counts[i] += 1;
+    }
+    counts
+}
+
+// Assumes every palette entry is used equally often, which is exact for
+// single-entry palettes and a guess for the rest
+fn estimate(total: u64, palette_len: usize) -> Vec<u64> {
+    let len = palette_len as u64;
+    (0..len).map(|i| total / len + u64::from(i < total % len)).collect()
+}
+
+// Most common first, ties by name
+fn sorted<K: Ord>(counts: &HashMap<K, u64>) -> Vec<(&K, &u64)> {
+    let mut sorted: Vec<(&K, &u64)> = counts.iter().collect();
+    sorted.sort_by(|a, b| b.1.cmp(a.1).then(a.0.cmp(b.0)));
+    sorted
+}
+
+fn print_counts<K: Ord + Display>(title: &str, counts: &HashMap<K, u64>) {
+    let total: u64 = counts.values().sum();
+
+    println!("{title}:");
+    for (name, count) in sorted(counts) {
+        println!("  {count:>12} {:>6.2}% {name}", *count as f64 * 100.0 / total as f64);
+    }
+}
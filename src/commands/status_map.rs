@@ -0,0 +1,107 @@
+use anyhow::{ Result, bail };
+use clap::Args;
+use std::{ collections::BTreeMap, path::PathBuf };
+use path_miner::{ World, Dimension, ParseOptions, chunk::{ CHUNK_STATUSES, LEGACY_CHUNK_STATUSES }, render::{ Image, Rgba } };
+
+const FULL_COLOR: Rgba = [60, 180, 75, 255];
+const UNKNOWN_COLOR: Rgba = [128, 128, 128, 255];
+// Proto-chunks go from the first color to the second the further they are
+const PROTO_COLORS: [Rgba; 2] = [[120, 20, 20, 255], [240, 220, 60, 255]];
+
+#[derive(Args)]
+pub struct StatusMapArgs {
+    /// World folder
+    world: PathBuf,
+    /// Output PNG, one square per chunk, green where generation is done
+    #[arg(short, long)]
+    output: Option<PathBuf>,
+    #[arg(long, default_value = "overworld")]
+    dimension: Dimension,
+    /// Pixels per chunk on the map
+    #[arg(long, default_value_t = 4)]
+    scale: usize,
+    /// Also list every chunk that isn't fully generated
+    #[arg(long)]
+    list: bool,
+}
+
+pub fn run(args: StatusMapArgs) -> Result<()> {
+    let world = World::open(&args.world)?;
+    let options = ParseOptions::new()
+        .skip("sections")
+        .skip("Sections")
+        .skip("block_entities")
+        .skip("TileEntities")
+        .skip("Entities")
+        .skip("Heightmaps");
+
+    let mut chunks = Vec::new();
+    for chunk in world.chunks_in(args.dimension).with_options(options) {
+        match chunk {
+            Ok((_, chunk)) => chunks.push(((chunk.x(), chunk.z()), chunk.status().map(str::to_string), chunk.is_fully_generated())),
+            Err(e) => log::warn!("Skipping chunk: {e:#}"),
+        }
+    }
+    if chunks.is_empty() {
+        bail!("World has no chunks in {:?}", args.dimension);
+    }
+
+    let mut counts: BTreeMap<(usize, String), usize> = BTreeMap::new();
+    for (_, status, full) in &chunks {
+        let name = status.clone().unwrap_or_else(|| if *full { "populated" } else { "unpopulated" }.to_string());
+        *counts.entry((stage(status.as_deref()).map_or(usize::MAX, |(i, _)| i), name)).or_default() += 1;
+    }
+    for ((_, status), count) in &counts {
+        println!("{status}: {count}");
+    }
+    let full = chunks.iter().filter(|(_, _, full)| *full).count();
+    eprintln!("{full} of {} chunks are fully generated ({:.1}%)", chunks.len(), full as f64 * 100.0 / chunks.len() as f64);
+
+    if args.list {
+        for ((x, z), status, full) in &chunks {
+            if !full {
+                println!("chunk ({x}, {z}): {}", status.as_deref().unwrap_or("unpopulated"));
+            }
+        }
+    }
+
+    if let Some(path) = &args.output {
+        let scale = args.scale.max(1);
+        let min = (chunks.iter().map(|c| c.0.0).min().unwrap(), chunks.iter().map(|c| c.0.1).min().unwrap());
+        let max = (chunks.iter().map(|c| c.0.0).max().unwrap(), chunks.iter().map(|c| c.0.1).max().unwrap());
+        let mut image = Image::new((max.0 - min.0 + 1) as usize * scale, (max.1 - min.1 + 1) as usize * scale);
+        for ((x, z), status, full) in &chunks {
+            let color = status_color(status.as_deref(), *full);
+            let (px, pz) = ((x - min.0) as usize * scale, (z - min.1) as usize * scale);
+            for dz in 0..scale {
+                for dx in 0..scale {
+                    image.set(px + dx, pz + dz, color);
+                }
+            }
+        }
+        image.save_png(path)?;
+        eprintln!("Wrote {}x{} map with chunk ({}, {}) in its corner to {}", image.width, image.height, min.0, min.1, path.display());
+    }
+
+    Ok(())
+}
+
+// Where the status is among the stages of its version, and how many stages there are
+fn stage(status: Option<&str>) -> Option<(usize, usize)> {
+    let status = status?;
+    [CHUNK_STATUSES, LEGACY_CHUNK_STATUSES].iter()
+        .find_map(|stages| stages.iter().position(|stage| *stage == status).map(|i| (i, stages.len())))
+}
+
+fn status_color(status: Option<&str>, full: bool) -> Rgba {
+    if full {
+        return FULL_COLOR;
+    }
+    let Some((i, stages)) = stage(status) else {
+        return UNKNOWN_COLOR;
+    };
+    let t = i as f32 / (stages - 1) as f32;
+    let [from, to] = PROTO_COLORS;
+    let channel = |c: usize| (from[c] as f32 + (to[c] as f32 - from[c] as f32) * t) as u8;
+    [channel(0), channel(1), channel(2), 255]
+}
@@ -0,0 +1,94 @@
+use anyhow::Result;
+use clap::Args;
+use std::{ fs, path::PathBuf };
+use path_miner::{ Dimension, BlockPos, actions::route_actions, baritone::goto_commands, pathfinding::Pathfinder, mcfunction::{ RouteMarker, route_commands, write_mcfunction } };
+
+#[derive(Args)]
+pub struct TourArgs {
+    /// World folder
+    world: PathBuf,
+    /// Start position as x,y,z
+    #[arg(long)]
+    from: BlockPos,
+    /// Block to collect, e.g. minecraft:diamond_ore
+    #[arg(long, required = true)]
+    block: Vec<String>,
+    #[arg(long, default_value = "overworld")]
+    dimension: Dimension,
+    /// Search radius around the start, in chunks
+    #[arg(long, default_value_t = 2)]
+    radius: i32,
+    /// Also write the route as a .mcfunction file to show it in game
+    #[arg(long, value_name = "FILE")]
+    mcfunction: Option<PathBuf>,
+    /// How the function shows the route, particles or blocks
+    #[arg(long, default_value = "particles")]
+    marker: RouteMarker,
+    /// Also write the route as Baritone #goto commands, one per line
+    #[arg(long, value_name = "FILE")]
+    baritone: Option<PathBuf>,
+    /// TOML file with dig costs per block, tool speeds and a penalty for going up or down
+    #[arg(long, value_name = "FILE")]
+    costs: Option<PathBuf>,
+    /// Go around lava, water, falling blocks and drops where it doesn't cost too much, instead of only warning about them
+    #[arg(long)]
+    avoid_hazards: bool,
+    #[command(flatten)]
+    actions: super::ActionArgs,
+    #[command(flatten)]
+    cache: super::CacheArgs,
+}
+
+pub fn run(args: TourArgs) -> Result<()> {
+    let world = args.cache.open(&args.world)?;
+    let costs = super::path::load_costs(args.costs.as_ref())?;
+
+    let min_chunk = (args.from.chunk_x() - args.radius, args.from.chunk_z() - args.radius);
+    let max_chunk = (args.from.chunk_x() + args.radius, args.from.chunk_z() + args.radius);
+    let view = world.view(args.dimension, min_chunk, max_chunk)?;
+
+    let names: Vec<&str> = args.block.iter().map(String::as_str).collect();
+    let targets = view.find_blocks(&names);
+    eprintln!("Found {} target blocks", targets.len());
+
+    let mut pathfinder = Pathfinder::new(&view).with_costs(&*costs);
+    if args.avoid_hazards {
+        pathfinder = pathfinder.avoid_hazards();
+    }
+    let tour = pathfinder.find_tour(args.from, &targets);
+
+    match args.actions.options() {
+        Some(options) => {
+            for action in route_actions(&view, &tour.route, options) {
+                println!("{action}");
+            }
+        },
+        None => {
+            for step in &tour.route.steps {
+                println!("{}", step);
+            }
+        },
+    }
+
+    for target in &tour.unreachable {
+        eprintln!("Unreachable: {}", target);
+    }
+
+    eprintln!("Tour: {} targets, {} steps, {} blocks to mine, {} to place, cost {}",
+        tour.order.len(), tour.route.steps.len(), tour.route.mined.len(), tour.route.supports.len(), tour.route.cost);
+    for hazard in &tour.route.hazards {
+        eprintln!("{hazard}");
+    }
+
+    if let Some(path) = &args.mcfunction {
+        write_mcfunction(path, &route_commands(&tour.route, &tour.order, args.marker))?;
+        eprintln!("Wrote {}", path.display());
+    }
+
+    if let Some(path) = &args.baritone {
+        fs::write(path, goto_commands(&tour.route, &tour.order).join("\n") + "\n")?;
+        eprintln!("Wrote {}", path.display());
+    }
+
+    args.cache.finish(&world)
+}
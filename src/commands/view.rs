@@ -0,0 +1,93 @@
+use anyhow::Result;
+use clap::Args;
+use std::path::PathBuf;
+use raylib::prelude::*;
+use path_miner::{ World, Dimension, BlockPos, mesh::exposed_faces, render::{ BlockColors, hashed_color } };
+
+#[derive(Args)]
+pub struct ViewArgs {
+    /// World folder
+    world: PathBuf,
+    /// Position to look at, as x,y,z
+    #[arg(long)]
+    center: BlockPos,
+    #[arg(long, default_value = "overworld")]
+    dimension: Dimension,
+    /// Chunks loaded around the center
+    #[arg(long, default_value_t = 1)]
+    radius: i32,
+    /// TOML or JSON file mapping block names to colors
+    #[arg(long, value_name = "FILE")]
+    palette: Option<PathBuf>,
+}
+
+fn block_color(colors: &BlockColors, name: &str) -> Color {
+    let [r, g, b, a] = colors.lookup(name).unwrap_or_else(|| hashed_color(name));
+    Color::new(r, g, b, a)
+}
+
+// Darker sides and bottoms make the shapes readable without lighting
+fn shade(color: Color, normal: [i32; 3]) -> Color {
+    let factor = match normal {
+        [0, 1, 0] => 1.0,
+        [0, -1, 0] => 0.5,
+        [_, 0, 0] => 0.8,
+        _ => 0.65,
+    };
+    Color::new((color.r as f32 * factor) as u8, (color.g as f32 * factor) as u8, (color.b as f32 * factor) as u8, color.a)
+}
+
+pub fn run(args: ViewArgs) -> Result<()> {
+    let world = World::open(&args.world)?;
+    let mut colors = BlockColors::default();
+    if let Some(palette) = &args.palette {
+        colors.load(palette)?;
+    }
+
+    let center_chunk = (args.center.chunk_x(), args.center.chunk_z());
+    let view = world.view(
+        args.dimension,
+        (center_chunk.0 - args.radius, center_chunk.1 - args.radius),
+        (center_chunk.0 + args.radius, center_chunk.1 + args.radius),
+    )?;
+
+    let triangles: Vec<([Vector3; 4], Color)> = exposed_faces(&view).iter()
+        .map(|face| {
+            let corners = face.corners().map(|[x, y, z]| Vector3::new(x as f32, y as f32, z as f32));
+            (corners, shade(block_color(&colors, &face.block.name), face.normal))
+        })
+        .collect();
+
+    eprintln!("Drawing {} faces", triangles.len());
+
+    let (mut rl, thread) = raylib::init()
+        .size(1280, 720)
+        .title("path-miner")
+        .resizable()
+        .build();
+
+    let target = Vector3::new(args.center.x as f32, args.center.y as f32, args.center.z as f32);
+    let mut camera = Camera3D::perspective(target + Vector3::new(24.0, 24.0, 24.0), target, Vector3::up(), 60.0);
+
+    rl.set_camera_mode(camera, CameraMode::CAMERA_FREE);
+    rl.set_target_fps(60);
+
+    while !rl.window_should_close() {
+        rl.update_camera(&mut camera);
+
+        let mut d = rl.begin_drawing(&thread);
+        d.clear_background(Color::SKYBLUE);
+
+        {
+            let mut d3 = d.begin_mode3D(camera);
+            for (corners, color) in &triangles {
+                d3.draw_triangle3D(corners[0], corners[1], corners[2], *color);
+                d3.draw_triangle3D(corners[0], corners[2], corners[3], *color);
+            }
+        }
+
+        d.draw_fps(10, 10);
+    }
+
+    Ok(())
+}
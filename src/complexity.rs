@@ -0,0 +1,210 @@
+use anyhow::{ Result, Context, bail };
+use serde::Deserialize;
+use std::{ collections::HashMap, fs, path::Path };
+
+use crate::{ chunk::{ Chunk, FLATTENING_DATA_VERSION, HeightmapKind, TOP_LEVEL_SECTIONS_DATA_VERSION }, nbt::TagPayload, render::inhabited_fraction };
+
+// Where each part of the score is at its most, so all of them go from 0 to
+// 1 before they're weighted
+const DISTINCT_SCALE: f64 = 64.0;
+const ENTROPY_SCALE: f64 = 6.0;
+const BLOCK_ENTITY_SCALE: f64 = 32.0;
+const SURFACE_SCALE: f64 = 16.0;
+
+// How much each part of a chunk's complexity counts toward its score, 1 each
+// unless a config file says otherwise
+#[derive(Clone, Copy, Debug, PartialEq, Deserialize)]
+#[serde(deny_unknown_fields, default)]
+pub struct ComplexityWeights {
+    pub distinct_blocks: f64,
+    pub palette_entropy: f64,
+    pub block_entities: f64,
+    pub surface_variance: f64,
+    // How long players spent near the chunk
+    pub activity: f64,
+}
+
+impl Default for ComplexityWeights {
+    fn default() -> ComplexityWeights {
+        ComplexityWeights { distinct_blocks: 1.0, palette_entropy: 1.0, block_entities: 1.0, surface_variance: 1.0, activity: 1.0 }
+    }
+}
+
+impl ComplexityWeights {
+    pub fn load(path: impl AsRef<Path>) -> Result<ComplexityWeights> {
+        let path = path.as_ref();
+        let text = fs::read_to_string(path).with_context(|| format!("Could not read {}", path.display()))?;
+        ComplexityWeights::parse(&text).with_context(|| format!("Could not parse {}", path.display()))
+    }
+
+    pub fn parse(text: &str) -> Result<ComplexityWeights> {
+        let weights: ComplexityWeights = toml::from_str(text)?;
+        let parts = [
+            ("distinct_blocks", weights.distinct_blocks),
+            ("palette_entropy", weights.palette_entropy),
+            ("block_entities", weights.block_entities),
+            ("surface_variance", weights.surface_variance),
+            ("activity", weights.activity),
+        ];
+        for (name, weight) in parts {
+            if weight.is_nan() || weight < 0.0 {
+                bail!("Weight of {name} can't be below 0");
+            }
+        }
+        Ok(weights)
+    }
+}
+
+// What makes a chunk stand out: villages, builds and odd terrain have many
+// kinds of blocks, chests and the like, and an uneven surface, while stone
+// and ocean have none of that
+#[derive(Clone, Debug, PartialEq)]
+pub struct ChunkComplexity {
+    pub x: i32,
+    pub z: i32,
+    // Block names across every section's palette
+    pub distinct: usize,
+    // Bits per block, see chunk_complexity
+    pub entropy: f64,
+    pub block_entities: usize,
+    // Standard deviation of the WORLD_SURFACE heights, 0 without the heightmap
+    pub surface_spread: f64,
+    pub inhabited: i64,
+}
+
+impl ChunkComplexity {
+    pub fn score(&self, weights: &ComplexityWeights) -> f64 {
+        weights.distinct_blocks * (self.distinct as f64 / DISTINCT_SCALE).min(1.0)
+            + weights.palette_entropy * (self.entropy / ENTROPY_SCALE).min(1.0)
+            + weights.block_entities * ((self.block_entities as f64).ln_1p() / BLOCK_ENTITY_SCALE.ln_1p()).min(1.0)
+            + weights.surface_variance * (self.surface_spread / SURFACE_SCALE).min(1.0)
+            + weights.activity * inhabited_fraction(self.inhabited) as f64
+    }
+}
+
+// Goes by the palettes in the chunk's NBT and never by its block data, so it
+// works on chunks read without their sections, which Chunk::from_nbt_where
+// with a keep that wants nothing gives without unpacking any block data. The
+// entropy takes each section's blocks as spread evenly over its palette,
+// which is exact for the sections of one block most of a world is.
+// Chunks from before 1.13 have no palettes and count only by the rest.
+pub fn chunk_complexity(chunk: &Chunk) -> ChunkComplexity {
+    let mut blocks: HashMap<&str, f64> = HashMap::new();
+    for palette in palettes(chunk) {
+        for entry in palette {
+            if let Some(TagPayload::String(name)) = entry.get("Name") {
+                *blocks.entry(name).or_default() += 4096.0 / palette.len() as f64;
+            }
+        }
+    }
+    let total: f64 = blocks.values().sum();
+    let entropy = blocks.values().map(|count| count / total).map(|p| -p * p.log2()).sum::<f64>().max(0.0);
+
+    let surface_spread = chunk.heightmap(HeightmapKind::WorldSurface).map_or(0.0, |heights| {
+        let heights: Vec<f64> = heights.iter().flatten().map(|height| *height as f64).collect();
+        let mean = heights.iter().sum::<f64>() / heights.len() as f64;
+        (heights.iter().map(|height| (height - mean).powi(2)).sum::<f64>() / heights.len() as f64).sqrt()
+    });
+
+    ChunkComplexity {
+        x: chunk.x(),
+        z: chunk.z(),
+        distinct: blocks.len(),
+        entropy,
+        block_entities: chunk.block_entities().len(),
+        surface_spread,
+        inhabited: chunk.inhabited_time(),
+    }
+}
+
+// The palette of every section with blocks, from the NBT
+fn palettes(chunk: &Chunk) -> Vec<&[TagPayload]> {
+    let (sections, palette) = if chunk.data_version() >= TOP_LEVEL_SECTIONS_DATA_VERSION {
+        ("sections", "block_states.palette")
+    } else if chunk.data_version() >= FLATTENING_DATA_VERSION {
+        ("Level.Sections", "Palette")
+    } else {
+        return Vec::new();
+    };
+    let Ok(TagPayload::List(sections)) = chunk.nbt().payload.get_path(sections) else {
+        return Vec::new();
+    };
+    sections.iter()
+        .filter_map(|section| match section.get_path(palette) {
+            Ok(TagPayload::List(entries)) if !entries.is_empty() => Some(entries.as_slice()),
+            _ => None,
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::nbt::Tag;
+
+    fn tag(name: &str, payload: TagPayload) -> Tag {
+        Tag { name: name.to_string(), payload }
+    }
+
+    fn block(name: &str) -> TagPayload {
+        TagPayload::Compound(vec![tag("Name", TagPayload::String(name.to_string()))].into())
+    }
+
+    // A 1.20.4 chunk of stone from y -64 to 319, with one section swapped
+    // for a jumble of 30 blocks if asked for. The jumble's block data is cut
+    // short, so only reading the palettes gets through it.
+    fn chunk(x: i32, jumble: bool) -> Tag {
+        let sections = (-4..20)
+            .map(|y| {
+                let block_states = if jumble && y == 0 {
+                    let palette = (0..30).map(|i| block(&format!("minecraft:block_{i}"))).collect();
+                    vec![tag("palette", TagPayload::List(palette)), tag("data", TagPayload::LongArray(vec![0; 3]))]
+                } else {
+                    vec![tag("palette", TagPayload::List(vec![block("minecraft:stone")]))]
+                };
+                TagPayload::Compound(vec![tag("Y", TagPayload::Byte(y)), tag("block_states", TagPayload::Compound(block_states.into()))].into())
+            })
+            .collect();
+        let root = vec![
+            tag("DataVersion", TagPayload::Int(3700)),
+            tag("xPos", TagPayload::Int(x)),
+            tag("zPos", TagPayload::Int(0)),
+            tag("yPos", TagPayload::Int(-4)),
+            tag("sections", TagPayload::List(sections)),
+        ];
+        tag("", TagPayload::Compound(root.into()))
+    }
+
+    #[test]
+    fn ranks_a_jumble_above_stone_from_the_palettes_alone() {
+        // The jumble's block data can't be unpacked, so reading it whole fails
+        assert!(Chunk::from_nbt(chunk(1, true)).is_err());
+
+        let read = |tag| Chunk::from_nbt_where(tag, |_| false).unwrap();
+        let (stone, jumble) = (read(chunk(0, false)), read(chunk(1, true)));
+        assert!(stone.sections().is_empty() && jumble.sections().is_empty());
+
+        let (stone, jumble) = (chunk_complexity(&stone), chunk_complexity(&jumble));
+        assert_eq!((stone.distinct, stone.entropy), (1, 0.0));
+        assert_eq!(jumble.distinct, 31);
+        // 23 sections of stone and one spread over 30 blocks
+        let p: f64 = 23.0 / 24.0;
+        assert!((jumble.entropy - (-p * p.log2() + (1.0 - p) * (30.0f64).log2() - (1.0 - p) * (1.0 - p).log2())).abs() < 1e-9);
+
+        let weights = ComplexityWeights::default();
+        assert_eq!(stone.score(&weights), 1.0 / 64.0);
+        assert!(jumble.score(&weights) > 0.5);
+
+        // Weighing only what the palettes don't tell puts them level
+        let weights = ComplexityWeights::parse("distinct_blocks = 0\npalette_entropy = 0").unwrap();
+        assert_eq!((stone.score(&weights), jumble.score(&weights)), (0.0, 0.0));
+    }
+
+    #[test]
+    fn parses_weights() {
+        let weights = ComplexityWeights::parse("palette_entropy = 2.5\nactivity = 0").unwrap();
+        assert_eq!(weights, ComplexityWeights { palette_entropy: 2.5, activity: 0.0, ..ComplexityWeights::default() });
+        assert!(ComplexityWeights::parse("entropy = 1").is_err());
+        assert!(ComplexityWeights::parse("block_entities = -1").is_err());
+    }
+}
@@ -0,0 +1,142 @@
+use anyhow::{ Result, Context, bail };
+use serde::Deserialize;
+use std::{ collections::HashMap, fs, path::Path };
+
+use crate::{ chunk::BlockType, pathfinding::{ STEP_COST, dig_cost }, pos::BlockPos, query::glob };
+
+// What moving through the world costs the pathfinder. Moves never cost less
+// than STEP_COST, the search relies on that to head for the goal first.
+pub trait CostModel {
+    // Extra cost of digging out a block, None if it can't or shouldn't be dug through
+    fn dig_cost(&self, block: &BlockType) -> Option<u32>;
+
+    // Cost of moving to a neighbouring position, before any digging
+    fn move_cost(&self, _from: BlockPos, _to: BlockPos) -> u32 {
+        STEP_COST
+    }
+}
+
+// The built in costs, see pathfinding::dig_cost
+pub struct DefaultCosts;
+
+impl CostModel for DefaultCosts {
+    fn dig_cost(&self, block: &BlockType) -> Option<u32> {
+        dig_cost(block)
+    }
+}
+
+// Costs read from a TOML file like
+//
+//     # Dig cost of blocks not listed, the built in costs are used if left out
+//     default = 4
+//     # Added for every block moved up or down
+//     vertical = 2
+//
+//     [blocks]
+//     "minecraft:obsidian" = 200
+//     "minecraft:lava" = "forbidden"
+//     "minecraft:water" = 30
+//     "*_ore" = 1
+//
+//     # Dig costs are divided by these, e.g. for the blocks a shovel is good at
+//     [tool_speeds]
+//     "minecraft:dirt" = 4.0
+//     "*" = 1.5
+//
+// Names without a namespace get minecraft: unless they start with *, where *
+// matches any run of characters. An exact name wins over patterns, and a
+// longer pattern over a shorter one. Air is free unless it's listed.
+// Blocks the built in costs forbid, like bedrock and fluids, stay forbidden
+// unless they're listed by their exact name, patterns and the default leave
+// them out.
+pub struct CostConfig {
+    default: Option<u32>,
+    vertical: u32,
+    blocks: Vec<(String, Option<u32>)>,
+    tool_speeds: Vec<(String, f64)>,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct RawCostConfig {
+    default: Option<u32>,
+    #[serde(default)]
+    vertical: u32,
+    #[serde(default)]
+    blocks: HashMap<String, RawCost>,
+    #[serde(default)]
+    tool_speeds: HashMap<String, f64>,
+}
+
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum RawCost {
+    Cost(u32),
+    Word(String),
+}
+
+impl CostConfig {
+    pub fn load(path: impl AsRef<Path>) -> Result<CostConfig> {
+        let path = path.as_ref();
+        let text = fs::read_to_string(path).with_context(|| format!("Could not read {}", path.display()))?;
+        CostConfig::parse(&text).with_context(|| format!("Could not parse {}", path.display()))
+    }
+
+    pub fn parse(text: &str) -> Result<CostConfig> {
+        let raw: RawCostConfig = toml::from_str(text)?;
+
+        let mut blocks = Vec::new();
+        for (name, cost) in raw.blocks {
+            let cost = match cost {
+                RawCost::Cost(cost) => Some(cost),
+                RawCost::Word(word) if word == "forbidden" => None,
+                RawCost::Word(word) => bail!("Cost of {name} is \"{word}\", expected a number or \"forbidden\""),
+            };
+            blocks.push((full_name(&name), cost));
+        }
+
+        let mut tool_speeds = Vec::new();
+        for (name, speed) in raw.tool_speeds {
+            if speed.is_nan() || speed <= 0.0 {
+                bail!("Tool speed for {name} has to be above 0");
+            }
+            tool_speeds.push((full_name(&name), speed));
+        }
+
+        Ok(CostConfig { default: raw.default, vertical: raw.vertical, blocks: most_specific_first(blocks), tool_speeds: most_specific_first(tool_speeds) })
+    }
+}
+
+impl CostModel for CostConfig {
+    fn dig_cost(&self, block: &BlockType) -> Option<u32> {
+        let listed = self.blocks.iter().find(|(pattern, _)| glob(pattern, &block.name));
+        let cost = match listed {
+            Some((name, cost)) if !name.contains('*') => (*cost)?,
+            Some((_, cost)) => {
+                dig_cost(block)?;
+                (*cost)?
+            },
+            None if block.is_air() => 0,
+            None => self.default.unwrap_or(dig_cost(block)?),
+        };
+
+        match self.tool_speeds.iter().find(|(pattern, _)| glob(pattern, &block.name)) {
+            Some((_, speed)) => Some((cost as f64 / speed).round() as u32),
+            None => Some(cost),
+        }
+    }
+
+    fn move_cost(&self, from: BlockPos, to: BlockPos) -> u32 {
+        STEP_COST + if from.y != to.y { self.vertical } else { 0 }
+    }
+}
+
+fn full_name(name: &str) -> String {
+    if name.contains(':') || name.starts_with('*') { name.to_string() } else { format!("minecraft:{name}") }
+}
+
+// Exact names, then patterns from the longest to the shortest
+fn most_specific_first<T>(mut entries: Vec<(String, T)>) -> Vec<(String, T)> {
+    entries.sort_by(|(a, _), (b, _)| a.contains('*').cmp(&b.contains('*')).then(b.len().cmp(&a.len())).then(a.cmp(b)));
+    entries
+}
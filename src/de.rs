@@ -0,0 +1,211 @@
+use serde::de::{ self, DeserializeSeed, IntoDeserializer, Visitor };
+use serde::de::value::{ BorrowedStrDeserializer, SeqDeserializer };
+use serde::Deserialize;
+
+use crate::nbt::{ SerdeError, Tag, TagPayload };
+
+// Reads a Rust value out of a parsed tag tree, following the mapping described
+// in ser::to_payload. Integers and floats convert to wider types, and to
+// narrower ones when the value fits. A missing compound entry reads as None.
+pub fn from_payload<'de, T: Deserialize<'de>>(payload: &'de TagPayload) -> Result<T, SerdeError> {
+    T::deserialize(Deserializer::new(payload))
+}
+
+pub fn from_tag<'de, T: Deserialize<'de>>(tag: &'de Tag) -> Result<T, SerdeError> {
+    from_payload(&tag.payload)
+}
+
+pub struct Deserializer<'de> {
+    payload: &'de TagPayload,
+}
+
+impl<'de> Deserializer<'de> {
+    pub fn new(payload: &'de TagPayload) -> Deserializer<'de> {
+        Deserializer { payload }
+    }
+}
+
+impl<'de> IntoDeserializer<'de, SerdeError> for &'de TagPayload {
+    type Deserializer = Deserializer<'de>;
+
+    fn into_deserializer(self) -> Deserializer<'de> {
+        Deserializer::new(self)
+    }
+}
+
+fn visit_seq<'de, V, I>(visitor: V, items: I) -> Result<V::Value, SerdeError>
+where
+    V: Visitor<'de>,
+    I: Iterator,
+    I::Item: IntoDeserializer<'de, SerdeError>,
+{
+    let mut seq = SeqDeserializer::new(items);
+    let value = visitor.visit_seq(&mut seq)?;
+    seq.end()?;
+    Ok(value)
+}
+
+impl<'de> de::Deserializer<'de> for Deserializer<'de> {
+    type Error = SerdeError;
+
+    fn deserialize_any<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, SerdeError> {
+        match self.payload {
+            TagPayload::Byte(x) => visitor.visit_i8(*x),
+            TagPayload::Short(x) => visitor.visit_i16(*x),
+            TagPayload::Int(x) => visitor.visit_i32(*x),
+            TagPayload::Long(x) => visitor.visit_i64(*x),
+            TagPayload::Float(x) => visitor.visit_f32(*x),
+            TagPayload::Double(x) => visitor.visit_f64(*x),
+            TagPayload::ByteArray(x) => visit_seq(visitor, x.iter().copied()),
+            TagPayload::String(x) => visitor.visit_borrowed_str(x),
+            TagPayload::List(x) => visit_seq(visitor, x.iter()),
+            TagPayload::Compound(x) => visitor.visit_map(CompoundAccess { tags: x.iter(), value: None }),
+            TagPayload::IntArray(x) => visit_seq(visitor, x.iter().copied()),
+            TagPayload::LongArray(x) => visit_seq(visitor, x.iter().copied()),
+        }
+    }
+
+    fn deserialize_bool<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, SerdeError> {
+        match self.payload {
+            TagPayload::Byte(x) => visitor.visit_bool(*x != 0),
+            _ => self.deserialize_any(visitor),
+        }
+    }
+
+    // Unsigned values are stored wrapped in the signed type of the same width
+    fn deserialize_u8<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, SerdeError> {
+        match self.payload {
+            TagPayload::Byte(x) => visitor.visit_u8(*x as u8),
+            _ => self.deserialize_any(visitor),
+        }
+    }
+
+    fn deserialize_u16<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, SerdeError> {
+        match self.payload {
+            TagPayload::Short(x) => visitor.visit_u16(*x as u16),
+            _ => self.deserialize_any(visitor),
+        }
+    }
+
+    fn deserialize_u32<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, SerdeError> {
+        match self.payload {
+            TagPayload::Int(x) => visitor.visit_u32(*x as u32),
+            _ => self.deserialize_any(visitor),
+        }
+    }
+
+    fn deserialize_u64<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, SerdeError> {
+        match self.payload {
+            TagPayload::Long(x) => visitor.visit_u64(*x as u64),
+            _ => self.deserialize_any(visitor),
+        }
+    }
+
+    fn deserialize_bytes<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, SerdeError> {
+        match self.payload {
+            TagPayload::ByteArray(x) => visitor.visit_byte_buf(x.iter().map(|&b| b as u8).collect()),
+            _ => self.deserialize_any(visitor),
+        }
+    }
+
+    fn deserialize_byte_buf<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, SerdeError> {
+        self.deserialize_bytes(visitor)
+    }
+
+    // NBT has no null, so anything that's present is Some
+    fn deserialize_option<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, SerdeError> {
+        visitor.visit_some(self)
+    }
+
+    fn deserialize_unit<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, SerdeError> {
+        visitor.visit_unit()
+    }
+
+    fn deserialize_unit_struct<V: Visitor<'de>>(self, _name: &'static str, visitor: V) -> Result<V::Value, SerdeError> {
+        visitor.visit_unit()
+    }
+
+    fn deserialize_newtype_struct<V: Visitor<'de>>(self, _name: &'static str, visitor: V) -> Result<V::Value, SerdeError> {
+        visitor.visit_newtype_struct(self)
+    }
+
+    fn deserialize_enum<V: Visitor<'de>>(self, _name: &'static str, _variants: &'static [&'static str], visitor: V) -> Result<V::Value, SerdeError> {
+        match self.payload {
+            TagPayload::String(x) => visitor.visit_enum(x.as_str().into_deserializer()),
+            TagPayload::Compound(x) if x.len() == 1 => visitor.visit_enum(Variant { tag: &x[0] }),
+            _ => Err(SerdeError::new("expected a string or a compound with one entry for an enum")),
+        }
+    }
+
+    fn deserialize_ignored_any<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, SerdeError> {
+        visitor.visit_unit()
+    }
+
+    serde::forward_to_deserialize_any! {
+        i8 i16 i32 i64 i128 u128 f32 f64 char str string
+        seq tuple tuple_struct map struct identifier
+    }
+}
+
+struct CompoundAccess<'de> {
+    tags: std::slice::Iter<'de, Tag>,
+    value: Option<&'de TagPayload>,
+}
+
+impl<'de> de::MapAccess<'de> for CompoundAccess<'de> {
+    type Error = SerdeError;
+
+    fn next_key_seed<K: DeserializeSeed<'de>>(&mut self, seed: K) -> Result<Option<K::Value>, SerdeError> {
+        match self.tags.next() {
+            Some(tag) => {
+                self.value = Some(&tag.payload);
+                seed.deserialize(BorrowedStrDeserializer::new(&tag.name)).map(Some)
+            },
+            None => Ok(None),
+        }
+    }
+
+    fn next_value_seed<V: DeserializeSeed<'de>>(&mut self, seed: V) -> Result<V::Value, SerdeError> {
+        let payload = self.value.take().ok_or_else(|| SerdeError::new("compound value read before its key"))?;
+        seed.deserialize(Deserializer::new(payload))
+    }
+
+    fn size_hint(&self) -> Option<usize> {
+        Some(self.tags.len())
+    }
+}
+
+// An enum variant stored as a compound with one entry named after it
+struct Variant<'de> {
+    tag: &'de Tag,
+}
+
+impl<'de> de::EnumAccess<'de> for Variant<'de> {
+    type Error = SerdeError;
+    type Variant = Self;
+
+    fn variant_seed<V: DeserializeSeed<'de>>(self, seed: V) -> Result<(V::Value, Self), SerdeError> {
+        let variant = seed.deserialize(BorrowedStrDeserializer::new(&self.tag.name))?;
+        Ok((variant, self))
+    }
+}
+
+impl<'de> de::VariantAccess<'de> for Variant<'de> {
+    type Error = SerdeError;
+
+    fn unit_variant(self) -> Result<(), SerdeError> {
+        Ok(())
+    }
+
+    fn newtype_variant_seed<T: DeserializeSeed<'de>>(self, seed: T) -> Result<T::Value, SerdeError> {
+        seed.deserialize(Deserializer::new(&self.tag.payload))
+    }
+
+    fn tuple_variant<V: Visitor<'de>>(self, _len: usize, visitor: V) -> Result<V::Value, SerdeError> {
+        de::Deserializer::deserialize_seq(Deserializer::new(&self.tag.payload), visitor)
+    }
+
+    fn struct_variant<V: Visitor<'de>>(self, _fields: &'static [&'static str], visitor: V) -> Result<V::Value, SerdeError> {
+        de::Deserializer::deserialize_map(Deserializer::new(&self.tag.payload), visitor)
+    }
+}
@@ -0,0 +1,113 @@
+use std::fmt;
+
+use crate::nbt::{ Tag, TagPayload };
+use crate::snbt::SnbtFormatter;
+
+// One difference between two NBT trees. Paths use the syntax get_path takes.
+#[derive(Clone)]
+pub enum TagDiff {
+    Added { path: String, value: TagPayload },
+    Removed { path: String, value: TagPayload },
+    Changed { path: String, old: TagPayload, new: TagPayload },
+}
+
+impl TagDiff {
+    pub fn path(&self) -> &str {
+        match self {
+            TagDiff::Added { path, .. } | TagDiff::Removed { path, .. } | TagDiff::Changed { path, .. } => path,
+        }
+    }
+}
+
+impl fmt::Display for TagDiff {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TagDiff::Added { path, value } => write!(f, "+ {path}: {}", short(value)),
+            TagDiff::Removed { path, value } => write!(f, "- {path}: {}", short(value)),
+            TagDiff::Changed { path, old, new } => write!(f, "~ {path}: {} -> {}", short(old), short(new)),
+        }
+    }
+}
+
+// Values as SNBT, except containers and arrays, which are summed up by their size
+fn short(value: &TagPayload) -> String {
+    match value {
+        TagPayload::ByteArray(values) => format!("[{} bytes]", values.len()),
+        TagPayload::IntArray(values) => format!("[{} ints]", values.len()),
+        TagPayload::LongArray(values) => format!("[{} longs]", values.len()),
+        TagPayload::List(items) => format!("[{} entries]", items.len()),
+        TagPayload::Compound(tags) => format!("{{{} tags}}", tags.len()),
+        other => SnbtFormatter::new().format_payload(other),
+    }
+}
+
+impl Tag {
+    // What changed from this tag to the other one, ignoring the root names
+    pub fn diff(&self, other: &Tag) -> Vec<TagDiff> {
+        self.payload.diff(&other.payload)
+    }
+}
+
+impl TagPayload {
+    // Compounds are compared by name and lists by index, so an entry inserted
+    // at the front of a list shows up as every entry after it changing
+    pub fn diff(&self, other: &TagPayload) -> Vec<TagDiff> {
+        let mut diffs = Vec::new();
+        diff_into(self, other, String::new(), &mut diffs);
+        diffs
+    }
+}
+
+fn join(path: &str, name: &str) -> String {
+    if path.is_empty() { name.to_string() } else { format!("{path}.{name}") }
+}
+
+fn diff_into(old: &TagPayload, new: &TagPayload, path: String, diffs: &mut Vec<TagDiff>) {
+    match (old, new) {
+        (TagPayload::Compound(old_tags), TagPayload::Compound(new_tags)) => {
+            for old_tag in old_tags {
+                let tag_path = join(&path, &old_tag.name);
+                match new_tags.get(&old_tag.name) {
+                    Some(new_payload) => diff_into(&old_tag.payload, new_payload, tag_path, diffs),
+                    None => diffs.push(TagDiff::Removed { path: tag_path, value: old_tag.payload.clone() }),
+                }
+            }
+            for new_tag in new_tags {
+                if !old_tags.contains(&new_tag.name) {
+                    diffs.push(TagDiff::Added { path: join(&path, &new_tag.name), value: new_tag.payload.clone() });
+                }
+            }
+        },
+        (TagPayload::List(old_items), TagPayload::List(new_items)) => {
+            for i in 0..old_items.len().max(new_items.len()) {
+                let item_path = format!("{path}[{i}]");
+                match (old_items.get(i), new_items.get(i)) {
+                    (Some(old_item), Some(new_item)) => diff_into(old_item, new_item, item_path, diffs),
+                    (Some(old_item), None) => diffs.push(TagDiff::Removed { path: item_path, value: old_item.clone() }),
+                    (None, Some(new_item)) => diffs.push(TagDiff::Added { path: item_path, value: new_item.clone() }),
+                    (None, None) => {},
+                }
+            }
+        },
+        _ if !same_value(old, new) => diffs.push(TagDiff::Changed { path, old: old.clone(), new: new.clone() }),
+        _ => {},
+    }
+}
+
+// Equality of anything but compounds and lists. Floats are compared bit for
+// bit so NaNs don't show up as changes.
+fn same_value(a: &TagPayload, b: &TagPayload) -> bool {
+    match (a, b) {
+        (TagPayload::Byte(a), TagPayload::Byte(b)) => a == b,
+        (TagPayload::Short(a), TagPayload::Short(b)) => a == b,
+        (TagPayload::Int(a), TagPayload::Int(b)) => a == b,
+        (TagPayload::Long(a), TagPayload::Long(b)) => a == b,
+        (TagPayload::Float(a), TagPayload::Float(b)) => a.to_bits() == b.to_bits(),
+        (TagPayload::Double(a), TagPayload::Double(b)) => a.to_bits() == b.to_bits(),
+        (TagPayload::ByteArray(a), TagPayload::ByteArray(b)) => a == b,
+        (TagPayload::String(a), TagPayload::String(b)) => a == b,
+        (TagPayload::IntArray(a), TagPayload::IntArray(b)) => a == b,
+        (TagPayload::LongArray(a), TagPayload::LongArray(b)) => a == b,
+        _ => false,
+    }
+}
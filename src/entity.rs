@@ -0,0 +1,131 @@
+use anyhow::{ Result, Context, bail };
+use std::path::Path;
+
+use crate::{ nbt::{ Tag, TagPayload }, pos::BlockPos, region::read_region };
+
+pub struct Entity {
+    id: String,
+    pos: [f64; 3],
+    nbt: TagPayload,
+}
+
+impl Entity {
+    pub fn from_nbt(nbt: TagPayload) -> Result<Entity> {
+        let id = match nbt.get("id") {
+            Some(TagPayload::String(id)) => id.clone(),
+            Some(_) => bail!("Entity id is not a string"),
+            None => bail!("Entity has no id"),
+        };
+
+        let pos = match nbt.get("Pos") {
+            Some(TagPayload::List(pos)) => match pos.as_slice() {
+                [TagPayload::Double(x), TagPayload::Double(y), TagPayload::Double(z)] => [*x, *y, *z],
+                _ => bail!("{id} Pos is not three doubles"),
+            },
+            _ => bail!("{id} has no Pos"),
+        };
+
+        Ok(Entity { id, pos, nbt })
+    }
+
+    pub fn id(&self) -> &str {
+        &self.id
+    }
+
+    pub fn pos(&self) -> [f64; 3] {
+        self.pos
+    }
+
+    // The block the entity's feet are in
+    pub fn block_pos(&self) -> BlockPos {
+        BlockPos::new(self.pos[0].floor() as i32, self.pos[1].floor() as i32, self.pos[2].floor() as i32)
+    }
+
+    pub fn nbt(&self) -> &TagPayload {
+        &self.nbt
+    }
+}
+
+pub struct EntityChunk {
+    x: i32,
+    z: i32,
+    entities: Vec<Entity>,
+}
+
+impl EntityChunk {
+    // Reads chunks from entities/ (1.17+) as well as terrain chunks. Before 1.17
+    // those kept their entities in Level.Entities, since 1.18 they have none.
+    pub fn from_nbt(nbt: Tag) -> Result<EntityChunk> {
+        let root = &nbt.payload;
+        let coord = |compound: &TagPayload, name: &str| match compound.get(name) {
+            Some(TagPayload::Int(i)) => Ok(*i),
+            _ => bail!("Missing {name} tag"),
+        };
+
+        let (x, z, list) = if let Some(position) = root.get("Position") {
+            match position {
+                TagPayload::IntArray(pos) if pos.len() == 2 => (pos[0], pos[1], root.get("Entities")),
+                _ => bail!("Position is not two ints"),
+            }
+        } else if let Some(level) = root.get("Level") {
+            (coord(level, "xPos")?, coord(level, "zPos")?, level.get("Entities"))
+        } else {
+            (coord(root, "xPos")?, coord(root, "zPos")?, None)
+        };
+
+        let mut entities = Vec::new();
+        match list {
+            Some(TagPayload::List(list)) => {
+                for (i, entity) in list.iter().enumerate() {
+                    entities.push(Entity::from_nbt(entity.clone()).with_context(|| format!("Invalid entity {i} in chunk ({x}, {z})"))?);
+                }
+            },
+            Some(_) => bail!("Entities is not a list"),
+            None => {},
+        }
+
+        Ok(EntityChunk { x, z, entities })
+    }
+
+    pub fn x(&self) -> i32 {
+        self.x
+    }
+
+    pub fn z(&self) -> i32 {
+        self.z
+    }
+
+    pub fn entities(&self) -> &[Entity] {
+        &self.entities
+    }
+}
+
+pub struct EntityRegion {
+    chunks: Vec<EntityChunk>,
+}
+
+impl EntityRegion {
+    // Chunks that can't be read are reported and skipped
+    pub fn load(path: impl AsRef<Path>) -> Result<EntityRegion> {
+        let path = path.as_ref();
+        let tags = read_region(path).with_context(|| format!("Could not read region {}", path.display()))?;
+
+        let mut chunks = Vec::new();
+        for tag in tags {
+            match EntityChunk::from_nbt(tag) {
+                Ok(chunk) => chunks.push(chunk),
+                Err(e) => log::warn!("Skipping entity chunk: {e:#}"),
+            }
+        }
+
+        Ok(EntityRegion { chunks })
+    }
+
+    pub fn chunks(&self) -> &[EntityChunk] {
+        &self.chunks
+    }
+
+    pub fn entities(&self) -> impl Iterator<Item = &Entity> {
+        self.chunks.iter().flat_map(|chunk| chunk.entities.iter())
+    }
+}
@@ -0,0 +1,223 @@
+use anyhow::{ Result, bail };
+use std::{ collections::{ HashMap, HashSet, VecDeque }, fmt, str::FromStr };
+
+use crate::{ actions::Action, chunk::BlockType, hazards::{ is_lava, is_water }, pathfinding::{ Pathfinder, Route }, pos::BlockPos, world::WorldView };
+
+// Added for digging out a block that lets a flood into the route when floods are only penalized
+pub const FLOOD_COST: u32 = 200;
+
+// A flood stops being followed after this many blocks, it's bad enough by then
+const MAX_WET: usize = 4096;
+
+// Routes planned again around floods before giving up
+const MAX_REPLANS: usize = 8;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Fluid {
+    Water,
+    Lava,
+}
+
+impl Fluid {
+    pub fn of(block: &BlockType) -> Option<Fluid> {
+        if is_lava(block) {
+            Some(Fluid::Lava)
+        } else if is_water(block) {
+            Some(Fluid::Water)
+        } else {
+            None
+        }
+    }
+
+    // How far it flows over a floor from a source, in the overworld
+    pub fn reach(self) -> u32 {
+        match self {
+            Fluid::Water => 7,
+            Fluid::Lava => 3,
+        }
+    }
+
+    // Flowing fluid has come part of the way already. Levels 8 and up are
+    // falling, which spreads like a source where it lands.
+    fn start(self, block: &BlockType) -> u32 {
+        let level: u32 = block.property("level").and_then(|level| level.parse().ok()).unwrap_or(0);
+        match (self, level) {
+            (_, 8..) => 0,
+            (Fluid::Water, level) => level,
+            // Lava drops two levels a block in the overworld
+            (Fluid::Lava, level) => level / 2,
+        }
+    }
+}
+
+// Worst first
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum FloodSeverity {
+    // Lava over blocks the miner walks through
+    Deadly,
+    // Water over blocks the miner walks through
+    Flooded,
+    // Gets into the tunnel but not where the miner walks
+    Contained,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Flood {
+    pub fluid: Fluid,
+    // The fluid block that pours in
+    pub source: BlockPos,
+    // The mined block it pours into, sealed by putting a block at source
+    pub breach: BlockPos,
+    // Feet positions of the route with fluid at the feet or head, in route order
+    pub flooded: Vec<BlockPos>,
+    // Blocks the fluid ends up in, at most MAX_WET
+    pub wet: usize,
+    pub severity: FloodSeverity,
+}
+
+impl fmt::Display for Flood {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let fluid = match self.fluid {
+            Fluid::Water => "Water",
+            Fluid::Lava => "Lava",
+        };
+        write!(f, "{fluid} at {} pours in when {} is mined", self.source, self.breach)?;
+        match (self.flooded.first(), self.flooded.last()) {
+            (Some(first), Some(last)) => write!(f, " and floods {} steps from {first} to {last}", self.flooded.len()),
+            _ => write!(f, ", filling {} blocks off the route", self.wet),
+        }
+    }
+}
+
+// Lets every fluid next to a mined block pour into the tunnel: down as far
+// as there's room, and where it can't go down, out to the side up to its
+// reach, through mined blocks and air. Fluid doesn't flow up, so blocks
+// under a mined one stay put. Worst floods first, then by where they break
+// in along the route.
+pub fn simulate_floods(view: &WorldView, route: &Route) -> Vec<Flood> {
+    let mined: HashSet<BlockPos> = route.mined.iter().copied().collect();
+    let is_open = |pos: BlockPos| mined.contains(&pos) || view.block_at(pos).is_some_and(|block| block.is_air() && !block.is_outside_world());
+
+    let mut floods = Vec::new();
+    let mut seen = HashSet::new();
+    for (i, breach) in route.mined.iter().enumerate() {
+        for source in [breach.offset(0, 1, 0), breach.offset(1, 0, 0), breach.offset(-1, 0, 0), breach.offset(0, 0, 1), breach.offset(0, 0, -1)] {
+            if mined.contains(&source) || !seen.insert(source) {
+                continue;
+            }
+            let Some(block) = view.block_at(source) else {
+                continue;
+            };
+            let Some(fluid) = Fluid::of(block) else {
+                continue;
+            };
+
+            let wet = spread(source, fluid, fluid.start(block), is_open);
+            let flooded: Vec<BlockPos> = route.steps.iter().copied()
+                .filter(|feet| wet.contains(feet) || wet.contains(&feet.offset(0, 1, 0)))
+                .collect();
+            let severity = match (fluid, flooded.is_empty()) {
+                (_, true) => FloodSeverity::Contained,
+                (Fluid::Lava, false) => FloodSeverity::Deadly,
+                (Fluid::Water, false) => FloodSeverity::Flooded,
+            };
+            floods.push((i, Flood { fluid, source, breach: *breach, flooded, wet: wet.len(), severity }));
+        }
+    }
+
+    floods.sort_by_key(|(i, flood)| (flood.severity, *i));
+    floods.into_iter().map(|(_, flood)| flood).collect()
+}
+
+// Open blocks the fluid reaches from source, which counts as start blocks
+// out from a source already
+fn spread(source: BlockPos, fluid: Fluid, start: u32, is_open: impl Fn(BlockPos) -> bool) -> HashSet<BlockPos> {
+    // The least distance from a source each block was reached at
+    let mut reached: HashMap<BlockPos, u32> = HashMap::new();
+    let mut queue = VecDeque::from([(source, start)]);
+    let mut wet = HashSet::new();
+
+    while let Some((pos, distance)) = queue.pop_front() {
+        if wet.len() >= MAX_WET {
+            break;
+        }
+        let below = pos.offset(0, -1, 0);
+        let next: Vec<(BlockPos, u32)> = if is_open(below) {
+            vec![(below, 0)]
+        } else if distance < fluid.reach() {
+            [(1, 0), (-1, 0), (0, 1), (0, -1)].into_iter()
+                .map(|(dx, dz)| (pos.offset(dx, 0, dz), distance + 1))
+                .filter(|(next, _)| is_open(*next))
+                .collect()
+        } else {
+            Vec::new()
+        };
+        for (next, distance) in next {
+            if reached.get(&next).is_none_or(|known| distance < *known) {
+                reached.insert(next, distance);
+                wet.insert(next);
+                queue.push_back((next, distance));
+            }
+        }
+    }
+    wet
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum FloodResponse {
+    // Plan around every block that lets a flood onto the route
+    Reject,
+    // Make digging those blocks cost FLOOD_COST more, floods may be left
+    Penalize,
+    // Keep the route and seal the floods off, see seal_floods
+    Seal,
+}
+
+impl FromStr for FloodResponse {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s {
+            "reject" => Ok(FloodResponse::Reject),
+            "penalize" => Ok(FloodResponse::Penalize),
+            "seal" => Ok(FloodResponse::Seal),
+            _ => bail!("Unknown flood response \"{s}\", expected reject, penalize or seal"),
+        }
+    }
+}
+
+// Finds a route and the floods it lets loose, planning again around the
+// breaches of floods that reach the route as the response says. None if
+// there's no route, or for Reject none that stays dry.
+pub fn find_dry_route(pathfinder: &mut Pathfinder, view: &WorldView, start: BlockPos, goal: BlockPos, response: FloodResponse) -> Option<(Route, Vec<Flood>)> {
+    for _ in 0..MAX_REPLANS {
+        let route = pathfinder.find_route(start, goal)?;
+        let floods = simulate_floods(view, &route);
+        let breaches: Vec<BlockPos> = floods.iter()
+            .filter(|flood| flood.severity != FloodSeverity::Contained)
+            .map(|flood| flood.breach)
+            .collect();
+        let fresh = match response {
+            FloodResponse::Seal => 0,
+            FloodResponse::Reject => breaches.iter().filter(|breach| pathfinder.avoid(**breach, None)).count(),
+            FloodResponse::Penalize => breaches.iter().filter(|breach| pathfinder.avoid(**breach, Some(FLOOD_COST))).count(),
+        };
+        if fresh == 0 {
+            return Some((route, floods));
+        }
+    }
+    None
+}
+
+// Puts a block at each flood's source right after its breach is mined,
+// unless something's placed there already
+pub fn seal_floods(actions: &mut Vec<Action>, floods: &[Flood]) {
+    for flood in floods {
+        if actions.contains(&Action::Place(flood.source)) {
+            continue;
+        }
+        if let Some(i) = actions.iter().position(|action| *action == Action::Mine(flood.breach)) {
+            actions.insert(i + 1, Action::Place(flood.source));
+        }
+    }
+}
@@ -0,0 +1,191 @@
+use anyhow::{ Result, Context };
+
+use crate::{ chunk::BlockEntity, nbt::TagPayload, pos::BlockPos, region::{ chunk_index_in_region, Region }, world::{ Dimension, World } };
+
+// The end's outer islands start this far from the origin, past the void
+// around the main island
+pub const MAIN_ISLAND_RADIUS: i32 = 1000;
+
+// An end gateway and where it sends players. The 20 gateways around the
+// main island only get an exit once someone has gone through them.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Gateway {
+    pub pos: BlockPos,
+    pub exit: Option<BlockPos>,
+    // Whether players come out at the exit itself rather than on the
+    // highest block near it
+    pub exact: bool,
+}
+
+impl Gateway {
+    // None for block entities that aren't end gateways. The exit was an
+    // ExitPortal compound before 1.20.5 and has been an exit_portal int
+    // array since.
+    pub fn from_block_entity(block_entity: &BlockEntity) -> Option<Gateway> {
+        if block_entity.id() != "minecraft:end_gateway" && block_entity.id() != "EndGateway" {
+            return None;
+        }
+        let nbt = block_entity.nbt();
+
+        let exit = match (nbt.get("exit_portal"), nbt.get("ExitPortal")) {
+            (Some(TagPayload::IntArray(exit)), _) if exit.len() == 3 => Some(BlockPos::new(exit[0], exit[1], exit[2])),
+            (_, Some(exit)) => match (exit.get("X"), exit.get("Y"), exit.get("Z")) {
+                (Some(TagPayload::Int(x)), Some(TagPayload::Int(y)), Some(TagPayload::Int(z))) => Some(BlockPos::new(*x, *y, *z)),
+                _ => None,
+            },
+            _ => None,
+        };
+        let exact = matches!(nbt.get("ExactTeleport"), Some(TagPayload::Byte(1)));
+
+        Some(Gateway { pos: block_entity.pos(), exit, exact })
+    }
+
+    pub fn is_on_main_island(&self) -> bool {
+        !is_outer_island(self.pos.x, self.pos.z)
+    }
+}
+
+// Whether a block position lies out among the outer islands
+pub fn is_outer_island(x: i32, z: i32) -> bool {
+    let (x, z) = (x as i64, z as i64);
+    x * x + z * z > MAIN_ISLAND_RADIUS as i64 * MAIN_ISLAND_RADIUS as i64
+}
+
+// Going by the chunk's middle, so a chunk belongs to one side of the radius
+pub fn is_outer_island_chunk(chunk_x: i32, chunk_z: i32) -> bool {
+    is_outer_island(chunk_x * 16 + 8, chunk_z * 16 + 8)
+}
+
+// A region of the end with chunks beyond the main island
+#[derive(Clone, Debug)]
+pub struct OuterIslandRegion {
+    pub x: i32,
+    pub z: i32,
+    // Generated chunks of the region past MAIN_ISLAND_RADIUS
+    pub chunks: usize,
+}
+
+// The end regions that have outer island chunks, going by the region
+// headers alone without reading any chunk
+pub fn outer_island_regions(world: &World) -> Result<Vec<OuterIslandRegion>> {
+    let mut regions = Vec::new();
+    for info in world.regions_in(Dimension::End) {
+        if info.is_empty() {
+            continue;
+        }
+        let region = Region::open(&info.path).with_context(|| format!("Could not read region {}", info.path.display()))?;
+
+        let mut chunks = 0;
+        for chunk_z in info.z * 32..info.z * 32 + 32 {
+            for chunk_x in info.x * 32..info.x * 32 + 32 {
+                if is_outer_island_chunk(chunk_x, chunk_z) && region.has_chunk(chunk_index_in_region(chunk_x, chunk_z)) {
+                    chunks += 1;
+                }
+            }
+        }
+        if chunks > 0 {
+            regions.push(OuterIslandRegion { x: info.x, z: info.z, chunks });
+        }
+    }
+    regions.sort_by_key(|region| (region.x, region.z));
+    Ok(regions)
+}
+
+// The gateway a link comes back through: the one closest to the exit, if
+// it's near enough that the game would have built it for this link
+pub fn return_gateway<'a>(gateway: &Gateway, gateways: &'a [Gateway]) -> Option<&'a Gateway> {
+    let exit = gateway.exit?;
+    gateways.iter()
+        .filter(|other| other.pos != gateway.pos)
+        .min_by_key(|other| other.pos.manhattan_distance(&exit))
+        .filter(|other| other.pos.manhattan_distance(&exit) <= 16)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::nbt::Tag;
+
+    fn tag(name: &str, payload: TagPayload) -> Tag {
+        Tag { name: name.to_string(), payload }
+    }
+
+    #[test]
+    fn round_trips_a_gateway_through_nbt() {
+        let exit = vec![tag("X", TagPayload::Int(-1012)), tag("Y", TagPayload::Int(72)), tag("Z", TagPayload::Int(1003))];
+        let gateway = vec![
+            tag("id", TagPayload::String("minecraft:end_gateway".to_string())),
+            tag("x", TagPayload::Int(-86)),
+            tag("y", TagPayload::Int(75)),
+            tag("z", TagPayload::Int(40)),
+            tag("Age", TagPayload::Long(12000)),
+            tag("ExactTeleport", TagPayload::Byte(1)),
+            tag("ExitPortal", TagPayload::Compound(exit.into())),
+        ];
+
+        let mut bytes = Vec::new();
+        tag("", TagPayload::Compound(gateway.into())).write(&mut bytes).unwrap();
+        let parsed = Tag::parse(&mut bytes.iter()).unwrap();
+        let block_entity = BlockEntity::from_nbt(parsed.payload).unwrap();
+
+        let gateway = Gateway::from_block_entity(&block_entity).unwrap();
+        assert_eq!(gateway, Gateway { pos: BlockPos::new(-86, 75, 40), exit: Some(BlockPos::new(-1012, 72, 1003)), exact: true });
+        assert!(gateway.is_on_main_island());
+        assert!(is_outer_island(gateway.exit.unwrap().x, gateway.exit.unwrap().z));
+    }
+
+    #[test]
+    fn reads_the_1_20_5_exit_array() {
+        let gateway = vec![
+            tag("id", TagPayload::String("minecraft:end_gateway".to_string())),
+            tag("x", TagPayload::Int(1010)),
+            tag("y", TagPayload::Int(60)),
+            tag("z", TagPayload::Int(-5)),
+            tag("exit_portal", TagPayload::IntArray(vec![96, 75, 0])),
+        ];
+        let block_entity = BlockEntity::from_nbt(TagPayload::Compound(gateway.into())).unwrap();
+
+        let gateway = Gateway::from_block_entity(&block_entity).unwrap();
+        assert_eq!(gateway.exit, Some(BlockPos::new(96, 75, 0)));
+        assert!(!gateway.exact);
+        assert!(!gateway.is_on_main_island());
+    }
+
+    #[test]
+    fn ignores_other_block_entities() {
+        let chest = vec![
+            tag("id", TagPayload::String("minecraft:chest".to_string())),
+            tag("x", TagPayload::Int(0)),
+            tag("y", TagPayload::Int(64)),
+            tag("z", TagPayload::Int(0)),
+        ];
+        let block_entity = BlockEntity::from_nbt(TagPayload::Compound(chest.into())).unwrap();
+        assert!(Gateway::from_block_entity(&block_entity).is_none());
+    }
+
+    #[test]
+    fn splits_chunks_at_the_main_island_radius() {
+        // Chunk 62 holds blocks 992 to 1007, its middle at x 1000 and z 8 is just outside
+        assert!(!is_outer_island_chunk(61, 0));
+        assert!(is_outer_island_chunk(62, 0));
+        // Chunk -63 holds blocks -1008 to -993, its middle at x -1000 too
+        assert!(!is_outer_island_chunk(-62, 0));
+        assert!(is_outer_island_chunk(-63, 0));
+        // Diagonally the radius is reached sooner
+        assert!(is_outer_island_chunk(45, -45));
+        assert!(!is_outer_island(0, -MAIN_ISLAND_RADIUS));
+        assert!(is_outer_island(0, -MAIN_ISLAND_RADIUS - 1));
+    }
+
+    #[test]
+    fn links_gateways_to_the_one_at_their_exit() {
+        let main = Gateway { pos: BlockPos::new(96, 75, 0), exit: Some(BlockPos::new(1100, 70, 3)), exact: false };
+        let outer = Gateway { pos: BlockPos::new(1098, 72, 1), exit: Some(BlockPos::new(96, 75, 0)), exact: true };
+        let far = Gateway { pos: BlockPos::new(-2000, 70, 0), exit: None, exact: false };
+        let gateways = vec![main.clone(), outer.clone(), far.clone()];
+
+        assert_eq!(return_gateway(&main, &gateways), Some(&outer));
+        assert_eq!(return_gateway(&outer, &gateways), Some(&main));
+        assert_eq!(return_gateway(&far, &gateways), None);
+    }
+}
@@ -0,0 +1,102 @@
+use std::fmt;
+
+use crate::{ chunk::BlockType, pos::BlockPos, world::WorldView };
+
+// Falls further than this hurt
+pub const SAFE_FALL: u32 = 3;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum HazardKind {
+    // Pours into the tunnel once the block between is mined
+    Lava,
+    Water,
+    // Sand, gravel and the like drop into the tunnel when the block under them is mined
+    FallingBlock,
+    // Air under the route for more than SAFE_FALL blocks, or nothing at all
+    Drop,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct Hazard {
+    pub kind: HazardKind,
+    // The lava, water or falling block, or for drops the block to stand on
+    pub pos: BlockPos,
+    // The mined block that lets it loose, None for drops
+    pub mined: Option<BlockPos>,
+}
+
+impl fmt::Display for Hazard {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match (self.kind, self.mined) {
+            (HazardKind::Lava, Some(mined)) => write!(f, "Lava at {}, place a block there before mining {}", self.pos, mined),
+            (HazardKind::Water, Some(mined)) => write!(f, "Water at {}, place a block there before mining {}", self.pos, mined),
+            (HazardKind::FallingBlock, Some(mined)) => write!(f, "Falling block at {}, it drops when {} is mined", self.pos, mined),
+            _ => write!(f, "Drop under {}, place a block there before walking on", self.pos),
+        }
+    }
+}
+
+pub fn is_lava(block: &BlockType) -> bool {
+    matches!(block.name.as_str(), "minecraft:lava" | "minecraft:flowing_lava")
+}
+
+// Water and everything that holds it, since waterlogged blocks spill too
+pub fn is_water(block: &BlockType) -> bool {
+    matches!(block.name.as_str(), "minecraft:water" | "minecraft:flowing_water" | "minecraft:bubble_column"
+        | "minecraft:kelp" | "minecraft:kelp_plant" | "minecraft:seagrass" | "minecraft:tall_seagrass")
+        || block.property("waterlogged") == Some("true")
+}
+
+pub fn is_falling(block: &BlockType) -> bool {
+    matches!(block.name.as_str(), "minecraft:sand" | "minecraft:red_sand" | "minecraft:gravel"
+        | "minecraft:suspicious_sand" | "minecraft:suspicious_gravel" | "minecraft:anvil" | "minecraft:chipped_anvil"
+        | "minecraft:damaged_anvil" | "minecraft:dragon_egg" | "minecraft:pointed_dripstone")
+        || block.name.ends_with("_concrete_powder")
+}
+
+// What mining block lets loose. Neighbours that are part of the tunnel don't
+// count, they're dug out or walked through anyway.
+pub fn exposed_by(view: &WorldView, block: BlockPos, in_tunnel: impl Fn(&BlockPos) -> bool) -> Vec<Hazard> {
+    let mut hazards = Vec::new();
+    for next in block.neighbors() {
+        if in_tunnel(&next) {
+            continue;
+        }
+        let Some(neighbor) = view.block_at(next) else {
+            continue;
+        };
+
+        let kind = if is_lava(neighbor) {
+            HazardKind::Lava
+        } else if is_water(neighbor) {
+            HazardKind::Water
+        } else if is_falling(neighbor) && next == block.offset(0, 1, 0) {
+            HazardKind::FallingBlock
+        } else {
+            continue;
+        };
+        hazards.push(Hazard { kind, pos: next, mined: Some(block) });
+    }
+    hazards
+}
+
+// How far it is to fall from feet, None past the bottom of the loaded blocks
+// or of the world
+pub fn fall_height(view: &WorldView, feet: BlockPos) -> Option<u32> {
+    let mut below = feet.offset(0, -1, 0);
+    let mut height = 0;
+    while view.block_at(below).filter(|block| !block.is_outside_world())?.is_air() {
+        height += 1;
+        below = below.offset(0, -1, 0);
+    }
+    Some(height)
+}
+
+// The block under feet if standing there means a fall that hurts
+pub fn drop_under(view: &WorldView, feet: BlockPos, in_tunnel: impl Fn(&BlockPos) -> bool) -> Option<Hazard> {
+    let below = feet.offset(0, -1, 0);
+    if in_tunnel(&below) || fall_height(view, feet).is_some_and(|height| height <= SAFE_FALL) {
+        return None;
+    }
+    Some(Hazard { kind: HazardKind::Drop, pos: below, mined: None })
+}
@@ -0,0 +1,164 @@
+use std::{ fmt, ops::RangeInclusive };
+
+use crate::{ chunk::TOP_LEVEL_SECTIONS_DATA_VERSION, nbt::TagPayload, world::Dimension };
+
+// The blocks a dimension has from bottom to top: min_y is the lowest and
+// height how many there are. The overworld went from 0..256 to -64..320 in
+// 1.18, and datapacks can give a dimension any height in steps of 16.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct WorldHeight {
+    pub min_y: i32,
+    pub height: u32,
+}
+
+impl WorldHeight {
+    // Every dimension before 1.18, and the nether and the end since
+    pub const LEGACY: WorldHeight = WorldHeight { min_y: 0, height: 256 };
+    pub const OVERWORLD: WorldHeight = WorldHeight { min_y: -64, height: 384 };
+
+    pub fn new(min_y: i32, height: u32) -> WorldHeight {
+        WorldHeight { min_y, height }
+    }
+
+    // What vanilla gives the dimension at a DataVersion, without datapacks
+    pub fn for_data_version(dimension: Dimension, data_version: i32) -> WorldHeight {
+        if dimension == Dimension::Overworld && data_version >= TOP_LEVEL_SECTIONS_DATA_VERSION {
+            WorldHeight::OVERWORLD
+        } else {
+            WorldHeight::LEGACY
+        }
+    }
+
+    // The heights a chunk's NBT stores sections for. Before 1.18 it's always
+    // 0..256. Since, every section of the dimension's height is stored, light
+    // only ones above and below aside, which is all there is to go by. None
+    // when there are no sections with blocks to tell.
+    pub fn of_chunk_nbt(root: &TagPayload) -> Option<WorldHeight> {
+        let data_version = match root.get("DataVersion") {
+            Some(TagPayload::Int(data_version)) => *data_version,
+            _ => 0,
+        };
+        if data_version < TOP_LEVEL_SECTIONS_DATA_VERSION {
+            return Some(WorldHeight::LEGACY);
+        }
+
+        let Some(TagPayload::List(sections)) = root.get("sections") else {
+            return None;
+        };
+        let with_blocks: Vec<i32> = sections.iter()
+            .filter(|section| section.get("block_states").is_some())
+            .filter_map(|section| match section.get("Y") {
+                Some(TagPayload::Byte(y)) => Some(*y as i32),
+                Some(TagPayload::Int(y)) => Some(*y),
+                _ => None,
+            })
+            .collect();
+        let min_section = match root.get("yPos") {
+            Some(TagPayload::Int(y)) => *y,
+            _ => *with_blocks.iter().min()?,
+        };
+        (!with_blocks.is_empty()).then(|| WorldHeight::new(min_section * 16, with_blocks.len() as u32 * 16))
+    }
+
+    // The highest y blocks can be at
+    pub fn max_y(&self) -> i32 {
+        self.min_y + self.height as i32 - 1
+    }
+
+    pub fn contains(&self, y: i32) -> bool {
+        y >= self.min_y && y <= self.max_y()
+    }
+
+    // The section Ys from the bottom to the top
+    pub fn sections(&self) -> RangeInclusive<i32> {
+        self.min_y.div_euclid(16)..=self.max_y().div_euclid(16)
+    }
+
+    // Heightmaps store heights above min_y from 0 to the height itself, in
+    // as many bits as that takes, 9 for both 256 and 384 blocks
+    pub fn heightmap_bits(&self) -> usize {
+        (u32::BITS - self.height.leading_zeros()) as usize
+    }
+
+    // The least height holding both
+    pub fn union(self, other: WorldHeight) -> WorldHeight {
+        let min_y = self.min_y.min(other.min_y);
+        let max_y = self.max_y().max(other.max_y());
+        WorldHeight::new(min_y, (max_y - min_y + 1) as u32)
+    }
+}
+
+impl fmt::Display for WorldHeight {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "y {} to {}", self.min_y, self.max_y())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{ World, nbt::Tag, region::put_chunks };
+    use std::fs;
+
+    fn tag(name: &str, payload: TagPayload) -> Tag {
+        Tag { name: name.to_string(), payload }
+    }
+
+    fn compound(tags: Vec<Tag>) -> TagPayload {
+        TagPayload::Compound(tags.into())
+    }
+
+    // A 1.20.4 chunk of air from y 0 to 63
+    fn low_chunk() -> Tag {
+        let air = compound(vec![tag("Name", TagPayload::String("minecraft:air".to_string()))]);
+        let sections = (0..4)
+            .map(|y| compound(vec![tag("Y", TagPayload::Byte(y)), tag("block_states", compound(vec![tag("palette", TagPayload::List(vec![air.clone()]))]))]))
+            .collect();
+        let root = vec![
+            tag("DataVersion", TagPayload::Int(3700)),
+            tag("xPos", TagPayload::Int(0)),
+            tag("zPos", TagPayload::Int(0)),
+            tag("yPos", TagPayload::Int(0)),
+            tag("sections", TagPayload::List(sections)),
+        ];
+        tag("", compound(root))
+    }
+
+    #[test]
+    fn takes_the_height_from_level_dat_datapacks_and_chunks() {
+        let dir = std::env::temp_dir().join(format!("path-miner-height-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        let pack = dir.join("datapacks").join("tall").join("data").join("custom").join("dimension_type");
+        fs::create_dir_all(&pack).unwrap();
+        fs::write(pack.join("tall.json"), r#"{ "min_y": -256, "height": 1024, "ultrawarm": false }"#).unwrap();
+        fs::create_dir_all(dir.join("DIM1").join("region")).unwrap();
+        put_chunks(dir.join("DIM1").join("region").join("r.0.0.mca"), &[(0, &low_chunk())]).unwrap();
+
+        // The overworld by a datapack's id, the nether written out and the end not listed
+        let nether = compound(vec![tag("min_y", TagPayload::Int(-128)), tag("height", TagPayload::Int(512))]);
+        let dimensions = vec![
+            tag("minecraft:overworld", compound(vec![tag("type", TagPayload::String("custom:tall".to_string()))])),
+            tag("minecraft:the_nether", compound(vec![tag("type", nether)])),
+        ];
+        let settings = vec![tag("seed", TagPayload::Long(1)), tag("dimensions", compound(dimensions))];
+        let data = vec![tag("DataVersion", TagPayload::Int(3700)), tag("WorldGenSettings", compound(settings))];
+        let mut level = Vec::new();
+        tag("", compound(vec![tag("Data", compound(data))])).write(&mut level).unwrap();
+        fs::write(dir.join("level.dat"), level).unwrap();
+
+        let world = World::open(&dir).unwrap();
+        assert_eq!(world.height(Dimension::Overworld).unwrap(), WorldHeight::new(-256, 1024));
+        assert_eq!(world.height(Dimension::Nether).unwrap(), WorldHeight::new(-128, 512));
+        assert_eq!(world.height(Dimension::End).unwrap(), WorldHeight::new(0, 64));
+
+        fs::remove_file(dir.join("level.dat")).unwrap();
+        fs::remove_dir_all(dir.join("datapacks")).unwrap();
+        let world = World::open(&dir).unwrap();
+        assert_eq!(world.height(Dimension::Overworld).unwrap(), WorldHeight::LEGACY);
+        assert_eq!(WorldHeight::OVERWORLD.union(WorldHeight::new(0, 512)), WorldHeight::new(-64, 576));
+        assert_eq!(WorldHeight::OVERWORLD.sections(), -4..=19);
+        assert_eq!(WorldHeight::OVERWORLD.to_string(), "y -64 to 319");
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
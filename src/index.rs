@@ -0,0 +1,330 @@
+use anyhow::{ Result, Context, bail };
+use flate2::{ Compression, read::GzDecoder, write::GzEncoder };
+use serde::{ Deserialize, Serialize };
+use std::{ collections::{ BTreeMap, HashMap }, fs::{ self, File }, io::{ BufWriter, Read, Write }, path::{ Path, PathBuf } };
+
+use crate::block_id::BlockId;
+use crate::chunk::Chunk;
+use crate::de::from_tag;
+use crate::nbt::{ ParseOptions, Tag };
+use crate::ser::{ IntArray, to_tag };
+use crate::world::{ Dimension, World };
+use crate::region::Region;
+
+// Bumped whenever the layout changes, older indexes are built again
+const VERSION: i32 = 1;
+
+// Which blocks each section of a world has, so searches can go straight to the
+// chunks that might hold what they look for. Chunks are indexed along with
+// their timestamp in the region, so an update only reads the ones saved since.
+pub struct WorldIndex {
+    path: PathBuf,
+    // Block names are stored once and referred to by their position here
+    names: Vec<String>,
+    name_ids: HashMap<String, u32>,
+    regions: BTreeMap<(Dimension, i32, i32), BTreeMap<usize, IndexedChunk>>,
+}
+
+#[derive(Clone, Serialize, Deserialize)]
+struct IndexedChunk {
+    #[serde(rename = "Index")]
+    index: u16,
+    #[serde(rename = "Timestamp")]
+    timestamp: u32,
+    #[serde(rename = "Sections")]
+    sections: Vec<IndexedSection>,
+}
+
+#[derive(Clone, Serialize, Deserialize)]
+struct IndexedSection {
+    #[serde(rename = "Y")]
+    y: i32,
+    // Ids into the index's names
+    #[serde(rename = "Blocks")]
+    blocks: IntArray,
+}
+
+#[derive(Serialize, Deserialize)]
+struct IndexFile {
+    #[serde(rename = "Version")]
+    version: i32,
+    #[serde(rename = "Names")]
+    names: Vec<String>,
+    #[serde(rename = "Regions")]
+    regions: Vec<IndexedRegion>,
+}
+
+#[derive(Serialize, Deserialize)]
+struct IndexedRegion {
+    #[serde(rename = "Dimension")]
+    dimension: String,
+    #[serde(rename = "X")]
+    x: i32,
+    #[serde(rename = "Z")]
+    z: i32,
+    #[serde(rename = "Chunks")]
+    chunks: Vec<IndexedChunk>,
+}
+
+// A chunk the index says has a wanted block, and the sections that do
+pub struct IndexHit {
+    pub dimension: Dimension,
+    pub region_x: i32,
+    pub region_z: i32,
+    pub index: usize,
+    pub sections: Vec<i32>,
+}
+
+impl WorldIndex {
+    // Kept in the world folder
+    pub const FILE_NAME: &'static str = "path-miner.pmidx";
+
+    // An index that can't be read, or is from another version, is started over
+    pub fn load(world: &World) -> WorldIndex {
+        let path = world.path().join(WorldIndex::FILE_NAME);
+        let mut index = WorldIndex { path, names: Vec::new(), name_ids: HashMap::new(), regions: BTreeMap::new() };
+        if !index.path.is_file() {
+            return index;
+        }
+
+        match read_index_file(&index.path) {
+            Ok(file) => {
+                index.name_ids = file.names.iter().enumerate().map(|(i, name)| (name.clone(), i as u32)).collect();
+                index.names = file.names;
+                for region in file.regions {
+                    let Ok(dimension) = region.dimension.parse() else {
+                        continue;
+                    };
+                    let chunks = region.chunks.into_iter().map(|chunk| (chunk.index as usize, chunk)).collect();
+                    index.regions.insert((dimension, region.x, region.z), chunks);
+                }
+            },
+            Err(e) => log::warn!("Building the index again, {}: {e:#}", index.path.display()),
+        }
+        index
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    pub fn chunk_count(&self) -> usize {
+        self.regions.values().map(BTreeMap::len).sum()
+    }
+
+    // Reads the chunks saved since they were indexed, or never indexed at all,
+    // and forgets the ones that are gone. Chunks that can't be read are
+    // indexed without sections, until they're saved again. Returns how many
+    // chunks were read.
+    pub fn update(&mut self, world: &World) -> Result<usize> {
+        let options = ParseOptions::new()
+            .skip("block_light")
+            .skip("sky_light")
+            .skip("BlockLight")
+            .skip("SkyLight")
+            .skip("Heightmaps")
+            .skip("biomes")
+            .skip("Biomes")
+            .skip("block_entities")
+            .skip("TileEntities")
+            .skip("Entities");
+        let mut read = 0;
+
+        let present: Vec<(Dimension, i32, i32)> = world.regions().iter().map(|region| (region.dimension, region.x, region.z)).collect();
+        self.regions.retain(|key, _| present.contains(key));
+
+        for region_info in world.regions() {
+            if region_info.is_empty() {
+                self.regions.remove(&(region_info.dimension, region_info.x, region_info.z));
+                continue;
+            }
+            let mut region = Region::open(&region_info.path).with_context(|| format!("Could not read region {}", region_info.path.display()))?;
+            let timestamps = region.timestamps().to_vec();
+            let chunks = self.regions.entry((region_info.dimension, region_info.x, region_info.z)).or_default();
+            chunks.retain(|&index, _| region.has_chunk(index));
+
+            let stale: Vec<usize> = (0..1024)
+                .filter(|&index| region.has_chunk(index))
+                .filter(|index| chunks.get(index).is_none_or(|chunk| chunk.timestamp != timestamps[*index]))
+                .collect();
+            if !stale.is_empty() {
+                log::info!("Indexing {} chunks of {}", stale.len(), region_info.path.display());
+            }
+
+            let mut indexed = Vec::with_capacity(stale.len());
+            for index in stale {
+                let chunk = region.read_chunk(index, &options).and_then(|tag| match tag {
+                    Some(tag) => Chunk::from_nbt(tag).map(Some),
+                    None => Ok(None),
+                });
+                let sections = match chunk {
+                    Ok(Some(chunk)) => chunk.sections().iter()
+                        .map(|section| (section.y(), section.palette().iter().map(|block| block.name).collect::<Vec<_>>()))
+                        .collect(),
+                    Ok(None) => continue,
+                    Err(e) => {
+                        log::warn!("Indexing unreadable chunk {index} of {} as empty: {e:#}", region_info.path.display());
+                        Vec::new()
+                    },
+                };
+                indexed.push((index, timestamps[index], sections));
+                read += 1;
+            }
+
+            for (index, timestamp, sections) in indexed {
+                let sections = sections.into_iter()
+                    .map(|(y, names): (i32, Vec<BlockId>)| {
+                        let mut blocks: Vec<i32> = names.into_iter().map(|name| self.name_id(&name) as i32).collect();
+                        blocks.sort_unstable();
+                        blocks.dedup();
+                        IndexedSection { y, blocks: IntArray(blocks) }
+                    })
+                    .collect();
+                let chunks = self.regions.entry((region_info.dimension, region_info.x, region_info.z)).or_default();
+                chunks.insert(index, IndexedChunk { index: index as u16, timestamp, sections });
+            }
+        }
+
+        Ok(read)
+    }
+
+    fn name_id(&mut self, name: &str) -> u32 {
+        if let Some(id) = self.name_ids.get(name) {
+            return *id;
+        }
+        let id = self.names.len() as u32;
+        self.names.push(name.to_string());
+        self.name_ids.insert(name.to_string(), id);
+        id
+    }
+
+    // Gzipped NBT, written next to the old index first
+    pub fn save(&self) -> Result<()> {
+        let file = IndexFile {
+            version: VERSION,
+            names: self.names.clone(),
+            regions: self.regions.iter()
+                .map(|((dimension, x, z), chunks)| IndexedRegion {
+                    dimension: dimension.id().to_string(),
+                    x: *x,
+                    z: *z,
+                    chunks: chunks.values().cloned().collect(),
+                })
+                .collect(),
+        };
+        let mut bytes = Vec::new();
+        to_tag("", &file)?.write(&mut bytes)?;
+
+        // Compressed in one go, feeding the encoder tag by tag is far slower
+        let temporary = self.path.with_extension("pmidx.tmp");
+        let mut encoder = GzEncoder::new(BufWriter::new(File::create(&temporary)?), Compression::fast());
+        encoder.write_all(&bytes)?;
+        encoder.finish()?.flush()?;
+        fs::rename(&temporary, &self.path).with_context(|| format!("Could not write {}", self.path.display()))?;
+        Ok(())
+    }
+
+    // The chunks with a section holding a block wanted says yes to, by name
+    pub fn find(&self, wanted: impl Fn(&str) -> bool) -> Vec<IndexHit> {
+        let wanted_ids: Vec<bool> = self.names.iter().map(|name| wanted(name)).collect();
+        let mut hits = Vec::new();
+
+        for (&(dimension, region_x, region_z), chunks) in &self.regions {
+            for (&index, chunk) in chunks {
+                let sections: Vec<i32> = chunk.sections.iter()
+                    .filter(|section| section.blocks.0.iter().any(|&id| wanted_ids.get(id as usize).copied().unwrap_or(false)))
+                    .map(|section| section.y)
+                    .collect();
+                if !sections.is_empty() {
+                    hits.push(IndexHit { dimension, region_x, region_z, index, sections });
+                }
+            }
+        }
+
+        hits
+    }
+}
+
+fn read_index_file(path: &Path) -> Result<IndexFile> {
+    let mut bytes = Vec::new();
+    GzDecoder::new(File::open(path)?).read_to_end(&mut bytes)?;
+    let tag = Tag::parse(&mut bytes.iter())?;
+    let file: IndexFile = from_tag(&tag)?;
+    if file.version != VERSION {
+        bail!("Index is version {}, expected {VERSION}", file.version);
+    }
+    Ok(file)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::nbt::TagPayload;
+    use crate::region::RegionWriter;
+
+    fn tag(name: &str, payload: TagPayload) -> Tag {
+        Tag { name: name.to_string(), payload }
+    }
+
+    // A 1.20.4 chunk with one section at the bottom holding the blocks named
+    fn chunk_tag(x: i32, z: i32, blocks: &[&str]) -> Tag {
+        let palette = blocks.iter()
+            .map(|name| TagPayload::Compound(vec![tag("Name", TagPayload::String(name.to_string()))].into()))
+            .collect();
+        let block_states = vec![tag("palette", TagPayload::List(palette)), tag("data", TagPayload::LongArray(vec![0; 256]))];
+        let section = TagPayload::Compound(vec![tag("Y", TagPayload::Byte(0)), tag("block_states", TagPayload::Compound(block_states.into()))].into());
+        let root = vec![
+            tag("DataVersion", TagPayload::Int(3700)),
+            tag("xPos", TagPayload::Int(x)),
+            tag("zPos", TagPayload::Int(z)),
+            tag("yPos", TagPayload::Int(0)),
+            tag("sections", TagPayload::List(vec![section])),
+        ];
+        tag("", TagPayload::Compound(root.into()))
+    }
+
+    fn write_region(path: &Path, chunks: &[(Tag, u32)]) {
+        let mut writer = RegionWriter::new();
+        for (chunk, timestamp) in chunks {
+            let (Some(TagPayload::Int(x)), Some(TagPayload::Int(z))) = (chunk.payload.get("xPos"), chunk.payload.get("zPos")) else { panic!("no position") };
+            writer.set_chunk(crate::region::chunk_index_in_region(*x, *z), chunk, *timestamp).unwrap();
+        }
+        let mut bytes = Vec::new();
+        writer.write(&mut bytes).unwrap();
+        fs::write(path, bytes).unwrap();
+    }
+
+    #[test]
+    fn indexes_around_empty_region_files_and_updates_saved_chunks() {
+        let dir = std::env::temp_dir().join(format!("path-miner-index-{}", std::process::id()));
+        let region_dir = dir.join("region");
+        fs::create_dir_all(&region_dir).unwrap();
+        let stone = ["minecraft:stone", "minecraft:air"];
+        write_region(&region_dir.join("r.0.0.mca"), &[(chunk_tag(0, 0, &stone), 100), (chunk_tag(1, 0, &["minecraft:stone", "minecraft:diamond_ore"]), 100)]);
+        // The game leaves these behind
+        fs::write(region_dir.join("r.1.0.mca"), []).unwrap();
+
+        let world = World::open(&dir).unwrap();
+        let mut index = WorldIndex::load(&world);
+        assert_eq!(index.update(&world).unwrap(), 2);
+        let hits = index.find(|name| name == "minecraft:diamond_ore");
+        assert_eq!(hits.len(), 1);
+        assert_eq!((hits[0].region_x, hits[0].region_z, hits[0].index, hits[0].sections.clone()), (0, 0, 1, vec![0]));
+
+        // Saved and loaded, nothing has changed
+        index.save().unwrap();
+        let mut index = WorldIndex::load(&world);
+        assert_eq!(index.chunk_count(), 2);
+        assert_eq!(index.update(&world).unwrap(), 0);
+        assert_eq!(index.find(|name| name == "minecraft:diamond_ore").len(), 1);
+
+        // The diamond chunk is gone and the other one was saved with it
+        write_region(&region_dir.join("r.0.0.mca"), &[(chunk_tag(0, 0, &["minecraft:stone", "minecraft:diamond_ore"]), 200)]);
+        assert_eq!(index.update(&world).unwrap(), 1);
+        let hits = index.find(|name| name == "minecraft:diamond_ore");
+        assert_eq!(hits.iter().map(|hit| hit.index).collect::<Vec<_>>(), vec![0]);
+        assert_eq!(index.chunk_count(), 1);
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,68 @@
+use crate::nbt::TagPayload;
+
+pub struct ItemStack {
+    id: String,
+    count: i32,
+    // Items inside a shulker box or bundle
+    contents: Vec<ItemStack>,
+}
+
+impl ItemStack {
+    // Understands both the tag based format and the item components used since
+    // 1.20.5. None if the compound isn't an item.
+    pub fn from_nbt(nbt: &TagPayload) -> Option<ItemStack> {
+        let id = match nbt.get("id")? {
+            TagPayload::String(id) => id.clone(),
+            _ => return None,
+        };
+
+        let count = match (nbt.get("Count"), nbt.get("count")) {
+            (Some(TagPayload::Byte(count)), _) => *count as i32,
+            (_, Some(TagPayload::Int(count))) => *count,
+            // Components leave the count out when it's 1
+            _ => 1,
+        };
+
+        let mut contents = Vec::new();
+        if let Ok(items) = nbt.get_path("tag.BlockEntityTag.Items") {
+            contents.extend(items_in(items));
+        }
+        if let Ok(items) = nbt.get_path("tag.Items") {
+            contents.extend(items_in(items));
+        }
+        if let Ok(TagPayload::List(slots)) = nbt.get_path("components.minecraft:container") {
+            contents.extend(slots.iter().filter_map(|slot| slot.get("item")).filter_map(ItemStack::from_nbt));
+        }
+        if let Ok(items) = nbt.get_path("components.minecraft:bundle_contents") {
+            contents.extend(items_in(items));
+        }
+
+        Some(ItemStack { id, count, contents })
+    }
+
+    pub fn id(&self) -> &str {
+        &self.id
+    }
+
+    pub fn count(&self) -> i32 {
+        self.count
+    }
+
+    pub fn contents(&self) -> &[ItemStack] {
+        &self.contents
+    }
+
+    // How many of an item this stack holds, counting nested containers
+    pub fn count_of(&self, id: &str) -> i64 {
+        let own = if self.id == id { self.count as i64 } else { 0 };
+        own + self.contents.iter().map(|item| item.count_of(id)).sum::<i64>() * self.count as i64
+    }
+}
+
+// Reads a list of item compounds like a chest's Items, skipping anything that isn't an item
+pub fn items_in(list: &TagPayload) -> Vec<ItemStack> {
+    match list {
+        TagPayload::List(items) => items.iter().filter_map(ItemStack::from_nbt).collect(),
+        _ => Vec::new(),
+    }
+}
@@ -0,0 +1,76 @@
+use serde_json::{ Map, Number, Value };
+
+use crate::nbt::{ Tag, TagPayload };
+
+// NBT has more types than JSON, so the conversion loses some of them:
+//
+// - Byte, Short, Int and Float/Double become plain numbers. NaN and the
+//   infinities have no JSON form and become null.
+// - Long becomes a decimal string, because JavaScript and most JSON parsers
+//   only keep 53 bits of an integer. The same goes for LongArray elements.
+// - ByteArray and IntArray become arrays of numbers. A ByteArray keeps the
+//   signed values NBT stores (-128..=127).
+// - Compound becomes an object with its keys in file order, and List becomes
+//   an array.
+//
+// The original tag types can't be recovered from the output. Use SNBT if the
+// result has to be read back.
+
+// The root tag's name is dropped, like in SNBT
+pub fn tag_to_json(tag: &Tag) -> Value {
+    payload_to_json(&tag.payload)
+}
+
+pub fn payload_to_json(payload: &TagPayload) -> Value {
+    match payload {
+        TagPayload::Byte(x) => Value::from(*x),
+        TagPayload::Short(x) => Value::from(*x),
+        TagPayload::Int(x) => Value::from(*x),
+        TagPayload::Long(x) => Value::String(x.to_string()),
+        // Going through the shortest decimal form keeps 0.1f from turning into
+        // 0.10000000149011612
+        TagPayload::Float(x) => float(x.to_string().parse().unwrap_or(f64::NAN)),
+        TagPayload::Double(x) => float(*x),
+        TagPayload::ByteArray(x) => x.iter().map(|&b| Value::from(b)).collect(),
+        TagPayload::String(x) => Value::String(x.clone()),
+        TagPayload::List(x) => x.iter().map(payload_to_json).collect(),
+        TagPayload::Compound(x) => {
+            let map: Map<String, Value> = x.iter()
+                .map(|tag| (tag.name.clone(), payload_to_json(&tag.payload)))
+                .collect();
+            Value::Object(map)
+        },
+        TagPayload::IntArray(x) => x.iter().map(|&i| Value::from(i)).collect(),
+        TagPayload::LongArray(x) => x.iter().map(|l| Value::String(l.to_string())).collect(),
+    }
+}
+
+fn float(x: f64) -> Value {
+    Number::from_f64(x).map_or(Value::Null, Value::Number)
+}
+
+// The way back, for hand written documents rather than tag_to_json's output:
+// whole numbers become Int, or Long if they don't fit, other numbers Double,
+// booleans Byte, and objects compounds. null has no NBT form and gives None,
+// as does an array that mixes types.
+pub fn json_to_payload(value: &Value) -> Option<TagPayload> {
+    Some(match value {
+        Value::Null => return None,
+        Value::Bool(b) => TagPayload::Byte(*b as i8),
+        Value::Number(n) => match n.as_i64() {
+            Some(i) => i32::try_from(i).map_or(TagPayload::Long(i), TagPayload::Int),
+            None => TagPayload::Double(n.as_f64()?),
+        },
+        Value::String(s) => TagPayload::String(s.clone()),
+        Value::Array(items) => {
+            let items: Vec<TagPayload> = items.iter().map(json_to_payload).collect::<Option<_>>()?;
+            if items.windows(2).any(|pair| pair[0].id() != pair[1].id()) {
+                return None;
+            }
+            TagPayload::List(items)
+        },
+        Value::Object(map) => TagPayload::Compound(map.iter()
+            .map(|(name, value)| Some(Tag { name: name.clone(), payload: json_to_payload(value)? }))
+            .collect::<Option<_>>()?),
+    })
+}
@@ -0,0 +1,130 @@
+// Numeric block ids from before 1.13, mapped to today's block names. Only the
+// data values that pick a different block (wool colour, log type, ...) are
+// looked at. Facing, age and the like are dropped with the rest of the state.
+
+const COLORS: [&str; 16] = [
+    "white", "orange", "magenta", "light_blue", "yellow", "lime", "pink", "gray",
+    "light_gray", "cyan", "purple", "blue", "brown", "green", "red", "black",
+];
+
+const WOODS: [&str; 6] = ["oak", "spruce", "birch", "jungle", "acacia", "dark_oak"];
+
+// Names by id for data value 0, or for any data value that isn't listed in
+// variant_name. Empty entries are ids vanilla never used.
+const BASE_NAMES: [&str; 256] = [
+    // 0
+    "air", "stone", "grass_block", "dirt", "cobblestone", "oak_planks", "oak_sapling", "bedrock",
+    "water", "water", "lava", "lava", "sand", "gravel", "gold_ore", "iron_ore",
+    // 16
+    "coal_ore", "oak_log", "oak_leaves", "sponge", "glass", "lapis_ore", "lapis_block", "dispenser",
+    "sandstone", "note_block", "red_bed", "powered_rail", "detector_rail", "sticky_piston", "cobweb", "grass",
+    // 32
+    "dead_bush", "piston", "piston_head", "white_wool", "moving_piston", "dandelion", "poppy", "brown_mushroom",
+    "red_mushroom", "gold_block", "iron_block", "smooth_stone_slab", "smooth_stone_slab", "bricks", "tnt", "bookshelf",
+    // 48
+    "mossy_cobblestone", "obsidian", "torch", "fire", "spawner", "oak_stairs", "chest", "redstone_wire",
+    "diamond_ore", "diamond_block", "crafting_table", "wheat", "farmland", "furnace", "furnace", "oak_sign",
+    // 64
+    "oak_door", "ladder", "rail", "cobblestone_stairs", "oak_wall_sign", "lever", "stone_pressure_plate", "iron_door",
+    "oak_pressure_plate", "redstone_ore", "redstone_ore", "redstone_torch", "redstone_torch", "stone_button", "snow", "ice",
+    // 80
+    "snow_block", "cactus", "clay", "sugar_cane", "jukebox", "oak_fence", "carved_pumpkin", "netherrack",
+    "soul_sand", "glowstone", "nether_portal", "jack_o_lantern", "cake", "repeater", "repeater", "white_stained_glass",
+    // 96
+    "oak_trapdoor", "infested_stone", "stone_bricks", "brown_mushroom_block", "red_mushroom_block", "iron_bars", "glass_pane", "melon",
+    "pumpkin_stem", "melon_stem", "vine", "oak_fence_gate", "brick_stairs", "stone_brick_stairs", "mycelium", "lily_pad",
+    // 112
+    "nether_bricks", "nether_brick_fence", "nether_brick_stairs", "nether_wart", "enchanting_table", "brewing_stand", "cauldron", "end_portal",
+    "end_portal_frame", "end_stone", "dragon_egg", "redstone_lamp", "redstone_lamp", "oak_slab", "oak_slab", "cocoa",
+    // 128
+    "sandstone_stairs", "emerald_ore", "ender_chest", "tripwire_hook", "tripwire", "emerald_block", "spruce_stairs", "birch_stairs",
+    "jungle_stairs", "command_block", "beacon", "cobblestone_wall", "flower_pot", "carrots", "potatoes", "oak_button",
+    // 144
+    "skeleton_skull", "anvil", "trapped_chest", "light_weighted_pressure_plate", "heavy_weighted_pressure_plate", "comparator", "comparator", "daylight_detector",
+    "redstone_block", "nether_quartz_ore", "hopper", "quartz_block", "quartz_stairs", "activator_rail", "dropper", "white_terracotta",
+    // 160
+    "white_stained_glass_pane", "acacia_leaves", "acacia_log", "acacia_stairs", "dark_oak_stairs", "slime_block", "barrier", "iron_trapdoor",
+    "prismarine", "sea_lantern", "hay_block", "white_carpet", "terracotta", "coal_block", "packed_ice", "sunflower",
+    // 176
+    "white_banner", "white_wall_banner", "daylight_detector", "red_sandstone", "red_sandstone_stairs", "red_sandstone_slab", "red_sandstone_slab", "spruce_fence_gate",
+    "birch_fence_gate", "jungle_fence_gate", "dark_oak_fence_gate", "acacia_fence_gate", "spruce_fence", "birch_fence", "jungle_fence", "dark_oak_fence",
+    // 192
+    "acacia_fence", "spruce_door", "birch_door", "jungle_door", "acacia_door", "dark_oak_door", "end_rod", "chorus_plant",
+    "chorus_flower", "purpur_block", "purpur_pillar", "purpur_stairs", "purpur_slab", "purpur_slab", "end_stone_bricks", "beetroots",
+    // 208
+    "dirt_path", "end_gateway", "repeating_command_block", "chain_command_block", "frosted_ice", "magma_block", "nether_wart_block", "red_nether_bricks",
+    "bone_block", "structure_void", "observer", "white_shulker_box", "orange_shulker_box", "magenta_shulker_box", "light_blue_shulker_box", "yellow_shulker_box",
+    // 224
+    "lime_shulker_box", "pink_shulker_box", "gray_shulker_box", "light_gray_shulker_box", "cyan_shulker_box", "purple_shulker_box", "blue_shulker_box", "brown_shulker_box",
+    "green_shulker_box", "red_shulker_box", "black_shulker_box", "white_glazed_terracotta", "orange_glazed_terracotta", "magenta_glazed_terracotta", "light_blue_glazed_terracotta", "yellow_glazed_terracotta",
+    // 240
+    "lime_glazed_terracotta", "pink_glazed_terracotta", "gray_glazed_terracotta", "light_gray_glazed_terracotta", "cyan_glazed_terracotta", "purple_glazed_terracotta", "blue_glazed_terracotta", "brown_glazed_terracotta",
+    "green_glazed_terracotta", "red_glazed_terracotta", "black_glazed_terracotta", "white_concrete", "white_concrete_powder", "", "", "structure_block",
+];
+
+fn pick(names: &[&'static str], data: usize) -> Option<String> {
+    names.get(data).map(|name| name.to_string())
+}
+
+fn colored(data: usize, block: &str) -> Option<String> {
+    Some(format!("{}_{block}", COLORS[data]))
+}
+
+fn wooden(data: usize, block: &str) -> Option<String> {
+    WOODS.get(data).map(|wood| format!("{wood}_{block}"))
+}
+
+// Blocks whose data value selects a different modern block
+fn variant_name(id: u16, data: usize) -> Option<String> {
+    match id {
+        1 => pick(&["stone", "granite", "polished_granite", "diorite", "polished_diorite", "andesite", "polished_andesite"], data),
+        3 => pick(&["dirt", "coarse_dirt", "podzol"], data),
+        5 => wooden(data, "planks"),
+        6 => wooden(data & 7, "sapling"),
+        12 => pick(&["sand", "red_sand"], data),
+        17 | 18 => wooden(data & 3, if id == 17 { "log" } else { "leaves" }),
+        19 => pick(&["sponge", "wet_sponge"], data),
+        24 => pick(&["sandstone", "chiseled_sandstone", "cut_sandstone"], data),
+        31 => pick(&["dead_bush", "grass", "fern"], data),
+        35 => colored(data, "wool"),
+        38 => pick(&["poppy", "blue_orchid", "allium", "azure_bluet", "red_tulip", "orange_tulip", "white_tulip", "pink_tulip", "oxeye_daisy"], data),
+        43 | 44 => pick(&["smooth_stone_slab", "sandstone_slab", "petrified_oak_slab", "cobblestone_slab", "brick_slab", "stone_brick_slab", "nether_brick_slab", "quartz_slab"], data & 7),
+        // Standing torches have data 5, the rest are on a wall
+        50 => Some(if data == 5 || data == 0 { "torch" } else { "wall_torch" }.to_string()),
+        75 | 76 => Some(if data == 5 || data == 0 { "redstone_torch" } else { "redstone_wall_torch" }.to_string()),
+        95 => colored(data, "stained_glass"),
+        97 => pick(&["infested_stone", "infested_cobblestone", "infested_stone_bricks", "infested_mossy_stone_bricks", "infested_cracked_stone_bricks", "infested_chiseled_stone_bricks"], data),
+        98 => pick(&["stone_bricks", "mossy_stone_bricks", "cracked_stone_bricks", "chiseled_stone_bricks"], data),
+        99 | 100 if data == 10 || data == 15 => Some("mushroom_stem".to_string()),
+        125 | 126 => wooden(data & 7, "slab"),
+        139 => pick(&["cobblestone_wall", "mossy_cobblestone_wall"], data),
+        145 => pick(&["anvil", "chipped_anvil", "damaged_anvil"], data >> 2),
+        155 => pick(&["quartz_block", "chiseled_quartz_block", "quartz_pillar", "quartz_pillar", "quartz_pillar"], data),
+        159 => colored(data, "terracotta"),
+        160 => colored(data, "stained_glass_pane"),
+        161 | 162 => pick(if id == 161 { &["acacia_leaves", "dark_oak_leaves"] } else { &["acacia_log", "dark_oak_log"] }, data & 3),
+        168 => pick(&["prismarine", "prismarine_bricks", "dark_prismarine"], data),
+        171 => colored(data, "carpet"),
+        // Only the lower half of a double plant says which plant it is
+        175 => pick(&["sunflower", "lilac", "tall_grass", "large_fern", "rose_bush", "peony"], data),
+        179 => pick(&["red_sandstone", "chiseled_red_sandstone", "cut_red_sandstone"], data),
+        251 => colored(data, "concrete"),
+        252 => colored(data, "concrete_powder"),
+        _ => None,
+    }
+}
+
+// Ids above 255 come from the Add array and only exist in modded worlds. They
+// and the ids vanilla never used are named legacy:<id>.
+pub fn legacy_block_name(id: u16, data: u8) -> String {
+    let data = (data & 15) as usize;
+
+    if let Some(name) = variant_name(id, data) {
+        return format!("minecraft:{name}");
+    }
+
+    match BASE_NAMES.get(id as usize) {
+        Some(name) if !name.is_empty() => format!("minecraft:{name}"),
+        _ => format!("legacy:{id}"),
+    }
+}
@@ -0,0 +1,152 @@
+use anyhow::{ Result, Context, anyhow };
+use flate2::read::GzDecoder;
+use serde::Deserialize;
+use std::{ fs, io::Read, path::Path };
+
+use crate::{ de::from_payload, height::WorldHeight, nbt::{ Tag, TagPayload }, pos::BlockPos, world::Dimension };
+
+#[derive(Clone, Debug, PartialEq, Eq, Deserialize)]
+pub struct GameVersion {
+    #[serde(rename = "Id")]
+    pub id: i32,
+    #[serde(rename = "Name")]
+    pub name: String,
+    #[serde(rename = "Snapshot", default)]
+    pub snapshot: bool,
+}
+
+// The fields of level.dat's Data compound that we care about. Where a field
+// moved between versions, both places are listed.
+#[derive(Deserialize)]
+struct RawData {
+    #[serde(rename = "LevelName", default)]
+    level_name: String,
+    #[serde(rename = "DataVersion")]
+    data_version: Option<i32>,
+    #[serde(rename = "Version")]
+    version: Option<GameVersion>,
+    // Before 1.16
+    #[serde(rename = "RandomSeed")]
+    random_seed: Option<i64>,
+    #[serde(rename = "WorldGenSettings")]
+    world_gen_settings: Option<RawWorldGenSettings>,
+    // Before 1.21.9
+    #[serde(rename = "SpawnX")]
+    spawn_x: Option<i32>,
+    #[serde(rename = "SpawnY")]
+    spawn_y: Option<i32>,
+    #[serde(rename = "SpawnZ")]
+    spawn_z: Option<i32>,
+    spawn: Option<RawSpawn>,
+}
+
+#[derive(Deserialize)]
+struct RawWorldGenSettings {
+    seed: i64,
+}
+
+#[derive(Deserialize)]
+struct RawSpawn {
+    pos: [i32; 3],
+}
+
+// A dimension's type as WorldGenSettings has it since 1.16: written out with
+// its height, or the id of one from the game or a datapack
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum DimensionType {
+    Height(WorldHeight),
+    Id(String),
+}
+
+pub struct LevelDat {
+    level_name: String,
+    data_version: Option<i32>,
+    version: Option<GameVersion>,
+    seed: Option<i64>,
+    spawn: Option<BlockPos>,
+    nbt: Tag,
+}
+
+impl LevelDat {
+    // level.dat is normally gzip compressed, but uncompressed files are accepted too
+    pub fn load(path: impl AsRef<Path>) -> Result<LevelDat> {
+        LevelDat::from_nbt(read_nbt_file(path.as_ref())?)
+    }
+
+    pub fn from_nbt(nbt: Tag) -> Result<LevelDat> {
+        let data = nbt.payload.get("Data").ok_or_else(|| anyhow!("Missing Data tag"))?;
+        let raw: RawData = from_payload(data).context("Malformed Data tag")?;
+
+        let seed = raw.world_gen_settings.map(|settings| settings.seed).or(raw.random_seed);
+        let spawn = match (raw.spawn, raw.spawn_x, raw.spawn_y, raw.spawn_z) {
+            (Some(spawn), _, _, _) => Some(BlockPos::new(spawn.pos[0], spawn.pos[1], spawn.pos[2])),
+            (None, Some(x), Some(y), Some(z)) => Some(BlockPos::new(x, y, z)),
+            _ => None,
+        };
+
+        Ok(LevelDat {
+            level_name: raw.level_name,
+            data_version: raw.data_version,
+            version: raw.version,
+            seed,
+            spawn,
+            nbt,
+        })
+    }
+
+    pub fn level_name(&self) -> &str {
+        &self.level_name
+    }
+
+    // Missing in worlds last saved before 1.9
+    pub fn data_version(&self) -> Option<i32> {
+        self.data_version
+    }
+
+    pub fn version(&self) -> Option<&GameVersion> {
+        self.version.as_ref()
+    }
+
+    pub fn seed(&self) -> Option<i64> {
+        self.seed
+    }
+
+    pub fn spawn(&self) -> Option<BlockPos> {
+        self.spawn
+    }
+
+    // None before 1.16, and for dimensions the world doesn't list
+    pub fn dimension_type(&self, dimension: Dimension) -> Option<DimensionType> {
+        let dimensions = self.nbt.payload.get_path("Data.WorldGenSettings.dimensions").ok()?;
+        match dimensions.get(dimension.id())?.get("type")? {
+            TagPayload::String(id) => Some(DimensionType::Id(id.clone())),
+            inline => {
+                let int = |name: &str| match inline.get(name) {
+                    Some(TagPayload::Int(value)) => Some(*value),
+                    _ => None,
+                };
+                Some(DimensionType::Height(WorldHeight::new(int("min_y")?, u32::try_from(int("height")?).ok()?)))
+            },
+        }
+    }
+
+    pub fn nbt(&self) -> &Tag {
+        &self.nbt
+    }
+}
+
+// A gzipped NBT file like level.dat or a player's .dat, or an uncompressed one
+pub(crate) fn read_nbt_file(path: &Path) -> Result<Tag> {
+    let bytes = fs::read(path).with_context(|| format!("Could not read {}", path.display()))?;
+
+    let bytes = if bytes.starts_with(&[0x1f, 0x8b]) {
+        let mut decompressed = Vec::new();
+        GzDecoder::new(bytes.as_slice()).read_to_end(&mut decompressed)
+            .with_context(|| format!("Could not decompress {}", path.display()))?;
+        decompressed
+    } else {
+        bytes
+    };
+
+    Tag::parse(&mut bytes.iter()).with_context(|| format!("Could not parse {}", path.display()))
+}
@@ -0,0 +1,644 @@
+use anyhow::{ Result, Context, bail, ensure };
+use flate2::read::{ DeflateDecoder, ZlibDecoder };
+use std::{ collections::{ BTreeMap, BTreeSet }, fs::{ self, File }, io::{ Read, Seek, SeekFrom }, ops::Bound, path::{ Path, PathBuf }, sync::Mutex };
+
+// Last 8 bytes of every table file
+const TABLE_MAGIC: u64 = 0xdb4775248b80fb57;
+const FOOTER_SIZE: usize = 48;
+// Block compression types, 2 and 4 are Mojang's additions for Bedrock
+const NO_COMPRESSION: u8 = 0;
+const SNAPPY: u8 = 1;
+const ZLIB: u8 = 2;
+const ZLIB_RAW: u8 = 4;
+const LOG_BLOCK_SIZE: usize = 32768;
+
+// The keys and values of a data block
+type Block = Vec<(Vec<u8>, Vec<u8>)>;
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+struct BlockHandle {
+    offset: u64,
+    size: u64,
+}
+
+enum Location {
+    // In a data block of the table file with this index
+    Table(usize, BlockHandle),
+    // Still in a log file, which is small enough to keep
+    Inline(Vec<u8>),
+}
+
+// Where the newest entry of a key is, None if that entry deleted it
+struct Entry {
+    sequence: u64,
+    location: Option<Location>,
+}
+
+// A LevelDB database opened for reading, like the db folder of Bedrock
+// worlds. The tables the manifest lists as live and the logs not yet
+// compacted into them are read, and the newest entry of each key wins, by its
+// sequence number. Only the keys are kept, values are read from their block
+// when asked for.
+pub struct LevelDb {
+    tables: Vec<PathBuf>,
+    entries: BTreeMap<Vec<u8>, Entry>,
+    // The last data block read, keys from one chunk tend to share a block
+    cache: Mutex<Option<(usize, BlockHandle, Block)>>,
+}
+
+impl LevelDb {
+    pub fn open(path: impl AsRef<Path>) -> Result<LevelDb> {
+        let path = path.as_ref();
+        // CURRENT names the manifest, the others are left over from before
+        let current = fs::read_to_string(path.join("CURRENT")).with_context(|| format!("{} is not a LevelDB database", path.display()))?;
+        let manifest = path.join(current.trim_end());
+        let live = fs::read(&manifest).map_err(anyhow::Error::from).and_then(|file| read_manifest(&file))
+            .with_context(|| format!("Could not read manifest {}", manifest.display()))?;
+
+        let mut db = LevelDb { tables: Vec::new(), entries: BTreeMap::new(), cache: Mutex::new(None) };
+        for number in live.tables {
+            // Older versions named tables .sst
+            let mut file = path.join(format!("{number:06}.ldb"));
+            if !file.is_file() {
+                file.set_extension("sst");
+            }
+            let table = db.tables.len();
+            db.tables.push(file.clone());
+            db.read_table(table).with_context(|| format!("Could not read table {}", file.display()))?;
+        }
+
+        // Logs before the manifest's log number are already in the tables
+        let mut logs: Vec<u64> = fs::read_dir(path).with_context(|| format!("Could not list {}", path.display()))?
+            .filter_map(|entry| entry.ok()?.file_name().to_str()?.strip_suffix(".log")?.parse().ok())
+            .filter(|number| *number >= live.log_number || *number == live.prev_log_number)
+            .collect();
+        logs.sort();
+        for number in logs {
+            let file = path.join(format!("{number:06}.log"));
+            db.read_log(&file).with_context(|| format!("Could not read log {}", file.display()))?;
+        }
+        Ok(db)
+    }
+
+    // Keys that haven't been deleted, in order
+    pub fn keys(&self) -> impl Iterator<Item = &[u8]> {
+        self.entries.iter().filter(|(_, entry)| entry.location.is_some()).map(|(key, _)| key.as_slice())
+    }
+
+    pub fn keys_with_prefix<'a>(&'a self, prefix: &'a [u8]) -> impl Iterator<Item = &'a [u8]> + 'a {
+        self.entries.range::<[u8], _>((Bound::Included(prefix), Bound::Unbounded))
+            .take_while(move |(key, _)| key.starts_with(prefix))
+            .filter(|(_, entry)| entry.location.is_some())
+            .map(|(key, _)| key.as_slice())
+    }
+
+    pub fn get(&self, key: &[u8]) -> Result<Option<Vec<u8>>> {
+        let Some((sequence, location)) = self.entries.get(key).and_then(|entry| Some((entry.sequence, entry.location.as_ref()?))) else {
+            return Ok(None);
+        };
+        let (table, handle) = match location {
+            Location::Inline(value) => return Ok(Some(value.clone())),
+            Location::Table(table, handle) => (*table, *handle),
+        };
+
+        let mut cache = self.cache.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+        if !cache.as_ref().is_some_and(|(cached, cached_handle, _)| *cached == table && *cached_handle == handle) {
+            // The block and the compression type and checksum after it
+            let mut file = File::open(&self.tables[table])?;
+            let len = file.metadata()?.len();
+            let size = handle.size.checked_add(5).filter(|size| handle.offset.checked_add(*size).is_some_and(|end| end <= len))
+                .with_context(|| format!("Block at {} runs past the end of {}", handle.offset, self.tables[table].display()))?;
+            let mut bytes = vec![0; size as usize];
+            file.seek(SeekFrom::Start(handle.offset))?;
+            file.read_exact(&mut bytes).with_context(|| format!("Could not read block at {} of {}", handle.offset, self.tables[table].display()))?;
+            *cache = Some((table, handle, read_block(&bytes, BlockHandle { offset: 0, size: handle.size })?));
+        }
+        let (_, _, block) = cache.as_ref().unwrap();
+        // The block can have older entries of the key too
+        Ok(block.iter()
+            .find(|(internal, _)| split_internal_key(internal).is_some_and(|(found, found_sequence, _)| found == key && found_sequence == sequence))
+            .map(|(_, value)| value.clone()))
+    }
+
+    fn insert(&mut self, key: &[u8], sequence: u64, location: Option<Location>) {
+        if self.entries.get(key).is_none_or(|entry| entry.sequence < sequence) {
+            self.entries.insert(key.to_vec(), Entry { sequence, location });
+        }
+    }
+
+    fn read_table(&mut self, table: usize) -> Result<()> {
+        let file = fs::read(&self.tables[table])?;
+        ensure!(file.len() >= FOOTER_SIZE, "Table is shorter than its footer");
+        let footer = &file[file.len() - FOOTER_SIZE..];
+        ensure!(u64::from_le_bytes(footer[40..].try_into()?) == TABLE_MAGIC, "Table doesn't end in the LevelDB magic number");
+
+        let mut cursor = footer;
+        let _metaindex = read_handle(&mut cursor)?;
+        let index = read_handle(&mut cursor)?;
+
+        for (_, handle) in read_block(&file, index)? {
+            let handle = read_handle(&mut handle.as_slice())?;
+            for (internal, _) in read_block(&file, handle)? {
+                let Some((key, sequence, kind)) = split_internal_key(&internal) else {
+                    bail!("Key without a sequence number in block at {}", handle.offset);
+                };
+                let location = (kind == 1).then_some(Location::Table(table, handle));
+                self.insert(key, sequence, location);
+            }
+        }
+        Ok(())
+    }
+
+    // Writes not yet compacted into a table, as batches of puts and deletes
+    fn read_log(&mut self, path: &Path) -> Result<()> {
+        let file = fs::read(path)?;
+        for batch in read_log_records(&file) {
+            let mut cursor = batch.as_slice();
+            ensure!(cursor.len() >= 12, "Write batch is cut short");
+            let mut sequence = u64::from_le_bytes(cursor[..8].try_into()?);
+            cursor = &cursor[12..];
+            while !cursor.is_empty() {
+                let kind = cursor[0];
+                cursor = &cursor[1..];
+                let key = read_slice(&mut cursor)?.to_vec();
+                let location = match kind {
+                    1 => Some(Location::Inline(read_slice(&mut cursor)?.to_vec())),
+                    0 => None,
+                    _ => bail!("Unknown write batch entry type {kind}"),
+                };
+                self.insert(&key, sequence, location);
+                sequence += 1;
+            }
+        }
+        Ok(())
+    }
+}
+
+// What the manifest says makes up the database: the table files by number,
+// and the log numbers whose logs may hold writes not in the tables yet
+struct LiveFiles {
+    tables: Vec<u64>,
+    log_number: u64,
+    prev_log_number: u64,
+}
+
+// The manifest is a log of edits to the set of files, each a list of tagged fields
+fn read_manifest(file: &[u8]) -> Result<LiveFiles> {
+    // By level and number, a table moving levels is deleted from one and added to the other
+    let mut tables = BTreeSet::new();
+    let (mut log_number, mut prev_log_number) = (0, 0);
+    for edit in read_log_records(file) {
+        let mut cursor = edit.as_slice();
+        while !cursor.is_empty() {
+            match read_varint(&mut cursor)? {
+                // Comparator name
+                1 => { read_slice(&mut cursor)?; },
+                2 => log_number = read_varint(&mut cursor)?,
+                // Next file number and last sequence number
+                3 | 4 => { read_varint(&mut cursor)?; },
+                // Where the next compaction of a level starts
+                5 => {
+                    read_varint(&mut cursor)?;
+                    read_slice(&mut cursor)?;
+                },
+                6 => {
+                    let level = read_varint(&mut cursor)?;
+                    tables.remove(&(level, read_varint(&mut cursor)?));
+                },
+                // Level, number, size and the smallest and largest key
+                7 => {
+                    let level = read_varint(&mut cursor)?;
+                    tables.insert((level, read_varint(&mut cursor)?));
+                    read_varint(&mut cursor)?;
+                    read_slice(&mut cursor)?;
+                    read_slice(&mut cursor)?;
+                },
+                9 => prev_log_number = read_varint(&mut cursor)?,
+                tag => bail!("Unknown manifest field {tag}"),
+            }
+        }
+    }
+    let tables = tables.into_iter().map(|(_, number)| number).collect();
+    Ok(LiveFiles { tables, log_number, prev_log_number })
+}
+
+// The key a user gave, the sequence number and whether it's a put (1) or a delete (0)
+fn split_internal_key(internal: &[u8]) -> Option<(&[u8], u64, u8)> {
+    let split = internal.len().checked_sub(8)?;
+    let trailer = u64::from_le_bytes(internal[split..].try_into().ok()?);
+    Some((&internal[..split], trailer >> 8, trailer as u8))
+}
+
+// Every key and value of a block, keys are stored as what they share with the
+// one before plus the rest
+fn read_block(file: &[u8], handle: BlockHandle) -> Result<Block> {
+    let (start, size) = (handle.offset as usize, handle.size as usize);
+    ensure!(start.checked_add(size).is_some_and(|end| end < file.len()), "Block at {start} runs past the end of the table");
+    let compression = file[start + size];
+    let data = decompress(compression, &file[start..start + size])?;
+
+    ensure!(data.len() >= 4, "Block at {start} is too short");
+    let restarts = u32::from_le_bytes(data[data.len() - 4..].try_into()?) as usize;
+    let end = data.len().checked_sub(4 + restarts * 4).with_context(|| format!("Block at {start} has too many restart points"))?;
+
+    let mut entries = Block::new();
+    let mut cursor = &data[..end];
+    let mut key = Vec::new();
+    while !cursor.is_empty() {
+        let shared = read_varint(&mut cursor)? as usize;
+        let unshared = read_varint(&mut cursor)? as usize;
+        let value_len = read_varint(&mut cursor)? as usize;
+        ensure!(shared <= key.len() && unshared.checked_add(value_len).is_some_and(|len| len <= cursor.len()), "Block entry at {start} is cut short");
+        key.truncate(shared);
+        key.extend_from_slice(&cursor[..unshared]);
+        let value = cursor[unshared..unshared + value_len].to_vec();
+        cursor = &cursor[unshared + value_len..];
+        entries.push((key.clone(), value));
+    }
+    Ok(entries)
+}
+
+fn decompress(compression: u8, data: &[u8]) -> Result<Vec<u8>> {
+    let mut out = Vec::new();
+    match compression {
+        NO_COMPRESSION => out.extend_from_slice(data),
+        ZLIB => { ZlibDecoder::new(data).read_to_end(&mut out)?; },
+        ZLIB_RAW => { DeflateDecoder::new(data).read_to_end(&mut out)?; },
+        SNAPPY => decompress_snappy(data, &mut out)?,
+        _ => bail!("Unknown block compression {compression}"),
+    }
+    Ok(out)
+}
+
+// Snappy's format is the uncompressed length followed by literals and copies
+// of what came before, each starting with a tag byte
+fn decompress_snappy(data: &[u8], out: &mut Vec<u8>) -> Result<()> {
+    let mut cursor = data;
+    let len = read_varint(&mut cursor)? as usize;
+    // The length comes from the file, so it isn't reserved up front
+    while let Some((&tag, rest)) = cursor.split_first() {
+        cursor = rest;
+        let (copy_len, offset) = match tag & 3 {
+            0 => {
+                let len = match (tag >> 2) as usize {
+                    // Longer literals give their length in the next 1 to 4 bytes
+                    len @ 60.. => {
+                        let bytes = len - 59;
+                        ensure!(cursor.len() >= bytes, "Snappy literal length is cut short");
+                        let (len_bytes, rest) = cursor.split_at(bytes);
+                        cursor = rest;
+                        len_bytes.iter().rev().fold(0, |len, &byte| len << 8 | byte as usize)
+                    },
+                    len => len,
+                } + 1;
+                ensure!(cursor.len() >= len, "Snappy literal runs past the end");
+                out.extend_from_slice(&cursor[..len]);
+                cursor = &cursor[len..];
+                continue;
+            },
+            1 => {
+                let (&low, rest) = cursor.split_first().context("Snappy copy is cut short")?;
+                cursor = rest;
+                (4 + (tag >> 2 & 7) as usize, (tag as usize >> 5) << 8 | low as usize)
+            },
+            kind => {
+                let bytes = if kind == 2 { 2 } else { 4 };
+                ensure!(cursor.len() >= bytes, "Snappy copy is cut short");
+                let offset = cursor[..bytes].iter().rev().fold(0, |offset, &byte| offset << 8 | byte as usize);
+                cursor = &cursor[bytes..];
+                ((tag >> 2) as usize + 1, offset)
+            },
+        };
+        ensure!(offset > 0 && offset <= out.len(), "Snappy copy reaches back {offset} bytes into {}", out.len());
+        // Copies can overlap what they write, repeating the last few bytes
+        for _ in 0..copy_len {
+            out.push(out[out.len() - offset]);
+        }
+        ensure!(out.len() <= len, "Snappy block is longer than it says");
+    }
+    ensure!(out.len() == len, "Snappy block is {} bytes instead of {len}", out.len());
+    Ok(())
+}
+
+// Log files are split into 32 KiB blocks, records too long for the rest of
+// one go on in the next in fragments
+fn read_log_records(file: &[u8]) -> Vec<Vec<u8>> {
+    let mut records = Vec::new();
+    let mut record = Vec::new();
+    for block in file.chunks(LOG_BLOCK_SIZE) {
+        let mut cursor = block;
+        while cursor.len() >= 7 {
+            let length = u16::from_le_bytes([cursor[4], cursor[5]]) as usize;
+            let kind = cursor[6];
+            // Zeroes are space the writer set aside and never used
+            if kind == 0 || 7 + length > cursor.len() {
+                break;
+            }
+            let fragment = &cursor[7..7 + length];
+            cursor = &cursor[7 + length..];
+            match kind {
+                1 => records.push(fragment.to_vec()),
+                2 => record = fragment.to_vec(),
+                3 => record.extend_from_slice(fragment),
+                4 => {
+                    record.extend_from_slice(fragment);
+                    records.push(std::mem::take(&mut record));
+                },
+                _ => break,
+            }
+        }
+    }
+    records
+}
+
+fn read_handle(cursor: &mut &[u8]) -> Result<BlockHandle> {
+    Ok(BlockHandle { offset: read_varint(cursor)?, size: read_varint(cursor)? })
+}
+
+fn read_slice<'a>(cursor: &mut &'a [u8]) -> Result<&'a [u8]> {
+    let len = read_varint(cursor)? as usize;
+    ensure!(len <= cursor.len(), "Length {len} runs past the end");
+    let (slice, rest) = cursor.split_at(len);
+    *cursor = rest;
+    Ok(slice)
+}
+
+fn read_varint(cursor: &mut &[u8]) -> Result<u64> {
+    let mut value = 0u64;
+    for shift in (0..64).step_by(7) {
+        let Some((&byte, rest)) = cursor.split_first() else {
+            bail!("Varint runs past the end");
+        };
+        *cursor = rest;
+        value |= ((byte & 0x7f) as u64) << shift;
+        if byte & 0x80 == 0 {
+            return Ok(value);
+        }
+    }
+    bail!("Varint is longer than 10 bytes")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use flate2::{ Compression, write::ZlibEncoder };
+    use std::io::Write;
+
+    fn varint(mut value: u64, out: &mut Vec<u8>) {
+        while value >= 0x80 {
+            out.push(value as u8 | 0x80);
+            value >>= 7;
+        }
+        out.push(value as u8);
+    }
+
+    fn slice(bytes: &[u8], out: &mut Vec<u8>) {
+        varint(bytes.len() as u64, out);
+        out.extend_from_slice(bytes);
+    }
+
+    fn internal_key(key: &[u8], sequence: u64, kind: u8) -> Vec<u8> {
+        [key, &(sequence << 8 | kind as u64).to_le_bytes()].concat()
+    }
+
+    // Block contents as the table writer lays them out, with a restart point
+    // every interval entries where the key is stored whole
+    fn block(entries: &[(Vec<u8>, Vec<u8>)], interval: usize) -> Vec<u8> {
+        let (mut out, mut restarts, mut last) = (Vec::new(), Vec::new(), &[][..]);
+        for (i, (key, value)) in entries.iter().enumerate() {
+            let shared = match i % interval {
+                0 => {
+                    restarts.push(out.len() as u32);
+                    0
+                },
+                _ => key.iter().zip(last).take_while(|(a, b)| a == b).count(),
+            };
+            varint(shared as u64, &mut out);
+            varint((key.len() - shared) as u64, &mut out);
+            varint(value.len() as u64, &mut out);
+            out.extend_from_slice(&key[shared..]);
+            out.extend_from_slice(value);
+            last = key;
+        }
+        for restart in &restarts {
+            out.extend(restart.to_le_bytes());
+        }
+        out.extend((restarts.len() as u32).to_le_bytes());
+        out
+    }
+
+    // Appends the block with its compression type and an unchecked checksum
+    fn push_block(file: &mut Vec<u8>, contents: &[u8], compression: u8) -> BlockHandle {
+        let handle = BlockHandle { offset: file.len() as u64, size: contents.len() as u64 };
+        file.extend_from_slice(contents);
+        file.push(compression);
+        file.extend([0; 4]);
+        handle
+    }
+
+    fn table(entries: &[(Vec<u8>, Vec<u8>)]) -> Vec<u8> {
+        let mut file = Vec::new();
+        let data = push_block(&mut file, &block(entries, 2), NO_COMPRESSION);
+        let mut handle = Vec::new();
+        varint(data.offset, &mut handle);
+        varint(data.size, &mut handle);
+        let index = push_block(&mut file, &block(&[(entries.last().unwrap().0.clone(), handle)], 1), NO_COMPRESSION);
+        let metaindex = push_block(&mut file, &block(&[], 1), NO_COMPRESSION);
+
+        let mut footer = Vec::new();
+        for handle in [metaindex, index] {
+            varint(handle.offset, &mut footer);
+            varint(handle.size, &mut footer);
+        }
+        footer.resize(40, 0);
+        footer.extend(TABLE_MAGIC.to_le_bytes());
+        file.extend(footer);
+        file
+    }
+
+    // Records split into fragments where they don't fit the rest of a block
+    fn log(records: &[Vec<u8>]) -> Vec<u8> {
+        let mut out = Vec::new();
+        for record in records {
+            let mut rest = record.as_slice();
+            let mut first = true;
+            loop {
+                let left = LOG_BLOCK_SIZE - out.len() % LOG_BLOCK_SIZE;
+                if left < 7 {
+                    out.resize(out.len() + left, 0);
+                    continue;
+                }
+                let len = rest.len().min(left - 7);
+                let last = len == rest.len();
+                let kind = match (first, last) {
+                    (true, true) => 1,
+                    (true, false) => 2,
+                    (false, false) => 3,
+                    (false, true) => 4,
+                };
+                out.extend([0; 4]);
+                out.extend((len as u16).to_le_bytes());
+                out.push(kind);
+                out.extend_from_slice(&rest[..len]);
+                rest = &rest[len..];
+                first = false;
+                if last {
+                    break;
+                }
+            }
+        }
+        out
+    }
+
+    // Puts have a value, deletes don't
+    fn batch(sequence: u64, writes: &[(&[u8], Option<&[u8]>)]) -> Vec<u8> {
+        let mut out = sequence.to_le_bytes().to_vec();
+        out.extend((writes.len() as u32).to_le_bytes());
+        for (key, value) in writes {
+            out.push(value.is_some() as u8);
+            slice(key, &mut out);
+            if let Some(value) = value {
+                slice(value, &mut out);
+            }
+        }
+        out
+    }
+
+    fn entries(pairs: &[(&[u8], &[u8])]) -> Vec<(Vec<u8>, Vec<u8>)> {
+        pairs.iter().map(|(key, value)| (key.to_vec(), value.to_vec())).collect()
+    }
+
+    #[test]
+    fn reads_blocks_with_shared_prefixes_and_restart_points() {
+        let pairs = entries(&[(b"chunk-a", b"1"), (b"chunk-b", b"22"), (b"chunk-bc", b""), (b"other", b"4444"), (b"others", b"5")]);
+        let contents = block(&pairs, 2);
+
+        let mut file = vec![0xaa; 3];
+        let handle = push_block(&mut file, &contents, NO_COMPRESSION);
+        assert_eq!(read_block(&file, handle).unwrap(), pairs);
+
+        let mut encoder = ZlibEncoder::new(Vec::new(), Compression::default());
+        encoder.write_all(&contents).unwrap();
+        let mut file = Vec::new();
+        let handle = push_block(&mut file, &encoder.finish().unwrap(), ZLIB);
+        assert_eq!(read_block(&file, handle).unwrap(), pairs);
+
+        // All one literal, its length in the two bytes after the tag
+        let mut snappy = Vec::new();
+        varint(contents.len() as u64, &mut snappy);
+        snappy.push(61 << 2);
+        snappy.extend(((contents.len() - 1) as u16).to_le_bytes());
+        snappy.extend_from_slice(&contents);
+        let mut file = Vec::new();
+        let handle = push_block(&mut file, &snappy, SNAPPY);
+        assert_eq!(read_block(&file, handle).unwrap(), pairs);
+
+        // More restart points than the block has room for
+        let mut broken = contents.clone();
+        let len = broken.len();
+        broken[len - 4..].copy_from_slice(&1000u32.to_le_bytes());
+        let mut file = Vec::new();
+        let handle = push_block(&mut file, &broken, NO_COMPRESSION);
+        assert!(read_block(&file, handle).is_err());
+
+        // The compression byte is past the end
+        assert!(read_block(&file[..handle.size as usize], handle).is_err());
+    }
+
+    #[test]
+    fn decompresses_snappy_copies() {
+        // A literal, a copy with a 1 byte offset overlapping what it writes and one with a 2 byte offset
+        let data = [14, 2 << 2, b'a', b'b', b'c', 1 | (5 << 2), 3, 2 | (1 << 2), 9, 0];
+        assert_eq!(decompress(SNAPPY, &data).unwrap(), b"abcabcabcabcab");
+        // Lengths that don't add up and copies from before the start
+        assert!(decompress(SNAPPY, &[4, 2 << 2, b'a', b'b', b'c']).is_err());
+        assert!(decompress(SNAPPY, &[6, 2 << 2, b'a', b'b', b'c', 2 | (2 << 2), 4, 0]).is_err());
+        assert!(decompress(SNAPPY, &[9, 2 << 2, b'a', b'b']).is_err());
+    }
+
+    #[test]
+    fn joins_log_records_split_across_blocks() {
+        let records: Vec<Vec<u8>> = [
+            // Leaves 3 bytes at the end of the first block, too few for a header
+            LOG_BLOCK_SIZE - 10,
+            10,
+            // First and last fragment
+            40_000,
+            // First, middle and last fragment
+            70_000,
+            0,
+        ].iter().enumerate().map(|(i, len)| (0..*len).map(|at| (at * 7 + i) as u8).collect()).collect();
+        let mut file = log(&records);
+        assert_eq!(file[LOG_BLOCK_SIZE - 3..LOG_BLOCK_SIZE], [0; 3]);
+        assert!(read_log_records(&file) == records);
+
+        // Space the writer set aside at the end
+        file.extend([0; 1000]);
+        assert!(read_log_records(&file) == records);
+
+        // The last fragment of the long record cut off by a crash
+        let file = log(&records);
+        let cut = read_log_records(&file[..file.len() - 10]);
+        assert!(cut == records[..3], "{} records", cut.len());
+    }
+
+    #[test]
+    fn opens_only_the_live_files_from_the_manifest() {
+        let dir = std::env::temp_dir().join(format!("path-miner-leveldb-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+
+        // Compacted into table 5 and deleted since, so its newer entries don't count
+        let stale = [(internal_key(b"a", 10, 1), b"stale a".to_vec()), (internal_key(b"stale", 9, 1), b"x".to_vec())];
+        fs::write(dir.join("000004.ldb"), table(&stale)).unwrap();
+        let live = [
+            (internal_key(b"a", 1, 1), b"table a".to_vec()),
+            (internal_key(b"b", 2, 1), b"old b".to_vec()),
+            (internal_key(b"gone", 3, 1), b"x".to_vec()),
+        ];
+        fs::write(dir.join("000005.sst"), table(&live)).unwrap();
+        // Before the log number, so already in the tables
+        fs::write(dir.join("000003.log"), log(&[batch(20, &[(b"old log", Some(b"x"))])])).unwrap();
+        fs::write(dir.join("000006.log"), log(&[batch(4, &[(b"b", Some(b"new b")), (b"gone", None)])])).unwrap();
+
+        let mut first = Vec::new();
+        varint(1, &mut first);
+        slice(b"leveldb.BytewiseComparator", &mut first);
+        for (tag, value) in [(2, 3), (3, 5), (4, 3)] {
+            varint(tag, &mut first);
+            varint(value, &mut first);
+        }
+        // New table 4 in level 0
+        for value in [7, 0, 4, 100] {
+            varint(value, &mut first);
+        }
+        slice(&internal_key(b"a", 10, 1), &mut first);
+        slice(&internal_key(b"stale", 9, 1), &mut first);
+        let mut second = Vec::new();
+        for value in [2, 6, 9, 0, 7, 1, 5, 200] {
+            varint(value, &mut second);
+        }
+        slice(&internal_key(b"a", 1, 1), &mut second);
+        slice(&internal_key(b"gone", 3, 1), &mut second);
+        for value in [6, 0, 4] {
+            varint(value, &mut second);
+        }
+        fs::write(dir.join("MANIFEST-000007"), log(&[first, second])).unwrap();
+        // An old manifest CURRENT no longer points to
+        fs::write(dir.join("MANIFEST-000002"), b"garbage").unwrap();
+        fs::write(dir.join("CURRENT"), "MANIFEST-000007\n").unwrap();
+
+        let db = LevelDb::open(&dir).unwrap();
+        assert_eq!(db.keys().collect::<Vec<_>>(), [b"a".as_slice(), b"b"]);
+        assert_eq!(db.keys_with_prefix(b"g").count(), 0);
+        assert_eq!(db.get(b"a").unwrap().as_deref(), Some(b"table a".as_slice()));
+        assert_eq!(db.get(b"b").unwrap().as_deref(), Some(b"new b".as_slice()));
+        assert_eq!(db.get(b"gone").unwrap(), None);
+        assert_eq!(db.get(b"stale").unwrap(), None);
+        assert_eq!(db.get(b"old log").unwrap(), None);
+
+        fs::write(dir.join("CURRENT"), "MANIFEST-000009\n").unwrap();
+        assert!(LevelDb::open(&dir).is_err());
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,71 @@
+pub mod nbt;
+pub mod region;
+pub mod chunk;
+pub mod height;
+pub mod block_id;
+pub mod legacy;
+pub mod world;
+pub mod cache;
+pub mod claims;
+pub mod index;
+#[cfg(feature = "async")]
+pub mod async_scan;
+#[cfg(feature = "bedrock")]
+pub mod leveldb;
+#[cfg(feature = "bedrock")]
+pub mod bedrock;
+pub mod level;
+pub mod player;
+pub mod entity;
+pub mod poi;
+pub mod structure;
+pub mod slime;
+pub mod item;
+pub mod sign;
+pub mod pos;
+pub mod pathfinding;
+pub mod costs;
+pub mod hazards;
+pub mod builds;
+pub mod flooding;
+pub mod caves;
+pub mod complexity;
+pub mod spawning;
+pub mod lighting;
+pub mod actions;
+pub mod branch_mine;
+pub mod portals;
+pub mod gateway;
+pub mod query;
+pub mod search;
+pub mod mesh;
+pub mod model;
+pub mod waypoints;
+pub mod mcfunction;
+pub mod baritone;
+pub mod render;
+pub mod tiles;
+pub mod report;
+pub mod schematic;
+pub mod snbt;
+pub mod diff;
+pub mod relocate;
+pub mod replace;
+pub mod json;
+pub mod ser;
+pub mod de;
+pub mod reader;
+pub mod tag_ref;
+pub mod tree;
+pub mod prelude;
+
+pub use nbt::{ Tag, TagPayload, NbtError, SerdeError, PathError, ParseOptions, NbtFlavor, Endianness };
+pub use ser::{ to_payload, to_tag };
+pub use de::{ from_payload, from_tag };
+pub use reader::{ NbtReader, NbtEvent };
+pub use tag_ref::{ TagRef, TagPayloadRef };
+pub use region::parse_chunks;
+pub use world::{ World, Dimension };
+pub use level::LevelDat;
+pub use pos::BlockPos;
+pub use block_id::BlockId;
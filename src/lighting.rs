@@ -0,0 +1,310 @@
+use std::{ collections::{ HashMap, HashSet, VecDeque }, fmt };
+
+use crate::{ chunk::{ BlockType, is_below_surface }, pathfinding::{ is_walkable, walking_moves }, pos::BlockPos, spawning::can_spawn_on, world::WorldView };
+
+// Block light of a torch, the planner's torches and the ones suggested here
+pub const TORCH_LIGHT: u8 = 14;
+
+// Block light a block gives off. Goes by name, with the lit property for
+// the blocks that can be switched off, so it's a close guess for the rest.
+pub fn light_emission(block: &BlockType) -> u8 {
+    if block.property("lit") == Some("false") {
+        return 0;
+    }
+    match block.name.as_str() {
+        "minecraft:light" => block.property("level").and_then(|level| level.parse().ok()).unwrap_or(15),
+        "minecraft:glowstone" | "minecraft:sea_lantern" | "minecraft:shroomlight" | "minecraft:jack_o_lantern"
+        | "minecraft:lantern" | "minecraft:lava" | "minecraft:fire" | "minecraft:beacon" | "minecraft:campfire"
+        | "minecraft:redstone_lamp" | "minecraft:end_gateway" | "minecraft:conduit" | "minecraft:respawn_anchor"
+        | "minecraft:ochre_froglight" | "minecraft:verdant_froglight" | "minecraft:pearlescent_froglight" => 15,
+        "minecraft:torch" | "minecraft:wall_torch" | "minecraft:end_rod" => 14,
+        "minecraft:nether_portal" => 11,
+        "minecraft:soul_torch" | "minecraft:soul_wall_torch" | "minecraft:soul_lantern" | "minecraft:soul_fire"
+        | "minecraft:soul_campfire" | "minecraft:crying_obsidian" | "minecraft:enchanting_table" => 10,
+        "minecraft:redstone_torch" | "minecraft:redstone_wall_torch" | "minecraft:glow_lichen" | "minecraft:cave_vines"
+        | "minecraft:cave_vines_plant" | "minecraft:redstone_ore" | "minecraft:deepslate_redstone_ore" => 7,
+        "minecraft:amethyst_cluster" => 5,
+        "minecraft:magma_block" => 3,
+        "minecraft:brewing_stand" | "minecraft:brown_mushroom" | "minecraft:dragon_egg" | "minecraft:end_portal_frame" => 1,
+        _ => 0,
+    }
+}
+
+// Block light over part of a world: the light the chunks were saved with,
+// and for sections saved without any, light spread from the blocks that
+// give it off. Torches added later light up what's around them on top.
+pub struct Lighting<'a> {
+    view: &'a WorldView,
+    // Spread from the blocks in the area, for when there's no light data
+    spread: HashMap<BlockPos, u8>,
+    // Spread from added torches
+    added: HashMap<BlockPos, u8>,
+}
+
+impl<'a> Lighting<'a> {
+    // Light reaches 15 blocks at most, so sources that far outside the box
+    // between min and max are looked at too
+    pub fn new(view: &'a WorldView, (min, max): (BlockPos, BlockPos)) -> Lighting<'a> {
+        let mut sources = Vec::new();
+        for y in min.y - 15..=max.y + 15 {
+            for z in min.z - 15..=max.z + 15 {
+                for x in min.x - 15..=max.x + 15 {
+                    let pos = BlockPos::new(x, y, z);
+                    let emission = view.block_at(pos).map_or(0, light_emission);
+                    if emission > 0 {
+                        sources.push((pos, emission));
+                    }
+                }
+            }
+        }
+
+        let mut spread = HashMap::new();
+        spread_light(view, sources, &mut spread);
+        Lighting { view, spread, added: HashMap::new() }
+    }
+
+    pub fn block_light(&self, pos: BlockPos) -> u8 {
+        let saved = self.view.block_light(pos).unwrap_or_else(|| self.spread.get(&pos).copied().unwrap_or(0));
+        saved.max(self.added.get(&pos).copied().unwrap_or(0))
+    }
+
+    pub fn add_torch(&mut self, pos: BlockPos) {
+        spread_light(self.view, vec![(pos, TORCH_LIGHT)], &mut self.added);
+    }
+}
+
+// Light goes one level down with every block it passes, and stops at full,
+// opaque blocks and at the edge of what's loaded
+fn spread_light(view: &WorldView, sources: Vec<(BlockPos, u8)>, levels: &mut HashMap<BlockPos, u8>) {
+    let mut queue = VecDeque::new();
+    for (pos, level) in sources {
+        if levels.get(&pos).is_none_or(|known| level > *known) {
+            levels.insert(pos, level);
+            queue.push_back((pos, level));
+        }
+    }
+
+    while let Some((pos, level)) = queue.pop_front() {
+        if levels.get(&pos).is_some_and(|known| *known > level) || level <= 1 {
+            continue;
+        }
+        for next in [pos.offset(1, 0, 0), pos.offset(-1, 0, 0), pos.offset(0, 1, 0), pos.offset(0, -1, 0), pos.offset(0, 0, 1), pos.offset(0, 0, -1)] {
+            if view.block_at(next).is_none_or(can_spawn_on) {
+                continue;
+            }
+            if levels.get(&next).is_none_or(|known| level - 1 > *known) {
+                levels.insert(next, level - 1);
+                queue.push_back((next, level - 1));
+            }
+        }
+    }
+}
+
+// Walkable positions next to each other that are too dark, with torches
+// that light all of them
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct DarkZone {
+    // Feet positions, sorted
+    pub positions: Vec<BlockPos>,
+    // Corners of the box around them
+    pub min: BlockPos,
+    pub max: BlockPos,
+    // Feet positions to put torches on the floor at, like the planner's
+    // Action::Torch
+    pub torches: Vec<BlockPos>,
+}
+
+impl fmt::Display for DarkZone {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "Dark zone of {} blocks from {} to {}, lit by {} torches", self.positions.len(), self.min, self.max, self.torches.len())
+    }
+}
+
+// Finds the dark spots of the mines already dug in the box between min and
+// max: positions below the surface the miner can stand at, where the block
+// light is at most max_block_light so mobs can spawn. Positions are in the
+// same zone when the miner can walk from one to the other in a step. Sky
+// light is left out since it's dark enough at night, like find_spawnable.
+pub fn find_dark_zones(view: &WorldView, (min, max): (BlockPos, BlockPos), max_block_light: u8) -> Vec<DarkZone> {
+    let mut lighting = Lighting::new(view, (min, max));
+
+    let mut dark = HashSet::new();
+    for chunk in view.chunks() {
+        let heights = chunk.surface_heights();
+        for z in (chunk.z() * 16).max(min.z)..=(chunk.z() * 16 + 15).min(max.z) {
+            for x in (chunk.x() * 16).max(min.x)..=(chunk.x() * 16 + 15).min(max.x) {
+                for y in min.y..=max.y {
+                    let feet = BlockPos::new(x, y, z);
+                    if is_below_surface(&heights, feet) && is_walkable(view, feet) && lighting.block_light(feet) <= max_block_light {
+                        dark.insert(feet);
+                    }
+                }
+            }
+        }
+    }
+
+    let mut zones = Vec::new();
+    let mut sorted: Vec<BlockPos> = dark.iter().copied().collect();
+    sorted.sort();
+    let mut seen = HashSet::new();
+    for start in sorted {
+        if !seen.insert(start) {
+            continue;
+        }
+        let mut positions = vec![start];
+        let mut queue = VecDeque::from([start]);
+        while let Some(pos) = queue.pop_front() {
+            for next in walking_moves(pos) {
+                if dark.contains(&next) && seen.insert(next) {
+                    positions.push(next);
+                    queue.push_back(next);
+                }
+            }
+        }
+        positions.sort();
+
+        let min = BlockPos::new(
+            positions.iter().map(|pos| pos.x).min().unwrap(),
+            positions.iter().map(|pos| pos.y).min().unwrap(),
+            positions.iter().map(|pos| pos.z).min().unwrap(),
+        );
+        let max = BlockPos::new(
+            positions.iter().map(|pos| pos.x).max().unwrap(),
+            positions.iter().map(|pos| pos.y).max().unwrap(),
+            positions.iter().map(|pos| pos.z).max().unwrap(),
+        );
+        let torches = place_torches(&mut lighting, &positions, max_block_light);
+        zones.push(DarkZone { positions, min, max, torches });
+    }
+
+    zones
+}
+
+// Goes through the zone in order, and for each position still dark puts a
+// torch on the dark position farthest from it that the torch still lights it
+// from, so each torch lights as much as it can that isn't lit yet
+fn place_torches(lighting: &mut Lighting, positions: &[BlockPos], max_block_light: u8) -> Vec<BlockPos> {
+    let zone: HashSet<BlockPos> = positions.iter().copied().collect();
+    // Light drops at least one level a block, so a torch this far away still lights a position enough
+    let reach = (TORCH_LIGHT - 1).saturating_sub(max_block_light) as u32;
+    let mut torches = Vec::new();
+
+    for dark in positions {
+        if lighting.block_light(*dark) > max_block_light {
+            continue;
+        }
+
+        let mut farthest = *dark;
+        let mut seen = HashSet::from([*dark]);
+        let mut queue = VecDeque::from([*dark]);
+        while let Some(pos) = queue.pop_front() {
+            if taxicab(pos, *dark) > taxicab(farthest, *dark) && lighting.block_light(pos) <= max_block_light {
+                farthest = pos;
+            }
+            for next in walking_moves(pos) {
+                if zone.contains(&next) && taxicab(next, *dark) <= reach && seen.insert(next) {
+                    queue.push_back(next);
+                }
+            }
+        }
+
+        // Walls in the way can make the light go the long way around
+        lighting.add_torch(farthest);
+        torches.push(farthest);
+        if lighting.block_light(*dark) <= max_block_light && farthest != *dark {
+            lighting.add_torch(*dark);
+            torches.push(*dark);
+        }
+    }
+
+    torches
+}
+
+fn taxicab(a: BlockPos, b: BlockPos) -> u32 {
+    a.x.abs_diff(b.x) + a.y.abs_diff(b.y) + a.z.abs_diff(b.z)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{ chunk::{ BlockState, Chunk }, nbt::{ Tag, TagPayload } };
+
+    fn tag(name: &str, payload: TagPayload) -> Tag {
+        Tag { name: name.to_string(), payload }
+    }
+
+    // Chunk 0, 0 from y 0 to 31 without light data, each block whatever block gives for it
+    fn view(block: impl Fn(BlockPos) -> &'static str) -> WorldView {
+        let air = TagPayload::Compound(vec![tag("Name", TagPayload::String("minecraft:air".to_string()))].into());
+        let sections = (0..2).map(|y| {
+            let block_states = vec![tag("palette", TagPayload::List(vec![air.clone()]))];
+            TagPayload::Compound(vec![tag("Y", TagPayload::Byte(y)), tag("block_states", TagPayload::Compound(block_states.into()))].into())
+        }).collect();
+        let root = vec![
+            tag("DataVersion", TagPayload::Int(3700)),
+            tag("xPos", TagPayload::Int(0)),
+            tag("zPos", TagPayload::Int(0)),
+            tag("yPos", TagPayload::Int(0)),
+            tag("sections", TagPayload::List(sections)),
+        ];
+        let mut chunk = Chunk::from_nbt(tag("", TagPayload::Compound(root.into()))).unwrap();
+        for y in 0..32 {
+            for z in 0..16 {
+                for x in 0..16 {
+                    let name = block(BlockPos::new(x, y, z));
+                    if name != "minecraft:air" {
+                        chunk.set_block(x as usize, y, z as usize, BlockState::new(name)).unwrap();
+                    }
+                }
+            }
+        }
+
+        let mut view = WorldView::new();
+        view.insert(chunk);
+        view
+    }
+
+    // A tunnel two blocks high along x at z 1 and another along z at x 14,
+    // joining at the corner, deep in the stone
+    fn tunnel(torches: &[BlockPos]) -> impl Fn(BlockPos) -> &'static str + '_ {
+        move |pos| {
+            if torches.contains(&pos) {
+                "minecraft:torch"
+            } else if (pos.y == 5 || pos.y == 6) && ((pos.z == 1 && pos.x <= 14) || (pos.x == 14 && pos.z >= 1)) {
+                "minecraft:air"
+            } else {
+                "minecraft:stone"
+            }
+        }
+    }
+
+    #[test]
+    fn finds_a_dark_tunnel_and_lights_it() {
+        let area = (BlockPos::new(0, 0, 0), BlockPos::new(15, 31, 15));
+        let zones = find_dark_zones(&view(tunnel(&[])), area, 0);
+
+        assert_eq!(zones.len(), 1);
+        let zone = &zones[0];
+        assert_eq!(zone.positions.len(), 15 + 14);
+        assert_eq!((zone.min, zone.max), (BlockPos::new(0, 5, 1), BlockPos::new(14, 5, 15)));
+        assert!(!zone.torches.is_empty());
+        assert!(zone.torches.len() < 4, "{:?}", zone.torches);
+        assert!(zone.torches.iter().all(|torch| zone.positions.contains(torch)));
+
+        // With the torches put in, the propagated light leaves nothing dark
+        assert!(find_dark_zones(&view(tunnel(&zone.torches)), area, 0).is_empty());
+        // Even where mobs spawned up to light 7, before 1.18
+        let zones = find_dark_zones(&view(tunnel(&[])), area, 7);
+        let torches: Vec<BlockPos> = zones.iter().flat_map(|zone| zone.torches.clone()).collect();
+        assert!(find_dark_zones(&view(tunnel(&torches)), area, 7).is_empty());
+    }
+
+    #[test]
+    fn goes_by_saved_light_and_emitters() {
+        let view = view(|pos| if pos == BlockPos::new(3, 5, 1) { "minecraft:glowstone" } else { tunnel(&[])(pos) });
+        let lighting = Lighting::new(&view, (BlockPos::new(0, 0, 0), BlockPos::new(15, 31, 15)));
+        assert_eq!(lighting.block_light(BlockPos::new(3, 6, 1)), 14);
+        assert_eq!(lighting.block_light(BlockPos::new(10, 5, 1)), 8);
+        assert_eq!(lighting.block_light(BlockPos::new(3, 5, 3)), 0);
+    }
+}
@@ -0,0 +1,93 @@
+use anyhow::{ Result, bail };
+use std::{ fs, path::Path, str::FromStr };
+
+use crate::flooding::{ Flood, Fluid };
+use crate::pathfinding::Route;
+use crate::pos::BlockPos;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum RouteMarker {
+    // Leaves the world alone but only shows for a moment, so the function has
+    // to be run again, e.g. from a repeating command block
+    Particles,
+    // Turns every block the route digs through into glass, leaving the targets
+    Blocks,
+}
+
+impl FromStr for RouteMarker {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<RouteMarker> {
+        match s {
+            "particles" => Ok(RouteMarker::Particles),
+            "blocks" => Ok(RouteMarker::Blocks),
+            _ => bail!("Unknown marker \"{s}\", expected particles or blocks"),
+        }
+    }
+}
+
+// Commands that show the route in game, with targets marked separately
+pub fn route_commands(route: &Route, targets: &[BlockPos], marker: RouteMarker) -> Vec<String> {
+    let mut commands = Vec::new();
+    if let (Some(start), Some(end)) = (route.steps.first(), route.steps.last()) {
+        commands.push(format!("# Route from {start} to {end}, {} steps and {} blocks to mine", route.steps.len(), route.mined.len()));
+    }
+    for hazard in &route.hazards {
+        commands.push(format!("# {hazard}"));
+    }
+
+    match marker {
+        RouteMarker::Particles => {
+            for pos in &route.steps {
+                commands.push(format!("particle minecraft:end_rod {} 0 0 0 0 1 force", center(pos)));
+            }
+            for pos in targets {
+                commands.push(format!("particle minecraft:happy_villager {} 0.3 0.3 0.3 0 5 force", center(pos)));
+            }
+            for hazard in &route.hazards {
+                commands.push(format!("particle minecraft:flame {} 0.3 0.3 0.3 0 5 force", center(&hazard.pos)));
+            }
+        },
+        RouteMarker::Blocks => {
+            for pos in route.mined.iter().filter(|pos| !targets.contains(pos)) {
+                commands.push(format!("setblock {} {} {} minecraft:glass", pos.x, pos.y, pos.z));
+            }
+        },
+    }
+
+    commands
+}
+
+// Commands that point out where floods would get into the route, to go
+// after route_commands. Blocks put glass over each source, which seals it.
+pub fn flood_commands(floods: &[Flood], marker: RouteMarker) -> Vec<String> {
+    let mut commands: Vec<String> = floods.iter().map(|flood| format!("# {flood}")).collect();
+    for flood in floods {
+        let pos = &flood.source;
+        match marker {
+            RouteMarker::Particles => {
+                let particle = match flood.fluid {
+                    Fluid::Lava => "minecraft:dripping_lava",
+                    Fluid::Water => "minecraft:dripping_water",
+                };
+                commands.push(format!("particle {particle} {} 0.3 0.3 0.3 0 5 force", center(pos)));
+            },
+            RouteMarker::Blocks => commands.push(format!("setblock {} {} {} minecraft:glass", pos.x, pos.y, pos.z)),
+        }
+    }
+    commands
+}
+
+// Puts a torch at each position, leaving whatever is there already
+pub fn torch_commands(torches: &[BlockPos]) -> Vec<String> {
+    torches.iter().map(|pos| format!("setblock {} {} {} minecraft:torch keep", pos.x, pos.y, pos.z)).collect()
+}
+
+fn center(pos: &BlockPos) -> String {
+    format!("{} {} {}", pos.x as f64 + 0.5, pos.y as f64 + 0.5, pos.z as f64 + 0.5)
+}
+
+pub fn write_mcfunction(path: impl AsRef<Path>, commands: &[String]) -> Result<()> {
+    fs::write(path, commands.join("\n") + "\n")?;
+    Ok(())
+}
@@ -0,0 +1,201 @@
+use crate::{ pos::BlockPos, world::WorldView, chunk::BlockType };
+
+pub const DIRECTIONS: [[i32; 3]; 6] = [
+    [1, 0, 0],
+    [-1, 0, 0],
+    [0, 1, 0],
+    [0, -1, 0],
+    [0, 0, 1],
+    [0, 0, -1],
+];
+
+pub struct Face<'a> {
+    pub pos: BlockPos,
+    pub normal: [i32; 3],
+    pub block: &'a BlockType,
+}
+
+impl Face<'_> {
+    // Corners of the face in counter-clockwise order when looking at it from outside the block
+    pub fn corners(&self) -> [[i32; 3]; 4] {
+        let [nx, ny, nz] = self.normal;
+        let BlockPos { x, y, z } = self.pos;
+
+        // The face lies on the plane of the cube side the normal points to
+        let base = [x + nx.max(0), y + ny.max(0), z + nz.max(0)];
+
+        let (u, v) = face_axes(self.normal);
+
+        let add = |a: [i32; 3], b: [i32; 3]| [a[0] + b[0], a[1] + b[1], a[2] + b[2]];
+        let corners = [base, add(base, u), add(add(base, u), v), add(base, v)];
+
+        // u x v points along the positive axis, so faces pointing the other way need the reverse order
+        if nx + ny + nz > 0 {
+            corners
+        } else {
+            [corners[0], corners[3], corners[2], corners[1]]
+        }
+    }
+}
+
+// Unit vectors spanning a face with this normal, so that u x v points along the
+// normal's axis
+fn face_axes(normal: [i32; 3]) -> ([i32; 3], [i32; 3]) {
+    match normal {
+        [_, 0, 0] => ([0, 1, 0], [0, 0, 1]),
+        [0, _, 0] => ([0, 0, 1], [1, 0, 0]),
+        _ => ([1, 0, 0], [0, 1, 0]),
+    }
+}
+
+// All block faces in the view that border air or unloaded space
+pub fn exposed_faces(view: &WorldView) -> Vec<Face<'_>> {
+    let mut faces = Vec::new();
+
+    for chunk in view.chunks() {
+        for section in chunk.sections() {
+            if section.palette().iter().all(BlockType::is_air) {
+                continue;
+            }
+
+            for (i, palette_index) in section.block_states().indices().into_iter().enumerate() {
+                let block = &section.palette()[palette_index];
+                if block.is_air() {
+                    continue;
+                }
+
+                let pos = chunk.block_pos(section, i);
+
+                for normal in DIRECTIONS {
+                    let neighbor = view.block_at(pos.offset(normal[0], normal[1], normal[2]));
+                    if neighbor.is_none_or(BlockType::is_air) {
+                        faces.push(Face { pos, normal, block });
+                    }
+                }
+            }
+        }
+    }
+
+    faces
+}
+
+// A rectangle of faces of the same block, merged by greedy_mesh
+pub struct Quad<'a> {
+    // The block in the corner closest to negative infinity
+    pub pos: BlockPos,
+    pub normal: [i32; 3],
+    // How many blocks the quad spans along the face's u and v axes
+    pub size: [i32; 2],
+    pub block: &'a BlockType,
+}
+
+impl Quad<'_> {
+    // Same order as Face::corners
+    pub fn corners(&self) -> [[i32; 3]; 4] {
+        let [nx, ny, nz] = self.normal;
+        let BlockPos { x, y, z } = self.pos;
+        let base = [x + nx.max(0), y + ny.max(0), z + nz.max(0)];
+
+        let (u, v) = face_axes(self.normal);
+        let u = u.map(|c| c * self.size[0]);
+        let v = v.map(|c| c * self.size[1]);
+
+        let add = |a: [i32; 3], b: [i32; 3]| [a[0] + b[0], a[1] + b[1], a[2] + b[2]];
+        let corners = [base, add(base, u), add(add(base, u), v), add(base, v)];
+
+        if nx + ny + nz > 0 {
+            corners
+        } else {
+            [corners[0], corners[3], corners[2], corners[1]]
+        }
+    }
+}
+
+// Visible faces of the blocks in the inclusive box from min to max, with
+// neighbouring faces of the same block merged into rectangles. Faces on the
+// sides of the box count as visible so cuts through the terrain are closed.
+pub fn greedy_mesh(view: &WorldView, min: BlockPos, max: BlockPos) -> Vec<Quad<'_>> {
+    let size = [max.x - min.x + 1, max.y - min.y + 1, max.z - min.z + 1];
+    if size.iter().any(|&side| side <= 0) {
+        return Vec::new();
+    }
+
+    // Solid blocks of the box, indexed like the box's own coordinates
+    let index = |p: [i32; 3]| ((p[1] * size[2] + p[2]) * size[0] + p[0]) as usize;
+    let mut solid: Vec<Option<&BlockType>> = Vec::with_capacity((size[0] * size[1] * size[2]) as usize);
+    for y in 0..size[1] {
+        for z in 0..size[2] {
+            for x in 0..size[0] {
+                solid.push(view.block_at(min.offset(x, y, z)).filter(|block| !block.is_air()));
+            }
+        }
+    }
+    let block_at = |p: [i32; 3]| {
+        let inside = (0..3).all(|axis| (0..size[axis]).contains(&p[axis]));
+        if inside { solid[index(p)] } else { None }
+    };
+
+    let mut quads = Vec::new();
+
+    for normal in DIRECTIONS {
+        let (u, v) = face_axes(normal);
+        let axis = |vector: [i32; 3]| vector.iter().position(|&c| c != 0).unwrap_or(0);
+        let (d, u, v) = (axis(normal), axis(u), axis(v));
+
+        for layer in 0..size[d] {
+            // Which block shows a face at each spot of the layer, row by row along v
+            let mut mask: Vec<Option<&BlockType>> = Vec::with_capacity((size[u] * size[v]) as usize);
+            for j in 0..size[v] {
+                for i in 0..size[u] {
+                    let mut p = [0; 3];
+                    p[d] = layer;
+                    p[u] = i;
+                    p[v] = j;
+                    let neighbor = [p[0] + normal[0], p[1] + normal[1], p[2] + normal[2]];
+                    mask.push(block_at(p).filter(|_| block_at(neighbor).is_none()));
+                }
+            }
+
+            let width = size[u] as usize;
+            let same = |a: Option<&BlockType>, b: &BlockType| a.is_some_and(|a| a.name == b.name);
+
+            for j in 0..size[v] as usize {
+                let mut i = 0;
+                while i < width {
+                    let Some(block) = mask[j * width + i] else {
+                        i += 1;
+                        continue;
+                    };
+
+                    let mut w = 1;
+                    while i + w < width && same(mask[j * width + i + w], block) {
+                        w += 1;
+                    }
+                    let mut h = 1;
+                    while j + h < size[v] as usize && (i..i + w).all(|k| same(mask[(j + h) * width + k], block)) {
+                        h += 1;
+                    }
+
+                    for row in j..j + h {
+                        mask[row * width + i..row * width + i + w].fill(None);
+                    }
+
+                    let mut offset = [0; 3];
+                    offset[d] = layer;
+                    offset[u] = i as i32;
+                    offset[v] = j as i32;
+                    quads.push(Quad {
+                        pos: min.offset(offset[0], offset[1], offset[2]),
+                        normal,
+                        size: [w as i32, h as i32],
+                        block,
+                    });
+
+                    i += w;
+                }
+            }
+        }
+    }
+
+    quads
+}
@@ -0,0 +1,158 @@
+use anyhow::Result;
+use serde_json::json;
+use std::{ collections::BTreeMap, fs::{ self, File }, io::{ BufWriter, Write }, path::Path };
+
+use crate::mesh::Quad;
+use crate::pos::BlockPos;
+use crate::render::{ BlockColors, hashed_color };
+
+// Writers for 3D model formats. Both take the corner of the selection as the
+// origin to keep the model near zero.
+
+// Quads grouped by block, so each block gets one material
+fn by_block<'a>(quads: &'a [Quad<'a>]) -> BTreeMap<&'a str, Vec<&'a Quad<'a>>> {
+    let mut groups: BTreeMap<&str, Vec<&Quad>> = BTreeMap::new();
+    for quad in quads {
+        groups.entry(quad.block.name.as_str()).or_default().push(quad);
+    }
+    groups
+}
+
+fn color_of(colors: &BlockColors, name: &str) -> [f32; 4] {
+    colors.lookup(name).unwrap_or_else(|| hashed_color(name)).map(|c| c as f32 / 255.0)
+}
+
+fn material_name(block: &str) -> String {
+    block.replace(':', "_")
+}
+
+// Writes a Wavefront OBJ file and, next to it, the .mtl file with a material per block
+pub fn write_obj(path: &Path, quads: &[Quad], colors: &BlockColors, origin: BlockPos) -> Result<()> {
+    let mtl_path = path.with_extension("mtl");
+    let groups = by_block(quads);
+
+    let mut mtl = BufWriter::new(File::create(&mtl_path)?);
+    for &block in groups.keys() {
+        let [r, g, b, a] = color_of(colors, block);
+        writeln!(mtl, "newmtl {}\nKd {r} {g} {b}\nd {a}\n", material_name(block))?;
+    }
+    mtl.flush()?;
+
+    let mut obj = BufWriter::new(File::create(path)?);
+    if let Some(name) = mtl_path.file_name() {
+        writeln!(obj, "mtllib {}", name.to_string_lossy())?;
+    }
+    for normal in crate::mesh::DIRECTIONS {
+        writeln!(obj, "vn {} {} {}", normal[0], normal[1], normal[2])?;
+    }
+
+    let mut vertices = 0;
+    for (block, quads) in &groups {
+        writeln!(obj, "usemtl {}", material_name(block))?;
+        for quad in quads {
+            for [x, y, z] in quad.corners() {
+                writeln!(obj, "v {} {} {}", x - origin.x, y - origin.y, z - origin.z)?;
+            }
+            let normal = crate::mesh::DIRECTIONS.iter().position(|&normal| normal == quad.normal).unwrap_or(0) + 1;
+            let n = vertices + 1;
+            writeln!(obj, "f {}//{normal} {}//{normal} {}//{normal} {}//{normal}", n, n + 1, n + 2, n + 3)?;
+            vertices += 4;
+        }
+    }
+    obj.flush()?;
+
+    Ok(())
+}
+
+// Writes a binary glTF (.glb) file with one primitive and material per block
+pub fn write_glb(path: &Path, quads: &[Quad], colors: &BlockColors, origin: BlockPos) -> Result<()> {
+    let mut buffer: Vec<u8> = Vec::new();
+    let mut buffer_views = Vec::new();
+    let mut accessors = Vec::new();
+    let mut materials = Vec::new();
+    let mut primitives = Vec::new();
+
+    // Appends the data as a buffer view and returns its index
+    let mut push_view = |buffer: &mut Vec<u8>, data: &[u8], target: u32| {
+        let view = json!({ "buffer": 0, "byteOffset": buffer.len(), "byteLength": data.len(), "target": target });
+        buffer.extend_from_slice(data);
+        buffer_views.push(view);
+        buffer_views.len() - 1
+    };
+
+    for (block, quads) in by_block(quads) {
+        let mut positions: Vec<f32> = Vec::with_capacity(quads.len() * 12);
+        let mut normals: Vec<f32> = Vec::with_capacity(quads.len() * 12);
+        let mut indices: Vec<u32> = Vec::with_capacity(quads.len() * 6);
+        let mut min = [f32::MAX; 3];
+        let mut max = [f32::MIN; 3];
+
+        for quad in &quads {
+            let first = (positions.len() / 3) as u32;
+            for corner in quad.corners() {
+                let position = [corner[0] - origin.x, corner[1] - origin.y, corner[2] - origin.z].map(|c| c as f32);
+                for axis in 0..3 {
+                    min[axis] = min[axis].min(position[axis]);
+                    max[axis] = max[axis].max(position[axis]);
+                }
+                positions.extend(position);
+                normals.extend(quad.normal.map(|c| c as f32));
+            }
+            // Corners go counter-clockwise seen from the front, as glTF wants
+            indices.extend([first, first + 1, first + 2, first, first + 2, first + 3]);
+        }
+
+        let bytes = |values: &[f32]| values.iter().flat_map(|value| value.to_le_bytes()).collect::<Vec<u8>>();
+        let position_view = push_view(&mut buffer, &bytes(&positions), 34962);
+        let normal_view = push_view(&mut buffer, &bytes(&normals), 34962);
+        let index_bytes: Vec<u8> = indices.iter().flat_map(|index| index.to_le_bytes()).collect();
+        let index_view = push_view(&mut buffer, &index_bytes, 34963);
+
+        let vertex_count = positions.len() / 3;
+        accessors.push(json!({ "bufferView": position_view, "componentType": 5126, "count": vertex_count, "type": "VEC3", "min": min, "max": max }));
+        accessors.push(json!({ "bufferView": normal_view, "componentType": 5126, "count": vertex_count, "type": "VEC3" }));
+        accessors.push(json!({ "bufferView": index_view, "componentType": 5125, "count": indices.len(), "type": "SCALAR" }));
+
+        materials.push(json!({
+            "name": block,
+            "pbrMetallicRoughness": { "baseColorFactor": color_of(colors, block), "metallicFactor": 0.0, "roughnessFactor": 1.0 },
+        }));
+        primitives.push(json!({
+            "attributes": { "POSITION": accessors.len() - 3, "NORMAL": accessors.len() - 2 },
+            "indices": accessors.len() - 1,
+            "material": materials.len() - 1,
+        }));
+    }
+
+    let document = json!({
+        "asset": { "version": "2.0", "generator": "path-miner" },
+        "scene": 0,
+        "scenes": [{ "nodes": [0] }],
+        "nodes": [{ "mesh": 0, "name": "terrain" }],
+        "meshes": [{ "primitives": primitives }],
+        "materials": materials,
+        "accessors": accessors,
+        "bufferViews": buffer_views,
+        "buffers": [{ "byteLength": buffer.len() }],
+    });
+
+    // Both chunks have to be padded to 4 bytes, JSON with spaces and the buffer with zeroes
+    let mut json = serde_json::to_vec(&document)?;
+    json.resize(json.len().next_multiple_of(4), b' ');
+    buffer.resize(buffer.len().next_multiple_of(4), 0);
+
+    let total = 12 + 8 + json.len() + 8 + buffer.len();
+    let mut glb = Vec::with_capacity(total);
+    glb.extend_from_slice(b"glTF");
+    glb.extend_from_slice(&2u32.to_le_bytes());
+    glb.extend_from_slice(&(total as u32).to_le_bytes());
+    glb.extend_from_slice(&(json.len() as u32).to_le_bytes());
+    glb.extend_from_slice(b"JSON");
+    glb.extend_from_slice(&json);
+    glb.extend_from_slice(&(buffer.len() as u32).to_le_bytes());
+    glb.extend_from_slice(b"BIN\0");
+    glb.extend_from_slice(&buffer);
+
+    fs::write(path, glb)?;
+    Ok(())
+}
@@ -0,0 +1,1494 @@
+use std::{collections::HashMap, slice::Iter, fmt, io::{self, Write}, ops::{Deref, DerefMut}, sync::OnceLock};
+
+// Nesting limit used by vanilla's NBT reader, the default for ParseOptions
+pub const MAX_DEPTH: usize = 512;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum NbtError {
+    UnexpectedEof { offset: usize },
+    InvalidTagId { offset: usize, id: u8 },
+    InvalidUtf8 { offset: usize },
+    NegativeLength { offset: usize, len: i32 },
+    DepthLimitExceeded { offset: usize, limit: usize },
+    LengthLimitExceeded { offset: usize, len: usize, limit: usize },
+    InvalidVarInt { offset: usize },
+}
+
+impl NbtError {
+    pub fn offset(&self) -> usize {
+        match self {
+            NbtError::UnexpectedEof { offset }
+            | NbtError::InvalidTagId { offset, .. }
+            | NbtError::InvalidUtf8 { offset }
+            | NbtError::NegativeLength { offset, .. }
+            | NbtError::DepthLimitExceeded { offset, .. }
+            | NbtError::LengthLimitExceeded { offset, .. }
+            | NbtError::InvalidVarInt { offset } => *offset,
+        }
+    }
+
+    // Errors are raised with the number of bytes left in the input, since that's all
+    // the iterator knows; this turns that into an offset from the start of the input
+    pub(crate) fn rebase(mut self, input_len: usize) -> NbtError {
+        match &mut self {
+            NbtError::UnexpectedEof { offset }
+            | NbtError::InvalidTagId { offset, .. }
+            | NbtError::InvalidUtf8 { offset }
+            | NbtError::NegativeLength { offset, .. }
+            | NbtError::DepthLimitExceeded { offset, .. }
+            | NbtError::LengthLimitExceeded { offset, .. }
+            | NbtError::InvalidVarInt { offset } => *offset = input_len - *offset,
+        }
+        self
+    }
+}
+
+impl fmt::Display for NbtError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            NbtError::UnexpectedEof { offset } => write!(f, "unexpected end of data at byte {offset}"),
+            NbtError::InvalidTagId { offset, id } => write!(f, "invalid tag id {id} at byte {offset}"),
+            NbtError::InvalidUtf8 { offset } => write!(f, "invalid UTF-8 string at byte {offset}"),
+            NbtError::NegativeLength { offset, len } => write!(f, "negative length {len} at byte {offset}"),
+            NbtError::DepthLimitExceeded { offset, limit } => write!(f, "nesting deeper than {limit} at byte {offset}"),
+            NbtError::LengthLimitExceeded { offset, len, limit } => write!(f, "length {len} over the limit of {limit} at byte {offset}"),
+            NbtError::InvalidVarInt { offset } => write!(f, "VarInt too long at byte {offset}"),
+        }
+    }
+}
+
+impl std::error::Error for NbtError {}
+
+// Raised when converting between TagPayload and Rust types with serde
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SerdeError {
+    message: String,
+}
+
+impl SerdeError {
+    pub(crate) fn new(message: impl Into<String>) -> SerdeError {
+        SerdeError { message: message.into() }
+    }
+}
+
+impl fmt::Display for SerdeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.message)
+    }
+}
+
+impl std::error::Error for SerdeError {}
+
+impl serde::ser::Error for SerdeError {
+    fn custom<T: fmt::Display>(msg: T) -> Self {
+        SerdeError::new(msg.to_string())
+    }
+}
+
+impl serde::de::Error for SerdeError {
+    fn custom<T: fmt::Display>(msg: T) -> Self {
+        SerdeError::new(msg.to_string())
+    }
+}
+
+pub(crate) trait NextPlusPlus {
+    fn next_byte(&mut self) -> Result<u8, NbtError>;
+
+    fn next_n<const N: usize>(&mut self) -> Result<[u8; N], NbtError>;
+
+    fn next_n_i8_vec(&mut self, n: usize) -> Result<Vec<i8>, NbtError>;
+    fn next_n_i32_vec(&mut self, n: usize) -> Result<Vec<i32>, NbtError>;
+    fn next_n_i64_vec(&mut self, n: usize) -> Result<Vec<i64>, NbtError>;
+
+    fn next_u8(&mut self) -> Result<u8, NbtError>;
+    fn next_u16(&mut self) -> Result<u16, NbtError>;
+    
+    fn next_i8(&mut self) -> Result<i8, NbtError>;
+    fn next_i16(&mut self) -> Result<i16, NbtError>;
+    fn next_i32(&mut self) -> Result<i32, NbtError>;
+    fn next_i64(&mut self) -> Result<i64, NbtError>;
+
+    fn next_f32(&mut self) -> Result<f32, NbtError>;
+    fn next_f64(&mut self) -> Result<f64, NbtError>;
+
+    fn next_string(&mut self, len: usize) -> Result<String, NbtError>;
+    fn next_len(&mut self) -> Result<usize, NbtError>;
+    fn next_tag_id(&mut self) -> Result<u8, NbtError>;
+    fn skip_n(&mut self, n: usize) -> Result<(), NbtError>;
+
+}
+
+// Reads through the iterator's remaining slice rather than byte by byte, so
+// numbers and arrays are copied out in one go
+impl NextPlusPlus for Iter<'_, u8> {
+
+    fn next_byte(&mut self) -> Result<u8, NbtError> {
+        match self.next() {
+            Some(byte) => Ok(*byte),
+            None => Err(NbtError::UnexpectedEof { offset: self.len() }),
+        }
+    }
+
+    fn next_n<const N: usize>(&mut self) -> Result<[u8; N], NbtError> {
+        let Some((bytes, rest)) = self.as_slice().split_first_chunk::<N>() else {
+            *self = [].iter();
+            return Err(NbtError::UnexpectedEof { offset: 0 });
+        };
+        *self = rest.iter();
+        Ok(*bytes)
+    }
+    
+    fn next_u8(&mut self) -> Result<u8, NbtError> {
+        self.next_byte()
+    }
+
+    fn next_u16(&mut self) -> Result<u16, NbtError> {
+        Ok(u16::from_be_bytes(self.next_n::<2>()?))
+    }
+
+    fn next_i8(&mut self) -> Result<i8, NbtError> {
+        Ok(self.next_byte()? as i8)
+    }
+
+    fn next_i16(&mut self) -> Result<i16, NbtError> {
+        Ok(i16::from_be_bytes(self.next_n::<2>()?))
+    }
+
+    fn next_i32(&mut self) -> Result<i32, NbtError> {
+        Ok(i32::from_be_bytes(self.next_n::<4>()?))
+    }
+
+    fn next_i64(&mut self) -> Result<i64, NbtError> {
+        Ok(i64::from_be_bytes(self.next_n::<8>()?))
+    }
+
+    fn next_f32(&mut self) -> Result<f32, NbtError> {
+        Ok(f32::from_be_bytes(self.next_n::<4>()?))
+    }
+
+    fn next_f64(&mut self) -> Result<f64, NbtError> {
+        Ok(f64::from_be_bytes(self.next_n::<8>()?))
+    }
+
+    fn next_string(&mut self, len: usize) -> Result<String, NbtError> {
+        let offset = self.len();
+        std::str::from_utf8(self.next_slice(len)?).map(str::to_string).map_err(|_| NbtError::InvalidUtf8 { offset })
+    }
+
+    fn next_len(&mut self) -> Result<usize, NbtError> {
+        let offset = self.len();
+        let len = self.next_i32()?;
+        usize::try_from(len).map_err(|_| NbtError::NegativeLength { offset, len })
+    }
+
+    fn next_tag_id(&mut self) -> Result<u8, NbtError> {
+        let offset = self.len();
+        match self.next_u8()? {
+            id @ 0..=12 => Ok(id),
+            id => Err(NbtError::InvalidTagId { offset, id }),
+        }
+    }
+
+    fn skip_n(&mut self, n: usize) -> Result<(), NbtError> {
+        self.next_slice(n).map(|_| ())
+    }
+
+    fn next_n_i8_vec(&mut self, n: usize) -> Result<Vec<i8>, NbtError> {
+        Ok(self.next_slice(n)?.iter().map(|&byte| byte as i8).collect())
+    }
+
+    fn next_n_i32_vec(&mut self, n: usize) -> Result<Vec<i32>, NbtError> {
+        let bytes = self.next_slice(n.saturating_mul(4))?;
+        Ok(bytes.chunks_exact(4).map(|chunk| i32::from_be_bytes([chunk[0], chunk[1], chunk[2], chunk[3]])).collect())
+    }
+
+    fn next_n_i64_vec(&mut self, n: usize) -> Result<Vec<i64>, NbtError> {
+        let bytes = self.next_slice(n.saturating_mul(8))?;
+        Ok(bytes.chunks_exact(8).map(|chunk| {
+            let mut long = [0; 8];
+            long.copy_from_slice(chunk);
+            i64::from_be_bytes(long)
+        }).collect())
+    }
+}
+
+pub(crate) trait NextSlice<'a> {
+    fn next_slice(&mut self, n: usize) -> Result<&'a [u8], NbtError>;
+}
+
+impl<'a> NextSlice<'a> for Iter<'a, u8> {
+    // The next n bytes, borrowed from the input
+    fn next_slice(&mut self, n: usize) -> Result<&'a [u8], NbtError> {
+        let bytes = self.as_slice();
+        if n > bytes.len() {
+            *self = [].iter();
+            return Err(NbtError::UnexpectedEof { offset: 0 });
+        }
+        let (taken, rest) = bytes.split_at(n);
+        *self = rest.iter();
+        Ok(taken)
+    }
+}
+
+#[derive(Clone)]
+pub struct Tag {
+    pub name: String,
+    pub payload: TagPayload,
+}
+
+impl fmt::Display for Tag {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.name.is_empty() {
+            write!(f, "{}", self.payload)
+        } else {
+            write!(f, "\"{}\": {}", self.name, self.payload)
+        }
+    }
+}
+
+// Only for the types in here, so more can be added without breaking anyone
+mod sealed {
+    pub trait Sealed {}
+
+    impl<T> Sealed for Vec<T> {}
+    impl Sealed for super::Compound {}
+}
+
+pub trait DumpContent: sealed::Sealed {
+    fn dump_content(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result;
+}
+
+impl<T: fmt::Display> DumpContent for Vec<T> {
+    fn dump_content(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if let Some((first, rest)) = self.split_first() {
+            write!(f, "{}", first)?;
+            for item in rest {
+                write!(f, ", {}", item)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+impl fmt::Display for TagPayload {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TagPayload::Byte(x) => write!(f, "{}", x),
+            TagPayload::Short(x) => write!(f, "{}", x),
+            TagPayload::Int(x) => write!(f, "{}", x),
+            TagPayload::Long(x) => write!(f, "{}", x),
+            TagPayload::Float(x) => write!(f, "{}", x),
+            TagPayload::Double(x) => write!(f, "{}", x),
+            TagPayload::ByteArray(x) => write!(f, "{:?}", x),
+            TagPayload::String(x) => write!(f, "\"{}\"", x),
+            TagPayload::List(x) => {
+                write!(f, "[ ")?;
+                x.dump_content(f)?;
+                write!(f, " ]")
+            },
+            TagPayload::Compound(x) => {
+                write!(f, "{{ ")?;
+                x.dump_content(f)?;
+                write!(f, " }}")
+            },
+            TagPayload::IntArray(x) => write!(f, "{:?}", x),
+            TagPayload::LongArray(x) => write!(f, "{:?}", x),
+        }
+    }
+}
+
+impl Tag {
+    
+    pub fn parse(iterator: &mut Iter<'_, u8>) -> Result<Tag, NbtError> {
+        Tag::parse_with(iterator, &NO_OPTIONS)
+    }
+
+    // Like parse, but leaves out the tags the options skip without building them
+    pub fn parse_with(iterator: &mut Iter<'_, u8>, options: &ParseOptions) -> Result<Tag, NbtError> {
+        let input_len = iterator.len();
+        Tag::parse_tag(iterator, options).map_err(|e| e.rebase(input_len))
+    }
+
+    pub fn parse_payload(iterator: &mut Iter<'_, u8>, tag_id: u8) -> Result<TagPayload, NbtError> {
+        let input_len = iterator.len();
+        Tag::parse_payload_at(iterator, tag_id, 0).map_err(|e| e.rebase(input_len))
+    }
+
+    fn parse_tag(iterator: &mut Iter<'_, u8>, options: &ParseOptions) -> Result<Tag, NbtError> {
+
+        let offset = iterator.len();
+        let tag_id = iterator.next_tag_id()?;
+        
+        if tag_id == 0 {
+            Err(NbtError::InvalidTagId { offset, id: tag_id })
+        } else {
+            
+            let name = match options.flavor.root_named() {
+                true => {
+                    let name_length = options.flavor.next_string_len(iterator)?;
+                    iterator.next_string(name_length)?
+                },
+                false => String::new(),
+            };
+
+            Ok(Tag {
+                name,
+                payload: Tag::parse_filtered(iterator, tag_id, 0, options, &mut Vec::new())?,
+            })
+        }
+    }
+
+    pub(crate) fn parse_payload_at(iterator: &mut Iter<'_, u8>, tag_id: u8, depth: usize) -> Result<TagPayload, NbtError> {
+        Tag::parse_filtered(iterator, tag_id, depth, &NO_OPTIONS, &mut Vec::new())
+    }
+
+    // path holds the names of the compound entries above this payload. Only
+    // lists and compounds recurse, the rest is parsed apart so the frames of
+    // the recursion stay small; debug builds would otherwise run out of a
+    // 2 MiB thread stack well before MAX_DEPTH.
+    fn parse_filtered(iterator: &mut Iter<'_, u8>, tag_id: u8, depth: usize, options: &ParseOptions, path: &mut Vec<String>) -> Result<TagPayload, NbtError> {
+        match tag_id {
+            9 => Tag::parse_list(iterator, depth, options, path),
+            10 => Tag::parse_compound(iterator, depth, options, path),
+            _ => Tag::parse_value(iterator, tag_id, options),
+        }
+    }
+
+    fn parse_value(iterator: &mut Iter<'_, u8>, tag_id: u8, options: &ParseOptions) -> Result<TagPayload, NbtError> {
+        let flavor = options.flavor;
+
+        match tag_id {
+            1 => Ok(TagPayload::Byte(iterator.next_i8()?)),
+            2 => Ok(TagPayload::Short(flavor.next_i16(iterator)?)),
+            3 => Ok(TagPayload::Int(flavor.next_i32(iterator)?)),
+            4 => Ok(TagPayload::Long(flavor.next_i64(iterator)?)),
+            5 => Ok(TagPayload::Float(flavor.next_f32(iterator)?)),
+            6 => Ok(TagPayload::Double(flavor.next_f64(iterator)?)),
+            7 => {
+                let arr_len = options.next_len(iterator)?;
+                Ok(TagPayload::ByteArray(iterator.next_n_i8_vec(arr_len)?))
+            },
+            8 => {
+                let str_len = flavor.next_string_len(iterator)?;
+                Ok(TagPayload::String(iterator.next_string(str_len)?))
+            },
+            11 => {
+                let arr_len = options.next_len(iterator)?;
+                Ok(TagPayload::IntArray(flavor.next_i32_vec(iterator, arr_len)?))
+            },
+            12 => {
+                let arr_len = options.next_len(iterator)?;
+                Ok(TagPayload::LongArray(flavor.next_i64_vec(iterator, arr_len)?))
+            },
+            _ => Err(NbtError::InvalidTagId { offset: iterator.len(), id: tag_id }),
+        }
+    }
+
+    fn parse_list(iterator: &mut Iter<'_, u8>, depth: usize, options: &ParseOptions, path: &mut Vec<String>) -> Result<TagPayload, NbtError> {
+        options.check_depth(iterator, depth)?;
+
+        let offset = iterator.len();
+        let tag_id = iterator.next_tag_id()?;
+        let tags_count = options.next_len(iterator)?;
+        let mut tag_list = Vec::new();
+
+        // Only empty lists may use the End tag as their element type
+        if tag_id == 0 && tags_count > 0 {
+            return Err(NbtError::InvalidTagId { offset, id: tag_id });
+        }
+        
+        for _ in 0..tags_count {
+            tag_list.push(Tag::parse_filtered(iterator, tag_id, depth + 1, options, path)?);
+        }
+
+        Ok(TagPayload::List(tag_list))
+    }
+
+    fn parse_compound(iterator: &mut Iter<'_, u8>, depth: usize, options: &ParseOptions, path: &mut Vec<String>) -> Result<TagPayload, NbtError> {
+        let flavor = options.flavor;
+
+        options.check_depth(iterator, depth)?;
+
+        let mut tag_id = iterator.next_tag_id()?;
+        let mut tag_list = Vec::new();
+
+        while tag_id != 0 {
+            
+            let name_length = flavor.next_string_len(iterator)?;
+            path.push(iterator.next_string(name_length)?);
+
+            if options.skips(path) {
+                Tag::skip_payload_at(iterator, tag_id, depth + 1, options.max_depth, flavor)?;
+                path.pop();
+            } else {
+                let payload = Tag::parse_filtered(iterator, tag_id, depth + 1, options, path)?;
+                let name = path.pop().unwrap_or_default();
+                tag_list.push(Tag { name, payload });
+            }
+
+            tag_id = iterator.next_tag_id()?;
+        }
+
+        Ok(TagPayload::Compound(tag_list.into()))
+    }
+
+    // Moves past a payload without building it. Only lists and compounds need
+    // walking, everything else has its size up front.
+    pub(crate) fn skip_payload_at(iterator: &mut Iter<'_, u8>, tag_id: u8, depth: usize, max_depth: usize, flavor: NbtFlavor) -> Result<(), NbtError> {
+        match tag_id {
+            1..=6 => match flavor.payload_size(tag_id) {
+                Some(size) => iterator.skip_n(size),
+                None => flavor.skip_varints(iterator, tag_id, 1),
+            },
+            7 | 11 | 12 => {
+                let arr_len = flavor.next_len(iterator)?;
+                let element_id = match tag_id { 7 => 1, 11 => 3, _ => 4 };
+                match flavor.payload_size(element_id) {
+                    Some(size) => iterator.skip_n(arr_len.saturating_mul(size)),
+                    None => flavor.skip_varints(iterator, element_id, arr_len),
+                }
+            },
+            8 => {
+                let str_len = flavor.next_string_len(iterator)?;
+                iterator.skip_n(str_len)
+            },
+            9 => {
+                if depth >= max_depth {
+                    return Err(NbtError::DepthLimitExceeded { offset: iterator.len(), limit: max_depth });
+                }
+
+                let offset = iterator.len();
+                let tag_id = iterator.next_tag_id()?;
+                let tags_count = flavor.next_len(iterator)?;
+
+                if tag_id == 0 && tags_count > 0 {
+                    return Err(NbtError::InvalidTagId { offset, id: tag_id });
+                }
+
+                if let Some(size) = flavor.payload_size(tag_id) {
+                    return iterator.skip_n(tags_count.saturating_mul(size));
+                }
+                for _ in 0..tags_count {
+                    Tag::skip_payload_at(iterator, tag_id, depth + 1, max_depth, flavor)?;
+                }
+                Ok(())
+            },
+            10 => {
+                if depth >= max_depth {
+                    return Err(NbtError::DepthLimitExceeded { offset: iterator.len(), limit: max_depth });
+                }
+
+                let mut tag_id = iterator.next_tag_id()?;
+                while tag_id != 0 {
+                    let name_length = flavor.next_string_len(iterator)?;
+                    iterator.skip_n(name_length)?;
+                    Tag::skip_payload_at(iterator, tag_id, depth + 1, max_depth, flavor)?;
+                    tag_id = iterator.next_tag_id()?;
+                }
+                Ok(())
+            },
+            _ => Err(NbtError::InvalidTagId { offset: iterator.len(), id: tag_id }),
+        }
+    }
+
+    pub fn write(&self, w: &mut impl Write) -> io::Result<()> {
+        self.write_as(w, NbtFlavor::Java)
+    }
+
+    // Writes the tag as the root of an NBT file or packet of this flavor
+    pub fn write_as(&self, w: &mut impl Write, flavor: NbtFlavor) -> io::Result<()> {
+        w.write_all(&[self.payload.id()])?;
+        if flavor.root_named() {
+            write_string(w, &self.name, flavor)?;
+        }
+        self.payload.write_as(w, flavor)
+    }
+}
+
+// Tags to leave out while parsing. Paths are the dot separated names of the
+// compounds below the root, with lists left out, so sections.block_light is the
+// block light of every section. A path without dots matches the name anywhere.
+//
+// The limits keep corrupt or hostile data from nesting deep enough to
+// overflow the stack or from making the parser reserve more than it reads.
+#[derive(Debug, Clone)]
+pub struct ParseOptions {
+    skip: Vec<Vec<String>>,
+    pub(crate) max_depth: usize,
+    max_len: usize,
+    flavor: NbtFlavor,
+}
+
+pub(crate) const NO_OPTIONS: ParseOptions = ParseOptions { skip: Vec::new(), max_depth: MAX_DEPTH, max_len: usize::MAX, flavor: NbtFlavor::Java };
+
+impl Default for ParseOptions {
+    fn default() -> ParseOptions {
+        NO_OPTIONS
+    }
+}
+
+impl ParseOptions {
+    pub fn new() -> ParseOptions {
+        ParseOptions::default()
+    }
+
+    // How deep lists and compounds may nest, MAX_DEPTH by default
+    pub fn max_depth(mut self, depth: usize) -> ParseOptions {
+        self.max_depth = depth;
+        self
+    }
+
+    // The most elements a list or array may declare, unlimited by default.
+    // Arrays longer than the rest of the input fail either way.
+    pub fn max_len(mut self, len: usize) -> ParseOptions {
+        self.max_len = len;
+        self
+    }
+
+    // How the NBT is encoded, Java's by default. TagRef and NbtReader only read Java's.
+    pub fn flavor(mut self, flavor: NbtFlavor) -> ParseOptions {
+        self.flavor = flavor;
+        self
+    }
+
+    pub fn skip(mut self, path: &str) -> ParseOptions {
+        self.skip.push(path.split('.').map(str::to_string).collect());
+        self
+    }
+
+    // Drops light data and heightmaps, which searching for blocks doesn't need
+    pub fn for_block_search() -> ParseOptions {
+        ParseOptions::for_surface_search().skip("Heightmaps")
+    }
+
+    // Drops light data but keeps the heightmaps, to tell where the surface is
+    pub fn for_surface_search() -> ParseOptions {
+        ParseOptions::new()
+            .skip("block_light")
+            .skip("sky_light")
+            .skip("BlockLight")
+            .skip("SkyLight")
+    }
+
+    pub(crate) fn check_depth(&self, iterator: &Iter<'_, u8>, depth: usize) -> Result<(), NbtError> {
+        if depth >= self.max_depth {
+            return Err(NbtError::DepthLimitExceeded { offset: iterator.len(), limit: self.max_depth });
+        }
+        Ok(())
+    }
+
+    // The length of a list or array, checked against max_len
+    pub(crate) fn next_len(&self, iterator: &mut Iter<'_, u8>) -> Result<usize, NbtError> {
+        let offset = iterator.len();
+        let len = self.flavor.next_len(iterator)?;
+        if len > self.max_len {
+            return Err(NbtError::LengthLimitExceeded { offset, len, limit: self.max_len });
+        }
+        Ok(len)
+    }
+
+    pub(crate) fn skips(&self, path: &[impl AsRef<str>]) -> bool {
+        self.skip.iter().any(|skip| match skip.as_slice() {
+            [name] => path.last().is_some_and(|last| last.as_ref() == name),
+            skip => skip.len() == path.len() && skip.iter().zip(path).all(|(a, b)| a == b.as_ref()),
+        })
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Endianness {
+    Big,
+    Little,
+}
+
+// The ways NBT gets encoded. Java saves it big-endian and Bedrock
+// little-endian. Java's protocol since 1.20.2 sends the root without a name,
+// Bedrock's sends lengths and ints as VarInts, with ints and longs zigzagged
+// so small negative numbers stay short.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum NbtFlavor {
+    #[default]
+    Java,
+    Bedrock,
+    JavaNetwork,
+    BedrockNetwork,
+}
+
+impl NbtFlavor {
+    pub fn endianness(self) -> Endianness {
+        match self {
+            NbtFlavor::Java | NbtFlavor::JavaNetwork => Endianness::Big,
+            NbtFlavor::Bedrock | NbtFlavor::BedrockNetwork => Endianness::Little,
+        }
+    }
+
+    fn varints(self) -> bool {
+        self == NbtFlavor::BedrockNetwork
+    }
+
+    fn root_named(self) -> bool {
+        self != NbtFlavor::JavaNetwork
+    }
+
+    // Size in bytes of the numeric payloads, tag ids 1 to 6. None for the
+    // ones written as VarInts and for everything else.
+    fn payload_size(self, tag_id: u8) -> Option<usize> {
+        match tag_id {
+            3 | 4 if self.varints() => None,
+            1 => Some(1),
+            2 => Some(2),
+            3 | 5 => Some(4),
+            4 | 6 => Some(8),
+            _ => None,
+        }
+    }
+
+    fn next_i16(self, iterator: &mut Iter<'_, u8>) -> Result<i16, NbtError> {
+        match self.endianness() {
+            Endianness::Big => iterator.next_i16(),
+            Endianness::Little => Ok(i16::from_le_bytes(iterator.next_n::<2>()?)),
+        }
+    }
+
+    fn next_i32(self, iterator: &mut Iter<'_, u8>) -> Result<i32, NbtError> {
+        match self.endianness() {
+            _ if self.varints() => Ok(unzigzag(next_varint(iterator, 5)?) as i32),
+            Endianness::Big => iterator.next_i32(),
+            Endianness::Little => Ok(i32::from_le_bytes(iterator.next_n::<4>()?)),
+        }
+    }
+
+    fn next_i64(self, iterator: &mut Iter<'_, u8>) -> Result<i64, NbtError> {
+        match self.endianness() {
+            _ if self.varints() => Ok(unzigzag(next_varint(iterator, 10)?)),
+            Endianness::Big => iterator.next_i64(),
+            Endianness::Little => Ok(i64::from_le_bytes(iterator.next_n::<8>()?)),
+        }
+    }
+
+    fn next_f32(self, iterator: &mut Iter<'_, u8>) -> Result<f32, NbtError> {
+        match self.endianness() {
+            Endianness::Big => iterator.next_f32(),
+            Endianness::Little => Ok(f32::from_le_bytes(iterator.next_n::<4>()?)),
+        }
+    }
+
+    fn next_f64(self, iterator: &mut Iter<'_, u8>) -> Result<f64, NbtError> {
+        match self.endianness() {
+            Endianness::Big => iterator.next_f64(),
+            Endianness::Little => Ok(f64::from_le_bytes(iterator.next_n::<8>()?)),
+        }
+    }
+
+    // Names and strings, whose lengths are unsigned
+    fn next_string_len(self, iterator: &mut Iter<'_, u8>) -> Result<usize, NbtError> {
+        match self.endianness() {
+            _ if self.varints() => Ok(next_varint(iterator, 5)? as u32 as usize),
+            Endianness::Big => Ok(iterator.next_u16()? as usize),
+            Endianness::Little => Ok(u16::from_le_bytes(iterator.next_n::<2>()?) as usize),
+        }
+    }
+
+    // Lists and arrays, whose lengths are ints
+    fn next_len(self, iterator: &mut Iter<'_, u8>) -> Result<usize, NbtError> {
+        let offset = iterator.len();
+        let len = self.next_i32(iterator)?;
+        usize::try_from(len).map_err(|_| NbtError::NegativeLength { offset, len })
+    }
+
+    fn next_i32_vec(self, iterator: &mut Iter<'_, u8>, n: usize) -> Result<Vec<i32>, NbtError> {
+        match self.endianness() {
+            _ if self.varints() => (0..n).map(|_| self.next_i32(iterator)).collect(),
+            Endianness::Big => iterator.next_n_i32_vec(n),
+            Endianness::Little => {
+                let bytes = iterator.next_slice(n.saturating_mul(4))?;
+                Ok(bytes.chunks_exact(4).map(|chunk| i32::from_le_bytes([chunk[0], chunk[1], chunk[2], chunk[3]])).collect())
+            },
+        }
+    }
+
+    fn next_i64_vec(self, iterator: &mut Iter<'_, u8>, n: usize) -> Result<Vec<i64>, NbtError> {
+        match self.endianness() {
+            _ if self.varints() => (0..n).map(|_| self.next_i64(iterator)).collect(),
+            Endianness::Big => iterator.next_n_i64_vec(n),
+            Endianness::Little => {
+                let bytes = iterator.next_slice(n.saturating_mul(8))?;
+                Ok(bytes.chunks_exact(8).map(|chunk| {
+                    let mut long = [0; 8];
+                    long.copy_from_slice(chunk);
+                    i64::from_le_bytes(long)
+                }).collect())
+            },
+        }
+    }
+
+    // Moves past n ints (tag id 3) or longs (4) written as VarInts
+    fn skip_varints(self, iterator: &mut Iter<'_, u8>, tag_id: u8, n: usize) -> Result<(), NbtError> {
+        let max_bytes = if tag_id == 3 { 5 } else { 10 };
+        for _ in 0..n {
+            next_varint(iterator, max_bytes)?;
+        }
+        Ok(())
+    }
+}
+
+// Seven bits at a time, lowest first, with the top bit set on all but the last byte
+fn next_varint(iterator: &mut Iter<'_, u8>, max_bytes: usize) -> Result<u64, NbtError> {
+    let offset = iterator.len();
+    let mut value = 0u64;
+    for i in 0..max_bytes {
+        let byte = iterator.next_byte()?;
+        value |= ((byte & 0x7f) as u64) << (i * 7);
+        if byte & 0x80 == 0 {
+            return Ok(value);
+        }
+    }
+    Err(NbtError::InvalidVarInt { offset })
+}
+
+fn write_varint(w: &mut impl Write, mut value: u64) -> io::Result<()> {
+    let mut bytes = Vec::with_capacity(10);
+    while value >= 0x80 {
+        bytes.push(value as u8 | 0x80);
+        value >>= 7;
+    }
+    bytes.push(value as u8);
+    w.write_all(&bytes)
+}
+
+fn zigzag(value: i64) -> u64 {
+    ((value << 1) ^ (value >> 63)) as u64
+}
+
+fn unzigzag(value: u64) -> i64 {
+    (value >> 1) as i64 ^ -((value & 1) as i64)
+}
+
+#[derive(Clone)]
+pub enum TagPayload {
+    Byte(i8),
+    Short(i16),
+    Int(i32),
+    Long(i64),
+    Float(f32),
+    Double(f64),
+    ByteArray(Vec<i8>),
+    String(String),
+    List(Vec<TagPayload>),
+    Compound(Compound),
+    IntArray(Vec<i32>),
+    LongArray(Vec<i64>),
+}
+
+// Compounds with more entries than this get an index of their names
+const INDEXED_LEN: usize = 8;
+
+// The entries of a compound tag, in the order they were read or added, so
+// they're written back the same way. Large compounds are looked up through an
+// index of their names, built on the first lookup and dropped whenever the
+// entries are changed through the Vec.
+#[derive(Default)]
+pub struct Compound {
+    tags: Vec<Tag>,
+    // Position of the first entry with each name
+    index: OnceLock<HashMap<String, usize>>,
+}
+
+impl Compound {
+    pub fn new() -> Compound {
+        Compound::default()
+    }
+
+    fn position(&self, name: &str) -> Option<usize> {
+        if self.tags.len() <= INDEXED_LEN {
+            return self.tags.iter().position(|tag| tag.name == name);
+        }
+        let index = self.index.get_or_init(|| {
+            let mut index = HashMap::with_capacity(self.tags.len());
+            for (i, tag) in self.tags.iter().enumerate() {
+                index.entry(tag.name.clone()).or_insert(i);
+            }
+            index
+        });
+        index.get(name).copied()
+    }
+
+    pub fn get(&self, name: &str) -> Option<&TagPayload> {
+        self.position(name).map(|i| &self.tags[i].payload)
+    }
+
+    // Changing the payload leaves the names alone, so the index stays
+    pub fn get_mut(&mut self, name: &str) -> Option<&mut TagPayload> {
+        self.position(name).map(|i| &mut self.tags[i].payload)
+    }
+
+    pub fn contains(&self, name: &str) -> bool {
+        self.position(name).is_some()
+    }
+
+    // Takes out the first entry with the name, the ones after it move up
+    pub fn remove(&mut self, name: &str) -> Option<TagPayload> {
+        let i = self.position(name)?;
+        Some(self.remove_at(i))
+    }
+
+    pub fn remove_at(&mut self, i: usize) -> TagPayload {
+        self.deref_mut().remove(i).payload
+    }
+
+    pub fn into_vec(self) -> Vec<Tag> {
+        self.tags
+    }
+}
+
+impl Deref for Compound {
+    type Target = Vec<Tag>;
+
+    fn deref(&self) -> &Vec<Tag> {
+        &self.tags
+    }
+}
+
+impl DerefMut for Compound {
+    fn deref_mut(&mut self) -> &mut Vec<Tag> {
+        self.index = OnceLock::new();
+        &mut self.tags
+    }
+}
+
+// The index is left behind, it's built again if the copy is looked up in
+impl Clone for Compound {
+    fn clone(&self) -> Compound {
+        Compound::from(self.tags.clone())
+    }
+}
+
+impl From<Vec<Tag>> for Compound {
+    fn from(tags: Vec<Tag>) -> Compound {
+        Compound { tags, index: OnceLock::new() }
+    }
+}
+
+impl FromIterator<Tag> for Compound {
+    fn from_iter<I: IntoIterator<Item = Tag>>(tags: I) -> Compound {
+        Compound::from(tags.into_iter().collect::<Vec<_>>())
+    }
+}
+
+impl IntoIterator for Compound {
+    type Item = Tag;
+    type IntoIter = std::vec::IntoIter<Tag>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.tags.into_iter()
+    }
+}
+
+impl<'a> IntoIterator for &'a Compound {
+    type Item = &'a Tag;
+    type IntoIter = std::slice::Iter<'a, Tag>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.tags.iter()
+    }
+}
+
+impl<'a> IntoIterator for &'a mut Compound {
+    type Item = &'a mut Tag;
+    type IntoIter = std::slice::IterMut<'a, Tag>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.deref_mut().iter_mut()
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum PathError {
+    Syntax { path: String },
+    Missing { path: String },
+    NotACompound { path: String },
+    NotAList { path: String },
+    IndexOutOfRange { path: String, len: usize },
+}
+
+impl fmt::Display for PathError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PathError::Syntax { path } => write!(f, "invalid tag path \"{path}\""),
+            PathError::Missing { path } => write!(f, "{path} does not exist"),
+            PathError::NotACompound { path } => write!(f, "{path} is not a compound"),
+            PathError::NotAList { path } => write!(f, "{path} is not a list"),
+            PathError::IndexOutOfRange { path, len } => write!(f, "{path} is out of range for a list of {len}"),
+        }
+    }
+}
+
+impl std::error::Error for PathError {}
+
+pub(crate) enum PathStep<'a> {
+    Name(&'a str),
+    Index(usize),
+}
+
+// Splits a tag path into its steps, each with the byte offset where it ends
+pub(crate) fn path_steps(path: &str) -> Result<Vec<(PathStep<'_>, usize)>, PathError> {
+    let syntax = || PathError::Syntax { path: path.to_string() };
+    let mut steps = Vec::new();
+    let mut i = 0;
+
+    while i < path.len() {
+        let rest = &path[i..];
+        if let Some(after) = rest.strip_prefix('[') {
+            let close = after.find(']').ok_or_else(syntax)?;
+            let index = after[..close].parse().map_err(|_| syntax())?;
+            i += close + 2;
+            steps.push((PathStep::Index(index), i));
+        } else {
+            // Every name but the first comes after a dot
+            let name = if steps.is_empty() {
+                rest
+            } else {
+                i += 1;
+                rest.strip_prefix('.').ok_or_else(syntax)?
+            };
+            let len = name.find(['.', '[']).unwrap_or(name.len());
+            if len == 0 {
+                return Err(syntax());
+            }
+            i += len;
+            steps.push((PathStep::Name(&name[..len]), i));
+        }
+    }
+
+    Ok(steps)
+}
+
+pub trait GetPayloadByName: sealed::Sealed {
+    fn get_by_name(&mut self, name: &str) -> &mut TagPayload;
+}
+
+impl GetPayloadByName for Vec<Tag> {
+    fn get_by_name(&mut self, name: &str) -> &mut TagPayload {
+        match self.iter_mut().find(|item| item.name == name) {
+            Some(item) => &mut item.payload,
+            None => panic!("NBT format error"),
+        }
+    }
+}
+
+impl GetPayloadByName for Compound {
+    fn get_by_name(&mut self, name: &str) -> &mut TagPayload {
+        self.get_mut(name).expect("NBT format error")
+    }
+}
+
+fn write_string(w: &mut impl Write, s: &str, flavor: NbtFlavor) -> io::Result<()> {
+    let too_long = || io::Error::new(io::ErrorKind::InvalidInput, "NBT string too long");
+    match flavor.endianness() {
+        _ if flavor.varints() => write_varint(w, u32::try_from(s.len()).map_err(|_| too_long())? as u64)?,
+        Endianness::Big => w.write_all(&u16::try_from(s.len()).map_err(|_| too_long())?.to_be_bytes())?,
+        Endianness::Little => w.write_all(&u16::try_from(s.len()).map_err(|_| too_long())?.to_le_bytes())?,
+    }
+    w.write_all(s.as_bytes())
+}
+
+fn write_len(w: &mut impl Write, len: usize, flavor: NbtFlavor) -> io::Result<()> {
+    let len = i32::try_from(len).map_err(|_| io::Error::new(io::ErrorKind::InvalidInput, "NBT array too long"))?;
+    write_i32(w, len, flavor)
+}
+
+fn write_i32(w: &mut impl Write, x: i32, flavor: NbtFlavor) -> io::Result<()> {
+    match flavor.endianness() {
+        _ if flavor.varints() => write_varint(w, zigzag(x as i64)),
+        Endianness::Big => w.write_all(&x.to_be_bytes()),
+        Endianness::Little => w.write_all(&x.to_le_bytes()),
+    }
+}
+
+fn write_i64(w: &mut impl Write, x: i64, flavor: NbtFlavor) -> io::Result<()> {
+    match flavor.endianness() {
+        _ if flavor.varints() => write_varint(w, zigzag(x)),
+        Endianness::Big => w.write_all(&x.to_be_bytes()),
+        Endianness::Little => w.write_all(&x.to_le_bytes()),
+    }
+}
+
+impl TagPayload {
+    pub fn id(&self) -> u8 {
+        match self {
+            TagPayload::Byte(_) => 1,
+            TagPayload::Short(_) => 2,
+            TagPayload::Int(_) => 3,
+            TagPayload::Long(_) => 4,
+            TagPayload::Float(_) => 5,
+            TagPayload::Double(_) => 6,
+            TagPayload::ByteArray(_) => 7,
+            TagPayload::String(_) => 8,
+            TagPayload::List(_) => 9,
+            TagPayload::Compound(_) => 10,
+            TagPayload::IntArray(_) => 11,
+            TagPayload::LongArray(_) => 12,
+        }
+    }
+
+    pub fn write(&self, w: &mut impl Write) -> io::Result<()> {
+        self.write_as(w, NbtFlavor::Java)
+    }
+
+    pub fn write_as(&self, w: &mut impl Write, flavor: NbtFlavor) -> io::Result<()> {
+        let little = flavor.endianness() == Endianness::Little;
+        match self {
+            TagPayload::Byte(x) => w.write_all(&x.to_be_bytes()),
+            TagPayload::Short(x) => w.write_all(&if little { x.to_le_bytes() } else { x.to_be_bytes() }),
+            TagPayload::Int(x) => write_i32(w, *x, flavor),
+            TagPayload::Long(x) => write_i64(w, *x, flavor),
+            TagPayload::Float(x) => w.write_all(&if little { x.to_le_bytes() } else { x.to_be_bytes() }),
+            TagPayload::Double(x) => w.write_all(&if little { x.to_le_bytes() } else { x.to_be_bytes() }),
+            TagPayload::ByteArray(x) => {
+                write_len(w, x.len(), flavor)?;
+                let bytes: Vec<u8> = x.iter().map(|b| *b as u8).collect();
+                w.write_all(&bytes)
+            },
+            TagPayload::String(x) => write_string(w, x, flavor),
+            TagPayload::List(x) => {
+                // Empty lists are written with the End tag as their element type
+                let element_id = x.first().map_or(0, TagPayload::id);
+                if x.iter().any(|item| item.id() != element_id) {
+                    return Err(io::Error::new(io::ErrorKind::InvalidInput, "NBT list with elements of different types"));
+                }
+                w.write_all(&[element_id])?;
+                write_len(w, x.len(), flavor)?;
+                for item in x {
+                    item.write_as(w, flavor)?;
+                }
+                Ok(())
+            },
+            TagPayload::Compound(x) => {
+                // Only the root can go without a name
+                for tag in x {
+                    w.write_all(&[tag.payload.id()])?;
+                    write_string(w, &tag.name, flavor)?;
+                    tag.payload.write_as(w, flavor)?;
+                }
+                w.write_all(&[0])
+            },
+            TagPayload::IntArray(x) => {
+                write_len(w, x.len(), flavor)?;
+                for i in x {
+                    write_i32(w, *i, flavor)?;
+                }
+                Ok(())
+            },
+            TagPayload::LongArray(x) => {
+                write_len(w, x.len(), flavor)?;
+                for l in x {
+                    write_i64(w, *l, flavor)?;
+                }
+                Ok(())
+            },
+        }
+    }
+
+    pub fn try_as_byte(&mut self) -> Option<&mut i8> {
+        match self {
+            TagPayload::Byte(x) => Some(x),
+            _ => None,
+        }
+    }
+
+    pub fn try_as_short(&mut self) -> Option<&mut i16> {
+        match self {
+            TagPayload::Short(x) => Some(x),
+            _ => None,
+        }
+    }
+
+    pub fn try_as_int(&mut self) -> Option<&mut i32> {
+        match self {
+            TagPayload::Int(x) => Some(x),
+            _ => None,
+        }
+    }
+
+    pub fn try_as_long(&mut self) -> Option<&mut i64> {
+        match self {
+            TagPayload::Long(x) => Some(x),
+            _ => None,
+        }
+    }
+
+    pub fn try_as_float(&mut self) -> Option<&mut f32> {
+        match self {
+            TagPayload::Float(x) => Some(x),
+            _ => None,
+        }
+    }
+
+    pub fn try_as_double(&mut self) -> Option<&mut f64> {
+        match self {
+            TagPayload::Double(x) => Some(x),
+            _ => None,
+        }
+    }
+
+    pub fn try_as_byte_array(&mut self) -> Option<&mut Vec<i8>> {
+        match self {
+            TagPayload::ByteArray(x) => Some(x),
+            _ => None,
+        }
+    }
+
+    pub fn try_as_string(&mut self) -> Option<&mut String> {
+        match self {
+            TagPayload::String(x) => Some(x),
+            _ => None,
+        }
+    }
+
+    pub fn try_as_list(&mut self) -> Option<&mut Vec<TagPayload>> {
+        match self {
+            TagPayload::List(x) => Some(x),
+            _ => None,
+        }
+    }
+
+    pub fn try_as_compound(&mut self) -> Option<&mut Compound> {
+        match self {
+            TagPayload::Compound(x) => Some(x),
+            _ => None,
+        }
+    }
+
+    pub fn try_as_int_array(&mut self) -> Option<&mut Vec<i32>> {
+        match self {
+            TagPayload::IntArray(x) => Some(x),
+            _ => None,
+        }
+    }
+
+    pub fn try_as_long_array(&mut self) -> Option<&mut Vec<i64>> {
+        match self {
+            TagPayload::LongArray(x) => Some(x),
+            _ => None,
+        }
+    }
+
+    // The as_* accessors panic on a type mismatch; prefer try_as_* for data from disk
+
+    pub fn as_byte(&mut self) -> &mut i8 {
+        self.try_as_byte().expect("NBT format error")
+    }
+
+    pub fn as_short(&mut self) -> &mut i16 {
+        self.try_as_short().expect("NBT format error")
+    }
+
+    pub fn as_int(&mut self) -> &mut i32 {
+        self.try_as_int().expect("NBT format error")
+    }
+
+    pub fn as_long(&mut self) -> &mut i64 {
+        self.try_as_long().expect("NBT format error")
+    }
+
+    pub fn as_float(&mut self) -> &mut f32 {
+        self.try_as_float().expect("NBT format error")
+    }
+
+    pub fn as_double(&mut self) -> &mut f64 {
+        self.try_as_double().expect("NBT format error")
+    }
+
+    pub fn as_byte_array(&mut self) -> &mut Vec<i8> {
+        self.try_as_byte_array().expect("NBT format error")
+    }
+
+    pub fn as_string(&mut self) -> &mut String {
+        self.try_as_string().expect("NBT format error")
+    }
+
+    pub fn as_list(&mut self) -> &mut Vec<TagPayload> {
+        self.try_as_list().expect("NBT format error")
+    }
+
+    pub fn as_compound(&mut self) -> &mut Compound {
+        self.try_as_compound().expect("NBT format error")
+    }
+
+    pub fn as_int_array(&mut self) -> &mut Vec<i32> {
+        self.try_as_int_array().expect("NBT format error")
+    }
+
+    pub fn as_long_array(&mut self) -> &mut Vec<i64> {
+        self.try_as_long_array().expect("NBT format error")
+    }
+
+    // Looks up an entry of a compound. Anything else has no entries.
+    pub fn get(&self, name: &str) -> Option<&TagPayload> {
+        match self {
+            TagPayload::Compound(tags) => tags.get(name),
+            _ => None,
+        }
+    }
+
+    pub fn get_mut(&mut self, name: &str) -> Option<&mut TagPayload> {
+        match self {
+            TagPayload::Compound(tags) => tags.get_mut(name),
+            _ => None,
+        }
+    }
+
+    // Follows a path like "Level.Sections[0].Y" through compounds and lists
+    pub fn get_path(&self, path: &str) -> Result<&TagPayload, PathError> {
+        let mut current = self;
+        let mut done = 0;
+
+        for (step, end) in path_steps(path)? {
+            let error_path = |until: usize| path[..until].to_string();
+            current = match step {
+                PathStep::Name(name) => match current {
+                    TagPayload::Compound(_) => current.get(name).ok_or_else(|| PathError::Missing { path: error_path(end) })?,
+                    _ => return Err(PathError::NotACompound { path: error_path(done) }),
+                },
+                PathStep::Index(index) => match current {
+                    TagPayload::List(items) => items.get(index).ok_or_else(|| PathError::IndexOutOfRange { path: error_path(end), len: items.len() })?,
+                    _ => return Err(PathError::NotAList { path: error_path(done) }),
+                },
+            };
+            done = end;
+        }
+
+        Ok(current)
+    }
+
+    pub fn get_path_mut(&mut self, path: &str) -> Result<&mut TagPayload, PathError> {
+        let mut current = self;
+        let mut done = 0;
+
+        for (step, end) in path_steps(path)? {
+            let error_path = |until: usize| path[..until].to_string();
+            current = match step {
+                PathStep::Name(name) => match current {
+                    TagPayload::Compound(_) => current.get_mut(name).ok_or_else(|| PathError::Missing { path: error_path(end) })?,
+                    _ => return Err(PathError::NotACompound { path: error_path(done) }),
+                },
+                PathStep::Index(index) => match current {
+                    TagPayload::List(items) => {
+                        let len = items.len();
+                        items.get_mut(index).ok_or_else(|| PathError::IndexOutOfRange { path: error_path(end), len })?
+                    },
+                    _ => return Err(PathError::NotAList { path: error_path(done) }),
+                },
+            };
+            done = end;
+        }
+
+        Ok(current)
+    }
+
+    // Takes what a path points to out of its compound or list. Later entries
+    // of a list move up to fill the gap.
+    pub fn remove_path(&mut self, path: &str) -> Result<TagPayload, PathError> {
+        let steps = path_steps(path)?;
+        let Some(((last, end), parents)) = steps.split_last() else {
+            return Err(PathError::Syntax { path: path.to_string() });
+        };
+        let parent_end = parents.last().map_or(0, |(_, end)| *end);
+
+        match (last, self.get_path_mut(&path[..parent_end])?) {
+            (PathStep::Name(name), TagPayload::Compound(tags)) => {
+                tags.remove(name).ok_or_else(|| PathError::Missing { path: path[..*end].to_string() })
+            },
+            (PathStep::Index(index), TagPayload::List(items)) => {
+                if *index >= items.len() {
+                    return Err(PathError::IndexOutOfRange { path: path[..*end].to_string(), len: items.len() });
+                }
+                Ok(items.remove(*index))
+            },
+            (PathStep::Name(_), _) => Err(PathError::NotACompound { path: path[..parent_end].to_string() }),
+            (PathStep::Index(_), _) => Err(PathError::NotAList { path: path[..parent_end].to_string() }),
+        }
+    }
+
+    // Merges other into this payload the way /data merge does: compounds are
+    // merged entry by entry, adding the ones that are missing, and anything
+    // else is replaced
+    pub fn merge(&mut self, other: TagPayload) {
+        match (self, other) {
+            (TagPayload::Compound(tags), TagPayload::Compound(others)) => {
+                for other in others {
+                    match tags.get_mut(&other.name) {
+                        Some(payload) => payload.merge(other.payload),
+                        None => tags.push(other),
+                    }
+                }
+            },
+            (this, other) => *this = other,
+        }
+    }
+
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn tag(name: &str, payload: TagPayload) -> Tag {
+        Tag { name: name.to_string(), payload }
+    }
+
+    // One of each tag type, with a list of compounds and an empty list
+    fn every_type() -> Tag {
+        let item = |count| TagPayload::Compound(vec![tag("id", TagPayload::String("minecraft:torch".to_string())), tag("Count", TagPayload::Byte(count))].into());
+        tag("level", TagPayload::Compound(vec![
+            tag("byte", TagPayload::Byte(-3)),
+            tag("short", TagPayload::Short(-300)),
+            tag("int", TagPayload::Int(-70000)),
+            tag("long", TagPayload::Long(i64::MIN + 5)),
+            tag("float", TagPayload::Float(0.5)),
+            tag("double", TagPayload::Double(-1.25e100)),
+            tag("bytes", TagPayload::ByteArray(vec![1, -1, 127])),
+            tag("string", TagPayload::String("héllo".to_string())),
+            tag("items", TagPayload::List(vec![item(1), item(64)])),
+            tag("empty", TagPayload::List(Vec::new())),
+            tag("ints", TagPayload::IntArray(vec![i32::MIN, 0, i32::MAX])),
+            tag("longs", TagPayload::LongArray(vec![-1, 1 << 40])),
+        ].into()))
+    }
+
+    fn bytes_of(tag: &Tag) -> Vec<u8> {
+        let mut bytes = Vec::new();
+        tag.write(&mut bytes).unwrap();
+        bytes
+    }
+
+    #[test]
+    fn writes_big_endian_with_ids_and_headers() {
+        assert_eq!(bytes_of(&tag("hi", TagPayload::Short(-2))), [2, 0, 2, b'h', b'i', 0xff, 0xfe]);
+        assert_eq!(bytes_of(&tag("", TagPayload::List(Vec::new()))), [9, 0, 0, 0, 0, 0, 0, 0]);
+        assert_eq!(bytes_of(&tag("", TagPayload::List(vec![TagPayload::Int(1), TagPayload::Int(256)]))), [9, 0, 0, 3, 0, 0, 0, 2, 0, 0, 0, 1, 0, 0, 1, 0]);
+        assert_eq!(bytes_of(&tag("", TagPayload::Compound(vec![tag("a", TagPayload::Byte(7))].into()))), [10, 0, 0, 1, 0, 1, b'a', 7, 0]);
+    }
+
+    #[test]
+    fn round_trips_every_tag_type() {
+        let bytes = bytes_of(&every_type());
+        let parsed = Tag::parse(&mut bytes.iter()).unwrap();
+
+        assert_eq!(parsed.name, "level");
+        let TagPayload::Compound(level) = &parsed.payload else { panic!("not a compound") };
+        assert!(matches!(level.get("long"), Some(TagPayload::Long(x)) if *x == i64::MIN + 5));
+        assert!(matches!(level.get("string"), Some(TagPayload::String(x)) if x == "héllo"));
+        assert!(matches!(level.get("items"), Some(TagPayload::List(items)) if items.len() == 2));
+        assert_eq!(bytes_of(&parsed), bytes);
+    }
+
+    fn bytes_as(tag: &Tag, flavor: NbtFlavor) -> Vec<u8> {
+        let mut bytes = Vec::new();
+        tag.write_as(&mut bytes, flavor).unwrap();
+        bytes
+    }
+
+    #[test]
+    fn round_trips_every_flavor() {
+        for flavor in [NbtFlavor::Java, NbtFlavor::Bedrock, NbtFlavor::JavaNetwork, NbtFlavor::BedrockNetwork] {
+            let bytes = bytes_as(&every_type(), flavor);
+            let options = ParseOptions::new().flavor(flavor);
+            let parsed = Tag::parse_with(&mut bytes.iter(), &options).unwrap();
+            assert_eq!(bytes_as(&parsed, flavor), bytes, "{flavor:?}");
+            assert_eq!(parsed.name, if flavor == NbtFlavor::JavaNetwork { "" } else { "level" });
+
+            // Skipping has to find its way past every payload of the flavor too
+            let skipping = options.skip("items").skip("int").skip("longs");
+            let TagPayload::Compound(level) = Tag::parse_with(&mut bytes.iter(), &skipping).unwrap().payload else { panic!("not a compound") };
+            assert_eq!(level.len(), 9, "{flavor:?}");
+            assert!(matches!(level.get("ints"), Some(TagPayload::IntArray(ints)) if ints[0] == i32::MIN), "{flavor:?}");
+        }
+    }
+
+    #[test]
+    fn writes_each_flavor_byte_for_byte() {
+        let int = tag("hi", TagPayload::Int(-1));
+        assert_eq!(bytes_as(&int, NbtFlavor::Java), [3, 0, 2, b'h', b'i', 0xff, 0xff, 0xff, 0xff]);
+        assert_eq!(bytes_as(&int, NbtFlavor::Bedrock), [3, 2, 0, b'h', b'i', 0xff, 0xff, 0xff, 0xff]);
+        assert_eq!(bytes_as(&int, NbtFlavor::JavaNetwork), [3, 0xff, 0xff, 0xff, 0xff]);
+        // -1 zigzags to 1, the name's length is a plain VarInt
+        assert_eq!(bytes_as(&int, NbtFlavor::BedrockNetwork), [3, 2, b'h', b'i', 1]);
+
+        let list = tag("", TagPayload::List(vec![TagPayload::Long(300), TagPayload::Long(-2)]));
+        assert_eq!(bytes_as(&list, NbtFlavor::BedrockNetwork), [9, 0, 4, 4, 0xd8, 0x04, 3]);
+        let short = tag("", TagPayload::Short(0x0102));
+        assert_eq!(bytes_as(&short, NbtFlavor::Bedrock), [2, 0, 0, 2, 1]);
+    }
+
+    #[test]
+    fn refuses_what_a_flavor_cant_hold() {
+        let mixed = tag("", TagPayload::List(vec![TagPayload::Int(1), TagPayload::Byte(1)]));
+        assert!(mixed.write(&mut Vec::new()).is_err());
+
+        // Java's string lengths are 16 bits, network Bedrock's VarInts
+        let long = tag("", TagPayload::String("a".repeat(70_000)));
+        assert!(long.write_as(&mut Vec::new(), NbtFlavor::Java).is_err());
+        assert!(long.write_as(&mut Vec::new(), NbtFlavor::Bedrock).is_err());
+        assert!(long.write_as(&mut Vec::new(), NbtFlavor::BedrockNetwork).is_ok());
+
+        let overlong = [3, 0, 0xff, 0xff, 0xff, 0xff, 0xff, 0x01];
+        let options = ParseOptions::new().flavor(NbtFlavor::BedrockNetwork);
+        assert!(matches!(Tag::parse_with(&mut overlong.iter(), &options), Err(NbtError::InvalidVarInt { .. })));
+    }
+
+    // An unnamed root list holding a list holding a list, depth lists in all
+    fn nested_lists(depth: usize) -> Vec<u8> {
+        let mut bytes = vec![9, 0, 0];
+        for _ in 1..depth {
+            bytes.extend([9, 0, 0, 0, 1]);
+        }
+        bytes.extend([0, 0, 0, 0, 0]);
+        bytes
+    }
+
+    // Compounds each holding the next as "a", depth compounds in all
+    fn nested_compounds(depth: usize) -> Vec<u8> {
+        let mut bytes = vec![10, 0, 0];
+        for _ in 1..depth {
+            bytes.extend([10, 0, 1, b'a']);
+        }
+        bytes.extend(vec![0; depth]);
+        bytes
+    }
+
+    #[test]
+    fn stops_at_the_depth_limit() {
+        // Right at the limit, which has to fit on a test thread's stack
+        for bytes in [nested_lists(MAX_DEPTH), nested_compounds(MAX_DEPTH)] {
+            let parsed = Tag::parse(&mut bytes.iter()).unwrap();
+            assert_eq!(bytes_of(&parsed), bytes);
+        }
+        assert!(matches!(Tag::parse(&mut nested_lists(MAX_DEPTH + 1).iter()), Err(NbtError::DepthLimitExceeded { limit: MAX_DEPTH, .. })));
+        assert!(matches!(Tag::parse(&mut nested_compounds(100_000).iter()), Err(NbtError::DepthLimitExceeded { limit: MAX_DEPTH, .. })));
+        // Skipped tags are walked, so they count too
+        let skip = ParseOptions::new().skip("a");
+        assert!(matches!(Tag::parse_with(&mut nested_compounds(MAX_DEPTH + 1).iter(), &skip), Err(NbtError::DepthLimitExceeded { limit: MAX_DEPTH, .. })));
+
+        let options = ParseOptions::new().max_depth(5);
+        assert!(Tag::parse_with(&mut nested_lists(5).iter(), &options).is_ok());
+        assert!(matches!(Tag::parse_with(&mut nested_lists(6).iter(), &options), Err(NbtError::DepthLimitExceeded { limit: 5, .. })));
+    }
+
+    #[test]
+    fn refuses_lengths_it_cant_or_shouldnt_read() {
+        // An int array declaring i32::MAX entries with one behind it
+        let huge = [11, 0, 0, 0x7f, 0xff, 0xff, 0xff, 0, 0, 0, 1];
+        assert!(matches!(Tag::parse(&mut huge.iter()), Err(NbtError::UnexpectedEof { .. })));
+
+        let negative = [7, 0, 0, 0xff, 0xff, 0xff, 0xfe];
+        assert_eq!(Tag::parse(&mut negative.iter()).err(), Some(NbtError::NegativeLength { offset: 3, len: -2 }));
+
+        let bytes = bytes_of(&tag("", TagPayload::ByteArray(vec![0; 1000])));
+        assert!(Tag::parse(&mut bytes.iter()).is_ok());
+        let options = ParseOptions::new().max_len(100);
+        assert_eq!(Tag::parse_with(&mut bytes.iter(), &options).err(), Some(NbtError::LengthLimitExceeded { offset: 3, len: 1000, limit: 100 }));
+    }
+}
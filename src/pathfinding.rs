@@ -0,0 +1,731 @@
+use std::{collections::{BinaryHeap, HashMap, HashSet}, cmp::Reverse};
+
+use crate::{ pos::BlockPos, world::WorldView, chunk::{ BlockType, blocks_motion }, costs::{ CostModel, DefaultCosts }, hazards::{ Hazard, exposed_by, drop_under, is_lava, is_water }, builds::{ ProtectBuilds, Severity, build_above } };
+
+// Cost of moving one block through open space
+pub const STEP_COST: u32 = 1;
+
+// Added for every hazard a move lets loose when avoiding them
+pub const HAZARD_COST: u32 = 50;
+
+// Added for every block dug under a build when builds are only penalized
+pub const BUILD_COST: u32 = 50;
+
+// Extra cost of placing a block to stand on where there's no floor
+pub const SUPPORT_COST: u32 = 4;
+
+// Extra cost of digging out a block, None if it can't or shouldn't be dug through
+pub fn dig_cost(block: &BlockType) -> Option<u32> {
+    if block.is_air() {
+        return Some(0);
+    }
+
+    match block.name.as_str() {
+        "minecraft:bedrock" | "minecraft:barrier" | "minecraft:end_portal_frame" | "minecraft:end_portal"
+        | "minecraft:nether_portal" | "minecraft:lava" | "minecraft:water" => None,
+        "minecraft:obsidian" | "minecraft:crying_obsidian" | "minecraft:ancient_debris"
+        | "minecraft:respawn_anchor" => Some(40),
+        _ => Some(4),
+    }
+}
+
+// Blocks the miner can go straight up and down in, and stand in without a floor
+pub fn is_climbable(block: &BlockType) -> bool {
+    matches!(block.name.as_str(), "minecraft:ladder" | "minecraft:vine" | "minecraft:scaffolding"
+        | "minecraft:twisting_vines" | "minecraft:twisting_vines_plant" | "minecraft:weeping_vines"
+        | "minecraft:weeping_vines_plant" | "minecraft:cave_vines" | "minecraft:cave_vines_plant")
+}
+
+// Whether the miner can stand on top of the block. Waterlogged slabs and
+// stairs hold, fluids themselves don't.
+pub fn is_floor(block: &BlockType) -> bool {
+    blocks_motion(block) && !is_lava(block) && (!is_water(block) || block.property("waterlogged") == Some("true"))
+}
+
+// Whether the miner can stand at feet without digging or building: a floor
+// below and air at the feet and head
+pub fn is_walkable(view: &WorldView, feet: BlockPos) -> bool {
+    view.block_at(feet.offset(0, -1, 0)).is_some_and(is_floor)
+        && [feet, feet.offset(0, 1, 0)].into_iter().all(|pos| view.block_at(pos).is_some_and(BlockType::is_air))
+}
+
+// The moves the miner can make from a position: a step to the side on the
+// same level, one up or one down, and straight up or down. Going straight
+// up or down only works on something climbable, find_route checks that.
+pub fn walking_moves(pos: BlockPos) -> impl Iterator<Item = BlockPos> {
+    [(1, 0), (-1, 0), (0, 1), (0, -1)].into_iter()
+        .flat_map(move |(dx, dz)| [pos.offset(dx, 0, dz), pos.offset(dx, 1, dz), pos.offset(dx, -1, dz)])
+        .chain([pos.offset(0, 1, 0), pos.offset(0, -1, 0)])
+}
+
+// Every move goes at most one block to the side and one up or down, so
+// this never overestimates
+fn estimate(pos: BlockPos, goal: BlockPos) -> u32 {
+    let side = pos.x.abs_diff(goal.x) + pos.z.abs_diff(goal.z);
+    side.max(pos.y.abs_diff(goal.y)) * STEP_COST
+}
+
+// Stepping up needs room above the head before moving over, stepping down
+// room above the head at the lower position while still on the higher one
+pub fn clearance(from: BlockPos, to: BlockPos) -> Option<BlockPos> {
+    if from.x == to.x && from.z == to.z {
+        None
+    } else if to.y > from.y {
+        Some(from.offset(0, 2, 0))
+    } else if to.y < from.y {
+        Some(to.offset(0, 2, 0))
+    } else {
+        None
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct Route {
+    // Feet positions from start to goal, inclusive
+    pub steps: Vec<BlockPos>,
+    // Blocks that have to be dug out along the way, in order
+    pub mined: Vec<BlockPos>,
+    // Blocks to place where a step has no floor, in order
+    pub supports: Vec<BlockPos>,
+    // Lava, water, falling blocks and drops along the way, in order
+    pub hazards: Vec<Hazard>,
+    pub cost: u32,
+}
+
+impl Route {
+    // Appends a route that starts where this one ends
+    fn extend(&mut self, next: Route) {
+        self.steps.extend(next.steps.into_iter().skip(1));
+        for block in next.mined {
+            if !self.mined.contains(&block) {
+                self.mined.push(block);
+            }
+        }
+        for block in next.supports {
+            if !self.supports.contains(&block) {
+                self.supports.push(block);
+            }
+        }
+        for hazard in next.hazards {
+            if !self.hazards.contains(&hazard) {
+                self.hazards.push(hazard);
+            }
+        }
+        self.cost += next.cost;
+    }
+}
+
+pub struct Pathfinder<'a> {
+    view: &'a WorldView,
+    costs: &'a dyn CostModel,
+    // Blocks already dug out, e.g. by earlier legs of a tour
+    cleared: HashSet<BlockPos>,
+    // Whether hazards cost HAZARD_COST each or only get reported
+    avoid_hazards: bool,
+    // Whether digging under builds is ruled out or costs BUILD_COST more
+    protect_builds: Option<ProtectBuilds>,
+    // Blocks that cost this much more to dig, or that can't be dug if None
+    penalties: HashMap<BlockPos, Option<u32>>,
+    // Give up after expanding this many nodes
+    pub max_nodes: usize,
+}
+
+impl<'a> Pathfinder<'a> {
+    pub fn new(view: &'a WorldView) -> Pathfinder<'a> {
+        Pathfinder { view, costs: &DefaultCosts, cleared: HashSet::new(), avoid_hazards: false, protect_builds: None, penalties: HashMap::new(), max_nodes: 2_000_000 }
+    }
+
+    pub fn with_costs(mut self, costs: &'a dyn CostModel) -> Self {
+        self.costs = costs;
+        self
+    }
+
+    pub fn avoid_hazards(mut self) -> Self {
+        self.avoid_hazards = true;
+        self
+    }
+
+    pub fn protect_builds(mut self, protect: ProtectBuilds) -> Self {
+        self.protect_builds = Some(protect);
+        self
+    }
+
+    pub fn clear(&mut self, pos: BlockPos) {
+        self.cleared.insert(pos);
+    }
+
+    // Makes digging pos cost penalty more, or rules it out if None. Whether
+    // that changed anything, a block can only be avoided more.
+    pub fn avoid(&mut self, pos: BlockPos, penalty: Option<u32>) -> bool {
+        let known = self.penalties.get(&pos).copied();
+        let worse = match (known, penalty) {
+            (None, _) | (Some(Some(_)), None) => true,
+            (Some(Some(known)), Some(penalty)) => penalty > known,
+            (Some(None), _) => false,
+        };
+        if worse {
+            self.penalties.insert(pos, penalty);
+        }
+        worse
+    }
+
+    fn block_cost(&self, pos: BlockPos) -> Option<u32> {
+        let block = self.view.block_at(pos).filter(|block| !block.is_outside_world())?;
+        if self.cleared.contains(&pos) || is_climbable(block) {
+            return Some(0);
+        }
+        let mut cost = self.costs.dig_cost(block)?;
+        if !block.is_air() {
+            if let Some(penalty) = self.penalties.get(&pos) {
+                cost += (*penalty)?;
+            }
+        }
+        match self.protect_builds {
+            Some(protect) if !block.is_air() && build_above(self.view, pos, protect.distance).is_some() => match protect.severity {
+                Severity::Forbid => None,
+                Severity::Penalize => Some(cost + BUILD_COST),
+            },
+            _ => Some(cost),
+        }
+    }
+
+    fn needs_digging(&self, pos: BlockPos) -> bool {
+        !self.cleared.contains(&pos) && self.view.block_at(pos).is_some_and(|b| !b.is_air() && !is_climbable(b))
+    }
+
+    fn is_climbable_at(&self, pos: BlockPos) -> bool {
+        !self.cleared.contains(&pos) && self.view.block_at(pos).is_some_and(is_climbable)
+    }
+
+    // Whether standing at feet takes placing a block below, None if it can't
+    // be stood at at all. Air, dug out blocks and fluids can be built into,
+    // anything else without collision can't.
+    fn needs_support(&self, feet: BlockPos) -> Option<bool> {
+        if self.is_climbable_at(feet) {
+            return Some(false);
+        }
+        let floor = feet.offset(0, -1, 0);
+        let block = self.view.block_at(floor).filter(|block| !block.is_outside_world())?;
+        if self.cleared.contains(&floor) || block.is_air() || is_lava(block) || is_water(block) && !is_floor(block) {
+            Some(true)
+        } else if is_floor(block) {
+            Some(false)
+        } else {
+            None
+        }
+    }
+
+    // Digging and building it takes to move from one position to the next,
+    // None if the move can't be made. Supports only go in on the level, where
+    // they can be placed against the floor just walked on.
+    fn walk_cost(&self, from: BlockPos, to: BlockPos) -> Option<u32> {
+        let vertical = from.x == to.x && from.z == to.z;
+        if vertical && !self.is_climbable_at(from) && !self.is_climbable_at(to) {
+            return None;
+        }
+        let mut cost = self.enter_cost(to)?;
+        if let Some(block) = clearance(from, to) {
+            cost += self.block_cost(block)?;
+        }
+        if self.needs_support(to)? {
+            if from.y != to.y {
+                return None;
+            }
+            cost += SUPPORT_COST;
+        }
+        Some(cost)
+    }
+
+    // The miner is two blocks tall, so entering a position means clearing both
+    // the feet and the head block. Only the digging, the move itself is extra.
+    fn enter_cost(&self, feet: BlockPos) -> Option<u32> {
+        let feet_cost = self.block_cost(feet)?;
+        let head_cost = self.block_cost(feet.offset(0, 1, 0))?;
+        Some(feet_cost + head_cost)
+    }
+
+    // Hazards moving from one position to the next lets loose, counting only
+    // the two positions as tunnel since the rest of the route isn't known yet
+    fn hazard_cost(&self, from: BlockPos, to: BlockPos) -> u32 {
+        let in_tunnel = |pos: &BlockPos| self.cleared.contains(pos) || Some(*pos) == clearance(from, to)
+            || [from, to].iter().any(|feet| *pos == *feet || *pos == feet.offset(0, 1, 0));
+        let mut hazards = drop_under(self.view, to, in_tunnel).into_iter().count();
+        for block in clearance(from, to).into_iter().chain([to, to.offset(0, 1, 0)]) {
+            if self.needs_digging(block) {
+                hazards += exposed_by(self.view, block, in_tunnel).len();
+            }
+        }
+        hazards as u32 * HAZARD_COST
+    }
+
+    pub fn find_route(&self, start: BlockPos, goal: BlockPos) -> Option<Route> {
+        // Don't flood the whole view looking for a goal that can never be entered
+        self.enter_cost(goal)?;
+        self.needs_support(goal)?;
+
+        let mut open = BinaryHeap::new();
+        let mut came_from: HashMap<BlockPos, BlockPos> = HashMap::new();
+        let mut best_cost: HashMap<BlockPos, u32> = HashMap::new();
+
+        best_cost.insert(start, 0);
+        open.push(Reverse((estimate(start, goal), 0, start)));
+
+        let mut expanded = 0;
+
+        while let Some(Reverse((_, cost, pos))) = open.pop() {
+            if pos == goal {
+                return Some(self.build_route(start, goal, cost, &came_from));
+            }
+
+            // Skip stale heap entries
+            if cost > best_cost[&pos] {
+                continue;
+            }
+
+            expanded += 1;
+            if expanded > self.max_nodes {
+                return None;
+            }
+
+            for next in walking_moves(pos) {
+                let Some(mut step) = self.walk_cost(pos, next).map(|dig| dig + self.costs.move_cost(pos, next).max(STEP_COST)) else {
+                    continue;
+                };
+                if self.avoid_hazards {
+                    step += self.hazard_cost(pos, next);
+                }
+
+                let next_cost = cost + step;
+                if best_cost.get(&next).is_none_or(|known| next_cost < *known) {
+                    best_cost.insert(next, next_cost);
+                    came_from.insert(next, pos);
+                    open.push(Reverse((next_cost + estimate(next, goal), next_cost, next)));
+                }
+            }
+        }
+
+        None
+    }
+
+    fn build_route(&self, start: BlockPos, goal: BlockPos, mut cost: u32, came_from: &HashMap<BlockPos, BlockPos>) -> Route {
+        let mut steps = vec![goal];
+        let mut pos = goal;
+        while pos != start {
+            pos = came_from[&pos];
+            steps.push(pos);
+        }
+        steps.reverse();
+
+        let mut mined = Vec::new();
+        let mut supports = Vec::new();
+        let mut tunnel: HashSet<BlockPos> = self.cleared.iter().copied().collect();
+        let moves = [(start, start)].into_iter().chain(steps.windows(2).map(|pair| (pair[0], pair[1])));
+        for (i, (from, to)) in moves.enumerate() {
+            for block in clearance(from, to).into_iter().chain([to, to.offset(0, 1, 0)]) {
+                tunnel.insert(block);
+                if self.needs_digging(block) && !mined.contains(&block) {
+                    mined.push(block);
+                }
+            }
+            // The start is already stood at, with or without a floor. The
+            // search goes by the world as it is, so a floor the route dug out
+            // itself earlier on gets filled back in at SUPPORT_COST.
+            let floor = to.offset(0, -1, 0);
+            if i == 0 || self.is_climbable_at(to) || supports.contains(&floor) {
+                continue;
+            }
+            if mined.contains(&floor) {
+                supports.push(floor);
+                cost += SUPPORT_COST;
+            } else if self.needs_support(to) == Some(true) {
+                supports.push(floor);
+            }
+        }
+
+        let in_tunnel = |pos: &BlockPos| tunnel.contains(pos);
+        let mut hazards = Vec::new();
+        for feet in &steps {
+            let mut found = drop_under(self.view, *feet, in_tunnel).into_iter().collect::<Vec<_>>();
+            for block in [*feet, feet.offset(0, 1, 0)] {
+                if mined.contains(&block) {
+                    found.extend(exposed_by(self.view, block, in_tunnel));
+                }
+            }
+            for hazard in found {
+                if !hazards.contains(&hazard) {
+                    hazards.push(hazard);
+                }
+            }
+        }
+
+        Route { steps, mined, supports, hazards, cost }
+    }
+}
+
+pub struct Tour {
+    pub route: Route,
+    // Targets in the order they are visited
+    pub order: Vec<BlockPos>,
+    pub unreachable: Vec<BlockPos>,
+}
+
+impl Pathfinder<'_> {
+    // Visits all targets, starting from start, in an order that keeps the total distance short
+    pub fn find_tour(&mut self, start: BlockPos, targets: &[BlockPos]) -> Tour {
+        let order = order_targets(start, targets);
+
+        let mut route = Route { steps: vec![start], mined: Vec::new(), supports: Vec::new(), hazards: Vec::new(), cost: 0 };
+        let mut visited = Vec::new();
+        let mut unreachable = Vec::new();
+        let mut pos = start;
+
+        for target in order {
+            match self.find_route(pos, target) {
+                Some(leg) => {
+                    for block in &leg.mined {
+                        self.clear(*block);
+                    }
+                    route.extend(leg);
+                    visited.push(target);
+                    pos = target;
+                },
+                None => unreachable.push(target),
+            }
+        }
+
+        Tour { route, order: visited, unreachable }
+    }
+}
+
+// Greedy nearest neighbour tour improved with 2-opt, using manhattan distance as the cost
+pub fn order_targets(start: BlockPos, targets: &[BlockPos]) -> Vec<BlockPos> {
+    let mut remaining: Vec<BlockPos> = targets.to_vec();
+    remaining.sort();
+    remaining.dedup();
+
+    let mut path = vec![start];
+    while !remaining.is_empty() {
+        let last = path[path.len() - 1];
+        let (nearest, _) = remaining.iter()
+            .enumerate()
+            .min_by_key(|(_, pos)| last.manhattan_distance(pos))
+            .unwrap();
+        path.push(remaining.swap_remove(nearest));
+    }
+
+    two_opt(&mut path);
+
+    path.remove(0);
+    path
+}
+
+// The start (index 0) stays fixed and the path doesn't return to it
+fn two_opt(path: &mut [BlockPos]) {
+    let dist = |a: &BlockPos, b: &BlockPos| a.manhattan_distance(b) as i64;
+    let n = path.len();
+
+    let mut improved = true;
+    let mut passes = 0;
+
+    while improved && passes < 100 {
+        improved = false;
+        passes += 1;
+
+        for i in 1..n.saturating_sub(1) {
+            for j in i + 1..n {
+                let before = dist(&path[i - 1], &path[i]) + if j + 1 < n { dist(&path[j], &path[j + 1]) } else { 0 };
+                let after = dist(&path[i - 1], &path[j]) + if j + 1 < n { dist(&path[i], &path[j + 1]) } else { 0 };
+
+                if after < before {
+                    path[i..=j].reverse();
+                    improved = true;
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{ actions::{ Action, ActionOptions, route_actions }, builds::{ BuildDamage, DamageKind, build_damage }, flooding::{ Fluid, FloodResponse, FloodSeverity, find_dry_route, seal_floods, simulate_floods }, chunk::{ BlockState, Chunk }, hazards::HazardKind, nbt::{ Tag, TagPayload } };
+
+    fn tag(name: &str, payload: TagPayload) -> Tag {
+        Tag { name: name.to_string(), payload }
+    }
+
+    // Chunk 0, 0 from y 0 to 31, each block whatever block gives for it
+    fn view(block: impl Fn(BlockPos) -> &'static str) -> WorldView {
+        let air = TagPayload::Compound(vec![tag("Name", TagPayload::String("minecraft:air".to_string()))].into());
+        let sections = (0..2)
+            .map(|y| {
+                let block_states = vec![tag("palette", TagPayload::List(vec![air.clone()]))];
+                TagPayload::Compound(vec![tag("Y", TagPayload::Byte(y)), tag("block_states", TagPayload::Compound(block_states.into()))].into())
+            })
+            .collect();
+        let root = vec![
+            tag("DataVersion", TagPayload::Int(3700)),
+            tag("xPos", TagPayload::Int(0)),
+            tag("zPos", TagPayload::Int(0)),
+            tag("yPos", TagPayload::Int(0)),
+            tag("sections", TagPayload::List(sections)),
+        ];
+        let mut chunk = Chunk::from_nbt(tag("", TagPayload::Compound(root.into()))).unwrap();
+        for y in 0..32 {
+            for z in 0..16 {
+                for x in 0..16 {
+                    let name = block(BlockPos::new(x, y, z));
+                    if name != "minecraft:air" {
+                        chunk.set_block(x as usize, y, z as usize, BlockState::new(name)).unwrap();
+                    }
+                }
+            }
+        }
+
+        let mut view = WorldView::new();
+        view.insert(chunk);
+        view
+    }
+
+    fn stone_below(floor: i32) -> impl Fn(BlockPos) -> &'static str {
+        move |pos| if pos.y <= floor { "minecraft:stone" } else { "minecraft:air" }
+    }
+
+    // Every step is one block to the side and at most one up or down, or
+    // straight up or down in something climbable, onto a floor not dug out
+    // yet or a support
+    fn assert_walkable(view: &WorldView, route: &Route) {
+        let mut dug = HashSet::new();
+        for pair in route.steps.windows(2) {
+            let (from, to) = (pair[0], pair[1]);
+            let side = from.x.abs_diff(to.x) + from.z.abs_diff(to.z);
+            let up = from.y.abs_diff(to.y);
+            if side == 0 {
+                assert_eq!(up, 1, "{from} to {to}");
+                assert!(is_climbable(view.block_at(from).unwrap()) || is_climbable(view.block_at(to).unwrap()), "{from} to {to}");
+            } else {
+                assert!(side == 1 && up <= 1, "{from} to {to}");
+            }
+
+            dug.extend(clearance(from, to).into_iter().chain([to, to.offset(0, 1, 0)]).filter(|block| route.mined.contains(block)));
+            let floor = to.offset(0, -1, 0);
+            let stands = is_climbable(view.block_at(to).unwrap())
+                || route.supports.contains(&floor)
+                || (is_floor(view.block_at(floor).unwrap()) && !dug.contains(&floor));
+            assert!(stands, "nothing to stand on at {to}");
+        }
+    }
+
+    #[test]
+    fn walks_along_the_floor() {
+        let view = view(stone_below(4));
+        let route = Pathfinder::new(&view).find_route(BlockPos::new(1, 5, 1), BlockPos::new(6, 5, 1)).unwrap();
+
+        assert_eq!(route.steps.len(), 6);
+        assert!(route.steps.iter().all(|step| step.y == 5));
+        assert!(route.mined.is_empty());
+        assert!(route.supports.is_empty());
+        assert_eq!(route.cost, 5 * STEP_COST);
+    }
+
+    #[test]
+    fn does_not_fly_up_to_a_goal_in_the_air() {
+        let view = view(stone_below(4));
+        assert!(Pathfinder::new(&view).find_route(BlockPos::new(1, 5, 1), BlockPos::new(1, 10, 1)).is_none());
+    }
+
+    #[test]
+    fn climbs_ladders_straight_up() {
+        let view = view(|pos| match (pos.x, pos.y, pos.z) {
+            (_, ..=4, _) => "minecraft:stone",
+            (1, 5..=10, 1) => "minecraft:ladder",
+            _ => "minecraft:air",
+        });
+        let route = Pathfinder::new(&view).find_route(BlockPos::new(2, 5, 1), BlockPos::new(1, 10, 1)).unwrap();
+
+        assert_walkable(&view, &route);
+        assert_eq!(route.steps.len(), 6);
+        assert!(route.mined.is_empty());
+    }
+
+    #[test]
+    fn digs_a_staircase_instead_of_going_up_a_shaft() {
+        let view = view(|pos| if pos.x == 3 && pos.z == 3 && (5..=12).contains(&pos.y) { "minecraft:air" } else { "minecraft:stone" });
+        let route = Pathfinder::new(&view).find_route(BlockPos::new(3, 5, 3), BlockPos::new(3, 12, 3)).unwrap();
+
+        assert_walkable(&view, &route);
+        assert!(route.steps.windows(2).all(|pair| pair[0].x != pair[1].x || pair[0].z != pair[1].z));
+        assert!(!route.mined.is_empty());
+    }
+
+    #[test]
+    fn digs_down_a_cliff_instead_of_dropping() {
+        // A cliff down from y 10 to the floor at 4, too high to bridge off
+        let view = view(|pos| if pos.y <= 4 || (pos.x <= 2 && pos.y <= 9) { "minecraft:stone" } else { "minecraft:air" });
+        let route = Pathfinder::new(&view).find_route(BlockPos::new(1, 10, 1), BlockPos::new(6, 5, 1)).unwrap();
+
+        assert_walkable(&view, &route);
+        assert!(route.steps.iter().all(|step| step.y >= 5));
+        assert!(route.supports.is_empty());
+        assert!(!route.mined.is_empty());
+    }
+
+    #[test]
+    fn clears_head_room_to_step_up() {
+        // The floor rises by one at x 3, with a ceiling over the lower part
+        let view = view(|pos| match (pos.x, pos.y) {
+            (3.., 5) => "minecraft:obsidian",
+            (_, ..=4) | (..=2, 7..) => "minecraft:stone",
+            _ => "minecraft:air",
+        });
+        let route = Pathfinder::new(&view).find_route(BlockPos::new(1, 5, 1), BlockPos::new(5, 6, 1)).unwrap();
+
+        assert_walkable(&view, &route);
+        assert_eq!(route.steps, vec![BlockPos::new(1, 5, 1), BlockPos::new(2, 5, 1), BlockPos::new(3, 6, 1), BlockPos::new(4, 6, 1), BlockPos::new(5, 6, 1)]);
+        assert_eq!(route.mined, vec![BlockPos::new(2, 7, 1)]);
+
+        let actions = route_actions(&view, &route, ActionOptions::new());
+        assert_eq!(actions[..3], [Action::Move(BlockPos::new(2, 5, 1)), Action::Mine(BlockPos::new(2, 7, 1)), Action::Move(BlockPos::new(3, 6, 1))]);
+    }
+
+    #[test]
+    fn bridges_gaps_with_supports() {
+        let view = view(|pos| if pos.y <= 4 && !(3..=4).contains(&pos.x) { "minecraft:stone" } else { "minecraft:air" });
+        let route = Pathfinder::new(&view).find_route(BlockPos::new(1, 5, 1), BlockPos::new(6, 5, 1)).unwrap();
+
+        assert_walkable(&view, &route);
+        assert_eq!(route.supports, vec![BlockPos::new(3, 4, 1), BlockPos::new(4, 4, 1)]);
+        assert_eq!(route.cost, 5 * STEP_COST + 2 * SUPPORT_COST);
+
+        let actions = route_actions(&view, &route, ActionOptions::new());
+        let place = actions.iter().position(|action| *action == Action::Place(BlockPos::new(3, 4, 1))).unwrap();
+        assert_eq!(actions[place + 1], Action::Move(BlockPos::new(3, 5, 1)));
+    }
+
+    #[test]
+    fn goes_around_lava_when_avoiding_hazards() {
+        // Lava right over the head of the straight tunnel
+        let view = view(|pos| if pos == BlockPos::new(4, 7, 1) { "minecraft:lava" } else { "minecraft:stone" });
+        let (start, goal) = (BlockPos::new(1, 5, 1), BlockPos::new(7, 5, 1));
+        let lava = Hazard { kind: HazardKind::Lava, pos: BlockPos::new(4, 7, 1), mined: Some(BlockPos::new(4, 6, 1)) };
+
+        let route = Pathfinder::new(&view).find_route(start, goal).unwrap();
+        assert_walkable(&view, &route);
+        assert_eq!(route.hazards, vec![lava.clone()]);
+        let actions = route_actions(&view, &route, ActionOptions::new());
+        let mine = actions.iter().position(|action| *action == Action::Mine(BlockPos::new(4, 6, 1))).unwrap();
+        assert_eq!(actions[mine + 1], Action::Place(BlockPos::new(4, 7, 1)));
+
+        let route = Pathfinder::new(&view).avoid_hazards().find_route(start, goal).unwrap();
+        assert_walkable(&view, &route);
+        assert!(route.hazards.is_empty());
+        assert!(!route.mined.contains(&BlockPos::new(4, 6, 1)));
+    }
+
+    #[test]
+    fn plans_around_lava_that_would_pour_in() {
+        // A pool of lava one block over the head of the straight tunnel
+        let view = view(|pos| if pos.y == 7 && pos.z == 1 && (4..=5).contains(&pos.x) { "minecraft:lava" } else { "minecraft:stone" });
+        let (start, goal) = (BlockPos::new(1, 5, 1), BlockPos::new(8, 5, 1));
+
+        let route = Pathfinder::new(&view).find_route(start, goal).unwrap();
+        let floods = simulate_floods(&view, &route);
+        assert_eq!(floods.len(), 2);
+        assert_eq!((floods[0].source, floods[0].breach), (BlockPos::new(4, 7, 1), BlockPos::new(4, 6, 1)));
+        assert_eq!((floods[1].source, floods[1].breach), (BlockPos::new(5, 7, 1), BlockPos::new(5, 6, 1)));
+        assert!(floods.iter().all(|flood| flood.fluid == Fluid::Lava && flood.severity == FloodSeverity::Deadly));
+        // It falls to the floor and runs three blocks each way along it
+        assert!(floods[0].flooded.contains(&BlockPos::new(4, 5, 1)));
+        assert!(floods[0].flooded.contains(&BlockPos::new(7, 5, 1)));
+        assert!(!floods[0].flooded.contains(&BlockPos::new(8, 5, 1)));
+
+        let mut actions = route_actions(&view, &route, ActionOptions::new());
+        seal_floods(&mut actions, &floods);
+        for flood in &floods {
+            let mine = actions.iter().position(|action| *action == Action::Mine(flood.breach)).unwrap();
+            assert_eq!(actions[mine + 1], Action::Place(flood.source));
+            assert_eq!(actions.iter().filter(|action| **action == Action::Place(flood.source)).count(), 1);
+        }
+
+        for response in [FloodResponse::Reject, FloodResponse::Penalize] {
+            let mut pathfinder = Pathfinder::new(&view);
+            let (route, floods) = find_dry_route(&mut pathfinder, &view, start, goal, response).unwrap();
+            assert_walkable(&view, &route);
+            assert!(floods.iter().all(|flood| flood.severity == FloodSeverity::Contained), "{response:?}");
+            assert!(!route.mined.contains(&BlockPos::new(4, 6, 1)), "{response:?}");
+        }
+    }
+
+    #[test]
+    fn digs_deeper_under_a_base_floor() {
+        // A floor of planks right on the head of the straight tunnel, from one
+        // side of the chunk to the other so there's no going around it
+        let view = view(|pos| if pos.y == 7 && (3..=5).contains(&pos.x) { "minecraft:oak_planks" } else { "minecraft:stone" });
+        let (start, goal) = (BlockPos::new(1, 5, 1), BlockPos::new(7, 5, 1));
+
+        let route = Pathfinder::new(&view).find_route(start, goal).unwrap();
+        assert!(route.steps.iter().all(|step| step.y == 5));
+        let floating = BuildDamage { kind: DamageKind::Floating, pos: BlockPos::new(3, 7, 1), mined: BlockPos::new(3, 6, 1) };
+        assert!(build_damage(&view, &route.mined).contains(&floating));
+
+        for severity in [Severity::Forbid, Severity::Penalize] {
+            let protect = ProtectBuilds { severity, distance: 2 };
+            let route = Pathfinder::new(&view).protect_builds(protect).find_route(start, goal).unwrap();
+            assert_walkable(&view, &route);
+            assert!(route.steps.iter().filter(|step| (3..=5).contains(&step.x)).all(|step| step.y < 4), "{severity:?}");
+            assert!(build_damage(&view, &route.mined).is_empty(), "{severity:?}");
+        }
+    }
+
+    #[test]
+    fn reports_builds_left_floating() {
+        let view = view(|pos| match pos.y {
+            7 if pos.x == 4 => "minecraft:oak_planks",
+            6 if pos.x == 5 => "minecraft:glass",
+            _ => "minecraft:stone",
+        });
+        let route = Pathfinder::new(&view).find_route(BlockPos::new(1, 5, 1), BlockPos::new(4, 5, 1)).unwrap();
+
+        let damage = build_damage(&view, &route.mined);
+        assert_eq!(damage, vec![
+            BuildDamage { kind: DamageKind::Floating, pos: BlockPos::new(4, 7, 1), mined: BlockPos::new(4, 6, 1) },
+            BuildDamage { kind: DamageKind::Exposed, pos: BlockPos::new(5, 6, 1), mined: BlockPos::new(4, 6, 1) },
+        ]);
+    }
+
+    #[test]
+    fn orders_targets_along_a_line() {
+        let targets = [BlockPos::new(10, 5, 0), BlockPos::new(2, 5, 0), BlockPos::new(5, 5, 0), BlockPos::new(2, 5, 0)];
+        assert_eq!(order_targets(BlockPos::new(0, 5, 0), &targets), vec![BlockPos::new(2, 5, 0), BlockPos::new(5, 5, 0), BlockPos::new(10, 5, 0)]);
+        assert!(order_targets(BlockPos::new(0, 5, 0), &[]).is_empty());
+    }
+
+    #[test]
+    fn untangles_crossing_legs() {
+        // Nearest first takes 1 6 and 4 5, goes back for 5 -3 and then all
+        // the way across to -6 5, 39 blocks. Starting with 5 -3 takes 29.
+        let start = BlockPos::new(0, 0, 0);
+        let targets = [BlockPos::new(5, 0, -3), BlockPos::new(-6, 0, 5), BlockPos::new(1, 0, 6), BlockPos::new(4, 0, 5)];
+        let order = order_targets(start, &targets);
+
+        assert_eq!(order, vec![BlockPos::new(5, 0, -3), BlockPos::new(4, 0, 5), BlockPos::new(1, 0, 6), BlockPos::new(-6, 0, 5)]);
+        let length: u32 = [start].iter().chain(&order).zip(&order).map(|(a, b)| a.manhattan_distance(b)).sum();
+        assert_eq!(length, 29);
+    }
+
+    #[test]
+    fn tours_reachable_targets_and_reports_the_rest() {
+        let view = view(stone_below(4));
+        let start = BlockPos::new(1, 5, 1);
+        let targets = [BlockPos::new(8, 5, 8), BlockPos::new(4, 5, 1), BlockPos::new(4, 12, 4)];
+        let mut pathfinder = Pathfinder::new(&view);
+        let tour = pathfinder.find_tour(start, &targets);
+
+        assert_eq!(tour.order, vec![BlockPos::new(4, 5, 1), BlockPos::new(8, 5, 8)]);
+        assert_eq!(tour.unreachable, vec![BlockPos::new(4, 12, 4)]);
+        assert_walkable(&view, &tour.route);
+        assert_eq!(tour.route.steps.first(), Some(&start));
+        assert_eq!(tour.route.steps.last(), Some(&BlockPos::new(8, 5, 8)));
+        assert_eq!(tour.route.steps.len() as u32 - 1, start.manhattan_distance(&targets[1]) + targets[1].manhattan_distance(&targets[0]));
+    }
+}
@@ -0,0 +1,143 @@
+use anyhow::{ Result, Context, bail };
+use std::{ fs, path::{ Path, PathBuf } };
+
+use crate::{ item::{ ItemStack, items_in }, level::read_nbt_file, nbt::{ Tag, TagPayload }, pos::BlockPos, world::{ Dimension, World } };
+
+// A player as saved in playerdata/<uuid>.dat when they last logged out, or
+// in level.dat for the host of a singleplayer world
+pub struct PlayerData {
+    uuid: String,
+    pos: [f64; 3],
+    dimension: String,
+    inventory: Vec<ItemStack>,
+    ender_chest: Vec<ItemStack>,
+    nbt: Tag,
+}
+
+impl PlayerData {
+    // The uuid comes from the file name, which is what the server goes by
+    pub fn load(path: impl AsRef<Path>) -> Result<PlayerData> {
+        let path = path.as_ref();
+        let nbt = read_nbt_file(path)?;
+        let mut player = PlayerData::from_nbt(nbt).with_context(|| format!("Invalid player data in {}", path.display()))?;
+        if let Some(uuid) = path.file_stem().and_then(|stem| stem.to_str()) {
+            player.uuid = uuid.to_string();
+        }
+        Ok(player)
+    }
+
+    pub fn from_nbt(nbt: Tag) -> Result<PlayerData> {
+        let root = &nbt.payload;
+
+        let pos = match root.get("Pos") {
+            Some(TagPayload::List(pos)) => match pos.as_slice() {
+                [TagPayload::Double(x), TagPayload::Double(y), TagPayload::Double(z)] => [*x, *y, *z],
+                _ => bail!("Pos is not three doubles"),
+            },
+            _ => bail!("Missing Pos tag"),
+        };
+
+        // Numbered before 1.16
+        let dimension = match root.get("Dimension") {
+            Some(TagPayload::String(id)) => id.clone(),
+            Some(TagPayload::Int(-1)) => Dimension::Nether.id().to_string(),
+            Some(TagPayload::Int(1)) => Dimension::End.id().to_string(),
+            Some(TagPayload::Int(_)) | None => Dimension::Overworld.id().to_string(),
+            Some(_) => bail!("Dimension is neither a string nor an int"),
+        };
+
+        // Armor and the offhand moved out of Inventory in 1.21.5
+        let mut inventory = root.get("Inventory").map(items_in).unwrap_or_default();
+        if let Some(TagPayload::Compound(equipment)) = root.get("equipment") {
+            inventory.extend(equipment.iter().filter_map(|tag| ItemStack::from_nbt(&tag.payload)));
+        }
+        let ender_chest = root.get("EnderItems").map(items_in).unwrap_or_default();
+
+        Ok(PlayerData { uuid: uuid_of(root).unwrap_or_default(), pos, dimension, inventory, ender_chest, nbt })
+    }
+
+    // Hyphenated, e.g. 069a79f4-44e9-4726-a5be-fca90e38aaf5. Empty for
+    // players saved without one, which only very old level.dat files are.
+    pub fn uuid(&self) -> &str {
+        &self.uuid
+    }
+
+    pub fn pos(&self) -> [f64; 3] {
+        self.pos
+    }
+
+    // The block the player's feet are in
+    pub fn block_pos(&self) -> BlockPos {
+        BlockPos::new(self.pos[0].floor() as i32, self.pos[1].floor() as i32, self.pos[2].floor() as i32)
+    }
+
+    // The dimension id, e.g. minecraft:the_nether, which can also be one added
+    // by a data pack
+    pub fn dimension_id(&self) -> &str {
+        &self.dimension
+    }
+
+    // None for dimensions other than the three built in ones
+    pub fn dimension(&self) -> Option<Dimension> {
+        self.dimension.parse().ok()
+    }
+
+    pub fn inventory(&self) -> &[ItemStack] {
+        &self.inventory
+    }
+
+    pub fn ender_chest(&self) -> &[ItemStack] {
+        &self.ender_chest
+    }
+
+    pub fn nbt(&self) -> &Tag {
+        &self.nbt
+    }
+}
+
+// Four ints since 1.16, two longs before
+fn uuid_of(root: &TagPayload) -> Option<String> {
+    let (most, least) = match (root.get("UUID"), root.get("UUIDMost"), root.get("UUIDLeast")) {
+        (Some(TagPayload::IntArray(ints)), _, _) if ints.len() == 4 => (
+            (ints[0] as u32 as u64) << 32 | ints[1] as u32 as u64,
+            (ints[2] as u32 as u64) << 32 | ints[3] as u32 as u64,
+        ),
+        (_, Some(TagPayload::Long(most)), Some(TagPayload::Long(least))) => (*most as u64, *least as u64),
+        _ => return None,
+    };
+    let hex = format!("{most:016x}{least:016x}");
+    Some(format!("{}-{}-{}-{}-{}", &hex[..8], &hex[8..12], &hex[12..16], &hex[16..20], &hex[20..]))
+}
+
+impl World {
+    // The .dat files in playerdata, sorted. Backups like <uuid>.dat_old are
+    // left out.
+    pub fn player_files(&self) -> Result<Vec<PathBuf>> {
+        let directory = self.path().join("playerdata");
+        if !directory.is_dir() {
+            return Ok(Vec::new());
+        }
+
+        let mut files = Vec::new();
+        for entry in fs::read_dir(&directory).with_context(|| format!("Could not list {}", directory.display()))? {
+            let path = entry?.path();
+            if path.extension().is_some_and(|extension| extension == "dat") {
+                files.push(path);
+            }
+        }
+        files.sort();
+        Ok(files)
+    }
+
+    // The singleplayer host, who's kept in level.dat rather than playerdata
+    pub fn host_player(&self) -> Result<Option<PlayerData>> {
+        if !self.path().join("level.dat").is_file() {
+            return Ok(None);
+        }
+        let level = self.level_dat()?;
+        match level.nbt().payload.get_path("Data.Player") {
+            Ok(player) => Ok(Some(PlayerData::from_nbt(Tag { name: "Player".to_string(), payload: player.clone() })?)),
+            Err(_) => Ok(None),
+        }
+    }
+}
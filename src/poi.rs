@@ -0,0 +1,121 @@
+use anyhow::{ Result, Context, bail };
+use std::path::Path;
+
+use crate::{ nbt::{ Tag, TagPayload }, pos::BlockPos, region::read_region };
+
+// A point of interest: a villager's bed or workstation, a bell, a nether
+// portal block, a lodestone, a bee nest and the like
+pub struct Poi {
+    kind: String,
+    pos: BlockPos,
+    free_tickets: i32,
+}
+
+impl Poi {
+    pub fn from_nbt(nbt: &TagPayload) -> Result<Poi> {
+        let kind = match nbt.get("type") {
+            Some(TagPayload::String(kind)) => kind.clone(),
+            _ => bail!("Point of interest has no type"),
+        };
+
+        // A compound in 1.14 and 1.15
+        let pos = match nbt.get("pos") {
+            Some(TagPayload::IntArray(pos)) if pos.len() == 3 => BlockPos::new(pos[0], pos[1], pos[2]),
+            Some(pos @ TagPayload::Compound(_)) => match (pos.get("X"), pos.get("Y"), pos.get("Z")) {
+                (Some(TagPayload::Int(x)), Some(TagPayload::Int(y)), Some(TagPayload::Int(z))) => BlockPos::new(*x, *y, *z),
+                _ => bail!("{kind} pos is missing a coordinate"),
+            },
+            _ => bail!("{kind} has no pos"),
+        };
+
+        let free_tickets = match nbt.get("free_tickets") {
+            Some(TagPayload::Int(tickets)) => *tickets,
+            _ => 0,
+        };
+
+        Ok(Poi { kind, pos, free_tickets })
+    }
+
+    // e.g. minecraft:nether_portal or minecraft:armorer
+    pub fn kind(&self) -> &str {
+        &self.kind
+    }
+
+    pub fn pos(&self) -> BlockPos {
+        self.pos
+    }
+
+    // How many more villagers can claim it, 0 for a taken bed or workstation
+    pub fn free_tickets(&self) -> i32 {
+        self.free_tickets
+    }
+}
+
+pub struct PoiChunk {
+    pois: Vec<Poi>,
+}
+
+impl PoiChunk {
+    // Sections not marked Valid are left out, the game finds their points of
+    // interest again from the blocks when it loads them
+    pub fn from_nbt(nbt: Tag) -> Result<PoiChunk> {
+        let sections = match nbt.payload.get("Sections") {
+            Some(TagPayload::Compound(sections)) => sections,
+            Some(_) => bail!("Sections is not a compound"),
+            None => return Ok(PoiChunk { pois: Vec::new() }),
+        };
+
+        let mut pois = Vec::new();
+        for section in sections {
+            if !matches!(section.payload.get("Valid"), Some(TagPayload::Byte(1))) {
+                continue;
+            }
+            match section.payload.get("Records") {
+                Some(TagPayload::List(records)) => {
+                    for (i, record) in records.iter().enumerate() {
+                        pois.push(Poi::from_nbt(record).with_context(|| format!("Invalid record {i} in section {}", section.name))?);
+                    }
+                },
+                Some(_) => bail!("Records of section {} is not a list", section.name),
+                None => {},
+            }
+        }
+
+        Ok(PoiChunk { pois })
+    }
+
+    pub fn pois(&self) -> &[Poi] {
+        &self.pois
+    }
+}
+
+// A region file from poi/, since 1.14
+pub struct PoiRegion {
+    chunks: Vec<PoiChunk>,
+}
+
+impl PoiRegion {
+    // Chunks that can't be read are reported and skipped
+    pub fn load(path: impl AsRef<Path>) -> Result<PoiRegion> {
+        let path = path.as_ref();
+        let tags = read_region(path).with_context(|| format!("Could not read region {}", path.display()))?;
+
+        let mut chunks = Vec::new();
+        for tag in tags {
+            match PoiChunk::from_nbt(tag) {
+                Ok(chunk) => chunks.push(chunk),
+                Err(e) => log::warn!("Skipping point of interest chunk: {e:#}"),
+            }
+        }
+
+        Ok(PoiRegion { chunks })
+    }
+
+    pub fn chunks(&self) -> &[PoiChunk] {
+        &self.chunks
+    }
+
+    pub fn pois(&self) -> impl Iterator<Item = &Poi> {
+        self.chunks.iter().flat_map(|chunk| chunk.pois.iter())
+    }
+}
@@ -0,0 +1,187 @@
+use anyhow::Result;
+use std::collections::HashSet;
+
+use crate::{ builds::ProtectBuilds, costs::CostModel, pathfinding::{ Pathfinder, Route }, poi::PoiRegion, pos::BlockPos, world::{ Dimension, World } };
+
+// Standing in a portal until it takes you through takes 4 seconds, about as
+// long as walking 20 blocks
+pub const PORTAL_COST: u32 = 20;
+
+// How far from the scaled position the game looks for a portal to come out
+// of before it builds a new one, since 1.16
+const NETHER_SEARCH_RADIUS: i32 = 16;
+const OVERWORLD_SEARCH_RADIUS: i32 = 128;
+
+// How many of the cheapest looking ways through the nether get pathfound
+const CANDIDATES: usize = 3;
+
+// The blocks of one nether portal, as the POI data has them
+#[derive(Clone, Debug)]
+pub struct Portal {
+    pub dimension: Dimension,
+    pub blocks: Vec<BlockPos>,
+}
+
+impl Portal {
+    // Where a route goes in and where players come out, the lowest block
+    pub fn entrance(&self) -> BlockPos {
+        *self.blocks.iter().min_by_key(|pos| (pos.y, pos.x, pos.z)).unwrap()
+    }
+}
+
+// One part of a route through a single dimension
+pub struct Leg {
+    pub dimension: Dimension,
+    pub route: Route,
+}
+
+// How each leg is planned
+pub struct LegOptions<'a> {
+    // Chunks loaded around the leg's ends
+    pub margin: i32,
+    pub costs: &'a dyn CostModel,
+    pub avoid_hazards: bool,
+    pub protect_builds: Option<ProtectBuilds>,
+}
+
+pub struct PortalRoute {
+    pub legs: Vec<Leg>,
+    // Includes PORTAL_COST for every portal taken
+    pub cost: u32,
+}
+
+// Every nether portal in the overworld and the nether, from the POI regions.
+// Touching portal blocks make up one portal.
+pub fn find_portals(world: &World) -> Result<Vec<Portal>> {
+    let mut portals = Vec::new();
+    for dimension in [Dimension::Overworld, Dimension::Nether] {
+        let mut blocks = HashSet::new();
+        for region in world.poi_regions_in(dimension) {
+            let region = PoiRegion::load(&region.path)?;
+            blocks.extend(region.pois().filter(|poi| poi.kind() == "minecraft:nether_portal").map(|poi| poi.pos()));
+        }
+
+        while let Some(&first) = blocks.iter().next() {
+            blocks.remove(&first);
+            let mut portal = vec![first];
+            let mut i = 0;
+            while i < portal.len() {
+                for next in portal[i].neighbors() {
+                    if blocks.remove(&next) {
+                        portal.push(next);
+                    }
+                }
+                i += 1;
+            }
+            portals.push(Portal { dimension, blocks: portal });
+        }
+    }
+    Ok(portals)
+}
+
+// The portal the game sends a player through portal to: the one closest to
+// the scaled position within the search radius, if there is one
+pub fn linked_portal<'a>(portal: &Portal, portals: &'a [Portal]) -> Option<&'a Portal> {
+    let from = portal.entrance();
+    let (to, target, radius) = match portal.dimension {
+        Dimension::Overworld => (Dimension::Nether, BlockPos::new(from.x.div_euclid(8), from.y, from.z.div_euclid(8)), NETHER_SEARCH_RADIUS),
+        Dimension::Nether => (Dimension::Overworld, BlockPos::new(from.x * 8, from.y, from.z * 8), OVERWORLD_SEARCH_RADIUS),
+        Dimension::End => return None,
+    };
+
+    portals.iter()
+        .filter(|other| other.dimension == to)
+        .filter_map(|other| {
+            let block = other.blocks.iter()
+                .filter(|pos| pos.x.abs_diff(target.x) as i32 <= radius && pos.z.abs_diff(target.z) as i32 <= radius)
+                .min_by_key(|pos| distance_squared(pos, &target))?;
+            Some((distance_squared(block, &target), other))
+        })
+        .min_by_key(|(distance, _)| *distance)
+        .map(|(_, other)| other)
+}
+
+fn distance_squared(a: &BlockPos, b: &BlockPos) -> i64 {
+    let d = |a: i32, b: i32| (a as i64 - b as i64).pow(2);
+    d(a.x, b.x) + d(a.y, b.y) + d(a.z, b.z)
+}
+
+// Plans from start to goal in the overworld, or in the nether, either staying
+// in that dimension or going through the other one by way of two portal
+// pairs, and returns whichever costs less
+pub fn find_route_through_portals(world: &World, dimension: Dimension, start: BlockPos, goal: BlockPos, portals: &[Portal], options: &LegOptions) -> Result<Option<PortalRoute>> {
+    let mut best = find_leg(world, dimension, start, goal, &[], options)?
+        .map(|route| PortalRoute { cost: route.cost, legs: vec![Leg { dimension, route }] });
+
+    // Every way in and out, with the portals on both sides
+    let links: Vec<(&Portal, &Portal)> = portals.iter()
+        .filter(|portal| portal.dimension == dimension)
+        .filter_map(|portal| Some((portal, linked_portal(portal, portals)?)))
+        .collect();
+    let exits: Vec<(&Portal, &Portal)> = portals.iter()
+        .filter(|portal| portal.dimension != dimension)
+        .filter_map(|portal| Some((portal, linked_portal(portal, portals).filter(|home| home.dimension == dimension)?)))
+        .collect();
+
+    // Straight lines are all that's known before pathfinding, so the
+    // combinations are ranked by those and only the best few are tried
+    let mut combinations = Vec::new();
+    for &(entry, entry_far) in &links {
+        for &(exit_far, exit) in &exits {
+            let estimate = start.manhattan_distance(&entry.entrance())
+                + entry_far.entrance().manhattan_distance(&exit_far.entrance())
+                + exit.entrance().manhattan_distance(&goal)
+                + 2 * PORTAL_COST;
+            combinations.push((estimate, entry, entry_far, exit_far, exit));
+        }
+    }
+    combinations.sort_by_key(|(estimate, ..)| *estimate);
+
+    let far = if dimension == Dimension::Overworld { Dimension::Nether } else { Dimension::Overworld };
+    for (estimate, entry, entry_far, exit_far, exit) in combinations.into_iter().take(CANDIDATES) {
+        if best.as_ref().is_some_and(|best| best.cost <= estimate) {
+            break;
+        }
+
+        let legs = [
+            (dimension, start, entry.entrance(), &entry.blocks),
+            (far, entry_far.entrance(), exit_far.entrance(), &exit_far.blocks),
+            (dimension, exit.entrance(), goal, &Vec::new()),
+        ];
+        let mut route = PortalRoute { legs: Vec::new(), cost: 2 * PORTAL_COST };
+        for (leg_dimension, from, to, passable) in legs {
+            let Some(leg) = find_leg(world, leg_dimension, from, to, passable, options)? else {
+                break;
+            };
+            route.cost += leg.cost;
+            route.legs.push(Leg { dimension: leg_dimension, route: leg });
+        }
+
+        if route.legs.len() == 3 && best.as_ref().is_none_or(|best| route.cost < best.cost) {
+            best = Some(route);
+        }
+    }
+
+    Ok(best)
+}
+
+// Portal blocks can't be dug through, the ones a leg ends in are passed as
+// passable so it can step into them
+fn find_leg(world: &World, dimension: Dimension, from: BlockPos, to: BlockPos, passable: &[BlockPos], options: &LegOptions) -> Result<Option<Route>> {
+    let margin = options.margin;
+    let min_chunk = (from.chunk_x().min(to.chunk_x()) - margin, from.chunk_z().min(to.chunk_z()) - margin);
+    let max_chunk = (from.chunk_x().max(to.chunk_x()) + margin, from.chunk_z().max(to.chunk_z()) + margin);
+    let view = world.view(dimension, min_chunk, max_chunk)?;
+
+    let mut pathfinder = Pathfinder::new(&view).with_costs(options.costs);
+    if options.avoid_hazards {
+        pathfinder = pathfinder.avoid_hazards();
+    }
+    if let Some(protect) = options.protect_builds {
+        pathfinder = pathfinder.protect_builds(protect);
+    }
+    for pos in passable {
+        pathfinder.clear(*pos);
+    }
+    Ok(pathfinder.find_route(from, to))
+}
@@ -0,0 +1,62 @@
+use std::{fmt, str::FromStr};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct BlockPos {
+    pub x: i32,
+    pub y: i32,
+    pub z: i32,
+}
+
+impl BlockPos {
+    pub fn new(x: i32, y: i32, z: i32) -> BlockPos {
+        BlockPos { x, y, z }
+    }
+
+    pub fn chunk_x(&self) -> i32 {
+        self.x.div_euclid(16)
+    }
+
+    pub fn chunk_z(&self) -> i32 {
+        self.z.div_euclid(16)
+    }
+
+    pub fn offset(&self, dx: i32, dy: i32, dz: i32) -> BlockPos {
+        BlockPos { x: self.x + dx, y: self.y + dy, z: self.z + dz }
+    }
+
+    pub fn manhattan_distance(&self, other: &BlockPos) -> u32 {
+        self.x.abs_diff(other.x) + self.y.abs_diff(other.y) + self.z.abs_diff(other.z)
+    }
+
+    pub fn neighbors(&self) -> [BlockPos; 6] {
+        [
+            self.offset(1, 0, 0),
+            self.offset(-1, 0, 0),
+            self.offset(0, 0, 1),
+            self.offset(0, 0, -1),
+            self.offset(0, 1, 0),
+            self.offset(0, -1, 0),
+        ]
+    }
+}
+
+// Parses "x,y,z"
+impl FromStr for BlockPos {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<BlockPos, String> {
+        let coords: Vec<&str> = s.split(',').map(str::trim).collect();
+        if coords.len() != 3 {
+            return Err(format!("Expected x,y,z but got \"{s}\""));
+        }
+
+        let parse = |c: &str| c.parse::<i32>().map_err(|e| format!("Invalid coordinate \"{c}\": {e}"));
+        Ok(BlockPos { x: parse(coords[0])?, y: parse(coords[1])?, z: parse(coords[2])? })
+    }
+}
+
+impl fmt::Display for BlockPos {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} {} {}", self.x, self.y, self.z)
+    }
+}
@@ -0,0 +1,40 @@
+//! The types most uses of the crate need, in one import. Planning a route to
+//! the nearest diamond ore and saving it as a function file:
+//!
+//! ```no_run
+//! use path_miner::prelude::*;
+//!
+//! fn main() -> anyhow::Result<()> {
+//!     let world = World::open("saves/New World")?;
+//!     let start = BlockPos::new(0, -50, 0);
+//!
+//!     let diamond = world.find_blocks_in(Dimension::Overworld, &["minecraft:diamond_ore"])
+//!         .min_by_key(|pos| pos.manhattan_distance(&start))
+//!         .ok_or_else(|| anyhow::anyhow!("No diamonds in the world"))?;
+//!
+//!     // Load the chunks around both ends, with a chunk to spare
+//!     let min = (start.chunk_x().min(diamond.chunk_x()) - 1, start.chunk_z().min(diamond.chunk_z()) - 1);
+//!     let max = (start.chunk_x().max(diamond.chunk_x()) + 1, start.chunk_z().max(diamond.chunk_z()) + 1);
+//!     let view = world.view(Dimension::Overworld, min, max)?;
+//!
+//!     let route = Pathfinder::new(&view).find_route(start, diamond)
+//!         .ok_or_else(|| anyhow::anyhow!("No route to {diamond}"))?;
+//!     println!("{} steps, {} blocks to mine", route.steps.len(), route.mined.len());
+//!
+//!     write_mcfunction("route.mcfunction", &route_commands(&route, &[diamond], RouteMarker::Particles))?;
+//!     Ok(())
+//! }
+//! ```
+
+pub use crate::{
+    block_id::BlockId,
+    chunk::{ BlockState, BlockType, Chunk },
+    mcfunction::{ route_commands, write_mcfunction, RouteMarker },
+    nbt::{ NbtError, ParseOptions, PathError, SerdeError, Tag, TagPayload },
+    pathfinding::{ Pathfinder, Route },
+    pos::BlockPos,
+    query::QueryError,
+    region::{ parse_chunks, ParseReport, Region },
+    snbt::SnbtError,
+    world::{ Dimension, World, WorldView },
+};
@@ -0,0 +1,418 @@
+use std::{ fmt, str::FromStr };
+
+use crate::chunk::BlockType;
+use crate::pos::BlockPos;
+
+// A search over blocks and where they are, like
+//
+//     minecraft:*_ore[!deepslate] & y<0
+//
+// Terms are joined with & and |, negated with ! and grouped with parentheses,
+// & binding tighter than |. A term is one of
+//
+// - a block name, where * matches any run of characters. Names without a
+//   namespace get minecraft: unless they start with *. Brackets after the name
+//   filter on properties with key=value or key!=value, values taking * too,
+//   and on the name itself with a bare word it has to contain or !word it
+//   mustn't.
+// - a tag like #logs or #minecraft:diamond_ores, from a built in list of the
+//   common ones
+// - a coordinate compared to a number, e.g. y<0 or x>=100, with <, <=, >, >=,
+//   = or !=
+#[derive(Clone, Debug)]
+pub struct Query {
+    expr: Expr,
+}
+
+#[derive(Clone, Debug)]
+enum Expr {
+    Or(Vec<Expr>),
+    And(Vec<Expr>),
+    Not(Box<Expr>),
+    Block(BlockPattern),
+    Tag(&'static [&'static str]),
+    Coordinate(Axis, Comparison, i32),
+}
+
+#[derive(Clone, Debug)]
+struct BlockPattern {
+    name: String,
+    filters: Vec<Filter>,
+}
+
+#[derive(Clone, Debug)]
+enum Filter {
+    Property { key: String, value: String, negated: bool },
+    NameContains { text: String, negated: bool },
+}
+
+#[derive(Clone, Copy, Debug)]
+enum Axis {
+    X,
+    Y,
+    Z,
+}
+
+#[derive(Clone, Copy, Debug)]
+enum Comparison {
+    Less,
+    LessOrEqual,
+    Greater,
+    GreaterOrEqual,
+    Equal,
+    NotEqual,
+}
+
+// Block tags by name and the block names they stand for
+const TAGS: &[(&str, &[&str])] = &[
+    ("ores", &["minecraft:*_ore", "minecraft:ancient_debris"]),
+    ("coal_ores", &["minecraft:coal_ore", "minecraft:deepslate_coal_ore"]),
+    ("copper_ores", &["minecraft:copper_ore", "minecraft:deepslate_copper_ore"]),
+    ("iron_ores", &["minecraft:iron_ore", "minecraft:deepslate_iron_ore"]),
+    ("gold_ores", &["minecraft:gold_ore", "minecraft:deepslate_gold_ore", "minecraft:nether_gold_ore"]),
+    ("redstone_ores", &["minecraft:redstone_ore", "minecraft:deepslate_redstone_ore"]),
+    ("lapis_ores", &["minecraft:lapis_ore", "minecraft:deepslate_lapis_ore"]),
+    ("diamond_ores", &["minecraft:diamond_ore", "minecraft:deepslate_diamond_ore"]),
+    ("emerald_ores", &["minecraft:emerald_ore", "minecraft:deepslate_emerald_ore"]),
+    ("logs", &["minecraft:*_log", "minecraft:*_wood", "minecraft:*_stem", "minecraft:*_hyphae"]),
+    ("leaves", &["minecraft:*_leaves"]),
+    ("planks", &["minecraft:*_planks"]),
+    ("wool", &["minecraft:*_wool"]),
+    ("base_stone_overworld", &["minecraft:stone", "minecraft:granite", "minecraft:diorite", "minecraft:andesite", "minecraft:tuff", "minecraft:deepslate"]),
+    ("base_stone_nether", &["minecraft:netherrack", "minecraft:basalt", "minecraft:blackstone"]),
+    ("air", &["minecraft:air", "minecraft:cave_air", "minecraft:void_air"]),
+];
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct QueryError {
+    // Byte offset into the query
+    pub offset: usize,
+    pub message: String,
+}
+
+impl fmt::Display for QueryError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} at offset {}", self.message, self.offset)
+    }
+}
+
+impl std::error::Error for QueryError {}
+
+impl Query {
+    pub fn parse(text: &str) -> Result<Query, QueryError> {
+        let mut parser = Parser { text, at: 0 };
+        let expr = parser.or()?;
+        if parser.peek().is_some() {
+            return Err(parser.error("Unexpected text after the query"));
+        }
+        Ok(Query { expr })
+    }
+
+    // Whether a block anywhere in the box between min and max matches, as far
+    // as that can be told without knowing where exactly it is. None means it
+    // depends on the position. Searches ask this once per palette entry with
+    // the section's bounds and only look at positions if the answer is None.
+    pub fn test(&self, block: &BlockType, min: BlockPos, max: BlockPos) -> Option<bool> {
+        self.expr.test(block, min, max)
+    }
+
+    // Whether the block matches somewhere, which is all a palette can tell
+    pub fn could_match(&self, block: &BlockType) -> bool {
+        self.test(block, BlockPos::new(i32::MIN, i32::MIN, i32::MIN), BlockPos::new(i32::MAX, i32::MAX, i32::MAX)) != Some(false)
+    }
+
+    // Whether a block of that name could match, for indexes that only know names
+    pub fn could_match_name(&self, name: &str) -> bool {
+        self.expr.test_name(name) != Some(false)
+    }
+
+    pub fn matches(&self, block: &BlockType, pos: BlockPos) -> bool {
+        self.expr.test(block, pos, pos).unwrap_or(false)
+    }
+}
+
+impl FromStr for Query {
+    type Err = QueryError;
+
+    fn from_str(s: &str) -> Result<Query, QueryError> {
+        Query::parse(s)
+    }
+}
+
+impl Expr {
+    fn test(&self, block: &BlockType, min: BlockPos, max: BlockPos) -> Option<bool> {
+        self.eval(&|term| match term {
+            Expr::Block(pattern) => Some(pattern.matches(block)),
+            Expr::Tag(names) => Some(names.iter().any(|name| glob(name, &block.name))),
+            Expr::Coordinate(axis, comparison, value) => {
+                let (low, high) = match axis {
+                    Axis::X => (min.x, max.x),
+                    Axis::Y => (min.y, max.y),
+                    Axis::Z => (min.z, max.z),
+                };
+                comparison.test(low, high, *value)
+            },
+            _ => None,
+        })
+    }
+
+    // Like test, knowing only the block's name, so property filters can't be decided either
+    fn test_name(&self, name: &str) -> Option<bool> {
+        self.eval(&|term| match term {
+            Expr::Block(pattern) => pattern.matches_name(name),
+            Expr::Tag(names) => Some(names.iter().any(|pattern| glob(pattern, name))),
+            _ => None,
+        })
+    }
+
+    // Three valued: an & with one false term is false even if another one
+    // can't be decided. term decides the terms that aren't &, | or !.
+    fn eval(&self, term: &impl Fn(&Expr) -> Option<bool>) -> Option<bool> {
+        match self {
+            Expr::Or(terms) => {
+                let mut result = Some(false);
+                for expr in terms {
+                    match expr.eval(term) {
+                        Some(true) => return Some(true),
+                        Some(false) => {},
+                        None => result = None,
+                    }
+                }
+                result
+            },
+            Expr::And(terms) => {
+                let mut result = Some(true);
+                for expr in terms {
+                    match expr.eval(term) {
+                        Some(false) => return Some(false),
+                        Some(true) => {},
+                        None => result = None,
+                    }
+                }
+                result
+            },
+            Expr::Not(expr) => expr.eval(term).map(|result| !result),
+            _ => term(self),
+        }
+    }
+}
+
+impl BlockPattern {
+    fn matches_name(&self, name: &str) -> Option<bool> {
+        if !glob(&self.name, name) {
+            return Some(false);
+        }
+        let mut result = Some(true);
+        for filter in &self.filters {
+            match filter {
+                Filter::Property { .. } => result = None,
+                Filter::NameContains { text, negated } if name.contains(text.as_str()) == *negated => return Some(false),
+                Filter::NameContains { .. } => {},
+            }
+        }
+        result
+    }
+
+    fn matches(&self, block: &BlockType) -> bool {
+        glob(&self.name, &block.name) && self.filters.iter().all(|filter| match filter {
+            Filter::Property { key, value, negated } => block.property(key).is_some_and(|actual| glob(value, actual)) != *negated,
+            Filter::NameContains { text, negated } => block.name.contains(text.as_str()) != *negated,
+        })
+    }
+}
+
+impl Comparison {
+    // Whether every value from low to high passes, none does, or only some
+    fn test(self, low: i32, high: i32, value: i32) -> Option<bool> {
+        let (all, none) = match self {
+            Comparison::Less => (high < value, low >= value),
+            Comparison::LessOrEqual => (high <= value, low > value),
+            Comparison::Greater => (low > value, high <= value),
+            Comparison::GreaterOrEqual => (low >= value, high < value),
+            Comparison::Equal => (low == value && high == value, value < low || value > high),
+            Comparison::NotEqual => (value < low || value > high, low == value && high == value),
+        };
+        if all {
+            Some(true)
+        } else if none {
+            Some(false)
+        } else {
+            None
+        }
+    }
+}
+
+// * matches any run of characters, everything else itself
+pub(crate) fn glob(pattern: &str, text: &str) -> bool {
+    let Some((first, rest)) = pattern.split_once('*') else {
+        return pattern == text;
+    };
+    let Some(mut text) = text.strip_prefix(first) else {
+        return false;
+    };
+
+    let mut parts: Vec<&str> = rest.split('*').collect();
+    let last = parts.pop().unwrap_or("");
+    for part in parts {
+        match text.find(part) {
+            Some(at) => text = &text[at + part.len()..],
+            None => return false,
+        }
+    }
+    text.len() >= last.len() && text.ends_with(last)
+}
+
+struct Parser<'a> {
+    text: &'a str,
+    at: usize,
+}
+
+impl<'a> Parser<'a> {
+    fn error(&self, message: impl Into<String>) -> QueryError {
+        QueryError { offset: self.at, message: message.into() }
+    }
+
+    fn peek(&mut self) -> Option<char> {
+        let rest = &self.text[self.at..];
+        self.at += rest.len() - rest.trim_start().len();
+        self.text[self.at..].chars().next()
+    }
+
+    fn eat(&mut self, c: char) -> bool {
+        let found = self.peek() == Some(c);
+        if found {
+            self.at += c.len_utf8();
+        }
+        found
+    }
+
+    fn or(&mut self) -> Result<Expr, QueryError> {
+        let mut terms = vec![self.and()?];
+        while self.eat('|') {
+            terms.push(self.and()?);
+        }
+        Ok(if terms.len() == 1 { terms.pop().unwrap() } else { Expr::Or(terms) })
+    }
+
+    fn and(&mut self) -> Result<Expr, QueryError> {
+        let mut terms = vec![self.unary()?];
+        while self.eat('&') {
+            terms.push(self.unary()?);
+        }
+        Ok(if terms.len() == 1 { terms.pop().unwrap() } else { Expr::And(terms) })
+    }
+
+    fn unary(&mut self) -> Result<Expr, QueryError> {
+        if self.eat('!') {
+            return Ok(Expr::Not(Box::new(self.unary()?)));
+        }
+        if self.eat('(') {
+            let expr = self.or()?;
+            if !self.eat(')') {
+                return Err(self.error("Expected )"));
+            }
+            return Ok(expr);
+        }
+        if self.eat('#') {
+            return self.tag();
+        }
+
+        let start = self.at;
+        let word = self.word();
+        if word.is_empty() {
+            return Err(self.error("Expected a block, tag or coordinate"));
+        }
+
+        let axis = match word {
+            "x" => Some(Axis::X),
+            "y" => Some(Axis::Y),
+            "z" => Some(Axis::Z),
+            _ => None,
+        };
+        if let Some(axis) = axis.filter(|_| matches!(self.peek(), Some('<' | '>' | '=' | '!'))) {
+            return self.coordinate(axis);
+        }
+
+        let name = if word.contains(':') || word.starts_with('*') { word.to_string() } else { format!("minecraft:{word}") };
+        let filters = if self.text[self.at..].starts_with('[') { self.filters()? } else { Vec::new() };
+        if name.ends_with(':') {
+            self.at = start;
+            return Err(self.error("Block name has nothing after its namespace"));
+        }
+        Ok(Expr::Block(BlockPattern { name, filters }))
+    }
+
+    fn tag(&mut self) -> Result<Expr, QueryError> {
+        let start = self.at;
+        let word = self.word();
+        let name = word.strip_prefix("minecraft:").unwrap_or(word);
+        match TAGS.iter().find(|(tag, _)| *tag == name) {
+            Some((_, names)) => Ok(Expr::Tag(names)),
+            None => {
+                let known: Vec<&str> = TAGS.iter().map(|(tag, _)| *tag).collect();
+                Err(QueryError { offset: start, message: format!("Unknown tag #{word}, known ones are {}", known.join(", ")) })
+            },
+        }
+    }
+
+    fn coordinate(&mut self, axis: Axis) -> Result<Expr, QueryError> {
+        let rest = &self.text[self.at..];
+        let (comparison, len) = [
+            ("<=", Comparison::LessOrEqual),
+            (">=", Comparison::GreaterOrEqual),
+            ("!=", Comparison::NotEqual),
+            ("<", Comparison::Less),
+            (">", Comparison::Greater),
+            ("=", Comparison::Equal),
+        ].into_iter()
+            .find(|(op, _)| rest.starts_with(op))
+            .map(|(op, comparison)| (comparison, op.len()))
+            .ok_or_else(|| self.error("Expected a comparison"))?;
+        self.at += len;
+
+        self.peek();
+        let rest = &self.text[self.at..];
+        let len = rest.char_indices()
+            .find(|&(i, c)| !(c.is_ascii_digit() || (i == 0 && c == '-')))
+            .map_or(rest.len(), |(i, _)| i);
+        let value = rest[..len].parse().map_err(|_| self.error("Expected a number"))?;
+        self.at += len;
+        Ok(Expr::Coordinate(axis, comparison, value))
+    }
+
+    // The bracketed filters after a block name, separated by commas
+    fn filters(&mut self) -> Result<Vec<Filter>, QueryError> {
+        let start = self.at;
+        let Some(close) = self.text[self.at..].find(']') else {
+            return Err(self.error("Missing ]"));
+        };
+        let inside = &self.text[self.at + 1..self.at + close];
+        self.at += close + 1;
+
+        let mut filters = Vec::new();
+        for filter in inside.split(',').map(str::trim).filter(|filter| !filter.is_empty()) {
+            let parsed = if let Some((key, value)) = filter.split_once("!=") {
+                Filter::Property { key: key.trim().to_string(), value: value.trim().to_string(), negated: true }
+            } else if let Some((key, value)) = filter.split_once('=') {
+                Filter::Property { key: key.trim().to_string(), value: value.trim().to_string(), negated: false }
+            } else if let Some(text) = filter.strip_prefix('!') {
+                Filter::NameContains { text: text.trim().to_string(), negated: true }
+            } else {
+                Filter::NameContains { text: filter.to_string(), negated: false }
+            };
+            if matches!(&parsed, Filter::Property { key, .. } | Filter::NameContains { text: key, .. } if key.is_empty()) {
+                return Err(QueryError { offset: start, message: format!("Empty filter {filter}") });
+            }
+            filters.push(parsed);
+        }
+        Ok(filters)
+    }
+
+    fn word(&mut self) -> &'a str {
+        self.peek();
+        let rest = &self.text[self.at..];
+        let len = rest.find(|c: char| !(c.is_ascii_alphanumeric() || matches!(c, '_' | ':' | '*' | '.' | '-' | '/'))).unwrap_or(rest.len());
+        self.at += len;
+        &rest[..len]
+    }
+}
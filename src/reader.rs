@@ -0,0 +1,176 @@
+use std::slice::Iter;
+
+use crate::nbt::{ NbtError, NbtFlavor, NextPlusPlus, Tag, TagPayload, MAX_DEPTH };
+
+pub enum NbtEvent<'a> {
+    // A named tag in a compound, or the root tag. Its payload comes next.
+    TagHeader { id: u8, name: &'a str },
+    CompoundStart,
+    CompoundEnd,
+    ListStart { element_id: u8, len: usize },
+    ListEnd,
+    // Any payload that isn't a list or compound
+    Value(TagPayload),
+}
+
+enum Frame {
+    Compound,
+    List { element_id: u8, remaining: usize },
+}
+
+// Walks an NBT document tag by tag without building the tree, so callers can
+// pick out a few fields and skip the rest
+pub struct NbtReader<'a> {
+    iterator: Iter<'a, u8>,
+    input_len: usize,
+    stack: Vec<Frame>,
+    // Id of the payload announced by the last TagHeader
+    pending: Option<u8>,
+    started: bool,
+}
+
+impl<'a> NbtReader<'a> {
+    pub fn new(data: &'a [u8]) -> NbtReader<'a> {
+        NbtReader { iterator: data.iter(), input_len: data.len(), stack: Vec::new(), pending: None, started: false }
+    }
+
+    // Bytes read so far
+    pub fn offset(&self) -> usize {
+        self.input_len - self.iterator.len()
+    }
+
+    // Nesting of the compounds and lists entered so far, 0 at the root
+    pub fn depth(&self) -> usize {
+        self.stack.len()
+    }
+
+    // None once the root tag has been read completely
+    pub fn next_event(&mut self) -> Result<Option<NbtEvent<'a>>, NbtError> {
+        let input_len = self.input_len;
+        self.read_event().map_err(|e| e.rebase(input_len))
+    }
+
+    // Reads the payload that's next, i.e. the one announced by a TagHeader or
+    // the next element of a list, into a TagPayload. None if no payload is next.
+    pub fn read_payload(&mut self) -> Result<Option<TagPayload>, NbtError> {
+        let Some(id) = self.take_next_payload() else {
+            return Ok(None);
+        };
+        let depth = self.stack.len();
+        let input_len = self.input_len;
+        Tag::parse_payload_at(&mut self.iterator, id, depth).map(Some).map_err(|e| e.rebase(input_len))
+    }
+
+    // Skips the payload announced by the last TagHeader. Anywhere else it skips
+    // the rest of the current compound or list, including its end event.
+    pub fn skip(&mut self) -> Result<(), NbtError> {
+        let input_len = self.input_len;
+        self.skip_inner().map_err(|e| e.rebase(input_len))
+    }
+
+    fn skip_inner(&mut self) -> Result<(), NbtError> {
+        let depth = self.stack.len();
+
+        if let Some(id) = self.pending.take() {
+            return Tag::skip_payload_at(&mut self.iterator, id, depth, MAX_DEPTH, NbtFlavor::Java);
+        }
+
+        match self.stack.pop() {
+            Some(Frame::Compound) => {
+                let mut id = self.iterator.next_tag_id()?;
+                while id != 0 {
+                    let name_length = self.iterator.next_u16()?;
+                    self.iterator.skip_n(name_length as usize)?;
+                    Tag::skip_payload_at(&mut self.iterator, id, depth, MAX_DEPTH, NbtFlavor::Java)?;
+                    id = self.iterator.next_tag_id()?;
+                }
+                Ok(())
+            },
+            Some(Frame::List { element_id, remaining }) => {
+                for _ in 0..remaining {
+                    Tag::skip_payload_at(&mut self.iterator, element_id, depth, MAX_DEPTH, NbtFlavor::Java)?;
+                }
+                Ok(())
+            },
+            None => Ok(()),
+        }
+    }
+
+    fn take_next_payload(&mut self) -> Option<u8> {
+        if let Some(id) = self.pending.take() {
+            return Some(id);
+        }
+        match self.stack.last_mut() {
+            Some(Frame::List { element_id, remaining }) if *remaining > 0 => {
+                *remaining -= 1;
+                Some(*element_id)
+            },
+            _ => None,
+        }
+    }
+
+    fn read_event(&mut self) -> Result<Option<NbtEvent<'a>>, NbtError> {
+        if let Some(id) = self.take_next_payload() {
+            return self.start_payload(id).map(Some);
+        }
+
+        match self.stack.last() {
+            None if self.started => Ok(None),
+            None => {
+                self.started = true;
+                let offset = self.iterator.len();
+                let id = self.iterator.next_tag_id()?;
+                if id == 0 {
+                    return Err(NbtError::InvalidTagId { offset, id });
+                }
+                self.read_header(id).map(Some)
+            },
+            Some(Frame::Compound) => {
+                let id = self.iterator.next_tag_id()?;
+                if id == 0 {
+                    self.stack.pop();
+                    return Ok(Some(NbtEvent::CompoundEnd));
+                }
+                self.read_header(id).map(Some)
+            },
+            // Lists with elements left were handled by take_next_payload
+            Some(Frame::List { .. }) => {
+                self.stack.pop();
+                Ok(Some(NbtEvent::ListEnd))
+            },
+        }
+    }
+
+    fn read_header(&mut self, id: u8) -> Result<NbtEvent<'a>, NbtError> {
+        let name_length = self.iterator.next_u16()? as usize;
+        let offset = self.iterator.len();
+        let bytes = self.iterator.as_slice();
+        self.iterator.skip_n(name_length)?;
+        let name = std::str::from_utf8(&bytes[..name_length]).map_err(|_| NbtError::InvalidUtf8 { offset })?;
+
+        self.pending = Some(id);
+        Ok(NbtEvent::TagHeader { id, name })
+    }
+
+    fn start_payload(&mut self, id: u8) -> Result<NbtEvent<'a>, NbtError> {
+        match id {
+            9 | 10 if self.stack.len() >= MAX_DEPTH => Err(NbtError::DepthLimitExceeded { offset: self.iterator.len(), limit: MAX_DEPTH }),
+            9 => {
+                let offset = self.iterator.len();
+                let element_id = self.iterator.next_tag_id()?;
+                let len = self.iterator.next_len()?;
+                if element_id == 0 && len > 0 {
+                    return Err(NbtError::InvalidTagId { offset, id: element_id });
+                }
+
+                self.stack.push(Frame::List { element_id, remaining: len });
+                Ok(NbtEvent::ListStart { element_id, len })
+            },
+            10 => {
+                self.stack.push(Frame::Compound);
+                Ok(NbtEvent::CompoundStart)
+            },
+            _ => Ok(NbtEvent::Value(Tag::parse_payload_at(&mut self.iterator, id, self.stack.len())?)),
+        }
+    }
+}
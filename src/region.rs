@@ -0,0 +1,1053 @@
+use anyhow::{ Result, Context, ensure, bail };
+use std::{fmt, io::{Cursor, Read, Write, Seek, SeekFrom}, fs::{self, File}, path::{Path, PathBuf}, time::SystemTime};
+use flate2::{bufread::{ZlibDecoder, GzDecoder}, write::ZlibEncoder, Compression};
+
+use crate::{ chunk::{ BlockType, Chunk }, height::WorldHeight, nbt::{ Tag, TagPayload, ParseOptions } };
+
+pub fn chunk_loc_to_byte_offset(bytes: [u8; 4]) -> Option<u64> {
+    if bytes[3] == 0 {
+        None
+    } else {
+        Some((((bytes[0] as u64) << 16) + ((bytes[1] as u64) << 8) + (bytes[2] as u64)) * 4096)
+    }
+}
+
+pub fn block_to_chunk_coord(block: i32) -> i32 {
+    block.div_euclid(16)
+}
+
+pub fn chunk_to_region_coord(chunk: i32) -> i32 {
+    chunk.div_euclid(32)
+}
+
+pub fn chunk_index_in_region(chunk_x: i32, chunk_z: i32) -> usize {
+    (chunk_x.rem_euclid(32) + chunk_z.rem_euclid(32) * 32) as usize
+}
+
+// Chunk coordinates of the chunk at index in the region at x, z
+pub fn chunk_at_index(region_x: i32, region_z: i32, index: usize) -> (i32, i32) {
+    (region_x * 32 + (index % 32) as i32, region_z * 32 + (index / 32) as i32)
+}
+
+// Region files are named r.<x>.<z>.mca
+pub fn parse_region_file_name(name: &str) -> Option<(i32, i32)> {
+    let mut parts = name.strip_prefix("r.")?.strip_suffix(".mca")?.split('.');
+    let x = parts.next()?.parse().ok()?;
+    let z = parts.next()?.parse().ok()?;
+    if parts.next().is_some() {
+        return None;
+    }
+    Some((x, z))
+}
+
+pub fn read_chunk_offsets(f: &mut File) -> Result<Vec<u64>> {
+    let mut buf4: [u8; 4] = [0; 4]; 
+
+    let mut chunk_offsets: Vec<u64> = Vec::new();
+
+    f.seek(SeekFrom::Start(0))?;
+
+    for _ in 0..1024 {
+        f.read_exact(&mut buf4)?;
+        let chunk_loc = chunk_loc_to_byte_offset(buf4);
+        // Sparse regions (e.g. the end's outer islands) have holes in the location table
+        if let Some(chunk_loc) = chunk_loc {
+            chunk_offsets.push(chunk_loc);
+        }
+    }
+
+    Ok(chunk_offsets)
+}
+
+pub const COMPRESSION_GZIP: u8 = 1;
+pub const COMPRESSION_ZLIB: u8 = 2;
+pub const COMPRESSION_NONE: u8 = 3;
+pub const COMPRESSION_LZ4: u8 = 4;
+
+// level goes from 0 (store only) to 9 (smallest)
+pub fn compress_zlib(data: &[u8], level: u32) -> Result<Vec<u8>> {
+    let mut encoder = ZlibEncoder::new(Vec::new(), Compression::new(level));
+    encoder.write_all(data)?;
+    Ok(encoder.finish()?)
+}
+
+// Far more than any real chunk needs, a few kilobytes of compressed data
+// could otherwise decompress to gigabytes
+pub const MAX_CHUNK_SIZE: usize = 256 << 20;
+
+pub fn decompress_chunk(compression: u8, data: &[u8]) -> Result<Vec<u8>> {
+    let mut decompressed: Vec<u8> = Vec::new();
+    decompress_chunk_into(compression, data, &mut decompressed)?;
+    Ok(decompressed)
+}
+
+// Like decompress_chunk, but replaces what's in out, keeping its capacity for
+// reading many chunks in a row
+pub fn decompress_chunk_into(compression: u8, data: &[u8], out: &mut Vec<u8>) -> Result<()> {
+    out.clear();
+    // One byte over the limit is enough to tell it was exceeded
+    let limit = MAX_CHUNK_SIZE as u64 + 1;
+
+    match compression {
+        COMPRESSION_GZIP => { GzDecoder::new(data).take(limit).read_to_end(out)?; },
+        COMPRESSION_ZLIB => { ZlibDecoder::new(data).take(limit).read_to_end(out)?; },
+        COMPRESSION_NONE => out.extend_from_slice(data),
+        COMPRESSION_LZ4 => decompress_lz4_blocks(data, out)?,
+        _ => bail!("Unknown chunk compression type {compression}"),
+    }
+    ensure!(out.len() <= MAX_CHUNK_SIZE, "Chunk decompresses to more than {} MiB", MAX_CHUNK_SIZE >> 20);
+
+    Ok(())
+}
+
+// Minecraft writes LZ4 chunks with lz4-java's LZ4BlockOutputStream framing:
+// "LZ4Block" magic, a token byte, compressed/decompressed lengths and a checksum per block
+fn decompress_lz4_blocks(mut data: &[u8], out: &mut Vec<u8>) -> Result<()> {
+    const MAGIC: &[u8] = b"LZ4Block";
+    const HEADER_LEN: usize = MAGIC.len() + 13;
+    const METHOD_RAW: u8 = 0x10;
+    const METHOD_LZ4: u8 = 0x20;
+
+    while !data.is_empty() {
+        ensure!(data.len() >= HEADER_LEN && data.starts_with(MAGIC), "Invalid LZ4 block header");
+
+        let token = data[MAGIC.len()];
+        let read_le = |at: usize| u32::from_le_bytes([data[at], data[at + 1], data[at + 2], data[at + 3]]) as usize;
+        let compressed_len = read_le(MAGIC.len() + 1);
+        let decompressed_len = read_le(MAGIC.len() + 5);
+
+        // A zero length block marks the end of the stream
+        if decompressed_len == 0 {
+            break;
+        }
+        ensure!(out.len() + decompressed_len <= MAX_CHUNK_SIZE, "Chunk decompresses to more than {} MiB", MAX_CHUNK_SIZE >> 20);
+
+        let body = data.get(HEADER_LEN..HEADER_LEN + compressed_len).ok_or_else(|| anyhow::anyhow!("Truncated LZ4 block"))?;
+
+        match token & 0xF0 {
+            METHOD_RAW => out.extend_from_slice(body),
+            METHOD_LZ4 => {
+                let start = out.len();
+                out.resize(start + decompressed_len, 0);
+                let written = lz4_flex::block::decompress_into(body, &mut out[start..])?;
+                ensure!(written == decompressed_len, "LZ4 block decompressed to {written} bytes, expected {decompressed_len}");
+            },
+            method => bail!("Unknown LZ4 block method {method:#x}"),
+        }
+
+        data = &data[HEADER_LEN + compressed_len..];
+    }
+
+    Ok(())
+}
+
+pub fn parse_chunks(f: &mut File, chunk_offsets: &[u64]) -> Result<Vec<Tag>> {
+    let chunks: Vec<(usize, u64, Option<PathBuf>)> = chunk_offsets.iter().enumerate().map(|(i, offset)| (i, *offset, None)).collect();
+    Ok(parse_chunks_from(f, &chunks, &ParseOptions::default(), &mut ChunkBuffer::default(), Ok).log_failures())
+}
+
+// Failures are numbered by their position in chunk_offsets
+pub fn parse_chunks_with(f: &mut File, chunk_offsets: &[u64], options: &ParseOptions) -> ParseReport {
+    let chunks: Vec<(usize, u64, Option<PathBuf>)> = chunk_offsets.iter().enumerate().map(|(i, offset)| (i, *offset, None)).collect();
+    parse_chunks_from(f, &chunks, options, &mut ChunkBuffer::default(), Chunk::from_nbt)
+}
+
+// Flag in the compression type of chunks over 1 MiB, which only keep that byte
+// in the region and are stored in a c.<x>.<z>.mcc file next to it
+pub const COMPRESSION_EXTERNAL: u8 = 128;
+
+// The chunks of a region that could be read, and why the others couldn't.
+// Chunks whose NBT can be read but isn't a valid chunk count as failed too.
+// Regions that don't hold terrain, like entity and POI regions, come as Tags.
+pub struct ParseReport<T = Chunk> {
+    pub ok: Vec<T>,
+    pub failed: Vec<(usize, anyhow::Error)>,
+}
+
+impl<T> ParseReport<T> {
+    // Fails on the first chunk that couldn't be read
+    pub fn into_result(self) -> Result<Vec<T>> {
+        match self.failed.into_iter().next() {
+            Some((i, e)) => Err(e.context(format!("Could not read chunk {i}"))),
+            None => Ok(self.ok),
+        }
+    }
+
+    // Logs the failures and keeps going with the chunks that could be read
+    pub fn log_failures(self) -> Vec<T> {
+        for (i, e) in &self.failed {
+            log::warn!("Could not read chunk {i}: {e:#}");
+        }
+        self.ok
+    }
+}
+
+// Scratch space for reading chunks one after another. The buffers keep the
+// capacity the largest chunk so far needed, so after the first few chunks of
+// a region reading one doesn't allocate anything but the parsed tags.
+#[derive(Default)]
+struct ChunkBuffer {
+    // Compressed, as stored in the region or .mcc file
+    raw: Vec<u8>,
+    decompressed: Vec<u8>,
+}
+
+// Each chunk comes with its index, for reporting, and the path of its .mcc file if it can be located.
+// convert turns the NBT into what the report holds, failing like a chunk that can't be read.
+fn parse_chunks_from<T>(f: &mut (impl Read + Seek), chunk_entries: &[(usize, u64, Option<PathBuf>)], options: &ParseOptions, buffer: &mut ChunkBuffer, mut convert: impl FnMut(Tag) -> Result<T>) -> ParseReport<T> {
+    let mut report = ParseReport { ok: Vec::new(), failed: Vec::new() };
+
+    for (index, chunk_offset, external) in chunk_entries {
+        match parse_chunk(f, *index, *chunk_offset, external.as_deref(), options, buffer).and_then(&mut convert) {
+            Ok(root) => report.ok.push(root),
+            Err(e) => report.failed.push((*index, e)),
+        }
+    }
+
+    log::info!("{}/{} chunks parsed successfully", report.ok.len(), chunk_entries.len());
+
+    report
+}
+
+fn parse_chunk(f: &mut (impl Read + Seek), index: usize, chunk_offset: u64, external: Option<&Path>, options: &ParseOptions, buffer: &mut ChunkBuffer) -> Result<Tag> {
+    let decompressed = read_chunk_data(f, index, chunk_offset, external, buffer)?;
+    Ok(Tag::parse_with(&mut decompressed.iter(), options)?)
+}
+
+// The chunk's NBT, decompressed into the buffer but not parsed yet
+fn read_chunk_data<'a>(f: &mut (impl Read + Seek), index: usize, chunk_offset: u64, external: Option<&Path>, buffer: &'a mut ChunkBuffer) -> Result<&'a [u8]> {
+    let mut compression = read_raw_chunk_into(f, index, chunk_offset, &mut buffer.raw)?;
+
+    if compression & COMPRESSION_EXTERNAL != 0 {
+        let Some(external) = external else {
+            bail!("Chunk is stored in an external .mcc file, which can't be located without the region's file name");
+        };
+        buffer.raw.clear();
+        File::open(external).and_then(|mut file| file.read_to_end(&mut buffer.raw))
+            .with_context(|| format!("Could not read external chunk {}", external.display()))?;
+        compression &= !COMPRESSION_EXTERNAL;
+    }
+
+    decompress_chunk_into(compression, &buffer.raw, &mut buffer.decompressed)?;
+    Ok(&buffer.decompressed)
+}
+
+// The compression type byte and the still compressed data as stored in the region
+fn read_raw_chunk(f: &mut (impl Read + Seek), index: usize, chunk_offset: u64) -> Result<(u8, Vec<u8>)> {
+    let mut chunk_data = Vec::new();
+    let compression = read_raw_chunk_into(f, index, chunk_offset, &mut chunk_data)?;
+    Ok((compression, chunk_data))
+}
+
+fn read_raw_chunk_into(f: &mut (impl Read + Seek), index: usize, chunk_offset: u64, chunk_data: &mut Vec<u8>) -> Result<u8> {
+    let mut buf4: [u8; 4] = [0; 4]; 
+
+    f.seek(SeekFrom::Start(chunk_offset))?;
+
+    f.read_exact(&mut buf4)?;
+    let chunk_length = u32::from_be_bytes(buf4);
+    log::debug!("Chunk {index} has length: {chunk_length}");
+
+    ensure!(chunk_length > 0, "Chunk has zero length");
+
+    let mut buf1: [u8; 1] = [0; 1]; 
+
+    f.read_exact(&mut buf1)?;
+
+    // The length includes the compression type byte read above. It's read
+    // as it comes rather than reserved up front, a corrupt length can be
+    // up to 4 GiB.
+    let expected = chunk_length as usize - 1;
+    chunk_data.clear();
+    f.take(expected as u64).read_to_end(chunk_data)?;
+    ensure!(chunk_data.len() == expected, "Chunk runs past the end of the file");
+
+    Ok(buf1[0])
+}
+
+// Where a chunk is kept if it's too large for its region, None if the region's
+// file name doesn't say where it is
+pub fn external_chunk_path(region: &Path, index: usize) -> Option<PathBuf> {
+    let (x, z) = region.file_name().and_then(|name| name.to_str()).and_then(parse_region_file_name)?;
+    let (chunk_x, chunk_z) = (x * 32 + (index % 32) as i32, z * 32 + (index / 32) as i32);
+    Some(region.with_file_name(format!("c.{chunk_x}.{chunk_z}.mcc")))
+}
+
+// Corrupt chunks are reported and skipped
+pub fn read_region(path: impl AsRef<Path>) -> Result<Vec<Tag>> {
+    read_region_with(path, &ParseOptions::default())
+}
+
+pub fn read_region_with(path: impl AsRef<Path>, options: &ParseOptions) -> Result<Vec<Tag>> {
+    Ok(Region::open(path)?.read_chunk_tags(options).log_failures())
+}
+
+// The location and timestamp tables from the first two sectors of a region
+fn parse_header(header: &[u8]) -> (Vec<(u32, u8)>, Vec<u32>) {
+    let entry = |table: usize, i: usize| -> [u8; 4] {
+        let at = table * SECTOR_SIZE + i * 4;
+        [header[at], header[at + 1], header[at + 2], header[at + 3]]
+    };
+    let locations = (0..1024).map(|i| {
+        let [a, b, c, count] = entry(0, i);
+        (u32::from_be_bytes([0, a, b, c]), count)
+    }).collect();
+    let timestamps = (0..1024).map(|i| u32::from_be_bytes(entry(1, i))).collect();
+    (locations, timestamps)
+}
+
+// Reads the chunks of a region file that's already in memory, saved at or
+// after since if given. The path is only used to find external chunks.
+pub fn read_region_bytes(path: &Path, bytes: &[u8], since: Option<u32>, options: &ParseOptions) -> Result<ParseReport> {
+    ensure!(bytes.len() >= 2 * SECTOR_SIZE, "Region is too short to have a header");
+    let (locations, timestamps) = parse_header(&bytes[..2 * SECTOR_SIZE]);
+
+    let chunk_entries: Vec<(usize, u64, Option<PathBuf>)> = (0..1024)
+        .filter(|&i| locations[i].1 > 0 && since.is_none_or(|since| timestamps[i] >= since))
+        .map(|i| (i, locations[i].0 as u64 * SECTOR_SIZE as u64, external_chunk_path(path, i)))
+        .collect();
+    Ok(parse_chunks_from(&mut Cursor::new(bytes), &chunk_entries, options, &mut ChunkBuffer::default(), Chunk::from_nbt))
+}
+
+// A region file with its two header tables read, the chunks are read on demand
+pub struct Region {
+    file: File,
+    path: PathBuf,
+    // Sector offset and sector count per chunk index, a count of 0 means the chunk was never saved
+    locations: Vec<(u32, u8)>,
+    timestamps: Vec<u32>,
+    buffer: ChunkBuffer,
+}
+
+impl Region {
+    pub fn open(path: impl AsRef<Path>) -> Result<Region> {
+        let path = path.as_ref().to_path_buf();
+        let mut file = File::open(&path)?;
+        let mut header = vec![0u8; 2 * SECTOR_SIZE];
+        file.read_exact(&mut header)?;
+        let (locations, timestamps) = parse_header(&header);
+
+        Ok(Region { file, path, locations, timestamps, buffer: ChunkBuffer::default() })
+    }
+
+    // Last time each chunk was saved, in seconds since the Unix epoch, by chunk index
+    pub fn timestamps(&self) -> &[u32] {
+        &self.timestamps
+    }
+
+    fn offset(&self, index: usize) -> Option<u64> {
+        let (sector, count) = self.locations[index];
+        (count > 0).then_some(sector as u64 * SECTOR_SIZE as u64)
+    }
+
+    pub fn has_chunk(&self, index: usize) -> bool {
+        self.offset(index).is_some()
+    }
+
+    pub fn chunk_offsets(&self) -> Vec<u64> {
+        (0..1024).filter_map(|i| self.offset(i)).collect()
+    }
+
+    fn external_path(&self, index: usize) -> Option<PathBuf> {
+        external_chunk_path(&self.path, index)
+    }
+
+    // None if there's no chunk at that index
+    pub fn read_chunk(&mut self, index: usize, options: &ParseOptions) -> Result<Option<Tag>> {
+        ensure!(index < 1024, "Chunk index {index} is outside the region");
+        let Some(offset) = self.offset(index) else {
+            return Ok(None);
+        };
+        let external = self.external_path(index);
+        parse_chunk(&mut self.file, index, offset, external.as_deref(), options, &mut self.buffer).map(Some)
+    }
+
+    // The chunk's NBT without parsing it, e.g. for TagRef::parse
+    pub fn read_chunk_data(&mut self, index: usize) -> Result<Option<Vec<u8>>> {
+        ensure!(index < 1024, "Chunk index {index} is outside the region");
+        let Some(offset) = self.offset(index) else {
+            return Ok(None);
+        };
+        let external = self.external_path(index);
+        read_chunk_data(&mut self.file, index, offset, external.as_deref(), &mut self.buffer).map(|data| Some(data.to_vec()))
+    }
+
+    // The chunk as stored, without decompressing it. Chunks kept in a .mcc file
+    // only have the compression type with COMPRESSION_EXTERNAL set.
+    pub fn read_raw_chunk(&mut self, index: usize) -> Result<Option<(u8, Vec<u8>)>> {
+        ensure!(index < 1024, "Chunk index {index} is outside the region");
+        let Some(offset) = self.offset(index) else {
+            return Ok(None);
+        };
+        read_raw_chunk(&mut self.file, index, offset).map(Some)
+    }
+
+    // The least height holding every chunk's sections, see
+    // WorldHeight::of_chunk_nbt. None without chunks to tell by, chunks that
+    // can't be read are left out.
+    pub fn world_height(&mut self) -> Option<WorldHeight> {
+        let mut height: Option<WorldHeight> = None;
+        for index in 0..1024 {
+            let Ok(Some(chunk)) = self.read_chunk(index, &ParseOptions::for_block_search()) else {
+                continue;
+            };
+            if let Some(chunk_height) = WorldHeight::of_chunk_nbt(&chunk.payload) {
+                height = Some(height.map_or(chunk_height, |height| height.union(chunk_height)));
+            }
+        }
+        height
+    }
+
+    // Failures are numbered by chunk index
+    pub fn read_chunks(&mut self, options: &ParseOptions) -> ParseReport {
+        self.read_chunks_where(options, None, |_| true)
+    }
+
+    // Only reads the chunks saved at or after the given Unix time
+    pub fn read_chunks_modified_since(&mut self, since: u32, options: &ParseOptions) -> ParseReport {
+        self.read_chunks_where(options, Some(since), |_| true)
+    }
+
+    // The chunks saved at or after since, if given, with only the sections
+    // that have a palette entry keep wants, see Chunk::from_nbt_where
+    pub fn read_chunks_where(&mut self, options: &ParseOptions, since: Option<u32>, keep: impl Fn(&BlockType) -> bool) -> ParseReport {
+        self.read_chunks_at(options, since, |_| true, keep)
+    }
+
+    // Like read_chunks_where, leaving out the chunks at indices wanted says no to
+    pub fn read_chunks_at(&mut self, options: &ParseOptions, since: Option<u32>, wanted: impl Fn(usize) -> bool, keep: impl Fn(&BlockType) -> bool) -> ParseReport {
+        self.read_since(options, since, wanted, |tag| Chunk::from_nbt_where(tag, &keep))
+    }
+
+    // The NBT of every chunk, for regions that don't hold terrain or to
+    // look at chunks as stored
+    pub fn read_chunk_tags(&mut self, options: &ParseOptions) -> ParseReport<Tag> {
+        self.read_since(options, None, |_| true, Ok)
+    }
+
+    fn read_since<T>(&mut self, options: &ParseOptions, since: Option<u32>, wanted: impl Fn(usize) -> bool, convert: impl FnMut(Tag) -> Result<T>) -> ParseReport<T> {
+        let chunk_entries: Vec<(usize, u64, Option<PathBuf>)> = (0..1024)
+            .filter(|i| wanted(*i) && since.is_none_or(|since| self.timestamps[*i] >= since))
+            .filter_map(|i| Some((i, self.offset(i)?, self.external_path(i))))
+            .collect();
+        parse_chunks_from(&mut self.file, &chunk_entries, options, &mut self.buffer, convert)
+    }
+
+    // Checks the location table, then reads every chunk and checks that it's
+    // stored at the index its coordinates belong to
+    pub fn check(&mut self) -> Result<Vec<RegionProblem>> {
+        let mut problems = Vec::new();
+        let file_sectors = self.file.metadata()?.len().div_ceil(SECTOR_SIZE as u64);
+
+        let mut in_use = Vec::new();
+        for (index, (sector, count)) in self.locations.iter().enumerate() {
+            let (start, end) = (*sector as u64, *sector as u64 + *count as u64);
+            if *count == 0 {
+                continue;
+            } else if start < 2 {
+                problems.push(RegionProblem::InHeader { index });
+            } else if end > file_sectors {
+                problems.push(RegionProblem::PastEndOfFile { index });
+            } else {
+                in_use.push((start, end, index));
+            }
+        }
+
+        in_use.sort();
+        let mut furthest: Option<(u64, usize)> = None;
+        for (start, end, index) in &in_use {
+            if let Some((furthest_end, other)) = furthest {
+                if *start < furthest_end {
+                    problems.push(RegionProblem::Overlap { index: *index, other });
+                }
+            }
+            if furthest.is_none_or(|(furthest_end, _)| *end > furthest_end) {
+                furthest = Some((*end, *index));
+            }
+        }
+
+        // Only the position is needed, so leave out everything big
+        let options = ParseOptions::new()
+            .skip("sections")
+            .skip("Sections")
+            .skip("block_entities")
+            .skip("TileEntities")
+            .skip("Entities")
+            .skip("Heightmaps");
+        let region = self.path.file_name().and_then(|name| name.to_str()).and_then(parse_region_file_name);
+
+        for (_, _, index) in in_use {
+            let chunk = match self.read_chunk(index, &options) {
+                Ok(chunk) => chunk,
+                Err(error) => {
+                    problems.push(RegionProblem::Unreadable { index, error });
+                    continue;
+                },
+            };
+
+            let Some((x, z)) = chunk.as_ref().and_then(|chunk| chunk_position(&chunk.payload)) else {
+                problems.push(RegionProblem::NoPosition { index });
+                continue;
+            };
+            let belongs_here = match region {
+                Some((region_x, region_z)) => chunk_to_region_coord(x) == region_x && chunk_to_region_coord(z) == region_z && chunk_index_in_region(x, z) == index,
+                None => chunk_index_in_region(x, z) == index,
+            };
+            if !belongs_here {
+                problems.push(RegionProblem::WrongPosition { index, x, z });
+            }
+        }
+
+        problems.sort_by_key(RegionProblem::index);
+        Ok(problems)
+    }
+}
+
+// Rewrites a region with its chunks packed back to back. map gets each chunk's
+// index, compression type and data as stored, and returns what to store instead,
+// or None to drop the chunk. The region is deleted once it's empty. Returns how
+// many chunks are left.
+pub fn rewrite_region(path: impl AsRef<Path>, mut map: impl FnMut(usize, u8, Vec<u8>) -> Result<Option<(u8, Vec<u8>)>>) -> Result<usize> {
+    let path = path.as_ref();
+    let mut region = Region::open(path)?;
+    let mut writer = RegionWriter::new();
+    let mut left = 0;
+
+    for index in 0..1024 {
+        let Some((compression, data)) = region.read_raw_chunk(index).with_context(|| format!("Could not read chunk {index}"))? else {
+            continue;
+        };
+        if let Some((compression, data)) = map(index, compression, data)? {
+            writer.set_raw_chunk(index, compression, data, region.timestamps[index])?;
+            left += 1;
+        }
+    }
+    drop(region);
+
+    if writer.is_empty() {
+        fs::remove_file(path)?;
+        return Ok(0);
+    }
+
+    replace_region_file(path, &writer, &[])?;
+    Ok(left)
+}
+
+// Writes next to the region first so a failure can't leave it half written.
+// Chunks too large for a region entry go to their .mcc file, like the game
+// does. Afterwards the .mcc files of the stale chunks are removed, unless
+// they were just written.
+fn replace_region_file(path: &Path, writer: &RegionWriter, stale: &[usize]) -> Result<()> {
+    let mut external = Vec::new();
+    for (index, data) in writer.oversized_chunks() {
+        let Some(external_path) = external_chunk_path(path, index) else {
+            bail!("Chunk {index} is too large for the region and {} doesn't say where its .mcc file goes", path.display());
+        };
+        fs::write(&external_path, data).with_context(|| format!("Could not write {}", external_path.display()))?;
+        external.push(index);
+    }
+
+    let temporary = path.with_extension("mca.tmp");
+    let mut file = File::create(&temporary)?;
+    writer.write_with_external(&mut file, &external)?;
+    file.sync_all()?;
+    fs::rename(&temporary, path)?;
+
+    let stale: Vec<usize> = stale.iter().copied().filter(|index| !external.contains(index)).collect();
+    remove_external_chunks(path, &stale)
+}
+
+// Rewrites a region without the chunks at the given indices, leaving no gaps
+// behind. The .mcc files of removed chunks go too, once the region is written.
+// Returns how many chunks are left.
+pub fn remove_chunks(path: impl AsRef<Path>, indices: &[usize]) -> Result<usize> {
+    let path = path.as_ref();
+    let mut removed = Vec::new();
+    let left = rewrite_region(path, |index, compression, data| {
+        if !indices.contains(&index) {
+            return Ok(Some((compression, data)));
+        }
+        removed.push(index);
+        Ok(None)
+    })?;
+    remove_external_chunks(path, &removed)?;
+    Ok(left)
+}
+
+// Deletes the .mcc files of chunks that are now stored in the region, or gone
+fn remove_external_chunks(path: &Path, indices: &[usize]) -> Result<()> {
+    for &index in indices {
+        if let Some(external) = external_chunk_path(path, index).filter(|external| external.is_file()) {
+            fs::remove_file(&external).with_context(|| format!("Could not remove {}", external.display()))?;
+        }
+    }
+    Ok(())
+}
+
+// Hands the chunks at the given indices to edit, which returns whether it
+// changed the chunk. Changed chunks are written back with the current time as
+// their timestamp, the rest stay as they were stored, as do chunks that
+// can't be read. The region is only
+// rewritten if something changed. Returns how many chunks changed.
+pub fn edit_chunks(path: impl AsRef<Path>, indices: &[usize], mut edit: impl FnMut(usize, &mut Tag) -> Result<bool>) -> Result<usize> {
+    let path = path.as_ref();
+    let mut region = Region::open(path)?;
+    let mut changed = Vec::new();
+
+    for &index in indices {
+        let mut chunk = match region.read_chunk(index, &ParseOptions::default()) {
+            Ok(Some(chunk)) => chunk,
+            Ok(None) => continue,
+            Err(e) => {
+                log::warn!("Leaving unreadable chunk {index} as it is: {e:#}");
+                continue;
+            },
+        };
+        if edit(index, &mut chunk)? {
+            changed.push((index, chunk));
+        }
+    }
+
+    if changed.is_empty() {
+        return Ok(0);
+    }
+
+    let mut writer = RegionWriter::new();
+    for index in 0..1024 {
+        let Some((compression, data)) = region.read_raw_chunk(index).with_context(|| format!("Could not read chunk {index}"))? else {
+            continue;
+        };
+        writer.set_raw_chunk(index, compression, data, region.timestamps[index])?;
+    }
+    drop(region);
+
+    let now = unix_now();
+    for (index, chunk) in &changed {
+        writer.set_chunk(*index, chunk, now)?;
+    }
+    // The changed chunks are back in the region, so any .mcc files they had are stale
+    let changed: Vec<usize> = changed.iter().map(|(index, _)| *index).collect();
+    replace_region_file(path, &writer, &changed)?;
+
+    Ok(changed.len())
+}
+
+// Stores the chunk at index, replacing whatever was there with it, and
+// creates the region if there's none yet. The chunk gets the current time as
+// its timestamp and a stale .mcc file of the old one is removed.
+pub fn put_chunk(path: impl AsRef<Path>, index: usize, chunk: &Tag) -> Result<()> {
+    put_chunks(path, &[(index, chunk)])
+}
+
+// Like put_chunk for many chunks of the same region, rewriting it only once
+pub fn put_chunks(path: impl AsRef<Path>, chunks: &[(usize, &Tag)]) -> Result<()> {
+    let path = path.as_ref();
+    if let Some((index, _)) = chunks.iter().find(|(index, _)| *index >= 1024) {
+        bail!("Chunk index {index} is outside the region");
+    }
+    let mut writer = RegionWriter::new();
+
+    if path.exists() {
+        let mut region = Region::open(path)?;
+        for other in (0..1024).filter(|other| !chunks.iter().any(|(index, _)| index == other)) {
+            let Some((compression, data)) = region.read_raw_chunk(other).with_context(|| format!("Could not read chunk {other}"))? else {
+                continue;
+            };
+            writer.set_raw_chunk(other, compression, data, region.timestamps[other])?;
+        }
+    }
+    let now = unix_now();
+    for (index, chunk) in chunks {
+        writer.set_chunk(*index, chunk, now)?;
+    }
+    let indices: Vec<usize> = chunks.iter().map(|(index, _)| *index).collect();
+    replace_region_file(path, &writer, &indices)
+}
+
+// What restore_chunks did with each chunk it was asked to restore
+#[derive(Default)]
+pub struct RestoreReport {
+    pub restored: Vec<usize>,
+    // Chunks the backup has no readable copy of, left as they were stored
+    pub kept: Vec<usize>,
+    // Chunks without a copy that couldn't even be read as stored, so they're
+    // gone and the game generates them anew
+    pub dropped: Vec<usize>,
+}
+
+// Rewrites a region with the chunks at the given indices taken from backup,
+// a copy of the same region from earlier. The chunks of the backup are parsed
+// first so a broken copy can't replace a broken chunk, they keep the backup's
+// timestamp. Nothing is written if none of the chunks changed.
+pub fn restore_chunks(path: impl AsRef<Path>, indices: &[usize], backup: &mut Region) -> Result<RestoreReport> {
+    let path = path.as_ref();
+    let mut region = Region::open(path)?;
+    let mut writer = RegionWriter::new();
+    let mut report = RestoreReport::default();
+
+    for index in 0..1024 {
+        if indices.contains(&index) {
+            if let Ok(Some(chunk)) = backup.read_chunk(index, &ParseOptions::default()) {
+                writer.set_chunk(index, &chunk, backup.timestamps[index])?;
+                report.restored.push(index);
+            } else if let Ok(Some((compression, data))) = region.read_raw_chunk(index) {
+                writer.set_raw_chunk(index, compression, data, region.timestamps[index])?;
+                report.kept.push(index);
+            } else {
+                report.dropped.push(index);
+            }
+        } else if let Some((compression, data)) = region.read_raw_chunk(index).with_context(|| format!("Could not read chunk {index}"))? {
+            writer.set_raw_chunk(index, compression, data, region.timestamps[index])?;
+        }
+    }
+    drop(region);
+
+    if report.restored.is_empty() && report.dropped.is_empty() {
+        return Ok(report);
+    }
+    // Restored chunks replace whatever .mcc files the broken ones had
+    replace_region_file(path, &writer, &report.restored)?;
+    Ok(report)
+}
+
+fn unix_now() -> u32 {
+    SystemTime::now().duration_since(SystemTime::UNIX_EPOCH).map_or(0, |time| time.as_secs() as u32)
+}
+
+// Chunk position of terrain chunks, old or new, and of entity chunks
+pub fn chunk_position(root: &TagPayload) -> Option<(i32, i32)> {
+    if let Some(TagPayload::IntArray(position)) = root.get("Position") {
+        return match position.as_slice() {
+            [x, z] => Some((*x, *z)),
+            _ => None,
+        };
+    }
+
+    let level = root.get("Level").unwrap_or(root);
+    match (level.get("xPos"), level.get("zPos")) {
+        (Some(TagPayload::Int(x)), Some(TagPayload::Int(z))) => Some((*x, *z)),
+        _ => None,
+    }
+}
+
+pub enum RegionProblem {
+    // The location points into the two header sectors
+    InHeader { index: usize },
+    PastEndOfFile { index: usize },
+    // Shares sectors with the chunk at other
+    Overlap { index: usize, other: usize },
+    Unreadable { index: usize, error: anyhow::Error },
+    NoPosition { index: usize },
+    // The chunk's own coordinates belong to another index or region
+    WrongPosition { index: usize, x: i32, z: i32 },
+}
+
+impl RegionProblem {
+    pub fn index(&self) -> usize {
+        match self {
+            RegionProblem::InHeader { index }
+            | RegionProblem::PastEndOfFile { index }
+            | RegionProblem::Overlap { index, .. }
+            | RegionProblem::Unreadable { index, .. }
+            | RegionProblem::NoPosition { index }
+            | RegionProblem::WrongPosition { index, .. } => *index,
+        }
+    }
+}
+
+impl fmt::Display for RegionProblem {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            RegionProblem::InHeader { index } => write!(f, "chunk {index} points into the region header"),
+            RegionProblem::PastEndOfFile { index } => write!(f, "chunk {index} ends past the end of the file"),
+            RegionProblem::Overlap { index, other } => write!(f, "chunk {index} overlaps chunk {other}"),
+            RegionProblem::Unreadable { index, error } => write!(f, "chunk {index} can't be read: {error:#}"),
+            RegionProblem::NoPosition { index } => write!(f, "chunk {index} has no position"),
+            RegionProblem::WrongPosition { index, x, z } => write!(f, "chunk {index} says it is chunk ({x}, {z}), which isn't stored there"),
+        }
+    }
+}
+
+const SECTOR_SIZE: usize = 4096;
+
+pub struct RegionWriter {
+    // Compression type, compressed payload and last-modified timestamp per chunk slot
+    chunks: Vec<Option<(u8, Vec<u8>, u32)>>,
+}
+
+impl Default for RegionWriter {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl RegionWriter {
+    pub fn new() -> RegionWriter {
+        RegionWriter { chunks: vec![None; 1024] }
+    }
+
+    pub fn set_chunk(&mut self, index: usize, chunk: &Tag, timestamp: u32) -> Result<()> {
+        ensure!(index < 1024, "Chunk index {index} is outside the region");
+
+        // Tag::write does many small writes, which are slow to feed to the encoder one by one
+        let mut raw = Vec::new();
+        chunk.write(&mut raw)?;
+
+        self.chunks[index] = Some((COMPRESSION_ZLIB, compress_zlib(&raw, Compression::default().level())?, timestamp));
+
+        Ok(())
+    }
+
+    // Stores an already compressed chunk as is, e.g. one from Region::read_raw_chunk
+    pub fn set_raw_chunk(&mut self, index: usize, compression: u8, data: Vec<u8>, timestamp: u32) -> Result<()> {
+        ensure!(index < 1024, "Chunk index {index} is outside the region");
+        self.chunks[index] = Some((compression, data, timestamp));
+        Ok(())
+    }
+
+    pub fn remove_chunk(&mut self, index: usize) {
+        if index < 1024 {
+            self.chunks[index] = None;
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.chunks.iter().all(Option::is_none)
+    }
+
+    // Chunks over 255 sectors can't be written with write, the functions
+    // above that rewrite region files put them in .mcc files instead
+    pub fn write(&self, w: &mut impl Write) -> Result<()> {
+        self.write_with_external(w, &[])
+    }
+
+    // The chunks that need more sectors than a region entry can address, and
+    // aren't already stored externally
+    fn oversized_chunks(&self) -> impl Iterator<Item = (usize, &[u8])> {
+        self.chunks.iter().enumerate().filter_map(|(i, chunk)| match chunk {
+            Some((compression, data, _)) if compression & COMPRESSION_EXTERNAL == 0 && sectors_for(data.len()) > 255 => Some((i, data.as_slice())),
+            _ => None,
+        })
+    }
+
+    // The chunks at the external indices only get their compression type with
+    // COMPRESSION_EXTERNAL set, their data has to be in their .mcc files
+    fn write_with_external(&self, w: &mut impl Write, external: &[usize]) -> Result<()> {
+        let mut locations = [0u8; SECTOR_SIZE];
+        let mut timestamps = [0u8; SECTOR_SIZE];
+        // What each slot keeps in the region itself
+        let entries: Vec<Option<(u8, &[u8], u32)>> = self.chunks.iter().enumerate().map(|(i, chunk)| {
+            let (compression, data, timestamp) = chunk.as_ref()?;
+            Some(match external.contains(&i) {
+                true => (compression | COMPRESSION_EXTERNAL, &[][..], *timestamp),
+                false => (*compression, data.as_slice(), *timestamp),
+            })
+        }).collect();
+
+        // The two header tables take up the first two sectors
+        let mut next_sector = 2;
+
+        for (i, entry) in entries.iter().enumerate() {
+            if let Some((_, data, timestamp)) = entry {
+                let sector_count = sectors_for(data.len());
+                if sector_count > 255 {
+                    bail!("Chunk {i} needs {sector_count} sectors, more than a region entry can address");
+                }
+                if next_sector > 0xFFFFFF {
+                    bail!("Region file is too large to address chunk {i}");
+                }
+
+                locations[i * 4..i * 4 + 3].copy_from_slice(&(next_sector as u32).to_be_bytes()[1..]);
+                locations[i * 4 + 3] = sector_count as u8;
+                timestamps[i * 4..i * 4 + 4].copy_from_slice(&timestamp.to_be_bytes());
+
+                next_sector += sector_count;
+            }
+        }
+
+        w.write_all(&locations)?;
+        w.write_all(&timestamps)?;
+
+        for (compression, data, _) in entries.iter().flatten() {
+            // Length counts the compression type byte too
+            w.write_all(&(data.len() as u32 + 1).to_be_bytes())?;
+            w.write_all(&[*compression])?;
+            w.write_all(data)?;
+
+            let written = data.len() + 5;
+            let padding = sectors_for(data.len()) * SECTOR_SIZE - written;
+            w.write_all(&vec![0u8; padding])?;
+        }
+
+        Ok(())
+    }
+}
+
+fn sectors_for(compressed_len: usize) -> usize {
+    (compressed_len + 5).div_ceil(SECTOR_SIZE)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn maps_coordinates_around_1000_blocks_to_regions() {
+        assert_eq!(block_to_chunk_coord(1000), 62);
+        assert_eq!(block_to_chunk_coord(1007), 62);
+        assert_eq!(block_to_chunk_coord(1008), 63);
+        assert_eq!(block_to_chunk_coord(-1000), -63);
+        assert_eq!(block_to_chunk_coord(-1008), -63);
+        assert_eq!(block_to_chunk_coord(-1009), -64);
+
+        assert_eq!(chunk_to_region_coord(62), 1);
+        assert_eq!(chunk_to_region_coord(64), 2);
+        assert_eq!(chunk_to_region_coord(-63), -2);
+        assert_eq!(chunk_to_region_coord(-65), -3);
+
+        // -63 is 1 past the start of region -2, 62 is 30 past the start of region 1
+        assert_eq!(chunk_index_in_region(-63, 62), 1 + 30 * 32);
+        assert_eq!(chunk_index_in_region(-64, -64), 0);
+        assert_eq!(chunk_index_in_region(-1, -1), 1023);
+    }
+
+    #[test]
+    fn parses_negative_region_file_names() {
+        assert_eq!(parse_region_file_name("r.-2.1.mca"), Some((-2, 1)));
+        assert_eq!(parse_region_file_name("r.-2.1.mcc"), None);
+        assert_eq!(parse_region_file_name("r.-2.1.0.mca"), None);
+    }
+
+    fn tag(name: &str, payload: TagPayload) -> Tag {
+        Tag { name: name.to_string(), payload }
+    }
+
+    fn chunk_tag(x: i32, z: i32) -> Tag {
+        let root = vec![tag("DataVersion", TagPayload::Int(3700)), tag("xPos", TagPayload::Int(x)), tag("zPos", TagPayload::Int(z))];
+        tag("", TagPayload::Compound(root.into()))
+    }
+
+    #[test]
+    fn reports_unreadable_and_invalid_chunks_with_their_index() {
+        let mut writer = RegionWriter::new();
+        writer.set_chunk(0, &chunk_tag(0, 0), 100).unwrap();
+        // Valid NBT, but not a chunk
+        writer.set_chunk(1, &tag("", TagPayload::Compound(vec![tag("DataVersion", TagPayload::Int(3700))].into())), 100).unwrap();
+        writer.set_raw_chunk(2, COMPRESSION_ZLIB, vec![1, 2, 3, 4], 100).unwrap();
+        writer.set_chunk(33, &chunk_tag(1, 1), 200).unwrap();
+        let mut bytes = Vec::new();
+        writer.write(&mut bytes).unwrap();
+
+        let report = read_region_bytes(Path::new("r.0.0.mca"), &bytes, None, &ParseOptions::default()).unwrap();
+        let positions: Vec<(i32, i32)> = report.ok.iter().map(|chunk| (chunk.x(), chunk.z())).collect();
+        assert_eq!(positions, vec![(0, 0), (1, 1)]);
+        let failed: Vec<usize> = report.failed.iter().map(|(i, _)| *i).collect();
+        assert_eq!(failed, vec![1, 2]);
+        assert!(report.into_result().is_err());
+
+        let report = read_region_bytes(Path::new("r.0.0.mca"), &bytes, Some(150), &ParseOptions::default()).unwrap();
+        assert_eq!(report.ok.len(), 1);
+        assert!(report.failed.is_empty());
+    }
+
+    #[test]
+    fn writes_regions_the_reader_and_check_accept() {
+        // Random longs don't compress, so this chunk takes several sectors
+        let mut seed = 1u64;
+        let noise: Vec<i64> = (0..3000).map(|_| {
+            seed = seed.wrapping_mul(6364136223846793005).wrapping_add(1442695040888963407);
+            seed as i64
+        }).collect();
+        let mut big = chunk_tag(-1, 0);
+        if let TagPayload::Compound(root) = &mut big.payload {
+            root.push(tag("noise", TagPayload::LongArray(noise)));
+        }
+
+        let mut writer = RegionWriter::new();
+        writer.set_chunk(chunk_index_in_region(-32, 0), &chunk_tag(-32, 0), 100).unwrap();
+        writer.set_chunk(chunk_index_in_region(-1, 0), &big, 200).unwrap();
+        writer.set_chunk(chunk_index_in_region(-31, 2), &chunk_tag(-31, 2), 300).unwrap();
+        writer.set_chunk(chunk_index_in_region(-30, 2), &chunk_tag(-30, 2), 300).unwrap();
+        writer.remove_chunk(chunk_index_in_region(-30, 2));
+        assert!(writer.set_chunk(1024, &chunk_tag(0, 0), 0).is_err());
+        let mut bytes = Vec::new();
+        writer.write(&mut bytes).unwrap();
+        assert_eq!(bytes.len() % SECTOR_SIZE, 0);
+
+        // Sectors follow one another in chunk index order, right after the header
+        let (locations, timestamps) = parse_header(&bytes);
+        let (first, big_slot, last) = (locations[0], locations[31], locations[65]);
+        assert_eq!(first, (2, 1));
+        assert_eq!(big_slot.0, 3);
+        assert!(big_slot.1 > 1);
+        assert_eq!(last, (3 + big_slot.1 as u32, 1));
+        assert_eq!(bytes.len(), (last.0 as usize + 1) * SECTOR_SIZE);
+        assert_eq!((timestamps[0], timestamps[31], timestamps[65], timestamps[66]), (100, 200, 300, 0));
+
+        let dir = std::env::temp_dir().join(format!("path-miner-region-writer-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("r.-1.0.mca");
+        fs::write(&path, &bytes).unwrap();
+        let mut region = Region::open(&path).unwrap();
+        assert!(region.check().unwrap().is_empty());
+        let read = region.read_chunk(31, &ParseOptions::default()).unwrap().unwrap();
+        assert!(matches!(read.payload.get("noise"), Some(TagPayload::LongArray(noise)) if noise.len() == 3000));
+
+        // Raw chunks are copied over as they are
+        let (compression, data) = region.read_raw_chunk(65).unwrap().unwrap();
+        let mut copy = RegionWriter::new();
+        copy.set_raw_chunk(65, compression, data, 300).unwrap();
+        let mut copied = Vec::new();
+        copy.write(&mut copied).unwrap();
+        let report = read_region_bytes(&path, &copied, None, &ParseOptions::default()).unwrap();
+        assert_eq!(report.ok.iter().map(|chunk| (chunk.x(), chunk.z())).collect::<Vec<_>>(), vec![(-31, 2)]);
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn keeps_chunks_too_large_for_the_region_in_mcc_files() {
+        // 1.2 MB of noise doesn't fit the 255 sectors a region entry can address
+        let mut seed = 7u64;
+        let noise: Vec<i64> = (0..150_000).map(|_| {
+            seed = seed.wrapping_mul(6364136223846793005).wrapping_add(1442695040888963407);
+            seed as i64
+        }).collect();
+        let mut huge = chunk_tag(33, 1);
+        if let TagPayload::Compound(root) = &mut huge.payload {
+            root.push(tag("noise", TagPayload::LongArray(noise)));
+        }
+        let index = chunk_index_in_region(33, 1);
+        let mut writer = RegionWriter::new();
+        writer.set_chunk(index, &huge, 100).unwrap();
+        assert!(writer.write(&mut Vec::new()).is_err());
+
+        let dir = std::env::temp_dir().join(format!("path-miner-region-mcc-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("r.1.0.mca");
+        let external = dir.join("c.33.1.mcc");
+        put_chunks(&path, &[(index, &huge), (chunk_index_in_region(32, 0), &chunk_tag(32, 0))]).unwrap();
+        assert!(external.is_file());
+        assert_eq!(fs::metadata(&path).unwrap().len(), 4 * SECTOR_SIZE as u64);
+
+        let mut region = Region::open(&path).unwrap();
+        assert!(region.check().unwrap().is_empty());
+        let read = region.read_chunk(index, &ParseOptions::default()).unwrap().unwrap();
+        assert!(matches!(read.payload.get("noise"), Some(TagPayload::LongArray(noise)) if noise.len() == 150_000));
+        assert_eq!(region.read_raw_chunk(index).unwrap().unwrap(), (COMPRESSION_ZLIB | COMPRESSION_EXTERNAL, Vec::new()));
+        drop(region);
+
+        // Editing another chunk leaves the external one where it is
+        assert_eq!(edit_chunks(&path, &[chunk_index_in_region(32, 0)], |_, _| Ok(true)).unwrap(), 1);
+        assert!(external.is_file());
+        assert_eq!(Region::open(&path).unwrap().read_chunks(&ParseOptions::default()).ok.len(), 2);
+
+        // Once it's small again it moves back into the region
+        put_chunk(&path, index, &chunk_tag(33, 1)).unwrap();
+        assert!(!external.exists());
+        let mut region = Region::open(&path).unwrap();
+        assert!(region.read_chunk(index, &ParseOptions::default()).unwrap().unwrap().payload.get("noise").is_none());
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
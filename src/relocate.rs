@@ -0,0 +1,105 @@
+use crate::nbt::TagPayload;
+
+// Moves a terrain chunk over by dx and dz chunks: its own coordinates, its
+// block entities and scheduled ticks, and before 1.17 its entities too.
+// Structures are dropped, their bounding boxes would still be where the chunk
+// came from.
+pub fn relocate_chunk(root: &mut TagPayload, dx: i32, dz: i32) {
+    let old = root.get("Level").is_some();
+    let Some(level) = (if old { root.get_mut("Level") } else { Some(root) }) else {
+        return;
+    };
+
+    shift_int(level, "xPos", dx);
+    shift_int(level, "zPos", dz);
+    let Some(level) = level.try_as_compound() else {
+        return;
+    };
+    level.remove(if old { "Structures" } else { "structures" });
+
+    let (bx, bz) = (dx * 16, dz * 16);
+    for name in ["block_entities", "TileEntities", "block_ticks", "fluid_ticks", "TileTicks", "LiquidTicks"] {
+        for entry in list_mut(level.get_mut(name)) {
+            shift_int(entry, "x", bx);
+            shift_int(entry, "z", bz);
+        }
+    }
+    for entity in list_mut(level.get_mut("Entities")) {
+        relocate_entity(entity, bx, bz);
+    }
+}
+
+// Moves a chunk from entities/, which the game has kept apart since 1.17
+pub fn relocate_entity_chunk(root: &mut TagPayload, dx: i32, dz: i32) {
+    if let Some(TagPayload::IntArray(position)) = root.get_mut("Position") {
+        if let [x, z] = position.as_mut_slice() {
+            *x += dx;
+            *z += dz;
+        }
+    }
+    for entity in list_mut(root.get_mut("Entities")) {
+        relocate_entity(entity, dx * 16, dz * 16);
+    }
+}
+
+// Moves a chunk from poi/, whose records are kept by block position per section
+pub fn relocate_poi_chunk(root: &mut TagPayload, dx: i32, dz: i32) {
+    let Some(TagPayload::Compound(sections)) = root.get_mut("Sections") else {
+        return;
+    };
+    for section in sections.iter_mut() {
+        for record in list_mut(section.payload.get_mut("Records")) {
+            if let Some(TagPayload::IntArray(pos)) = record.get_mut("pos") {
+                if let [x, _, z] = pos.as_mut_slice() {
+                    *x += dx * 16;
+                    *z += dz * 16;
+                }
+            }
+        }
+    }
+}
+
+// Moves an entity and whatever rides it by blocks. The copy gets a UUID of
+// its own, two entities with the same one can't be loaded together.
+fn relocate_entity(entity: &mut TagPayload, bx: i32, bz: i32) {
+    if let Some(TagPayload::List(pos)) = entity.get_mut("Pos") {
+        if let [TagPayload::Double(x), _, TagPayload::Double(z)] = pos.as_mut_slice() {
+            *x += bx as f64;
+            *z += bz as f64;
+        }
+    }
+    // Paintings and item frames also keep the block they hang on
+    shift_int(entity, "TileX", bx);
+    shift_int(entity, "TileZ", bz);
+
+    let salt = (bx as i64) << 32 | (bz as u32 as i64);
+    match entity.get_mut("UUID") {
+        Some(TagPayload::IntArray(uuid)) if uuid.len() == 4 => {
+            uuid[2] ^= (salt >> 32) as i32;
+            uuid[3] ^= salt as i32;
+        },
+        _ => {
+            if let Some(TagPayload::Long(least)) = entity.get_mut("UUIDLeast") {
+                *least ^= salt;
+            }
+        },
+    }
+
+    for passenger in list_mut(entity.get_mut("Passengers")) {
+        relocate_entity(passenger, bx, bz);
+    }
+}
+
+fn shift_int(compound: &mut TagPayload, name: &str, by: i32) {
+    if let Some(TagPayload::Int(value)) = compound.get_mut(name) {
+        *value += by;
+    }
+}
+
+fn list_mut(list: Option<&mut TagPayload>) -> impl Iterator<Item = &mut TagPayload> {
+    let list = match list {
+        Some(TagPayload::List(list)) => Some(list),
+        _ => None,
+    };
+    list.into_iter().flatten()
+}
@@ -0,0 +1,596 @@
+use anyhow::{ Result, Context, anyhow, bail };
+use serde::Deserialize;
+use std::{collections::HashMap, fs::{self, File}, hash::{Hash, Hasher}, io::{BufReader, BufWriter, Read, Write}, path::Path};
+
+use crate::block_id::BlockId;
+use crate::chunk::{ Chunk, HeightmapKind };
+
+pub type Rgba = [u8; 4];
+
+// Blended over slime chunks on maps
+pub const SLIME_TINT: Rgba = [40, 200, 40, 110];
+
+pub struct Image {
+    pub width: usize,
+    pub height: usize,
+    // Row-major RGBA
+    pub pixels: Vec<u8>,
+}
+
+impl Image {
+    pub fn new(width: usize, height: usize) -> Image {
+        Image { width, height, pixels: vec![0; width * height * 4] }
+    }
+
+    pub fn get(&self, x: usize, y: usize) -> Rgba {
+        let i = (y * self.width + x) * 4;
+        [self.pixels[i], self.pixels[i + 1], self.pixels[i + 2], self.pixels[i + 3]]
+    }
+
+    pub fn set(&mut self, x: usize, y: usize, color: Rgba) {
+        let i = (y * self.width + x) * 4;
+        self.pixels[i..i + 4].copy_from_slice(&color);
+    }
+
+    pub fn save_png(&self, path: impl AsRef<Path>) -> Result<()> {
+        self.write_png(BufWriter::new(File::create(path)?))
+    }
+
+    pub fn write_png(&self, w: impl Write) -> Result<()> {
+        let mut encoder = png::Encoder::new(w, self.width as u32, self.height as u32);
+        encoder.set_color(png::ColorType::Rgba);
+        encoder.set_depth(png::BitDepth::Eight);
+
+        let mut writer = encoder.write_header()?;
+        writer.write_image_data(&self.pixels)?;
+        writer.finish()?;
+
+        Ok(())
+    }
+
+    pub fn load_png(path: impl AsRef<Path>) -> Result<Image> {
+        let path = path.as_ref();
+        let file = File::open(path).with_context(|| format!("Could not open {}", path.display()))?;
+        Image::read_png(BufReader::new(file)).with_context(|| format!("Could not read {}", path.display()))
+    }
+
+    // Only reads 8 bit RGBA, what write_png writes
+    pub fn read_png(r: impl Read) -> Result<Image> {
+        let mut reader = png::Decoder::new(r).read_info()?;
+        let info = reader.info();
+        if info.color_type != png::ColorType::Rgba || info.bit_depth != png::BitDepth::Eight {
+            bail!("Expected an 8 bit RGBA PNG, got {:?} at {:?}", info.color_type, info.bit_depth);
+        }
+        let mut image = Image::new(info.width as usize, info.height as usize);
+        reader.next_frame(&mut image.pixels)?;
+        Ok(image)
+    }
+
+    // The width by height pixels from x, y on, which have to be in the image
+    pub fn crop(&self, x: usize, y: usize, width: usize, height: usize) -> Image {
+        let mut cropped = Image::new(width, height);
+        for row in 0..height {
+            let from = ((y + row) * self.width + x) * 4;
+            cropped.pixels[row * width * 4..(row + 1) * width * 4].copy_from_slice(&self.pixels[from..from + width * 4]);
+        }
+        cropped
+    }
+
+    // Copies other over this image with its top left corner at x, y, cutting
+    // off what doesn't fit
+    pub fn paste(&mut self, other: &Image, x: usize, y: usize) {
+        let width = other.width.min(self.width.saturating_sub(x));
+        for row in 0..other.height.min(self.height.saturating_sub(y)) {
+            let to = ((y + row) * self.width + x) * 4;
+            self.pixels[to..to + width * 4].copy_from_slice(&other.pixels[row * other.width * 4..(row * other.width + width) * 4]);
+        }
+    }
+
+    // Half the width and height, each pixel the average of the four it covers
+    pub fn half(&self) -> Image {
+        let mut half = Image::new(self.width / 2, self.height / 2);
+        for y in 0..half.height {
+            for x in 0..half.width {
+                let corners = [self.get(x * 2, y * 2), self.get(x * 2 + 1, y * 2), self.get(x * 2, y * 2 + 1), self.get(x * 2 + 1, y * 2 + 1)];
+                let channel = |i: usize| (corners.iter().map(|color| color[i] as u32).sum::<u32>() / 4) as u8;
+                half.set(x, y, [channel(0), channel(1), channel(2), channel(3)]);
+            }
+        }
+        half
+    }
+}
+
+pub struct BlockColors {
+    colors: HashMap<String, Rgba>,
+    pub fallback: Rgba,
+}
+
+impl Default for BlockColors {
+    fn default() -> Self {
+        let defaults: [(&str, Rgba); 52] = [
+            ("minecraft:stone", [125, 125, 125, 255]),
+            ("minecraft:cobblestone", [122, 122, 122, 255]),
+            ("minecraft:andesite", [136, 136, 137, 255]),
+            ("minecraft:diorite", [188, 188, 189, 255]),
+            ("minecraft:granite", [149, 103, 85, 255]),
+            ("minecraft:tuff", [108, 109, 102, 255]),
+            ("minecraft:deepslate", [80, 80, 85, 255]),
+            ("minecraft:cobbled_deepslate", [77, 77, 80, 255]),
+            ("minecraft:calcite", [223, 224, 220, 255]),
+            ("minecraft:dirt", [134, 96, 67, 255]),
+            ("minecraft:coarse_dirt", [119, 85, 59, 255]),
+            ("minecraft:grass_block", [95, 159, 53, 255]),
+            ("minecraft:podzol", [91, 63, 24, 255]),
+            ("minecraft:mycelium", [111, 99, 105, 255]),
+            ("minecraft:farmland", [81, 44, 15, 255]),
+            ("minecraft:dirt_path", [148, 121, 65, 255]),
+            ("minecraft:mud", [60, 57, 60, 255]),
+            ("minecraft:clay", [160, 166, 179, 255]),
+            ("minecraft:bedrock", [50, 50, 50, 255]),
+            ("minecraft:sand", [219, 207, 163, 255]),
+            ("minecraft:red_sand", [190, 102, 33, 255]),
+            ("minecraft:sandstone", [216, 203, 155, 255]),
+            ("minecraft:gravel", [136, 126, 126, 255]),
+            ("minecraft:water", [63, 118, 228, 255]),
+            ("minecraft:lava", [207, 92, 15, 255]),
+            ("minecraft:snow", [249, 254, 254, 255]),
+            ("minecraft:snow_block", [249, 254, 254, 255]),
+            ("minecraft:ice", [145, 183, 253, 255]),
+            ("minecraft:packed_ice", [141, 180, 250, 255]),
+            ("minecraft:oak_leaves", [59, 122, 36, 255]),
+            ("minecraft:birch_leaves", [80, 129, 50, 255]),
+            ("minecraft:spruce_leaves", [49, 80, 49, 255]),
+            ("minecraft:jungle_leaves", [48, 134, 18, 255]),
+            ("minecraft:oak_log", [109, 85, 50, 255]),
+            ("minecraft:birch_log", [216, 215, 210, 255]),
+            ("minecraft:spruce_log", [58, 37, 16, 255]),
+            ("minecraft:oak_planks", [162, 130, 78, 255]),
+            ("minecraft:short_grass", [100, 150, 60, 255]),
+            ("minecraft:netherrack", [111, 54, 52, 255]),
+            ("minecraft:soul_sand", [81, 62, 50, 255]),
+            ("minecraft:basalt", [80, 81, 86, 255]),
+            ("minecraft:blackstone", [42, 35, 40, 255]),
+            ("minecraft:end_stone", [219, 222, 158, 255]),
+            ("minecraft:obsidian", [15, 10, 24, 255]),
+            ("minecraft:coal_ore", [105, 105, 105, 255]),
+            ("minecraft:iron_ore", [136, 129, 122, 255]),
+            ("minecraft:copper_ore", [124, 125, 120, 255]),
+            ("minecraft:gold_ore", [252, 238, 75, 255]),
+            ("minecraft:redstone_ore", [175, 24, 5, 255]),
+            ("minecraft:diamond_ore", [92, 219, 213, 255]),
+            ("minecraft:emerald_ore", [23, 221, 98, 255]),
+            ("minecraft:redstone_block", [175, 24, 5, 255]),
+        ];
+
+        BlockColors {
+            colors: defaults.iter().map(|(name, color)| (name.to_string(), *color)).collect(),
+            fallback: [255, 0, 255, 255],
+        }
+    }
+}
+
+impl BlockColors {
+    // Colors for biome maps, loosely following the ones Amidst uses
+    pub fn biomes() -> BlockColors {
+        let defaults: [(&str, Rgba); 37] = [
+            ("minecraft:plains", [141, 179, 96, 255]),
+            ("minecraft:sunflower_plains", [181, 219, 136, 255]),
+            ("minecraft:snowy_plains", [255, 255, 255, 255]),
+            ("minecraft:desert", [250, 148, 24, 255]),
+            ("minecraft:swamp", [7, 249, 178, 255]),
+            ("minecraft:mangrove_swamp", [103, 121, 45, 255]),
+            ("minecraft:forest", [5, 102, 33, 255]),
+            ("minecraft:flower_forest", [45, 142, 73, 255]),
+            ("minecraft:birch_forest", [48, 116, 68, 255]),
+            ("minecraft:dark_forest", [64, 81, 26, 255]),
+            ("minecraft:cherry_grove", [255, 183, 213, 255]),
+            ("minecraft:taiga", [11, 102, 89, 255]),
+            ("minecraft:snowy_taiga", [49, 85, 74, 255]),
+            ("minecraft:savanna", [189, 178, 95, 255]),
+            ("minecraft:jungle", [83, 123, 9, 255]),
+            ("minecraft:badlands", [217, 69, 21, 255]),
+            ("minecraft:meadow", [96, 164, 69, 255]),
+            ("minecraft:grove", [71, 114, 108, 255]),
+            ("minecraft:snowy_slopes", [196, 196, 196, 255]),
+            ("minecraft:jagged_peaks", [220, 220, 200, 255]),
+            ("minecraft:frozen_peaks", [176, 179, 206, 255]),
+            ("minecraft:stony_peaks", [123, 143, 116, 255]),
+            ("minecraft:windswept_hills", [96, 96, 96, 255]),
+            ("minecraft:river", [0, 0, 255, 255]),
+            ("minecraft:frozen_river", [160, 160, 255, 255]),
+            ("minecraft:beach", [250, 222, 85, 255]),
+            ("minecraft:stony_shore", [162, 162, 132, 255]),
+            ("minecraft:ocean", [0, 0, 112, 255]),
+            ("minecraft:deep_ocean", [0, 0, 48, 255]),
+            ("minecraft:warm_ocean", [0, 0, 172, 255]),
+            ("minecraft:cold_ocean", [32, 32, 112, 255]),
+            ("minecraft:frozen_ocean", [112, 112, 214, 255]),
+            ("minecraft:mushroom_fields", [255, 0, 255, 255]),
+            ("minecraft:nether_wastes", [191, 59, 59, 255]),
+            ("minecraft:crimson_forest", [221, 8, 8, 255]),
+            ("minecraft:warped_forest", [73, 144, 123, 255]),
+            ("minecraft:the_end", [128, 128, 255, 255]),
+        ];
+
+        BlockColors {
+            colors: defaults.iter().map(|(name, color)| (name.to_string(), *color)).collect(),
+            fallback: [128, 128, 128, 255],
+        }
+    }
+
+    pub fn set(&mut self, name: &str, color: Rgba) {
+        self.colors.insert(name.to_string(), color);
+    }
+
+    pub fn get(&self, name: &str) -> Rgba {
+        self.lookup(name).unwrap_or(self.fallback)
+    }
+
+    // None instead of the fallback for names without a color
+    pub fn lookup(&self, name: &str) -> Option<Rgba> {
+        self.colors.get(name).copied()
+    }
+
+    // Overrides colors with the ones in a TOML file, or JSON for any other
+    // extension. Both map names to "#rrggbb", "#rrggbbaa" or [r, g, b, a].
+    pub fn load(&mut self, path: impl AsRef<Path>) -> Result<()> {
+        let path = path.as_ref();
+        let text = fs::read_to_string(path).with_context(|| format!("Could not read {}", path.display()))?;
+
+        let entries: HashMap<String, ColorEntry> = if path.extension().is_some_and(|extension| extension == "toml") {
+            toml::from_str(&text).with_context(|| format!("Could not parse {}", path.display()))?
+        } else {
+            serde_json::from_str(&text).with_context(|| format!("Could not parse {}", path.display()))?
+        };
+
+        for (name, entry) in entries {
+            let color = entry.to_rgba().with_context(|| format!("Invalid color for {name} in {}", path.display()))?;
+            self.set(&name, color);
+        }
+
+        Ok(())
+    }
+}
+
+// By every color, in name order, so colors loaded in another order hash the same
+impl Hash for BlockColors {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        let mut colors: Vec<(&String, &Rgba)> = self.colors.iter().collect();
+        colors.sort();
+        colors.hash(state);
+        self.fallback.hash(state);
+    }
+}
+
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum ColorEntry {
+    Hex(String),
+    Channels(Vec<u8>),
+}
+
+impl ColorEntry {
+    fn to_rgba(&self) -> Result<Rgba> {
+        match self {
+            ColorEntry::Hex(hex) => parse_hex_color(hex).ok_or_else(|| anyhow!("Expected #rrggbb or #rrggbbaa, got \"{hex}\"")),
+            ColorEntry::Channels(channels) => match channels.as_slice() {
+                [r, g, b] => Ok([*r, *g, *b, 255]),
+                [r, g, b, a] => Ok([*r, *g, *b, *a]),
+                _ => bail!("Expected 3 or 4 channels, got {}", channels.len()),
+            },
+        }
+    }
+}
+
+// Stable made up color for blocks without one, so they can still be told apart
+pub fn hashed_color(name: &str) -> Rgba {
+    let hash = name.bytes().fold(2166136261u32, |h, b| (h ^ b as u32).wrapping_mul(16777619));
+    [(hash >> 16) as u8, (hash >> 8) as u8, hash as u8, 255]
+}
+
+// Parses "#rrggbb" or "#rrggbbaa"
+pub fn parse_hex_color(s: &str) -> Option<Rgba> {
+    let hex = s.strip_prefix('#').unwrap_or(s);
+    if !hex.is_ascii() || (hex.len() != 6 && hex.len() != 8) {
+        return None;
+    }
+
+    let channel = |i: usize| u8::from_str_radix(&hex[i * 2..i * 2 + 2], 16).ok();
+    let alpha = if hex.len() == 8 { channel(3)? } else { 255 };
+    Some([channel(0)?, channel(1)?, channel(2)?, alpha])
+}
+
+// Ticks after which a chunk's local difficulty stops going up, where the
+// inhabited time heatmap is at its hottest
+pub const MAX_INHABITED_TIME: i64 = 3_600_000;
+
+// Blue through green and yellow to red for 0.0 to 1.0, partly see-through so
+// the map shows below
+pub fn heat_color(fraction: f32) -> Rgba {
+    let stops: [[f32; 3]; 4] = [[40.0, 60.0, 255.0], [40.0, 220.0, 80.0], [255.0, 230.0, 40.0], [255.0, 30.0, 30.0]];
+    let scaled = fraction.clamp(0.0, 1.0) * (stops.len() - 1) as f32;
+    let i = (scaled as usize).min(stops.len() - 2);
+    let t = scaled - i as f32;
+    let channel = |c: usize| (stops[i][c] + (stops[i + 1][c] - stops[i][c]) * t) as u8;
+    [channel(0), channel(1), channel(2), 170]
+}
+
+// Where between nothing and MAX_INHABITED_TIME the ticks are, on a log scale
+// so a few minutes of someone passing by still shows
+pub fn inhabited_fraction(ticks: i64) -> f32 {
+    if ticks <= 0 {
+        return 0.0;
+    }
+    ((ticks as f32).ln_1p() / (MAX_INHABITED_TIME as f32).ln_1p()).min(1.0)
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum MapLayer {
+    Blocks,
+    // Colors each column by the biome at its surface, needs 1.18+ chunks
+    Biomes,
+}
+
+// Renders the highest non-air block of every column, one pixel per block with north up
+pub struct TopDownRenderer<'a> {
+    colors: &'a BlockColors,
+    layer: MapLayer,
+    min_chunk: (i32, i32),
+    image: Image,
+    heights: Vec<Option<i32>>,
+    tinted: Vec<((i32, i32), Rgba)>,
+    // Single columns by block x and z, like tinted
+    marked: Vec<((i32, i32), Rgba)>,
+}
+
+impl<'a> TopDownRenderer<'a> {
+    // Covers the inclusive chunk rectangle from min_chunk to max_chunk
+    pub fn new(colors: &'a BlockColors, layer: MapLayer, min_chunk: (i32, i32), max_chunk: (i32, i32)) -> TopDownRenderer<'a> {
+        let width = ((max_chunk.0 - min_chunk.0 + 1) * 16) as usize;
+        let height = ((max_chunk.1 - min_chunk.1 + 1) * 16) as usize;
+
+        TopDownRenderer {
+            colors,
+            layer,
+            min_chunk,
+            image: Image::new(width, height),
+            heights: vec![None; width * height],
+            tinted: Vec::new(),
+            marked: Vec::new(),
+        }
+    }
+
+    // Blends color over whole chunks once the map is done, its alpha saying
+    // how strongly, e.g. to mark slime chunks
+    pub fn tint_chunks(&mut self, chunks: impl IntoIterator<Item = (i32, i32)>, color: Rgba) {
+        self.tinted.extend(chunks.into_iter().map(|chunk| (chunk, color)));
+    }
+
+    // Like tint_chunks for single block columns, e.g. to show where caves are
+    pub fn tint_columns(&mut self, columns: impl IntoIterator<Item = (i32, i32)>, color: Rgba) {
+        self.marked.extend(columns.into_iter().map(|column| (column, color)));
+    }
+
+    pub fn add_chunk(&mut self, chunk: &Chunk) {
+        let offset_x = (chunk.x() - self.min_chunk.0) * 16;
+        let offset_z = (chunk.z() - self.min_chunk.1) * 16;
+        if offset_x < 0 || offset_z < 0 || offset_x as usize >= self.image.width || offset_z as usize >= self.image.height {
+            return;
+        }
+
+        let surface = chunk.heightmap(HeightmapKind::WorldSurface);
+
+        for z in 0..16 {
+            for x in 0..16 {
+                // Fall back to scanning the column when the heightmap is missing or points at air
+                let from_heightmap = surface.and_then(|heights| {
+                    let y = heights[z][x] - 1;
+                    chunk.block_at(x, y, z).filter(|block| !block.is_air()).map(|block| (y, block))
+                });
+                if let Some((y, block)) = from_heightmap.or_else(|| chunk.top_block(x, z)) {
+                    let px = offset_x as usize + x;
+                    let pz = offset_z as usize + z;
+                    let color = match self.layer {
+                        MapLayer::Blocks => self.colors.get(&block.name),
+                        MapLayer::Biomes => chunk.biome_at(x, y, z).map_or(self.colors.fallback, |biome| self.colors.get(biome)),
+                    };
+                    self.image.set(px, pz, color);
+                    self.heights[pz * self.image.width + px] = Some(y);
+                }
+            }
+        }
+    }
+
+    // Shades each pixel against its northern neighbour, like in-game maps do
+    pub fn finish(mut self) -> Image {
+        for z in 1..self.image.height {
+            for x in 0..self.image.width {
+                let here = self.heights[z * self.image.width + x];
+                let north = self.heights[(z - 1) * self.image.width + x];
+
+                if let (Some(here), Some(north)) = (here, north) {
+                    let factor = match here.cmp(&north) {
+                        std::cmp::Ordering::Greater => 1.1,
+                        std::cmp::Ordering::Less => 0.85,
+                        std::cmp::Ordering::Equal => continue,
+                    };
+                    let [r, g, b, a] = self.image.get(x, z);
+                    let scale = |c: u8| (c as f32 * factor).min(255.0) as u8;
+                    self.image.set(x, z, [scale(r), scale(g), scale(b), a]);
+                }
+            }
+        }
+
+        for ((chunk_x, chunk_z), color) in std::mem::take(&mut self.tinted) {
+            let offset_x = (chunk_x - self.min_chunk.0) * 16;
+            let offset_z = (chunk_z - self.min_chunk.1) * 16;
+            if offset_x < 0 || offset_z < 0 || offset_x as usize >= self.image.width || offset_z as usize >= self.image.height {
+                continue;
+            }
+            for z in offset_z as usize..offset_z as usize + 16 {
+                for x in offset_x as usize..offset_x as usize + 16 {
+                    self.blend(x, z, color);
+                }
+            }
+        }
+
+        // Each column once, however many blocks of it were marked
+        let marked: HashMap<(i32, i32), Rgba> = std::mem::take(&mut self.marked).into_iter().collect();
+        for ((x, z), color) in marked {
+            let (x, z) = (x - self.min_chunk.0 * 16, z - self.min_chunk.1 * 16);
+            if x >= 0 && z >= 0 && (x as usize) < self.image.width && (z as usize) < self.image.height {
+                self.blend(x as usize, z as usize, color);
+            }
+        }
+
+        self.image
+    }
+
+    fn blend(&mut self, x: usize, z: usize, [tint_r, tint_g, tint_b, alpha]: Rgba) {
+        let blend = |c: u8, tint: u8| ((c as u32 * (255 - alpha as u32) + tint as u32 * alpha as u32) / 255) as u8;
+        let [r, g, b, a] = self.image.get(x, z);
+        self.image.set(x, z, [blend(r, tint_r), blend(g, tint_g), blend(b, tint_b), a.max(alpha)]);
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SlicePlane {
+    // Horizontal, at this y
+    Y(i32),
+    // Vertical, facing east or west at this x
+    X(i32),
+    // Vertical, facing north or south at this z
+    Z(i32),
+}
+
+pub fn is_ore(name: &str) -> bool {
+    name.ends_with("_ore") || name == "minecraft:ancient_debris"
+}
+
+// Renders the blocks on a single plane, one pixel per block. Highlighted
+// blocks keep their color and everything else is drawn in dark grey so they
+// stand out. Y slices have north up, X and Z slices have the sky up.
+pub struct SliceRenderer<'a> {
+    colors: &'a BlockColors,
+    plane: SlicePlane,
+    highlighted: Vec<BlockId>,
+    // Whether air is drawn by how light it is
+    light: bool,
+    // Block coordinates of the top left pixel, along the image's x and y
+    origin: (i32, i32),
+    image: Image,
+}
+
+impl<'a> SliceRenderer<'a> {
+    // Covers the inclusive chunk rectangle from min_chunk to max_chunk, and
+    // for vertical planes the inclusive y range from min_y to max_y
+    pub fn new(colors: &'a BlockColors, plane: SlicePlane, min_chunk: (i32, i32), max_chunk: (i32, i32), min_y: i32, max_y: i32) -> SliceRenderer<'a> {
+        let span = |min: i32, max: i32| ((max - min + 1) * 16) as usize;
+        let height = (max_y - min_y + 1).max(0) as usize;
+
+        let (origin, width, height) = match plane {
+            SlicePlane::Y(_) => ((min_chunk.0 * 16, min_chunk.1 * 16), span(min_chunk.0, max_chunk.0), span(min_chunk.1, max_chunk.1)),
+            SlicePlane::X(_) => ((min_chunk.1 * 16, max_y), span(min_chunk.1, max_chunk.1), height),
+            SlicePlane::Z(_) => ((min_chunk.0 * 16, max_y), span(min_chunk.0, max_chunk.0), height),
+        };
+
+        SliceRenderer { colors, plane, highlighted: Vec::new(), light: false, origin, image: Image::new(width, height) }
+    }
+
+    // Blocks to keep in color, ores and ancient debris if none are given
+    pub fn highlight(mut self, names: Vec<String>) -> Self {
+        self.highlighted = names.iter().map(|name| BlockId::intern(name)).collect();
+        self
+    }
+
+    // Draws air too, from black where it's pitch dark to pale yellow in full
+    // light, by the brighter of block and sky light. Chunks read without
+    // their light data leave the air out as before.
+    pub fn show_light(mut self) -> Self {
+        self.light = true;
+        self
+    }
+
+    pub fn origin(&self) -> (i32, i32) {
+        self.origin
+    }
+
+    // Whether the plane goes through the chunk at all
+    pub fn crosses(&self, chunk_x: i32, chunk_z: i32) -> bool {
+        match self.plane {
+            SlicePlane::Y(_) => true,
+            SlicePlane::X(x) => x.div_euclid(16) == chunk_x,
+            SlicePlane::Z(z) => z.div_euclid(16) == chunk_z,
+        }
+    }
+
+    pub fn add_chunk(&mut self, chunk: &Chunk) {
+        if !self.crosses(chunk.x(), chunk.z()) {
+            return;
+        }
+
+        // Each pixel of the chunk on the plane as image coordinates and the block it shows, local to the chunk
+        let mut pixels = Vec::new();
+        match self.plane {
+            SlicePlane::Y(y) => {
+                for z in 0..16 {
+                    for x in 0..16 {
+                        pixels.push((chunk.x() * 16 + x as i32 - self.origin.0, chunk.z() * 16 + z as i32 - self.origin.1, (x, y, z)));
+                    }
+                }
+            },
+            SlicePlane::X(x) => {
+                let local_x = x.rem_euclid(16) as usize;
+                for y in 0..self.image.height as i32 {
+                    for z in 0..16 {
+                        pixels.push((chunk.z() * 16 + z as i32 - self.origin.0, y, (local_x, self.origin.1 - y, z)));
+                    }
+                }
+            },
+            SlicePlane::Z(z) => {
+                let local_z = z.rem_euclid(16) as usize;
+                for y in 0..self.image.height as i32 {
+                    for x in 0..16 {
+                        pixels.push((chunk.x() * 16 + x as i32 - self.origin.0, y, (x, self.origin.1 - y, local_z)));
+                    }
+                }
+            },
+        }
+
+        for (px, py, (x, y, z)) in pixels {
+            if px < 0 || py < 0 || px as usize >= self.image.width || py as usize >= self.image.height {
+                continue;
+            }
+            let Some(block) = chunk.block_at(x, y, z) else {
+                continue;
+            };
+            if block.is_air() {
+                let level = chunk.block_light(x, y, z).max(chunk.sky_light(x, y, z));
+                if let Some(level) = level.filter(|_| self.light) {
+                    let scale = |c: f32| (c * level as f32 / 15.0) as u8;
+                    self.image.set(px as usize, py as usize, [scale(255.0), scale(240.0), scale(170.0), 255]);
+                }
+                continue;
+            }
+
+            let color = self.colors.get(&block.name);
+            let highlighted = if self.highlighted.is_empty() {
+                is_ore(&block.name)
+            } else {
+                self.highlighted.contains(&block.name)
+            };
+            let color = if highlighted {
+                color
+            } else {
+                let [r, g, b, a] = color;
+                let grey = (0.3 * r as f32 + 0.59 * g as f32 + 0.11 * b as f32) * 0.4;
+                [grey as u8, grey as u8, grey as u8, a]
+            };
+            self.image.set(px as usize, py as usize, color);
+        }
+    }
+
+    pub fn finish(self) -> Image {
+        self.image
+    }
+}
@@ -0,0 +1,153 @@
+use anyhow::{ Result, Context, bail };
+use std::{ fmt, path::{ Path, PathBuf } };
+
+use crate::{ chunk::{ BlockState, Chunk }, claims::ChunkPolicy, pos::BlockPos, region::{ Region, chunk_at_index, chunk_index_in_region, edit_chunks, parse_region_file_name }, world::{ Dimension, World } };
+
+// What a replace did, or would do on a dry run
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct ReplaceSummary {
+    pub replaced: usize,
+    pub chunks: usize,
+    // Chunks in the box the policy doesn't allow, left as they are
+    pub refused: Vec<(i32, i32)>,
+    pub dry_run: bool,
+}
+
+impl fmt::Display for ReplaceSummary {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if self.dry_run {
+            write!(f, "Would replace {} blocks in {} chunks", self.replaced, self.chunks)?;
+        } else {
+            write!(f, "Replaced {} blocks in {} chunks", self.replaced, self.chunks)?;
+        }
+        if !self.refused.is_empty() {
+            let refused: Vec<String> = self.refused.iter().map(|(x, z)| format!("({x}, {z})")).collect();
+            write!(f, ", refused to change {} excluded chunks in the box: {}", self.refused.len(), refused.join(", "))?;
+        }
+        Ok(())
+    }
+}
+
+// Turns every from block in the box between min and max into to, in a world
+// folder or a region file, changing it in place. Chunks the policy doesn't
+// allow are never edited, however much of them the box covers.
+pub fn replace_blocks(path: &Path, dimension: Dimension, from: &BlockState, to: &BlockState, (min, max): (BlockPos, BlockPos), policy: &ChunkPolicy, dry_run: bool) -> Result<ReplaceSummary> {
+    let region_range = (min.chunk_x().div_euclid(32)..=max.chunk_x().div_euclid(32), min.chunk_z().div_euclid(32)..=max.chunk_z().div_euclid(32));
+
+    let regions: Vec<(PathBuf, Option<Dimension>, i32, i32)> = if path.is_dir() {
+        let world = World::open(path)?;
+        world.regions_in(dimension)
+            .filter(|region| region_range.0.contains(&region.x) && region_range.1.contains(&region.z))
+            .map(|region| (region.path.clone(), Some(dimension), region.x, region.z))
+            .collect()
+    } else {
+        let Some((x, z)) = path.file_name().and_then(|name| name.to_str()).and_then(parse_region_file_name) else {
+            bail!("The region file has to be named r.<x>.<z>.mca to know where the box is");
+        };
+        vec![(path.to_path_buf(), None, x, z)]
+    };
+
+    let mut summary = ReplaceSummary { dry_run, ..ReplaceSummary::default() };
+    for (path, dimension, region_x, region_z) in regions {
+        let region = Region::open(&path).with_context(|| format!("Could not read region {}", path.display()))?;
+        let in_box: Vec<usize> = (min.chunk_z().max(region_z * 32)..=max.chunk_z().min(region_z * 32 + 31))
+            .flat_map(|chunk_z| (min.chunk_x().max(region_x * 32)..=max.chunk_x().min(region_x * 32 + 31)).map(move |chunk_x| (chunk_x, chunk_z)))
+            .map(|(chunk_x, chunk_z)| chunk_index_in_region(chunk_x, chunk_z))
+            .filter(|index| region.has_chunk(*index))
+            .collect();
+        let (indices, refused): (Vec<usize>, Vec<usize>) = in_box.into_iter().partition(|index| {
+            let (x, z) = chunk_at_index(region_x, region_z, *index);
+            policy.allows(dimension, x, z)
+        });
+        summary.refused.extend(refused.into_iter().map(|index| chunk_at_index(region_x, region_z, index)));
+        drop(region);
+        if indices.is_empty() {
+            continue;
+        }
+
+        let mut touched = 0;
+        let changed = edit_chunks(&path, &indices, |_, tag| {
+            let mut chunk = Chunk::from_nbt(tag.clone())?;
+            let count = chunk.replace_blocks(from, to, min, max)?;
+            summary.replaced += count;
+            touched += usize::from(count > 0);
+            if count == 0 || dry_run {
+                return Ok(false);
+            }
+            *tag = chunk.into_nbt()?;
+            Ok(true)
+        }).with_context(|| format!("Could not edit region {}", path.display()))?;
+        summary.chunks += if dry_run { touched } else { changed };
+    }
+
+    Ok(summary)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{ claims::Claims, nbt::{ ParseOptions, Tag, TagPayload }, region::put_chunks };
+    use std::fs;
+
+    fn tag(name: &str, payload: TagPayload) -> Tag {
+        Tag { name: name.to_string(), payload }
+    }
+
+    // Stone from y 0 to 15
+    fn stone_chunk(x: i32, z: i32) -> Tag {
+        let stone = TagPayload::Compound(vec![tag("Name", TagPayload::String("minecraft:stone".to_string()))].into());
+        let block_states = vec![tag("palette", TagPayload::List(vec![stone]))];
+        let section = TagPayload::Compound(vec![tag("Y", TagPayload::Byte(0)), tag("block_states", TagPayload::Compound(block_states.into()))].into());
+        let root = vec![
+            tag("DataVersion", TagPayload::Int(3700)),
+            tag("xPos", TagPayload::Int(x)),
+            tag("zPos", TagPayload::Int(z)),
+            tag("yPos", TagPayload::Int(0)),
+            tag("sections", TagPayload::List(vec![section])),
+        ];
+        tag("", TagPayload::Compound(root.into()))
+    }
+
+    #[test]
+    fn leaves_excluded_chunks_in_the_box_alone() {
+        let dir = std::env::temp_dir().join(format!("path-miner-replace-claims-{}", std::process::id()));
+        fs::create_dir_all(dir.join("region")).unwrap();
+        let path = dir.join("region").join("r.0.0.mca");
+        let chunks: Vec<Tag> = (0..3).map(|x| stone_chunk(x, 0)).collect();
+        put_chunks(&path, &chunks.iter().enumerate().collect::<Vec<_>>()).unwrap();
+
+        // The box runs over all three chunks, the middle one is claimed
+        let policy = ChunkPolicy::new().exclude("chunk 1 0".parse::<Claims>().unwrap());
+        let area = (BlockPos::new(8, 0, 0), BlockPos::new(39, 0, 15));
+        let (from, to) = (BlockState::new("minecraft:stone"), BlockState::new("minecraft:glass"));
+
+        let untouched = Region::open(&path).unwrap().read_raw_chunk(1).unwrap();
+        let dry_run = replace_blocks(&dir, Dimension::Overworld, &from, &to, area, &policy, true).unwrap();
+        assert_eq!(dry_run, ReplaceSummary { replaced: 256, chunks: 2, refused: vec![(1, 0)], dry_run: true });
+
+        let summary = replace_blocks(&dir, Dimension::Overworld, &from, &to, area, &policy, false).unwrap();
+        assert_eq!(summary, ReplaceSummary { replaced: 256, chunks: 2, refused: vec![(1, 0)], dry_run: false });
+        assert_eq!(summary.to_string(), "Replaced 256 blocks in 2 chunks, refused to change 1 excluded chunks in the box: (1, 0)");
+
+        let mut region = Region::open(&path).unwrap();
+        let read: Vec<Chunk> = region.read_chunks(&ParseOptions::default()).into_result().unwrap();
+        let block = |x: i32| read[x as usize / 16].block_at(x as usize % 16, 0, 0).unwrap().name.to_string();
+        assert_eq!(block(7), "minecraft:stone");
+        assert_eq!(block(8), "minecraft:glass");
+        assert_eq!(block(16), "minecraft:stone");
+        assert_eq!(block(31), "minecraft:stone");
+        assert_eq!(block(39), "minecraft:glass");
+        assert_eq!(block(40), "minecraft:stone");
+        // The excluded chunk wasn't even written back
+        assert_eq!(region.read_raw_chunk(1).unwrap(), untouched);
+
+        // Scans leave it out too and say so
+        let world = World::open(&dir).unwrap();
+        let mut scan = world.chunks().only_allowed(&policy);
+        let scanned: Vec<(i32, i32)> = scan.by_ref().map(|chunk| chunk.map(|(_, chunk)| (chunk.x(), chunk.z())).unwrap()).collect();
+        assert_eq!(scanned, vec![(0, 0), (2, 0)]);
+        assert_eq!(scan.progress().excluded, 1);
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,300 @@
+use anyhow::Result;
+use std::{ collections::BTreeMap, fmt::Write };
+
+use crate::{ chunk::BlockEntity, nbt::TagPayload, pos::BlockPos, render::{ Image, hashed_color } };
+
+// Listed one by one at most, the rest are only counted
+const MAX_ROWS: usize = 500;
+
+const SECONDS_PER_DAY: u32 = 86400;
+
+// Blocks worth a trip on their own
+pub fn is_valuable(name: &str) -> bool {
+    matches!(name, "minecraft:diamond_ore" | "minecraft:deepslate_diamond_ore" | "minecraft:emerald_ore"
+        | "minecraft:deepslate_emerald_ore" | "minecraft:ancient_debris" | "minecraft:budding_amethyst")
+}
+
+// The mob a spawner spawns, from SpawnData.entity.id since 1.18 and
+// SpawnData.id before. None for spawners that haven't been set up.
+pub fn spawner_mob(block_entity: &BlockEntity) -> Option<&str> {
+    let data = block_entity.nbt().get("SpawnData")?;
+    let id = data.get("entity").and_then(|entity| entity.get("id")).or_else(|| data.get("id"));
+    match id {
+        Some(TagPayload::String(id)) => Some(id),
+        _ => None,
+    }
+}
+
+// Everything a report shows, worked out beforehand by whatever scanned the world
+pub struct Report {
+    pub title: String,
+    pub map: Option<Image>,
+    // Block x and z of the map's top left pixel
+    pub map_origin: (i32, i32),
+    // Counts by block name, shown most common first
+    pub blocks: Vec<(String, u64)>,
+    // Counts by ore, then by y
+    pub ores: BTreeMap<String, BTreeMap<i32, u64>>,
+    pub valuables: Vec<(String, BlockPos)>,
+    // The mob each spawner spawns, if it's set up
+    pub spawners: Vec<(Option<String>, BlockPos)>,
+    // When each chunk was last saved, as a Unix timestamp
+    pub activity: Vec<((i32, i32), u32)>,
+}
+
+// One HTML file with no links to anything, the map included as a data URL
+pub fn write_html(report: &Report) -> Result<String> {
+    let mut html = String::new();
+    writeln!(html, "<!DOCTYPE html>")?;
+    writeln!(html, "<html><head><meta charset=\"utf-8\"><title>{}</title>", escape(&report.title))?;
+    writeln!(html, "<style>")?;
+    writeln!(html, "body {{ font-family: sans-serif; margin: 2em; }}")?;
+    writeln!(html, "table {{ border-collapse: collapse; }}")?;
+    writeln!(html, "td, th {{ padding: 2px 8px; text-align: left; }}")?;
+    writeln!(html, "td.n {{ text-align: right; font-variant-numeric: tabular-nums; }}")?;
+    writeln!(html, "img {{ image-rendering: pixelated; max-width: 100%; }}")?;
+    writeln!(html, ".bar {{ background: #4a7; height: 1em; }}")?;
+    writeln!(html, ".swatch {{ display: inline-block; width: 1em; height: 1em; vertical-align: middle; }}")?;
+    writeln!(html, "</style></head><body>")?;
+    writeln!(html, "<h1>{}</h1>", escape(&report.title))?;
+
+    write_map(&mut html, report)?;
+    write_blocks(&mut html, &report.blocks)?;
+    write_ores(&mut html, &report.ores)?;
+
+    writeln!(html, "<h2>Valuables</h2>")?;
+    let valuables = report.valuables.iter().map(|(name, pos)| vec![escape(name), pos.to_string()]);
+    write_table(&mut html, &["Block", "Position"], valuables)?;
+
+    writeln!(html, "<h2>Spawners</h2>")?;
+    let spawners = report.spawners.iter().map(|(mob, pos)| vec![escape(mob.as_deref().unwrap_or("nothing")), pos.to_string()]);
+    write_table(&mut html, &["Spawns", "Position"], spawners)?;
+
+    write_activity(&mut html, &report.activity)?;
+
+    writeln!(html, "</body></html>")?;
+    Ok(html)
+}
+
+fn write_map(html: &mut String, report: &Report) -> Result<()> {
+    writeln!(html, "<h2>Map</h2>")?;
+    let Some(map) = &report.map else {
+        writeln!(html, "<p>None</p>")?;
+        return Ok(());
+    };
+    let mut png = Vec::new();
+    map.write_png(&mut png)?;
+    writeln!(html, "<img alt=\"Map\" width=\"{}\" height=\"{}\" src=\"data:image/png;base64,{}\">", map.width, map.height, base64(&png))?;
+    writeln!(html, "<p>North is up, the top left corner is block ({}, {}).</p>", report.map_origin.0, report.map_origin.1)?;
+    Ok(())
+}
+
+fn write_blocks(html: &mut String, blocks: &[(String, u64)]) -> Result<()> {
+    writeln!(html, "<h2>Blocks</h2>")?;
+    let total: u64 = blocks.iter().map(|(_, count)| count).sum();
+    let mut sorted: Vec<&(String, u64)> = blocks.iter().collect();
+    sorted.sort_by(|a, b| b.1.cmp(&a.1).then(a.0.cmp(&b.0)));
+    let rows = sorted.into_iter().map(|(name, count)| {
+        vec![escape(name), count.to_string(), format!("{:.2}%", *count as f64 * 100.0 / total as f64)]
+    });
+    write_table(html, &["Block", "Count", "Share"], rows)
+}
+
+// One line per ore across the y levels, with y going up the chart like in
+// game and the count to the right
+fn write_ores(html: &mut String, ores: &BTreeMap<String, BTreeMap<i32, u64>>) -> Result<()> {
+    writeln!(html, "<h2>Ores by y</h2>")?;
+    let min_y = ores.values().filter_map(|by_y| by_y.keys().next()).min().copied();
+    let max_y = ores.values().filter_map(|by_y| by_y.keys().next_back()).max().copied();
+    let (Some(min_y), Some(max_y)) = (min_y, max_y) else {
+        writeln!(html, "<p>None found</p>")?;
+        return Ok(());
+    };
+    let max_count = ores.values().flat_map(|by_y| by_y.values()).max().copied().unwrap_or(1).max(1);
+
+    const ROW: i32 = 3;
+    const LEFT: i32 = 40;
+    const WIDTH: i32 = 600;
+    let height = (max_y - min_y + 1) * ROW + 20;
+    let point = |y: i32, count: u64| (LEFT + (count * (WIDTH - LEFT - 10) as u64 / max_count) as i32, 10 + (max_y - y) * ROW);
+
+    writeln!(html, "<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"{WIDTH}\" height=\"{height}\" font-size=\"10\">")?;
+    writeln!(html, "<line x1=\"{LEFT}\" y1=\"10\" x2=\"{LEFT}\" y2=\"{}\" stroke=\"black\"/>", height - 10)?;
+    for y in (min_y..=max_y).filter(|y| y.rem_euclid(16) == 0) {
+        let (_, py) = point(y, 0);
+        writeln!(html, "<text x=\"{}\" y=\"{}\" text-anchor=\"end\">{y}</text>", LEFT - 4, py + 3)?;
+    }
+    for (name, by_y) in ores {
+        let points: Vec<String> = (min_y..=max_y)
+            .map(|y| point(y, by_y.get(&y).copied().unwrap_or(0)))
+            .map(|(x, y)| format!("{x},{y}"))
+            .collect();
+        writeln!(html, "<polyline fill=\"none\" stroke=\"{}\" points=\"{}\"><title>{}</title></polyline>", css_color(name), points.join(" "), escape(name))?;
+    }
+    writeln!(html, "</svg>")?;
+
+    let rows = ores.iter().map(|(name, by_y)| {
+        let total: u64 = by_y.values().sum();
+        let most = by_y.iter().max_by_key(|(y, count)| (**count, std::cmp::Reverse(**y))).map_or(0, |(y, _)| *y);
+        vec![format!("<span class=\"swatch\" style=\"background: {}\"></span> {}", css_color(name), escape(name)), total.to_string(), most.to_string()]
+    });
+    write_table(html, &["Ore", "Count", "Most at y"], rows)
+}
+
+// Chunks saved per day, in UTC
+fn write_activity(html: &mut String, activity: &[((i32, i32), u32)]) -> Result<()> {
+    writeln!(html, "<h2>Activity</h2>")?;
+    let mut days: BTreeMap<u32, u64> = BTreeMap::new();
+    for (_, timestamp) in activity.iter().filter(|(_, timestamp)| *timestamp > 0) {
+        *days.entry(timestamp / SECONDS_PER_DAY).or_default() += 1;
+    }
+    let most = days.values().max().copied().unwrap_or(1);
+    let rows = days.iter().rev().map(|(day, chunks)| {
+        let (year, month, date) = civil_date(*day);
+        let bar = format!("<div class=\"bar\" style=\"width: {}px\"></div>", (chunks * 300 / most).max(1));
+        vec![format!("{year}-{month:02}-{date:02}"), chunks.to_string(), bar]
+    });
+    write_table(html, &["Day", "Chunks last saved", ""], rows)
+}
+
+// Rows are HTML already, numbers get right aligned
+fn write_table(html: &mut String, header: &[&str], rows: impl Iterator<Item = Vec<String>>) -> Result<()> {
+    let mut rows = rows.peekable();
+    if rows.peek().is_none() {
+        writeln!(html, "<p>None found</p>")?;
+        return Ok(());
+    }
+
+    writeln!(html, "<table>")?;
+    let header: Vec<String> = header.iter().map(|name| format!("<th>{name}</th>")).collect();
+    writeln!(html, "<tr>{}</tr>", header.concat())?;
+    let mut left_out = 0;
+    for (i, row) in rows.enumerate() {
+        if i >= MAX_ROWS {
+            left_out += 1;
+            continue;
+        }
+        let cells: Vec<String> = row.iter()
+            .map(|cell| if cell.parse::<f64>().is_ok() { format!("<td class=\"n\">{cell}</td>") } else { format!("<td>{cell}</td>") })
+            .collect();
+        writeln!(html, "<tr>{}</tr>", cells.concat())?;
+    }
+    writeln!(html, "</table>")?;
+    if left_out > 0 {
+        writeln!(html, "<p>And {left_out} more</p>")?;
+    }
+    Ok(())
+}
+
+fn css_color(name: &str) -> String {
+    let [r, g, b, _] = hashed_color(name);
+    format!("#{r:02x}{g:02x}{b:02x}")
+}
+
+fn escape(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            _ => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+fn base64(bytes: &[u8]) -> String {
+    const ALPHABET: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+    let mut encoded = String::with_capacity(bytes.len().div_ceil(3) * 4);
+    for group in bytes.chunks(3) {
+        let n = (group[0] as u32) << 16 | (*group.get(1).unwrap_or(&0) as u32) << 8 | *group.get(2).unwrap_or(&0) as u32;
+        for i in 0..4 {
+            if i <= group.len() {
+                encoded.push(ALPHABET[(n >> (18 - 6 * i) & 63) as usize] as char);
+            } else {
+                encoded.push('=');
+            }
+        }
+    }
+    encoded
+}
+
+// Year, month and day of the days since 1970-01-01, from Howard Hinnant's
+// civil_from_days
+fn civil_date(days: u32) -> (i64, u32, u32) {
+    let z = days as i64 + 719468;
+    let era = z / 146097;
+    let day_of_era = z - era * 146097;
+    let year_of_era = (day_of_era - day_of_era / 1460 + day_of_era / 36524 - day_of_era / 146096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let mp = (5 * day_of_year + 2) / 153;
+    let day = (day_of_year - (153 * mp + 2) / 5 + 1) as u32;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 } as u32;
+    let year = year_of_era + era * 400 + i64::from(month <= 2);
+    (year, month, day)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn report() -> Report {
+        let mut map = Image::new(2, 1);
+        map.set(0, 0, [255, 0, 0, 255]);
+        Report {
+            title: "Base <north>".to_string(),
+            map: Some(map),
+            map_origin: (-32, 16),
+            blocks: vec![("minecraft:stone".to_string(), 30), ("minecraft:air".to_string(), 70)],
+            ores: BTreeMap::from([("minecraft:diamond_ore".to_string(), BTreeMap::from([(-58, 3), (-60, 5)]))]),
+            valuables: vec![("minecraft:diamond_ore".to_string(), BlockPos::new(1, -58, 2))],
+            spawners: vec![(Some("minecraft:zombie".to_string()), BlockPos::new(10, 20, 30)), (None, BlockPos::new(0, 0, 0))],
+            activity: vec![((0, 0), 1_700_000_000), ((0, 1), 1_700_000_100), ((1, 0), 1_600_000_000)],
+        }
+    }
+
+    #[test]
+    fn encodes_base64_with_padding() {
+        assert_eq!(base64(b""), "");
+        assert_eq!(base64(b"M"), "TQ==");
+        assert_eq!(base64(b"Ma"), "TWE=");
+        assert_eq!(base64(b"Man"), "TWFu");
+        assert_eq!(base64(&[0xff, 0xef, 0x00, 0x01]), "/+8AAQ==");
+    }
+
+    #[test]
+    fn turns_days_into_dates() {
+        assert_eq!(civil_date(0), (1970, 1, 1));
+        assert_eq!(civil_date(1_700_000_000 / SECONDS_PER_DAY), (2023, 11, 14));
+        assert_eq!(civil_date(11016), (2000, 2, 29));
+    }
+
+    #[test]
+    fn writes_every_section() {
+        let html = write_html(&report()).unwrap();
+        assert!(html.contains("<title>Base &lt;north&gt;</title>"));
+        assert!(html.contains("src=\"data:image/png;base64,iVBORw0KGgo"));
+        assert!(html.contains("block (-32, 16)"));
+        // Most common first
+        let air = html.find("<td>minecraft:air</td><td class=\"n\">70</td><td>70.00%</td>").unwrap();
+        assert!(air < html.find("<td>minecraft:stone</td>").unwrap());
+        assert!(html.contains("<polyline"));
+        assert!(html.contains("<td class=\"n\">8</td><td class=\"n\">-60</td>"));
+        assert!(html.contains("<td>minecraft:diamond_ore</td><td>1 -58 2</td>"));
+        assert!(html.contains("<td>minecraft:zombie</td>"));
+        assert!(html.contains("<td>nothing</td>"));
+        assert!(html.contains("<td>2023-11-14</td><td class=\"n\">2</td>"));
+        assert!(html.contains("<td>2020-09-13</td><td class=\"n\">1</td>"));
+        assert!(!html.contains("None found"));
+    }
+
+    #[test]
+    fn says_when_a_section_is_empty() {
+        let report = Report { map: None, ores: BTreeMap::new(), valuables: Vec::new(), spawners: Vec::new(), activity: Vec::new(), ..report() };
+        let html = write_html(&report).unwrap();
+        assert_eq!(html.matches("None found").count(), 4);
+        assert!(!html.contains("<svg"));
+    }
+}
@@ -0,0 +1,277 @@
+use anyhow::{ Result, bail };
+use flate2::{ Compression, write::GzEncoder };
+use std::{ collections::HashMap, fs::File, io::{ BufWriter, Write }, path::Path, time::SystemTime };
+
+use crate::chunk::pack_straddled_indices;
+use crate::nbt::{ Tag, TagPayload };
+use crate::pos::BlockPos;
+use crate::world::WorldView;
+
+const AIR: &str = "minecraft:air";
+
+// A box of blocks cut out of a world, ready to be written in one of the
+// schematic formats building mods read
+pub struct Schematic {
+    origin: BlockPos,
+    // Along x, y and z
+    size: (usize, usize, usize),
+    data_version: i32,
+    // Block states with their properties, air first
+    palette: Vec<String>,
+    // Ordered y, then z, then x, like in chunk sections
+    blocks: Vec<usize>,
+    // Block entity NBT with coordinates relative to the origin
+    block_entities: Vec<(BlockPos, TagPayload)>,
+}
+
+impl Schematic {
+    // Cuts out the inclusive box between two corners. Blocks in chunks the
+    // view hasn't loaded come out as air.
+    pub fn from_view(view: &WorldView, a: BlockPos, b: BlockPos) -> Result<Schematic> {
+        let origin = BlockPos::new(a.x.min(b.x), a.y.min(b.y), a.z.min(b.z));
+        let size = ((a.x.abs_diff(b.x) + 1) as usize, (a.y.abs_diff(b.y) + 1) as usize, (a.z.abs_diff(b.z) + 1) as usize);
+        // Sponge schematics store each side as a short
+        if size.0 > u16::MAX as usize || size.1 > u16::MAX as usize || size.2 > u16::MAX as usize {
+            bail!("Selection is {}x{}x{}, more than schematics can hold", size.0, size.1, size.2);
+        }
+
+        let mut palette = vec![AIR.to_string()];
+        let mut palette_indices: HashMap<String, usize> = HashMap::from([(AIR.to_string(), 0)]);
+        let mut blocks = Vec::with_capacity(size.0 * size.1 * size.2);
+
+        for y in 0..size.1 as i32 {
+            for z in 0..size.2 as i32 {
+                for x in 0..size.0 as i32 {
+                    let state = match view.block_at(origin.offset(x, y, z)) {
+                        Some(block) if !block.is_outside_world() => block.state_string(),
+                        _ => AIR.to_string(),
+                    };
+                    let index = *palette_indices.entry(state).or_insert_with_key(|state| {
+                        palette.push(state.clone());
+                        palette.len() - 1
+                    });
+                    blocks.push(index);
+                }
+            }
+        }
+
+        let inside = |pos: BlockPos| {
+            let relative = (pos.x - origin.x, pos.y - origin.y, pos.z - origin.z);
+            (0..size.0 as i32).contains(&relative.0) && (0..size.1 as i32).contains(&relative.1) && (0..size.2 as i32).contains(&relative.2)
+        };
+        let mut block_entities: Vec<(BlockPos, TagPayload)> = view.chunks()
+            .flat_map(|chunk| chunk.block_entities())
+            .filter(|block_entity| inside(block_entity.pos()))
+            .map(|block_entity| {
+                let pos = block_entity.pos();
+                (BlockPos::new(pos.x - origin.x, pos.y - origin.y, pos.z - origin.z), block_entity.nbt().clone())
+            })
+            .collect();
+        block_entities.sort_by_key(|(pos, _)| *pos);
+
+        let data_version = view.chunks().map(|chunk| chunk.data_version()).max().unwrap_or(0);
+
+        Ok(Schematic { origin, size, data_version, palette, blocks, block_entities })
+    }
+
+    // A schematic of just the given blocks, air everywhere else in the box around them
+    pub fn from_blocks(blocks: &[(BlockPos, String)], data_version: i32) -> Result<Schematic> {
+        let Some(first) = blocks.first() else {
+            bail!("Nothing to put in the schematic");
+        };
+        let (mut min, mut max) = (first.0, first.0);
+        for (pos, _) in blocks {
+            min = BlockPos::new(min.x.min(pos.x), min.y.min(pos.y), min.z.min(pos.z));
+            max = BlockPos::new(max.x.max(pos.x), max.y.max(pos.y), max.z.max(pos.z));
+        }
+        let size = ((max.x - min.x + 1) as usize, (max.y - min.y + 1) as usize, (max.z - min.z + 1) as usize);
+        if size.0 > u16::MAX as usize || size.1 > u16::MAX as usize || size.2 > u16::MAX as usize {
+            bail!("Blocks span {}x{}x{}, more than schematics can hold", size.0, size.1, size.2);
+        }
+
+        let mut palette = vec![AIR.to_string()];
+        let mut indices = vec![0; size.0 * size.1 * size.2];
+        for (pos, state) in blocks {
+            let index = match palette.iter().position(|known| known == state) {
+                Some(index) => index,
+                None => {
+                    palette.push(state.clone());
+                    palette.len() - 1
+                },
+            };
+            let (x, y, z) = ((pos.x - min.x) as usize, (pos.y - min.y) as usize, (pos.z - min.z) as usize);
+            indices[(y * size.2 + z) * size.0 + x] = index;
+        }
+
+        Ok(Schematic { origin: min, size, data_version, palette, blocks: indices, block_entities: Vec::new() })
+    }
+
+    pub fn origin(&self) -> BlockPos {
+        self.origin
+    }
+
+    pub fn size(&self) -> (usize, usize, usize) {
+        self.size
+    }
+
+    pub fn palette(&self) -> &[String] {
+        &self.palette
+    }
+
+    // Coordinates are relative to the origin
+    pub fn block_at(&self, x: usize, y: usize, z: usize) -> &str {
+        &self.palette[self.blocks[(y * self.size.2 + z) * self.size.0 + x]]
+    }
+
+    // Sponge schematic version 2, as WorldEdit writes them
+    pub fn to_sponge_nbt(&self) -> Tag {
+        let palette = self.palette.iter().enumerate()
+            .map(|(i, state)| Tag { name: state.clone(), payload: TagPayload::Int(i as i32) })
+            .collect();
+
+        // Indices are written as varints, so small palettes take a byte per block
+        let mut block_data = Vec::with_capacity(self.blocks.len());
+        for &index in &self.blocks {
+            let mut value = index as u32;
+            while value >= 0x80 {
+                block_data.push((value as u8 & 0x7f | 0x80) as i8);
+                value >>= 7;
+            }
+            block_data.push(value as i8);
+        }
+
+        let block_entities = self.block_entities.iter()
+            .map(|(pos, nbt)| {
+                let mut tags = vec![
+                    Tag { name: "Pos".to_string(), payload: TagPayload::IntArray(vec![pos.x, pos.y, pos.z]) },
+                ];
+                if let TagPayload::Compound(fields) = nbt {
+                    for field in fields {
+                        match field.name.as_str() {
+                            "x" | "y" | "z" | "keepPacked" => {},
+                            "id" => tags.push(Tag { name: "Id".to_string(), payload: field.payload.clone() }),
+                            _ => tags.push(field.clone()),
+                        }
+                    }
+                }
+                TagPayload::Compound(tags.into())
+            })
+            .collect();
+
+        let tag = |name: &str, payload: TagPayload| Tag { name: name.to_string(), payload };
+        Tag {
+            name: "Schematic".to_string(),
+            payload: TagPayload::Compound(vec![
+                tag("Version", TagPayload::Int(2)),
+                tag("DataVersion", TagPayload::Int(self.data_version)),
+                tag("Width", TagPayload::Short(self.size.0 as u16 as i16)),
+                tag("Height", TagPayload::Short(self.size.1 as u16 as i16)),
+                tag("Length", TagPayload::Short(self.size.2 as u16 as i16)),
+                tag("Offset", TagPayload::IntArray(vec![self.origin.x, self.origin.y, self.origin.z])),
+                tag("PaletteMax", TagPayload::Int(self.palette.len() as i32)),
+                tag("Palette", TagPayload::Compound(palette)),
+                tag("BlockData", TagPayload::ByteArray(block_data)),
+                tag("BlockEntities", TagPayload::List(block_entities)),
+            ].into()),
+        }
+    }
+
+    // Writes a gzipped .schem file
+    pub fn write_sponge(&self, path: impl AsRef<Path>) -> Result<()> {
+        write_gzipped(path.as_ref(), &self.to_sponge_nbt())
+    }
+
+    // Litematica schematic with a single region of the given name
+    pub fn to_litematic_nbt(&self, name: &str) -> Tag {
+        let tag = |name: &str, payload: TagPayload| Tag { name: name.to_string(), payload };
+        let vector = |x: i32, y: i32, z: i32| TagPayload::Compound(vec![
+            tag("x", TagPayload::Int(x)),
+            tag("y", TagPayload::Int(y)),
+            tag("z", TagPayload::Int(z)),
+        ].into());
+        let size = vector(self.size.0 as i32, self.size.1 as i32, self.size.2 as i32);
+
+        let palette = self.palette.iter()
+            .map(|state| {
+                let (name, properties) = split_state(state);
+                let mut entry = vec![tag("Name", TagPayload::String(name.to_string()))];
+                if !properties.is_empty() {
+                    let properties = properties.into_iter().map(|(key, value)| tag(key, TagPayload::String(value.to_string()))).collect();
+                    entry.push(tag("Properties", TagPayload::Compound(properties)));
+                }
+                TagPayload::Compound(entry.into())
+            })
+            .collect();
+
+        // At least 2 bits per block, like Litematica itself writes
+        let bits = (usize::BITS - (self.palette.len() - 1).leading_zeros()).max(2) as usize;
+        let block_states = pack_straddled_indices(&self.blocks, bits);
+
+        // Tile entities keep their id but get coordinates relative to the region
+        let tile_entities = self.block_entities.iter()
+            .map(|(pos, nbt)| {
+                let mut tags = vec![tag("x", TagPayload::Int(pos.x)), tag("y", TagPayload::Int(pos.y)), tag("z", TagPayload::Int(pos.z))];
+                if let TagPayload::Compound(fields) = nbt {
+                    tags.extend(fields.iter().filter(|field| !matches!(field.name.as_str(), "x" | "y" | "z" | "keepPacked")).cloned());
+                }
+                TagPayload::Compound(tags.into())
+            })
+            .collect();
+
+        let volume = (self.size.0 * self.size.1 * self.size.2) as i32;
+        let non_air = self.blocks.iter().filter(|&&index| index != 0).count() as i32;
+        let now = SystemTime::now().duration_since(SystemTime::UNIX_EPOCH).map_or(0, |time| time.as_millis() as i64);
+
+        let region = TagPayload::Compound(vec![
+            tag("Position", vector(0, 0, 0)),
+            tag("Size", size.clone()),
+            tag("BlockStatePalette", TagPayload::List(palette)),
+            tag("BlockStates", TagPayload::LongArray(block_states)),
+            tag("TileEntities", TagPayload::List(tile_entities)),
+            tag("Entities", TagPayload::List(Vec::new())),
+            tag("PendingBlockTicks", TagPayload::List(Vec::new())),
+            tag("PendingFluidTicks", TagPayload::List(Vec::new())),
+        ].into());
+
+        Tag {
+            name: String::new(),
+            payload: TagPayload::Compound(vec![
+                tag("MinecraftDataVersion", TagPayload::Int(self.data_version)),
+                tag("Version", TagPayload::Int(6)),
+                tag("Metadata", TagPayload::Compound(vec![
+                    tag("Name", TagPayload::String(name.to_string())),
+                    tag("Author", TagPayload::String("path-miner".to_string())),
+                    tag("Description", TagPayload::String(String::new())),
+                    tag("RegionCount", TagPayload::Int(1)),
+                    tag("TotalVolume", TagPayload::Int(volume)),
+                    tag("TotalBlocks", TagPayload::Int(non_air)),
+                    tag("TimeCreated", TagPayload::Long(now)),
+                    tag("TimeModified", TagPayload::Long(now)),
+                    tag("EnclosingSize", size),
+                ].into())),
+                tag("Regions", TagPayload::Compound(vec![tag(name, region)].into())),
+            ].into()),
+        }
+    }
+
+    // Writes a gzipped .litematic file
+    pub fn write_litematic(&self, path: impl AsRef<Path>, name: &str) -> Result<()> {
+        write_gzipped(path.as_ref(), &self.to_litematic_nbt(name))
+    }
+}
+
+fn write_gzipped(path: &Path, tag: &Tag) -> Result<()> {
+    let mut encoder = GzEncoder::new(BufWriter::new(File::create(path)?), Compression::default());
+    tag.write(&mut encoder)?;
+    encoder.finish()?.flush()?;
+    Ok(())
+}
+
+// Takes minecraft:oak_log[axis=y] apart into the name and its properties
+fn split_state(state: &str) -> (&str, Vec<(&str, &str)>) {
+    let Some((name, properties)) = state.strip_suffix(']').and_then(|state| state.split_once('[')) else {
+        return (state, Vec::new());
+    };
+    let properties = properties.split(',').filter_map(|property| property.split_once('=')).collect();
+    (name, properties)
+}
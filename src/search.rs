@@ -0,0 +1,507 @@
+use anyhow::{ Result, Context, bail };
+use std::{ path::PathBuf, str::FromStr, sync::{ Condvar, Mutex, atomic::{ AtomicBool, AtomicUsize, Ordering }, mpsc::{ SyncSender, sync_channel } }, thread, time::Duration };
+
+use crate::{ chunk::{ BlockType, Chunk }, claims::ChunkPolicy, nbt::ParseOptions, pos::BlockPos, region::{ Region, chunk_at_index }, world::{ Dimension, ScanProgress } };
+
+// Batches of hits in flight between the workers and the writer. A batch is a
+// chunk's hits, or a region's when keeping chunk order.
+const BATCHES_IN_FLIGHT: usize = 64;
+
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct Hit {
+    // None for region files read on their own
+    pub dimension: Option<Dimension>,
+    pub block: String,
+    pub pos: BlockPos,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum HitFormat {
+    // Dimension, block and position separated by spaces
+    Text,
+    // One JSON object per line
+    Ndjson,
+    // Comma separated, with a header row
+    Csv,
+}
+
+impl FromStr for HitFormat {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s {
+            "text" => Ok(HitFormat::Text),
+            "ndjson" | "jsonl" => Ok(HitFormat::Ndjson),
+            "csv" => Ok(HitFormat::Csv),
+            _ => bail!("Unknown format {s:?}, expected text, ndjson or csv"),
+        }
+    }
+}
+
+impl HitFormat {
+    pub fn header(self) -> Option<&'static str> {
+        match self {
+            HitFormat::Csv => Some("dimension,block,x,y,z"),
+            _ => None,
+        }
+    }
+
+    pub fn line(self, hit: &Hit) -> String {
+        let Hit { dimension, block, pos } = hit;
+        match self {
+            HitFormat::Text => match dimension {
+                Some(dimension) => format!("{dimension:?} {block} {pos}"),
+                None => format!("{block} {pos}"),
+            },
+            HitFormat::Ndjson => serde_json::json!({
+                "dimension": dimension.map(Dimension::id),
+                "block": block,
+                "x": pos.x,
+                "y": pos.y,
+                "z": pos.z,
+            }).to_string(),
+            HitFormat::Csv => format!("{},{block},{},{},{}", dimension.map_or("", Dimension::id), pos.x, pos.y, pos.z),
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum HitOrder {
+    // As the workers find them, fastest
+    Unordered,
+    // A region's hits all at once, by chunk index, and the regions in the
+    // order they were given, so the output is the same on every run. Each
+    // worker holds back at most the region it's reading until its turn.
+    Chunk,
+}
+
+impl FromStr for HitOrder {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s {
+            "unordered" => Ok(HitOrder::Unordered),
+            "chunk" => Ok(HitOrder::Chunk),
+            _ => bail!("Unknown order {s:?}, expected unordered or chunk"),
+        }
+    }
+}
+
+// A region file to search, and the dimension its chunks are in
+#[derive(Clone, Debug)]
+pub struct SearchRegion {
+    pub dimension: Option<Dimension>,
+    pub x: i32,
+    pub z: i32,
+    pub path: PathBuf,
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct SearchSummary {
+    pub hits: usize,
+    // Chunks that couldn't be read and were skipped
+    pub skipped: usize,
+    // Whether the search stopped at the limit with more hits left
+    pub truncated: bool,
+    pub progress: ScanProgress,
+}
+
+enum Message {
+    Hits(Vec<Hit>),
+    Failed(anyhow::Error),
+    // Chunks read and left out by the policy
+    RegionDone(usize, usize),
+}
+
+// Searches regions on worker threads, handing the hits to a single writer
+// as they come in. Workers wait for the writer when it falls behind, so
+// memory doesn't grow with the number of hits.
+pub struct Search<'a> {
+    threads: usize,
+    order: HitOrder,
+    limit: Option<usize>,
+    strict: bool,
+    options: ParseOptions,
+    modified_since: Option<u32>,
+    policy: Option<&'a ChunkPolicy>,
+    on_progress: Option<Box<dyn FnMut(ScanProgress) + 'a>>,
+}
+
+impl<'a> Search<'a> {
+    pub fn new() -> Search<'a> {
+        Search {
+            threads: thread::available_parallelism().map_or(4, |threads| threads.get()),
+            order: HitOrder::Unordered,
+            limit: None,
+            strict: false,
+            options: ParseOptions::for_block_search(),
+            modified_since: None,
+            policy: None,
+            on_progress: None,
+        }
+    }
+
+    pub fn threads(mut self, threads: usize) -> Self {
+        self.threads = threads.max(1);
+        self
+    }
+
+    pub fn order(mut self, order: HitOrder) -> Self {
+        self.order = order;
+        self
+    }
+
+    // Stops the workers once this many hits have been written
+    pub fn limit(mut self, limit: usize) -> Self {
+        self.limit = Some(limit);
+        self
+    }
+
+    // Fails on the first chunk that can't be read instead of skipping it
+    pub fn strict(mut self) -> Self {
+        self.strict = true;
+        self
+    }
+
+    pub fn with_options(mut self, options: ParseOptions) -> Self {
+        self.options = options;
+        self
+    }
+
+    pub fn modified_since(mut self, since: u32) -> Self {
+        self.modified_since = Some(since);
+        self
+    }
+
+    pub fn only_allowed(mut self, policy: &'a ChunkPolicy) -> Self {
+        self.policy = Some(policy);
+        self
+    }
+
+    // Calls f on the writer's thread every time a region is done
+    pub fn on_progress(mut self, f: impl FnMut(ScanProgress) + 'a) -> Self {
+        self.on_progress = Some(Box::new(f));
+        self
+    }
+
+    // Chunks come with only the sections keep wants, see Chunk::from_nbt_where.
+    // find gives the hits of a chunk, write is called with each of them in
+    // turn on the calling thread.
+    pub fn run(
+        mut self,
+        regions: &[SearchRegion],
+        keep: impl Fn(&BlockType) -> bool + Sync,
+        find: impl Fn(Option<Dimension>, &Chunk) -> Vec<Hit> + Sync,
+        mut write: impl FnMut(&Hit) -> Result<()>,
+    ) -> Result<SearchSummary> {
+        let next = AtomicUsize::new(0);
+        let cancelled = AtomicBool::new(false);
+        let turn = Turn::default();
+        let mut summary = SearchSummary { progress: ScanProgress { regions_total: regions.len(), ..ScanProgress::default() }, ..SearchSummary::default() };
+
+        let mut on_progress = self.on_progress.take();
+        thread::scope(|scope| {
+            let (sender, receiver) = sync_channel(BATCHES_IN_FLIGHT);
+            for _ in 0..self.threads.min(regions.len()) {
+                let sender = sender.clone();
+                let worker = Worker {
+                    order: self.order,
+                    options: &self.options,
+                    modified_since: self.modified_since,
+                    policy: self.policy,
+                    keep: &keep,
+                    find: &find,
+                    cancelled: &cancelled,
+                    turn: &turn,
+                    sender,
+                };
+                scope.spawn(|| {
+                    let worker = worker;
+                    loop {
+                        let i = next.fetch_add(1, Ordering::Relaxed);
+                        let Some(region) = regions.get(i) else {
+                            break;
+                        };
+                        if !worker.search_region(i, region) {
+                            break;
+                        }
+                    }
+                });
+            }
+            drop(sender);
+
+            // Leaving early drops the receiver, which stops the workers at their next send
+            let result = (|| {
+                for message in &receiver {
+                    match message {
+                        Message::Hits(hits) => {
+                            for hit in &hits {
+                                if self.limit.is_some_and(|limit| summary.hits >= limit) {
+                                    summary.truncated = true;
+                                    return Ok(());
+                                }
+                                write(hit)?;
+                                summary.hits += 1;
+                            }
+                        },
+                        Message::Failed(e) if self.strict => return Err(e),
+                        Message::Failed(e) => {
+                            log::warn!("Skipping chunk: {e:#}");
+                            summary.skipped += 1;
+                        },
+                        Message::RegionDone(chunks, excluded) => {
+                            summary.progress.regions_done += 1;
+                            summary.progress.chunks += chunks;
+                            summary.progress.excluded += excluded;
+                            if let Some(on_progress) = &mut on_progress {
+                                on_progress(summary.progress);
+                            }
+                        },
+                    }
+                }
+                Ok(())
+            })();
+            cancelled.store(true, Ordering::Relaxed);
+            drop(receiver);
+            result
+        })?;
+
+        Ok(summary)
+    }
+}
+
+// Whose turn it is to hand over their region's hits, by the region's place
+// in the list, when keeping chunk order
+#[derive(Default)]
+struct Turn {
+    region: Mutex<usize>,
+    passed: Condvar,
+}
+
+impl Default for Search<'_> {
+    fn default() -> Self {
+        Search::new()
+    }
+}
+
+struct Worker<'s, K, F> {
+    order: HitOrder,
+    options: &'s ParseOptions,
+    modified_since: Option<u32>,
+    policy: Option<&'s ChunkPolicy>,
+    keep: &'s K,
+    find: &'s F,
+    cancelled: &'s AtomicBool,
+    turn: &'s Turn,
+    sender: SyncSender<Message>,
+}
+
+impl<K: Fn(&BlockType) -> bool, F: Fn(Option<Dimension>, &Chunk) -> Vec<Hit>> Worker<'_, K, F> {
+    // False once the search has been called off
+    fn search_region(&self, i: usize, info: &SearchRegion) -> bool {
+        let mut region = match Region::open(&info.path) {
+            Ok(region) => region,
+            Err(e) => {
+                let e = e.context(format!("Could not read region {}", info.path.display()));
+                return self.send(Message::Failed(e)) && self.hand_over(i, Vec::new()) && self.send(Message::RegionDone(0, 0));
+            },
+        };
+
+        let (mut chunks, mut excluded) = (0, 0);
+        let mut held_back = Vec::new();
+        for index in 0..1024 {
+            if !region.has_chunk(index) {
+                continue;
+            }
+            if self.cancelled.load(Ordering::Relaxed) {
+                return false;
+            }
+            let (x, z) = chunk_at_index(info.x, info.z, index);
+            if self.policy.is_some_and(|policy| !policy.allows(info.dimension, x, z)) {
+                excluded += 1;
+                continue;
+            }
+            if self.modified_since.is_some_and(|since| region.timestamps()[index] < since) {
+                continue;
+            }
+
+            chunks += 1;
+            let chunk = region.read_chunk(index, self.options)
+                .and_then(|tag| tag.map(|tag| Chunk::from_nbt_where(tag, self.keep)).transpose())
+                .with_context(|| format!("Could not read chunk {index} of region {}", info.path.display()));
+            match chunk {
+                Ok(Some(chunk)) => {
+                    let hits = (self.find)(info.dimension, &chunk);
+                    if hits.is_empty() {
+                        continue;
+                    }
+                    match self.order {
+                        HitOrder::Unordered => if !self.send(Message::Hits(hits)) {
+                            return false;
+                        },
+                        HitOrder::Chunk => held_back.extend(hits),
+                    }
+                },
+                Ok(None) => {},
+                Err(e) => if !self.send(Message::Failed(e)) {
+                    return false;
+                },
+            }
+        }
+
+        self.hand_over(i, held_back) && self.send(Message::RegionDone(chunks, excluded))
+    }
+
+    // Sends the hits held back for the i-th region once the regions before it
+    // have sent theirs
+    fn hand_over(&self, i: usize, hits: Vec<Hit>) -> bool {
+        if self.order == HitOrder::Unordered {
+            return true;
+        }
+        let mut turn = self.turn.region.lock().unwrap();
+        while *turn != i {
+            if self.cancelled.load(Ordering::Relaxed) {
+                return false;
+            }
+            turn = self.turn.passed.wait_timeout(turn, Duration::from_millis(50)).unwrap().0;
+        }
+        let sent = hits.is_empty() || self.send(Message::Hits(hits));
+        *turn += 1;
+        self.turn.passed.notify_all();
+        sent
+    }
+
+    fn send(&self, message: Message) -> bool {
+        self.sender.send(message).is_ok()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{ chunk::BlockState, nbt::{ Tag, TagPayload }, region::{ chunk_index_in_region, put_chunks } };
+    use std::{ collections::HashSet, fs };
+
+    fn tag(name: &str, payload: TagPayload) -> Tag {
+        Tag { name: name.to_string(), payload }
+    }
+
+    // Stone from y 0 to 15, 4096 blocks of it
+    fn stone_chunk(x: i32, z: i32) -> Tag {
+        let stone = TagPayload::Compound(vec![tag("Name", TagPayload::String("minecraft:stone".to_string()))].into());
+        let block_states = vec![tag("palette", TagPayload::List(vec![stone]))];
+        let section = TagPayload::Compound(vec![tag("Y", TagPayload::Byte(0)), tag("block_states", TagPayload::Compound(block_states.into()))].into());
+        let root = vec![
+            tag("DataVersion", TagPayload::Int(3700)),
+            tag("xPos", TagPayload::Int(x)),
+            tag("zPos", TagPayload::Int(z)),
+            tag("yPos", TagPayload::Int(0)),
+            tag("sections", TagPayload::List(vec![section])),
+        ];
+        tag("", TagPayload::Compound(root.into()))
+    }
+
+    // 25 stone chunks spread over three regions, 102400 blocks of stone
+    fn stone_world(name: &str) -> (PathBuf, Vec<SearchRegion>) {
+        let dir = std::env::temp_dir().join(format!("path-miner-search-{name}-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let mut regions = Vec::new();
+        for (region_x, region_z, count) in [(0, 0, 9), (1, 0, 8), (0, -1, 8)] {
+            let chunks: Vec<(usize, Tag)> = (0..count).map(|i| {
+                let (x, z) = (region_x * 32 + i % 3, region_z * 32 + i / 3);
+                (chunk_index_in_region(x, z), stone_chunk(x, z))
+            }).collect();
+            let path = dir.join(format!("r.{region_x}.{region_z}.mca"));
+            put_chunks(&path, &chunks.iter().map(|(index, chunk)| (*index, chunk)).collect::<Vec<_>>()).unwrap();
+            regions.push(SearchRegion { dimension: Some(Dimension::Overworld), x: region_x, z: region_z, path });
+        }
+        (dir, regions)
+    }
+
+    fn find_stone(dimension: Option<Dimension>, chunk: &Chunk) -> Vec<Hit> {
+        chunk.find_states(&[BlockState::new("minecraft:stone")]).into_iter()
+            .map(|(pos, block)| Hit { dimension, block: block.name.to_string(), pos })
+            .collect()
+    }
+
+    #[test]
+    fn writes_every_hit_once() {
+        let (dir, regions) = stone_world("all");
+        let mut seen = HashSet::new();
+        let summary = Search::new().threads(4).run(&regions, |_| true, find_stone, |hit| {
+            assert!(seen.insert(hit.pos));
+            Ok(())
+        }).unwrap();
+        assert_eq!(summary.hits, 102400);
+        assert_eq!(seen.len(), 102400);
+        assert!(!summary.truncated);
+        assert_eq!(summary.progress.regions_done, 3);
+        assert_eq!(summary.progress.chunks, 25);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn keeps_chunks_together_in_order() {
+        let (dir, regions) = stone_world("ordered");
+        let mut chunks: Vec<(i32, i32)> = Vec::new();
+        Search::new().threads(3).order(HitOrder::Chunk).run(&regions, |_| true, find_stone, |hit| {
+            let chunk = (hit.pos.chunk_x(), hit.pos.chunk_z());
+            if chunks.last() != Some(&chunk) {
+                chunks.push(chunk);
+            }
+            Ok(())
+        }).unwrap();
+
+        // Every chunk in one run, the regions in the order given and a region's chunks by index
+        assert_eq!(chunks.len(), 25);
+        let in_order: Vec<(i32, i32)> = chunks.chunk_by(|a, b| (a.0.div_euclid(32), a.1.div_euclid(32)) == (b.0.div_euclid(32), b.1.div_euclid(32)))
+            .map(|region| {
+                assert!(region.windows(2).all(|pair| chunk_index_in_region(pair[0].0, pair[0].1) < chunk_index_in_region(pair[1].0, pair[1].1)));
+                (region[0].0.div_euclid(32), region[0].1.div_euclid(32))
+            })
+            .collect();
+        assert_eq!(in_order, vec![(0, 0), (1, 0), (0, -1)]);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn stops_at_the_limit() {
+        let (dir, regions) = stone_world("limit");
+        let mut written = 0;
+        let summary = Search::new().threads(2).limit(1000).run(&regions, |_| true, find_stone, |_| {
+            written += 1;
+            Ok(())
+        }).unwrap();
+        assert_eq!((written, summary.hits), (1000, 1000));
+        assert!(summary.truncated);
+
+        // Reaching the limit exactly isn't cutting anything off
+        let summary = Search::new().limit(102400).run(&regions, |_| true, find_stone, |_| Ok(())).unwrap();
+        assert_eq!(summary.hits, 102400);
+        assert!(!summary.truncated);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn stops_when_writing_fails() {
+        let (dir, regions) = stone_world("broken-pipe");
+        let mut written = 0;
+        let result = Search::new().run(&regions, |_| true, find_stone, |_| {
+            written += 1;
+            if written == 10 { bail!("Broken pipe") } else { Ok(()) }
+        });
+        assert!(result.is_err());
+        assert_eq!(written, 10);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn formats_hits() {
+        let hit = Hit { dimension: Some(Dimension::Nether), block: "minecraft:ancient_debris".to_string(), pos: BlockPos::new(1, 15, -3) };
+        assert_eq!(HitFormat::Text.line(&hit), "Nether minecraft:ancient_debris 1 15 -3");
+        assert_eq!(HitFormat::Ndjson.line(&hit), r#"{"dimension":"minecraft:the_nether","block":"minecraft:ancient_debris","x":1,"y":15,"z":-3}"#);
+        assert_eq!(HitFormat::Csv.line(&hit), "minecraft:the_nether,minecraft:ancient_debris,1,15,-3");
+        let hit = Hit { dimension: None, ..hit };
+        assert_eq!(HitFormat::Ndjson.line(&hit), r#"{"dimension":null,"block":"minecraft:ancient_debris","x":1,"y":15,"z":-3}"#);
+    }
+}
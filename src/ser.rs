@@ -0,0 +1,365 @@
+use serde::ser::{ self, Serialize };
+use serde::Deserialize;
+
+use crate::nbt::{ Compound, SerdeError, Tag, TagPayload };
+
+// Newtype names the serializer looks for to write array tags instead of Lists
+const BYTE_ARRAY: &str = "__path_miner_byte_array";
+const INT_ARRAY: &str = "__path_miner_int_array";
+const LONG_ARRAY: &str = "__path_miner_long_array";
+
+// A Vec<i8> is written as a List of Bytes. Wrap it in one of these to get the
+// matching array tag instead. They read back from both arrays and Lists.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Deserialize)]
+pub struct ByteArray(pub Vec<i8>);
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Deserialize)]
+pub struct IntArray(pub Vec<i32>);
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Deserialize)]
+pub struct LongArray(pub Vec<i64>);
+
+impl Serialize for ByteArray {
+    fn serialize<S: ser::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_newtype_struct(BYTE_ARRAY, &self.0)
+    }
+}
+
+impl Serialize for IntArray {
+    fn serialize<S: ser::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_newtype_struct(INT_ARRAY, &self.0)
+    }
+}
+
+impl Serialize for LongArray {
+    fn serialize<S: ser::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_newtype_struct(LONG_ARRAY, &self.0)
+    }
+}
+
+// Rust types map onto NBT like this:
+//
+// - bool is a Byte (0 or 1), i8..i64 are Byte, Short, Int and Long, f32 and f64
+//   are Float and Double. NBT has no unsigned types, so u8..u64 are stored in
+//   the signed type of the same width and wrap around.
+// - Strings and chars are Strings, byte slices are ByteArrays.
+// - Sequences and tuples are Lists, so all their elements need the same type.
+// - Structs and maps are Compounds. Map keys must be strings.
+// - None is left out of the compound it's in, and can't appear anywhere else.
+// - Unit enum variants are Strings. Other variants are a Compound with one
+//   entry named after the variant.
+pub fn to_payload<T: Serialize + ?Sized>(value: &T) -> Result<TagPayload, SerdeError> {
+    value.serialize(Serializer)?
+        .ok_or_else(|| SerdeError::new("None can only be written as a compound field"))
+}
+
+pub fn to_tag<T: Serialize + ?Sized>(name: &str, value: &T) -> Result<Tag, SerdeError> {
+    Ok(Tag { name: name.to_string(), payload: to_payload(value)? })
+}
+
+// Produces None for Option::None, so compounds can skip the field
+struct Serializer;
+
+impl ser::Serializer for Serializer {
+    type Ok = Option<TagPayload>;
+    type Error = SerdeError;
+
+    type SerializeSeq = ListSerializer;
+    type SerializeTuple = ListSerializer;
+    type SerializeTupleStruct = ListSerializer;
+    type SerializeTupleVariant = VariantSerializer<ListSerializer>;
+    type SerializeMap = CompoundSerializer;
+    type SerializeStruct = CompoundSerializer;
+    type SerializeStructVariant = VariantSerializer<CompoundSerializer>;
+
+    fn serialize_bool(self, v: bool) -> Result<Self::Ok, SerdeError> {
+        Ok(Some(TagPayload::Byte(v as i8)))
+    }
+
+    fn serialize_i8(self, v: i8) -> Result<Self::Ok, SerdeError> {
+        Ok(Some(TagPayload::Byte(v)))
+    }
+
+    fn serialize_i16(self, v: i16) -> Result<Self::Ok, SerdeError> {
+        Ok(Some(TagPayload::Short(v)))
+    }
+
+    fn serialize_i32(self, v: i32) -> Result<Self::Ok, SerdeError> {
+        Ok(Some(TagPayload::Int(v)))
+    }
+
+    fn serialize_i64(self, v: i64) -> Result<Self::Ok, SerdeError> {
+        Ok(Some(TagPayload::Long(v)))
+    }
+
+    fn serialize_u8(self, v: u8) -> Result<Self::Ok, SerdeError> {
+        self.serialize_i8(v as i8)
+    }
+
+    fn serialize_u16(self, v: u16) -> Result<Self::Ok, SerdeError> {
+        self.serialize_i16(v as i16)
+    }
+
+    fn serialize_u32(self, v: u32) -> Result<Self::Ok, SerdeError> {
+        self.serialize_i32(v as i32)
+    }
+
+    fn serialize_u64(self, v: u64) -> Result<Self::Ok, SerdeError> {
+        self.serialize_i64(v as i64)
+    }
+
+    fn serialize_f32(self, v: f32) -> Result<Self::Ok, SerdeError> {
+        Ok(Some(TagPayload::Float(v)))
+    }
+
+    fn serialize_f64(self, v: f64) -> Result<Self::Ok, SerdeError> {
+        Ok(Some(TagPayload::Double(v)))
+    }
+
+    fn serialize_char(self, v: char) -> Result<Self::Ok, SerdeError> {
+        Ok(Some(TagPayload::String(v.to_string())))
+    }
+
+    fn serialize_str(self, v: &str) -> Result<Self::Ok, SerdeError> {
+        Ok(Some(TagPayload::String(v.to_string())))
+    }
+
+    fn serialize_bytes(self, v: &[u8]) -> Result<Self::Ok, SerdeError> {
+        Ok(Some(TagPayload::ByteArray(v.iter().map(|&b| b as i8).collect())))
+    }
+
+    fn serialize_none(self) -> Result<Self::Ok, SerdeError> {
+        Ok(None)
+    }
+
+    fn serialize_some<T: Serialize + ?Sized>(self, value: &T) -> Result<Self::Ok, SerdeError> {
+        value.serialize(self)
+    }
+
+    fn serialize_unit(self) -> Result<Self::Ok, SerdeError> {
+        Ok(Some(TagPayload::Compound(Compound::new())))
+    }
+
+    fn serialize_unit_struct(self, _name: &'static str) -> Result<Self::Ok, SerdeError> {
+        self.serialize_unit()
+    }
+
+    fn serialize_unit_variant(self, _name: &'static str, _index: u32, variant: &'static str) -> Result<Self::Ok, SerdeError> {
+        self.serialize_str(variant)
+    }
+
+    fn serialize_newtype_struct<T: Serialize + ?Sized>(self, name: &'static str, value: &T) -> Result<Self::Ok, SerdeError> {
+        let payload = to_payload(value)?;
+        match name {
+            BYTE_ARRAY | INT_ARRAY | LONG_ARRAY => into_array(name, payload).map(Some),
+            _ => Ok(Some(payload)),
+        }
+    }
+
+    fn serialize_newtype_variant<T: Serialize + ?Sized>(self, _name: &'static str, _index: u32, variant: &'static str, value: &T) -> Result<Self::Ok, SerdeError> {
+        Ok(Some(TagPayload::Compound(vec![to_tag(variant, value)?].into())))
+    }
+
+    fn serialize_seq(self, len: Option<usize>) -> Result<ListSerializer, SerdeError> {
+        Ok(ListSerializer { items: Vec::with_capacity(len.unwrap_or(0)) })
+    }
+
+    fn serialize_tuple(self, len: usize) -> Result<ListSerializer, SerdeError> {
+        self.serialize_seq(Some(len))
+    }
+
+    fn serialize_tuple_struct(self, _name: &'static str, len: usize) -> Result<ListSerializer, SerdeError> {
+        self.serialize_seq(Some(len))
+    }
+
+    fn serialize_tuple_variant(self, _name: &'static str, _index: u32, variant: &'static str, len: usize) -> Result<Self::SerializeTupleVariant, SerdeError> {
+        Ok(VariantSerializer { variant, inner: self.serialize_seq(Some(len))? })
+    }
+
+    fn serialize_map(self, len: Option<usize>) -> Result<CompoundSerializer, SerdeError> {
+        Ok(CompoundSerializer { tags: Vec::with_capacity(len.unwrap_or(0)), key: None })
+    }
+
+    fn serialize_struct(self, _name: &'static str, len: usize) -> Result<CompoundSerializer, SerdeError> {
+        self.serialize_map(Some(len))
+    }
+
+    fn serialize_struct_variant(self, _name: &'static str, _index: u32, variant: &'static str, len: usize) -> Result<Self::SerializeStructVariant, SerdeError> {
+        Ok(VariantSerializer { variant, inner: self.serialize_map(Some(len))? })
+    }
+}
+
+fn into_array(name: &str, payload: TagPayload) -> Result<TagPayload, SerdeError> {
+    let items = match payload {
+        TagPayload::List(items) => items,
+        array @ (TagPayload::ByteArray(_) | TagPayload::IntArray(_) | TagPayload::LongArray(_)) => return Ok(array),
+        _ => return Err(SerdeError::new("array wrappers need a sequence")),
+    };
+
+    let mismatch = || SerdeError::new("array elements don't match the array type");
+    let array = match name {
+        BYTE_ARRAY => TagPayload::ByteArray(items.into_iter()
+            .map(|item| match item { TagPayload::Byte(x) => Ok(x), _ => Err(mismatch()) })
+            .collect::<Result<_, _>>()?),
+        INT_ARRAY => TagPayload::IntArray(items.into_iter()
+            .map(|item| match item { TagPayload::Int(x) => Ok(x), _ => Err(mismatch()) })
+            .collect::<Result<_, _>>()?),
+        _ => TagPayload::LongArray(items.into_iter()
+            .map(|item| match item { TagPayload::Long(x) => Ok(x), _ => Err(mismatch()) })
+            .collect::<Result<_, _>>()?),
+    };
+    Ok(array)
+}
+
+pub struct ListSerializer {
+    items: Vec<TagPayload>,
+}
+
+impl ListSerializer {
+    fn push<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), SerdeError> {
+        let item = to_payload(value)?;
+        if let Some(first) = self.items.first() {
+            if first.id() != item.id() {
+                return Err(SerdeError::new("all elements of an NBT list must have the same type"));
+            }
+        }
+        self.items.push(item);
+        Ok(())
+    }
+}
+
+impl ser::SerializeSeq for ListSerializer {
+    type Ok = Option<TagPayload>;
+    type Error = SerdeError;
+
+    fn serialize_element<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), SerdeError> {
+        self.push(value)
+    }
+
+    fn end(self) -> Result<Self::Ok, SerdeError> {
+        Ok(Some(TagPayload::List(self.items)))
+    }
+}
+
+impl ser::SerializeTuple for ListSerializer {
+    type Ok = Option<TagPayload>;
+    type Error = SerdeError;
+
+    fn serialize_element<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), SerdeError> {
+        self.push(value)
+    }
+
+    fn end(self) -> Result<Self::Ok, SerdeError> {
+        ser::SerializeSeq::end(self)
+    }
+}
+
+impl ser::SerializeTupleStruct for ListSerializer {
+    type Ok = Option<TagPayload>;
+    type Error = SerdeError;
+
+    fn serialize_field<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), SerdeError> {
+        self.push(value)
+    }
+
+    fn end(self) -> Result<Self::Ok, SerdeError> {
+        ser::SerializeSeq::end(self)
+    }
+}
+
+pub struct CompoundSerializer {
+    tags: Vec<Tag>,
+    key: Option<String>,
+}
+
+impl CompoundSerializer {
+    fn push<T: Serialize + ?Sized>(&mut self, name: &str, value: &T) -> Result<(), SerdeError> {
+        if let Some(payload) = value.serialize(Serializer)? {
+            self.tags.push(Tag { name: name.to_string(), payload });
+        }
+        Ok(())
+    }
+}
+
+impl ser::SerializeMap for CompoundSerializer {
+    type Ok = Option<TagPayload>;
+    type Error = SerdeError;
+
+    fn serialize_key<T: Serialize + ?Sized>(&mut self, key: &T) -> Result<(), SerdeError> {
+        match to_payload(key)? {
+            TagPayload::String(key) => {
+                self.key = Some(key);
+                Ok(())
+            },
+            _ => Err(SerdeError::new("compound keys must be strings")),
+        }
+    }
+
+    fn serialize_value<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), SerdeError> {
+        let key = self.key.take().ok_or_else(|| SerdeError::new("map value written before its key"))?;
+        self.push(&key, value)
+    }
+
+    fn end(self) -> Result<Self::Ok, SerdeError> {
+        Ok(Some(TagPayload::Compound(self.tags.into())))
+    }
+}
+
+impl ser::SerializeStruct for CompoundSerializer {
+    type Ok = Option<TagPayload>;
+    type Error = SerdeError;
+
+    fn serialize_field<T: Serialize + ?Sized>(&mut self, key: &'static str, value: &T) -> Result<(), SerdeError> {
+        self.push(key, value)
+    }
+
+    fn end(self) -> Result<Self::Ok, SerdeError> {
+        ser::SerializeMap::end(self)
+    }
+}
+
+// Wraps the variant's contents in a compound with a single entry
+pub struct VariantSerializer<S> {
+    variant: &'static str,
+    inner: S,
+}
+
+impl VariantSerializer<ListSerializer> {
+    fn end(self) -> Result<Option<TagPayload>, SerdeError> {
+        let payload = TagPayload::List(self.inner.items);
+        Ok(Some(TagPayload::Compound(vec![Tag { name: self.variant.to_string(), payload }].into())))
+    }
+}
+
+impl VariantSerializer<CompoundSerializer> {
+    fn end(self) -> Result<Option<TagPayload>, SerdeError> {
+        let payload = TagPayload::Compound(self.inner.tags.into());
+        Ok(Some(TagPayload::Compound(vec![Tag { name: self.variant.to_string(), payload }].into())))
+    }
+}
+
+impl ser::SerializeTupleVariant for VariantSerializer<ListSerializer> {
+    type Ok = Option<TagPayload>;
+    type Error = SerdeError;
+
+    fn serialize_field<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), SerdeError> {
+        self.inner.push(value)
+    }
+
+    fn end(self) -> Result<Self::Ok, SerdeError> {
+        VariantSerializer::<ListSerializer>::end(self)
+    }
+}
+
+impl ser::SerializeStructVariant for VariantSerializer<CompoundSerializer> {
+    type Ok = Option<TagPayload>;
+    type Error = SerdeError;
+
+    fn serialize_field<T: Serialize + ?Sized>(&mut self, key: &'static str, value: &T) -> Result<(), SerdeError> {
+        self.inner.push(key, value)
+    }
+
+    fn end(self) -> Result<Self::Ok, SerdeError> {
+        VariantSerializer::<CompoundSerializer>::end(self)
+    }
+}
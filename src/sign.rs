@@ -0,0 +1,89 @@
+use serde_json::Value;
+
+use crate::nbt::TagPayload;
+
+pub struct SignText {
+    pub front: [String; 4],
+    // Always empty before 1.20, when signs got a second side
+    pub back: [String; 4],
+}
+
+impl SignText {
+    // Handles Text1..Text4 from before 1.20 as well as front_text/back_text.
+    // None if the compound holds neither.
+    pub fn from_nbt(nbt: &TagPayload) -> Option<SignText> {
+        if let Some(front) = nbt.get("front_text") {
+            let back = nbt.get("back_text").map(side_lines).unwrap_or_default();
+            return Some(SignText { front: side_lines(front), back });
+        }
+
+        nbt.get("Text1")?;
+        let front = ["Text1", "Text2", "Text3", "Text4"].map(|name| nbt.get(name).map(component_text).unwrap_or_default());
+        Some(SignText { front, back: Default::default() })
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.front.iter().chain(&self.back).all(|line| line.trim().is_empty())
+    }
+}
+
+fn side_lines(side: &TagPayload) -> [String; 4] {
+    let mut lines: [String; 4] = Default::default();
+    if let Some(TagPayload::List(messages)) = side.get("messages") {
+        for (line, message) in lines.iter_mut().zip(messages) {
+            *line = component_text(message);
+        }
+    }
+    lines
+}
+
+// Text components were stored as JSON strings until 1.21.5 and as NBT since
+fn component_text(component: &TagPayload) -> String {
+    match component {
+        TagPayload::String(text) => match serde_json::from_str::<Value>(text) {
+            Ok(json) => json_text(&json),
+            // Plain text, from before 1.8 or from 1.21.5 on
+            Err(_) => text.clone(),
+        },
+        TagPayload::Compound(_) => {
+            let mut text = match component.get("text") {
+                Some(TagPayload::String(text)) => text.clone(),
+                _ => String::new(),
+            };
+            if let Some(TagPayload::List(extra)) = component.get("extra") {
+                text.extend(extra.iter().map(nbt_component_text));
+            }
+            text
+        },
+        TagPayload::List(parts) => parts.iter().map(nbt_component_text).collect(),
+        _ => String::new(),
+    }
+}
+
+// Strings nested in an NBT component are already plain text, not JSON
+fn nbt_component_text(component: &TagPayload) -> String {
+    match component {
+        TagPayload::String(text) => text.clone(),
+        _ => component_text(component),
+    }
+}
+
+// Flattens a JSON text component to the text it shows, ignoring formatting
+pub fn json_text(json: &Value) -> String {
+    match json {
+        Value::String(text) => text.clone(),
+        Value::Array(parts) => parts.iter().map(json_text).collect(),
+        Value::Object(object) => {
+            let mut text = match object.get("text").or_else(|| object.get("translate")) {
+                Some(Value::String(text)) => text.clone(),
+                _ => String::new(),
+            };
+            if let Some(Value::Array(extra)) = object.get("extra") {
+                text.extend(extra.iter().map(json_text));
+            }
+            text
+        },
+        Value::Null => String::new(),
+        other => other.to_string(),
+    }
+}
@@ -0,0 +1,48 @@
+// Where slimes spawn below y 40 regardless of light, which only depends on the
+// world seed. Only the overworld has slime chunks, swamps and the Slimes game
+// rule aside.
+
+// java.util.Random, as far as the check needs it
+struct JavaRandom {
+    seed: i64,
+}
+
+impl JavaRandom {
+    const MULTIPLIER: i64 = 0x5DEECE66D;
+    const MASK: i64 = (1 << 48) - 1;
+
+    fn new(seed: i64) -> JavaRandom {
+        JavaRandom { seed: (seed ^ JavaRandom::MULTIPLIER) & JavaRandom::MASK }
+    }
+
+    fn next(&mut self, bits: u32) -> i32 {
+        self.seed = self.seed.wrapping_mul(JavaRandom::MULTIPLIER).wrapping_add(0xB) & JavaRandom::MASK;
+        (self.seed >> (48 - bits)) as i32
+    }
+
+    // Rejects the top of the range so every value is equally likely
+    fn next_int(&mut self, bound: i32) -> i32 {
+        loop {
+            let bits = self.next(31);
+            let value = bits % bound;
+            if bits.wrapping_sub(value).wrapping_add(bound - 1) >= 0 {
+                return value;
+            }
+        }
+    }
+}
+
+pub fn is_slime_chunk(seed: i64, chunk_x: i32, chunk_z: i32) -> bool {
+    // The game does this part in 32 bit ints, overflow included
+    let x = chunk_x.wrapping_mul(chunk_x).wrapping_mul(0x4c1906) as i64 + chunk_x.wrapping_mul(0x5ac0db) as i64;
+    let z = (chunk_z.wrapping_mul(chunk_z) as i64).wrapping_mul(0x4307a7) + chunk_z.wrapping_mul(0x5f24f) as i64;
+    let mut random = JavaRandom::new(seed.wrapping_add(x).wrapping_add(z) ^ 0x3ad8025f);
+    random.next_int(10) == 0
+}
+
+// The slime chunks at most radius chunks from center on either axis, row by row
+pub fn slime_chunks_near(seed: i64, center: (i32, i32), radius: i32) -> impl Iterator<Item = (i32, i32)> {
+    (center.1 - radius..=center.1 + radius)
+        .flat_map(move |z| (center.0 - radius..=center.0 + radius).map(move |x| (x, z)))
+        .filter(move |&(x, z)| is_slime_chunk(seed, x, z))
+}
@@ -0,0 +1,508 @@
+use std::fmt::{self, Write};
+
+use crate::nbt::{ Compound, Tag, TagPayload, MAX_DEPTH };
+
+pub struct SnbtFormatter {
+    // Spaces per nesting level, or None to put everything on one line
+    pub indent: Option<usize>,
+}
+
+impl Default for SnbtFormatter {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl SnbtFormatter {
+    pub fn new() -> SnbtFormatter {
+        SnbtFormatter { indent: None }
+    }
+
+    pub fn pretty() -> SnbtFormatter {
+        SnbtFormatter { indent: Some(4) }
+    }
+
+    // The root tag's name isn't part of SNBT, so only its payload is written
+    pub fn format(&self, tag: &Tag) -> String {
+        self.format_payload(&tag.payload)
+    }
+
+    pub fn format_payload(&self, payload: &TagPayload) -> String {
+        let mut out = String::new();
+        self.write_payload(&mut out, payload, 0).expect("writing to a String can't fail");
+        out
+    }
+
+    fn newline(&self, out: &mut String, depth: usize) -> fmt::Result {
+        if let Some(indent) = self.indent {
+            writeln!(out)?;
+            write!(out, "{:width$}", "", width = indent * depth)?;
+        }
+        Ok(())
+    }
+
+    fn write_payload(&self, out: &mut String, payload: &TagPayload, depth: usize) -> fmt::Result {
+        match payload {
+            TagPayload::Byte(x) => write!(out, "{}b", x),
+            TagPayload::Short(x) => write!(out, "{}s", x),
+            TagPayload::Int(x) => write!(out, "{}", x),
+            TagPayload::Long(x) => write!(out, "{}L", x),
+            TagPayload::Float(x) => write_float(out, *x, "f"),
+            TagPayload::Double(x) => write_float(out, *x, "d"),
+            TagPayload::ByteArray(x) => write_array(out, "B", x.iter().map(|b| format!("{}b", b))),
+            TagPayload::String(x) => write_quoted(out, x),
+            TagPayload::List(x) => {
+                if x.is_empty() {
+                    return write!(out, "[]");
+                }
+
+                write!(out, "[")?;
+                for (i, item) in x.iter().enumerate() {
+                    if i > 0 {
+                        write!(out, ",")?;
+                    }
+                    self.newline(out, depth + 1)?;
+                    self.write_payload(out, item, depth + 1)?;
+                }
+                self.newline(out, depth)?;
+                write!(out, "]")
+            },
+            TagPayload::Compound(x) => {
+                if x.is_empty() {
+                    return write!(out, "{{}}");
+                }
+
+                write!(out, "{{")?;
+                for (i, tag) in x.iter().enumerate() {
+                    if i > 0 {
+                        write!(out, ",")?;
+                    }
+                    self.newline(out, depth + 1)?;
+                    write_key(out, &tag.name)?;
+                    write!(out, "{}", if self.indent.is_some() { ": " } else { ":" })?;
+                    self.write_payload(out, &tag.payload, depth + 1)?;
+                }
+                self.newline(out, depth)?;
+                write!(out, "}}")
+            },
+            TagPayload::IntArray(x) => write_array(out, "I", x.iter().map(|i| i.to_string())),
+            TagPayload::LongArray(x) => write_array(out, "L", x.iter().map(|l| format!("{}L", l))),
+        }
+    }
+}
+
+fn write_array(out: &mut String, prefix: &str, items: impl Iterator<Item = String>) -> fmt::Result {
+    write!(out, "[{};", prefix)?;
+    for (i, item) in items.enumerate() {
+        if i > 0 {
+            write!(out, ",")?;
+        }
+        write!(out, "{}", item)?;
+    }
+    write!(out, "]")
+}
+
+// NaN and the infinities are spelled the way Minecraft writes them, which
+// parse_snbt reads back
+fn write_float<F: Into<f64> + fmt::Display + Copy>(out: &mut String, x: F, suffix: &str) -> fmt::Result {
+    let wide: f64 = x.into();
+    if wide.is_nan() {
+        write!(out, "NaN{}", suffix)
+    } else if wide.is_infinite() {
+        write!(out, "{}Infinity{}", if wide < 0.0 { "-" } else { "" }, suffix)
+    } else {
+        write!(out, "{}{}", x, suffix)
+    }
+}
+
+fn write_quoted(out: &mut String, s: &str) -> fmt::Result {
+    write!(out, "\"")?;
+    for c in s.chars() {
+        match c {
+            '"' => write!(out, "\\\"")?,
+            '\\' => write!(out, "\\\\")?,
+            c => write!(out, "{}", c)?,
+        }
+    }
+    write!(out, "\"")
+}
+
+// Keys made only of these characters can be left unquoted
+fn is_unquoted_key(key: &str) -> bool {
+    !key.is_empty() && key.chars().all(|c| c.is_ascii_alphanumeric() || matches!(c, '_' | '-' | '.' | '+'))
+}
+
+fn write_key(out: &mut String, key: &str) -> fmt::Result {
+    if is_unquoted_key(key) {
+        write!(out, "{}", key)
+    } else {
+        write_quoted(out, key)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SnbtError {
+    // Byte offset into the text
+    pub offset: usize,
+    pub message: String,
+}
+
+impl fmt::Display for SnbtError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} at offset {}", self.message, self.offset)
+    }
+}
+
+impl std::error::Error for SnbtError {}
+
+// Reads SNBT as /data and the formatter above write it. Numbers without a
+// suffix are ints, or doubles if they have a fraction or exponent, and true
+// and false are bytes. Other unquoted words are strings. Lists and compounds
+// may nest MAX_DEPTH levels deep, like in binary NBT.
+pub fn parse_snbt(text: &str) -> Result<TagPayload, SnbtError> {
+    let mut parser = SnbtParser { text, at: 0, depth: 0 };
+    let payload = parser.value()?;
+    parser.skip_whitespace();
+    if parser.at < text.len() {
+        return Err(parser.error("Unexpected text after the value"));
+    }
+    Ok(payload)
+}
+
+struct SnbtParser<'a> {
+    text: &'a str,
+    at: usize,
+    // Lists and compounds currently open
+    depth: usize,
+}
+
+impl SnbtParser<'_> {
+    fn error(&self, message: impl Into<String>) -> SnbtError {
+        SnbtError { offset: self.at, message: message.into() }
+    }
+
+    fn skip_whitespace(&mut self) {
+        let rest = &self.text[self.at..];
+        self.at += rest.len() - rest.trim_start().len();
+    }
+
+    fn peek(&mut self) -> Option<char> {
+        self.skip_whitespace();
+        self.text[self.at..].chars().next()
+    }
+
+    // Called when a list or compound opens, so deep nesting fails instead of
+    // overflowing the stack. Undone by leave.
+    fn enter(&mut self) -> Result<(), SnbtError> {
+        if self.depth >= MAX_DEPTH {
+            return Err(self.error(format!("Nested deeper than {MAX_DEPTH} levels")));
+        }
+        self.depth += 1;
+        Ok(())
+    }
+
+    fn leave(&mut self) {
+        self.depth -= 1;
+    }
+
+    fn expect(&mut self, c: char) -> Result<(), SnbtError> {
+        if self.peek() != Some(c) {
+            return Err(self.error(format!("Expected '{c}'")));
+        }
+        self.at += c.len_utf8();
+        Ok(())
+    }
+
+    // Consumes a separating comma, true if there was one
+    fn comma(&mut self) -> bool {
+        let found = self.peek() == Some(',');
+        if found {
+            self.at += 1;
+        }
+        found
+    }
+
+    fn value(&mut self) -> Result<TagPayload, SnbtError> {
+        match self.peek() {
+            Some('{') => self.compound(),
+            Some('[') => self.list_or_array(),
+            Some('"' | '\'') => Ok(TagPayload::String(self.quoted()?)),
+            Some(_) => {
+                let word = self.word();
+                if word.is_empty() {
+                    return Err(self.error("Expected a value"));
+                }
+                Ok(scalar(word))
+            },
+            None => Err(self.error("Expected a value")),
+        }
+    }
+
+    fn compound(&mut self) -> Result<TagPayload, SnbtError> {
+        self.enter()?;
+        self.expect('{')?;
+        let mut tags = Compound::new();
+        if self.peek() == Some('}') {
+            self.at += 1;
+            self.leave();
+            return Ok(TagPayload::Compound(tags));
+        }
+
+        loop {
+            // Quoted keys can be empty, the formatter writes them as ""
+            let name = match self.peek() {
+                Some('"' | '\'') => self.quoted()?,
+                _ => match self.word() {
+                    "" => return Err(self.error("Expected a key")),
+                    word => word.to_string(),
+                },
+            };
+            self.expect(':')?;
+            let payload = self.value()?;
+            // A repeated key wins over the earlier one, like in Minecraft
+            match tags.get_mut(&name) {
+                Some(existing) => *existing = payload,
+                None => tags.push(Tag { name, payload }),
+            }
+            if !self.comma() {
+                break;
+            }
+        }
+
+        self.expect('}')?;
+        self.leave();
+        Ok(TagPayload::Compound(tags))
+    }
+
+    fn list_or_array(&mut self) -> Result<TagPayload, SnbtError> {
+        self.expect('[')?;
+        let rest = &self.text[self.at..];
+        let array_type = ["B;", "I;", "L;"].into_iter().find(|prefix| rest.trim_start().starts_with(prefix));
+        if let Some(prefix) = array_type {
+            self.skip_whitespace();
+            self.at += prefix.len();
+            return self.array(&prefix[..1]);
+        }
+
+        self.enter()?;
+        let mut items = Vec::new();
+        if self.peek() == Some(']') {
+            self.at += 1;
+            self.leave();
+            return Ok(TagPayload::List(items));
+        }
+
+        loop {
+            let start = self.at;
+            let item = self.value()?;
+            if items.first().is_some_and(|first: &TagPayload| first.id() != item.id()) {
+                return Err(SnbtError { offset: start, message: "List mixes tag types".to_string() });
+            }
+            items.push(item);
+            if !self.comma() {
+                break;
+            }
+        }
+
+        self.expect(']')?;
+        self.leave();
+        Ok(TagPayload::List(items))
+    }
+
+    fn array(&mut self, array_type: &str) -> Result<TagPayload, SnbtError> {
+        let mut values = Vec::new();
+        if self.peek() != Some(']') {
+            loop {
+                let start = self.at;
+                let value = scalar(self.word());
+                values.push((start, value));
+                if !self.comma() {
+                    break;
+                }
+            }
+        }
+        self.expect(']')?;
+
+        let wrong = |offset: usize| SnbtError { offset, message: format!("Expected a {} in the array", match array_type {
+            "B" => "byte",
+            "I" => "int",
+            _ => "long",
+        }) };
+        match array_type {
+            "B" => values.into_iter()
+                .map(|(offset, value)| match value { TagPayload::Byte(b) => Ok(b), _ => Err(wrong(offset)) })
+                .collect::<Result<_, _>>().map(TagPayload::ByteArray),
+            "I" => values.into_iter()
+                .map(|(offset, value)| match value { TagPayload::Int(i) => Ok(i), _ => Err(wrong(offset)) })
+                .collect::<Result<_, _>>().map(TagPayload::IntArray),
+            _ => values.into_iter()
+                .map(|(offset, value)| match value { TagPayload::Long(l) => Ok(l), _ => Err(wrong(offset)) })
+                .collect::<Result<_, _>>().map(TagPayload::LongArray),
+        }
+    }
+
+    fn quoted(&mut self) -> Result<String, SnbtError> {
+        let quote = self.peek().ok_or_else(|| self.error("Expected a string"))?;
+        let start = self.at;
+        self.at += 1;
+        let mut out = String::new();
+        let mut chars = self.text[self.at..].char_indices();
+
+        while let Some((i, c)) = chars.next() {
+            match c {
+                '\\' => match chars.next() {
+                    Some((_, escaped)) => out.push(escaped),
+                    None => break,
+                },
+                c if c == quote => {
+                    self.at += i + 1;
+                    return Ok(out);
+                },
+                c => out.push(c),
+            }
+        }
+
+        Err(SnbtError { offset: start, message: "Unterminated string".to_string() })
+    }
+
+    // The characters keys and unquoted values can be made of
+    fn word(&mut self) -> &str {
+        self.skip_whitespace();
+        let rest = &self.text[self.at..];
+        let len = rest.find(|c: char| !(c.is_ascii_alphanumeric() || matches!(c, '_' | '-' | '.' | '+'))).unwrap_or(rest.len());
+        self.at += len;
+        &rest[..len]
+    }
+}
+
+// Numbers with their type suffix, booleans, or failing those a string
+fn scalar(word: &str) -> TagPayload {
+    let number = |suffixes: &[char]| word.strip_suffix(suffixes).filter(|n| !n.is_empty());
+    // Rust also reads inf and nan in any case, which would turn words like
+    // nand into a NaN, so only the spellings the formatter writes count
+    let spelled_out = |n: &str| matches!(n.strip_prefix(['-', '+']).unwrap_or(n), "NaN" | "Infinity");
+    let parsed = match word {
+        "true" => Some(TagPayload::Byte(1)),
+        "false" => Some(TagPayload::Byte(0)),
+        _ => None,
+    }
+        .or_else(|| number(&['b', 'B'])?.parse().ok().map(TagPayload::Byte))
+        .or_else(|| number(&['s', 'S'])?.parse().ok().map(TagPayload::Short))
+        .or_else(|| number(&['l', 'L'])?.parse().ok().map(TagPayload::Long))
+        .or_else(|| number(&['f', 'F']).and_then(|n| n.parse().ok().filter(|x: &f32| x.is_finite() || spelled_out(n))).map(TagPayload::Float))
+        .or_else(|| number(&['d', 'D']).and_then(|n| n.parse().ok().filter(|x: &f64| x.is_finite() || spelled_out(n))).map(TagPayload::Double))
+        .or_else(|| word.parse().ok().map(TagPayload::Int))
+        .or_else(|| {
+            let looks_numeric = word.starts_with(|c: char| c.is_ascii_digit() || matches!(c, '-' | '+' | '.'));
+            // Non-finite doubles need their suffix, so words like -inf stay strings
+            looks_numeric.then(|| word.parse().ok().filter(|x: &f64| x.is_finite()).map(TagPayload::Double)).flatten()
+        });
+    parsed.unwrap_or_else(|| TagPayload::String(word.to_string()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn tag(name: &str, payload: TagPayload) -> Tag {
+        Tag { name: name.to_string(), payload }
+    }
+
+    fn bytes_of(payload: &TagPayload) -> Vec<u8> {
+        let mut bytes = Vec::new();
+        tag("", payload.clone()).write(&mut bytes).unwrap();
+        bytes
+    }
+
+    fn every_type() -> TagPayload {
+        let nested = vec![tag("deep", TagPayload::List(vec![TagPayload::Compound(vec![tag("a", TagPayload::Byte(1))].into())]))];
+        TagPayload::Compound(vec![
+            tag("byte", TagPayload::Byte(-1)),
+            tag("short", TagPayload::Short(-300)),
+            tag("int", TagPayload::Int(i32::MIN)),
+            tag("long", TagPayload::Long(i64::MAX)),
+            tag("float", TagPayload::Float(0.1)),
+            tag("double", TagPayload::Double(-1e300)),
+            tag("bytes", TagPayload::ByteArray(vec![-128, 0, 127])),
+            tag("string", TagPayload::String("minecraft:stone".to_string())),
+            tag("list", TagPayload::List(vec![TagPayload::Int(1), TagPayload::Int(2)])),
+            tag("empty list", TagPayload::List(Vec::new())),
+            tag("empty", TagPayload::Compound(Compound::new())),
+            tag("compound", TagPayload::Compound(nested.into())),
+            tag("ints", TagPayload::IntArray(vec![0, -1, i32::MAX])),
+            tag("longs", TagPayload::LongArray(vec![i64::MIN, 2])),
+            tag("no longs", TagPayload::LongArray(Vec::new())),
+        ].into())
+    }
+
+    #[test]
+    fn round_trips_every_tag_type() {
+        for formatter in [SnbtFormatter::new(), SnbtFormatter::pretty()] {
+            let text = formatter.format_payload(&every_type());
+            assert_eq!(bytes_of(&parse_snbt(&text).unwrap()), bytes_of(&every_type()), "{text}");
+        }
+        assert_eq!(SnbtFormatter::new().format_payload(&TagPayload::Compound(vec![tag("a", TagPayload::Float(0.1))].into())), "{a:0.1f}");
+    }
+
+    #[test]
+    fn escapes_quotes_in_keys_and_values() {
+        let payload = TagPayload::Compound(vec![
+            tag("say \"hi\"", TagPayload::String("it's \"quoted\" \\ escaped".to_string())),
+            tag("", TagPayload::String(String::new())),
+        ].into());
+        let text = SnbtFormatter::new().format_payload(&payload);
+        assert_eq!(text, r#"{"say \"hi\"":"it's \"quoted\" \\ escaped","":""}"#);
+        assert_eq!(bytes_of(&parse_snbt(&text).unwrap()), bytes_of(&payload));
+
+        // Single quotes work too, with the other kind inside unescaped
+        let parsed = parse_snbt(r#"{'a "b"':'c \'d\' "e"'}"#).unwrap();
+        assert!(matches!(parsed.get("a \"b\""), Some(TagPayload::String(s)) if s == "c 'd' \"e\""));
+    }
+
+    #[test]
+    fn reads_typed_arrays() {
+        let parsed = parse_snbt("[B; 1b, -2b, true]").unwrap();
+        assert!(matches!(parsed, TagPayload::ByteArray(ref b) if *b == [1, -2, 1]));
+        let parsed = parse_snbt("[I;1,2,-3]").unwrap();
+        assert!(matches!(parsed, TagPayload::IntArray(ref i) if *i == [1, 2, -3]));
+        let parsed = parse_snbt("[ L; 1L, -9000000000L ]").unwrap();
+        assert!(matches!(parsed, TagPayload::LongArray(ref l) if *l == [1, -9000000000]));
+        assert!(matches!(parse_snbt("[I;]").unwrap(), TagPayload::IntArray(ref i) if i.is_empty()));
+
+        // Elements have to have the array's type
+        assert_eq!(parse_snbt("[I; 1, 2L]").err().unwrap().offset, 6);
+        assert!(parse_snbt("[B; 1]").is_err());
+        assert!(parse_snbt("[L; 1b]").is_err());
+    }
+
+    #[test]
+    fn rejects_lists_mixing_types() {
+        let error = parse_snbt("[1, 2b]").err().unwrap();
+        assert_eq!((error.offset, error.message.as_str()), (3, "List mixes tag types"));
+        assert!(parse_snbt("[{}, []]").is_err());
+        assert!(parse_snbt("[[1], [2b]]").is_ok());
+    }
+
+    #[test]
+    fn limits_nesting() {
+        let nested = |depth: usize| format!("{}{}", "[".repeat(depth), "]".repeat(depth));
+        assert!(parse_snbt(&nested(MAX_DEPTH)).is_ok());
+        assert!(parse_snbt(&nested(MAX_DEPTH + 1)).err().unwrap().message.contains("deeper"));
+        assert!(parse_snbt(&"{a:".repeat(100_000)).err().unwrap().message.contains("deeper"));
+    }
+
+    #[test]
+    fn writes_non_finite_floats_so_they_read_back() {
+        let payload = TagPayload::List(vec![TagPayload::Float(f32::NAN), TagPayload::Float(f32::INFINITY), TagPayload::Float(f32::NEG_INFINITY)]);
+        let text = SnbtFormatter::new().format_payload(&payload);
+        assert_eq!(text, "[NaNf,Infinityf,-Infinityf]");
+        let TagPayload::List(items) = parse_snbt(&text).unwrap() else { panic!("not a list") };
+        assert!(matches!(items[..], [TagPayload::Float(nan), TagPayload::Float(f32::INFINITY), TagPayload::Float(f32::NEG_INFINITY)] if nan.is_nan()));
+        assert_eq!(SnbtFormatter::new().format_payload(&TagPayload::Double(f64::NEG_INFINITY)), "-Infinityd");
+
+        // Other spellings Rust would read as floats stay strings
+        for word in ["nand", "inff", "-inf", "infinity"] {
+            assert!(matches!(parse_snbt(word).unwrap(), TagPayload::String(s) if s == word), "{word}");
+        }
+    }
+}
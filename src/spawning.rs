@@ -0,0 +1,125 @@
+use crate::{ chunk::BlockType, pos::BlockPos, world::WorldView };
+
+// Whether hostile mobs can stand on top of the block: a full, opaque block.
+// Goes by name, so it's a close guess rather than the game's own shapes.
+pub fn can_spawn_on(block: &BlockType) -> bool {
+    let name = block.name.as_str();
+    if block.is_air() || is_fluid(name) {
+        return false;
+    }
+    if NOT_SPAWNABLE.contains(&name) {
+        return false;
+    }
+    // Slabs and stairs count only when their top is full
+    if name.ends_with("_slab") {
+        return matches!(block.property("type"), Some("top" | "double"));
+    }
+    if name.ends_with("_stairs") {
+        return block.property("half") == Some("top");
+    }
+    !NOT_FULL.iter().any(|part| name.ends_with(part))
+}
+
+// Whether a mob's feet or head can be in the block: no collision and no fluid
+pub fn is_spawn_space(block: &BlockType) -> bool {
+    let name = block.name.as_str();
+    if block.is_air() {
+        return true;
+    }
+    if is_fluid(name) || block.property("waterlogged") == Some("true") {
+        return false;
+    }
+    if name == "minecraft:snow" {
+        return block.property("layers").is_none_or(|layers| layers == "1");
+    }
+    matches!(name, "minecraft:short_grass" | "minecraft:grass" | "minecraft:fern" | "minecraft:dead_bush"
+        | "minecraft:redstone_wire" | "minecraft:lever" | "minecraft:tripwire" | "minecraft:vine" | "minecraft:glow_lichen")
+        || PASSABLE.iter().any(|part| name.ends_with(part))
+}
+
+// Full blocks mobs still can't spawn on, being see-through or not quite full
+const NOT_SPAWNABLE: &[&str] = &[
+    "minecraft:barrier", "minecraft:light", "minecraft:glass", "minecraft:tinted_glass", "minecraft:ice", "minecraft:packed_ice",
+    "minecraft:blue_ice", "minecraft:frosted_ice", "minecraft:mud", "minecraft:soul_sand", "minecraft:dirt_path",
+    "minecraft:farmland", "minecraft:chest", "minecraft:trapped_chest", "minecraft:ender_chest", "minecraft:slime_block",
+    "minecraft:honey_block", "minecraft:scaffolding", "minecraft:magma_block",
+];
+
+fn is_fluid(name: &str) -> bool {
+    matches!(name, "minecraft:water" | "minecraft:lava" | "minecraft:flowing_water" | "minecraft:flowing_lava" | "minecraft:bubble_column")
+}
+
+// Name endings of blocks mobs can't spawn on
+const NOT_FULL: &[&str] = &[
+    "_glass", "_glass_pane", "glass_pane", "_leaves", "_carpet", "_fence", "_fence_gate", "_wall", "_door", "_trapdoor",
+    "_sign", "_hanging_sign", "_pressure_plate", "_button", "_bed", "_banner", "_torch", "torch", "_rail", "rail",
+    "_sapling", "_flower", "_tulip", "_mushroom", "_candle", "candle", "_head", "_skull", "_pot", "_coral", "_coral_fan",
+    "poppy", "dandelion", "orchid", "allium", "azure_bluet", "oxeye_daisy", "cornflower", "lily_of_the_valley", "_roots", "_fungus",
+    "_bars", "chain", "ladder", "lantern", "_plate", "cake", "_grass", "fern", "bush", "vine", "_vines", "lichen",
+    "anvil", "bell", "campfire", "cactus", "lectern", "stonecutter", "enchanting_table", "hopper", "brewing_stand",
+    "cauldron", "composter", "daylight_detector", "repeater", "comparator", "end_rod", "lightning_rod", "pointed_dripstone",
+    "amethyst_cluster", "_bud", "_shulker_box", "shulker_box", "conduit", "grindstone", "sea_pickle", "turtle_egg",
+    "dragon_egg", "bamboo", "sugar_cane", "kelp", "kelp_plant", "seagrass", "lily_pad", "cobweb", "sculk_vein", "sculk_sensor",
+    "sculk_shrieker", "azalea", "dripleaf", "spore_blossom", "hanging_roots", "frogspawn", "piston_head", "moving_piston",
+    "_wire", "tripwire", "tripwire_hook", "lever", "minecraft:snow",
+];
+
+// Name endings of blocks without collision that don't hurt
+const PASSABLE: &[&str] = &["_sign", "_banner", "_button", "_pressure_plate", "_rail", "rail", "_carpet"];
+
+pub struct SpawnOptions {
+    // Blocks closer than this to the center don't count, since mobs don't
+    // spawn within 24 blocks of a player
+    pub min_distance: f64,
+    pub radius: f64,
+    // The brightest block light mobs still spawn in, 0 since 1.18 and 7 before
+    pub max_block_light: u8,
+}
+
+// Feet positions in the sphere around center where a hostile mob could spawn
+// at night: a block to stand on, two blocks of space and not too much block
+// light. Sky light is left out since it's dark enough at night. Only the
+// blocks up to the surface are looked at.
+pub fn find_spawnable(view: &WorldView, center: BlockPos, options: &SpawnOptions) -> Vec<BlockPos> {
+    let mut found = Vec::new();
+    let radius = options.radius.ceil() as i32;
+
+    for chunk in view.chunks() {
+        let heights = chunk.surface_heights();
+        for (z, row) in heights.iter().enumerate() {
+            for (x, height) in row.iter().enumerate() {
+                let (block_x, block_z) = (chunk.x() * 16 + x as i32, chunk.z() * 16 + z as i32);
+                let top = (*height).min(center.y + radius);
+                for y in (center.y - radius)..=top {
+                    let feet = BlockPos::new(block_x, y, block_z);
+                    let distance = distance(&feet, &center);
+                    if distance > options.radius || distance < options.min_distance {
+                        continue;
+                    }
+                    if is_spawnable(view, feet, options.max_block_light) {
+                        found.push(feet);
+                    }
+                }
+            }
+        }
+    }
+
+    found.sort();
+    found
+}
+
+fn is_spawnable(view: &WorldView, feet: BlockPos, max_block_light: u8) -> bool {
+    let head = feet.offset(0, 1, 0);
+    let below = feet.offset(0, -1, 0);
+    let space = |pos: BlockPos| view.block_at(pos).is_none_or(is_spawn_space);
+    view.block_at(feet).is_some_and(is_spawn_space)
+        && space(head)
+        && view.block_at(below).is_some_and(can_spawn_on)
+        // The game leaves the array out of sections without any block light
+        && view.block_light(feet).unwrap_or(0) <= max_block_light
+}
+
+fn distance(a: &BlockPos, b: &BlockPos) -> f64 {
+    let d = |a: i32, b: i32| (a as f64 - b as f64).powi(2);
+    (d(a.x, b.x) + d(a.y, b.y) + d(a.z, b.z)).sqrt()
+}
@@ -0,0 +1,157 @@
+use anyhow::{ Result, bail };
+
+use crate::{ nbt::TagPayload, pos::BlockPos };
+
+// The structures a chunk knows about: the ones that start in it, with their
+// bounding boxes, and the chunks holding the starts of the ones that reach
+// into it. Chunks from before 1.13 kept neither, that went into data/.
+pub struct ChunkStructures {
+    starts: Vec<StructureStart>,
+    references: Vec<(String, Vec<(i32, i32)>)>,
+}
+
+pub struct StructureStart {
+    kind: String,
+    chunk: (i32, i32),
+    min: BlockPos,
+    max: BlockPos,
+    pieces: Vec<StructurePiece>,
+}
+
+// A room, corridor, house or the like, with its box
+pub struct StructurePiece {
+    id: String,
+    min: BlockPos,
+    max: BlockPos,
+}
+
+impl ChunkStructures {
+    // structures.starts and References since 1.18, Level.Structures.Starts
+    // and References before
+    pub(crate) fn from_nbt(structures: Option<&TagPayload>, chunk: (i32, i32)) -> Result<ChunkStructures> {
+        let mut result = ChunkStructures { starts: Vec::new(), references: Vec::new() };
+        let Some(structures) = structures else {
+            return Ok(result);
+        };
+
+        let starts = structures.get("starts").or_else(|| structures.get("Starts"));
+        if let Some(TagPayload::Compound(starts)) = starts {
+            for start in starts {
+                // Before 1.18 chunks list every kind of structure, most as INVALID
+                if let Some(start) = StructureStart::from_nbt(&start.name, &start.payload, chunk)? {
+                    result.starts.push(start);
+                }
+            }
+        }
+
+        if let Some(TagPayload::Compound(references)) = structures.get("References") {
+            for reference in references {
+                let chunks = match &reference.payload {
+                    // x in the low half, z in the high one
+                    TagPayload::LongArray(chunks) => chunks.iter().map(|&packed| (packed as i32, (packed >> 32) as i32)).collect(),
+                    _ => bail!("References to {} is not a long array", reference.name),
+                };
+                result.references.push((reference.name.clone(), chunks));
+            }
+        }
+
+        Ok(result)
+    }
+
+    pub fn starts(&self) -> &[StructureStart] {
+        &self.starts
+    }
+
+    // The structure's name and the chunks its start is in, for each kind of
+    // structure reaching into this chunk
+    pub fn references(&self) -> &[(String, Vec<(i32, i32)>)] {
+        &self.references
+    }
+}
+
+impl StructureStart {
+    fn from_nbt(kind: &str, nbt: &TagPayload, chunk: (i32, i32)) -> Result<Option<StructureStart>> {
+        if matches!(nbt.get("id"), Some(TagPayload::String(id)) if id == "INVALID") {
+            return Ok(None);
+        }
+
+        let mut pieces = Vec::new();
+        if let Some(TagPayload::List(children)) = nbt.get("Children").or_else(|| nbt.get("children")) {
+            for child in children {
+                let id = match child.get("id") {
+                    Some(TagPayload::String(id)) => id.clone(),
+                    _ => String::new(),
+                };
+                let Some((min, max)) = child.get("BB").and_then(bounding_box) else {
+                    bail!("{kind} piece {id} has no bounding box");
+                };
+                pieces.push(StructurePiece { id, min, max });
+            }
+        }
+
+        // Starts had their own box until 1.18, since then it's all the pieces'
+        let (min, max) = match nbt.get("BB").and_then(bounding_box) {
+            Some(bounds) => bounds,
+            None => {
+                let Some(first) = pieces.first() else {
+                    return Ok(None);
+                };
+                pieces.iter().fold((first.min, first.max), |(min, max), piece| (
+                    BlockPos::new(min.x.min(piece.min.x), min.y.min(piece.min.y), min.z.min(piece.min.z)),
+                    BlockPos::new(max.x.max(piece.max.x), max.y.max(piece.max.y), max.z.max(piece.max.z)),
+                ))
+            },
+        };
+
+        Ok(Some(StructureStart { kind: kind.to_string(), chunk, min, max, pieces }))
+    }
+
+    // e.g. minecraft:fortress, or Fortress before 1.18
+    pub fn kind(&self) -> &str {
+        &self.kind
+    }
+
+    // Whether this is the given kind of structure, going by the name without
+    // its namespace, case or underscores, so fortress matches Fortress and
+    // end_city matches EndCity
+    pub fn is_kind(&self, kind: &str) -> bool {
+        normalize_kind(&self.kind) == normalize_kind(kind)
+    }
+
+    pub fn chunk(&self) -> (i32, i32) {
+        self.chunk
+    }
+
+    // Both corners inclusive
+    pub fn bounds(&self) -> (BlockPos, BlockPos) {
+        (self.min, self.max)
+    }
+
+    pub fn pieces(&self) -> &[StructurePiece] {
+        &self.pieces
+    }
+}
+
+impl StructurePiece {
+    // e.g. minecraft:nefcr for a fortress corridor crossing
+    pub fn id(&self) -> &str {
+        &self.id
+    }
+
+    pub fn bounds(&self) -> (BlockPos, BlockPos) {
+        (self.min, self.max)
+    }
+}
+
+fn normalize_kind(kind: &str) -> String {
+    let name = kind.rsplit_once(':').map_or(kind, |(_, name)| name);
+    name.chars().filter(|c| *c != '_').map(|c| c.to_ascii_lowercase()).collect()
+}
+
+// Six ints, the lowest x, y and z and then the highest
+fn bounding_box(payload: &TagPayload) -> Option<(BlockPos, BlockPos)> {
+    match payload {
+        TagPayload::IntArray(bb) if bb.len() == 6 => Some((BlockPos::new(bb[0], bb[1], bb[2]), BlockPos::new(bb[3], bb[4], bb[5]))),
+        _ => None,
+    }
+}
@@ -0,0 +1,375 @@
+use std::slice::Iter;
+
+use crate::nbt::{ path_steps, NbtError, NbtFlavor, NextPlusPlus, NextSlice, ParseOptions, PathError, PathStep, Tag, TagPayload, NO_OPTIONS };
+
+// A tag borrowing its names, strings and arrays from the buffer it was parsed
+// from, for reading through a lot of NBT without copying it all out first
+#[derive(Clone, Debug)]
+pub struct TagRef<'a> {
+    pub name: &'a str,
+    pub payload: TagPayloadRef<'a>,
+}
+
+#[derive(Clone, Debug)]
+pub enum TagPayloadRef<'a> {
+    Byte(i8),
+    Short(i16),
+    Int(i32),
+    Long(i64),
+    Float(f32),
+    Double(f64),
+    ByteArray(&'a [u8]),
+    String(&'a str),
+    List(Vec<TagPayloadRef<'a>>),
+    Compound(Vec<TagRef<'a>>),
+    IntArray(IntArrayRef<'a>),
+    LongArray(LongArrayRef<'a>),
+}
+
+// Int and long arrays are big-endian, so they can't be borrowed as slices of
+// numbers. These keep the bytes and convert an element at a time.
+#[derive(Clone, Copy, Debug)]
+pub struct IntArrayRef<'a>(&'a [u8]);
+
+#[derive(Clone, Copy, Debug)]
+pub struct LongArrayRef<'a>(&'a [u8]);
+
+impl<'a> IntArrayRef<'a> {
+    pub fn len(&self) -> usize {
+        self.0.len() / 4
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    pub fn get(&self, i: usize) -> Option<i32> {
+        // Checked first so huge indices can't overflow the byte offset
+        if i >= self.len() {
+            return None;
+        }
+        Some(be_i32(&self.0[i * 4..i * 4 + 4]))
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = i32> + 'a {
+        self.0.chunks_exact(4).map(be_i32)
+    }
+
+    pub fn to_vec(&self) -> Vec<i32> {
+        self.iter().collect()
+    }
+}
+
+impl<'a> LongArrayRef<'a> {
+    pub fn len(&self) -> usize {
+        self.0.len() / 8
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    pub fn get(&self, i: usize) -> Option<i64> {
+        // Checked first so huge indices can't overflow the byte offset
+        if i >= self.len() {
+            return None;
+        }
+        Some(be_i64(&self.0[i * 8..i * 8 + 8]))
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = i64> + 'a {
+        self.0.chunks_exact(8).map(be_i64)
+    }
+
+    pub fn to_vec(&self) -> Vec<i64> {
+        self.iter().collect()
+    }
+}
+
+fn be_i32(bytes: &[u8]) -> i32 {
+    let mut array = [0; 4];
+    array.copy_from_slice(bytes);
+    i32::from_be_bytes(array)
+}
+
+fn be_i64(bytes: &[u8]) -> i64 {
+    let mut array = [0; 8];
+    array.copy_from_slice(bytes);
+    i64::from_be_bytes(array)
+}
+
+fn take_str<'a>(iterator: &mut Iter<'a, u8>) -> Result<&'a str, NbtError> {
+    let len = iterator.next_u16()? as usize;
+    let offset = iterator.len();
+    std::str::from_utf8(iterator.next_slice(len)?).map_err(|_| NbtError::InvalidUtf8 { offset })
+}
+
+fn take_array<'a>(iterator: &mut Iter<'a, u8>, element_size: usize, options: &ParseOptions) -> Result<&'a [u8], NbtError> {
+    let len = options.next_len(iterator)?;
+    iterator.next_slice(len.saturating_mul(element_size))
+}
+
+impl<'a> TagRef<'a> {
+    pub fn parse(data: &'a [u8]) -> Result<TagRef<'a>, NbtError> {
+        TagRef::parse_with(data, &NO_OPTIONS)
+    }
+
+    // Like Tag::parse_with, the skipped tags are left out
+    pub fn parse_with(data: &'a [u8], options: &ParseOptions) -> Result<TagRef<'a>, NbtError> {
+        let mut iterator = data.iter();
+        TagRef::parse_tag(&mut iterator, options).map_err(|e| e.rebase(data.len()))
+    }
+
+    fn parse_tag(iterator: &mut Iter<'a, u8>, options: &ParseOptions) -> Result<TagRef<'a>, NbtError> {
+        let offset = iterator.len();
+        let tag_id = iterator.next_tag_id()?;
+        if tag_id == 0 {
+            return Err(NbtError::InvalidTagId { offset, id: tag_id });
+        }
+
+        let name = take_str(iterator)?;
+        let payload = TagRef::parse_payload(iterator, tag_id, 0, options, &mut Vec::new())?;
+        Ok(TagRef { name, payload })
+    }
+
+    // Same checks as Tag::parse_filtered, which builds the owned tree
+    fn parse_payload(iterator: &mut Iter<'a, u8>, tag_id: u8, depth: usize, options: &ParseOptions, path: &mut Vec<&'a str>) -> Result<TagPayloadRef<'a>, NbtError> {
+        match tag_id {
+            1 => Ok(TagPayloadRef::Byte(iterator.next_i8()?)),
+            2 => Ok(TagPayloadRef::Short(iterator.next_i16()?)),
+            3 => Ok(TagPayloadRef::Int(iterator.next_i32()?)),
+            4 => Ok(TagPayloadRef::Long(iterator.next_i64()?)),
+            5 => Ok(TagPayloadRef::Float(iterator.next_f32()?)),
+            6 => Ok(TagPayloadRef::Double(iterator.next_f64()?)),
+            7 => Ok(TagPayloadRef::ByteArray(take_array(iterator, 1, options)?)),
+            8 => Ok(TagPayloadRef::String(take_str(iterator)?)),
+            9 => {
+                options.check_depth(iterator, depth)?;
+
+                let offset = iterator.len();
+                let tag_id = iterator.next_tag_id()?;
+                let tags_count = options.next_len(iterator)?;
+                if tag_id == 0 && tags_count > 0 {
+                    return Err(NbtError::InvalidTagId { offset, id: tag_id });
+                }
+
+                let mut items = Vec::new();
+                for _ in 0..tags_count {
+                    items.push(TagRef::parse_payload(iterator, tag_id, depth + 1, options, path)?);
+                }
+                Ok(TagPayloadRef::List(items))
+            },
+            10 => {
+                options.check_depth(iterator, depth)?;
+
+                let mut tags = Vec::new();
+                let mut tag_id = iterator.next_tag_id()?;
+                while tag_id != 0 {
+                    let name = take_str(iterator)?;
+                    path.push(name);
+                    if options.skips(path) {
+                        Tag::skip_payload_at(iterator, tag_id, depth + 1, options.max_depth, NbtFlavor::Java)?;
+                    } else {
+                        let payload = TagRef::parse_payload(iterator, tag_id, depth + 1, options, path)?;
+                        tags.push(TagRef { name, payload });
+                    }
+                    path.pop();
+                    tag_id = iterator.next_tag_id()?;
+                }
+                Ok(TagPayloadRef::Compound(tags))
+            },
+            11 => Ok(TagPayloadRef::IntArray(IntArrayRef(take_array(iterator, 4, options)?))),
+            12 => Ok(TagPayloadRef::LongArray(LongArrayRef(take_array(iterator, 8, options)?))),
+            _ => Err(NbtError::InvalidTagId { offset: iterator.len(), id: tag_id }),
+        }
+    }
+
+    // Copies everything out into an owned tag
+    pub fn to_owned_tag(&self) -> Tag {
+        Tag { name: self.name.to_string(), payload: self.payload.to_owned_payload() }
+    }
+}
+
+impl<'a> TagPayloadRef<'a> {
+    pub fn id(&self) -> u8 {
+        match self {
+            TagPayloadRef::Byte(_) => 1,
+            TagPayloadRef::Short(_) => 2,
+            TagPayloadRef::Int(_) => 3,
+            TagPayloadRef::Long(_) => 4,
+            TagPayloadRef::Float(_) => 5,
+            TagPayloadRef::Double(_) => 6,
+            TagPayloadRef::ByteArray(_) => 7,
+            TagPayloadRef::String(_) => 8,
+            TagPayloadRef::List(_) => 9,
+            TagPayloadRef::Compound(_) => 10,
+            TagPayloadRef::IntArray(_) => 11,
+            TagPayloadRef::LongArray(_) => 12,
+        }
+    }
+
+    pub fn get(&self, name: &str) -> Option<&TagPayloadRef<'a>> {
+        match self {
+            TagPayloadRef::Compound(tags) => tags.iter().find(|tag| tag.name == name).map(|tag| &tag.payload),
+            _ => None,
+        }
+    }
+
+    // Same paths as TagPayload::get_path
+    pub fn get_path(&self, path: &str) -> Result<&TagPayloadRef<'a>, PathError> {
+        let mut current = self;
+        let mut done = 0;
+
+        for (step, end) in path_steps(path)? {
+            let error_path = |until: usize| path[..until].to_string();
+            current = match step {
+                PathStep::Name(name) => match current {
+                    TagPayloadRef::Compound(_) => current.get(name).ok_or_else(|| PathError::Missing { path: error_path(end) })?,
+                    _ => return Err(PathError::NotACompound { path: error_path(done) }),
+                },
+                PathStep::Index(index) => match current {
+                    TagPayloadRef::List(items) => items.get(index).ok_or_else(|| PathError::IndexOutOfRange { path: error_path(end), len: items.len() })?,
+                    _ => return Err(PathError::NotAList { path: error_path(done) }),
+                },
+            };
+            done = end;
+        }
+
+        Ok(current)
+    }
+
+    pub fn as_str(&self) -> Option<&'a str> {
+        match self {
+            TagPayloadRef::String(value) => Some(value),
+            _ => None,
+        }
+    }
+
+    // Any of the integer types, widened
+    pub fn as_i64(&self) -> Option<i64> {
+        match *self {
+            TagPayloadRef::Byte(value) => Some(value as i64),
+            TagPayloadRef::Short(value) => Some(value as i64),
+            TagPayloadRef::Int(value) => Some(value as i64),
+            TagPayloadRef::Long(value) => Some(value),
+            _ => None,
+        }
+    }
+
+    pub fn as_list(&self) -> Option<&[TagPayloadRef<'a>]> {
+        match self {
+            TagPayloadRef::List(items) => Some(items),
+            _ => None,
+        }
+    }
+
+    pub fn as_compound(&self) -> Option<&[TagRef<'a>]> {
+        match self {
+            TagPayloadRef::Compound(tags) => Some(tags),
+            _ => None,
+        }
+    }
+
+    pub fn to_owned_payload(&self) -> TagPayload {
+        match self {
+            TagPayloadRef::Byte(value) => TagPayload::Byte(*value),
+            TagPayloadRef::Short(value) => TagPayload::Short(*value),
+            TagPayloadRef::Int(value) => TagPayload::Int(*value),
+            TagPayloadRef::Long(value) => TagPayload::Long(*value),
+            TagPayloadRef::Float(value) => TagPayload::Float(*value),
+            TagPayloadRef::Double(value) => TagPayload::Double(*value),
+            TagPayloadRef::ByteArray(bytes) => TagPayload::ByteArray(bytes.iter().map(|&byte| byte as i8).collect()),
+            TagPayloadRef::String(value) => TagPayload::String(value.to_string()),
+            TagPayloadRef::List(items) => TagPayload::List(items.iter().map(TagPayloadRef::to_owned_payload).collect()),
+            TagPayloadRef::Compound(tags) => TagPayload::Compound(tags.iter().map(TagRef::to_owned_tag).collect()),
+            TagPayloadRef::IntArray(values) => TagPayload::IntArray(values.to_vec()),
+            TagPayloadRef::LongArray(values) => TagPayload::LongArray(values.to_vec()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn tag(name: &str, payload: TagPayload) -> Tag {
+        Tag { name: name.to_string(), payload }
+    }
+
+    fn bytes_of(tag: &Tag) -> Vec<u8> {
+        let mut bytes = Vec::new();
+        tag.write(&mut bytes).unwrap();
+        bytes
+    }
+
+    fn every_type() -> Tag {
+        let nested = vec![tag("deep", TagPayload::List(vec![TagPayload::Compound(vec![tag("é", TagPayload::String("ünïcode".to_string()))].into())]))];
+        let root = vec![
+            tag("byte", TagPayload::Byte(-1)),
+            tag("short", TagPayload::Short(-300)),
+            tag("int", TagPayload::Int(i32::MIN)),
+            tag("long", TagPayload::Long(i64::MAX)),
+            tag("float", TagPayload::Float(1.5)),
+            tag("double", TagPayload::Double(-0.25)),
+            tag("bytes", TagPayload::ByteArray(vec![-128, 0, 127])),
+            tag("string", TagPayload::String("minecraft:stone".to_string())),
+            tag("list", TagPayload::List(vec![TagPayload::Int(1), TagPayload::Int(2)])),
+            tag("empty", TagPayload::List(Vec::new())),
+            tag("compound", TagPayload::Compound(nested.into())),
+            tag("ints", TagPayload::IntArray(vec![0, -1, i32::MAX, 0x01020304])),
+            tag("longs", TagPayload::LongArray(vec![i64::MIN, 0x0102030405060708, -2])),
+        ];
+        tag("root", TagPayload::Compound(root.into()))
+    }
+
+    #[test]
+    fn reads_the_same_tags_as_tag_parse() {
+        let bytes = bytes_of(&every_type());
+
+        let owned = Tag::parse(&mut bytes.iter()).unwrap();
+        let borrowed = TagRef::parse(&bytes).unwrap();
+        assert_eq!(bytes_of(&borrowed.to_owned_tag()), bytes_of(&owned));
+        assert_eq!(bytes_of(&owned), bytes);
+
+        let options = ParseOptions::new().skip("compound").skip("longs");
+        let owned = Tag::parse_with(&mut bytes.iter(), &options).unwrap();
+        let borrowed = TagRef::parse_with(&bytes, &options).unwrap();
+        assert_eq!(bytes_of(&borrowed.to_owned_tag()), bytes_of(&owned));
+        assert!(borrowed.payload.get("compound").is_none());
+        assert_eq!(borrowed.payload.get_path("int").unwrap().as_i64(), Some(i32::MIN as i64));
+    }
+
+    #[test]
+    fn indexes_int_and_long_arrays() {
+        let bytes = bytes_of(&every_type());
+        let borrowed = TagRef::parse(&bytes).unwrap();
+
+        let Some(TagPayloadRef::IntArray(ints)) = borrowed.payload.get("ints") else { panic!("no ints") };
+        assert_eq!(ints.len(), 4);
+        assert_eq!((ints.get(0), ints.get(1), ints.get(3), ints.get(4)), (Some(0), Some(-1), Some(0x01020304), None));
+        assert_eq!(ints.get(usize::MAX / 4), None);
+        assert_eq!(ints.to_vec(), vec![0, -1, i32::MAX, 0x01020304]);
+
+        let Some(TagPayloadRef::LongArray(longs)) = borrowed.payload.get("longs") else { panic!("no longs") };
+        assert_eq!(longs.len(), 3);
+        assert_eq!((longs.get(0), longs.get(1), longs.get(2), longs.get(3)), (Some(i64::MIN), Some(0x0102030405060708), Some(-2), None));
+        assert_eq!(longs.iter().collect::<Vec<_>>(), vec![i64::MIN, 0x0102030405060708, -2]);
+    }
+
+    #[test]
+    fn fails_where_tag_parse_fails() {
+        let bytes = bytes_of(&every_type());
+
+        for len in [0, 1, 5, bytes.len() / 2, bytes.len() - 1] {
+            let owned = Tag::parse(&mut bytes[..len].iter()).err();
+            assert!(owned.is_some(), "cut at {len}");
+            assert_eq!(TagRef::parse(&bytes[..len]).err(), owned, "cut at {len}");
+        }
+
+        // A list of end tags that claims to have items
+        let list = [9, 0, 1, b'l', 0, 0, 0, 0, 2];
+        let owned = Tag::parse(&mut list.iter()).err();
+        assert!(matches!(owned, Some(NbtError::InvalidTagId { id: 0, .. })));
+        assert_eq!(TagRef::parse(&list).err(), owned);
+    }
+}
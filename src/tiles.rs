@@ -0,0 +1,405 @@
+use anyhow::{ Result, Context, bail };
+use serde::{ Deserialize, Serialize };
+use std::{ collections::{ BTreeMap, BTreeSet, hash_map::DefaultHasher }, fs::{ self, File }, hash::{ Hash, Hasher }, io::{ BufWriter, Write }, path::{ Path, PathBuf }, time::UNIX_EPOCH };
+
+use crate::{ claims::ChunkPolicy, nbt::ParseOptions, region::Region, render::{ BlockColors, Image, MapLayer, SLIME_TINT, TopDownRenderer }, slime::slime_chunks_near, world::{ Dimension, World } };
+
+// A tile is one region, 32 chunks of 16 blocks on a side
+pub const TILE_SIZE: usize = 512;
+
+const MANIFEST: &str = "manifest.json";
+
+// Goes into the settings hash, bumped when the same settings render different tiles
+const TILE_VERSION: u32 = 1;
+
+// Tells whether a file changed since a tile was rendered from it
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+struct FileStamp {
+    len: u64,
+    // Nanoseconds since the epoch
+    modified: u64,
+}
+
+impl FileStamp {
+    fn of(path: &Path) -> Option<FileStamp> {
+        let metadata = fs::metadata(path).ok()?;
+        let modified = metadata.modified().ok()?.duration_since(UNIX_EPOCH).ok()?.as_nanos() as u64;
+        Some(FileStamp { len: metadata.len(), modified })
+    }
+}
+
+// What a tile was rendered from: its region and the one north of it, whose
+// last row of blocks shades the tile's first. None where there's no region.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+struct TileEntry {
+    region: Option<FileStamp>,
+    north: Option<FileStamp>,
+    // Tiles without a single pixel drawn get no PNG
+    empty: bool,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct Manifest {
+    // Hash of everything besides the regions that changes how tiles come out
+    settings: String,
+    // By "x.z" of the tile's region
+    tiles: BTreeMap<String, TileEntry>,
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct TileProgress {
+    pub rendered: usize,
+    // Tiles already rendered by an earlier run from the regions as they are
+    pub current: usize,
+    pub total: usize,
+}
+
+impl TileProgress {
+    pub fn is_done(&self) -> bool {
+        self.rendered + self.current == self.total
+    }
+}
+
+// Renders the top-down map of a dimension one region at a time into a folder
+// of tiles, 0/<x>.<z>.png by region position. A manifest in the folder says
+// what each tile was rendered from, so a run that was cut short or a run
+// after the world changed only renders the tiles that are missing or out of
+// date. Stitched together the tiles are the same image TopDownRenderer makes
+// of the whole dimension at once.
+pub struct TileMap<'a> {
+    world: &'a World,
+    dimension: Dimension,
+    dir: PathBuf,
+    colors: &'a BlockColors,
+    layer: MapLayer,
+    slime_seed: Option<i64>,
+    policy: Option<&'a ChunkPolicy>,
+    // In regions, inclusive
+    min: (i32, i32),
+    max: (i32, i32),
+}
+
+impl<'a> TileMap<'a> {
+    pub fn new(world: &'a World, dimension: Dimension, dir: impl Into<PathBuf>, colors: &'a BlockColors) -> Result<TileMap<'a>> {
+        let regions: Vec<(i32, i32)> = world.regions_in(dimension).map(|region| (region.x, region.z)).collect();
+        let (Some(min_x), Some(max_x)) = (regions.iter().map(|region| region.0).min(), regions.iter().map(|region| region.0).max()) else {
+            bail!("World has no region files in {dimension:?} to render tiles of");
+        };
+        let min_z = regions.iter().map(|region| region.1).min().unwrap();
+        let max_z = regions.iter().map(|region| region.1).max().unwrap();
+
+        Ok(TileMap {
+            world,
+            dimension,
+            dir: dir.into(),
+            colors,
+            layer: MapLayer::Blocks,
+            slime_seed: None,
+            policy: None,
+            min: (min_x, min_z),
+            max: (max_x, max_z),
+        })
+    }
+
+    pub fn layer(mut self, layer: MapLayer) -> Self {
+        self.layer = layer;
+        self
+    }
+
+    // Tints the slime chunks of a world with this seed
+    pub fn slime_chunks(mut self, seed: i64) -> Self {
+        self.slime_seed = Some(seed);
+        self
+    }
+
+    // Leaves the chunks the policy doesn't allow off the map
+    pub fn only_allowed(mut self, policy: &'a ChunkPolicy) -> Self {
+        self.policy = Some(policy);
+        self
+    }
+
+    // The chunk the stitched map starts at in its top left corner
+    pub fn min_chunk(&self) -> (i32, i32) {
+        (self.min.0 * 32, self.min.1 * 32)
+    }
+
+    pub fn tile_path(&self, level: u32, x: i32, z: i32) -> PathBuf {
+        self.dir.join(level.to_string()).join(format!("{x}.{z}.png"))
+    }
+
+    // Every region position between the corners, row by row
+    fn positions(&self) -> Vec<(i32, i32)> {
+        (self.min.1..=self.max.1).flat_map(|z| (self.min.0..=self.max.0).map(move |x| (x, z))).collect()
+    }
+
+    // DefaultHasher may hash differently after a Rust update, which only
+    // costs rendering the tiles once more
+    fn settings(&self) -> String {
+        let mut hasher = DefaultHasher::new();
+        TILE_VERSION.hash(&mut hasher);
+        self.dimension.hash(&mut hasher);
+        self.layer.hash(&mut hasher);
+        self.slime_seed.hash(&mut hasher);
+        format!("{:?}", self.policy).hash(&mut hasher);
+        self.colors.hash(&mut hasher);
+        format!("{:016x}", hasher.finish())
+    }
+
+    fn entry(&self, x: i32, z: i32) -> TileEntry {
+        let stamp = |x: i32, z: i32| self.world.region(self.dimension, x, z).and_then(|region| FileStamp::of(&region.path));
+        TileEntry { region: stamp(x, z), north: stamp(x, z - 1), empty: false }
+    }
+
+    // A manifest from other settings, or one that can't be read, is started over
+    fn load_manifest(&self, settings: &str) -> Manifest {
+        let path = self.dir.join(MANIFEST);
+        let manifest = fs::read_to_string(&path).ok().and_then(|text| match serde_json::from_str::<Manifest>(&text) {
+            Ok(manifest) => Some(manifest),
+            Err(e) => {
+                log::warn!("Rendering every tile again, {}: {e}", path.display());
+                None
+            },
+        });
+        match manifest {
+            Some(manifest) if manifest.settings == settings => manifest,
+            _ => Manifest { settings: settings.to_string(), tiles: BTreeMap::new() },
+        }
+    }
+
+    fn save_manifest(&self, manifest: &Manifest) -> Result<()> {
+        let path = self.dir.join(MANIFEST);
+        write_replacing(&path, |file| {
+            let mut w = BufWriter::new(file);
+            serde_json::to_writer_pretty(&mut w, manifest)?;
+            Ok(w.flush()?)
+        })
+    }
+
+    // Renders the tiles that are missing or out of date, asking go_on before
+    // each one and stopping where it says no. The manifest is saved after
+    // every tile, so nothing rendered is lost however the run ends.
+    pub fn render(&self, mut go_on: impl FnMut(TileProgress) -> bool) -> Result<TileProgress> {
+        fs::create_dir_all(self.dir.join("0")).with_context(|| format!("Could not create {}", self.dir.display()))?;
+        let mut manifest = self.load_manifest(&self.settings());
+        let positions = self.positions();
+        let keys: BTreeSet<String> = positions.iter().map(|(x, z)| format!("{x}.{z}")).collect();
+        manifest.tiles.retain(|key, _| keys.contains(key));
+
+        let mut progress = TileProgress { total: positions.len(), ..TileProgress::default() };
+        for (x, z) in positions {
+            let key = format!("{x}.{z}");
+            let mut entry = self.entry(x, z);
+            let is_current = manifest.tiles.get(&key).is_some_and(|done| {
+                done.region == entry.region && done.north == entry.north && (done.empty || self.tile_path(0, x, z).is_file())
+            });
+            if is_current {
+                progress.current += 1;
+                continue;
+            }
+            if !go_on(progress) {
+                return Ok(progress);
+            }
+
+            let tile = self.render_tile(x, z)?;
+            let path = self.tile_path(0, x, z);
+            entry.empty = tile.pixels.iter().all(|channel| *channel == 0);
+            if entry.empty {
+                if path.is_file() {
+                    fs::remove_file(&path)?;
+                }
+            } else {
+                write_replacing(&path, |file| tile.write_png(BufWriter::new(file)))?;
+            }
+            manifest.tiles.insert(key, entry);
+            self.save_manifest(&manifest)?;
+            progress.rendered += 1;
+        }
+        Ok(progress)
+    }
+
+    // The region at x, z with the last row of chunks of the region north of
+    // it, for the shading, which is cut off again
+    fn render_tile(&self, x: i32, z: i32) -> Result<Image> {
+        let mut renderer = TopDownRenderer::new(self.colors, self.layer, (x * 32, z * 32 - 1), (x * 32 + 31, z * 32 + 31));
+        if let Some(seed) = self.slime_seed {
+            renderer.tint_chunks(slime_chunks_near(seed, (x * 32 + 16, z * 32 + 16), 17), SLIME_TINT);
+        }
+        // Chunks from first_index on, the last row of the region north of the tile or all of the tile's
+        for (region_z, first_index) in [(z - 1, 31 * 32), (z, 0)] {
+            let Some(info) = self.world.region(self.dimension, x, region_z).filter(|info| !info.is_empty()) else {
+                continue;
+            };
+            let mut region = Region::open(&info.path).with_context(|| format!("Could not read region {}", info.path.display()))?;
+            let allowed = |index: usize| {
+                let (chunk_x, chunk_z) = (x * 32 + (index % 32) as i32, region_z * 32 + (index / 32) as i32);
+                index >= first_index && self.policy.is_none_or(|policy| policy.allows(Some(self.dimension), chunk_x, chunk_z))
+            };
+            for chunk in region.read_chunks_at(&ParseOptions::default(), None, allowed, |_| true).log_failures() {
+                renderer.add_chunk(&chunk);
+            }
+        }
+        Ok(renderer.finish().crop(0, 16, TILE_SIZE, TILE_SIZE))
+    }
+
+    // The whole map from the tiles, which all have to be rendered
+    pub fn stitch(&self) -> Result<Image> {
+        let manifest = self.load_manifest(&self.settings());
+        let columns = (self.max.0 - self.min.0 + 1) as usize;
+        let rows = (self.max.1 - self.min.1 + 1) as usize;
+        let mut image = Image::new(columns * TILE_SIZE, rows * TILE_SIZE);
+        for (x, z) in self.positions() {
+            let Some(entry) = manifest.tiles.get(&format!("{x}.{z}")) else {
+                bail!("Tile {x}.{z} hasn't been rendered yet");
+            };
+            if entry.empty {
+                continue;
+            }
+            let tile = Image::load_png(self.tile_path(0, x, z))?;
+            image.paste(&tile, (x - self.min.0) as usize * TILE_SIZE, (z - self.min.1) as usize * TILE_SIZE);
+        }
+        Ok(image)
+    }
+
+    // Zooms out from the rendered tiles, each level's tiles covering four of
+    // the level below at half the size, until a level doesn't have fewer
+    // tiles than the one below. Tile x, z of level n starts at block
+    // x * 512 * 2^n, z * 512 * 2^n. Returns how many levels there are,
+    // counting the tiles themselves.
+    pub fn build_levels(&self) -> Result<u32> {
+        let mut below: Vec<(i32, i32)> = self.positions().into_iter().filter(|(x, z)| self.tile_path(0, *x, *z).is_file()).collect();
+        let mut level = 0;
+        loop {
+            let mut above: BTreeMap<(i32, i32), Vec<(i32, i32)>> = BTreeMap::new();
+            for (x, z) in &below {
+                above.entry((x.div_euclid(2), z.div_euclid(2))).or_default().push((*x, *z));
+            }
+            if above.len() >= below.len() && level > 0 {
+                break;
+            }
+            let dir = self.dir.join((level + 1).to_string());
+            if dir.is_dir() {
+                fs::remove_dir_all(&dir)?;
+            }
+            fs::create_dir_all(&dir)?;
+            for ((x, z), children) in &above {
+                let mut tile = Image::new(TILE_SIZE * 2, TILE_SIZE * 2);
+                for (child_x, child_z) in children {
+                    let child = Image::load_png(self.tile_path(level, *child_x, *child_z))?;
+                    tile.paste(&child, (child_x - x * 2) as usize * TILE_SIZE, (child_z - z * 2) as usize * TILE_SIZE);
+                }
+                let tile = tile.half();
+                write_replacing(&self.tile_path(level + 1, *x, *z), |file| tile.write_png(BufWriter::new(file)))?;
+            }
+            below = above.into_keys().collect();
+            level += 1;
+            if below.len() <= 1 {
+                break;
+            }
+        }
+        Ok(level + 1)
+    }
+}
+
+// Writes next to path and moves the file over it once it's complete, so an
+// interrupted run never leaves half a tile or manifest behind
+fn write_replacing(path: &Path, write: impl FnOnce(&File) -> Result<()>) -> Result<()> {
+    let temporary = path.with_extension("tmp");
+    let file = File::create(&temporary).with_context(|| format!("Could not create {}", temporary.display()))?;
+    write(&file)?;
+    file.sync_all()?;
+    fs::rename(&temporary, path).with_context(|| format!("Could not write {}", path.display()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{ nbt::{ Tag, TagPayload }, region::{ chunk_index_in_region, put_chunks } };
+
+    fn tag(name: &str, payload: TagPayload) -> Tag {
+        Tag { name: name.to_string(), payload }
+    }
+
+    fn section(y: i8, block: &str) -> TagPayload {
+        let block = TagPayload::Compound(vec![tag("Name", TagPayload::String(block.to_string()))].into());
+        let block_states = vec![tag("palette", TagPayload::List(vec![block]))];
+        TagPayload::Compound(vec![tag("Y", TagPayload::Byte(y)), tag("block_states", TagPayload::Compound(block_states.into()))].into())
+    }
+
+    // Stone up to y 15, or with a layer of dirt up to y 31 on top
+    fn chunk(x: i32, z: i32, high: bool) -> Tag {
+        let top = if high { "minecraft:dirt" } else { "minecraft:air" };
+        let root = vec![
+            tag("DataVersion", TagPayload::Int(3700)),
+            tag("xPos", TagPayload::Int(x)),
+            tag("zPos", TagPayload::Int(z)),
+            tag("yPos", TagPayload::Int(0)),
+            tag("sections", TagPayload::List(vec![section(0, "minecraft:stone"), section(1, top)])),
+        ];
+        tag("", TagPayload::Compound(root.into()))
+    }
+
+    // Chunks on both sides of the borders between regions (0, 0), (1, 0),
+    // (-1, 0) and (0, 1), where shading and slime tints cross from one tile
+    // into the next. (1, 1) and (-1, 1) have no region.
+    fn world(dir: &Path) {
+        let chunks: [(i32, i32, bool); 8] = [(0, 31, true), (0, 32, false), (5, 31, false), (5, 32, true), (31, 4, true), (32, 4, false), (-1, 7, true), (0, 7, false)];
+        fs::create_dir_all(dir.join("region")).unwrap();
+        for (x, z, high) in chunks {
+            let path = dir.join("region").join(format!("r.{}.{}.mca", x.div_euclid(32), z.div_euclid(32)));
+            put_chunks(&path, &[(chunk_index_in_region(x, z), &chunk(x, z, high))]).unwrap();
+        }
+    }
+
+    // The map the way the map command makes it without tiles
+    fn whole_map(world: &World, colors: &BlockColors, seed: i64) -> Image {
+        let (min_chunk, max_chunk) = world.chunk_bounds(Dimension::Overworld).unwrap();
+        let mut renderer = TopDownRenderer::new(colors, MapLayer::Blocks, min_chunk, max_chunk);
+        renderer.tint_chunks(slime_chunks_near(seed, (0, 0), 100), SLIME_TINT);
+        for chunk in world.chunks_in(Dimension::Overworld) {
+            renderer.add_chunk(&chunk.unwrap().1);
+        }
+        renderer.finish()
+    }
+
+    #[test]
+    fn picks_up_where_it_stopped() {
+        let dir = std::env::temp_dir().join(format!("path-miner-tiles-{}", std::process::id()));
+        world(&dir.join("world"));
+        let world = World::open(dir.join("world")).unwrap();
+        let colors = BlockColors::default();
+        let tiles = TileMap::new(&world, Dimension::Overworld, dir.join("tiles"), &colors).unwrap().slime_chunks(42);
+
+        // Cut short after two tiles, like a Ctrl-C would
+        let progress = tiles.render(|progress| progress.rendered < 2).unwrap();
+        assert_eq!(progress, TileProgress { rendered: 2, current: 0, total: 6 });
+        assert!(!progress.is_done());
+        assert!(tiles.stitch().is_err());
+
+        let progress = tiles.render(|_| true).unwrap();
+        assert_eq!(progress, TileProgress { rendered: 4, current: 2, total: 6 });
+        let stitched = tiles.stitch().unwrap();
+        let whole = whole_map(&world, &colors, 42);
+        assert_eq!((stitched.width, stitched.height), (whole.width, whole.height));
+        assert!(stitched.pixels == whole.pixels);
+
+        // Nothing changed, nothing to render
+        assert_eq!(tiles.render(|_| true).unwrap(), TileProgress { rendered: 0, current: 6, total: 6 });
+
+        // A changed region takes its own tile and the one south of it along
+        put_chunks(dir.join("world").join("region").join("r.0.0.mca"), &[(chunk_index_in_region(0, 31), &chunk(0, 31, false))]).unwrap();
+        assert_eq!(tiles.render(|_| true).unwrap(), TileProgress { rendered: 2, current: 4, total: 6 });
+        assert!(tiles.stitch().unwrap().pixels == whole_map(&world, &colors, 42).pixels);
+
+        // Other settings start over
+        let tiles = TileMap::new(&world, Dimension::Overworld, dir.join("tiles"), &colors).unwrap();
+        assert_eq!(tiles.render(|_| true).unwrap().rendered, 6);
+
+        // Regions -1 to 1 zoom out to -1 and 0, which stay -1 and 0
+        assert_eq!(tiles.build_levels().unwrap(), 2);
+        assert!(tiles.tile_path(1, -1, 0).is_file());
+        assert!(tiles.tile_path(1, 0, 0).is_file());
+        assert_eq!(Image::load_png(tiles.tile_path(1, 0, 0)).unwrap().width, TILE_SIZE);
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,362 @@
+use std::collections::{ HashMap, HashSet };
+
+use crate::nbt::{ TagPayload, path_steps };
+
+// Values of an array shown at a time, the rest are paged through
+pub const ARRAY_PAGE: usize = 64;
+
+// One line of the tree as it's shown
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Row {
+    pub depth: usize,
+    // Path of the tag in get_path syntax. Values of an array have the array's,
+    // get_path can't go into arrays.
+    pub path: String,
+    pub text: String,
+    // Compounds, lists and arrays with anything in them
+    pub expandable: bool,
+    pub expanded: bool,
+}
+
+// A collapsible tree over a tag, with a cursor. Keeps only what's expanded
+// and where, the tag itself is passed in each time so it can be swapped for
+// another chunk's without the tree borrowing it. Rows start with the root's
+// children, a chunk's root is always a compound.
+#[derive(Clone, Debug, Default)]
+pub struct TagTree {
+    expanded: HashSet<String>,
+    // The page of ARRAY_PAGE values shown for each array not on its first
+    pages: HashMap<String, usize>,
+    pub cursor: usize,
+}
+
+impl TagTree {
+    pub fn new() -> TagTree {
+        TagTree::default()
+    }
+
+    pub fn rows(&self, root: &TagPayload) -> Vec<Row> {
+        let mut rows = Vec::new();
+        self.push_children(root, "", 0, &mut rows);
+        rows
+    }
+
+    // Path of the row under the cursor, empty if there are no rows
+    pub fn path(&self, root: &TagPayload) -> String {
+        self.rows(root).into_iter().nth(self.cursor).map(|row| row.path).unwrap_or_default()
+    }
+
+    pub fn up(&mut self) {
+        self.cursor = self.cursor.saturating_sub(1);
+    }
+
+    pub fn down(&mut self, root: &TagPayload) {
+        self.cursor = (self.cursor + 1).min(self.rows(root).len().saturating_sub(1));
+    }
+
+    // Expands the row under the cursor, or moves onto its first child when it
+    // is already
+    pub fn right(&mut self, root: &TagPayload) {
+        let Some(row) = self.rows(root).into_iter().nth(self.cursor) else {
+            return;
+        };
+        if !row.expandable {
+            return;
+        }
+        if row.expanded {
+            self.down(root);
+        } else {
+            self.expanded.insert(row.path);
+        }
+    }
+
+    // Collapses the row under the cursor, or moves onto its parent when it
+    // has nothing to collapse
+    pub fn left(&mut self, root: &TagPayload) {
+        let rows = self.rows(root);
+        let Some(row) = rows.get(self.cursor) else {
+            return;
+        };
+        if row.expanded {
+            self.expanded.remove(&row.path);
+        } else if let Some(parent) = rows[..self.cursor].iter().rposition(|parent| parent.depth < row.depth) {
+            self.cursor = parent;
+        }
+    }
+
+    pub fn toggle(&mut self, root: &TagPayload) {
+        let Some(row) = self.rows(root).into_iter().nth(self.cursor) else {
+            return;
+        };
+        if row.expanded {
+            self.expanded.remove(&row.path);
+        } else if row.expandable {
+            self.expanded.insert(row.path);
+        }
+    }
+
+    // Shows the next or previous page of the expanded array the cursor is on
+    // or in, going no further than its first and last page
+    pub fn page(&mut self, root: &TagPayload, pages: isize) {
+        let path = self.path(root);
+        if !self.expanded.contains(&path) {
+            return;
+        }
+        let Some(len) = root.get_path(&path).ok().and_then(array_len) else {
+            return;
+        };
+        let last = len.saturating_sub(1) / ARRAY_PAGE;
+        let page = self.pages.get(&path).copied().unwrap_or(0).saturating_add_signed(pages).min(last);
+        self.pages.insert(path.clone(), page);
+
+        // Back onto the array itself, the values it had are gone
+        if let Some(row) = self.rows(root).iter().position(|row| row.path == path) {
+            self.cursor = row;
+        }
+    }
+
+    // Moves the cursor to the next tag after it whose name has the text in
+    // it, ignoring case, wrapping around at the end. The tags it's in get
+    // expanded so it shows. Whether there was one.
+    pub fn find(&mut self, root: &TagPayload, needle: &str) -> bool {
+        let needle = needle.to_lowercase();
+        let mut paths = Vec::new();
+        tag_paths(root, "", &mut paths);
+        let current = self.path(root);
+        let start = paths.iter().position(|(path, _)| *path == current).map_or(0, |i| i + 1);
+
+        let found = paths.iter().cycle().skip(start).take(paths.len())
+            .find(|(_, name)| name.as_deref().is_some_and(|name| name.to_lowercase().contains(&needle)));
+        let Some((path, _)) = found else {
+            return false;
+        };
+
+        if let Ok(steps) = path_steps(path) {
+            for (_, end) in &steps[..steps.len().saturating_sub(1)] {
+                self.expanded.insert(path[..*end].to_string());
+            }
+        }
+        if let Some(row) = self.rows(root).iter().position(|row| row.path == *path) {
+            self.cursor = row;
+        }
+        true
+    }
+
+    fn push_children(&self, tag: &TagPayload, path: &str, depth: usize, rows: &mut Vec<Row>) {
+        match tag {
+            TagPayload::Compound(tags) => {
+                for child in tags.iter() {
+                    self.push_row(&child.payload, join(path, &child.name), &child.name, depth, rows);
+                }
+            },
+            TagPayload::List(items) => {
+                for (i, item) in items.iter().enumerate() {
+                    let step = format!("[{i}]");
+                    self.push_row(item, join(path, &step), &step, depth, rows);
+                }
+            },
+            _ => {},
+        }
+    }
+
+    fn push_row(&self, tag: &TagPayload, path: String, label: &str, depth: usize, rows: &mut Vec<Row>) {
+        let len = match tag {
+            TagPayload::Compound(tags) => Some(tags.len()),
+            TagPayload::List(items) => Some(items.len()),
+            other => array_len(other),
+        };
+        let expandable = len.is_some_and(|len| len > 0);
+        let expanded = expandable && self.expanded.contains(&path);
+        rows.push(Row { depth, path: path.clone(), text: format!("{label}: {}", summary(tag)), expandable, expanded });
+        if !expanded {
+            return;
+        }
+
+        let Some(len) = array_len(tag) else {
+            self.push_children(tag, &path, depth + 1, rows);
+            return;
+        };
+        let first = self.pages.get(&path).copied().unwrap_or(0) * ARRAY_PAGE;
+        let end = (first + ARRAY_PAGE).min(len);
+        for i in first..end {
+            let value = match tag {
+                TagPayload::ByteArray(values) => values[i].to_string(),
+                TagPayload::IntArray(values) => values[i].to_string(),
+                TagPayload::LongArray(values) => values[i].to_string(),
+                _ => unreachable!(),
+            };
+            rows.push(Row { depth: depth + 1, path: path.clone(), text: format!("[{i}] {value}"), expandable: false, expanded: false });
+        }
+        if len > ARRAY_PAGE {
+            rows.push(Row { depth: depth + 1, path, text: format!("values {first} to {} of {len}", end - 1), expandable: false, expanded: false });
+        }
+    }
+}
+
+fn array_len(tag: &TagPayload) -> Option<usize> {
+    match tag {
+        TagPayload::ByteArray(values) => Some(values.len()),
+        TagPayload::IntArray(values) => Some(values.len()),
+        TagPayload::LongArray(values) => Some(values.len()),
+        _ => None,
+    }
+}
+
+// Every tag below tag in the order the tree shows them, with its name if it
+// has one, so list items have none
+fn tag_paths(tag: &TagPayload, path: &str, paths: &mut Vec<(String, Option<String>)>) {
+    match tag {
+        TagPayload::Compound(tags) => {
+            for child in tags.iter() {
+                let child_path = join(path, &child.name);
+                paths.push((child_path.clone(), Some(child.name.clone())));
+                tag_paths(&child.payload, &child_path, paths);
+            }
+        },
+        TagPayload::List(items) => {
+            for (i, item) in items.iter().enumerate() {
+                let item_path = join(path, &format!("[{i}]"));
+                paths.push((item_path.clone(), None));
+                tag_paths(item, &item_path, paths);
+            }
+        },
+        _ => {},
+    }
+}
+
+// Adds a step to a get_path path, a name or an index like [3]
+pub fn join(path: &str, step: &str) -> String {
+    if path.is_empty() || step.starts_with('[') {
+        format!("{path}{step}")
+    } else {
+        format!("{path}.{step}")
+    }
+}
+
+pub fn type_name(tag: &TagPayload) -> &'static str {
+    match tag {
+        TagPayload::Byte(_) => "byte",
+        TagPayload::Short(_) => "short",
+        TagPayload::Int(_) => "int",
+        TagPayload::Long(_) => "long",
+        TagPayload::Float(_) => "float",
+        TagPayload::Double(_) => "double",
+        TagPayload::ByteArray(_) => "byte[]",
+        TagPayload::String(_) => "string",
+        TagPayload::List(_) => "list",
+        TagPayload::Compound(_) => "compound",
+        TagPayload::IntArray(_) => "int[]",
+        TagPayload::LongArray(_) => "long[]",
+    }
+}
+
+// A single line describing the tag, with long strings cut short
+pub fn summary(tag: &TagPayload) -> String {
+    match tag {
+        TagPayload::ByteArray(values) => format!("{} values", values.len()),
+        TagPayload::IntArray(values) => format!("{} values", values.len()),
+        TagPayload::LongArray(values) => format!("{} values", values.len()),
+        TagPayload::List(items) => format!("{} entries", items.len()),
+        TagPayload::Compound(tags) => format!("{} tags", tags.len()),
+        TagPayload::String(text) if text.chars().count() > 60 => format!("\"{}...\"", text.chars().take(60).collect::<String>()),
+        other => other.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::nbt::Tag;
+
+    fn tag(name: &str, payload: TagPayload) -> Tag {
+        Tag { name: name.to_string(), payload }
+    }
+
+    fn chunk() -> TagPayload {
+        let section = |y: i8| TagPayload::Compound(vec![
+            tag("Y", TagPayload::Byte(y)),
+            tag("BlockLight", TagPayload::ByteArray(vec![0; 2048])),
+        ].into());
+        TagPayload::Compound(vec![
+            tag("DataVersion", TagPayload::Int(3700)),
+            tag("sections", TagPayload::List(vec![section(0), section(1)])),
+            tag("Status", TagPayload::String("minecraft:full".to_string())),
+        ].into())
+    }
+
+    fn texts(tree: &TagTree, root: &TagPayload) -> Vec<String> {
+        tree.rows(root).into_iter().map(|row| format!("{}{}", "  ".repeat(row.depth), row.text)).collect()
+    }
+
+    #[test]
+    fn expands_collapses_and_walks_back_up() {
+        let root = chunk();
+        let mut tree = TagTree::new();
+        assert_eq!(texts(&tree, &root), vec!["DataVersion: 3700", "sections: 2 entries", "Status: \"minecraft:full\""]);
+
+        tree.right(&root);
+        assert_eq!(tree.rows(&root).len(), 3, "ints don't expand");
+        // Expand sections, onto [0], expand it and onto Y
+        tree.down(&root);
+        for _ in 0..4 {
+            tree.right(&root);
+        }
+        assert_eq!(texts(&tree, &root), vec![
+            "DataVersion: 3700",
+            "sections: 2 entries",
+            "  [0]: 2 tags",
+            "    Y: 0",
+            "    BlockLight: 2048 values",
+            "  [1]: 2 tags",
+            "Status: \"minecraft:full\"",
+        ]);
+        assert_eq!(tree.cursor, 3);
+        assert_eq!(tree.path(&root), "sections[0].Y");
+        assert!(root.get_path(&tree.path(&root)).is_ok());
+
+        // Left goes up to the parent, then collapses it
+        tree.left(&root);
+        assert_eq!(tree.path(&root), "sections[0]");
+        tree.left(&root);
+        assert_eq!(tree.rows(&root).len(), 5);
+        tree.down(&root);
+        tree.down(&root);
+        tree.down(&root);
+        assert_eq!(tree.path(&root), "Status");
+    }
+
+    #[test]
+    fn pages_through_long_arrays() {
+        let root = chunk();
+        let mut tree = TagTree::new();
+        assert!(tree.find(&root, "blocklight"));
+        assert_eq!(tree.path(&root), "sections[0].BlockLight");
+
+        tree.right(&root);
+        let rows = tree.rows(&root);
+        assert_eq!(rows.len(), 7 + ARRAY_PAGE + 1);
+        assert_eq!(rows[tree.cursor + ARRAY_PAGE + 1].text, "values 0 to 63 of 2048");
+
+        tree.down(&root);
+        tree.page(&root, 1);
+        assert_eq!(tree.path(&root), "sections[0].BlockLight");
+        assert_eq!(tree.rows(&root)[tree.cursor + 1].text, "[64] 0");
+        tree.page(&root, 100);
+        assert_eq!(tree.rows(&root)[tree.cursor + ARRAY_PAGE + 1].text, "values 1984 to 2047 of 2048");
+        tree.page(&root, -100);
+        assert_eq!(tree.rows(&root)[tree.cursor + 1].text, "[0] 0");
+    }
+
+    #[test]
+    fn finds_names_after_the_cursor_and_wraps() {
+        let root = chunk();
+        let mut tree = TagTree::new();
+        assert!(tree.find(&root, "Y"));
+        assert_eq!(tree.path(&root), "sections[0].Y");
+        assert!(tree.find(&root, "Y"));
+        assert_eq!(tree.path(&root), "sections[1].Y");
+        assert!(tree.find(&root, "Y"));
+        assert_eq!(tree.path(&root), "sections[0].Y");
+        assert!(!tree.find(&root, "Heightmaps"));
+    }
+}
@@ -0,0 +1,193 @@
+use anyhow::{ Result, bail };
+use serde_json::json;
+use std::{ fs, path::Path, str::FromStr, time::SystemTime };
+
+use crate::pos::BlockPos;
+use crate::render::Rgba;
+use crate::world::Dimension;
+
+pub struct Waypoint {
+    pub name: String,
+    pub pos: BlockPos,
+    pub dimension: Dimension,
+    pub color: Rgba,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum WaypointFormat {
+    // Xaero's Minimap, a text file per dimension
+    Xaeros,
+    // JourneyMap 5, a JSON file per waypoint
+    JourneyMap,
+    // Baritone's binary user waypoints, a file per dimension
+    Baritone,
+}
+
+impl FromStr for WaypointFormat {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<WaypointFormat> {
+        match s.to_ascii_lowercase().as_str() {
+            "xaeros" | "xaero" => Ok(WaypointFormat::Xaeros),
+            "journeymap" => Ok(WaypointFormat::JourneyMap),
+            "baritone" => Ok(WaypointFormat::Baritone),
+            _ => bail!("Unknown waypoint format \"{s}\", expected xaeros, journeymap or baritone"),
+        }
+    }
+}
+
+// The 16 chat colors Xaero's Minimap picks waypoint colors from
+const CHAT_COLORS: [Rgba; 16] = [
+    [0, 0, 0, 255],
+    [0, 0, 170, 255],
+    [0, 170, 0, 255],
+    [0, 170, 170, 255],
+    [170, 0, 0, 255],
+    [170, 0, 170, 255],
+    [255, 170, 0, 255],
+    [170, 170, 170, 255],
+    [85, 85, 85, 255],
+    [85, 85, 255, 255],
+    [85, 255, 85, 255],
+    [85, 255, 255, 255],
+    [255, 85, 85, 255],
+    [255, 85, 255, 255],
+    [255, 255, 85, 255],
+    [255, 255, 255, 255],
+];
+
+fn nearest_chat_color(color: Rgba) -> usize {
+    let distance = |other: &Rgba| (0..3).map(|i| (color[i] as i32 - other[i] as i32).pow(2)).sum::<i32>();
+    (0..CHAT_COLORS.len()).min_by_key(|&i| distance(&CHAT_COLORS[i])).unwrap_or(0)
+}
+
+// Writes the waypoints into a folder laid out the way the mod keeps them, so
+// its contents can be copied into the mod's folder for the world. Returns the
+// number of files written.
+pub fn write_waypoints(dir: &Path, format: WaypointFormat, waypoints: &[Waypoint]) -> Result<usize> {
+    match format {
+        WaypointFormat::Xaeros => write_xaeros(dir, waypoints),
+        WaypointFormat::JourneyMap => write_journeymap(dir, waypoints),
+        WaypointFormat::Baritone => write_baritone(dir, waypoints),
+    }
+}
+
+// Goes into XaeroWaypoints/<world>/, with a dim%<id> folder per dimension
+fn write_xaeros(dir: &Path, waypoints: &[Waypoint]) -> Result<usize> {
+    let mut files = 0;
+
+    for dimension in Dimension::ALL {
+        let lines: Vec<String> = waypoints.iter()
+            .filter(|waypoint| waypoint.dimension == dimension)
+            .map(|waypoint| {
+                // Colons separate the fields, so they can't be part of the name
+                let name = waypoint.name.replace(':', "_");
+                let initials: String = name.chars().find(char::is_ascii_alphanumeric).map(|c| c.to_ascii_uppercase()).into_iter().collect();
+                let BlockPos { x, y, z } = waypoint.pos;
+                format!("waypoint:{name}:{initials}:{x}:{y}:{z}:{}:false:0:gui.xaero_default:false:0:0:false", nearest_chat_color(waypoint.color))
+            })
+            .collect();
+        if lines.is_empty() {
+            continue;
+        }
+
+        let id = match dimension {
+            Dimension::Overworld => 0,
+            Dimension::Nether => -1,
+            Dimension::End => 1,
+        };
+        let folder = dir.join(format!("dim%{id}"));
+        fs::create_dir_all(&folder)?;
+
+        let header = "#\n#waypoint:name:initials:x:y:z:color:disabled:type:set:rotate_on_tp:tp_yaw:visibility_type:destination\n#\n";
+        fs::write(folder.join("mw$default_1.txt"), format!("{header}{}\n", lines.join("\n")))?;
+        files += 1;
+    }
+
+    Ok(files)
+}
+
+// Goes into journeymap/data/sp/<world>/waypoints/
+fn write_journeymap(dir: &Path, waypoints: &[Waypoint]) -> Result<usize> {
+    fs::create_dir_all(dir)?;
+
+    for waypoint in waypoints {
+        let BlockPos { x, y, z } = waypoint.pos;
+        let [r, g, b, _] = waypoint.color;
+        let id = format!("{}_{x},{y},{z}", waypoint.name.replace(':', "_"));
+        let json = json!({
+            "id": id,
+            "name": waypoint.name,
+            "icon": "waypoint-normal.png",
+            "x": x,
+            "y": y,
+            "z": z,
+            "r": r,
+            "g": g,
+            "b": b,
+            "enable": true,
+            "type": "Normal",
+            "origin": "path-miner",
+            "dimensions": [waypoint.dimension.id()],
+            "persistent": true,
+        });
+        fs::write(dir.join(format!("{id}.json")), serde_json::to_string_pretty(&json)?)?;
+    }
+
+    Ok(waypoints.len())
+}
+
+// Goes into saves/<world>/baritone/ for single player worlds, with the other
+// dimensions in DIM-1 and DIM1 like the save itself. Baritone writes these
+// with Java's DataOutputStream, so everything is big endian.
+fn write_baritone(dir: &Path, waypoints: &[Waypoint]) -> Result<usize> {
+    // Baritone checks files start with this
+    const MAGIC: i64 = 121977993584;
+    let now = SystemTime::now().duration_since(SystemTime::UNIX_EPOCH).map_or(0, |time| time.as_millis() as i64);
+    let mut files = 0;
+
+    for dimension in Dimension::ALL {
+        let waypoints: Vec<&Waypoint> = waypoints.iter().filter(|waypoint| waypoint.dimension == dimension).collect();
+        if waypoints.is_empty() {
+            continue;
+        }
+
+        let mut data = Vec::new();
+        data.extend(MAGIC.to_be_bytes());
+        data.extend((waypoints.len() as i64).to_be_bytes());
+        for waypoint in waypoints {
+            write_java_utf(&mut data, &waypoint.name);
+            data.extend(now.to_be_bytes());
+            for coord in [waypoint.pos.x, waypoint.pos.y, waypoint.pos.z] {
+                data.extend(coord.to_be_bytes());
+            }
+        }
+
+        let folder = dir.join(dimension.directory()).join("waypoints");
+        fs::create_dir_all(&folder)?;
+        fs::write(folder.join("user.mp4"), data)?;
+        files += 1;
+    }
+
+    Ok(files)
+}
+
+// DataOutputStream.writeUTF: a length, then UTF-16 units as modified UTF-8,
+// which writes 0 as two bytes and characters past the BMP as two surrogates.
+// Names are cut short rather than going over the 65535 bytes it allows.
+fn write_java_utf(out: &mut Vec<u8>, s: &str) {
+    let mut bytes = Vec::new();
+    for unit in s.encode_utf16() {
+        let encoded: &[u8] = match unit {
+            0x0001..=0x007f => &[unit as u8],
+            0x0000 | 0x0080..=0x07ff => &[0xc0 | (unit >> 6) as u8, 0x80 | (unit & 0x3f) as u8],
+            _ => &[0xe0 | (unit >> 12) as u8, 0x80 | (unit >> 6 & 0x3f) as u8, 0x80 | (unit & 0x3f) as u8],
+        };
+        if bytes.len() + encoded.len() > u16::MAX as usize {
+            break;
+        }
+        bytes.extend_from_slice(encoded);
+    }
+    out.extend((bytes.len() as u16).to_be_bytes());
+    out.extend(bytes);
+}
@@ -0,0 +1,578 @@
+use anyhow::{ Result, Context, ensure, bail };
+use serde::Deserialize;
+use std::{fs, path::{Path, PathBuf}, vec, collections::HashMap, str::FromStr, sync::Mutex};
+
+use crate::{ block_id::BlockId, height::WorldHeight, level::DimensionType, cache::{ AccessLog, CacheConfig, CacheStats, ChunkCache, ChunkKey }, claims::ChunkPolicy, nbt::ParseOptions, chunk::{ Chunk, BlockType }, level::LevelDat, pos::BlockPos, region::{Region, parse_region_file_name, chunk_to_region_coord, chunk_index_in_region, chunk_at_index} };
+#[cfg(feature = "bedrock")]
+use crate::bedrock::BedrockDb;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Dimension {
+    Overworld,
+    Nether,
+    End,
+}
+
+impl Dimension {
+    pub const ALL: [Dimension; 3] = [Dimension::Overworld, Dimension::Nether, Dimension::End];
+
+    // Relative to the save folder
+    pub fn directory(self) -> &'static str {
+        match self {
+            Dimension::Overworld => "",
+            Dimension::Nether => "DIM-1",
+            Dimension::End => "DIM1",
+        }
+    }
+
+    // Namespaced id, as commands and newer save data refer to it
+    pub fn id(self) -> &'static str {
+        match self {
+            Dimension::Overworld => "minecraft:overworld",
+            Dimension::Nether => "minecraft:the_nether",
+            Dimension::End => "minecraft:the_end",
+        }
+    }
+}
+
+impl FromStr for Dimension {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Dimension> {
+        match s.trim_start_matches("minecraft:") {
+            "overworld" => Ok(Dimension::Overworld),
+            "nether" | "the_nether" => Ok(Dimension::Nether),
+            "end" | "the_end" => Ok(Dimension::End),
+            _ => bail!("Unknown dimension \"{s}\""),
+        }
+    }
+}
+
+// The part of a datapack's dimension type that sets the height
+#[derive(Deserialize)]
+struct RawDimensionType {
+    min_y: i32,
+    height: u32,
+}
+
+#[derive(Clone, Debug)]
+pub struct RegionInfo {
+    pub dimension: Dimension,
+    pub x: i32,
+    pub z: i32,
+    pub path: PathBuf,
+}
+
+impl RegionInfo {
+    // The game leaves 0 byte region files behind, they have no chunks
+    pub fn is_empty(&self) -> bool {
+        fs::metadata(&self.path).is_ok_and(|metadata| metadata.len() == 0)
+    }
+}
+
+pub struct World {
+    path: PathBuf,
+    regions: Vec<RegionInfo>,
+    entity_regions: Vec<RegionInfo>,
+    poi_regions: Vec<RegionInfo>,
+    // Chunks kept between views, none unless asked for
+    cache: Mutex<ChunkCache>,
+    // Set for Bedrock worlds, which have no regions
+    #[cfg(feature = "bedrock")]
+    bedrock: Option<BedrockDb>,
+}
+
+impl World {
+    pub fn open(path: impl AsRef<Path>) -> Result<World> {
+        let path = path.as_ref().to_path_buf();
+        ensure!(path.is_dir(), "{} is not a world folder", path.display());
+
+        let regions = find_regions(&path, "region")?;
+        let entity_regions = find_regions(&path, "entities")?;
+        let poi_regions = find_regions(&path, "poi")?;
+
+        #[cfg(feature = "bedrock")]
+        let bedrock = match path.join("db").join("CURRENT").is_file() {
+            true => Some(BedrockDb::open(path.join("db")).context("Could not open the Bedrock world's database")?),
+            false => None,
+        };
+
+        Ok(World {
+            path,
+            regions,
+            entity_regions,
+            poi_regions,
+            cache: Mutex::new(ChunkCache::new(CacheConfig::default())),
+            #[cfg(feature = "bedrock")]
+            bedrock,
+        })
+    }
+
+    // Keeps chunks views read, so views over the same chunks don't read them again
+    pub fn with_cache(mut self, config: CacheConfig) -> World {
+        self.cache = Mutex::new(ChunkCache::new(config));
+        self
+    }
+
+    // Records every chunk views look up, to replay against other caches
+    pub fn record_accesses(self) -> World {
+        self.cache.lock().unwrap().record();
+        self
+    }
+
+    pub fn cache_stats(&self) -> CacheStats {
+        self.cache.lock().unwrap().stats()
+    }
+
+    pub fn access_log(&self) -> Option<AccessLog> {
+        self.cache.lock().unwrap().log()
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    pub fn level_dat(&self) -> Result<LevelDat> {
+        LevelDat::load(self.path.join("level.dat"))
+    }
+
+    // The dimension's height from its type, where level.dat or a datapack
+    // sets it, else from the sections of the first region's chunks, else the
+    // game's for the world's version
+    pub fn height(&self, dimension: Dimension) -> Result<WorldHeight> {
+        let level = self.level_dat().ok();
+        match level.as_ref().and_then(|level| level.dimension_type(dimension)) {
+            Some(DimensionType::Height(height)) => return Ok(height),
+            Some(DimensionType::Id(id)) => {
+                if let Some(height) = self.datapack_height(&id)? {
+                    return Ok(height);
+                }
+            },
+            None => {},
+        }
+
+        for region in self.regions_in(dimension).filter(|region| !region.is_empty()) {
+            match Region::open(&region.path) {
+                Ok(mut file) => if let Some(height) = file.world_height() {
+                    return Ok(height);
+                },
+                Err(e) => log::warn!("Skipping region {}: {e:#}", region.path.display()),
+            }
+        }
+
+        let data_version = level.and_then(|level| level.data_version()).unwrap_or(0);
+        Ok(WorldHeight::for_data_version(dimension, data_version))
+    }
+
+    // The height of a dimension type from the datapacks folder. Zipped
+    // datapacks aren't read, and where several have the type the first by
+    // name wins, which needn't be the one the game enabled last.
+    fn datapack_height(&self, id: &str) -> Result<Option<WorldHeight>> {
+        let (namespace, name) = id.split_once(':').unwrap_or(("minecraft", id));
+        let Ok(entries) = fs::read_dir(self.path.join("datapacks")) else {
+            return Ok(None);
+        };
+        let mut packs: Vec<PathBuf> = entries.filter_map(|entry| Some(entry.ok()?.path())).filter(|path| path.is_dir()).collect();
+        packs.sort();
+
+        for pack in packs {
+            let path = pack.join("data").join(namespace).join("dimension_type").join(format!("{name}.json"));
+            if !path.is_file() {
+                continue;
+            }
+            let text = fs::read_to_string(&path).with_context(|| format!("Could not read {}", path.display()))?;
+            let raw: RawDimensionType = serde_json::from_str(&text).with_context(|| format!("Could not parse {}", path.display()))?;
+            return Ok(Some(WorldHeight::new(raw.min_y, raw.height)));
+        }
+        Ok(None)
+    }
+
+    pub fn regions(&self) -> &[RegionInfo] {
+        &self.regions
+    }
+
+    pub fn regions_in(&self, dimension: Dimension) -> impl Iterator<Item = &RegionInfo> {
+        self.regions.iter().filter(move |region| region.dimension == dimension)
+    }
+
+    pub fn region(&self, dimension: Dimension, x: i32, z: i32) -> Option<&RegionInfo> {
+        self.regions.iter().find(|region| region.dimension == dimension && region.x == x && region.z == z)
+    }
+
+    // Region files under entities/, which only exist since 1.17
+    pub fn entity_regions(&self) -> &[RegionInfo] {
+        &self.entity_regions
+    }
+
+    // Worlds saved before 1.17 kept entities in the terrain chunks, so their
+    // regions are used when a dimension has no entities folder
+    pub fn entity_regions_in(&self, dimension: Dimension) -> Vec<&RegionInfo> {
+        let entity_regions: Vec<&RegionInfo> = self.entity_regions.iter().filter(|region| region.dimension == dimension).collect();
+        if entity_regions.is_empty() {
+            self.regions_in(dimension).collect()
+        } else {
+            entity_regions
+        }
+    }
+
+    // Region files under poi/, which only exist since 1.14
+    pub fn poi_regions(&self) -> &[RegionInfo] {
+        &self.poi_regions
+    }
+
+    pub fn poi_regions_in(&self, dimension: Dimension) -> impl Iterator<Item = &RegionInfo> {
+        self.poi_regions.iter().filter(move |region| region.dimension == dimension)
+    }
+
+    // Regions are read one at a time as the iterator advances
+    pub fn chunks(&self) -> WorldChunks<'_> {
+        #[cfg(feature = "bedrock")]
+        if let Some(bedrock) = &self.bedrock {
+            return WorldChunks::bedrock(bedrock, None);
+        }
+        WorldChunks::new(self.regions.iter().collect())
+    }
+
+    pub fn chunks_in(&self, dimension: Dimension) -> WorldChunks<'_> {
+        #[cfg(feature = "bedrock")]
+        if let Some(bedrock) = &self.bedrock {
+            return WorldChunks::bedrock(bedrock, Some(dimension));
+        }
+        WorldChunks::new(self.regions_in(dimension).collect())
+    }
+
+    // The smallest chunk rectangle around every chunk of the dimension, going
+    // by the regions for Java worlds. None if there are no chunks.
+    pub fn chunk_bounds(&self, dimension: Dimension) -> Option<((i32, i32), (i32, i32))> {
+        #[cfg(feature = "bedrock")]
+        if let Some(bedrock) = &self.bedrock {
+            let chunks: Vec<(i32, i32)> = bedrock.chunk_positions(dimension).collect();
+            let min = (chunks.iter().map(|c| c.0).min()?, chunks.iter().map(|c| c.1).min()?);
+            let max = (chunks.iter().map(|c| c.0).max()?, chunks.iter().map(|c| c.1).max()?);
+            return Some((min, max));
+        }
+        let regions: Vec<&RegionInfo> = self.regions_in(dimension).collect();
+        let min = (regions.iter().map(|r| r.x).min()? * 32, regions.iter().map(|r| r.z).min()? * 32);
+        let max = (regions.iter().map(|r| r.x).max()? * 32 + 31, regions.iter().map(|r| r.z).max()? * 32 + 31);
+        Some((min, max))
+    }
+
+    // Whether pos is below the highest block of its column, reading just the
+    // one chunk. False when the chunk hasn't been generated.
+    pub fn is_underground(&self, dimension: Dimension, pos: BlockPos) -> Result<bool> {
+        let Some(region) = self.region(dimension, chunk_to_region_coord(pos.chunk_x()), chunk_to_region_coord(pos.chunk_z())) else {
+            return Ok(false);
+        };
+        let mut region = Region::open(&region.path)?;
+        let Some(tag) = region.read_chunk(chunk_index_in_region(pos.chunk_x(), pos.chunk_z()), &ParseOptions::for_surface_search())? else {
+            return Ok(false);
+        };
+        Ok(Chunk::from_nbt(tag)?.is_underground(pos))
+    }
+
+    // Loads every chunk whose coordinates lie within the given (inclusive) chunk rectangle
+    pub fn view(&self, dimension: Dimension, min_chunk: (i32, i32), max_chunk: (i32, i32)) -> Result<WorldView> {
+        let mut view = WorldView::new();
+        let mut cache = self.cache.lock().unwrap();
+        let mut files = HashMap::new();
+
+        for x in min_chunk.0..=max_chunk.0 {
+            for z in min_chunk.1..=max_chunk.1 {
+                if let Some(chunk) = cache.get(ChunkKey::new(dimension, x, z), |key| self.read_chunk(key, &mut files)) {
+                    view.insert(chunk);
+                }
+            }
+        }
+
+        Ok(view)
+    }
+
+    // Like a scan, a broken chunk or region doesn't keep the rest from being
+    // read. Regions stay open in files for the chunks after.
+    fn read_chunk(&self, key: ChunkKey, files: &mut HashMap<(i32, i32), Option<Region>>) -> Option<Chunk> {
+        #[cfg(feature = "bedrock")]
+        if let Some(bedrock) = &self.bedrock {
+            return bedrock.chunk(key.dimension, key.x, key.z).unwrap_or_else(|e| {
+                log::warn!("Skipping chunk: {e:#}");
+                None
+            });
+        }
+
+        let (region_x, region_z) = (chunk_to_region_coord(key.x), chunk_to_region_coord(key.z));
+        let file = files.entry((region_x, region_z)).or_insert_with(|| {
+            let region = self.region(key.dimension, region_x, region_z).filter(|region| !region.is_empty())?;
+            Region::open(&region.path)
+                .inspect_err(|e| log::warn!("Skipping region {}: {e:#}", region.path.display()))
+                .ok()
+        });
+
+        let index = chunk_index_in_region(key.x, key.z);
+        file.as_mut()?
+            .read_chunks_at(&ParseOptions::default(), None, |i| i == index, |_| true)
+            .log_failures()
+            .pop()
+            .filter(|chunk| chunk.x() == key.x && chunk.z() == key.z)
+    }
+
+    // Only sections with one of the blocks in their palette have their block data unpacked
+    pub fn find_blocks<'a>(&'a self, names: &'a [&str]) -> impl Iterator<Item = BlockPos> + 'a {
+        find_in_chunks(self.chunks(), names)
+    }
+
+    pub fn find_blocks_in<'a>(&'a self, dimension: Dimension, names: &'a [&str]) -> impl Iterator<Item = BlockPos> + 'a {
+        find_in_chunks(self.chunks_in(dimension), names)
+    }
+}
+
+// Region files in <dimension>/<folder> for every dimension, sorted by dimension and position
+fn find_regions(world: &Path, folder: &str) -> Result<Vec<RegionInfo>> {
+    let mut regions = Vec::new();
+
+    for dimension in Dimension::ALL {
+        let region_dir = world.join(dimension.directory()).join(folder);
+        if !region_dir.is_dir() {
+            continue;
+        }
+
+        let entries = fs::read_dir(&region_dir).with_context(|| format!("Could not list {}", region_dir.display()))?;
+        for entry in entries {
+            let entry = entry?;
+            let name = entry.file_name();
+            if let Some((x, z)) = name.to_str().and_then(parse_region_file_name) {
+                regions.push(RegionInfo { dimension, x, z, path: entry.path() });
+            }
+        }
+    }
+
+    regions.sort_by_key(|region| (region.dimension, region.x, region.z));
+
+    Ok(regions)
+}
+
+fn find_in_chunks<'a>(chunks: WorldChunks<'a>, names: &'a [&str]) -> impl Iterator<Item = BlockPos> + 'a {
+    let ids: Vec<BlockId> = names.iter().map(|name| BlockId::intern(name)).collect();
+    let chunks = chunks
+        .with_options(ParseOptions::for_block_search())
+        .only_sections_with(move |block| ids.contains(&block.name));
+    chunks.flat_map(move |chunk| match chunk {
+        Ok((_, chunk)) => chunk.find_blocks(names).into_iter().map(|(pos, _)| pos).collect(),
+        Err(e) => {
+            log::warn!("Skipping chunk: {e:#}");
+            Vec::new()
+        },
+    })
+}
+
+// How far a scan over a world's regions has got
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct ScanProgress {
+    pub regions_done: usize,
+    pub regions_total: usize,
+    // Chunks in the regions read so far, including ones that couldn't be parsed
+    pub chunks: usize,
+    // Chunks left out because the chunk policy doesn't allow them
+    pub excluded: usize,
+}
+
+// Decides from a palette entry whether a section is worth loading
+type SectionFilter<'a> = Box<dyn Fn(&BlockType) -> bool + 'a>;
+#[cfg(feature = "bedrock")]
+type BedrockChunks<'a> = Box<dyn Iterator<Item = Result<(Dimension, Chunk)>> + 'a>;
+
+pub struct WorldChunks<'a> {
+    regions: vec::IntoIter<&'a RegionInfo>,
+    current: Option<(Dimension, vec::IntoIter<Result<Chunk>>)>,
+    options: ParseOptions,
+    modified_since: Option<u32>,
+    progress: ScanProgress,
+    on_progress: Option<Box<dyn FnMut(ScanProgress) + 'a>>,
+    keep_sections: Option<SectionFilter<'a>>,
+    policy: Option<&'a ChunkPolicy>,
+    // Bedrock worlds hand out their chunks from here instead of regions
+    #[cfg(feature = "bedrock")]
+    bedrock: Option<BedrockChunks<'a>>,
+}
+
+impl<'a> WorldChunks<'a> {
+    fn new(regions: Vec<&'a RegionInfo>) -> WorldChunks<'a> {
+        let progress = ScanProgress { regions_done: 0, regions_total: regions.len(), chunks: 0, excluded: 0 };
+        WorldChunks {
+            regions: regions.into_iter(),
+            current: None,
+            options: ParseOptions::default(),
+            modified_since: None,
+            progress,
+            on_progress: None,
+            keep_sections: None,
+            policy: None,
+            #[cfg(feature = "bedrock")]
+            bedrock: None,
+        }
+    }
+
+    // Options, filters and progress don't apply to Bedrock chunks, they're
+    // read whole. Only the chunk policy does.
+    #[cfg(feature = "bedrock")]
+    fn bedrock(db: &'a BedrockDb, dimension: Option<Dimension>) -> WorldChunks<'a> {
+        let mut chunks = WorldChunks::new(Vec::new());
+        chunks.bedrock = Some(Box::new(db.chunks(dimension)));
+        chunks
+    }
+
+    // Calls f every time the chunks of a region have all been handed out
+    pub fn on_progress(mut self, f: impl FnMut(ScanProgress) + 'a) -> Self {
+        self.on_progress = Some(Box::new(f));
+        self
+    }
+
+    // Parses the chunks with these options, e.g. to skip light data
+    pub fn with_options(mut self, options: ParseOptions) -> Self {
+        self.options = options;
+        self
+    }
+
+    // Skips chunks last saved before the given Unix time
+    pub fn modified_since(mut self, since: u32) -> Self {
+        self.modified_since = Some(since);
+        self
+    }
+
+    // Only loads the sections with a palette entry keep wants, see Chunk::from_nbt_where
+    pub fn only_sections_with(mut self, keep: impl Fn(&BlockType) -> bool + 'a) -> Self {
+        self.keep_sections = Some(Box::new(keep));
+        self
+    }
+
+    // Leaves out the chunks the policy doesn't allow without reading them
+    pub fn only_allowed(mut self, policy: &'a ChunkPolicy) -> Self {
+        self.policy = Some(policy);
+        self
+    }
+
+    pub fn progress(&self) -> ScanProgress {
+        self.progress
+    }
+}
+
+impl Iterator for WorldChunks<'_> {
+    type Item = Result<(Dimension, Chunk)>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        #[cfg(feature = "bedrock")]
+        if let Some(chunks) = &mut self.bedrock {
+            for chunk in chunks.by_ref() {
+                if let (Ok((dimension, chunk)), Some(policy)) = (&chunk, self.policy) {
+                    if !policy.allows(Some(*dimension), chunk.x(), chunk.z()) {
+                        self.progress.excluded += 1;
+                        continue;
+                    }
+                }
+                return Some(chunk);
+            }
+            return None;
+        }
+        loop {
+            if let Some((dimension, chunks)) = &mut self.current {
+                if let Some(chunk) = chunks.next() {
+                    return Some(chunk.map(|chunk| (*dimension, chunk)));
+                }
+            }
+
+            if self.current.take().is_some() {
+                self.progress.regions_done += 1;
+                if let Some(on_progress) = &mut self.on_progress {
+                    on_progress(self.progress);
+                }
+            }
+
+            let region = self.regions.next()?;
+            if region.is_empty() {
+                self.current = Some((region.dimension, Vec::new().into_iter()));
+                continue;
+            }
+            let keep = &self.keep_sections;
+            let allowed = |index: usize| {
+                let (x, z) = chunk_at_index(region.x, region.z, index);
+                self.policy.is_none_or(|policy| policy.allows(Some(region.dimension), x, z))
+            };
+            let report = Region::open(&region.path).map(|mut file| {
+                self.progress.excluded += (0..1024).filter(|index| file.has_chunk(*index) && !allowed(*index)).count();
+                file.read_chunks_at(&self.options, self.modified_since, allowed, |block| keep.as_ref().is_none_or(|keep| keep(block)))
+            });
+            match report {
+                Ok(report) => {
+                    // Corrupt chunks come out as errors after the ones that could be read
+                    let failed = report.failed.into_iter().map(|(i, e)| Err(e.context(format!("Could not read chunk {i} of region {}", region.path.display()))));
+                    let chunks: Vec<Result<Chunk>> = report.ok.into_iter().map(Ok).chain(failed).collect();
+                    self.progress.chunks += chunks.len();
+                    self.current = Some((region.dimension, chunks.into_iter()));
+                },
+                Err(e) => {
+                    // Still counts as done once the error has been handed out
+                    self.current = Some((region.dimension, Vec::new().into_iter()));
+                    return Some(Err(e.context(format!("Could not read region {}", region.path.display()))));
+                },
+            }
+        }
+    }
+}
+
+#[derive(Default)]
+pub struct WorldView {
+    chunks: HashMap<(i32, i32), Chunk>,
+}
+
+impl WorldView {
+    pub fn new() -> WorldView {
+        WorldView { chunks: HashMap::new() }
+    }
+
+    pub fn insert(&mut self, chunk: Chunk) {
+        self.chunks.insert((chunk.x(), chunk.z()), chunk);
+    }
+
+    pub fn chunk(&self, x: i32, z: i32) -> Option<&Chunk> {
+        self.chunks.get(&(x, z))
+    }
+
+    pub fn chunks(&self) -> impl Iterator<Item = &Chunk> {
+        self.chunks.values()
+    }
+
+    pub fn len(&self) -> usize {
+        self.chunks.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.chunks.is_empty()
+    }
+
+    pub fn find_blocks(&self, names: &[&str]) -> Vec<BlockPos> {
+        let mut found: Vec<BlockPos> = self.chunks.values()
+            .flat_map(|chunk| chunk.find_blocks(names).into_iter().map(|(pos, _)| pos))
+            .collect();
+        found.sort();
+        found
+    }
+
+    // None when the chunk isn't loaded, see Chunk::is_underground
+    pub fn is_underground(&self, pos: BlockPos) -> Option<bool> {
+        Some(self.chunk(pos.chunk_x(), pos.chunk_z())?.is_underground(pos))
+    }
+
+    // None when the chunk isn't loaded or the section has no light data
+    pub fn block_light(&self, pos: BlockPos) -> Option<u8> {
+        self.chunk(pos.chunk_x(), pos.chunk_z())?.block_light(pos.x.rem_euclid(16) as usize, pos.y, pos.z.rem_euclid(16) as usize)
+    }
+
+    pub fn sky_light(&self, pos: BlockPos) -> Option<u8> {
+        self.chunk(pos.chunk_x(), pos.chunk_z())?.sky_light(pos.x.rem_euclid(16) as usize, pos.y, pos.z.rem_euclid(16) as usize)
+    }
+
+    // None when the chunk isn't loaded or the height has no section, and
+    // OUTSIDE_WORLD above and below the world, see Chunk::block_at
+    pub fn block_at(&self, pos: BlockPos) -> Option<&BlockType> {
+        let chunk = self.chunk(pos.chunk_x(), pos.chunk_z())?;
+        chunk.block_at(pos.x.rem_euclid(16) as usize, pos.y, pos.z.rem_euclid(16) as usize)
+    }
+}
@@ -0,0 +1,255 @@
+// Runs the built binary on a small world made here and compares what it
+// prints with the files in tests/golden. After a change to the output that's
+// meant, write the new golden files with
+//
+//     PATH_MINER_BLESS=1 cargo test --test cli
+//
+// and look over the diff before committing them.
+
+use std::{ fs, path::{ Path, PathBuf }, process::Command };
+use path_miner::{ Tag, TagPayload, chunk::{ BlockState, Chunk }, region::{ RegionWriter, chunk_index_in_region } };
+
+// Every chunk is saved at the same time, so nothing printed depends on when the test runs
+const SAVED_AT: u32 = 1_700_000_000;
+
+fn tag(name: &str, payload: TagPayload) -> Tag {
+    Tag { name: name.to_string(), payload }
+}
+
+fn compound(tags: Vec<Tag>) -> TagPayload {
+    TagPayload::Compound(tags.into())
+}
+
+fn section(y: i8, block: &str) -> TagPayload {
+    let palette = |name: &str| TagPayload::List(vec![compound(vec![tag("Name", TagPayload::String(name.to_string()))])]);
+    compound(vec![
+        tag("Y", TagPayload::Byte(y)),
+        tag("block_states", compound(vec![tag("palette", palette(block))])),
+        tag("biomes", compound(vec![tag("palette", TagPayload::List(vec![TagPayload::String("minecraft:plains".to_string())]))])),
+    ])
+}
+
+// Stone from y 0 to 15 and air from 16 to 31
+fn ground(x: i32, z: i32) -> Tag {
+    tag("", compound(vec![
+        tag("DataVersion", TagPayload::Int(3700)),
+        tag("xPos", TagPayload::Int(x)),
+        tag("zPos", TagPayload::Int(z)),
+        tag("yPos", TagPayload::Int(0)),
+        tag("Status", TagPayload::String("minecraft:full".to_string())),
+        tag("sections", TagPayload::List(vec![section(0, "minecraft:stone"), section(1, "minecraft:air")])),
+        tag("block_entities", TagPayload::List(Vec::new())),
+    ]))
+}
+
+fn chest(x: i32, y: i32, z: i32, item: &str, count: i8) -> TagPayload {
+    let stack = compound(vec![
+        tag("Slot", TagPayload::Byte(0)),
+        tag("id", TagPayload::String(item.to_string())),
+        tag("Count", TagPayload::Byte(count)),
+    ]);
+    compound(vec![
+        tag("id", TagPayload::String("minecraft:chest".to_string())),
+        tag("x", TagPayload::Int(x)),
+        tag("y", TagPayload::Int(y)),
+        tag("z", TagPayload::Int(z)),
+        tag("Items", TagPayload::List(vec![stack])),
+    ])
+}
+
+// Blocks put into a ground chunk at positions local to it. The block
+// entities go in after, setting a block drops the one that was there.
+fn edited(x: i32, z: i32, blocks: &[(usize, i32, usize, &str)], block_entities: Vec<TagPayload>) -> Tag {
+    let mut chunk = Chunk::from_nbt(ground(x, z)).unwrap();
+    for (x, y, z, block) in blocks {
+        chunk.set_block(*x, *y, *z, block.parse::<BlockState>().unwrap()).unwrap();
+    }
+    let mut nbt = chunk.into_nbt().unwrap();
+    nbt.payload.get_mut("block_entities").unwrap().as_list().extend(block_entities);
+    nbt
+}
+
+// A world of five chunks over two regions: ores, a small cave and a chest
+// with diamonds in r.0.0.mca, and a plain stone chunk in r.-1.0.mca
+struct Fixture {
+    dir: PathBuf,
+}
+
+impl Fixture {
+    fn new(name: &str) -> Fixture {
+        let dir = std::env::temp_dir().join(format!("path-miner-cli-{name}-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(dir.join("region")).unwrap();
+
+        let mut cave: Vec<(usize, i32, usize, &str)> = Vec::new();
+        for y in 3..6 {
+            for z in 8..12 {
+                for x in 5..9 {
+                    cave.push((x, y, z, "minecraft:air"));
+                }
+            }
+        }
+        let blocks = [
+            &[
+                (3, 2, 5, "minecraft:diamond_ore"),
+                (10, 5, 1, "minecraft:iron_ore"),
+                (1, 14, 1, "minecraft:coal_ore"),
+                (8, 16, 8, "minecraft:chest[facing=north]"),
+            ][..],
+            &cave,
+        ].concat();
+        let origin = edited(0, 0, &blocks, vec![chest(8, 16, 8, "minecraft:diamond", 3)]);
+        let chunks = [
+            (0, 0, origin),
+            (1, 0, edited(1, 0, &[(4, 3, 4, "minecraft:diamond_ore"), (5, 3, 4, "minecraft:diamond_ore")], Vec::new())),
+            (0, 1, ground(0, 1)),
+            (1, 1, edited(1, 1, &[(1, 6, 1, "minecraft:gold_ore")], Vec::new())),
+        ];
+        write_region(&dir.join("region").join("r.0.0.mca"), &chunks);
+        write_region(&dir.join("region").join("r.-1.0.mca"), &[(-1, 0, ground(-1, 0))]);
+
+        Fixture { dir }
+    }
+
+    fn region(&self, name: &str) -> String {
+        self.dir.join("region").join(name).display().to_string()
+    }
+
+    fn world(&self) -> String {
+        self.dir.display().to_string()
+    }
+
+    // Runs the binary, which has to succeed, and gives what it printed, the
+    // summaries on stderr after a --- stderr line, with the fixture's folder
+    // written as <world>
+    fn run(&self, args: &[&str]) -> String {
+        let output = Command::new(env!("CARGO_BIN_EXE_path-miner")).args(args).output().unwrap();
+        let stderr = String::from_utf8(output.stderr).unwrap();
+        assert!(output.status.success(), "path-miner {} failed:\n{stderr}", args.join(" "));
+        let mut printed = String::from_utf8(output.stdout).unwrap();
+        if !stderr.is_empty() {
+            printed += "--- stderr\n";
+            printed += &stderr;
+        }
+        printed.replace(&self.world(), "<world>")
+    }
+}
+
+impl Drop for Fixture {
+    fn drop(&mut self) {
+        let _ = fs::remove_dir_all(&self.dir);
+    }
+}
+
+fn write_region(path: &Path, chunks: &[(i32, i32, Tag)]) {
+    let mut writer = RegionWriter::new();
+    for (x, z, chunk) in chunks {
+        writer.set_chunk(chunk_index_in_region(*x, *z), chunk, SAVED_AT).unwrap();
+    }
+    writer.write(&mut fs::File::create(path).unwrap()).unwrap();
+}
+
+fn check_golden(name: &str, output: &str) {
+    let path = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests").join("golden").join(format!("{name}.txt"));
+    if std::env::var_os("PATH_MINER_BLESS").is_some() {
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        fs::write(&path, output).unwrap();
+        return;
+    }
+
+    let golden = fs::read_to_string(&path).unwrap_or_else(|_| panic!("{} is missing, run with PATH_MINER_BLESS=1 to write it", path.display()));
+    if golden != output {
+        let line = golden.lines().zip(output.lines()).position(|(a, b)| a != b).unwrap_or(golden.lines().count().min(output.lines().count()));
+        panic!(
+            "Output differs from {} at line {}:\n  golden: {:?}\n  output: {:?}\nRun with PATH_MINER_BLESS=1 to accept the new output",
+            path.display(), line + 1, golden.lines().nth(line), output.lines().nth(line),
+        );
+    }
+}
+
+#[test]
+fn dump() {
+    let fixture = Fixture::new("dump");
+    let region = fixture.region("r.-1.0.mca");
+    check_golden("dump_plain", &fixture.run(&["dump", &region]));
+    check_golden("dump_snbt", &fixture.run(&["dump", &region, "--snbt"]));
+    check_golden("dump_json", &fixture.run(&["dump", &region, "--format", "json", "--pretty"]));
+}
+
+#[test]
+fn palette() {
+    let fixture = Fixture::new("palette");
+    check_golden("palette", &fixture.run(&["palette", &fixture.region("r.0.0.mca")]));
+}
+
+#[test]
+fn check() {
+    let fixture = Fixture::new("check");
+    check_golden("check", &fixture.run(&["check", &fixture.region("r.0.0.mca")]));
+}
+
+#[test]
+fn find() {
+    let fixture = Fixture::new("find");
+    let world = fixture.world();
+    check_golden("find_text", &fixture.run(&["find", &world, "--block", "minecraft:diamond_ore", "--order", "chunk", "-q"]));
+    check_golden("find_ndjson", &fixture.run(&["find", &world, "--query", "minecraft:*_ore", "--format", "ndjson", "--order", "chunk", "-q"]));
+    check_golden("find_csv", &fixture.run(&["find", &world, "--query", "minecraft:*_ore", "--format", "csv", "--order", "chunk", "--limit", "3", "-q"]));
+    check_golden("find_region", &fixture.run(&["find", &fixture.region("r.0.0.mca"), "--block", "minecraft:chest", "--order", "chunk"]));
+}
+
+#[test]
+fn stats() {
+    let fixture = Fixture::new("stats");
+    let world = fixture.world();
+    check_golden("stats", &fixture.run(&["stats", &world, "-q"]));
+    check_golden("stats_csv", &fixture.run(&["stats", &world, "--exact", "--per-chunk", "--format", "csv", "-q"]));
+}
+
+#[test]
+fn complexity() {
+    let fixture = Fixture::new("complexity");
+    check_golden("complexity", &fixture.run(&["complexity", &fixture.world(), "--format", "csv", "-q"]));
+}
+
+#[test]
+fn analyze() {
+    let fixture = Fixture::new("analyze");
+    check_golden("analyze_csv", &fixture.run(&["analyze", &fixture.world(), "--format", "csv", "-q"]));
+}
+
+#[test]
+fn block_entities_and_items() {
+    let fixture = Fixture::new("containers");
+    let world = fixture.world();
+    check_golden("find_block_entities", &fixture.run(&["find-block-entities", &world, "-q"]));
+    check_golden("find_items", &fixture.run(&["find-items", &world, "--item", "minecraft:diamond", "-q"]));
+}
+
+#[test]
+fn caves() {
+    let fixture = Fixture::new("caves");
+    check_golden("caves", &fixture.run(&["caves", &fixture.world(), "--radius", "1", "--min-volume", "1", "--entrances"]));
+}
+
+#[test]
+fn path() {
+    let fixture = Fixture::new("path");
+    check_golden("path", &fixture.run(&["path", &fixture.world(), "--from", "2,16,2", "--to", "28,8,4", "--margin", "0"]));
+    check_golden("path_actions", &fixture.run(&["path", &fixture.world(), "--from", "2,16,2", "--to", "28,8,4", "--margin", "0", "--actions"]));
+}
+
+#[test]
+fn dark_spots() {
+    let fixture = Fixture::new("dark-spots");
+    check_golden("dark_spots", &fixture.run(&["dark-spots", &fixture.world(), "--box", "0,0,0,31,15,31", "--positions"]));
+}
+
+#[test]
+fn replay() {
+    let fixture = Fixture::new("replay");
+    let log = fixture.dir.join("accesses.log").display().to_string();
+    // The tour reads nine chunks once, which prefetching reads ahead of it
+    fixture.run(&["tour", &fixture.world(), "--from", "2,16,2", "--radius", "1", "--block", "minecraft:diamond_ore", "--cache-size", "4", "--record-accesses", &log]);
+    check_golden("replay", &fixture.run(&["replay", &log, "--sizes", "0,4,16", "--policies", "lru,2q", "--prefetch-radius", "0,1"]));
+}